@@ -0,0 +1,70 @@
+//! Align Module - Alignment of objects within a reference box
+//!
+//! Pure functions computing how far each object must move; the editor picks the
+//! reference box (selection bounds or canvas) and applies the offsets.
+
+use crate::spatial::BoundingBox;
+
+/// Edge or center line to align to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignMode {
+    Left,
+    Right,
+    /// Align horizontal centers
+    CenterH,
+    Top,
+    Bottom,
+    /// Align vertical centers
+    CenterV,
+}
+
+impl AlignMode {
+    /// Parse the API name of a mode ("left", "right", "center_h", "top", "bottom", "center_v")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(AlignMode::Left),
+            "right" => Some(AlignMode::Right),
+            "center_h" => Some(AlignMode::CenterH),
+            "top" => Some(AlignMode::Top),
+            "bottom" => Some(AlignMode::Bottom),
+            "center_v" => Some(AlignMode::CenterV),
+            _ => None,
+        }
+    }
+}
+
+/// Translation that aligns `bounds` to `target` along one axis
+pub fn align_offset(bounds: &BoundingBox, target: &BoundingBox, mode: AlignMode) -> (f64, f64) {
+    match mode {
+        AlignMode::Left => (target.min_x - bounds.min_x, 0.0),
+        AlignMode::Right => (target.max_x - bounds.max_x, 0.0),
+        AlignMode::CenterH => (target.center().0 - bounds.center().0, 0.0),
+        AlignMode::Top => (0.0, target.min_y - bounds.min_y),
+        AlignMode::Bottom => (0.0, target.max_y - bounds.max_y),
+        AlignMode::CenterV => (0.0, target.center().1 - bounds.center().1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_align_offsets() {
+        let target = BoundingBox::new(0.0, 0.0, 100.0, 50.0);
+        let bounds = BoundingBox::new(30.0, 10.0, 40.0, 30.0);
+
+        assert_eq!(align_offset(&bounds, &target, AlignMode::Left), (-30.0, 0.0));
+        assert_eq!(align_offset(&bounds, &target, AlignMode::Right), (60.0, 0.0));
+        assert_eq!(align_offset(&bounds, &target, AlignMode::CenterH), (15.0, 0.0));
+        assert_eq!(align_offset(&bounds, &target, AlignMode::Top), (0.0, -10.0));
+        assert_eq!(align_offset(&bounds, &target, AlignMode::Bottom), (0.0, 20.0));
+        assert_eq!(align_offset(&bounds, &target, AlignMode::CenterV), (0.0, 5.0));
+    }
+
+    #[test]
+    fn test_align_mode_names() {
+        assert_eq!(AlignMode::from_name("center_v"), Some(AlignMode::CenterV));
+        assert_eq!(AlignMode::from_name("middle"), None);
+    }
+}
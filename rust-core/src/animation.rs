@@ -0,0 +1,437 @@
+//! Animation Module - Keyframed timeline
+//!
+//! Objects have at most one track per animated property. A track holds keyframes
+//! sorted by time; between two keyframes the value follows the easing curve of
+//! the earlier one, and outside the keyframed range it holds the nearest value.
+//! Evaluating the timeline writes the animated values into the scene, so
+//! rendering, export and hit testing see the animated state as ordinary objects.
+
+use serde::{Deserialize, Serialize};
+
+use crate::color::parse_hex_color;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{ObjectId, SceneGraph, SceneNode};
+
+/// Keyframes closer together than this (seconds) are the same keyframe
+const TIME_EPSILON: f64 = 1e-9;
+
+/// Timeline and playback settings stored with the document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Timeline {
+    /// Length of the animation in seconds
+    pub duration: f64,
+    /// Playback frame rate
+    pub fps: f64,
+    /// Restart from the beginning after the end during playback
+    pub looping: bool,
+    /// Time the scene was last evaluated at, in seconds
+    pub current_time: f64,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<Track>,
+}
+
+/// Animated values of one property of one object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Track {
+    pub object_id: ObjectId,
+    pub property: AnimatedProperty,
+    /// Sorted by time
+    pub keyframes: Vec<Keyframe>,
+}
+
+/// A value at a point in time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds from the start of the timeline
+    pub time: f64,
+    pub value: KeyValue,
+    /// Curve used from this keyframe to the next one
+    #[serde(default, skip_serializing_if = "Easing::is_linear")]
+    pub easing: Easing,
+}
+
+/// Properties that can be keyframed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimatedProperty {
+    /// Translation of the object's transform: [x, y]
+    Position,
+    /// Degrees, clockwise on screen
+    Rotation,
+    /// Scale along the object's axes: [sx, sy]
+    Scale,
+    /// 0..1 (leaf objects only)
+    Opacity,
+    /// "#rrggbb" fill color (leaf objects only)
+    Fill,
+}
+
+/// Keyframe value; its shape depends on the property
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum KeyValue {
+    Number(f64),
+    Pair([f64; 2]),
+    Color(String),
+}
+
+/// Timing curve between two keyframes (CSS names and definitions)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Keep the value until the next keyframe
+    Hold,
+    /// cubic-bezier(x1, y1, x2, y2) with x1 and x2 in 0..1
+    CubicBezier { x1: f64, y1: f64, x2: f64, y2: f64 },
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Timeline { duration: 5.0, fps: 30.0, looping: false, current_time: 0.0, tracks: Vec::new() }
+    }
+}
+
+impl Timeline {
+    pub fn is_default(&self) -> bool {
+        *self == Timeline::default()
+    }
+
+    /// The track animating a property of an object
+    pub fn track(&self, object_id: &str, property: AnimatedProperty) -> Option<&Track> {
+        self.tracks.iter().find(|track| track.object_id == object_id && track.property == property)
+    }
+
+    /// Whether a track has a keyframe at a time
+    pub fn has_keyframe(&self, object_id: &str, property: AnimatedProperty, time: f64) -> bool {
+        self.track(object_id, property)
+            .is_some_and(|track| track.keyframes.iter().any(|keyframe| (keyframe.time - time).abs() <= TIME_EPSILON))
+    }
+
+    /// Add a keyframe, replacing any keyframe of the same track at the same time
+    pub fn set_keyframe(&mut self, object_id: &str, property: AnimatedProperty, keyframe: Keyframe) {
+        let index = match self.tracks.iter().position(|track| track.object_id == object_id && track.property == property) {
+            Some(index) => index,
+            None => {
+                self.tracks.push(Track { object_id: object_id.to_string(), property, keyframes: Vec::new() });
+                self.tracks.len() - 1
+            }
+        };
+        let keyframes = &mut self.tracks[index].keyframes;
+        keyframes.retain(|existing| (existing.time - keyframe.time).abs() > TIME_EPSILON);
+        let position = keyframes.partition_point(|existing| existing.time < keyframe.time);
+        keyframes.insert(position, keyframe);
+    }
+
+    /// Remove the keyframe at a time; tracks left without keyframes are dropped
+    /// Returns false if there was no such keyframe
+    pub fn remove_keyframe(&mut self, object_id: &str, property: AnimatedProperty, time: f64) -> bool {
+        let mut removed = false;
+        for track in self.tracks.iter_mut().filter(|track| track.object_id == object_id && track.property == property) {
+            let before = track.keyframes.len();
+            track.keyframes.retain(|keyframe| (keyframe.time - time).abs() > TIME_EPSILON);
+            removed |= track.keyframes.len() != before;
+        }
+        self.tracks.retain(|track| !track.keyframes.is_empty());
+        removed
+    }
+}
+
+impl Track {
+    /// Value of the track at a time (None if it has no keyframes)
+    pub fn sample(&self, time: f64) -> Option<KeyValue> {
+        let first = self.keyframes.first()?;
+        let index = self.keyframes.partition_point(|keyframe| keyframe.time <= time);
+        if index == 0 {
+            return Some(first.value.clone());
+        }
+        let from = &self.keyframes[index - 1];
+        let Some(to) = self.keyframes.get(index) else {
+            return Some(from.value.clone());
+        };
+        let progress = from.easing.apply((time - from.time) / (to.time - from.time));
+        Some(interpolate(&from.value, &to.value, progress))
+    }
+}
+
+impl AnimatedProperty {
+    /// Parse a property name: "position" | "rotation" | "scale" | "opacity" | "fill"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "position" => Some(AnimatedProperty::Position),
+            "rotation" => Some(AnimatedProperty::Rotation),
+            "scale" => Some(AnimatedProperty::Scale),
+            "opacity" => Some(AnimatedProperty::Opacity),
+            "fill" => Some(AnimatedProperty::Fill),
+            _ => None,
+        }
+    }
+
+    /// Whether a value has the shape this property expects
+    pub fn accepts(self, value: &KeyValue) -> bool {
+        match (self, value) {
+            (AnimatedProperty::Position | AnimatedProperty::Scale, KeyValue::Pair([x, y])) => x.is_finite() && y.is_finite(),
+            (AnimatedProperty::Rotation | AnimatedProperty::Opacity, KeyValue::Number(value)) => value.is_finite(),
+            (AnimatedProperty::Fill, KeyValue::Color(color)) => parse_hex_color(color).is_some(),
+            _ => false,
+        }
+    }
+
+    /// Whether the property applies to a node (style properties need a leaf)
+    pub fn applies_to(self, node: &SceneNode) -> bool {
+        match self {
+            AnimatedProperty::Position | AnimatedProperty::Rotation | AnimatedProperty::Scale => true,
+            AnimatedProperty::Opacity | AnimatedProperty::Fill => matches!(node, SceneNode::Leaf { .. }),
+        }
+    }
+}
+
+impl Easing {
+    /// Parse an easing name: "linear" | "ease_in" | "ease_out" | "ease_in_out" | "hold"
+    /// or "cubic-bezier(x1, y1, x2, y2)"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linear" => Some(Easing::Linear),
+            "ease_in" => Some(Easing::EaseIn),
+            "ease_out" => Some(Easing::EaseOut),
+            "ease_in_out" => Some(Easing::EaseInOut),
+            "hold" => Some(Easing::Hold),
+            _ => {
+                let args = name.strip_prefix("cubic-bezier(")?.strip_suffix(')')?;
+                let values: Vec<f64> = args.split(',').map(|value| value.trim().parse().ok()).collect::<Option<_>>()?;
+                let &[x1, y1, x2, y2] = values.as_slice() else {
+                    return None;
+                };
+                let valid = values.iter().all(|value| value.is_finite()) && (0.0..=1.0).contains(&x1) && (0.0..=1.0).contains(&x2);
+                valid.then_some(Easing::CubicBezier { x1, y1, x2, y2 })
+            }
+        }
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Easing::Linear => "linear".to_string(),
+            Easing::EaseIn => "ease_in".to_string(),
+            Easing::EaseOut => "ease_out".to_string(),
+            Easing::EaseInOut => "ease_in_out".to_string(),
+            Easing::Hold => "hold".to_string(),
+            Easing::CubicBezier { x1, y1, x2, y2 } => format!("cubic-bezier({}, {}, {}, {})", x1, y1, x2, y2),
+        }
+    }
+
+    fn is_linear(&self) -> bool {
+        *self == Easing::Linear
+    }
+
+    /// Map linear progress (0..1) to eased progress
+    pub fn apply(self, progress: f64) -> f64 {
+        let progress = if progress.is_finite() { progress.clamp(0.0, 1.0) } else { 1.0 };
+        match self {
+            Easing::Linear => progress,
+            Easing::Hold => 0.0,
+            Easing::EaseIn => cubic_bezier_ease(0.42, 0.0, 1.0, 1.0, progress),
+            Easing::EaseOut => cubic_bezier_ease(0.0, 0.0, 0.58, 1.0, progress),
+            Easing::EaseInOut => cubic_bezier_ease(0.42, 0.0, 0.58, 1.0, progress),
+            Easing::CubicBezier { x1, y1, x2, y2 } => cubic_bezier_ease(x1, y1, x2, y2, progress),
+        }
+    }
+}
+
+impl TryFrom<String> for Easing {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        Easing::from_name(&name).ok_or_else(|| format!("unknown easing '{}'", name))
+    }
+}
+
+impl From<Easing> for String {
+    fn from(easing: Easing) -> Self {
+        easing.name()
+    }
+}
+
+/// y of a CSS timing curve at a given x (the curve runs from (0,0) to (1,1))
+fn cubic_bezier_ease(x1: f64, y1: f64, x2: f64, y2: f64, x: f64) -> f64 {
+    if x <= 0.0 || x >= 1.0 {
+        return x.clamp(0.0, 1.0);
+    }
+    let bezier = |p1: f64, p2: f64, s: f64| 3.0 * (1.0 - s) * (1.0 - s) * s * p1 + 3.0 * (1.0 - s) * s * s * p2 + s * s * s;
+    // x(s) is monotonic for x1, x2 in 0..1, so bisection always converges
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..60 {
+        let mid = (low + high) / 2.0;
+        if bezier(x1, x2, mid) < x {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    bezier(y1, y2, (low + high) / 2.0)
+}
+
+fn interpolate(from: &KeyValue, to: &KeyValue, progress: f64) -> KeyValue {
+    let lerp = |a: f64, b: f64| a + (b - a) * progress;
+    match (from, to) {
+        (KeyValue::Number(a), KeyValue::Number(b)) => KeyValue::Number(lerp(*a, *b)),
+        (KeyValue::Pair(a), KeyValue::Pair(b)) => KeyValue::Pair([lerp(a[0], b[0]), lerp(a[1], b[1])]),
+        (KeyValue::Color(a), KeyValue::Color(b)) => match (parse_hex_color(a), parse_hex_color(b)) {
+            (Some(a), Some(b)) => {
+                let channel = |i: usize| (lerp(a[i], b[i]) * 255.0).round().clamp(0.0, 255.0) as u8;
+                KeyValue::Color(format!("#{:02x}{:02x}{:02x}", channel(0), channel(1), channel(2)))
+            }
+            _ => from.clone(),
+        },
+        _ => from.clone(),
+    }
+}
+
+/// Evaluate the timeline at a time and write the animated values into the scene
+/// Only top-level objects are animated; tracks of missing objects are ignored.
+pub fn evaluate(scene: &mut SceneGraph, time: f64) {
+    scene.timeline.current_time = time;
+    let values: Vec<(ObjectId, AnimatedProperty, KeyValue)> = scene
+        .timeline
+        .tracks
+        .iter()
+        .filter_map(|track| Some((track.object_id.clone(), track.property, track.sample(time)?)))
+        .collect();
+    for (object_id, property, value) in values {
+        if let Some(node) = scene.get_node_by_id_mut(&object_id) {
+            apply_value(node, property, &value);
+        }
+    }
+}
+
+/// Write one property value into a node
+pub fn apply_value(node: &mut SceneNode, property: AnimatedProperty, value: &KeyValue) {
+    match (property, value) {
+        (AnimatedProperty::Position | AnimatedProperty::Rotation | AnimatedProperty::Scale, _) => {
+            let transform = node.transform_mut();
+            let mut components = transform.decompose();
+            match (property, value) {
+                (AnimatedProperty::Position, KeyValue::Pair([x, y])) => {
+                    components.translate_x = *x;
+                    components.translate_y = *y;
+                }
+                (AnimatedProperty::Rotation, KeyValue::Number(degrees)) => components.rotation = degrees.to_radians(),
+                (AnimatedProperty::Scale, KeyValue::Pair([sx, sy])) => {
+                    components.scale_x = *sx;
+                    components.scale_y = *sy;
+                }
+                _ => return,
+            }
+            *transform = TransformMatrix::compose(&components);
+        }
+        (AnimatedProperty::Opacity, KeyValue::Number(opacity)) => {
+            if let SceneNode::Leaf { style, .. } = node {
+                style.opacity = opacity.clamp(0.0, 1.0);
+            }
+        }
+        (AnimatedProperty::Fill, KeyValue::Color(color)) => {
+            if let SceneNode::Leaf { style, .. } = node {
+                style.fill_color = Some(color.clone());
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Current value of a property on a node, in keyframe form
+pub fn current_value(node: &SceneNode, property: AnimatedProperty) -> Option<KeyValue> {
+    let components = node.transform().decompose();
+    match (property, node) {
+        (AnimatedProperty::Position, _) => Some(KeyValue::Pair([components.translate_x, components.translate_y])),
+        (AnimatedProperty::Rotation, _) => Some(KeyValue::Number(components.rotation.to_degrees())),
+        (AnimatedProperty::Scale, _) => Some(KeyValue::Pair([components.scale_x, components.scale_y])),
+        (AnimatedProperty::Opacity, SceneNode::Leaf { style, .. }) => Some(KeyValue::Number(style.opacity)),
+        (AnimatedProperty::Fill, SceneNode::Leaf { style, .. }) => style.fill_color.clone().map(KeyValue::Color),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::VectorObject;
+
+    fn keyframe(time: f64, value: KeyValue, easing: Easing) -> Keyframe {
+        Keyframe { time, value, easing }
+    }
+
+    #[test]
+    fn test_track_sampling_holds_outside_range() {
+        let mut timeline = Timeline::default();
+        timeline.set_keyframe("a", AnimatedProperty::Rotation, keyframe(2.0, KeyValue::Number(90.0), Easing::Linear));
+        timeline.set_keyframe("a", AnimatedProperty::Rotation, keyframe(1.0, KeyValue::Number(0.0), Easing::Linear));
+        let track = timeline.track("a", AnimatedProperty::Rotation).expect("track");
+        assert_eq!(track.keyframes[0].time, 1.0);
+        assert_eq!(track.sample(0.0), Some(KeyValue::Number(0.0)));
+        assert_eq!(track.sample(1.5), Some(KeyValue::Number(45.0)));
+        assert_eq!(track.sample(3.0), Some(KeyValue::Number(90.0)));
+
+        // Same time replaces; removing the last keyframe drops the track
+        timeline.set_keyframe("a", AnimatedProperty::Rotation, keyframe(2.0, KeyValue::Number(180.0), Easing::Hold));
+        assert_eq!(timeline.tracks[0].keyframes.len(), 2);
+        assert!(timeline.remove_keyframe("a", AnimatedProperty::Rotation, 1.0));
+        assert!(timeline.remove_keyframe("a", AnimatedProperty::Rotation, 2.0));
+        assert!(!timeline.remove_keyframe("a", AnimatedProperty::Rotation, 2.0));
+        assert!(timeline.tracks.is_empty());
+    }
+
+    #[test]
+    fn test_easing_curves() {
+        assert_eq!(Easing::Hold.apply(0.9), 0.0);
+        assert!((Easing::EaseInOut.apply(0.5) - 0.5).abs() < 1e-9);
+        assert!(Easing::EaseIn.apply(0.25) < 0.25);
+        assert!(Easing::EaseOut.apply(0.25) > 0.25);
+        assert_eq!(Easing::EaseIn.apply(1.0), 1.0);
+        let linear = Easing::from_name("cubic-bezier(0.25, 0.25, 0.75, 0.75)").expect("curve");
+        assert!((linear.apply(0.3) - 0.3).abs() < 1e-9);
+        assert_eq!(Easing::from_name(&linear.name()), Some(linear));
+        assert_eq!(Easing::from_name("cubic-bezier(2, 0, 0.5, 1)"), None);
+        assert_eq!(Easing::from_name("bounce"), None);
+    }
+
+    #[test]
+    fn test_color_interpolation() {
+        let track = Track {
+            object_id: "a".to_string(),
+            property: AnimatedProperty::Fill,
+            keyframes: vec![
+                keyframe(0.0, KeyValue::Color("#000000".to_string()), Easing::Linear),
+                keyframe(1.0, KeyValue::Color("#ff8000".to_string()), Easing::Linear),
+            ],
+        };
+        assert_eq!(track.sample(0.5), Some(KeyValue::Color("#804000".to_string())));
+    }
+
+    #[test]
+    fn test_evaluate_writes_transform_and_style() {
+        let mut scene = SceneGraph::new();
+        scene.add_object("a".to_string(), VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 }, TransformMatrix::identity());
+        let timeline = &mut scene.timeline;
+        timeline.set_keyframe("a", AnimatedProperty::Position, keyframe(0.0, KeyValue::Pair([0.0, 0.0]), Easing::Linear));
+        timeline.set_keyframe("a", AnimatedProperty::Position, keyframe(1.0, KeyValue::Pair([100.0, 50.0]), Easing::Linear));
+        timeline.set_keyframe("a", AnimatedProperty::Scale, keyframe(0.0, KeyValue::Pair([2.0, 2.0]), Easing::Linear));
+        timeline.set_keyframe("a", AnimatedProperty::Opacity, keyframe(0.0, KeyValue::Number(1.0), Easing::Linear));
+        timeline.set_keyframe("a", AnimatedProperty::Opacity, keyframe(1.0, KeyValue::Number(0.0), Easing::Linear));
+        timeline.set_keyframe("missing", AnimatedProperty::Rotation, keyframe(0.0, KeyValue::Number(10.0), Easing::Linear));
+
+        evaluate(&mut scene, 0.5);
+        assert_eq!(scene.timeline.current_time, 0.5);
+        let Some(SceneNode::Leaf { transform, style, .. }) = scene.roots.first() else {
+            panic!("leaf expected");
+        };
+        let (x, y) = transform.transform_point(1.0, 1.0);
+        assert!((x - 52.0).abs() < 1e-9 && (y - 27.0).abs() < 1e-9);
+        assert!((style.opacity - 0.5).abs() < 1e-9);
+        let node = &scene.roots[0];
+        assert_eq!(current_value(node, AnimatedProperty::Position), Some(KeyValue::Pair([50.0, 25.0])));
+    }
+}
@@ -0,0 +1,120 @@
+//! Artboard Module - Named page frames within a document
+//!
+//! Artboards are rectangles in world space that each export as their own page,
+//! e.g. one per icon or per screen. Objects are not stored on artboards: a root
+//! object belongs to the first artboard (in artboard order) that contains the
+//! center of its world bounds, so moving an object onto another frame moves it
+//! to that page.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{ObjectId, SceneGraph};
+use crate::spatial::BoundingBox;
+
+/// Named rectangular frame exported as its own page
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Artboard {
+    pub id: ObjectId,
+    pub name: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+impl Artboard {
+    /// World-space frame of the artboard
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox::from_rect(self.x, self.y, self.width, self.height)
+    }
+}
+
+/// Whether (x, y, width, height) describes a usable artboard frame
+pub fn is_valid_frame(x: f64, y: f64, width: f64, height: f64) -> bool {
+    x.is_finite() && y.is_finite() && width.is_finite() && height.is_finite() && width > 0.0 && height > 0.0
+}
+
+/// Index of the artboard a root object belongs to, if any
+fn artboard_index(scene: &SceneGraph, bounds: &BoundingBox) -> Option<usize> {
+    let (cx, cy) = bounds.center();
+    scene.artboards.iter().position(|artboard| artboard.bounds().contains_point(cx, cy))
+}
+
+/// IDs of the root objects on an artboard, in paint order
+/// Returns None for an unknown artboard.
+pub fn artboard_objects(scene: &SceneGraph, artboard_id: &str) -> Option<Vec<ObjectId>> {
+    let index = scene.artboards.iter().position(|artboard| artboard.id == artboard_id)?;
+    let identity = TransformMatrix::identity();
+    let objects = scene
+        .roots
+        .iter()
+        .filter(|root| {
+            geometry::node_world_bounds(scene, root, &identity)
+                .is_some_and(|bounds| artboard_index(scene, &bounds) == Some(index))
+        })
+        .map(|root| root.id().clone())
+        .collect();
+    Some(objects)
+}
+
+/// Copy of the scene holding only the objects on an artboard, with the artboard's frame
+/// Returns None for an unknown artboard.
+pub fn artboard_scene(scene: &SceneGraph, artboard_id: &str) -> Option<(SceneGraph, BoundingBox)> {
+    let objects = artboard_objects(scene, artboard_id)?;
+    let frame = scene.artboards.iter().find(|artboard| artboard.id == artboard_id)?.bounds();
+    let mut page = scene.clone();
+    page.roots.retain(|root| objects.contains(root.id()));
+    Some((page, frame))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::VectorObject;
+
+    fn scene_with_artboards() -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        for (id, x) in [("artboard_a", 0.0), ("artboard_b", 100.0)] {
+            let name = id.to_string();
+            scene.artboards.push(Artboard { id: id.to_string(), name, x, y: 0.0, width: 100.0, height: 100.0 });
+        }
+        for (x, width) in [(10.0, 20.0), (90.0, 30.0), (150.0, 10.0), (300.0, 10.0)] {
+            let id = scene.generate_id();
+            let rect = VectorObject::Rectangle { x, y: 10.0, width, height: 10.0 };
+            scene.add_object(id, rect, TransformMatrix::identity());
+        }
+        scene
+    }
+
+    #[test]
+    fn test_objects_are_assigned_by_center() {
+        let scene = scene_with_artboards();
+        assert_eq!(artboard_objects(&scene, "artboard_a"), Some(vec!["obj_1".to_string()]));
+        // obj_2 straddles the edge; its center (105) is on the second artboard
+        let on_b = artboard_objects(&scene, "artboard_b");
+        assert_eq!(on_b, Some(vec!["obj_2".to_string(), "obj_3".to_string()]));
+        assert_eq!(artboard_objects(&scene, "artboard_c"), None);
+    }
+
+    #[test]
+    fn test_earlier_artboards_win_overlaps() {
+        let mut scene = scene_with_artboards();
+        scene.artboards[1].x = 0.0;
+        assert_eq!(artboard_objects(&scene, "artboard_b"), Some(vec![]));
+        scene.artboards.swap(0, 1);
+        assert_eq!(artboard_objects(&scene, "artboard_b"), Some(vec!["obj_1".to_string()]));
+    }
+
+    #[test]
+    fn test_artboard_scene_keeps_only_its_objects() {
+        let scene = scene_with_artboards();
+        let (page, frame) = artboard_scene(&scene, "artboard_b").expect("artboard");
+        assert_eq!(page.roots.len(), 2);
+        assert_eq!(frame.to_array(), [100.0, 0.0, 200.0, 100.0]);
+        assert!(is_valid_frame(0.0, 0.0, 1.0, 1.0));
+        assert!(!is_valid_frame(0.0, 0.0, 0.0, 1.0));
+        assert!(!is_valid_frame(f64::NAN, 0.0, 1.0, 1.0));
+    }
+}
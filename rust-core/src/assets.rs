@@ -0,0 +1,262 @@
+//! Assets Module - Audit of the resources a document depends on
+//!
+//! A document depends on its library symbols, on the fonts its text is set in and on
+//! the images its pattern fills tile. For each the audit reports how often it is used
+//! and, for what the document embeds, how much it adds to the saved file. Before
+//! export or sharing it flags unused, oversized and missing symbols, fonts with no
+//! registered font of their family, and oversized images.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::core::scene::{SceneGraph, SceneNode, TextFont};
+use crate::fonts::FontRegistry;
+use crate::pattern::PatternSource;
+
+/// Symbols whose content serializes to more than this are flagged as oversized
+pub const OVERSIZED_SYMBOL_BYTES: usize = 1024 * 1024;
+
+/// Images whose href (a data URL embeds the whole image) is longer than this are flagged as oversized
+pub const OVERSIZED_IMAGE_BYTES: usize = 1024 * 1024;
+
+/// Usage of one library symbol
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymbolUsage {
+    pub id: String,
+    pub name: String,
+    /// Instances placed in the scene or inside other symbols, and pattern fills tiling it
+    pub uses: usize,
+    /// Size of the symbol's content in the saved document
+    pub bytes: usize,
+    pub unused: bool,
+    pub oversized: bool,
+}
+
+/// Instance whose symbol is not in the library
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MissingSymbol {
+    pub instance_id: String,
+    pub symbol_id: String,
+}
+
+/// Font the document's text is set in
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FontUsage {
+    pub family: String,
+    pub weight: u16,
+    pub italic: bool,
+    /// Text objects set in it
+    pub uses: usize,
+    /// Registered font the text is shaped with (None if missing)
+    pub font_id: Option<String>,
+    /// No font of the family is registered
+    pub missing: bool,
+}
+
+/// Image tiled by pattern fills
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ImageUsage {
+    pub href: String,
+    /// Objects filled with it
+    pub uses: usize,
+    /// Size of the href in the saved document (the whole image for a data URL)
+    pub bytes: usize,
+    pub oversized: bool,
+}
+
+/// Result of an asset audit
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AssetReport {
+    pub symbols: Vec<SymbolUsage>,
+    pub missing_symbols: Vec<MissingSymbol>,
+    pub fonts: Vec<FontUsage>,
+    pub images: Vec<ImageUsage>,
+    /// Whether anything was flagged (unused, oversized or missing)
+    pub has_issues: bool,
+}
+
+/// Audit the symbols, fonts and images a scene uses, with the fonts registered now
+pub fn audit(scene: &SceneGraph, fonts: &FontRegistry) -> AssetReport {
+    let mut uses: HashMap<&str, usize> = HashMap::new();
+    let mut missing_symbols = Vec::new();
+    let mut images: Vec<ImageUsage> = Vec::new();
+    count_instances(scene, &scene.roots, &mut uses, &mut missing_symbols, &mut images);
+    for symbol in &scene.symbols {
+        count_instances(scene, &symbol.children, &mut uses, &mut missing_symbols, &mut images);
+    }
+
+    let symbols: Vec<SymbolUsage> = scene
+        .symbols
+        .iter()
+        .map(|symbol| {
+            let uses = uses.get(symbol.id.as_str()).copied().unwrap_or(0);
+            let bytes = serde_json::to_string(&symbol.children).map(|json| json.len()).unwrap_or(0);
+            SymbolUsage {
+                id: symbol.id.clone(),
+                name: symbol.name.clone(),
+                uses,
+                bytes,
+                unused: uses == 0,
+                oversized: bytes > OVERSIZED_SYMBOL_BYTES,
+            }
+        })
+        .collect();
+
+    let mut font_uses: Vec<(&TextFont, usize)> = Vec::new();
+    for (_, text) in scene.text_objects() {
+        match font_uses.iter_mut().find(|(font, _)| **font == text.font) {
+            Some((_, uses)) => *uses += 1,
+            None => font_uses.push((&text.font, 1)),
+        }
+    }
+    let fonts: Vec<FontUsage> = font_uses
+        .into_iter()
+        .map(|(font, uses)| {
+            let font_id = fonts.resolve(font).map(str::to_string);
+            FontUsage {
+                family: font.family.clone(),
+                weight: font.weight,
+                italic: font.italic,
+                uses,
+                missing: font_id.is_none(),
+                font_id,
+            }
+        })
+        .collect();
+
+    let has_issues = !missing_symbols.is_empty()
+        || symbols.iter().any(|symbol| symbol.unused || symbol.oversized)
+        || fonts.iter().any(|font| font.missing)
+        || images.iter().any(|image| image.oversized);
+    AssetReport { symbols, missing_symbols, fonts, images, has_issues }
+}
+
+/// Count symbol instances and pattern tiles (symbols and images) under `nodes`
+fn count_instances<'a>(
+    scene: &SceneGraph,
+    nodes: &'a [SceneNode],
+    uses: &mut HashMap<&'a str, usize>,
+    missing: &mut Vec<MissingSymbol>,
+    images: &mut Vec<ImageUsage>,
+) {
+    for node in nodes {
+        match node {
+            SceneNode::Group { children, .. } => count_instances(scene, children, uses, missing, images),
+            SceneNode::Instance { id, symbol_id, .. } => {
+                if scene.get_symbol(symbol_id).is_some() {
+                    *uses.entry(symbol_id.as_str()).or_default() += 1;
+                } else {
+                    missing.push(MissingSymbol { instance_id: id.clone(), symbol_id: symbol_id.clone() });
+                }
+            }
+            SceneNode::Leaf { style, .. } => match style.fill_pattern.as_ref().map(|pattern| &pattern.source) {
+                Some(PatternSource::Symbol { symbol_id }) => *uses.entry(symbol_id.as_str()).or_default() += 1,
+                Some(PatternSource::Image { href, .. }) => match images.iter_mut().find(|image| image.href == *href) {
+                    Some(image) => image.uses += 1,
+                    None => images.push(ImageUsage {
+                        href: href.clone(),
+                        uses: 1,
+                        bytes: href.len(),
+                        oversized: href.len() > OVERSIZED_IMAGE_BYTES,
+                    }),
+                },
+                None => {}
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::VectorObject;
+
+    fn scene_with_rects(count: usize) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        for i in 0..count {
+            let id = scene.generate_id();
+            scene.add_object(
+                id,
+                VectorObject::Rectangle { x: i as f64 * 20.0, y: 0.0, width: 10.0, height: 10.0 },
+                TransformMatrix::identity(),
+            );
+        }
+        scene
+    }
+
+    #[test]
+    fn test_counts_instances_and_flags_unused() {
+        let mut scene = scene_with_rects(2);
+        let (used, instance_id) = scene.create_symbol("Used", &["obj_1".to_string()]).unwrap();
+        let (unused, unused_instance) = scene.create_symbol("Unused", &["obj_2".to_string()]).unwrap();
+        scene.duplicate_roots(&[instance_id], 10.0, 0.0);
+        scene.remove_roots(&[unused_instance]);
+
+        let report = audit(&scene, &FontRegistry::new());
+        let usage = |id: &str| report.symbols.iter().find(|symbol| symbol.id == id).unwrap().clone();
+        assert_eq!(usage(&used).uses, 2);
+        assert!(!usage(&used).unused);
+        assert!(usage(&unused).unused);
+        assert!(usage(&used).bytes > 0);
+        assert!(report.has_issues);
+    }
+
+    #[test]
+    fn test_reports_fonts_and_image_tiles() {
+        use crate::core::scene::TextObject;
+        use crate::pattern::PatternFill;
+
+        const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+        let mut scene = scene_with_rects(3);
+        let text = |family: &str| TextObject {
+            content: "Hi".to_string(),
+            font: TextFont { family: family.to_string(), weight: 400, italic: false },
+            size: 12.0,
+            x: 0.0,
+            y: 0.0,
+            paragraph: Default::default(),
+            typography: Default::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        for family in ["Roboto", "Helvetica", "Roboto"] {
+            let id = scene.generate_id();
+            scene.add_object(id, VectorObject::Text(Box::new(text(family))), TransformMatrix::identity());
+        }
+        let huge = format!("data:image/png;base64,{}", "A".repeat(OVERSIZED_IMAGE_BYTES));
+        for (index, href) in ["tile.png", "tile.png", huge.as_str()].into_iter().enumerate() {
+            if let SceneNode::Leaf { style, .. } = &mut scene.roots[index] {
+                let source = PatternSource::Image { href: href.to_string(), width: 8.0, height: 8.0 };
+                style.fill_pattern = Some(PatternFill { source, spacing: [0.0, 0.0], scale: 1.0, rotation: 0.0 });
+            }
+        }
+        let mut fonts = FontRegistry::new();
+        fonts.register(ROBOTO);
+
+        let report = audit(&scene, &fonts);
+        let families: Vec<_> = report.fonts.iter().map(|font| (font.family.as_str(), font.uses, font.missing)).collect();
+        assert_eq!(families, vec![("Roboto", 2, false), ("Helvetica", 1, true)]);
+        assert_eq!(report.fonts[0].font_id.as_deref(), Some("font_1"));
+        let images: Vec<_> = report.images.iter().map(|image| (image.uses, image.bytes, image.oversized)).collect();
+        assert_eq!(images, vec![(2, 8, false), (1, huge.len(), true)]);
+        assert!(report.has_issues);
+    }
+
+    #[test]
+    fn test_reports_missing_symbols() {
+        let mut scene = SceneGraph::new();
+        scene.roots.push(SceneNode::Instance {
+            id: "obj_9".to_string(),
+            symbol_id: "sym_404".to_string(),
+            transform: TransformMatrix::identity(),
+            name: None,
+        });
+        let report = audit(&scene, &FontRegistry::new());
+        assert_eq!(
+            report.missing_symbols,
+            vec![MissingSymbol { instance_id: "obj_9".to_string(), symbol_id: "sym_404".to_string() }]
+        );
+        assert!(report.has_issues);
+    }
+}
@@ -0,0 +1,266 @@
+//! Brush Module - Pressure-sensitive freehand strokes
+//!
+//! A stroke is a list of pointer samples, each drawn as a disc whose radius
+//! follows the pen pressure. The outline is the union of the convex hulls of
+//! consecutive discs, so the width tapers smoothly between samples.
+//!
+//! Samples with timestamps also give the pen's speed: like ink, a fast stroke
+//! draws thinner, and its centerline is smoothed more strongly (hand jitter
+//! matters less than the sweep of the motion). Without timestamps the stroke
+//! follows the samples and the pressure only.
+
+use serde::{Deserialize, Serialize};
+
+use crate::path_ops::{self, BooleanOp, Polygon};
+use crate::stroke;
+
+/// Pressure used for samples without a usable reading (keeps light strokes visible)
+pub const MIN_PRESSURE: f64 = 0.1;
+
+/// Samples closer than this to the previous one are dropped
+const MIN_SAMPLE_SPACING: f64 = 0.5;
+
+/// Pen speed (units per millisecond) at which a stroke draws at half its width
+const HALF_WIDTH_SPEED: f64 = 4.0;
+
+/// Narrowest a fast stroke gets, as a share of its width at rest
+const MIN_SPEED_SCALE: f64 = 0.4;
+
+/// Share of each movement the smoothed centerline lags behind, approached at high speed
+const MAX_SMOOTHING: f64 = 0.6;
+
+/// Weight of the newest speed reading; the rest carries over, damping timer jitter
+const SPEED_RESPONSE: f64 = 0.5;
+
+/// One pointer sample
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct BrushSample {
+    pub x: f64,
+    pub y: f64,
+    /// 0..1 as reported by pointer events
+    pub pressure: f64,
+    /// Milliseconds, as the pointer event's timeStamp (None without timing)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time: Option<f64>,
+}
+
+/// A point of the drawn stroke: its smoothed center and the radius of its disc
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StrokePoint {
+    pub x: f64,
+    pub y: f64,
+    pub radius: f64,
+}
+
+/// A brush stroke being drawn
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrushStroke {
+    /// Stroke width at full pressure
+    pub width: f64,
+    samples: Vec<BrushSample>,
+}
+
+impl BrushStroke {
+    pub fn new(width: f64) -> Self {
+        BrushStroke { width, samples: Vec::new() }
+    }
+
+    /// Add a sample; returns false if it was dropped (too close to the last one or not finite)
+    /// Timestamps that do not move forward are ignored.
+    pub fn add_sample(&mut self, x: f64, y: f64, pressure: f64, time: Option<f64>) -> bool {
+        if !x.is_finite() || !y.is_finite() {
+            return false;
+        }
+        let pressure = if pressure.is_finite() { pressure.clamp(MIN_PRESSURE, 1.0) } else { MIN_PRESSURE };
+        let time = time.filter(|time| time.is_finite());
+        if let Some(last) = self.samples.last_mut() {
+            if (x - last.x).hypot(y - last.y) < MIN_SAMPLE_SPACING {
+                // Keep the heavier pressure so a pause does not thin the stroke
+                last.pressure = last.pressure.max(pressure);
+                return false;
+            }
+        }
+        self.samples.push(BrushSample { x, y, pressure, time });
+        true
+    }
+
+    pub fn samples(&self) -> &[BrushSample] {
+        &self.samples
+    }
+
+    /// The stroke as drawn: a disc of `width / 2 * pressure` per sample, narrowed by the
+    /// pen's speed and with its center smoothed by it where the samples are timed.
+    /// The first and last points stay where the pen went down and up
+    pub fn profile(&self) -> Vec<StrokePoint> {
+        let mut points: Vec<StrokePoint> = Vec::with_capacity(self.samples.len());
+        let mut speed: Option<f64> = None;
+        for (index, sample) in self.samples.iter().enumerate() {
+            let previous = index.checked_sub(1).and_then(|previous| self.samples.get(previous));
+            let elapsed = match (previous.and_then(|previous| previous.time), sample.time) {
+                (Some(from), Some(to)) => to - from,
+                _ => 0.0,
+            };
+            if let Some(previous) = previous.filter(|_| elapsed > 0.0) {
+                let reading = (sample.x - previous.x).hypot(sample.y - previous.y) / elapsed;
+                speed = Some(speed.map_or(reading, |speed| speed + (reading - speed) * SPEED_RESPONSE));
+            }
+            let (scale, smoothing) = match speed {
+                Some(speed) => (
+                    (1.0 / (1.0 + speed / HALF_WIDTH_SPEED)).max(MIN_SPEED_SCALE),
+                    MAX_SMOOTHING * speed / (speed + HALF_WIDTH_SPEED),
+                ),
+                None => (1.0, 0.0),
+            };
+            let (x, y) = match points.last() {
+                Some(last) if index + 1 < self.samples.len() => {
+                    (last.x + (sample.x - last.x) * (1.0 - smoothing), last.y + (sample.y - last.y) * (1.0 - smoothing))
+                }
+                _ => (sample.x, sample.y),
+            };
+            points.push(StrokePoint { x, y, radius: self.width / 2.0 * sample.pressure * scale });
+        }
+        points
+    }
+
+    /// Filled outline of the stroke
+    pub fn outline(&self) -> Vec<Polygon> {
+        if self.width <= 0.0 || !self.width.is_finite() {
+            return Vec::new();
+        }
+        variable_width_outline(&self.profile())
+    }
+}
+
+/// Outline of a centerline through discs of varying radius
+pub fn variable_width_outline(points: &[StrokePoint]) -> Vec<Polygon> {
+    let disc = |point: &StrokePoint| stroke::circle((point.x, point.y), point.radius);
+    let mut pieces: Vec<Polygon> = match points {
+        [] => return Vec::new(),
+        [single] => vec![disc(single)],
+        _ => points
+            .windows(2)
+            .map(|pair| {
+                let mut points = disc(&pair[0]);
+                points.extend(disc(&pair[1]));
+                convex_hull(points)
+            })
+            .collect(),
+    };
+    pieces.retain(|piece| piece.len() >= 3);
+    path_ops::boolean(BooleanOp::Union, &[pieces])
+}
+
+/// Convex hull, counter-clockwise in y-up terms (Andrew's monotone chain)
+fn convex_hull(mut points: Vec<(f64, f64)>) -> Polygon {
+    points.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: (f64, f64), a: (f64, f64), b: (f64, f64)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0);
+    let mut hull: Vec<(f64, f64)> = Vec::with_capacity(points.len() * 2);
+    // Lower chain, then upper chain; each drops its last point, which starts the other
+    for &point in &points {
+        while hull.len() >= 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    hull.pop();
+    let lower_len = hull.len();
+    for &point in points.iter().rev() {
+        while hull.len() >= lower_len + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], point) <= 0.0 {
+            hull.pop();
+        }
+        hull.push(point);
+    }
+    hull.pop();
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(polygons: &[Polygon]) -> f64 {
+        polygons
+            .iter()
+            .map(|p| (0..p.len()).map(|i| p[i].0 * p[(i + 1) % p.len()].1 - p[(i + 1) % p.len()].0 * p[i].1).sum::<f64>() / 2.0)
+            .sum::<f64>()
+            .abs()
+    }
+
+    #[test]
+    fn test_constant_pressure_line_is_a_capsule() {
+        let mut stroke = BrushStroke::new(4.0);
+        for i in 0..=10 {
+            stroke.add_sample(i as f64 * 2.0, 0.0, 1.0, None);
+        }
+        let outline = stroke.outline();
+        assert_eq!(outline.len(), 1);
+        // 20 long, 4 wide, plus two half discs of radius 2; the outline stays within
+        // the flattening tolerance of the true edge along its whole perimeter
+        let expected = 20.0 * 4.0 + std::f64::consts::PI * 4.0;
+        let perimeter = 40.0 + std::f64::consts::PI * 4.0;
+        let error = (area(&outline) - expected).abs();
+        assert!(error < perimeter * path_ops::FLATTEN_TOLERANCE, "{}", error);
+    }
+
+    #[test]
+    fn test_width_follows_pressure() {
+        let mut stroke = BrushStroke::new(10.0);
+        stroke.add_sample(0.0, 0.0, 0.2, None);
+        stroke.add_sample(50.0, 0.0, 1.0, None);
+        let outline = stroke.outline();
+        let half_height_at = |x: f64| {
+            outline[0].iter().filter(|p| (p.0 - x).abs() < 0.5).map(|p| p.1.abs()).fold(0.0, f64::max)
+        };
+        assert!((half_height_at(0.0) - 1.0).abs() < 0.1);
+        assert!((half_height_at(50.0) - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_close_samples_are_merged() {
+        let mut stroke = BrushStroke::new(4.0);
+        assert!(stroke.add_sample(0.0, 0.0, 0.3, Some(10.0)));
+        assert!(!stroke.add_sample(0.1, 0.0, 0.8, Some(12.0)));
+        assert!(!stroke.add_sample(f64::NAN, 0.0, 0.8, None));
+        assert_eq!(stroke.samples(), &[BrushSample { x: 0.0, y: 0.0, pressure: 0.8, time: Some(10.0) }]);
+        // A single sample is a dot
+        assert_eq!(stroke.outline().len(), 1);
+    }
+
+    #[test]
+    fn test_speed_thins_and_smooths_the_stroke() {
+        // The same zigzag drawn slowly (0.5 units/ms) and fast (8 units/ms)
+        let draw = |millis_per_sample: Option<f64>| {
+            let mut stroke = BrushStroke::new(10.0);
+            for i in 0..=20 {
+                let y = if i % 2 == 0 { 0.0 } else { 2.0 };
+                stroke.add_sample(i as f64 * 4.0, y, 1.0, millis_per_sample.map(|millis| i as f64 * millis));
+            }
+            stroke.profile()
+        };
+        let (untimed, slow, fast) = (draw(None), draw(Some(8.0)), draw(Some(0.5)));
+        let middle = |points: &[StrokePoint]| points[10];
+        assert_eq!(middle(&untimed).radius, 5.0);
+        assert!(middle(&slow).radius > 4.0 && middle(&slow).radius < 5.0, "{:?}", middle(&slow));
+        assert!(middle(&fast).radius < 2.5, "{:?}", middle(&fast));
+        assert!(fast.iter().all(|point| point.radius >= 5.0 * MIN_SPEED_SCALE));
+
+        // Fast strokes flatten the jitter more; both ends stay on the samples
+        let jitter = |points: &[StrokePoint]| points[2..19].iter().map(|point| point.y).fold(f64::MIN, f64::max)
+            - points[2..19].iter().map(|point| point.y).fold(f64::MAX, f64::min);
+        assert_eq!(jitter(&untimed), 2.0);
+        assert!(jitter(&fast) < jitter(&slow) && jitter(&slow) < 2.0, "{} {}", jitter(&slow), jitter(&fast));
+        assert_eq!((fast[0].x, fast[0].y), (0.0, 0.0));
+        assert_eq!((fast[20].x, fast[20].y), (80.0, 0.0));
+    }
+
+    #[test]
+    fn test_convex_hull_of_square_with_inner_point() {
+        let hull = convex_hull(vec![(0.0, 0.0), (1.0, 0.0), (0.5, 0.5), (1.0, 1.0), (0.0, 1.0)]);
+        assert_eq!(hull.len(), 4);
+        assert!(!hull.contains(&(0.5, 0.5)));
+    }
+}
@@ -0,0 +1,193 @@
+//! Canvas Renderer Module - Drawing straight to a 2D canvas context from WASM
+//!
+//! Built with the `canvas` feature. The frontend hands over its
+//! `CanvasRenderingContext2D` (or an `OffscreenCanvasRenderingContext2D`, e.g. from a
+//! worker) and render commands are replayed on it here, so the main canvas skips the
+//! JSON or binary round trip entirely. Replays like executeRenderCommands in
+//! Canvas.tsx, except that image pattern tiles are not loaded: fills with one keep
+//! the preceding fill color.
+
+use std::collections::HashMap;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{
+    CanvasPattern, CanvasRenderingContext2d, CanvasWindingRule, DomMatrix2dInit, OffscreenCanvas,
+    OffscreenCanvasRenderingContext2d, SvgMatrix,
+};
+
+use crate::renderer::RenderCommand;
+
+/// The drawing calls render commands need, shared by on- and offscreen contexts
+pub trait Context2d {
+    fn set_transform(&self, a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Result<(), JsValue>;
+    fn reset_transform(&self) -> Result<(), JsValue>;
+    fn begin_path(&self);
+    fn rect(&self, x: f64, y: f64, width: f64, height: f64);
+    fn ellipse(&self, cx: f64, cy: f64, rx: f64, ry: f64) -> Result<(), JsValue>;
+    fn move_to(&self, x: f64, y: f64);
+    fn line_to(&self, x: f64, y: f64);
+    fn bezier_curve_to(&self, cp1x: f64, cp1y: f64, cp2x: f64, cp2y: f64, x: f64, y: f64);
+    fn quadratic_curve_to(&self, cpx: f64, cpy: f64, x: f64, y: f64);
+    fn close_path(&self);
+    fn set_fill_style_str(&self, color: &str);
+    fn set_fill_style_pattern(&self, pattern: &CanvasPattern);
+    fn set_stroke_style_str(&self, color: &str);
+    fn set_line_width(&self, width: f64);
+    fn set_line_cap(&self, cap: &str);
+    fn set_line_join(&self, join: &str);
+    fn set_global_alpha(&self, alpha: f64);
+    fn fill(&self, rule: CanvasWindingRule);
+    fn stroke(&self);
+    fn create_pattern(&self, tile: &OffscreenCanvas) -> Result<Option<CanvasPattern>, JsValue>;
+}
+
+macro_rules! impl_context_2d {
+    ($context:ty) => {
+        impl Context2d for $context {
+            fn set_transform(&self, a: f64, b: f64, c: f64, d: f64, e: f64, f: f64) -> Result<(), JsValue> {
+                <$context>::set_transform(self, a, b, c, d, e, f)
+            }
+            fn reset_transform(&self) -> Result<(), JsValue> {
+                <$context>::reset_transform(self)
+            }
+            fn begin_path(&self) {
+                <$context>::begin_path(self)
+            }
+            fn rect(&self, x: f64, y: f64, width: f64, height: f64) {
+                <$context>::rect(self, x, y, width, height)
+            }
+            fn ellipse(&self, cx: f64, cy: f64, rx: f64, ry: f64) -> Result<(), JsValue> {
+                <$context>::ellipse(self, cx, cy, rx, ry, 0.0, 0.0, std::f64::consts::TAU)
+            }
+            fn move_to(&self, x: f64, y: f64) {
+                <$context>::move_to(self, x, y)
+            }
+            fn line_to(&self, x: f64, y: f64) {
+                <$context>::line_to(self, x, y)
+            }
+            fn bezier_curve_to(&self, cp1x: f64, cp1y: f64, cp2x: f64, cp2y: f64, x: f64, y: f64) {
+                <$context>::bezier_curve_to(self, cp1x, cp1y, cp2x, cp2y, x, y)
+            }
+            fn quadratic_curve_to(&self, cpx: f64, cpy: f64, x: f64, y: f64) {
+                <$context>::quadratic_curve_to(self, cpx, cpy, x, y)
+            }
+            fn close_path(&self) {
+                <$context>::close_path(self)
+            }
+            fn set_fill_style_str(&self, color: &str) {
+                <$context>::set_fill_style_str(self, color)
+            }
+            fn set_fill_style_pattern(&self, pattern: &CanvasPattern) {
+                <$context>::set_fill_style_canvas_pattern(self, pattern)
+            }
+            fn set_stroke_style_str(&self, color: &str) {
+                <$context>::set_stroke_style_str(self, color)
+            }
+            fn set_line_width(&self, width: f64) {
+                <$context>::set_line_width(self, width)
+            }
+            fn set_line_cap(&self, cap: &str) {
+                <$context>::set_line_cap(self, cap)
+            }
+            fn set_line_join(&self, join: &str) {
+                <$context>::set_line_join(self, join)
+            }
+            fn set_global_alpha(&self, alpha: f64) {
+                <$context>::set_global_alpha(self, alpha)
+            }
+            fn fill(&self, rule: CanvasWindingRule) {
+                <$context>::fill_with_canvas_winding_rule(self, rule)
+            }
+            fn stroke(&self) {
+                <$context>::stroke(self)
+            }
+            fn create_pattern(&self, tile: &OffscreenCanvas) -> Result<Option<CanvasPattern>, JsValue> {
+                <$context>::create_pattern_with_offscreen_canvas(self, tile, "repeat")
+            }
+        }
+    };
+}
+
+impl_context_2d!(CanvasRenderingContext2d);
+impl_context_2d!(OffscreenCanvasRenderingContext2d);
+
+/// Replays render commands on a canvas context, keeping pattern tile bitmaps
+#[derive(Debug, Clone, Default)]
+pub struct CanvasRenderer {
+    /// Pattern tile bitmaps by tile key
+    tiles: HashMap<String, OffscreenCanvas>,
+}
+
+impl CanvasRenderer {
+    pub fn new() -> Self {
+        CanvasRenderer::default()
+    }
+
+    /// Draw commands on a context
+    pub fn draw(&mut self, ctx: &impl Context2d, commands: &[RenderCommand]) -> Result<(), JsValue> {
+        for command in commands {
+            match command {
+                RenderCommand::SetTransform { a, b, c, d, e, f } => ctx.set_transform(*a, *b, *c, *d, *e, *f)?,
+                RenderCommand::ResetTransform => ctx.reset_transform()?,
+                RenderCommand::BeginPath => ctx.begin_path(),
+                RenderCommand::Rect { x, y, width, height } => ctx.rect(*x, *y, *width, *height),
+                RenderCommand::Ellipse { cx, cy, rx, ry } => ctx.ellipse(*cx, *cy, *rx, *ry)?,
+                RenderCommand::MoveTo { x, y } => ctx.move_to(*x, *y),
+                RenderCommand::LineTo { x, y } => ctx.line_to(*x, *y),
+                RenderCommand::BezierCurveTo { cp1x, cp1y, cp2x, cp2y, x, y } => {
+                    ctx.bezier_curve_to(*cp1x, *cp1y, *cp2x, *cp2y, *x, *y)
+                }
+                RenderCommand::QuadraticCurveTo { cpx, cpy, x, y } => ctx.quadratic_curve_to(*cpx, *cpy, *x, *y),
+                RenderCommand::ClosePath => ctx.close_path(),
+                RenderCommand::SetFillStyle { color } => ctx.set_fill_style_str(color),
+                RenderCommand::SetFillPattern { image: Some(_), .. } => {}
+                RenderCommand::SetFillPattern { key, width, height, commands, image: None, a, b, c, d, e, f } => {
+                    let Some(tile) = self.tile(key, *width, *height, commands)? else {
+                        continue;
+                    };
+                    let Some(pattern) = ctx.create_pattern(&tile)? else {
+                        continue;
+                    };
+                    // The bitmap is rounded up to whole pixels; stretch it back to the cell size
+                    let (sx, sy) = (width / f64::from(tile.width()), height / f64::from(tile.height()));
+                    let matrix = DomMatrix2dInit::new();
+                    matrix.set_a(a * sx);
+                    matrix.set_b(b * sx);
+                    matrix.set_c(c * sy);
+                    matrix.set_d(d * sy);
+                    matrix.set_e(*e);
+                    matrix.set_f(*f);
+                    // setTransform takes any DOMMatrix2DInit; web-sys only types it as SVGMatrix
+                    pattern.set_transform(matrix.unchecked_ref::<SvgMatrix>());
+                    ctx.set_fill_style_pattern(&pattern);
+                }
+                RenderCommand::SetStrokeStyle { color } => ctx.set_stroke_style_str(color),
+                RenderCommand::SetLineWidth { width } => ctx.set_line_width(*width),
+                RenderCommand::SetLineCap { cap } => ctx.set_line_cap(cap),
+                RenderCommand::SetLineJoin { join } => ctx.set_line_join(join),
+                RenderCommand::SetGlobalAlpha { alpha } => ctx.set_global_alpha(*alpha),
+                RenderCommand::Fill { rule } => ctx.fill(match rule.as_str() {
+                    "evenodd" => CanvasWindingRule::Evenodd,
+                    _ => CanvasWindingRule::Nonzero,
+                }),
+                RenderCommand::Stroke => ctx.stroke(),
+                RenderCommand::BeginCache { .. } | RenderCommand::EndCache { .. } => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Bitmap of a pattern tile, drawn the first time its key is seen
+    fn tile(&mut self, key: &str, width: f64, height: f64, commands: &[RenderCommand]) -> Result<Option<OffscreenCanvas>, JsValue> {
+        if let Some(tile) = self.tiles.get(key) {
+            return Ok(Some(tile.clone()));
+        }
+        let tile = OffscreenCanvas::new(width.ceil().max(1.0) as u32, height.ceil().max(1.0) as u32)?;
+        let Some(tile_ctx) = tile.get_context("2d")?.and_then(|ctx| ctx.dyn_into::<OffscreenCanvasRenderingContext2d>().ok())
+        else {
+            return Ok(None);
+        };
+        self.draw(&tile_ctx, commands)?;
+        self.tiles.insert(key.to_string(), tile.clone());
+        Ok(Some(tile))
+    }
+}
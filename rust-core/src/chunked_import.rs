@@ -0,0 +1,410 @@
+//! Chunked Import Module - Receiving large documents piece by piece
+//!
+//! The frontend streams a file in chunks instead of passing one huge string.
+//! Size limits and nesting depth are checked as bytes arrive, so hostile or
+//! oversized input is rejected early. The document is parsed as it arrives too:
+//! each top-level field, and each top-level node, is parsed on the chunk that
+//! completes it, and its text is dropped. When the format version comes before the
+//! nodes (as in every document this editor writes), nodes are also migrated and
+//! checked as they arrive, so finishing only assembles the scene and checks what
+//! depends on all of it (symbol references, instance expansion, settings).
+
+use serde_json::{Map, Value};
+
+use crate::core::scene::{SceneGraph, SceneNode};
+use crate::migration::{self, MigrationReport};
+use crate::validation::{self, ErrorLocation, ImportError, ImportErrorCode, ImportLimits, JsonScanner};
+
+/// A document received in full: its scene, how it was migrated, and its saved history
+pub type ReceivedDocument = (SceneGraph, MigrationReport, Option<Value>);
+
+/// An import in progress
+#[derive(Debug, Clone)]
+pub struct ChunkedImport {
+    total: usize,
+    received: usize,
+    limits: ImportLimits,
+    scanner: JsonScanner,
+    /// Text of the field or node being received
+    piece: Vec<u8>,
+    /// Where `piece` starts: line, and bytes before it on that line
+    piece_start: (usize, usize),
+    /// Position of the next byte: line, and bytes before it on that line
+    position: (usize, usize),
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// The closing brace of the document has been received
+    ended: bool,
+    roots: RootsField,
+    fields: Map<String, Value>,
+    nodes: Option<Nodes>,
+}
+
+/// Progress through the "roots" field
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RootsField {
+    NotYet,
+    /// Its key has been read; the array has not started
+    Key,
+    Array,
+    Done,
+}
+
+/// Top-level nodes received so far
+#[derive(Debug, Clone)]
+enum Nodes {
+    /// Migrated, deserialized and checked on arrival, for the given format version
+    Checked { format_version: Value, nodes: Vec<SceneNode>, node_count: usize },
+    /// Kept as JSON until the end, because the format version was not known yet
+    Raw(Vec<Value>),
+}
+
+impl ChunkedImport {
+    /// Start an import of `total_size` bytes
+    pub fn begin(total_size: usize, limits: &ImportLimits) -> Result<Self, ImportError> {
+        if total_size > limits.max_input_bytes {
+            return Err(too_large(total_size, limits));
+        }
+        Ok(ChunkedImport {
+            total: total_size,
+            received: 0,
+            limits: *limits,
+            scanner: JsonScanner::new(limits),
+            piece: Vec::new(),
+            piece_start: (1, 0),
+            position: (1, 0),
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            ended: false,
+            roots: RootsField::NotYet,
+            fields: Map::new(),
+            nodes: None,
+        })
+    }
+
+    /// Append the next chunk, parsing what it completes; returns the progress (0..1)
+    pub fn push(&mut self, bytes: &[u8]) -> Result<f64, ImportError> {
+        let received = self.received + bytes.len();
+        if received > self.total {
+            return Err(ImportError::new(
+                ImportErrorCode::InputTooLarge,
+                format!("received {} bytes, announced {}", received, self.total),
+            ));
+        }
+        self.scanner.feed(bytes)?;
+        self.received = received;
+
+        let mut from = 0;
+        for (index, &byte) in bytes.iter().enumerate() {
+            self.step(bytes, &mut from, index, byte)?;
+            if byte == b'\n' {
+                self.position = (self.position.0 + 1, 0);
+            } else {
+                self.position.1 += 1;
+            }
+        }
+        self.piece.extend_from_slice(&bytes[from..]);
+        Ok(self.progress())
+    }
+
+    /// Fraction of the announced size received so far
+    pub fn progress(&self) -> f64 {
+        if self.total == 0 {
+            1.0
+        } else {
+            self.received as f64 / self.total as f64
+        }
+    }
+
+    pub fn received(&self) -> usize {
+        self.received
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+
+    /// Complete the import: assemble the scene and check it as a whole
+    /// Saved history is returned as is, for the caller to restore.
+    pub fn finish(self) -> Result<ReceivedDocument, ImportError> {
+        if self.received != self.total || !self.ended {
+            return Err(ImportError::new(
+                ImportErrorCode::ParseError,
+                format!("import ended after {} of {} bytes", self.received, self.total),
+            ));
+        }
+        let mut fields = self.fields;
+        let history = fields.remove("history");
+        let (scene, report) = match self.nodes {
+            Some(Nodes::Checked { nodes, node_count, .. }) => {
+                fields.insert("roots".to_string(), Value::Array(Vec::new()));
+                let (mut scene, report) = migrate_scene(Value::Object(fields))?;
+                scene.roots = nodes;
+                validation::validate_checked_scene(&scene, &self.limits, node_count)?;
+                (scene, report)
+            }
+            nodes => {
+                if let Some(Nodes::Raw(nodes)) = nodes {
+                    fields.insert("roots".to_string(), Value::Array(nodes));
+                }
+                let (scene, report) = migrate_scene(Value::Object(fields))?;
+                validation::validate_scene(&scene, &self.limits)?;
+                (scene, report)
+            }
+        };
+        Ok((scene, report, history))
+    }
+
+    /// Follow the document's structure through one byte, at `index` in `bytes`; the
+    /// current piece continues from `from`
+    fn step(&mut self, bytes: &[u8], from: &mut usize, index: usize, byte: u8) -> Result<(), ImportError> {
+        if self.in_string {
+            match byte {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => self.in_string = false,
+                _ => {}
+            }
+            return Ok(());
+        }
+        if self.depth == 0 && !byte.is_ascii_whitespace() && (self.ended || byte != b'{') {
+            return Err(self.error_here(if self.ended {
+                "trailing characters after the document"
+            } else {
+                "scene document must be a JSON object"
+            }));
+        }
+        match byte {
+            b'"' => self.in_string = true,
+            b'{' | b'[' => {
+                self.depth += 1;
+                if self.depth == 1 || (self.depth == 2 && byte == b'[' && self.roots == RootsField::Key) {
+                    if self.depth == 2 {
+                        self.roots = RootsField::Array;
+                        self.nodes = Some(match self.fields.get("format_version") {
+                            Some(version) => {
+                                Nodes::Checked { format_version: version.clone(), nodes: Vec::new(), node_count: 0 }
+                            }
+                            None => Nodes::Raw(Vec::new()),
+                        });
+                    }
+                    self.start_piece(from, index + 1);
+                }
+            }
+            b':' if self.depth == 1 && self.roots == RootsField::NotYet => {
+                self.piece.extend_from_slice(&bytes[*from..index]);
+                *from = index;
+                if serde_json::from_slice::<String>(self.piece.trim_ascii()).is_ok_and(|key| key == "roots") {
+                    self.roots = RootsField::Key;
+                }
+            }
+            b',' | b'}' | b']' => {
+                let closing = byte != b',';
+                if self.depth == 2 && self.roots == RootsField::Array {
+                    self.piece.extend_from_slice(&bytes[*from..index]);
+                    self.end_node(closing)?;
+                    if closing {
+                        self.roots = RootsField::Done;
+                    }
+                    self.start_piece(from, index + 1);
+                } else if self.depth == 1 {
+                    self.piece.extend_from_slice(&bytes[*from..index]);
+                    self.end_field(closing)?;
+                    self.ended = closing;
+                    self.start_piece(from, index + 1);
+                }
+                if closing {
+                    self.depth = self.depth.saturating_sub(1);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Begin a new piece at `index` of the current chunk
+    fn start_piece(&mut self, from: &mut usize, index: usize) {
+        self.piece.clear();
+        *from = index;
+        self.piece_start = (self.position.0, self.position.1 + 1);
+    }
+
+    /// A top-level field is complete
+    fn end_field(&mut self, closing: bool) -> Result<(), ImportError> {
+        let roots = std::mem::replace(&mut self.roots, RootsField::NotYet);
+        if roots == RootsField::Done || (closing && self.fields.is_empty() && self.piece.trim_ascii().is_empty()) {
+            return Ok(());
+        }
+        let mut text = Vec::with_capacity(self.piece.len() + 2);
+        text.push(b'{');
+        text.extend_from_slice(&self.piece);
+        text.push(b'}');
+        let field: Map<String, Value> = serde_json::from_slice(&text).map_err(|e| self.located(&e, 1))?;
+        self.fields.extend(field);
+        Ok(())
+    }
+
+    /// A top-level node is complete
+    fn end_node(&mut self, closing: bool) -> Result<(), ImportError> {
+        let empty_array = closing && self.piece.trim_ascii().is_empty() && self.node_count() == 0;
+        if empty_array {
+            return Ok(());
+        }
+        let node: Value = serde_json::from_slice(&self.piece).map_err(|e| self.located(&e, 0))?;
+        match &mut self.nodes {
+            Some(Nodes::Checked { format_version, nodes, node_count }) => {
+                let node = migration::migrate_root(node, format_version)?;
+                let node = serde_json::from_value::<SceneNode>(node).map_err(|e| ImportError::json(&e))?;
+                validation::check_nodes(std::slice::from_ref(&node), &self.limits, node_count)?;
+                nodes.push(node);
+            }
+            Some(Nodes::Raw(nodes)) => nodes.push(node),
+            None => {}
+        }
+        Ok(())
+    }
+
+    fn node_count(&self) -> usize {
+        match &self.nodes {
+            Some(Nodes::Checked { nodes, .. }) => nodes.len(),
+            Some(Nodes::Raw(nodes)) => nodes.len(),
+            None => 0,
+        }
+    }
+
+    /// A JSON error in the current piece, located in the whole document
+    /// `prefix`: bytes put before the piece to parse it
+    fn located(&self, error: &serde_json::Error, prefix: usize) -> ImportError {
+        let located = ImportError::json(error);
+        if error.line() == 0 {
+            return located;
+        }
+        let (line, column) = self.piece_start;
+        let location = if error.line() == 1 {
+            (line, (column + error.column()).saturating_sub(prefix))
+        } else {
+            (line + error.line() - 1, error.column())
+        };
+        located.at(ErrorLocation { line: Some(location.0), column: Some(location.1), ..Default::default() })
+    }
+
+    fn error_here(&self, message: &str) -> ImportError {
+        let (line, column) = self.position;
+        ImportError::new(ImportErrorCode::ParseError, message)
+            .at(ErrorLocation { line: Some(line), column: Some(column + 1), ..Default::default() })
+    }
+}
+
+/// Migrate a scene document and deserialize it
+fn migrate_scene(mut document: Value) -> Result<(SceneGraph, MigrationReport), ImportError> {
+    let report = migration::migrate(&mut document)?;
+    let scene = serde_json::from_value::<SceneGraph>(document).map_err(|e| ImportError::json(&e))?;
+    Ok((scene, report))
+}
+
+fn too_large(size: usize, limits: &ImportLimits) -> ImportError {
+    ImportError::new(
+        ImportErrorCode::InputTooLarge,
+        format!("input is {} bytes, limit is {}", size, limits.max_input_bytes),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::VectorObject;
+
+    fn sample_scene() -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        for i in 0..3 {
+            let id = scene.generate_id();
+            scene.add_object(id, VectorObject::Rectangle { x: i as f64, y: 0.0, width: 10.0, height: 10.0 }, TransformMatrix::identity());
+        }
+        let id = scene.roots[0].id().clone();
+        scene.create_symbol("Tile", &[id]);
+        if let Some(SceneNode::Leaf { name, .. }) = scene.roots.last_mut() {
+            *name = Some("ü".to_string());
+        }
+        scene
+    }
+
+    fn import(json: &str, chunk: usize) -> Result<(ChunkedImport, ReceivedDocument), ImportError> {
+        let mut import = ChunkedImport::begin(json.len(), &ImportLimits::default())?;
+        for bytes in json.as_bytes().chunks(chunk) {
+            import.push(bytes)?;
+        }
+        let pending = import.clone();
+        Ok((pending, import.finish()?))
+    }
+
+    #[test]
+    fn test_nodes_are_parsed_as_they_arrive() {
+        let scene = sample_scene();
+        let json = serde_json::to_string_pretty(&scene).unwrap();
+        // Every split, UTF-8 characters included, reassembles the same scene
+        for chunk in [1, 7, json.len()] {
+            let (pending, (imported, report, history)) = import(&json, chunk).unwrap();
+            assert_eq!(imported, scene);
+            assert!(report.applied.is_empty() && history.is_none());
+            // The nodes were checked before finishing and their text is gone
+            assert!(matches!(&pending.nodes, Some(Nodes::Checked { nodes, .. }) if nodes.len() == 3));
+            assert!(pending.piece.trim_ascii().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_unversioned_documents_are_migrated_at_the_end() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id, VectorObject::Path { commands: Vec::new(), is_closed: false, anchor_types: Vec::new() }, TransformMatrix::identity());
+        let mut document = serde_json::to_value(&scene).unwrap();
+        document.as_object_mut().unwrap().remove("format_version");
+        document["roots"][0]["Leaf"]["object"]["Path"].as_object_mut().unwrap().remove("is_closed");
+        let json = document.to_string();
+        let (pending, (scene, report, _)) = import(&json, 5).unwrap();
+        assert!(matches!(pending.nodes, Some(Nodes::Raw(_))));
+        assert_eq!(report.detected_version, 0);
+        assert!(matches!(&scene.roots[0], SceneNode::Leaf { object: VectorObject::Path { is_closed: true, .. }, .. }));
+    }
+
+    #[test]
+    fn test_rejects_early() {
+        let limits = ImportLimits { max_input_bytes: 10, ..ImportLimits::default() };
+        assert!(ChunkedImport::begin(11, &limits).is_err());
+
+        let mut import = ChunkedImport::begin(4, &ImportLimits::default()).unwrap();
+        assert!(import.push(b"12345").is_err());
+
+        let limits = ImportLimits { max_depth: 1, ..ImportLimits::default() };
+        let mut import = ChunkedImport::begin(100, &limits).unwrap();
+        assert_eq!(import.push(&[b'['; 20]).unwrap_err().code, ImportErrorCode::TooDeep);
+
+        // A bad node fails the chunk that completes it, located in the whole document
+        let json = "{\"format_version\":1,\n\"roots\":[{\"Leaf\":5}, ";
+        let mut import = ChunkedImport::begin(1000, &ImportLimits::default()).unwrap();
+        assert_eq!(import.push(json.as_bytes()).unwrap_err().code, ImportErrorCode::ParseError);
+
+        // Located where parsing the whole text would locate it
+        for text in ["\n  {\"roots\": [{\"x\": tru }]}", "{\"a\":\n [1,, 2]}"] {
+            let mut import = ChunkedImport::begin(1000, &ImportLimits::default()).unwrap();
+            let location = import.push(text.as_bytes()).unwrap_err().location.unwrap();
+            let whole = serde_json::from_str::<Value>(text).unwrap_err();
+            assert_eq!((location.line, location.column), (Some(whole.line()), Some(whole.column())), "{}", text);
+        }
+    }
+
+    #[test]
+    fn test_incomplete_import_fails() {
+        let mut import = ChunkedImport::begin(4, &ImportLimits::default()).unwrap();
+        import.push(b"{}").unwrap();
+        assert!(import.finish().is_err());
+        let mut import = ChunkedImport::begin(2, &ImportLimits::default()).unwrap();
+        import.push(b"{\"").unwrap();
+        assert!(import.finish().is_err());
+        let mut import = ChunkedImport::begin(4, &ImportLimits::default()).unwrap();
+        assert!(import.push(b"{}{}").is_err());
+    }
+}
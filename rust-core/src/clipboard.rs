@@ -0,0 +1,222 @@
+//! Clipboard Module - Self-contained scene fragments for copy/paste
+//!
+//! A fragment carries the copied nodes together with every symbol they
+//! reference, so it can be pasted into another document. The system clipboard
+//! itself is managed by the frontend; fragments travel as JSON strings.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{ObjectId, SceneGraph, SceneNode, Symbol};
+use crate::spatial::BoundingBox;
+use crate::validation::{self, ImportError, ImportLimits};
+
+/// Current fragment format version
+pub const FRAGMENT_VERSION: u32 = 1;
+
+/// Copied nodes plus the symbols they need
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClipboardFragment {
+    pub version: u32,
+    pub nodes: Vec<SceneNode>,
+    #[serde(default)]
+    pub symbols: Vec<Symbol>,
+}
+
+/// Build a fragment from root nodes of the scene (kept in z-order)
+pub fn copy_fragment(scene: &SceneGraph, ids: &[ObjectId]) -> ClipboardFragment {
+    let nodes: Vec<SceneNode> = scene
+        .roots
+        .iter()
+        .filter(|node| ids.contains(node.id()))
+        .cloned()
+        .collect();
+
+    // Gather referenced symbols transitively
+    let mut needed: Vec<&str> = Vec::new();
+    collect_symbol_ids(&nodes, &mut needed);
+    let mut seen = HashSet::new();
+    let mut symbols = Vec::new();
+    while let Some(symbol_id) = needed.pop() {
+        if !seen.insert(symbol_id.to_string()) {
+            continue;
+        }
+        if let Some(symbol) = scene.get_symbol(symbol_id) {
+            collect_symbol_ids(&symbol.children, &mut needed);
+            symbols.push(symbol.clone());
+        }
+    }
+
+    ClipboardFragment { version: FRAGMENT_VERSION, nodes, symbols }
+}
+
+/// Parse and validate an untrusted fragment
+pub fn parse_fragment(json: &str, limits: &ImportLimits) -> Result<ClipboardFragment, ImportError> {
+    validation::check_json_input(json, limits)?;
+    let fragment: ClipboardFragment = serde_json::from_str(json).map_err(|e| ImportError::json(&e))?;
+
+    // Validate as a standalone scene so instances must resolve within the fragment
+    validation::validate_scene(&fragment.to_scene(), limits)?;
+    Ok(fragment)
+}
+
+impl ClipboardFragment {
+    /// The fragment as a standalone scene (for validation and measuring)
+    fn to_scene(&self) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        scene.roots = self.nodes.clone();
+        scene.symbols = self.symbols.clone();
+        scene
+    }
+
+    /// World-space bounds of the copied content, in the coordinates it was copied from
+    pub fn bounds(&self) -> Option<BoundingBox> {
+        geometry::scene_bounds(&self.to_scene())
+    }
+}
+
+/// Insert a fragment at the top of the z-order, offset by (dx, dy)
+/// All node IDs are replaced with fresh ones. Symbols already present with identical
+/// content are reused; others are added to the library under fresh IDs.
+/// Returns the IDs of the inserted root nodes.
+pub fn paste_fragment(scene: &mut SceneGraph, fragment: ClipboardFragment, dx: f64, dy: f64) -> Vec<ObjectId> {
+    // Decide the library ID for every symbol in the fragment
+    let mut symbol_ids: HashMap<ObjectId, ObjectId> = HashMap::new();
+    let mut new_symbols = Vec::new();
+    for symbol in fragment.symbols {
+        let reusable = scene
+            .get_symbol(&symbol.id)
+            .is_some_and(|existing| same_content(existing, &symbol));
+        if reusable {
+            symbol_ids.insert(symbol.id.clone(), symbol.id.clone());
+        } else {
+            let new_id = scene.generate_symbol_id();
+            symbol_ids.insert(symbol.id.clone(), new_id.clone());
+            new_symbols.push(Symbol { id: new_id, ..symbol });
+        }
+    }
+
+    for mut symbol in new_symbols {
+        for child in &mut symbol.children {
+            scene.assign_fresh_ids(child);
+            remap_symbol_refs(child, &symbol_ids);
+        }
+        scene.symbols.push(symbol);
+    }
+
+    let offset = TransformMatrix::translate(dx, dy);
+    let mut new_ids = Vec::with_capacity(fragment.nodes.len());
+    for mut node in fragment.nodes {
+        scene.assign_fresh_ids(&mut node);
+        remap_symbol_refs(&mut node, &symbol_ids);
+        let transform = node.transform_mut();
+        *transform = offset.multiply(transform);
+        new_ids.push(node.id().clone());
+        scene.roots.push(node);
+    }
+    new_ids
+}
+
+fn same_content(a: &Symbol, b: &Symbol) -> bool {
+    match (serde_json::to_string(&a.children), serde_json::to_string(&b.children)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn collect_symbol_ids<'a>(nodes: &'a [SceneNode], result: &mut Vec<&'a str>) {
+    for node in nodes {
+        match node {
+            SceneNode::Group { children, .. } => collect_symbol_ids(children, result),
+            SceneNode::Instance { symbol_id, .. } => result.push(symbol_id),
+            SceneNode::Leaf { .. } => {}
+        }
+    }
+}
+
+fn remap_symbol_refs(node: &mut SceneNode, symbol_ids: &HashMap<ObjectId, ObjectId>) {
+    match node {
+        SceneNode::Group { children, .. } => {
+            for child in children {
+                remap_symbol_refs(child, symbol_ids);
+            }
+        }
+        SceneNode::Instance { symbol_id, .. } => {
+            if let Some(new_id) = symbol_ids.get(symbol_id) {
+                *symbol_id = new_id.clone();
+            }
+        }
+        SceneNode::Leaf { .. } => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::VectorObject;
+
+    fn scene_with_rect() -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 },
+            TransformMatrix::identity(),
+        );
+        scene
+    }
+
+    #[test]
+    fn test_paste_remaps_ids_and_offsets() {
+        let mut scene = scene_with_rect();
+        let fragment = copy_fragment(&scene, &["obj_1".to_string()]);
+        let json = serde_json::to_string(&fragment).unwrap();
+
+        let parsed = parse_fragment(&json, &ImportLimits::default()).unwrap();
+        let ids = paste_fragment(&mut scene, parsed, 10.0, 10.0);
+
+        assert_eq!(ids.len(), 1);
+        assert_ne!(ids[0], "obj_1");
+        assert_eq!(scene.roots.last().unwrap().id(), &ids[0]);
+        assert!((scene.roots[1].transform().tx - 10.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_symbols_travel_with_instances() {
+        let mut source = scene_with_rect();
+        let (symbol_id, instance_id) = source.create_symbol("Tile", &["obj_1".to_string()]).unwrap();
+        let fragment = copy_fragment(&source, &[instance_id]);
+        assert_eq!(fragment.symbols.len(), 1);
+
+        // Same document: the identical symbol is reused
+        paste_fragment(&mut source, fragment.clone(), 0.0, 0.0);
+        assert_eq!(source.symbols.len(), 1);
+
+        // Other document with a conflicting symbol ID: pasted under a fresh ID
+        let mut target = scene_with_rect();
+        let (_, _) = target.create_symbol("Other", &["obj_1".to_string()]).unwrap();
+        target.symbols[0].children.clear();
+        assert_eq!(target.symbols[0].id, symbol_id);
+        let ids = paste_fragment(&mut target, fragment, 0.0, 0.0);
+        assert_eq!(target.symbols.len(), 2);
+        match target.get_node_by_id(&ids[0]) {
+            Some(SceneNode::Instance { symbol_id: pasted, .. }) => assert_ne!(pasted, &symbol_id),
+            other => panic!("expected instance, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_fragment_bounds() {
+        let scene = scene_with_rect();
+        let bounds = copy_fragment(&scene, &["obj_1".to_string()]).bounds().unwrap();
+        assert_eq!(bounds.center(), (5.0, 5.0));
+    }
+
+    #[test]
+    fn test_rejects_fragment_with_dangling_instance() {
+        let json = r#"{"version":1,"nodes":[{"Instance":{"id":"a","symbol_id":"missing","transform":{"a":1,"b":0,"c":0,"d":1,"tx":0,"ty":0}}}]}"#;
+        assert!(parse_fragment(json, &ImportLimits::default()).is_err());
+    }
+}
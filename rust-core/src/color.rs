@@ -0,0 +1,311 @@
+//! Color Module - Color parsing, manipulation and blending spaces
+//!
+//! Style colors are stored as sRGB hex strings. Colors coming in through the
+//! API may use any of the CSS forms handled by `Color::parse` (hex, rgb(),
+//! rgba(), hsl(), hsla()) and are normalized to hex before they are stored;
+//! transparency belongs to the object's opacity, so their alpha is dropped.
+//! The document's working space decides whether raster export blends
+//! translucent colors on the stored gamma-encoded values, as browsers do, or on
+//! linear light values, as color-managed tools do.
+
+use serde::{Deserialize, Serialize};
+
+/// Color space in which the document blends colors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColorSpace {
+    /// Blend gamma-encoded sRGB values (web default)
+    #[default]
+    Srgb,
+    /// Blend in linear-light sRGB
+    LinearSrgb,
+}
+
+impl ColorSpace {
+    /// Parse the API name of a color space ("srgb" or "linear_srgb")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "srgb" => Some(ColorSpace::Srgb),
+            "linear_srgb" => Some(ColorSpace::LinearSrgb),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "srgb",
+            ColorSpace::LinearSrgb => "linear_srgb",
+        }
+    }
+
+    /// Value of the SVG `color-interpolation` property for this space
+    pub fn svg_color_interpolation(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "sRGB",
+            ColorSpace::LinearSrgb => "linearRGB",
+        }
+    }
+}
+
+/// Convert a gamma-encoded sRGB channel (0..1) to linear light
+pub fn srgb_to_linear(value: f64) -> f64 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Convert a linear-light channel (0..1) to gamma-encoded sRGB
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// sRGB color with alpha, every channel in 0..1
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub r: f64,
+    pub g: f64,
+    pub b: f64,
+    pub a: f64,
+}
+
+impl Color {
+    /// Opaque color from sRGB channels (clamped to 0..1)
+    pub fn rgb(r: f64, g: f64, b: f64) -> Self {
+        Color::rgba(r, g, b, 1.0)
+    }
+
+    /// Color from sRGB channels and alpha (clamped to 0..1)
+    pub fn rgba(r: f64, g: f64, b: f64, a: f64) -> Self {
+        let unit = |value: f64| if value.is_nan() { 0.0 } else { value.clamp(0.0, 1.0) };
+        Color { r: unit(r), g: unit(g), b: unit(b), a: unit(a) }
+    }
+
+    /// Color from hue (degrees), saturation and lightness (0..1)
+    pub fn from_hsl(hue: f64, saturation: f64, lightness: f64, alpha: f64) -> Self {
+        let (s, l) = (saturation.clamp(0.0, 1.0), lightness.clamp(0.0, 1.0));
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        let h = hue.rem_euclid(360.0) / 60.0;
+        let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as u32 {
+            0 => (chroma, x, 0.0),
+            1 => (x, chroma, 0.0),
+            2 => (0.0, chroma, x),
+            3 => (0.0, x, chroma),
+            4 => (x, 0.0, chroma),
+            _ => (chroma, 0.0, x),
+        };
+        let m = l - chroma / 2.0;
+        Color::rgba(r + m, g + m, b + m, alpha)
+    }
+
+    /// Parse a CSS color: "#rgb", "#rgba", "#rrggbb", "#rrggbbaa", "rgb()", "rgba()",
+    /// "hsl()" or "hsla()" (comma or space separated, with an optional "/ alpha")
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        if let Some(hex) = text.strip_prefix('#') {
+            return Color::parse_hex(hex);
+        }
+        let open = text.find('(')?;
+        let name = text[..open].trim().to_ascii_lowercase();
+        let arguments = text[open + 1..].strip_suffix(')')?;
+        let parts: Vec<&str> =
+            arguments.split([',', ' ', '/']).map(str::trim).filter(|part| !part.is_empty()).collect();
+        if parts.len() != 3 && parts.len() != 4 {
+            return None;
+        }
+        let alpha = match parts.get(3) {
+            Some(part) => parse_fraction(part)?,
+            None => 1.0,
+        };
+        match name.as_str() {
+            "rgb" | "rgba" => {
+                let channel = |part: &str| match part.strip_suffix('%') {
+                    Some(percent) => percent.parse::<f64>().ok().map(|value| value / 100.0),
+                    None => part.parse::<f64>().ok().map(|value| value / 255.0),
+                };
+                Some(Color::rgba(channel(parts[0])?, channel(parts[1])?, channel(parts[2])?, alpha))
+            }
+            "hsl" | "hsla" => {
+                let hue = parts[0].strip_suffix("deg").unwrap_or(parts[0]).parse::<f64>().ok()?;
+                let percent = |part: &str| part.strip_suffix('%')?.parse::<f64>().ok().map(|value| value / 100.0);
+                if !hue.is_finite() {
+                    return None;
+                }
+                Some(Color::from_hsl(hue, percent(parts[1])?, percent(parts[2])?, alpha))
+            }
+            _ => None,
+        }
+    }
+
+    fn parse_hex(hex: &str) -> Option<Self> {
+        let digits: Vec<f64> = match hex.len() {
+            6 | 8 => (0..hex.len() / 2)
+                .map(|i| u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok())
+                .map(|value| value.map(|value| value as f64 / 255.0))
+                .collect::<Option<_>>()?,
+            3 | 4 => hex.chars().map(|c| c.to_digit(16).map(|d| (d * 17) as f64 / 255.0)).collect::<Option<_>>()?,
+            _ => return None,
+        };
+        Some(Color::rgba(digits[0], digits[1], digits[2], digits.get(3).copied().unwrap_or(1.0)))
+    }
+
+    /// Hue (degrees, 0..360), saturation and lightness (0..1)
+    pub fn to_hsl(self) -> (f64, f64, f64) {
+        let max = self.r.max(self.g).max(self.b);
+        let min = self.r.min(self.g).min(self.b);
+        let lightness = (max + min) / 2.0;
+        let chroma = max - min;
+        if chroma <= f64::EPSILON {
+            return (0.0, 0.0, lightness);
+        }
+        let saturation = chroma / (1.0 - (2.0 * lightness - 1.0).abs());
+        let hue = if max == self.r {
+            ((self.g - self.b) / chroma).rem_euclid(6.0)
+        } else if max == self.g {
+            (self.b - self.r) / chroma + 2.0
+        } else {
+            (self.r - self.g) / chroma + 4.0
+        };
+        (hue * 60.0, saturation.clamp(0.0, 1.0), lightness)
+    }
+
+    /// sRGB channels without alpha
+    pub fn channels(self) -> [f64; 3] {
+        [self.r, self.g, self.b]
+    }
+
+    /// "#rrggbb" (alpha is dropped)
+    pub fn to_hex(self) -> String {
+        let [r, g, b] = self.channels().map(|channel| (channel * 255.0).round() as u8);
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+
+    /// "#rrggbb" when opaque, "rgba(r, g, b, a)" otherwise
+    pub fn to_css(self) -> String {
+        if self.a >= 1.0 {
+            return self.to_hex();
+        }
+        let [r, g, b] = self.channels().map(|channel| (channel * 255.0).round() as u8);
+        format!("rgba({}, {}, {}, {})", r, g, b, (self.a * 1000.0).round() / 1000.0)
+    }
+
+    /// Raise the HSL lightness by `amount` (0..1)
+    pub fn lighten(self, amount: f64) -> Self {
+        let (h, s, l) = self.to_hsl();
+        Color::from_hsl(h, s, l + amount, self.a)
+    }
+
+    /// Lower the HSL lightness by `amount` (0..1)
+    pub fn darken(self, amount: f64) -> Self {
+        self.lighten(-amount)
+    }
+
+    /// Raise the HSL saturation by `amount` (0..1)
+    pub fn saturate(self, amount: f64) -> Self {
+        let (h, s, l) = self.to_hsl();
+        Color::from_hsl(h, s + amount, l, self.a)
+    }
+
+    /// Lower the HSL saturation by `amount` (0..1)
+    pub fn desaturate(self, amount: f64) -> Self {
+        self.saturate(-amount)
+    }
+
+    /// Same color with another alpha
+    pub fn with_alpha(self, alpha: f64) -> Self {
+        Color::rgba(self.r, self.g, self.b, alpha)
+    }
+
+    /// Opaque color from linear-light channels
+    pub fn from_linear(linear: [f64; 3]) -> Self {
+        let [r, g, b] = linear.map(linear_to_srgb);
+        Color::rgb(r, g, b)
+    }
+}
+
+/// Number or percentage in 0..1 ("0.5" or "50%")
+fn parse_fraction(part: &str) -> Option<f64> {
+    match part.strip_suffix('%') {
+        Some(percent) => percent.parse::<f64>().ok().map(|value| value / 100.0),
+        None => part.parse::<f64>().ok(),
+    }
+}
+
+/// Parse any color `Color::parse` accepts into the stored "#rrggbb" form
+pub fn normalize_color(color: &str) -> Option<String> {
+    Color::parse(color).map(Color::to_hex)
+}
+
+/// Parse a stored "#rrggbb" or "#rgb" color into sRGB channels (0..1)
+pub fn parse_hex_color(color: &str) -> Option<[f64; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 3 {
+        return None;
+    }
+    Color::parse_hex(hex).map(Color::channels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_transfer_functions_round_trip() {
+        for value in [0.0, 0.002, 0.04045, 0.2, 0.5, 1.0] {
+            assert!((linear_to_srgb(srgb_to_linear(value)) - value).abs() < 1e-6);
+        }
+        assert!((srgb_to_linear(0.5) - 0.214).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_parse_hex_color() {
+        assert_eq!(parse_hex_color("#ff0000"), Some([1.0, 0.0, 0.0]));
+        assert_eq!(parse_hex_color("#fff"), Some([1.0, 1.0, 1.0]));
+        assert_eq!(parse_hex_color("red"), None);
+        assert_eq!(parse_hex_color("#12345"), None);
+    }
+
+    #[test]
+    fn test_parse_css_colors() {
+        let orange = Some(Color::rgb(1.0, 0.4, 0.0));
+        assert_eq!(Color::parse("#ff6600"), orange);
+        assert_eq!(Color::parse("#f60"), orange);
+        assert_eq!(Color::parse("rgb(255, 102, 0)"), orange);
+        assert_eq!(Color::parse("rgb(100% 40% 0%)"), orange);
+        assert_eq!(Color::parse("#ff660080").map(|c| (c.a * 255.0).round()), Some(128.0));
+        assert_eq!(Color::parse("rgba(255, 102, 0, 0.5)"), Some(Color::rgba(1.0, 0.4, 0.0, 0.5)));
+        assert_eq!(Color::parse("rgb(255 102 0 / 50%)"), Some(Color::rgba(1.0, 0.4, 0.0, 0.5)));
+        assert_eq!(Color::parse("hsl(24, 100%, 50%)").map(Color::to_hex).as_deref(), Some("#ff6600"));
+        assert_eq!(Color::parse("hsla(240deg 100% 50% / 0.25)"), Some(Color::rgba(0.0, 0.0, 1.0, 0.25)));
+        for invalid in ["orange", "#ff666", "rgb(1, 2)", "hsl(10, 20, 30)", "rgb(1, 2, x)", "rgb(1, 2, 3"] {
+            assert_eq!(Color::parse(invalid), None, "{}", invalid);
+        }
+        assert_eq!(normalize_color(" RGB(0, 0, 255) ").as_deref(), Some("#0000ff"));
+        assert_eq!(parse_hex_color("#ff000080"), None);
+    }
+
+    #[test]
+    fn test_hsl_round_trip_and_adjustments() {
+        let color = Color::rgb(0.2, 0.6, 0.4);
+        let (h, s, l) = color.to_hsl();
+        let back = Color::from_hsl(h, s, l, 1.0);
+        assert!(color.channels().iter().zip(back.channels()).all(|(a, b)| (a - b).abs() < 1e-9));
+
+        let red = Color::rgb(1.0, 0.0, 0.0);
+        assert_eq!(red.lighten(0.25).to_hex(), "#ff8080");
+        assert_eq!(red.darken(0.25).to_hex(), "#800000");
+        assert_eq!(red.desaturate(1.0).to_hex(), "#808080");
+        assert_eq!(Color::rgb(0.75, 0.25, 0.25).saturate(1.0).to_hex(), "#ff0000");
+        assert_eq!(red.lighten(2.0).to_hex(), "#ffffff");
+        assert_eq!(red.with_alpha(0.5).to_css(), "rgba(255, 0, 0, 0.5)");
+        assert_eq!(red.to_css(), "#ff0000");
+        let grey = Color::from_linear([srgb_to_linear(0.5); 3]);
+        assert!((grey.r - 0.5).abs() < 1e-9);
+    }
+}
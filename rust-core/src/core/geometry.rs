@@ -0,0 +1,533 @@
+//! Geometry module - Curve math and exact bounds computation
+//!
+//! All bounds are computed in world space after applying the transform,
+//! so rotated objects get a tight axis-aligned box rather than the AABB
+//! of their transformed local box.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+use super::math::TransformMatrix;
+use super::scene::{PathCommand, SceneGraph, SceneNode, VectorObject};
+use crate::spatial::BoundingBox;
+
+/// Evaluate one coordinate of a cubic bezier at parameter t
+pub fn cubic_at(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let mt = 1.0 - t;
+    mt * mt * mt * p0 + 3.0 * mt * mt * t * p1 + 3.0 * mt * t * t * p2 + t * t * t * p3
+}
+
+/// Parameters in (0, 1) where one coordinate of a cubic bezier has a local extremum
+pub fn cubic_extrema(p0: f64, p1: f64, p2: f64, p3: f64) -> Vec<f64> {
+    // Derivative: 3[(p1-p0)(1-t)² + 2(p2-p1)(1-t)t + (p3-p2)t²] = a t² + b t + c
+    let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+    let b = 2.0 * (p0 - 2.0 * p1 + p2);
+    let c = p1 - p0;
+
+    let mut roots = Vec::new();
+    if a.abs() < 1e-12 {
+        if b.abs() > 1e-12 {
+            roots.push(-c / b);
+        }
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant >= 0.0 {
+            let sqrt_d = discriminant.sqrt();
+            roots.push((-b + sqrt_d) / (2.0 * a));
+            roots.push((-b - sqrt_d) / (2.0 * a));
+        }
+    }
+    roots.retain(|t| *t > 0.0 && *t < 1.0);
+    roots
+}
+
+/// Tight bounds of a cubic bezier segment (endpoints plus extrema)
+pub fn cubic_bounds(p0: (f64, f64), p1: (f64, f64), p2: (f64, f64), p3: (f64, f64)) -> BoundingBox {
+    let mut bounds = BoundingBox::new(p0.0.min(p3.0), p0.1.min(p3.1), p0.0.max(p3.0), p0.1.max(p3.1));
+    for t in cubic_extrema(p0.0, p1.0, p2.0, p3.0) {
+        let x = cubic_at(p0.0, p1.0, p2.0, p3.0, t);
+        bounds.min_x = bounds.min_x.min(x);
+        bounds.max_x = bounds.max_x.max(x);
+    }
+    for t in cubic_extrema(p0.1, p1.1, p2.1, p3.1) {
+        let y = cubic_at(p0.1, p1.1, p2.1, p3.1, t);
+        bounds.min_y = bounds.min_y.min(y);
+        bounds.max_y = bounds.max_y.max(y);
+    }
+    bounds
+}
+
+/// Exact cubic form of a quadratic bezier
+pub fn quad_to_cubic(from: (f64, f64), control: (f64, f64), to: (f64, f64)) -> PathCommand {
+    PathCommand::CurveTo {
+        x1: from.0 + 2.0 / 3.0 * (control.0 - from.0),
+        y1: from.1 + 2.0 / 3.0 * (control.1 - from.1),
+        x2: to.0 + 2.0 / 3.0 * (control.0 - to.0),
+        y2: to.1 + 2.0 / 3.0 * (control.1 - to.1),
+        x: to.0,
+        y: to.1,
+    }
+}
+
+/// Convert an SVG elliptical arc to cubic beziers (at most 90° each)
+/// Follows the endpoint-to-center conversion of SVG 1.1 appendix F.6.5,
+/// including scaling up radii that are too small to reach the end point.
+pub fn arc_to_curves(
+    from: (f64, f64),
+    radii: (f64, f64),
+    rotation_degrees: f64,
+    large_arc: bool,
+    sweep: bool,
+    to: (f64, f64),
+) -> Vec<PathCommand> {
+    let (mut rx, mut ry) = (radii.0.abs(), radii.1.abs());
+    if from == to {
+        return Vec::new();
+    }
+    if rx < 1e-12 || ry < 1e-12 {
+        return vec![PathCommand::LineTo { x: to.0, y: to.1 }];
+    }
+
+    let (sin_phi, cos_phi) = rotation_degrees.to_radians().sin_cos();
+    let (hx, hy) = ((from.0 - to.0) / 2.0, (from.1 - to.1) / 2.0);
+    let x1 = cos_phi * hx + sin_phi * hy;
+    let y1 = -sin_phi * hx + cos_phi * hy;
+
+    let lambda = (x1 * x1) / (rx * rx) + (y1 * y1) / (ry * ry);
+    if lambda > 1.0 {
+        rx *= lambda.sqrt();
+        ry *= lambda.sqrt();
+    }
+
+    let numerator = rx * rx * ry * ry - rx * rx * y1 * y1 - ry * ry * x1 * x1;
+    let denominator = rx * rx * y1 * y1 + ry * ry * x1 * x1;
+    let mut coefficient = (numerator / denominator).max(0.0).sqrt();
+    if large_arc == sweep {
+        coefficient = -coefficient;
+    }
+    let (cx1, cy1) = (coefficient * rx * y1 / ry, -coefficient * ry * x1 / rx);
+    let cx = cos_phi * cx1 - sin_phi * cy1 + (from.0 + to.0) / 2.0;
+    let cy = sin_phi * cx1 + cos_phi * cy1 + (from.1 + to.1) / 2.0;
+
+    let angle = |ux: f64, uy: f64, vx: f64, vy: f64| (ux * vy - uy * vx).atan2(ux * vx + uy * vy);
+    let start_angle = angle(1.0, 0.0, (x1 - cx1) / rx, (y1 - cy1) / ry);
+    let mut sweep_angle = angle((x1 - cx1) / rx, (y1 - cy1) / ry, (-x1 - cx1) / rx, (-y1 - cy1) / ry);
+    if !sweep && sweep_angle > 0.0 {
+        sweep_angle -= 2.0 * PI;
+    } else if sweep && sweep_angle < 0.0 {
+        sweep_angle += 2.0 * PI;
+    }
+
+    // Map a point of the unit circle onto the ellipse
+    let map = |ux: f64, uy: f64| (cx + rx * cos_phi * ux - ry * sin_phi * uy, cy + rx * sin_phi * ux + ry * cos_phi * uy);
+
+    let segments = (sweep_angle.abs() / FRAC_PI_2 - 1e-9).ceil().max(1.0) as usize;
+    let step = sweep_angle / segments as f64;
+    let k = 4.0 / 3.0 * (step / 4.0).tan();
+    let mut curves = Vec::with_capacity(segments);
+    for i in 0..segments {
+        let t1 = start_angle + step * i as f64;
+        let t2 = t1 + step;
+        let (sin1, cos1) = t1.sin_cos();
+        let (sin2, cos2) = t2.sin_cos();
+        let (x1, y1) = map(cos1 - k * sin1, sin1 + k * cos1);
+        let (x2, y2) = map(cos2 + k * sin2, sin2 - k * cos2);
+        // Land exactly on the requested end point
+        let (x, y) = if i + 1 == segments { to } else { map(cos2, sin2) };
+        curves.push(PathCommand::CurveTo { x1, y1, x2, y2, x, y });
+    }
+    curves
+}
+
+/// Same path with quadratic curves and arcs converted to cubic beziers
+pub fn to_cubic_commands(commands: &[PathCommand]) -> Vec<PathCommand> {
+    let mut cubic = Vec::with_capacity(commands.len());
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    for cmd in commands {
+        match *cmd {
+            PathCommand::MoveTo { x, y } => {
+                cubic.push(cmd.clone());
+                start = (x, y);
+                current = start;
+            }
+            PathCommand::LineTo { x, y } | PathCommand::CurveTo { x, y, .. } => {
+                cubic.push(cmd.clone());
+                current = (x, y);
+            }
+            PathCommand::QuadTo { x1, y1, x, y } => {
+                cubic.push(quad_to_cubic(current, (x1, y1), (x, y)));
+                current = (x, y);
+            }
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+                cubic.extend(arc_to_curves(current, (rx, ry), rotation, large_arc, sweep, (x, y)));
+                current = (x, y);
+            }
+            PathCommand::ClosePath => {
+                cubic.push(PathCommand::ClosePath);
+                current = start;
+            }
+        }
+    }
+    cubic
+}
+
+/// Whether a path has commands other than MoveTo/LineTo/CurveTo/ClosePath
+pub fn has_quads_or_arcs(commands: &[PathCommand]) -> bool {
+    commands.iter().any(|cmd| matches!(cmd, PathCommand::QuadTo { .. } | PathCommand::ArcTo { .. }))
+}
+
+/// Arc radii, rotation (degrees) and sweep flag after an affine transform
+/// The image of an ellipse is an ellipse: its axes come from the singular value
+/// decomposition of the transform applied to the arc's own axes. Mirroring
+/// transforms reverse the sweep direction.
+pub fn transform_arc(
+    radii: (f64, f64),
+    rotation_degrees: f64,
+    sweep: bool,
+    transform: &TransformMatrix,
+) -> (f64, f64, f64, bool) {
+    let (rx, ry) = radii;
+    let (sin, cos) = rotation_degrees.to_radians().sin_cos();
+    let TransformMatrix { a, b, c, d, .. } = *transform;
+    let (m00, m01) = (rx * (a * cos + b * sin), ry * (b * cos - a * sin));
+    let (m10, m11) = (rx * (c * cos + d * sin), ry * (d * cos - c * sin));
+    let (e, f) = ((m00 + m11) / 2.0, (m00 - m11) / 2.0);
+    let (g, h) = ((m10 + m01) / 2.0, (m10 - m01) / 2.0);
+    let (q, r) = (e.hypot(h), f.hypot(g));
+    let angle = (g.atan2(f) + h.atan2(e)) / 2.0;
+    let mirrored = a * d - b * c < 0.0;
+    (q + r, (q - r).abs(), angle.to_degrees(), sweep != mirrored)
+}
+
+/// Exact world-space axis-aligned bounds of an object under a transform
+/// Returns None for empty paths
+pub fn object_world_bounds(object: &VectorObject, transform: &TransformMatrix) -> Option<BoundingBox> {
+    match object {
+        VectorObject::Rectangle { x, y, width, height } => {
+            Some(BoundingBox::from_rect(*x, *y, *width, *height).transform(transform))
+        }
+        VectorObject::Ellipse { cx, cy, rx, ry } => {
+            // An affine image of an ellipse is an ellipse; its half extents are
+            // the lengths of the rows of the linear part scaled by the radii.
+            let (wcx, wcy) = transform.transform_point(*cx, *cy);
+            let half_w = ((transform.a * rx).powi(2) + (transform.b * ry).powi(2)).sqrt();
+            let half_h = ((transform.c * rx).powi(2) + (transform.d * ry).powi(2)).sqrt();
+            Some(BoundingBox::new(wcx - half_w, wcy - half_h, wcx + half_w, wcy + half_h))
+        }
+        VectorObject::Circle { cx, cy, r } => {
+            let ellipse = VectorObject::Ellipse { cx: *cx, cy: *cy, rx: *r, ry: *r };
+            object_world_bounds(&ellipse, transform)
+        }
+        VectorObject::Text(_) => object_world_bounds(&object.drawable(), transform),
+        VectorObject::Path { commands, .. } => {
+            // Bezier curves are affine invariant, so transform control points first
+            // and find extrema in world space. Quadratics and arcs are measured in
+            // their cubic form.
+            let world = transform_path_commands(commands, transform);
+            let mut bounds: Option<BoundingBox> = None;
+            let mut current = (0.0, 0.0);
+            let mut include = |b: BoundingBox| {
+                bounds = Some(match bounds {
+                    Some(existing) => existing.union(&b),
+                    None => b,
+                });
+            };
+
+            for cmd in to_cubic_commands(&world) {
+                match cmd {
+                    PathCommand::MoveTo { x, y } | PathCommand::LineTo { x, y } => {
+                        current = (x, y);
+                        include(BoundingBox::new(x, y, x, y));
+                    }
+                    PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                        include(cubic_bounds(current, (x1, y1), (x2, y2), (x, y)));
+                        current = (x, y);
+                    }
+                    PathCommand::QuadTo { .. } | PathCommand::ArcTo { .. } | PathCommand::ClosePath => {}
+                }
+            }
+            bounds
+        }
+    }
+}
+
+/// Magic number for approximating a quarter ellipse with one cubic bezier
+const KAPPA: f64 = 0.552_284_749_830_793_4;
+
+/// Path commands tracing the outline of an object
+/// Rectangles start at the top-left corner; ellipses are four cubic arcs starting at 3 o'clock.
+/// Text gives its glyph outlines.
+pub fn object_to_path_commands(object: &VectorObject) -> Vec<PathCommand> {
+    match object {
+        VectorObject::Rectangle { x, y, width, height } => vec![
+            PathCommand::MoveTo { x: *x, y: *y },
+            PathCommand::LineTo { x: x + width, y: *y },
+            PathCommand::LineTo { x: x + width, y: y + height },
+            PathCommand::LineTo { x: *x, y: y + height },
+            PathCommand::ClosePath,
+        ],
+        VectorObject::Ellipse { cx, cy, rx, ry } => ellipse_commands(*cx, *cy, *rx, *ry),
+        VectorObject::Circle { cx, cy, r } => ellipse_commands(*cx, *cy, *r, *r),
+        VectorObject::Path { commands, .. } => commands.clone(),
+        VectorObject::Text(text) => text.outline.clone(),
+    }
+}
+
+fn ellipse_commands(cx: f64, cy: f64, rx: f64, ry: f64) -> Vec<PathCommand> {
+    let (kx, ky) = (rx * KAPPA, ry * KAPPA);
+    vec![
+        PathCommand::MoveTo { x: cx + rx, y: cy },
+        PathCommand::CurveTo { x1: cx + rx, y1: cy + ky, x2: cx + kx, y2: cy + ry, x: cx, y: cy + ry },
+        PathCommand::CurveTo { x1: cx - kx, y1: cy + ry, x2: cx - rx, y2: cy + ky, x: cx - rx, y: cy },
+        PathCommand::CurveTo { x1: cx - rx, y1: cy - ky, x2: cx - kx, y2: cy - ry, x: cx, y: cy - ry },
+        PathCommand::CurveTo { x1: cx + kx, y1: cy - ry, x2: cx + rx, y2: cy - ky, x: cx + rx, y: cy },
+        PathCommand::ClosePath,
+    ]
+}
+
+/// Apply a transform to every point of a path
+/// Exact, since beziers are affine invariant and arcs are mapped onto the transformed ellipse.
+pub fn transform_path_commands(commands: &[PathCommand], transform: &TransformMatrix) -> Vec<PathCommand> {
+    commands
+        .iter()
+        .map(|cmd| match cmd {
+            PathCommand::MoveTo { x, y } => {
+                let (x, y) = transform.transform_point(*x, *y);
+                PathCommand::MoveTo { x, y }
+            }
+            PathCommand::LineTo { x, y } => {
+                let (x, y) = transform.transform_point(*x, *y);
+                PathCommand::LineTo { x, y }
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                let (x1, y1) = transform.transform_point(*x1, *y1);
+                let (x2, y2) = transform.transform_point(*x2, *y2);
+                let (x, y) = transform.transform_point(*x, *y);
+                PathCommand::CurveTo { x1, y1, x2, y2, x, y }
+            }
+            PathCommand::QuadTo { x1, y1, x, y } => {
+                let (x1, y1) = transform.transform_point(*x1, *y1);
+                let (x, y) = transform.transform_point(*x, *y);
+                PathCommand::QuadTo { x1, y1, x, y }
+            }
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+                let (rx, ry, rotation, sweep) = transform_arc((*rx, *ry), *rotation, *sweep, transform);
+                let (x, y) = transform.transform_point(*x, *y);
+                PathCommand::ArcTo { rx, ry, rotation, large_arc: *large_arc, sweep, x, y }
+            }
+            PathCommand::ClosePath => PathCommand::ClosePath,
+        })
+        .collect()
+}
+
+/// Exact world-space bounds of a node (union of all leaves for groups and instances)
+/// The scene is needed to resolve instances against its symbol library.
+pub fn node_world_bounds(
+    scene: &SceneGraph,
+    node: &SceneNode,
+    parent_transform: &TransformMatrix,
+) -> Option<BoundingBox> {
+    match node {
+        SceneNode::Leaf { object, transform, .. } => {
+            object_world_bounds(object, &parent_transform.multiply(transform))
+        }
+        SceneNode::Group { children, transform, .. } => {
+            let world_transform = parent_transform.multiply(transform);
+            nodes_world_bounds(scene, children, &world_transform)
+        }
+        SceneNode::Instance { symbol_id, transform, .. } => {
+            let symbol = scene.get_symbol(symbol_id)?;
+            nodes_world_bounds(scene, &symbol.children, &parent_transform.multiply(transform))
+        }
+    }
+}
+
+/// Union of the world-space bounds of a list of nodes
+pub fn nodes_world_bounds(
+    scene: &SceneGraph,
+    nodes: &[SceneNode],
+    parent_transform: &TransformMatrix,
+) -> Option<BoundingBox> {
+    nodes
+        .iter()
+        .filter_map(|node| node_world_bounds(scene, node, parent_transform))
+        .reduce(|acc, b| acc.union(&b))
+}
+
+/// Union of the geometric bounds of every object in the scene
+pub fn scene_bounds(scene: &SceneGraph) -> Option<BoundingBox> {
+    nodes_world_bounds(scene, &scene.roots, &TransformMatrix::identity())
+}
+
+/// Union of the painted bounds of every object in the scene
+/// Like `scene_bounds`, but grown by half the (transformed) stroke width of stroked objects.
+pub fn scene_visual_bounds(scene: &SceneGraph) -> Option<BoundingBox> {
+    scene
+        .iter_leaf_views()
+        .into_iter()
+        .filter_map(|leaf| {
+            let bounds = object_world_bounds(leaf.object, &leaf.world_transform)?;
+            if leaf.style.stroke_color.is_some() {
+                let scale = leaf.world_transform.determinant().abs().sqrt();
+                Some(bounds.expand(leaf.style.stroke_width * scale / 2.0))
+            } else {
+                Some(bounds)
+            }
+        })
+        .reduce(|acc, b| acc.union(&b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::PI;
+
+    #[test]
+    fn test_cubic_bounds_include_extrema() {
+        // Symmetric arch: endpoints at y=0, peak at y=-75 (t = 0.5)
+        let bounds = cubic_bounds((0.0, 0.0), (0.0, -100.0), (100.0, -100.0), (100.0, 0.0));
+        assert!((bounds.min_y + 75.0).abs() < 1e-9);
+        assert!(bounds.max_y.abs() < 1e-9);
+        assert!(bounds.min_x.abs() < 1e-9);
+        assert!((bounds.max_x - 100.0).abs() < 1e-9);
+    }
+
+    fn end_point(cmd: &PathCommand) -> (f64, f64) {
+        match *cmd {
+            PathCommand::MoveTo { x, y } | PathCommand::LineTo { x, y } | PathCommand::CurveTo { x, y, .. } => (x, y),
+            _ => panic!("expected a point"),
+        }
+    }
+
+    #[test]
+    fn test_arc_half_circle() {
+        // Half circle of radius 10 from (0, 0) to (20, 0), bulging towards -y (sweep 1 = clockwise)
+        let curves = arc_to_curves((0.0, 0.0), (10.0, 10.0), 0.0, false, true, (20.0, 0.0));
+        assert_eq!(curves.len(), 2);
+        let mid = end_point(&curves[0]);
+        assert!((mid.0 - 10.0).abs() < 1e-9 && (mid.1 + 10.0).abs() < 1e-9, "{:?}", mid);
+        assert_eq!(end_point(&curves[1]), (20.0, 0.0));
+
+        // Radii too small to reach are scaled up; zero radii give a straight line
+        let scaled = arc_to_curves((0.0, 0.0), (1.0, 1.0), 0.0, false, false, (20.0, 0.0));
+        let mid = end_point(&scaled[0]);
+        assert!((mid.1 - 10.0).abs() < 1e-9, "{:?}", mid);
+        assert_eq!(arc_to_curves((0.0, 0.0), (0.0, 5.0), 0.0, false, true, (5.0, 5.0)), vec![PathCommand::LineTo { x: 5.0, y: 5.0 }]);
+    }
+
+    #[test]
+    fn test_to_cubic_commands() {
+        let commands = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::QuadTo { x1: 15.0, y1: 30.0, x: 30.0, y: 0.0 },
+            PathCommand::ArcTo { rx: 10.0, ry: 10.0, rotation: 0.0, large_arc: false, sweep: true, x: 50.0, y: 0.0 },
+            PathCommand::ClosePath,
+        ];
+        assert!(has_quads_or_arcs(&commands));
+        let cubic = to_cubic_commands(&commands);
+        assert!(!has_quads_or_arcs(&cubic));
+        assert_eq!(cubic[1], PathCommand::CurveTo { x1: 10.0, y1: 20.0, x2: 20.0, y2: 20.0, x: 30.0, y: 0.0 });
+        // The half circle takes two quarter curves
+        assert_eq!(cubic.len(), 5);
+        assert_eq!(end_point(&cubic[3]), (50.0, 0.0));
+    }
+
+    #[test]
+    fn test_transform_arc_matches_transformed_curves() {
+        let arc = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::ArcTo { rx: 20.0, ry: 10.0, rotation: 15.0, large_arc: true, sweep: false, x: 30.0, y: 5.0 },
+        ];
+        let skewed = TransformMatrix { a: 1.5, b: 0.4, c: -0.2, d: -0.8, tx: 3.0, ty: 7.0 };
+        let expected = transform_path_commands(&to_cubic_commands(&arc), &skewed);
+        let actual = to_cubic_commands(&transform_path_commands(&arc, &skewed));
+        // Both must trace the same curve: every point of one lies on the other
+        let (polyline, _) = crate::path_ops::flatten_subpaths(&expected, 0.01).remove(0);
+        let distance = |(px, py): (f64, f64)| {
+            polyline
+                .windows(2)
+                .map(|pair| {
+                    let ((ax, ay), (bx, by)) = (pair[0], pair[1]);
+                    let t = (((px - ax) * (bx - ax) + (py - ay) * (by - ay)) / ((bx - ax).powi(2) + (by - ay).powi(2))).clamp(0.0, 1.0);
+                    (px - ax - t * (bx - ax)).hypot(py - ay - t * (by - ay))
+                })
+                .fold(f64::MAX, f64::min)
+        };
+        for cmd in &actual[1..] {
+            let point = end_point(cmd);
+            assert!(distance(point) < 0.1, "{:?} is off the arc", point);
+        }
+        assert_eq!(end_point(&actual[actual.len() - 1]), end_point(&expected[expected.len() - 1]));
+    }
+
+    #[test]
+    fn test_quadratic_bounds() {
+        let path = VectorObject::Path {
+            commands: vec![PathCommand::MoveTo { x: 0.0, y: 0.0 }, PathCommand::QuadTo { x1: 50.0, y1: 100.0, x: 100.0, y: 0.0 }],
+            is_closed: false,
+            anchor_types: Vec::new(),
+        };
+        let bounds = object_world_bounds(&path, &TransformMatrix::identity()).unwrap();
+        assert!((bounds.max_y - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_rotated_ellipse_bounds() {
+        let ellipse = VectorObject::Ellipse { cx: 0.0, cy: 0.0, rx: 50.0, ry: 10.0 };
+        let bounds = object_world_bounds(&ellipse, &TransformMatrix::rotate(PI / 2.0)).unwrap();
+        assert!((bounds.width() - 20.0).abs() < 1e-9);
+        assert!((bounds.height() - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_shape_outlines_keep_bounds() {
+        let rotation = TransformMatrix::rotate(0.3);
+        for object in [
+            VectorObject::Rectangle { x: 5.0, y: 5.0, width: 20.0, height: 10.0 },
+            VectorObject::Ellipse { cx: 0.0, cy: 0.0, rx: 50.0, ry: 10.0 },
+        ] {
+            let path = VectorObject::Path {
+                commands: object_to_path_commands(&object),
+                is_closed: true,
+                anchor_types: Vec::new(),
+            };
+            let expected = object_world_bounds(&object, &rotation).unwrap();
+            let actual = object_world_bounds(&path, &rotation).unwrap();
+            // Bezier ellipse approximation is within 0.03% of the radius
+            assert!((expected.width() - actual.width()).abs() < 0.05);
+            assert!((expected.height() - actual.height()).abs() < 0.05);
+        }
+    }
+
+    #[test]
+    fn test_transform_path_commands() {
+        let commands = vec![PathCommand::MoveTo { x: 1.0, y: 0.0 }, PathCommand::LineTo { x: 0.0, y: 2.0 }];
+        let moved = transform_path_commands(&commands, &TransformMatrix::translate(10.0, 0.0));
+        match moved[1] {
+            PathCommand::LineTo { x, y } => assert_eq!((x, y), (10.0, 2.0)),
+            _ => panic!("expected LineTo"),
+        }
+    }
+
+    #[test]
+    fn test_group_bounds_apply_group_transform() {
+        let leaf = SceneNode::Leaf {
+            id: "obj_1".to_string(),
+            object: VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 20.0 },
+            transform: TransformMatrix::translate(5.0, 0.0),
+            style: Default::default(),
+            name: None,
+        };
+        let group = SceneNode::Group {
+            id: "group".to_string(),
+            children: vec![leaf],
+            transform: TransformMatrix::scale(2.0, 2.0),
+            name: None,
+            cache_as_bitmap: false,
+        };
+        let bounds = node_world_bounds(&SceneGraph::new(), &group, &TransformMatrix::identity()).unwrap();
+        assert!((bounds.min_x - 10.0).abs() < 1e-9);
+        assert!((bounds.max_x - 30.0).abs() < 1e-9);
+        assert!((bounds.max_y - 40.0).abs() < 1e-9);
+    }
+}
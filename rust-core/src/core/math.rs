@@ -136,6 +136,61 @@ impl TransformMatrix {
     pub fn translation(&self) -> (f64, f64) {
         (self.tx, self.ty)
     }
+
+    /// Split the matrix into translation, rotation, skew and scale
+    /// The matrix equals Translate × Rotate × SkewX × Scale (see `compose`). Mirroring is
+    /// reported as a negative scale_y. A matrix that collapses the x axis has no
+    /// defined rotation; it decomposes with rotation and skew of 0.
+    pub fn decompose(&self) -> TransformComponents {
+        let scale_x = self.a.hypot(self.c);
+        if scale_x < 1e-12 {
+            return TransformComponents {
+                translate_x: self.tx,
+                translate_y: self.ty,
+                rotation: 0.0,
+                scale_x: 0.0,
+                scale_y: self.b.hypot(self.d),
+                skew: 0.0,
+            };
+        }
+        let rotation = self.c.atan2(self.a);
+        let (sin, cos) = rotation.sin_cos();
+        // Undo the rotation on the y axis: what is left is (tan(skew) * scale_y, scale_y)
+        let scale_y = self.determinant() / scale_x;
+        let sheared = self.b * cos + self.d * sin;
+        TransformComponents {
+            translate_x: self.tx,
+            translate_y: self.ty,
+            rotation,
+            scale_x,
+            scale_y,
+            skew: if scale_y.abs() < 1e-12 { 0.0 } else { (sheared / scale_y).atan() },
+        }
+    }
+
+    /// Build a matrix from its components: Translate × Rotate × SkewX × Scale
+    pub fn compose(components: &TransformComponents) -> Self {
+        let skew = TransformMatrix {
+            b: components.skew.tan(),
+            ..TransformMatrix::identity()
+        };
+        TransformMatrix::translate(components.translate_x, components.translate_y)
+            .multiply(&TransformMatrix::rotate(-components.rotation))
+            .multiply(&skew)
+            .multiply(&TransformMatrix::scale(components.scale_x, components.scale_y))
+    }
+}
+
+/// Decomposed form of a transform (angles in radians, clockwise on screen)
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct TransformComponents {
+    pub translate_x: f64,
+    pub translate_y: f64,
+    pub rotation: f64,
+    pub scale_x: f64,
+    pub scale_y: f64,
+    /// Horizontal skew: the angle the object's y axis leans away from vertical
+    pub skew: f64,
 }
 
 impl Default for TransformMatrix {
@@ -226,4 +281,42 @@ mod tests {
         assert!((px - 100.0).abs() < 1e-10);
         assert!((py - 100.0).abs() < 1e-10);
     }
+
+    fn assert_matrix_eq(m: &TransformMatrix, expected: &TransformMatrix) {
+        for (value, expected) in [(m.a, expected.a), (m.b, expected.b), (m.c, expected.c), (m.d, expected.d), (m.tx, expected.tx), (m.ty, expected.ty)] {
+            assert!((value - expected).abs() < 1e-9, "{:?} != {:?}", m, expected);
+        }
+    }
+
+    #[test]
+    fn test_decompose_rotation_and_scale() {
+        let m = TransformMatrix::translate(30.0, 40.0)
+            .multiply(&TransformMatrix::rotate(-32f64.to_radians()))
+            .multiply(&TransformMatrix::scale(2.0, 3.0));
+        let parts = m.decompose();
+        assert!((parts.rotation.to_degrees() - 32.0).abs() < 1e-9);
+        assert!((parts.scale_x - 2.0).abs() < 1e-9);
+        assert!((parts.scale_y - 3.0).abs() < 1e-9);
+        assert!(parts.skew.abs() < 1e-9);
+        assert_eq!((parts.translate_x, parts.translate_y), (30.0, 40.0));
+    }
+
+    #[test]
+    fn test_decompose_compose_roundtrip() {
+        let skewed = TransformMatrix { a: 1.5, b: 0.7, c: -0.4, d: 2.0, tx: -5.0, ty: 12.0 };
+        let mirrored = TransformMatrix::rotate(1.0).multiply(&TransformMatrix::scale(-1.0, 2.0));
+        for m in [skewed, mirrored, TransformMatrix::identity()] {
+            assert_matrix_eq(&TransformMatrix::compose(&m.decompose()), &m);
+        }
+        // Mirroring shows up as a negative scale_y
+        assert!(mirrored.decompose().scale_y < 0.0);
+    }
+
+    #[test]
+    fn test_decompose_degenerate_matrix() {
+        let parts = TransformMatrix::scale(0.0, 4.0).decompose();
+        assert_eq!(parts.scale_x, 0.0);
+        assert_eq!(parts.scale_y, 4.0);
+        assert_eq!(parts.rotation, 0.0);
+    }
 }
@@ -1,4 +1,5 @@
 //! Core module - Contains fundamental types and algorithms
 
+pub mod geometry;
 pub mod math;
 pub mod scene;
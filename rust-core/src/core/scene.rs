@@ -3,15 +3,26 @@
 //! Uses the Composite Pattern for hierarchical scene structure
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::borrow::Cow;
+use tsify::Tsify;
 
 use super::math::TransformMatrix;
+use crate::animation::Timeline;
+use crate::artboard::Artboard;
+use crate::color::ColorSpace;
+use crate::document::DocumentSettings;
+use crate::library::{SharedStyle, Swatch};
+use crate::migration::FORMAT_VERSION;
+use crate::pattern::PatternFill;
+use crate::snap::{GridSettings, RulerGuide};
+use crate::text_engine::KerningOverrides;
 
 /// Unique identifier for scene objects
+#[tsify::declare]
 pub type ObjectId = String;
 
 /// Vector object types
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum VectorObject {
     Rectangle {
         x: f64,
@@ -25,52 +36,611 @@ pub enum VectorObject {
         rx: f64,
         ry: f64,
     },
+    /// Circle with a single radius (stays circular when its radius is edited)
+    Circle {
+        cx: f64,
+        cy: f64,
+        r: f64,
+    },
     Path {
         commands: Vec<PathCommand>,
         /// Whether the path is closed (ends with ClosePath command)
-        /// Default true for backward compatibility with existing save files
-        #[serde(default = "default_true")]
         is_closed: bool,
+        /// Anchor types in get_path_points order; anchors past the end are corners
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        anchor_types: Vec<AnchorType>,
     },
+    /// Text shaped with a loaded font (boxed to keep scene nodes small)
+    Text(Box<TextObject>),
+}
+
+/// Text content and the glyph outlines of its last shaping
+/// The outlines are kept with the object, so it draws, hit tests and exports
+/// like a closed path even without the font.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextObject {
+    pub content: String,
+    /// Font the text is set in, resolved against the registered fonts when it is shaped
+    pub font: TextFont,
+    /// Em size in local units
+    pub size: f64,
+    /// Start of the baseline
+    pub x: f64,
+    pub y: f64,
+    /// Wrapping, alignment, indent and leading of the lines
+    #[serde(default, skip_serializing_if = "ParagraphStyle::is_default")]
+    pub paragraph: ParagraphStyle,
+    /// Letter spacing and OpenType features
+    #[serde(default, skip_serializing_if = "Typography::is_default")]
+    pub typography: Typography,
+    /// Glyph outlines in local units
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outline: Vec<PathCommand>,
+    /// Layers of the color glyphs (emoji), drawn over the outline in their own colors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub color_layers: Vec<ColorLayer>,
+}
+
+/// Font of a text object by family, weight and style
+/// Saved with the document, so text finds its font again in any session where a
+/// font of the family is registered.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextFont {
+    pub family: String,
+    /// CSS weight (100..900)
+    #[serde(default = "TextFont::regular")]
+    pub weight: u16,
+    #[serde(default)]
+    pub italic: bool,
+}
+
+impl TextFont {
+    fn regular() -> u16 {
+        400
+    }
+}
+
+/// One layer of a color font glyph (COLR/CPAL): an outline filled with one color
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ColorLayer {
+    /// CSS color; None for the text's own fill color
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<String>,
+    /// Outline in local units
+    pub commands: Vec<PathCommand>,
+}
+
+/// How the lines of a text object are laid out
+/// Paragraphs are separated by '\n'. In vertical text lines are columns: the width,
+/// alignment and indent apply down the column.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ParagraphStyle {
+    /// Width lines wrap at (area text); None for point text, which only breaks at newlines
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<f64>,
+    #[serde(default)]
+    pub align: TextAlign,
+    /// Offset of the first line of each paragraph
+    #[serde(default)]
+    pub indent: f64,
+    /// Distance between baselines in local units; None for automatic (1.2 em)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub leading: Option<f64>,
+    #[serde(default, skip_serializing_if = "WritingMode::is_horizontal")]
+    pub writing_mode: WritingMode,
+}
+
+impl ParagraphStyle {
+    fn is_default(&self) -> bool {
+        *self == ParagraphStyle::default()
+    }
+}
+
+/// Character level settings of a text object
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Typography {
+    /// Extra space after each character in thousandths of an em (negative tightens)
+    #[serde(default)]
+    pub tracking: f64,
+    /// OpenType feature settings passed to shaping, e.g. "smcp", "-liga", "kern=0"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub features: Vec<String>,
+    /// Manual kerning by pair index (between characters i and i + 1 of the content) in
+    /// units of the primary font, on top of the font's own kerning
+    #[serde(default, skip_serializing_if = "KerningOverrides::is_empty")]
+    pub kerning: KerningOverrides,
+}
+
+impl Typography {
+    fn is_default(&self) -> bool {
+        *self == Typography::default()
+    }
+}
+
+/// Direction lines of text run in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WritingMode {
+    /// Lines run left to right (or right to left) and stack downwards
+    #[default]
+    Horizontal,
+    /// Lines are columns running top to bottom, stacking to the left (CJK vertical
+    /// writing). Upright characters keep their orientation; others (Latin) are turned
+    /// 90° clockwise.
+    Vertical,
+}
+
+impl WritingMode {
+    /// Parse a writing mode name: "horizontal" | "vertical"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "horizontal" => Some(WritingMode::Horizontal),
+            "vertical" => Some(WritingMode::Vertical),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WritingMode::Horizontal => "horizontal",
+            WritingMode::Vertical => "vertical",
+        }
+    }
+
+    fn is_horizontal(&self) -> bool {
+        *self == WritingMode::Horizontal
+    }
+}
+
+/// Horizontal alignment of text lines
+/// Point text aligns around its x; area text within its width. Justified lines
+/// (all but the last of a paragraph) stretch their spaces to fill the width.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+    Justify,
+}
+
+impl TextAlign {
+    /// Parse an alignment name: "left" | "center" | "right" | "justify"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "left" => Some(TextAlign::Left),
+            "center" => Some(TextAlign::Center),
+            "right" => Some(TextAlign::Right),
+            "justify" => Some(TextAlign::Justify),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TextAlign::Left => "left",
+            TextAlign::Center => "center",
+            TextAlign::Right => "right",
+            TextAlign::Justify => "justify",
+        }
+    }
+}
+
+impl VectorObject {
+    /// Geometry to draw: text becomes the closed path of its glyph outlines,
+    /// color glyph layers included
+    pub fn drawable(&self) -> Cow<'_, VectorObject> {
+        match self {
+            VectorObject::Text(text) => Cow::Owned(VectorObject::Path {
+                commands: text.outline.iter().chain(text.color_layers.iter().flat_map(|layer| &layer.commands)).cloned().collect(),
+                is_closed: true,
+                anchor_types: Vec::new(),
+            }),
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// Geometry filled and stroked with the object's style: [`drawable`](Self::drawable)
+    /// without the color glyph layers, which are painted in their own colors on top
+    pub fn styled_geometry(&self) -> Cow<'_, VectorObject> {
+        match self {
+            VectorObject::Text(text) => Cow::Owned(VectorObject::Path {
+                commands: text.outline.clone(),
+                is_closed: true,
+                anchor_types: Vec::new(),
+            }),
+            _ => Cow::Borrowed(self),
+        }
+    }
+
+    /// Color glyph layers drawn over the styled geometry (text in a color font only)
+    pub fn color_layers(&self) -> &[ColorLayer] {
+        match self {
+            VectorObject::Text(text) => &text.color_layers,
+            _ => &[],
+        }
+    }
 }
 
-/// Default function for is_closed field (backward compatibility)
-fn default_true() -> bool {
-    true
+/// How a path anchor treats its handles
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorType {
+    /// Handles move independently
+    #[default]
+    Corner,
+    /// Handles stay collinear and mirrored through the anchor
+    Smooth,
+}
+
+impl AnchorType {
+    /// Parse an anchor type name: "corner" | "smooth"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "corner" => Some(AnchorType::Corner),
+            "smooth" => Some(AnchorType::Smooth),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AnchorType::Corner => "corner",
+            AnchorType::Smooth => "smooth",
+        }
+    }
 }
 
 /// SVG-compatible path commands
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
 #[serde(tag = "type")]
 pub enum PathCommand {
     MoveTo { x: f64, y: f64 },
     LineTo { x: f64, y: f64 },
     CurveTo { x1: f64, y1: f64, x2: f64, y2: f64, x: f64, y: f64 },
+    /// Quadratic bezier with a single control point
+    QuadTo { x1: f64, y1: f64, x: f64, y: f64 },
+    /// Elliptical arc in SVG endpoint form (rotation of the x axis in degrees)
+    ArcTo { rx: f64, ry: f64, rotation: f64, large_arc: bool, sweep: bool, x: f64, y: f64 },
     ClosePath,
 }
 
-/// Scene node - either a group or a leaf object
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl PathCommand {
+    /// Point the command ends on (None for ClosePath, which returns to the subpath start)
+    pub fn end_point(&self) -> Option<(f64, f64)> {
+        match *self {
+            PathCommand::MoveTo { x, y }
+            | PathCommand::LineTo { x, y }
+            | PathCommand::CurveTo { x, y, .. }
+            | PathCommand::QuadTo { x, y, .. }
+            | PathCommand::ArcTo { x, y, .. } => Some((x, y)),
+            PathCommand::ClosePath => None,
+        }
+    }
+}
+
+/// Scene node - a group, a leaf object, or an instance of a library symbol
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SceneNode {
     Group {
         id: ObjectId,
         children: Vec<SceneNode>,
         transform: TransformMatrix,
+        /// Optional user-facing name
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+        /// Hint that the subtree is static and its output can be cached as a bitmap
+        #[serde(default)]
+        cache_as_bitmap: bool,
     },
     Leaf {
         id: ObjectId,
         object: VectorObject,
         transform: TransformMatrix,
         style: ObjectStyle,
+        /// Optional user-facing name
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
     },
+    /// Placement of a shared symbol from the scene's symbol library
+    Instance {
+        id: ObjectId,
+        symbol_id: ObjectId,
+        transform: TransformMatrix,
+        /// Optional user-facing name
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        name: Option<String>,
+    },
+}
+
+impl SceneNode {
+    /// Get the node ID
+    pub fn id(&self) -> &ObjectId {
+        match self {
+            SceneNode::Group { id, .. } | SceneNode::Leaf { id, .. } | SceneNode::Instance { id, .. } => id,
+        }
+    }
+
+    /// Get the node's own (local) transform
+    pub fn transform(&self) -> &TransformMatrix {
+        match self {
+            SceneNode::Group { transform, .. }
+            | SceneNode::Leaf { transform, .. }
+            | SceneNode::Instance { transform, .. } => transform,
+        }
+    }
+
+    /// Get the node's own (local) transform for editing
+    pub fn transform_mut(&mut self) -> &mut TransformMatrix {
+        match self {
+            SceneNode::Group { transform, .. }
+            | SceneNode::Leaf { transform, .. }
+            | SceneNode::Instance { transform, .. } => transform,
+        }
+    }
+
+    /// Get the node name, if one has been set
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            SceneNode::Group { name, .. } | SceneNode::Leaf { name, .. } | SceneNode::Instance { name, .. } => {
+                name.as_deref()
+            }
+        }
+    }
+
+    /// Check whether this node is a group flagged for bitmap caching
+    pub fn is_cached_as_bitmap(&self) -> bool {
+        matches!(self, SceneNode::Group { cache_as_bitmap: true, .. })
+    }
+
+    /// Set or clear the node name
+    pub fn set_name(&mut self, new_name: Option<String>) {
+        match self {
+            SceneNode::Group { name, .. } | SceneNode::Leaf { name, .. } | SceneNode::Instance { name, .. } => {
+                *name = new_name
+            }
+        }
+    }
+}
+
+/// A reusable piece of artwork in the symbol library
+/// Instances reference it by ID, so editing the symbol updates every instance.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Symbol {
+    pub id: ObjectId,
+    pub name: String,
+    pub children: Vec<SceneNode>,
+}
+
+/// A leaf object together with its identity and accumulated world transform
+#[derive(Debug, Clone, Copy)]
+pub struct LeafView<'a> {
+    pub id: &'a ObjectId,
+    /// ID of the top-level node containing this leaf (the leaf itself at root level)
+    pub root_id: &'a ObjectId,
+    /// Whether the leaf is drawn through a symbol instance (its ID is then shared by all instances)
+    pub instanced: bool,
+    pub name: Option<&'a str>,
+    pub object: &'a VectorObject,
+    pub world_transform: TransformMatrix,
+    pub style: &'a ObjectStyle,
 }
 
 /// Visual style for objects
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
 pub struct ObjectStyle {
     pub fill_color: Option<String>,
     pub stroke_color: Option<String>,
     pub stroke_width: f64,
+    /// Antialiasing hint
+    #[serde(default, skip_serializing_if = "ShapeRendering::is_auto")]
+    pub shape_rendering: ShapeRendering,
+    /// Shape of the ends of open subpaths
+    #[serde(default, skip_serializing_if = "LineCap::is_default")]
+    pub line_cap: LineCap,
+    /// Shape of the corners between segments
+    #[serde(default, skip_serializing_if = "LineJoin::is_default")]
+    pub line_join: LineJoin,
+    /// Where the stroke sits relative to the outline of closed shapes
+    #[serde(default, skip_serializing_if = "StrokeAlign::is_default")]
+    pub stroke_align: StrokeAlign,
+    /// Which areas of a self-intersecting or multi-subpath outline are filled
+    #[serde(default, skip_serializing_if = "FillRule::is_default")]
+    pub fill_rule: FillRule,
+    /// Opacity of the whole object, 0..1
+    #[serde(default = "fully_opaque", skip_serializing_if = "is_fully_opaque")]
+    pub opacity: f64,
+    /// Repeating tile drawn instead of the fill color (which stays as the fallback)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fill_pattern: Option<PatternFill>,
+    /// Shared style this style was copied from; editing that style rewrites it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shared_style: Option<ObjectId>,
+}
+
+fn fully_opaque() -> f64 {
+    1.0
+}
+
+fn is_fully_opaque(opacity: &f64) -> bool {
+    *opacity == 1.0
+}
+
+/// Stroke end shape (same names as Canvas and SVG)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+impl LineCap {
+    /// Parse a cap name: "butt" | "round" | "square"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "butt" => Some(LineCap::Butt),
+            "round" => Some(LineCap::Round),
+            "square" => Some(LineCap::Square),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineCap::Butt => "butt",
+            LineCap::Round => "round",
+            LineCap::Square => "square",
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == LineCap::Butt
+    }
+}
+
+/// Stroke corner shape (same names as Canvas and SVG)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+impl LineJoin {
+    /// Parse a join name: "miter" | "round" | "bevel"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "miter" => Some(LineJoin::Miter),
+            "round" => Some(LineJoin::Round),
+            "bevel" => Some(LineJoin::Bevel),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            LineJoin::Miter => "miter",
+            LineJoin::Round => "round",
+            LineJoin::Bevel => "bevel",
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == LineJoin::Miter
+    }
+}
+
+/// Stroke position relative to the outline (open paths are always centered)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum StrokeAlign {
+    #[default]
+    Center,
+    Inside,
+    Outside,
+}
+
+impl StrokeAlign {
+    /// Parse an alignment name: "center" | "inside" | "outside"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "center" => Some(StrokeAlign::Center),
+            "inside" => Some(StrokeAlign::Inside),
+            "outside" => Some(StrokeAlign::Outside),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            StrokeAlign::Center => "center",
+            StrokeAlign::Inside => "inside",
+            StrokeAlign::Outside => "outside",
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == StrokeAlign::Center
+    }
+}
+
+/// Fill rule (same names as Canvas and SVG)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum FillRule {
+    /// Inside where the outline winds around the point a nonzero number of times
+    #[default]
+    NonZero,
+    /// Inside where a ray from the point crosses the outline an odd number of times
+    EvenOdd,
+}
+
+impl FillRule {
+    /// Parse a rule name: "nonzero" | "evenodd"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "nonzero" => Some(FillRule::NonZero),
+            "evenodd" => Some(FillRule::EvenOdd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FillRule::NonZero => "nonzero",
+            FillRule::EvenOdd => "evenodd",
+        }
+    }
+
+    /// Whether a winding number counts as inside
+    pub fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+
+    fn is_default(&self) -> bool {
+        *self == FillRule::NonZero
+    }
+}
+
+/// Rendering hint for an object's edges
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "kebab-case")]
+pub enum ShapeRendering {
+    /// Regular antialiased rendering
+    #[default]
+    Auto,
+    /// Align outlines to the pixel grid so 1px lines and pixel art stay sharp
+    CrispEdges,
+}
+
+impl ShapeRendering {
+    /// Parse a hint name: "auto" | "crisp-edges"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "auto" => Some(ShapeRendering::Auto),
+            "crisp-edges" => Some(ShapeRendering::CrispEdges),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShapeRendering::Auto => "auto",
+            ShapeRendering::CrispEdges => "crisp-edges",
+        }
+    }
+
+    fn is_auto(&self) -> bool {
+        *self == ShapeRendering::Auto
+    }
 }
 
 impl Default for ObjectStyle {
@@ -79,46 +649,149 @@ impl Default for ObjectStyle {
             fill_color: Some("#3b82f6".to_string()), // Blue
             stroke_color: Some("#1e40af".to_string()), // Dark blue
             stroke_width: 2.0,
+            shape_rendering: ShapeRendering::Auto,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            stroke_align: StrokeAlign::Center,
+            fill_rule: FillRule::NonZero,
+            opacity: 1.0,
+            fill_pattern: None,
+            shared_style: None,
         }
     }
 }
 
 /// Scene graph - manages all objects in the scene
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SceneGraph {
+    /// Schema version of the saved document (see the migration module)
+    #[serde(default)]
+    pub format_version: u32,
+    /// Page size, background and units
+    #[serde(default)]
+    pub document: DocumentSettings,
+    /// Page frames exported separately (objects belong to them by position)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub artboards: Vec<Artboard>,
     /// Root nodes (top-level objects)
     pub roots: Vec<SceneNode>,
+    /// Symbol library referenced by instance nodes
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
+    /// Named colors for the color pickers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub swatches: Vec<Swatch>,
+    /// Named styles that objects link to
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub shared_styles: Vec<SharedStyle>,
+    /// Working color space used for blending
+    #[serde(default)]
+    pub color_space: ColorSpace,
+    /// Snapping grid
+    #[serde(default)]
+    pub grid: GridSettings,
+    /// Ruler guides (document-only, not exported)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guides: Vec<RulerGuide>,
+    /// Keyframes and playback settings
+    #[serde(default, skip_serializing_if = "Timeline::is_default")]
+    pub timeline: Timeline,
     /// Counter for generating unique IDs
     id_counter: u64,
-    /// Quick lookup for object transforms (for future spatial indexing)
-    #[serde(skip)]
-    transform_cache: HashMap<ObjectId, TransformMatrix>,
 }
 
 impl SceneGraph {
     /// Create a new empty scene graph
     pub fn new() -> Self {
         SceneGraph {
+            format_version: FORMAT_VERSION,
+            document: DocumentSettings::default(),
+            artboards: Vec::new(),
+            roots: Vec::new(),
+            symbols: Vec::new(),
+            swatches: Vec::new(),
+            shared_styles: Vec::new(),
+            color_space: ColorSpace::default(),
+            grid: GridSettings::default(),
+            guides: Vec::new(),
+            timeline: Timeline::default(),
+            id_counter: 0,
+        }
+    }
+
+    /// A copy of the document-level parts of the scene: everything but the top-level
+    /// nodes and the ID counter (which are left empty)
+    pub fn clone_settings(&self) -> Self {
+        SceneGraph {
+            format_version: self.format_version,
+            document: self.document.clone(),
+            artboards: self.artboards.clone(),
             roots: Vec::new(),
+            symbols: self.symbols.clone(),
+            swatches: self.swatches.clone(),
+            shared_styles: self.shared_styles.clone(),
+            color_space: self.color_space,
+            grid: self.grid,
+            guides: self.guides.clone(),
+            timeline: self.timeline.clone(),
             id_counter: 0,
-            transform_cache: HashMap::new(),
         }
     }
 
+    /// Counter behind generated IDs
+    pub fn id_counter(&self) -> u64 {
+        self.id_counter
+    }
+
+    /// Continue generating IDs from `counter`
+    pub fn set_id_counter(&mut self, counter: u64) {
+        self.id_counter = counter;
+    }
+
     /// Generate a unique object ID
     pub fn generate_id(&mut self) -> ObjectId {
         self.id_counter += 1;
         format!("obj_{}", self.id_counter)
     }
 
+    /// Generate a unique symbol ID
+    pub fn generate_symbol_id(&mut self) -> ObjectId {
+        self.id_counter += 1;
+        format!("sym_{}", self.id_counter)
+    }
+
+    /// Generate a unique ruler guide ID
+    pub fn generate_guide_id(&mut self) -> ObjectId {
+        self.id_counter += 1;
+        format!("guide_{}", self.id_counter)
+    }
+
+    /// Generate a unique artboard ID
+    pub fn generate_artboard_id(&mut self) -> ObjectId {
+        self.id_counter += 1;
+        format!("artboard_{}", self.id_counter)
+    }
+
+    /// Generate a unique swatch ID
+    pub fn generate_swatch_id(&mut self) -> ObjectId {
+        self.id_counter += 1;
+        format!("swatch_{}", self.id_counter)
+    }
+
+    /// Generate a unique shared style ID
+    pub fn generate_style_id(&mut self) -> ObjectId {
+        self.id_counter += 1;
+        format!("style_{}", self.id_counter)
+    }
+
     /// Add an object to the scene root
     pub fn add_object(&mut self, id: ObjectId, object: VectorObject, transform: TransformMatrix) {
-        self.transform_cache.insert(id.clone(), transform);
         let node = SceneNode::Leaf {
             id,
             object,
             transform,
             style: ObjectStyle::default(),
+            name: None,
         };
         self.roots.push(node);
     }
@@ -130,7 +803,7 @@ impl SceneGraph {
 
     fn count_nodes(&self, nodes: &[SceneNode]) -> usize {
         nodes.iter().map(|node| match node {
-            SceneNode::Leaf { .. } => 1,
+            SceneNode::Leaf { .. } | SceneNode::Instance { .. } => 1,
             SceneNode::Group { children, .. } => 1 + self.count_nodes(children),
         }).sum()
     }
@@ -158,10 +831,250 @@ impl SceneGraph {
                     let world_transform = parent_transform.multiply(transform);
                     self.collect_leaves(children, world_transform, result);
                 }
+                SceneNode::Instance { symbol_id, transform, .. } => {
+                    if let Some(symbol) = self.get_symbol(symbol_id) {
+                        let world_transform = parent_transform.multiply(transform);
+                        self.collect_leaves(&symbol.children, world_transform, result);
+                    }
+                }
             }
         }
     }
 
+    /// Iterate over all leaf nodes with their IDs, names and accumulated transforms
+    pub fn iter_leaf_views(&self) -> Vec<LeafView<'_>> {
+        let mut result = Vec::new();
+        for root in &self.roots {
+            self.collect_leaf_views(std::slice::from_ref(root), root.id(), false, TransformMatrix::identity(), &mut result);
+        }
+        result
+    }
+
+    /// Leaf views of one top-level node
+    pub fn layer_leaf_views<'a>(&'a self, root: &'a SceneNode) -> Vec<LeafView<'a>> {
+        let mut result = Vec::new();
+        self.collect_leaf_views(std::slice::from_ref(root), root.id(), false, TransformMatrix::identity(), &mut result);
+        result
+    }
+
+    fn collect_leaf_views<'a>(
+        &'a self,
+        nodes: &'a [SceneNode],
+        root_id: &'a ObjectId,
+        instanced: bool,
+        parent_transform: TransformMatrix,
+        result: &mut Vec<LeafView<'a>>,
+    ) {
+        for node in nodes {
+            match node {
+                SceneNode::Leaf { id, object, transform, style, name } => {
+                    result.push(LeafView {
+                        id,
+                        root_id,
+                        instanced,
+                        name: name.as_deref(),
+                        object,
+                        world_transform: parent_transform.multiply(transform),
+                        style,
+                    });
+                }
+                SceneNode::Group { children, transform, .. } => {
+                    let world_transform = parent_transform.multiply(transform);
+                    self.collect_leaf_views(children, root_id, instanced, world_transform, result);
+                }
+                SceneNode::Instance { symbol_id, transform, .. } => {
+                    if let Some(symbol) = self.get_symbol(symbol_id) {
+                        let world_transform = parent_transform.multiply(transform);
+                        self.collect_leaf_views(&symbol.children, root_id, true, world_transform, result);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Look up a symbol in the library
+    pub fn get_symbol(&self, symbol_id: &str) -> Option<&Symbol> {
+        self.symbols.iter().find(|symbol| symbol.id == symbol_id)
+    }
+
+    /// Move root nodes into a new library symbol and put an instance in their place
+    /// The instance takes the z-position of the lowest moved node; child transforms are kept,
+    /// so the instance starts with an identity transform and nothing moves on screen.
+    /// Returns (symbol ID, instance ID), or None if none of the IDs are root nodes.
+    pub fn create_symbol(&mut self, name: &str, ids: &[ObjectId]) -> Option<(ObjectId, ObjectId)> {
+        let insert_at = self.roots.iter().position(|node| ids.contains(node.id()))?;
+        let children = self.remove_roots(ids);
+
+        let symbol_id = self.generate_symbol_id();
+        self.symbols.push(Symbol { id: symbol_id.clone(), name: name.to_string(), children });
+
+        let instance_id = self.generate_id();
+        self.roots.insert(
+            insert_at,
+            SceneNode::Instance {
+                id: instance_id.clone(),
+                symbol_id: symbol_id.clone(),
+                transform: TransformMatrix::identity(),
+                name: None,
+            },
+        );
+        Some((symbol_id, instance_id))
+    }
+
+    /// Replace a root-level instance with editable copies of its symbol's content
+    /// Copies get fresh IDs and have the instance transform baked into their own transforms.
+    /// Returns the IDs of the new root nodes, or None if the ID is not a root instance.
+    pub fn detach_instance(&mut self, instance_id: &str) -> Option<Vec<ObjectId>> {
+        let index = self
+            .roots
+            .iter()
+            .position(|node| matches!(node, SceneNode::Instance { id, .. } if id == instance_id))?;
+        let (symbol_id, instance_transform) = match &self.roots[index] {
+            SceneNode::Instance { symbol_id, transform, .. } => (symbol_id.clone(), *transform),
+            _ => return None,
+        };
+        let children = self.get_symbol(&symbol_id)?.children.clone();
+
+        let mut copies = Vec::with_capacity(children.len());
+        for mut child in children {
+            self.assign_fresh_ids(&mut child);
+            let transform = child.transform_mut();
+            *transform = instance_transform.multiply(transform);
+            copies.push(child);
+        }
+        let new_ids = copies.iter().map(|node| node.id().clone()).collect();
+        self.roots.splice(index..=index, copies);
+        Some(new_ids)
+    }
+
+    /// Deep-copy root nodes with fresh IDs, offset by (dx, dy)
+    /// Each copy is placed directly above its original in the z-order.
+    /// Returns the IDs of the copies in z-order.
+    pub fn duplicate_roots(&mut self, ids: &[ObjectId], dx: f64, dy: f64) -> Vec<ObjectId> {
+        let offset = TransformMatrix::translate(dx, dy);
+        let mut new_ids = Vec::new();
+        let mut index = 0;
+        while index < self.roots.len() {
+            if ids.contains(self.roots[index].id()) {
+                let mut copy = self.roots[index].clone();
+                self.assign_fresh_ids(&mut copy);
+                let transform = copy.transform_mut();
+                *transform = offset.multiply(transform);
+                new_ids.push(copy.id().clone());
+                self.roots.insert(index + 1, copy);
+                index += 1; // Skip over the copy
+            }
+            index += 1;
+        }
+        new_ids
+    }
+
+    /// Replace a root node with a group holding it and transformed copies of it
+    /// Each placement is applied in world space on top of the node's own transform;
+    /// the original stays first in the group. Copies of instances stay instances.
+    /// Returns the group ID, or None if the ID is not a root node.
+    pub fn repeat_root(&mut self, source_id: &str, placements: &[TransformMatrix]) -> Option<ObjectId> {
+        let index = self.roots.iter().position(|node| node.id() == source_id)?;
+        let source = self.roots[index].clone();
+
+        let mut children = Vec::with_capacity(placements.len() + 1);
+        children.push(source.clone());
+        for placement in placements {
+            let mut copy = source.clone();
+            self.assign_fresh_ids(&mut copy);
+            let transform = copy.transform_mut();
+            *transform = placement.multiply(transform);
+            children.push(copy);
+        }
+
+        let group_id = self.generate_id();
+        self.roots[index] = SceneNode::Group {
+            id: group_id.clone(),
+            children,
+            transform: TransformMatrix::identity(),
+            name: None,
+            cache_as_bitmap: false,
+        };
+        Some(group_id)
+    }
+
+    /// Remove root nodes by ID, returning them in z-order
+    pub fn remove_roots(&mut self, ids: &[ObjectId]) -> Vec<SceneNode> {
+        let (removed, remaining): (Vec<SceneNode>, Vec<SceneNode>) =
+            std::mem::take(&mut self.roots).into_iter().partition(|node| ids.contains(node.id()));
+        self.roots = remaining;
+        removed
+    }
+
+    /// Give a copied subtree new, unique IDs
+    pub fn assign_fresh_ids(&mut self, node: &mut SceneNode) {
+        let new_id = self.generate_id();
+        match node {
+            SceneNode::Group { id, children, .. } => {
+                *id = new_id;
+                for child in children {
+                    self.assign_fresh_ids(child);
+                }
+            }
+            SceneNode::Leaf { id, .. } | SceneNode::Instance { id, .. } => *id = new_id,
+        }
+    }
+
+    /// Find IDs of all nodes whose name matches a pattern
+    /// Supports `*` (any run of characters) and `?` (any single character) wildcards;
+    /// matching is case-insensitive.
+    pub fn find_by_name(&self, pattern: &str) -> Vec<ObjectId> {
+        let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+        let mut result = Vec::new();
+        Self::collect_by_name(&self.roots, &pattern, &mut result);
+        result
+    }
+
+    fn collect_by_name(nodes: &[SceneNode], pattern: &[char], result: &mut Vec<ObjectId>) {
+        for node in nodes {
+            if let Some(name) = node.name() {
+                let name: Vec<char> = name.to_lowercase().chars().collect();
+                if wildcard_match(pattern, &name) {
+                    result.push(node.id().clone());
+                }
+            }
+            if let SceneNode::Group { children, .. } = node {
+                Self::collect_by_name(children, pattern, result);
+            }
+        }
+    }
+
+    /// Text objects of the scene and of the symbol definitions with their IDs, in document order
+    pub fn text_objects(&self) -> Vec<(&ObjectId, &TextObject)> {
+        fn collect<'a>(nodes: &'a [SceneNode], result: &mut Vec<(&'a ObjectId, &'a TextObject)>) {
+            for node in nodes {
+                match node {
+                    SceneNode::Leaf { id, object: VectorObject::Text(text), .. } => result.push((id, text)),
+                    SceneNode::Group { children, .. } => collect(children, result),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut result = Vec::new();
+        collect(&self.roots, &mut result);
+        for symbol in &self.symbols {
+            collect(&symbol.children, &mut result);
+        }
+        result
+    }
+
+    /// Get a text object by ID (mutable), searching the scene and the symbol definitions
+    pub fn text_object_mut(&mut self, target_id: &str) -> Option<&mut TextObject> {
+        let symbols = self.symbols.iter_mut().map(|symbol| symbol.children.as_mut_slice());
+        std::iter::once(self.roots.as_mut_slice()).chain(symbols).find_map(|nodes| {
+            match Self::find_node_by_id_mut(nodes, target_id)? {
+                SceneNode::Leaf { object: VectorObject::Text(text), .. } => Some(text.as_mut()),
+                _ => None,
+            }
+        })
+    }
+
     /// Get a node by ID (immutable)
     pub fn get_node_by_id(&self, target_id: &str) -> Option<&SceneNode> {
         self.find_node_by_id(&self.roots, target_id)
@@ -170,7 +1083,7 @@ impl SceneGraph {
     fn find_node_by_id<'a>(&'a self, nodes: &'a [SceneNode], target_id: &str) -> Option<&'a SceneNode> {
         for node in nodes {
             match node {
-                SceneNode::Leaf { id, .. } if id == target_id => return Some(node),
+                SceneNode::Leaf { id, .. } | SceneNode::Instance { id, .. } if id == target_id => return Some(node),
                 SceneNode::Group { id, children, .. } => {
                     if id == target_id {
                         return Some(node);
@@ -185,26 +1098,123 @@ impl SceneGraph {
         None
     }
 
-    /// Get a node by ID (mutable)
-    /// Note: For deep hierarchies, this may not find nested nodes. Use for flat scenes.
+    /// Get a node by ID together with the accumulated transform of its parent
+    pub fn get_node_with_parent_transform(&self, target_id: &str) -> Option<(&SceneNode, TransformMatrix)> {
+        Self::find_node_with_parent_transform(&self.roots, TransformMatrix::identity(), target_id)
+    }
+
+    fn find_node_with_parent_transform<'a>(
+        nodes: &'a [SceneNode],
+        parent_transform: TransformMatrix,
+        target_id: &str,
+    ) -> Option<(&'a SceneNode, TransformMatrix)> {
+        for node in nodes {
+            if node.id() == target_id {
+                return Some((node, parent_transform));
+            }
+            if let SceneNode::Group { children, transform, .. } = node {
+                let world_transform = parent_transform.multiply(transform);
+                if let Some(found) = Self::find_node_with_parent_transform(children, world_transform, target_id) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    /// Get a node by ID (mutable), searching inside groups
     pub fn get_node_by_id_mut(&mut self, target_id: &str) -> Option<&mut SceneNode> {
-        self.roots.iter_mut().find(|node| {
-            match node {
-                SceneNode::Leaf { id, .. } => id == target_id,
-                SceneNode::Group { id, .. } => id == target_id,
+        Self::find_node_by_id_mut(&mut self.roots, target_id)
+    }
+
+    fn find_node_by_id_mut<'a>(nodes: &'a mut [SceneNode], target_id: &str) -> Option<&'a mut SceneNode> {
+        for node in nodes {
+            if node.id() == target_id {
+                return Some(node);
             }
+            if let SceneNode::Group { children, .. } = node {
+                if let Some(found) = Self::find_node_by_id_mut(children, target_id) {
+                    return Some(found);
+                }
+            }
+        }
+        None
+    }
+
+    /// Express a world-space transform in the space of a node's parent
+    /// Applying the result on top of the node's own transform moves the node on the
+    /// page as `matrix` would move a top-level object. None if the node is not found or
+    /// its parent's transform is singular.
+    pub fn parent_space_matrix(&self, target_id: &str, matrix: &TransformMatrix) -> Option<TransformMatrix> {
+        let (_, parent_transform) = self.get_node_with_parent_transform(target_id)?;
+        Some(parent_transform.inverse()?.multiply(matrix).multiply(&parent_transform))
+    }
+
+    /// Move a node into a group (or to the top level if `new_parent_id` is None) at a
+    /// z-order index among its new siblings (0 = bottom, past the end = top)
+    /// The node's local transform is adjusted so it stays where it is on the page.
+    /// Returns false if either node is missing, the new parent is not a group, is the
+    /// node itself or lies inside it, or its transform is singular.
+    pub fn reparent(&mut self, target_id: &str, new_parent_id: Option<&str>, index: usize) -> bool {
+        let Some((node, old_parent_transform)) = self.get_node_with_parent_transform(target_id) else {
+            return false;
+        };
+        let new_parent_transform = match new_parent_id {
+            None => TransformMatrix::identity(),
+            Some(parent_id) => {
+                if Self::find_in(std::slice::from_ref(node), parent_id) {
+                    return false;
+                }
+                match self.get_node_with_parent_transform(parent_id) {
+                    Some((SceneNode::Group { transform, .. }, transform_above)) => transform_above.multiply(transform),
+                    _ => return false,
+                }
+            }
+        };
+        let Some(inverse) = new_parent_transform.inverse() else {
+            return false;
+        };
+        let world_transform = old_parent_transform.multiply(node.transform());
+
+        let Some(mut node) = Self::take_node(&mut self.roots, target_id) else {
+            return false;
+        };
+        *node.transform_mut() = inverse.multiply(&world_transform);
+        // The new parent was checked above and is not inside the taken node
+        let siblings = match new_parent_id {
+            None => &mut self.roots,
+            Some(parent_id) => match self.get_node_by_id_mut(parent_id) {
+                Some(SceneNode::Group { children, .. }) => children,
+                _ => return false,
+            },
+        };
+        siblings.insert(index.min(siblings.len()), node);
+        true
+    }
+
+    /// Whether a node with this ID is among the nodes or their descendants
+    fn find_in(nodes: &[SceneNode], target_id: &str) -> bool {
+        nodes.iter().any(|node| {
+            node.id() == target_id
+                || matches!(node, SceneNode::Group { children, .. } if Self::find_in(children, target_id))
+        })
+    }
+
+    /// Remove a node from wherever it is in the tree
+    fn take_node(nodes: &mut Vec<SceneNode>, target_id: &str) -> Option<SceneNode> {
+        if let Some(index) = nodes.iter().position(|node| node.id() == target_id) {
+            return Some(nodes.remove(index));
+        }
+        nodes.iter_mut().find_map(|node| match node {
+            SceneNode::Group { children, .. } => Self::take_node(children, target_id),
+            _ => None,
         })
     }
 
     /// Bring a node to the front (end of the vector = top of z-order)
     pub fn bring_to_front(&mut self, target_id: &str) -> bool {
         // Find the index of the node with the given ID
-        if let Some(index) = self.roots.iter().position(|node| {
-            match node {
-                SceneNode::Leaf { id, .. } => id == target_id,
-                SceneNode::Group { id, .. } => id == target_id,
-            }
-        }) {
+        if let Some(index) = self.roots.iter().position(|node| node.id() == target_id) {
             // Only move if not already at the end
             if index < self.roots.len() - 1 {
                 let node = self.roots.remove(index);
@@ -218,12 +1228,7 @@ impl SceneGraph {
     /// Send a node to the back (beginning of the vector = bottom of z-order)
     pub fn send_to_back(&mut self, target_id: &str) -> bool {
         // Find the index of the node with the given ID
-        if let Some(index) = self.roots.iter().position(|node| {
-            match node {
-                SceneNode::Leaf { id, .. } => id == target_id,
-                SceneNode::Group { id, .. } => id == target_id,
-            }
-        }) {
+        if let Some(index) = self.roots.iter().position(|node| node.id() == target_id) {
             // Only move if not already at the beginning
             if index > 0 {
                 let node = self.roots.remove(index);
@@ -235,6 +1240,30 @@ impl SceneGraph {
     }
 }
 
+/// Glob-style match supporting `*` and `?`
+fn wildcard_match(pattern: &[char], text: &[char]) -> bool {
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            backtrack = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = backtrack {
+            // Let the last `*` absorb one more character
+            p = star_p + 1;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 impl Default for SceneGraph {
     fn default() -> Self {
         Self::new()
@@ -276,4 +1305,194 @@ mod tests {
         let leaves = scene.iter_leaves();
         assert_eq!(leaves.len(), 2);
     }
+
+    #[test]
+    fn test_find_by_name() {
+        let mut scene = SceneGraph::new();
+        for name in ["Logo Mark", "logo text", "Background"] {
+            let id = scene.generate_id();
+            scene.add_object(
+                id.clone(),
+                VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 },
+                TransformMatrix::identity(),
+            );
+            scene.get_node_by_id_mut(&id).unwrap().set_name(Some(name.to_string()));
+        }
+
+        assert_eq!(scene.find_by_name("logo*"), vec!["obj_1", "obj_2"]);
+        assert_eq!(scene.find_by_name("background"), vec!["obj_3"]);
+        assert_eq!(scene.find_by_name("?ogo ?ext"), vec!["obj_2"]);
+        assert!(scene.find_by_name("logo").is_empty());
+    }
+
+    #[test]
+    fn test_name_round_trips_through_json() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id.clone(),
+            VectorObject::Ellipse { cx: 0.0, cy: 0.0, rx: 5.0, ry: 5.0 },
+            TransformMatrix::identity(),
+        );
+        scene.get_node_by_id_mut(&id).unwrap().set_name(Some("Dot".to_string()));
+
+        let json = serde_json::to_string(&scene).unwrap();
+        let restored: SceneGraph = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.get_node_by_id(&id).unwrap().name(), Some("Dot"));
+    }
+
+    fn scene_with_rects(count: usize) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        for i in 0..count {
+            let id = scene.generate_id();
+            scene.add_object(
+                id,
+                VectorObject::Rectangle { x: i as f64 * 20.0, y: 0.0, width: 10.0, height: 10.0 },
+                TransformMatrix::identity(),
+            );
+        }
+        scene
+    }
+
+    #[test]
+    fn test_create_symbol_replaces_selection_in_place() {
+        let mut scene = scene_with_rects(3);
+        let (symbol_id, instance_id) = scene
+            .create_symbol("Tile", &["obj_2".to_string(), "obj_3".to_string()])
+            .unwrap();
+
+        let root_ids: Vec<&str> = scene.roots.iter().map(|node| node.id().as_str()).collect();
+        assert_eq!(root_ids, vec!["obj_1", instance_id.as_str()]);
+        assert_eq!(scene.get_symbol(&symbol_id).unwrap().children.len(), 2);
+
+        // Instanced leaves are still drawn, attributed to the instance
+        let views = scene.iter_leaf_views();
+        assert_eq!(views.len(), 3);
+        assert!(views[1].instanced);
+        assert_eq!(views[2].root_id, &instance_id);
+    }
+
+    #[test]
+    fn test_detach_instance_bakes_transform_and_renumbers() {
+        let mut scene = scene_with_rects(1);
+        let (_, instance_id) = scene.create_symbol("Tile", &["obj_1".to_string()]).unwrap();
+        *scene.roots[0].transform_mut() = TransformMatrix::translate(100.0, 0.0);
+
+        let new_ids = scene.detach_instance(&instance_id).unwrap();
+        assert_eq!(new_ids.len(), 1);
+        assert_ne!(new_ids[0], "obj_1");
+        let node = scene.get_node_by_id(&new_ids[0]).unwrap();
+        assert!(matches!(node, SceneNode::Leaf { .. }));
+        assert!((node.transform().tx - 100.0).abs() < 1e-9);
+        // The symbol stays in the library
+        assert_eq!(scene.symbols.len(), 1);
+    }
+
+    #[test]
+    fn test_repeat_root_groups_original_and_copies() {
+        let mut scene = scene_with_rects(2);
+        let placements = [TransformMatrix::translate(20.0, 0.0), TransformMatrix::translate(40.0, 0.0)];
+        let group_id = scene.repeat_root("obj_1", &placements).unwrap();
+
+        assert_eq!(scene.roots.len(), 2);
+        assert_eq!(scene.roots[0].id(), &group_id);
+        let SceneNode::Group { children, .. } = &scene.roots[0] else { panic!("expected a group") };
+        assert_eq!(children.len(), 3);
+        assert_eq!(children[0].id(), "obj_1");
+        assert!((children[2].transform().tx - 40.0).abs() < 1e-9);
+        assert!(scene.repeat_root("missing", &placements).is_none());
+    }
+
+    #[test]
+    fn test_duplicate_roots_places_copies_above_originals() {
+        let mut scene = scene_with_rects(3);
+        let copies = scene.duplicate_roots(&["obj_1".to_string(), "obj_3".to_string()], 5.0, 5.0);
+
+        let root_ids: Vec<&str> = scene.roots.iter().map(|node| node.id().as_str()).collect();
+        assert_eq!(root_ids, vec!["obj_1", "obj_4", "obj_2", "obj_3", "obj_5"]);
+        assert_eq!(copies, vec!["obj_4", "obj_5"]);
+        assert!((scene.roots[1].transform().tx - 5.0).abs() < 1e-9);
+        assert!(scene.roots[0].transform().tx.abs() < 1e-9);
+    }
+
+    fn world_transform(scene: &SceneGraph, id: &str) -> TransformMatrix {
+        let (node, parent_transform) = scene.get_node_with_parent_transform(id).unwrap();
+        parent_transform.multiply(node.transform())
+    }
+
+    fn assert_same_transform(a: &TransformMatrix, b: &TransformMatrix) {
+        let (a, b) = ([a.a, a.b, a.c, a.d, a.tx, a.ty], [b.a, b.b, b.c, b.d, b.tx, b.ty]);
+        assert!(a.iter().zip(&b).all(|(x, y)| (x - y).abs() < 1e-9), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn test_mutable_lookup_finds_nested_nodes() {
+        let mut scene = scene_with_rects(2);
+        let children = scene.remove_roots(&["obj_2".to_string()]);
+        scene.roots.push(SceneNode::Group {
+            id: "g".to_string(),
+            children,
+            transform: TransformMatrix::identity(),
+            name: None,
+            cache_as_bitmap: false,
+        });
+
+        scene.get_node_by_id_mut("obj_2").unwrap().set_name(Some("inner".to_string()));
+        assert_eq!(scene.find_by_name("inner"), vec!["obj_2"]);
+        assert!(scene.get_node_by_id_mut("missing").is_none());
+    }
+
+    #[test]
+    fn test_reparent_keeps_world_position() {
+        let mut scene = scene_with_rects(3);
+        let children = scene.remove_roots(&["obj_3".to_string()]);
+        let group_transform = TransformMatrix::translate(100.0, 0.0).multiply(&TransformMatrix::rotate(0.5));
+        scene.roots.push(SceneNode::Group {
+            id: "g".to_string(),
+            children,
+            transform: group_transform,
+            name: None,
+            cache_as_bitmap: false,
+        });
+        *scene.roots[0].transform_mut() = TransformMatrix::translate(7.0, 3.0);
+        let before = world_transform(&scene, "obj_1");
+
+        // Into the rotated group, below its existing child
+        assert!(scene.reparent("obj_1", Some("g"), 0));
+        let SceneNode::Group { children, .. } = &scene.roots[1] else { panic!("expected a group") };
+        let child_ids: Vec<&str> = children.iter().map(|node| node.id().as_str()).collect();
+        assert_eq!(child_ids, vec!["obj_1", "obj_3"]);
+        assert_same_transform(&world_transform(&scene, "obj_1"), &before);
+
+        // And back out, on top
+        assert!(scene.reparent("obj_1", None, usize::MAX));
+        assert_eq!(scene.roots.last().map(|node| node.id().as_str()), Some("obj_1"));
+        assert_same_transform(scene.roots[2].transform(), &before);
+
+        // A group cannot move into itself or a leaf
+        assert!(!scene.reparent("g", Some("obj_3"), 0));
+        assert!(!scene.reparent("g", Some("g"), 0));
+        assert!(!scene.reparent("obj_2", Some("obj_1"), 0));
+        assert!(!scene.reparent("missing", None, 0));
+        assert_eq!(scene.roots.len(), 3);
+    }
+
+    #[test]
+    fn test_parent_space_matrix_moves_nested_nodes_on_the_page() {
+        let mut scene = scene_with_rects(1);
+        let children = scene.remove_roots(&["obj_1".to_string()]);
+        scene.roots.push(SceneNode::Group {
+            id: "g".to_string(),
+            children,
+            transform: TransformMatrix::scale(2.0, 2.0),
+            name: None,
+            cache_as_bitmap: false,
+        });
+        let before = world_transform(&scene, "obj_1");
+        let shift = TransformMatrix::translate(10.0, 0.0);
+        let local = scene.parent_space_matrix("obj_1", &shift).unwrap();
+        let transform = scene.get_node_by_id_mut("obj_1").unwrap().transform_mut();
+        *transform = local.multiply(transform);
+        assert_same_transform(&world_transform(&scene, "obj_1"), &shift.multiply(&before));
+    }
 }
@@ -0,0 +1,118 @@
+//! Document Module - Page properties saved with the document
+//!
+//! Scene coordinates are CSS pixels (1/96 inch) whatever the unit system; the
+//! units only change how lengths are entered and shown. The canvas size and
+//! background define the exported page, and the DPI sets the resolution of
+//! raster exports.
+
+use serde::{Deserialize, Serialize};
+
+/// Document units (CSS pixels) per inch
+pub const UNITS_PER_INCH: f64 = 96.0;
+
+/// Unit system for lengths shown to the user
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Units {
+    #[default]
+    Px,
+    Mm,
+    In,
+}
+
+impl Units {
+    /// Parse a unit name: "px" | "mm" | "in"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "px" => Some(Units::Px),
+            "mm" => Some(Units::Mm),
+            "in" => Some(Units::In),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Units::Px => "px",
+            Units::Mm => "mm",
+            Units::In => "in",
+        }
+    }
+
+    /// Document units in one of these units
+    pub fn scale(self) -> f64 {
+        match self {
+            Units::Px => 1.0,
+            Units::Mm => UNITS_PER_INCH / 25.4,
+            Units::In => UNITS_PER_INCH,
+        }
+    }
+
+    /// Convert a length in these units to document units
+    pub fn to_document(self, value: f64) -> f64 {
+        value * self.scale()
+    }
+
+    /// Convert a length in document units to these units
+    pub fn from_document(self, value: f64) -> f64 {
+        value / self.scale()
+    }
+}
+
+/// Page properties of a document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DocumentSettings {
+    /// Canvas width in document units
+    pub width: f64,
+    /// Canvas height in document units
+    pub height: f64,
+    /// Page background color ("#rrggbb"); None for a transparent page
+    pub background: Option<String>,
+    /// Unit system for lengths shown to the user
+    pub units: Units,
+    /// Resolution of raster exports, in pixels per inch
+    pub dpi: f64,
+}
+
+impl Default for DocumentSettings {
+    fn default() -> Self {
+        DocumentSettings {
+            width: 800.0,
+            height: 600.0,
+            background: Some("#1e1e1e".to_string()),
+            units: Units::Px,
+            dpi: UNITS_PER_INCH,
+        }
+    }
+}
+
+impl DocumentSettings {
+    /// Bitmap pixels per document unit for raster exports
+    pub fn raster_scale(&self) -> f64 {
+        self.dpi / UNITS_PER_INCH
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unit_conversions() {
+        assert_eq!(Units::In.to_document(2.0), 192.0);
+        assert!((Units::Mm.to_document(25.4) - 96.0).abs() < 1e-9);
+        assert!((Units::Mm.from_document(Units::Mm.to_document(210.0)) - 210.0).abs() < 1e-9);
+        assert_eq!(Units::Px.from_document(12.5), 12.5);
+        assert_eq!(Units::from_name(Units::Mm.name()), Some(Units::Mm));
+        assert_eq!(Units::from_name("pt"), None);
+    }
+
+    #[test]
+    fn test_raster_scale_follows_dpi() {
+        let mut settings = DocumentSettings::default();
+        assert_eq!(settings.raster_scale(), 1.0);
+        settings.dpi = 300.0;
+        assert_eq!(settings.raster_scale(), 3.125);
+    }
+}
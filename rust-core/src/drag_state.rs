@@ -5,8 +5,10 @@
 //! from the baseline, preventing cumulative floating-point errors.
 
 use std::collections::HashMap;
+use std::f64::consts::PI;
 use serde::{Deserialize, Serialize};
 use crate::core::math::TransformMatrix;
+use crate::spatial::BoundingBox;
 
 /// Handle index for resize operations (corners and edges)
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -15,35 +17,79 @@ pub enum HandleIndex {
     TopRight = 1,
     BottomRight = 2,
     BottomLeft = 3,
+    Top = 4,
+    Right = 5,
+    Bottom = 6,
+    Left = 7,
 }
 
 impl HandleIndex {
-    /// Get the opposite corner (for calculating pivot during resize)
+    /// All handles in index order (corners first, then edge midpoints)
+    pub const ALL: [HandleIndex; 8] = [
+        HandleIndex::TopLeft,
+        HandleIndex::TopRight,
+        HandleIndex::BottomRight,
+        HandleIndex::BottomLeft,
+        HandleIndex::Top,
+        HandleIndex::Right,
+        HandleIndex::Bottom,
+        HandleIndex::Left,
+    ];
+
+    /// Convert a numeric handle index (as used by the frontend)
+    pub fn from_index(index: u8) -> Option<Self> {
+        Self::ALL.get(index as usize).copied()
+    }
+
+    /// Get the opposite handle (for calculating pivot during resize)
     pub fn opposite(&self) -> Self {
         match self {
             HandleIndex::TopLeft => HandleIndex::BottomRight,
             HandleIndex::TopRight => HandleIndex::BottomLeft,
             HandleIndex::BottomRight => HandleIndex::TopLeft,
             HandleIndex::BottomLeft => HandleIndex::TopRight,
+            HandleIndex::Top => HandleIndex::Bottom,
+            HandleIndex::Right => HandleIndex::Left,
+            HandleIndex::Bottom => HandleIndex::Top,
+            HandleIndex::Left => HandleIndex::Right,
+        }
+    }
+
+    /// Position of this handle on a box given as [top-left, top-right, bottom-right, bottom-left]
+    pub fn position(&self, corners: &[(f64, f64); 4]) -> (f64, f64) {
+        let mid = |a: (f64, f64), b: (f64, f64)| ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        match self {
+            HandleIndex::TopLeft => corners[0],
+            HandleIndex::TopRight => corners[1],
+            HandleIndex::BottomRight => corners[2],
+            HandleIndex::BottomLeft => corners[3],
+            HandleIndex::Top => mid(corners[0], corners[1]),
+            HandleIndex::Right => mid(corners[1], corners[2]),
+            HandleIndex::Bottom => mid(corners[2], corners[3]),
+            HandleIndex::Left => mid(corners[3], corners[0]),
         }
     }
+
+    /// Edge handles scale along a single axis only
+    pub fn scales_x(&self) -> bool {
+        !matches!(self, HandleIndex::Top | HandleIndex::Bottom)
+    }
+
+    pub fn scales_y(&self) -> bool {
+        !matches!(self, HandleIndex::Left | HandleIndex::Right)
+    }
 }
 
 /// Drag operation mode
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub enum DragMode {
+    #[default]
     None,
     Moving,
     Resizing(HandleIndex),
     Rotating,
 }
 
-impl Default for DragMode {
-    fn default() -> Self {
-        DragMode::None
-    }
-}
-
 /// Drag state tracking
 #[derive(Debug, Clone, Default)]
 pub struct DragState {
@@ -53,8 +99,23 @@ pub struct DragState {
     pub start_point: (f64, f64),
     /// Initial transforms of selected objects at drag start
     pub initial_transforms: HashMap<String, TransformMatrix>,
-    /// Pivot point for resize/rotate operations (opposite corner or center)
+    /// Pivot point for resize/rotate operations (opposite corner or center);
+    /// for moves, the selection's top-left corner (the point that snaps to the grid)
     pub pivot: (f64, f64),
+    /// Edge vectors of the selection box at drag start: (top-left → top-right, top-left → bottom-left)
+    /// Resizing scales along these axes so rotated objects are not sheared
+    pub axes: ((f64, f64), (f64, f64)),
+    /// Center of the selection box at drag start (pivot when resizing from center)
+    pub center: (f64, f64),
+    /// Rotation applied so far by a rotate drag, in radians (clockwise on screen is positive)
+    pub angle: f64,
+    /// World bounds of the selection at drag start (for smart guides while moving)
+    pub start_bounds: Option<BoundingBox>,
+    /// Distance the mouse must travel from the start point before a move takes effect
+    /// (so a click does not nudge the selection); 0 moves immediately
+    pub threshold: f64,
+    /// Whether the mouse has left the threshold (it then moves freely, even back to the start)
+    pub passed_threshold: bool,
 }
 
 impl DragState {
@@ -81,6 +142,9 @@ impl DragState {
         self.start_point = (start_x, start_y);
         self.initial_transforms = transforms;
         self.pivot = pivot;
+        self.angle = 0.0;
+        self.threshold = 0.0;
+        self.passed_threshold = false;
     }
 
     /// End the current drag operation
@@ -89,6 +153,21 @@ impl DragState {
         self.start_point = (0.0, 0.0);
         self.initial_transforms.clear();
         self.pivot = (0.0, 0.0);
+        self.axes = ((0.0, 0.0), (0.0, 0.0));
+        self.center = (0.0, 0.0);
+        self.angle = 0.0;
+        self.start_bounds = None;
+        self.threshold = 0.0;
+        self.passed_threshold = false;
+    }
+
+    /// Whether the mouse at `current` has travelled past the drag threshold
+    pub fn past_threshold(&mut self, current_x: f64, current_y: f64) -> bool {
+        if !self.passed_threshold {
+            let (dx, dy) = self.delta(current_x, current_y);
+            self.passed_threshold = dx.hypot(dy) >= self.threshold;
+        }
+        self.passed_threshold
     }
 
     /// Calculate delta from start point
@@ -102,6 +181,120 @@ impl DragState {
     }
 }
 
+/// Constrain a move delta to the closest of the horizontal, vertical and 45° directions
+/// Always computed from the drag baseline, so the locked axis can change mid-drag.
+pub fn constrain_delta(dx: f64, dy: f64) -> (f64, f64) {
+    // Octant of the delta direction: 0/4 = horizontal, 2/6 = vertical, odd = diagonal
+    let octant = (dy.atan2(dx) / (PI / 4.0)).round() as i32;
+    match octant.rem_euclid(4) {
+        0 => (dx, 0.0),
+        2 => (0.0, dy),
+        _ => {
+            // Project onto the diagonal
+            let distance = (dx.abs() + dy.abs()) / 2.0;
+            (distance * dx.signum(), distance * dy.signum())
+        }
+    }
+}
+
+/// Angle swept by the mouse around `pivot` from `start` to `current`, in radians
+/// Positive is clockwise on screen (the y axis points down); normalized to (-π, π]
+pub fn sweep_angle(pivot: (f64, f64), start: (f64, f64), current: (f64, f64)) -> f64 {
+    let start_angle = (start.1 - pivot.1).atan2(start.0 - pivot.0);
+    let current_angle = (current.1 - pivot.1).atan2(current.0 - pivot.0);
+    let mut angle = current_angle - start_angle;
+    if angle <= -PI {
+        angle += 2.0 * PI;
+    } else if angle > PI {
+        angle -= 2.0 * PI;
+    }
+    angle
+}
+
+/// Quantize an angle (radians) to the nearest multiple of `snap_degrees`
+/// A non-positive step leaves the angle unchanged.
+pub fn snap_angle(angle: f64, snap_degrees: f64) -> f64 {
+    if snap_degrees <= 0.0 || !snap_degrees.is_finite() {
+        return angle;
+    }
+    let step = snap_degrees.to_radians();
+    (angle / step).round() * step
+}
+
+/// Compute the scale matrix for a resize drag
+/// Scaling happens in the selection box's own frame (given by `axes`) around `pivot`:
+/// corner handles scale both axes independently, edge handles scale a single axis.
+/// With `keep_aspect` both axes get the same factor (the mouse position projected onto
+/// the handle's diagonal for corners, the dragged axis for edges).
+/// With `allow_flip`, dragging a handle past the pivot mirrors the selection; otherwise
+/// the factor stops at the minimum scale.
+pub fn resize_matrix(
+    handle: HandleIndex,
+    pivot: (f64, f64),
+    axes: ((f64, f64), (f64, f64)),
+    start: (f64, f64),
+    current: (f64, f64),
+    keep_aspect: bool,
+    allow_flip: bool,
+) -> TransformMatrix {
+    let basis = box_basis(axes);
+    let basis_inv = basis.inverse().unwrap_or_else(TransformMatrix::identity);
+
+    // Handle offsets from the pivot, expressed in box coordinates
+    let (start_u, start_v) = basis_inv.transform_point(start.0 - pivot.0, start.1 - pivot.1);
+    let (current_u, current_v) = basis_inv.transform_point(current.0 - pivot.0, current.1 - pivot.1);
+
+    let ratio = |current: f64, start: f64| {
+        if start.abs() < 1e-9 {
+            1.0
+        } else {
+            clamp_scale(current / start, allow_flip)
+        }
+    };
+    let (sx, sy) = if keep_aspect {
+        let uniform = match (handle.scales_x(), handle.scales_y()) {
+            (true, false) => ratio(current_u, start_u),
+            (false, true) => ratio(current_v, start_v),
+            _ => ratio(
+                current_u * start_u + current_v * start_v,
+                start_u * start_u + start_v * start_v,
+            ),
+        };
+        (uniform, uniform)
+    } else {
+        let sx = if handle.scales_x() { ratio(current_u, start_u) } else { 1.0 };
+        let sy = if handle.scales_y() { ratio(current_v, start_v) } else { 1.0 };
+        (sx, sy)
+    };
+
+    // Translate(pivot) × Basis × Scale × Basis⁻¹ × Translate(-pivot)
+    let to_origin = TransformMatrix::translate(-pivot.0, -pivot.1);
+    let from_origin = TransformMatrix::translate(pivot.0, pivot.1);
+    let scale = TransformMatrix::scale(sx, sy);
+    from_origin.multiply(&basis.multiply(&scale.multiply(&basis_inv.multiply(&to_origin))))
+}
+
+/// Clamp a scale factor's magnitude to a reasonable range (0.1..10)
+/// Negative factors (a flip) keep their sign when flipping is allowed.
+fn clamp_scale(ratio: f64, allow_flip: bool) -> f64 {
+    if allow_flip && ratio < 0.0 {
+        ratio.clamp(-10.0, -0.1)
+    } else {
+        ratio.clamp(0.1, 10.0)
+    }
+}
+
+/// Matrix mapping unit box axes to the normalized edge directions of the selection box
+fn box_basis(axes: ((f64, f64), (f64, f64))) -> TransformMatrix {
+    let normalize = |v: (f64, f64), fallback: (f64, f64)| {
+        let len = (v.0 * v.0 + v.1 * v.1).sqrt();
+        if len < 1e-9 { fallback } else { (v.0 / len, v.1 / len) }
+    };
+    let u = normalize(axes.0, (1.0, 0.0));
+    let v = normalize(axes.1, (0.0, 1.0));
+    TransformMatrix { a: u.0, b: v.0, c: u.1, d: v.1, tx: 0.0, ty: 0.0 }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,9 +319,130 @@ mod tests {
         assert!(!state.is_active());
     }
 
+    #[test]
+    fn test_drag_threshold_latches() {
+        let mut state = DragState::new();
+        state.begin(DragMode::Moving, 100.0, 100.0, HashMap::new(), (0.0, 0.0));
+        assert!(state.past_threshold(100.0, 100.0));
+
+        state.begin(DragMode::Moving, 100.0, 100.0, HashMap::new(), (0.0, 0.0));
+        state.threshold = 3.0;
+        assert!(!state.past_threshold(102.0, 102.0));
+        assert!(state.past_threshold(103.0, 100.0));
+        // Once past, returning to the start still moves
+        assert!(state.past_threshold(100.0, 100.0));
+    }
+
+    #[test]
+    fn test_constrain_delta_picks_dominant_axis() {
+        assert_eq!(constrain_delta(30.0, 5.0), (30.0, 0.0));
+        assert_eq!(constrain_delta(-30.0, 5.0), (-30.0, 0.0));
+        assert_eq!(constrain_delta(4.0, -50.0), (0.0, -50.0));
+        assert_eq!(constrain_delta(20.0, 18.0), (19.0, 19.0));
+        assert_eq!(constrain_delta(-20.0, 18.0), (-19.0, 19.0));
+    }
+
+    #[test]
+    fn test_sweep_angle_wraps_and_snaps() {
+        // From just above the negative x axis to just below it: a small clockwise sweep
+        let angle = sweep_angle((0.0, 0.0), (-10.0, -0.1), (-10.0, 0.1));
+        assert!(angle < 0.0 && angle > -0.1);
+
+        // Quarter turn clockwise on screen (from +x towards +y, y pointing down)
+        let angle = sweep_angle((0.0, 0.0), (10.0, 0.0), (0.0, 10.0));
+        assert!((angle - PI / 2.0).abs() < 1e-9);
+
+        assert!((snap_angle(50f64.to_radians(), 15.0) - 45f64.to_radians()).abs() < 1e-9);
+        assert!((snap_angle(-23f64.to_radians(), 45.0) - (-45f64).to_radians()).abs() < 1e-9);
+        assert_eq!(snap_angle(0.3, 0.0), 0.3);
+    }
+
     #[test]
     fn test_handle_opposite() {
         assert_eq!(HandleIndex::TopLeft.opposite(), HandleIndex::BottomRight);
         assert_eq!(HandleIndex::BottomRight.opposite(), HandleIndex::TopLeft);
+        assert_eq!(HandleIndex::Top.opposite(), HandleIndex::Bottom);
+        assert_eq!(HandleIndex::from_index(7), Some(HandleIndex::Left));
+        assert_eq!(HandleIndex::from_index(8), None);
+    }
+
+    const AXIS_ALIGNED: ((f64, f64), (f64, f64)) = ((100.0, 0.0), (0.0, 50.0));
+
+    #[test]
+    fn test_corner_resize_is_non_uniform() {
+        // Box (0,0)-(100,50), drag bottom-right corner from (100,50) to (200,75)
+        let m = resize_matrix(HandleIndex::BottomRight, (0.0, 0.0), AXIS_ALIGNED, (100.0, 50.0), (200.0, 75.0), false, true);
+        let (x, y) = m.transform_point(100.0, 50.0);
+        assert!((x - 200.0).abs() < 1e-9);
+        assert!((y - 75.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_edge_resize_scales_one_axis() {
+        // Drag right edge midpoint; vertical mouse movement must be ignored
+        let m = resize_matrix(HandleIndex::Right, (0.0, 25.0), AXIS_ALIGNED, (100.0, 25.0), (150.0, 80.0), false, true);
+        assert!((m.a - 1.5).abs() < 1e-9);
+        assert!((m.d - 1.0).abs() < 1e-9);
+        let (_, y) = m.transform_point(100.0, 0.0);
+        assert!(y.abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_keep_aspect_uses_single_factor() {
+        // Mouse moved mostly horizontally; the box still keeps its 2:1 ratio
+        let m = resize_matrix(HandleIndex::BottomRight, (0.0, 0.0), AXIS_ALIGNED, (100.0, 50.0), (200.0, 50.0), true, true);
+        assert!((m.a - m.d).abs() < 1e-9);
+        assert!((m.a - 1.8).abs() < 1e-9); // (200*100 + 50*50) / (100² + 50²)
+
+        // Edge handle with the lock scales the other axis along
+        let m = resize_matrix(HandleIndex::Right, (0.0, 25.0), AXIS_ALIGNED, (100.0, 25.0), (150.0, 25.0), true, true);
+        assert!((m.a - 1.5).abs() < 1e-9);
+        assert!((m.d - 1.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_resize_past_pivot_flips() {
+        // Drag the right edge of (0,0)-(100,50) to x = -50: mirrored, half as wide
+        let m = resize_matrix(HandleIndex::Right, (0.0, 25.0), AXIS_ALIGNED, (100.0, 25.0), (-50.0, 25.0), false, true);
+        assert!((m.a + 0.5).abs() < 1e-9);
+        assert!((m.d - 1.0).abs() < 1e-9);
+        let (x, _) = m.transform_point(100.0, 25.0);
+        assert!((x + 50.0).abs() < 1e-9);
+
+        // Without flipping the factor stops at the minimum scale
+        let m = resize_matrix(HandleIndex::Right, (0.0, 25.0), AXIS_ALIGNED, (100.0, 25.0), (-50.0, 25.0), false, false);
+        assert!((m.a - 0.1).abs() < 1e-9);
+
+        // Corner with the aspect lock flips both axes together
+        let m = resize_matrix(HandleIndex::BottomRight, (0.0, 0.0), AXIS_ALIGNED, (100.0, 50.0), (-100.0, -50.0), true, true);
+        assert!((m.a + 1.0).abs() < 1e-9);
+        assert!((m.d + 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_resize_from_center_is_symmetric() {
+        // Box (0,0)-(100,50) with pivot at its center; dragging the corner
+        // 50 right and 25 down doubles the box in both directions.
+        let m = resize_matrix(HandleIndex::BottomRight, (50.0, 25.0), AXIS_ALIGNED, (100.0, 50.0), (150.0, 75.0), false, true);
+        let (x, y) = m.transform_point(0.0, 0.0);
+        assert!((x + 50.0).abs() < 1e-9);
+        assert!((y + 25.0).abs() < 1e-9);
+        let (x, y) = m.transform_point(50.0, 25.0);
+        assert!((x - 50.0).abs() < 1e-9);
+        assert!((y - 25.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_resize_in_rotated_frame() {
+        // Box rotated 90°: its width axis points down the screen
+        let axes = ((0.0, 100.0), (-50.0, 0.0));
+        let m = resize_matrix(HandleIndex::Right, (0.0, 0.0), axes, (0.0, 100.0), (0.0, 200.0), false, true);
+        // Stretching along the box's width axis doubles screen-space height only
+        let (x, y) = m.transform_point(0.0, 100.0);
+        assert!(x.abs() < 1e-9);
+        assert!((y - 200.0).abs() < 1e-9);
+        let (x, y) = m.transform_point(-50.0, 0.0);
+        assert!((x + 50.0).abs() < 1e-9);
+        assert!(y.abs() < 1e-9);
     }
 }
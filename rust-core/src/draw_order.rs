@@ -0,0 +1,397 @@
+//! Draw Order Module - Pen-travel optimization for plotter / G-code export
+//!
+//! Computes a stroke order that minimizes the distance the pen travels
+//! while lifted (nearest-neighbor construction followed by 2-opt refinement).
+//! The result is an ordering that is exported separately; the scene's
+//! z-order is never modified.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{PathCommand, SceneNode, VectorObject};
+
+/// Maximum number of full 2-opt passes (each pass is O(n²))
+const MAX_TWO_OPT_PASSES: usize = 50;
+
+/// A drawable unit with its world-space start and end points
+#[derive(Debug, Clone)]
+pub struct Stroke {
+    pub id: String,
+    pub start: (f64, f64),
+    pub end: (f64, f64),
+    /// Whether the unit may be drawn backwards (false for groups)
+    pub reversible: bool,
+}
+
+/// One step of the exported plot order
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PlotStep {
+    pub id: String,
+    /// Draw the path from its end point back to its start point
+    pub reversed: bool,
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let dx = b.0 - a.0;
+    let dy = b.1 - a.1;
+    (dx * dx + dy * dy).sqrt()
+}
+
+fn entry_point(stroke: &Stroke, reversed: bool) -> (f64, f64) {
+    if reversed { stroke.end } else { stroke.start }
+}
+
+fn exit_point(stroke: &Stroke, reversed: bool) -> (f64, f64) {
+    if reversed { stroke.start } else { stroke.end }
+}
+
+/// Total pen-up travel for an ordering, starting from `origin`
+pub fn travel_distance(strokes: &[Stroke], order: &[(usize, bool)], origin: (f64, f64)) -> f64 {
+    let mut position = origin;
+    let mut total = 0.0;
+    for &(index, reversed) in order {
+        let stroke = &strokes[index];
+        total += distance(position, entry_point(stroke, reversed));
+        position = exit_point(stroke, reversed);
+    }
+    total
+}
+
+/// Compute an optimized ordering as (stroke index, reversed) pairs
+pub fn optimize_order(strokes: &[Stroke], origin: (f64, f64)) -> Vec<(usize, bool)> {
+    let mut order = nearest_neighbor(strokes, origin);
+    two_opt(strokes, &mut order, origin);
+    order
+}
+
+/// Greedy construction: always travel to the closest unvisited endpoint
+fn nearest_neighbor(strokes: &[Stroke], origin: (f64, f64)) -> Vec<(usize, bool)> {
+    let mut visited = vec![false; strokes.len()];
+    let mut order = Vec::with_capacity(strokes.len());
+    let mut position = origin;
+
+    for _ in 0..strokes.len() {
+        let mut best: Option<(usize, bool, f64)> = None;
+        for (index, stroke) in strokes.iter().enumerate() {
+            if visited[index] {
+                continue;
+            }
+            let forward = distance(position, stroke.start);
+            if best.is_none_or(|(_, _, d)| forward < d) {
+                best = Some((index, false, forward));
+            }
+            if stroke.reversible {
+                let backward = distance(position, stroke.end);
+                if best.is_none_or(|(_, _, d)| backward < d) {
+                    best = Some((index, true, backward));
+                }
+            }
+        }
+        if let Some((index, reversed, _)) = best {
+            visited[index] = true;
+            order.push((index, reversed));
+            position = exit_point(&strokes[index], reversed);
+        }
+    }
+
+    order
+}
+
+/// 2-opt refinement: reverse sub-sequences while that shortens travel.
+/// Reversing a sub-sequence also flips the drawing direction of each stroke in it,
+/// so segments containing non-reversible strokes are skipped.
+fn two_opt(strokes: &[Stroke], order: &mut [(usize, bool)], origin: (f64, f64)) {
+    let n = order.len();
+    if n < 2 {
+        return;
+    }
+
+    for _ in 0..MAX_TWO_OPT_PASSES {
+        let mut improved = false;
+        for i in 0..n - 1 {
+            let prev_exit = if i == 0 {
+                origin
+            } else {
+                let (index, reversed) = order[i - 1];
+                exit_point(&strokes[index], reversed)
+            };
+
+            for j in i + 1..n {
+                if !order[i..=j].iter().all(|&(index, _)| strokes[index].reversible) {
+                    break;
+                }
+
+                let (first, first_rev) = order[i];
+                let (last, last_rev) = order[j];
+                let first_entry = entry_point(&strokes[first], first_rev);
+                let last_exit = exit_point(&strokes[last], last_rev);
+
+                let mut before = distance(prev_exit, first_entry);
+                let mut after = distance(prev_exit, last_exit);
+                if j + 1 < n {
+                    let (next, next_rev) = order[j + 1];
+                    let next_entry = entry_point(&strokes[next], next_rev);
+                    before += distance(last_exit, next_entry);
+                    after += distance(first_entry, next_entry);
+                }
+
+                if after + 1e-9 < before {
+                    order[i..=j].reverse();
+                    for step in order[i..=j].iter_mut() {
+                        step.1 = !step.1;
+                    }
+                    improved = true;
+                }
+            }
+        }
+        if !improved {
+            break;
+        }
+    }
+}
+
+/// First and last drawn point of an object in world space
+fn object_endpoints(object: &VectorObject, transform: &TransformMatrix) -> Option<((f64, f64), (f64, f64))> {
+    let (start, end) = match object {
+        VectorObject::Rectangle { x, y, .. } => ((*x, *y), (*x, *y)),
+        VectorObject::Ellipse { cx, cy, rx, .. } => ((cx + rx, *cy), (cx + rx, *cy)),
+        VectorObject::Circle { cx, cy, r } => ((cx + r, *cy), (cx + r, *cy)),
+        VectorObject::Path { commands, is_closed, .. } => {
+            let mut first = None;
+            let mut last = None;
+            for cmd in commands {
+                match cmd {
+                    PathCommand::MoveTo { x, y }
+                    | PathCommand::LineTo { x, y }
+                    | PathCommand::CurveTo { x, y, .. }
+                    | PathCommand::QuadTo { x, y, .. }
+                    | PathCommand::ArcTo { x, y, .. } => {
+                        first.get_or_insert((*x, *y));
+                        last = Some((*x, *y));
+                    }
+                    PathCommand::ClosePath => {}
+                }
+            }
+            let first = first?;
+            let last = if *is_closed { first } else { last? };
+            (first, last)
+        }
+        VectorObject::Text(_) => return object_endpoints(&object.drawable(), transform),
+    };
+    Some((
+        transform.transform_point(start.0, start.1),
+        transform.transform_point(end.0, end.1),
+    ))
+}
+
+/// Optimize each sibling list in the scene graph and flatten the result into plot steps.
+/// Groups are kept contiguous and are plotted as a single, non-reversible unit; the
+/// order inside a group starts from where the pen is when the group is reached.
+pub fn plot_order(nodes: &[SceneNode], parent_transform: TransformMatrix, origin: (f64, f64)) -> Vec<PlotStep> {
+    let mut pen = origin;
+    plot_siblings(nodes, parent_transform, &mut pen)
+}
+
+/// Plot steps for a sibling list starting at `pen`, which is left where the last step ends
+fn plot_siblings(nodes: &[SceneNode], parent_transform: TransformMatrix, pen: &mut (f64, f64)) -> Vec<PlotStep> {
+    let mut strokes = Vec::new();
+    let mut groups: Vec<Option<(&[SceneNode], TransformMatrix)>> = Vec::new();
+
+    for node in nodes {
+        match node {
+            SceneNode::Leaf { id, object, transform, .. } => {
+                let world = parent_transform.multiply(transform);
+                if let Some((start, end)) = object_endpoints(object, &world) {
+                    strokes.push(Stroke { id: id.clone(), start, end, reversible: true });
+                    groups.push(None);
+                }
+            }
+            SceneNode::Group { id, children, transform, .. } => {
+                let world = parent_transform.multiply(transform);
+                if let Some((start, end)) = group_endpoints(children, world, *pen) {
+                    strokes.push(Stroke { id: id.clone(), start, end, reversible: false });
+                    groups.push(Some((children, world)));
+                }
+            }
+            // Leaves inside symbol instances have no unique IDs to put in a plot order;
+            // instances must be detached before plotting.
+            SceneNode::Instance { .. } => {}
+        }
+    }
+
+    let mut result = Vec::new();
+    for (index, reversed) in optimize_order(&strokes, *pen) {
+        match groups[index] {
+            None => {
+                result.push(PlotStep { id: strokes[index].id.clone(), reversed });
+                *pen = exit_point(&strokes[index], reversed);
+            }
+            Some((children, world)) => result.extend(plot_siblings(children, world, pen)),
+        }
+    }
+    result
+}
+
+/// Estimated entry and exit points of a group, from its leaves visited nearest first
+/// from `pen` (the group's actual order is planned once its place is known)
+fn group_endpoints(children: &[SceneNode], world: TransformMatrix, pen: (f64, f64)) -> Option<((f64, f64), (f64, f64))> {
+    let mut leaves = Vec::new();
+    collect_leaf_strokes(children, world, &mut leaves);
+    let order = nearest_neighbor(&leaves, pen);
+    let &(first, first_reversed) = order.first()?;
+    let &(last, last_reversed) = order.last()?;
+    Some((entry_point(&leaves[first], first_reversed), exit_point(&leaves[last], last_reversed)))
+}
+
+/// Plot steps in plain scene order (the baseline an optimized order is compared against)
+pub fn plot_order_unoptimized(nodes: &[SceneNode], parent_transform: TransformMatrix) -> Vec<PlotStep> {
+    let mut leaves = Vec::new();
+    collect_leaf_strokes(nodes, parent_transform, &mut leaves);
+    leaves
+        .into_iter()
+        .map(|stroke| PlotStep { id: stroke.id, reversed: false })
+        .collect()
+}
+
+/// Total pen-up travel for a list of plot steps over the given scene nodes
+pub fn plot_travel(nodes: &[SceneNode], steps: &[PlotStep], origin: (f64, f64)) -> f64 {
+    let mut strokes = Vec::new();
+    collect_leaf_strokes(nodes, TransformMatrix::identity(), &mut strokes);
+
+    let order: Vec<(usize, bool)> = steps
+        .iter()
+        .filter_map(|step| {
+            strokes
+                .iter()
+                .position(|stroke| stroke.id == step.id)
+                .map(|index| (index, step.reversed))
+        })
+        .collect();
+    travel_distance(&strokes, &order, origin)
+}
+
+fn collect_leaf_strokes(nodes: &[SceneNode], parent_transform: TransformMatrix, result: &mut Vec<Stroke>) {
+    for node in nodes {
+        match node {
+            SceneNode::Leaf { id, object, transform, .. } => {
+                let world = parent_transform.multiply(transform);
+                if let Some((start, end)) = object_endpoints(object, &world) {
+                    result.push(Stroke { id: id.clone(), start, end, reversible: true });
+                }
+            }
+            SceneNode::Group { children, transform, .. } => {
+                collect_leaf_strokes(children, parent_transform.multiply(transform), result);
+            }
+            SceneNode::Instance { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(id: &str, start: (f64, f64), end: (f64, f64)) -> Stroke {
+        Stroke { id: id.to_string(), start, end, reversible: true }
+    }
+
+    #[test]
+    fn test_nearest_neighbor_reverses_strokes() {
+        // Two horizontal lines drawn right-to-left in scene order
+        let strokes = vec![
+            line("a", (10.0, 0.0), (0.0, 0.0)),
+            line("b", (20.0, 0.0), (10.0, 0.0)),
+        ];
+        let order = optimize_order(&strokes, (0.0, 0.0));
+        assert_eq!(order, vec![(0, true), (1, true)]);
+        assert!(travel_distance(&strokes, &order, (0.0, 0.0)) < 1e-9);
+    }
+
+    #[test]
+    fn test_optimized_travel_not_worse_than_scene_order() {
+        let strokes: Vec<Stroke> = (0..12)
+            .map(|i| {
+                let x = ((i * 7) % 12) as f64 * 30.0;
+                let y = ((i * 5) % 12) as f64 * 20.0;
+                line(&format!("p{}", i), (x, y), (x + 10.0, y + 5.0))
+            })
+            .collect();
+        let naive: Vec<(usize, bool)> = (0..strokes.len()).map(|i| (i, false)).collect();
+        let optimized = optimize_order(&strokes, (0.0, 0.0));
+
+        assert_eq!(optimized.len(), strokes.len());
+        assert!(
+            travel_distance(&strokes, &optimized, (0.0, 0.0))
+                <= travel_distance(&strokes, &naive, (0.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn test_plot_order_keeps_groups_contiguous() {
+        let leaf = |id: &str, x: f64| SceneNode::Leaf {
+            id: id.to_string(),
+            object: VectorObject::Path {
+                commands: vec![
+                    PathCommand::MoveTo { x, y: 0.0 },
+                    PathCommand::LineTo { x: x + 5.0, y: 0.0 },
+                ],
+                is_closed: false,
+                anchor_types: Vec::new(),
+            },
+            transform: TransformMatrix::identity(),
+            style: Default::default(),
+            name: None,
+        };
+        let nodes = vec![
+            leaf("far", 500.0),
+            SceneNode::Group {
+                id: "group".to_string(),
+                children: vec![leaf("g2", 20.0), leaf("g1", 10.0)],
+                transform: TransformMatrix::identity(),
+                name: None,
+                cache_as_bitmap: false,
+            },
+            leaf("near", 0.0),
+        ];
+
+        let ids: Vec<String> = plot_order(&nodes, TransformMatrix::identity(), (0.0, 0.0))
+            .into_iter()
+            .map(|step| step.id)
+            .collect();
+        assert_eq!(ids, vec!["near", "g1", "g2", "far"]);
+    }
+
+    #[test]
+    fn test_groups_start_from_the_pen_position() {
+        let leaf = |id: &str, x: f64, length: f64| SceneNode::Leaf {
+            id: id.to_string(),
+            object: VectorObject::Path {
+                commands: vec![
+                    PathCommand::MoveTo { x, y: 0.0 },
+                    PathCommand::LineTo { x: x + length, y: 0.0 },
+                ],
+                is_closed: false,
+                anchor_types: Vec::new(),
+            },
+            transform: TransformMatrix::identity(),
+            style: Default::default(),
+            name: None,
+        };
+        let nodes = vec![
+            leaf("a", 0.0, 100.0),
+            SceneNode::Group {
+                id: "group".to_string(),
+                children: vec![leaf("left", -20.0, 5.0), leaf("right", 120.0, 5.0)],
+                transform: TransformMatrix::identity(),
+                name: None,
+                cache_as_bitmap: false,
+            },
+        ];
+
+        // After "a" the pen is at x = 100, so the group starts with its right-hand path
+        let steps = plot_order(&nodes, TransformMatrix::identity(), (0.0, 0.0));
+        let ids: Vec<&str> = steps.iter().map(|step| step.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "right", "left"]);
+        assert!((plot_travel(&nodes, &steps, (0.0, 0.0)) - 160.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,1847 @@
+//! Editor behaviour tests, driven through command batches
+//!
+//! The protocol module tests batching, versioning and command coverage; these
+//! check what the editor does with the commands.
+
+use serde_json::Value;
+
+use crate::core::geometry;
+use crate::core::scene::{PathCommand, SceneNode, VectorObject};
+use crate::protocol::{execute_batch_json, Response, ResponseBatch, PROTOCOL_VERSION};
+use crate::validation::ImportErrorCode;
+use crate::Editor;
+
+fn run(editor: &mut Editor, commands: &str) -> ResponseBatch {
+    let json = format!(r#"{{"version":{},"commands":{}}}"#, PROTOCOL_VERSION, commands);
+    serde_json::from_str(&execute_batch_json(editor, &json)).unwrap()
+}
+
+#[test]
+fn test_flip_mirrors_around_selection_center() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":30,"y":0,"width":20,"height":10},
+            {"op":"select","ids":["obj_1","obj_2"]},
+            {"op":"flip_selected_horizontal"},
+            {"op":"get_object_bounds","id":"obj_1"}]"#,
+    );
+    // Selection spans 0..50, so the small square moves to the right end
+    assert_eq!(batch.responses[4], Response::ok(serde_json::json!([40.0, 0.0, 50.0, 10.0])));
+}
+
+#[test]
+fn test_rotate_quarter_turn_clockwise() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":40,"height":20},
+            {"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1","obj_2"]},
+            {"op":"rotate_selected","angle_degrees":90},
+            {"op":"get_object_bounds","id":"obj_2"}]"#,
+    );
+    // Center (20, 10): the top-left square swings to the top-right corner (y down)
+    let bounds: Vec<f64> = serde_json::from_value(match &batch.responses[4] {
+        Response::Ok { result } => result.clone(),
+        other => panic!("unexpected {:?}", other),
+    })
+    .unwrap();
+    let expected = [20.0, -10.0, 30.0, 0.0];
+    for (value, expected) in bounds.iter().zip(expected) {
+        assert!((value - expected).abs() < 1e-9, "{:?}", bounds);
+    }
+}
+
+#[test]
+fn test_step_and_repeat_builds_grid_group() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"step_and_repeat","id":"obj_1","rows":2,"cols":3,"dx":20,"dy":15},
+            {"op":"get_selected_ids"},
+            {"op":"get_object_bounds","id":"obj_7"},
+            {"op":"step_and_repeat","id":"obj_1","rows":0,"cols":3,"dx":20,"dy":15}]"#,
+    );
+    let Response::Ok { result } = &batch.responses[1] else { panic!("unexpected {:?}", batch.responses[1]) };
+    let group_id = result.as_str().unwrap();
+    assert_eq!(group_id, "obj_7");
+    assert_eq!(batch.responses[2], Response::ok(serde_json::json!([group_id])));
+    // The group spans three columns and two rows of 10x10 cells
+    assert_eq!(batch.responses[3], Response::ok(serde_json::json!([0.0, 0.0, 50.0, 25.0])));
+    assert!(matches!(batch.responses[4], Response::Error { .. }));
+    // Group plus six cells
+    assert_eq!(editor.object_count(), 7);
+}
+
+#[test]
+fn test_path_data_editing() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":5},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"set_path_d","id":"obj_1","d":"M0 0 h20 v20 z"},
+            {"op":"get_object_bounds","id":"obj_1"},
+            {"op":"set_path_d","id":"obj_1","d":"M0 0 L"},
+            {"op":"undo"},
+            {"op":"get_path_d","id":"obj_1"}]"#,
+    );
+    assert_eq!(batch.responses[1], Response::ok("M0,0 L10,0 L10,5 L0,5 Z"));
+    assert_eq!(batch.responses[3], Response::ok(serde_json::json!([0.0, 0.0, 20.0, 20.0])));
+    assert!(matches!(&batch.responses[4], Response::Error { message } if message.contains("parse_error")));
+    // The failed edit recorded no undo step, so undo restores the rectangle
+    assert_eq!(batch.responses[6], Response::ok("M0,0 L10,0 L10,5 L0,5 Z"));
+}
+
+#[test]
+fn test_boolean_union_replaces_operands() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":5,"y":5,"width":10,"height":10},
+            {"op":"add_rectangle","x":100,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1","obj_2"]},
+            {"op":"boolean_op_selected","operation":"union"},
+            {"op":"get_object_bounds","id":"obj_4"},
+            {"op":"boolean_op_selected","operation":"nope"}]"#,
+    );
+    assert_eq!(batch.responses[4], Response::ok("obj_4"));
+    assert_eq!(batch.responses[5], Response::ok(serde_json::json!([0.0, 0.0, 15.0, 15.0])));
+    assert!(matches!(batch.responses[6], Response::Error { .. }));
+    // The result sits below the untouched rectangle
+    assert_eq!(editor.scene.roots.len(), 2);
+    assert_eq!(editor.scene.roots[0].id(), "obj_4");
+}
+
+#[test]
+fn test_outline_stroke_and_offset_path() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"offset_path","id":"obj_1","distance":2},
+            {"op":"get_object_bounds","id":"obj_2"},
+            {"op":"outline_stroke","id":"obj_1"},
+            {"op":"get_object_bounds","id":"obj_1"}]"#,
+    );
+    let bounds = |index: usize| -> Vec<f64> {
+        match &batch.responses[index] {
+            Response::Ok { result } => serde_json::from_value(result.clone()).unwrap(),
+            other => panic!("unexpected {:?}", other),
+        }
+    };
+    let assert_bounds = |actual: Vec<f64>, expected: [f64; 4]| {
+        for (value, expected) in actual.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-9, "{:?}", actual);
+        }
+    };
+    assert_eq!(batch.responses[1], Response::ok("obj_2"));
+    assert_bounds(bounds(2), [-2.0, -2.0, 12.0, 12.0]);
+    assert_eq!(batch.responses[3], Response::ok(Value::Null));
+    // Default 2px stroke with mitered corners reaches 1px outside the rectangle
+    assert_bounds(bounds(4), [-1.0, -1.0, 11.0, 11.0]);
+}
+
+#[test]
+fn test_erase_stroke() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":100,"height":20},
+            {"op":"add_path","commands":[{"type":"MoveTo","x":0,"y":50},{"type":"LineTo","x":100,"y":50}]},
+            {"op":"set_path_d","id":"obj_2","d":"M0,50 L100,50"},
+            {"op":"add_rectangle","x":200,"y":200,"width":10,"height":10},
+            {"op":"erase_stroke","samples":[{"x":50,"y":-10},{"x":50,"y":60}],"radius":5},
+            {"op":"erase_stroke","samples":[{"x":205,"y":205}],"radius":20},
+            {"op":"erase_stroke","samples":[],"radius":5}]"#,
+    );
+    // The open line is split into two paths; the rectangle keeps one object with two parts
+    assert_eq!(
+        batch.responses[4],
+        Response::ok(serde_json::json!({ "changed": ["obj_2", "obj_1"], "created": ["obj_4"], "removed": [] }))
+    );
+    assert_eq!(
+        batch.responses[5],
+        Response::ok(serde_json::json!({ "changed": [], "created": [], "removed": ["obj_3"] }))
+    );
+    assert!(matches!(batch.responses[6], Response::Error { .. }));
+    let ids: Vec<&str> = editor.scene.roots.iter().map(|node| node.id().as_str()).collect();
+    assert_eq!(ids, ["obj_1", "obj_2", "obj_4"]);
+}
+
+#[test]
+fn test_split_path_at() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"split_path_at","id":"obj_1","x":12,"y":5},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"split_path_at","id":"obj_1","x":0,"y":11},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"get_path_d","id":"obj_2"},
+            {"op":"split_path_at","id":"obj_2","x":10,"y":5}]"#,
+    );
+    assert_eq!(batch.responses[1], Response::ok(serde_json::json!(["obj_1"])));
+    assert_eq!(batch.responses[2], Response::ok("M10,5 L10,10 L0,10 L0,0 L10,0 L10,5"));
+    assert_eq!(batch.responses[3], Response::ok(serde_json::json!(["obj_1", "obj_2"])));
+    assert_eq!(batch.responses[4], Response::ok("M10,5 L10,10 L0,10"));
+    assert_eq!(batch.responses[5], Response::ok("M0,10 L0,0 L10,0 L10,5"));
+    // The end of an open path cannot be cut
+    assert!(matches!(batch.responses[6], Response::Error { .. }));
+}
+
+#[test]
+fn test_join_paths() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"split_path_at","id":"obj_1","x":5,"y":-1},
+            {"op":"join_paths","id_a":"obj_1","id_b":"obj_1"},
+            {"op":"add_rectangle","x":20,"y":0,"width":10,"height":10},
+            {"op":"split_path_at","id":"obj_2","x":25,"y":11},
+            {"op":"join_paths","id_a":"obj_1","id_b":"obj_2"},
+            {"op":"get_path_d","id":"obj_1"}]"#,
+    );
+    assert!(matches!(batch.responses[2], Response::Error { .. }));
+    assert_eq!(batch.responses[5], Response::ok(Value::Null));
+    // obj_1 ends at (5, 0) and obj_2 starts and ends at (25, 10): a straight segment joins them
+    assert_eq!(
+        batch.responses[6],
+        Response::ok("M5,0 L10,0 L10,10 L0,10 L0,0 L5,0 L25,10 L20,10 L20,0 L30,0 L30,10 L25,10")
+    );
+    assert_eq!(editor.scene.roots.len(), 1);
+}
+
+#[test]
+fn test_insert_path_point() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_path","commands":[{"type":"MoveTo","x":0,"y":0},{"type":"LineTo","x":10,"y":0},{"type":"LineTo","x":10,"y":10},{"type":"ClosePath"}]},
+            {"op":"insert_path_point","id":"obj_1","segment_index":2,"t":0.5},
+            {"op":"insert_path_point_at","id":"obj_1","x":4,"y":-3},
+            {"op":"insert_path_point","id":"obj_1","segment_index":9,"t":0.5},
+            {"op":"insert_path_point_at","id":"obj_1","x":10,"y":10},
+            {"op":"get_path_d","id":"obj_1"}]"#,
+    );
+    assert_eq!(batch.responses[1], Response::ok(3));
+    assert_eq!(batch.responses[2], Response::ok(1));
+    assert!(matches!(batch.responses[3], Response::Error { .. }));
+    assert!(matches!(batch.responses[4], Response::Error { .. }));
+    assert_eq!(batch.responses[5], Response::ok("M0,0 L4,0 L10,0 L10,10 L5,5 Z"));
+}
+
+#[test]
+fn test_set_anchor_type() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_path","commands":[{"type":"MoveTo","x":0,"y":0},{"type":"LineTo","x":10,"y":10},{"type":"LineTo","x":20,"y":0}]},
+            {"op":"set_anchor_type","id":"obj_1","index":1,"anchor_type":"smooth"},
+            {"op":"set_anchor_type","id":"obj_1","index":3,"anchor_type":"smooth"},
+            {"op":"set_anchor_type","id":"obj_1","index":1,"anchor_type":"round"}]"#,
+    );
+    assert_eq!(batch.responses[1], Response::ok(Value::Null));
+    assert!(matches!(batch.responses[2], Response::Error { .. }));
+    assert!(matches!(batch.responses[3], Response::Error { .. }));
+    // Both lines become curves whose handles at (10, 10) are level and equally long
+    let Some(SceneNode::Leaf { object: VectorObject::Path { commands, .. }, .. }) = editor.scene.get_node_by_id("obj_1")
+    else {
+        panic!("expected a path");
+    };
+    let (PathCommand::CurveTo { x2, y2, .. }, PathCommand::CurveTo { x1, y1, .. }) = (&commands[1], &commands[2]) else {
+        panic!("expected curves");
+    };
+    assert_eq!((*y1, *y2), (10.0, 10.0));
+    assert!((x1 - 10.0 - (10.0 - x2)).abs() < 1e-12);
+
+    let points: Value = serde_json::from_str(&editor.get_path_points("obj_1")).unwrap();
+    assert_eq!(points[1]["anchor"], "smooth");
+    assert_eq!(points[2]["anchor"], "corner");
+    // Inserting a point on a curve adds a smooth anchor
+    assert_eq!(editor.insert_path_point("obj_1", 0, 0.5), Some(1));
+    let points: Value = serde_json::from_str(&editor.get_path_points("obj_1")).unwrap();
+    assert_eq!(points[1]["anchor"], "smooth");
+    assert_eq!(points[2]["anchor"], "smooth");
+}
+
+#[test]
+fn test_path_handles() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_path","commands":[{"type":"MoveTo","x":0,"y":0},{"type":"LineTo","x":10,"y":10},{"type":"LineTo","x":20,"y":0}]},
+            {"op":"update_path_handle","id":"obj_1","anchor_index":1,"which":"in","x":5,"y":10},
+            {"op":"set_anchor_type","id":"obj_1","index":1,"anchor_type":"smooth"},
+            {"op":"update_path_handle","id":"obj_1","anchor_index":1,"which":"in","x":5,"y":10,"mirror":true},
+            {"op":"get_path_anchors","id":"obj_1"},
+            {"op":"update_path_handle","id":"obj_1","anchor_index":1,"which":"out","x":15,"y":20},
+            {"op":"get_path_anchors","id":"obj_1"}]"#,
+    );
+    // Straight segments have no handles to drag
+    assert!(matches!(batch.responses[1], Response::Error { .. }));
+    assert_eq!(batch.responses[3], Response::ok(Value::Null));
+    let anchors = |index: usize| match &batch.responses[index] {
+        Response::Ok { result } => result.clone(),
+        other => panic!("unexpected {:?}", other),
+    };
+    assert_eq!(anchors(4)[1]["in"], serde_json::json!([5.0, 10.0]));
+    assert_eq!(anchors(4)[1]["out"], serde_json::json!([15.0, 10.0]));
+    assert_eq!(anchors(4)[0]["out"], serde_json::json!([10.0 / 3.0, 10.0 / 3.0]));
+    // Dragging without mirroring breaks the handles
+    assert_eq!(anchors(6)[1]["in"], serde_json::json!([5.0, 10.0]));
+    assert_eq!(anchors(6)[1]["out"], serde_json::json!([15.0, 20.0]));
+    assert_eq!(anchors(6)[1]["anchor"], "corner");
+}
+
+#[test]
+fn test_anchor_selection() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"set_path_d","id":"obj_1","d":"M0,0 L10,0 L10,10 L0,10 Z"},
+            {"op":"select_anchors_in_rect","id":"obj_1","x1":8,"y1":-2,"x2":12,"y2":12},
+            {"op":"select_anchors","id":"obj_1","indices":[3,7]},
+            {"op":"select_anchors_in_rect","id":"obj_1","x1":8,"y1":-2,"x2":12,"y2":12,"add":true},
+            {"op":"move_selected_anchors","dx":5,"dy":0},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"get_selected_anchors"}]"#,
+    );
+    assert_eq!(batch.responses[2], Response::ok(serde_json::json!([1, 2])));
+    assert_eq!(batch.responses[3], Response::ok(serde_json::json!([3])));
+    assert_eq!(batch.responses[4], Response::ok(serde_json::json!([1, 2, 3])));
+    assert_eq!(batch.responses[6], Response::ok("M0,0 L15,0 L15,10 L5,10 Z"));
+    assert_eq!(batch.responses[7], Response::ok(serde_json::json!({ "id": "obj_1", "indices": [1, 2, 3] })));
+
+    editor.clear_anchor_selection();
+    assert!(!editor.move_selected_anchors(1.0, 1.0));
+}
+
+#[test]
+fn test_close_and_open_path() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"set_path_d","id":"obj_1","d":"M0,0 L10,0 L10,10"},
+            {"op":"close_path","id":"obj_1"},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"close_path","id":"obj_1"},
+            {"op":"open_path","id":"obj_1"},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"open_path","id":"obj_1"},
+            {"op":"undo"},
+            {"op":"get_path_d","id":"obj_1"}]"#,
+    );
+    assert_eq!(batch.responses[2], Response::ok(Value::Null));
+    assert_eq!(batch.responses[3], Response::ok("M0,0 L10,0 L10,10 Z"));
+    assert!(matches!(batch.responses[4], Response::Error { .. }));
+    assert_eq!(batch.responses[6], Response::ok("M0,0 L10,0 L10,10"));
+    assert!(matches!(batch.responses[7], Response::Error { .. }));
+    assert_eq!(batch.responses[9], Response::ok("M0,0 L10,0 L10,10 Z"));
+}
+
+#[test]
+fn test_fill_rule_affects_hit_test_and_export() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"set_path_d","id":"obj_1","d":"M0,0 L100,0 L100,100 L0,100 Z M25,25 L75,25 L75,75 L25,75 Z"},
+            {"op":"hit_test","x":50,"y":50},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"set_fill_rule","rule":"evenodd"},
+            {"op":"hit_test","x":50,"y":50},
+            {"op":"hit_test","x":10,"y":10},
+            {"op":"set_fill_rule","rule":"winding"},
+            {"op":"export_svg"}]"#,
+    );
+    assert_eq!(batch.responses[2], Response::ok("obj_1"));
+    assert_eq!(batch.responses[4], Response::ok(Value::Null));
+    assert_eq!(batch.responses[5], Response::ok(""));
+    assert_eq!(batch.responses[6], Response::ok("obj_1"));
+    assert!(matches!(batch.responses[7], Response::Error { .. }));
+    let Response::Ok { result } = &batch.responses[8] else {
+        panic!("export failed");
+    };
+    assert!(result.as_str().is_some_and(|svg| svg.contains(r#"fill-rule="evenodd""#)));
+}
+
+#[test]
+fn test_single_object_setters_record_undo() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_circle","cx":0,"cy":0,"r":5},
+            {"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"set_circle_radius","id":"obj_1","radius":8},
+            {"op":"set_shape_rendering","id":"obj_1","hint":"crisp-edges"},
+            {"op":"set_stroke_line_style","cap":"round","join":"bevel"},
+            {"op":"set_name","id":"obj_1","name":"Dot"},
+            {"op":"step_and_repeat","id":"obj_2","rows":1,"cols":2,"dx":20,"dy":0},
+            {"op":"set_cache_as_bitmap","id":"obj_4","enabled":true},
+            {"op":"undo"},
+            {"op":"get_cache_as_bitmap","id":"obj_4"},
+            {"op":"undo"},
+            {"op":"undo"},
+            {"op":"get_name","id":"obj_1"},
+            {"op":"undo"},
+            {"op":"undo"},
+            {"op":"get_shape_rendering","id":"obj_1"},
+            {"op":"undo"},
+            {"op":"get_circle_radius","id":"obj_1"}]"#,
+    );
+    assert_eq!(batch.responses[7], Response::ok("obj_4"));
+    assert_eq!(batch.responses[8], Response::ok(Value::Null));
+    assert_eq!(batch.responses[10], Response::ok(false));
+    assert_eq!(batch.responses[13], Response::ok(""));
+    assert_eq!(batch.responses[16], Response::ok("auto"));
+    assert_eq!(batch.responses[18], Response::ok(5.0));
+}
+
+#[test]
+fn test_stroke_align() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r##"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"update_style","fill":"#ffffff","stroke":"#ff0000","stroke_width":2},
+            {"op":"set_stroke_align","align":"middle"},
+            {"op":"set_stroke_align","align":"outside"},
+            {"op":"export_svg"}]"##,
+    );
+    assert!(matches!(batch.responses[3], Response::Error { .. }));
+    assert_eq!(batch.responses[4], Response::ok(Value::Null));
+    let Response::Ok { result } = &batch.responses[5] else {
+        panic!("export failed");
+    };
+    let svg = result.as_str().unwrap_or_default();
+    assert!(svg.contains(r#"data-stroke-align="outside""#), "{}", svg);
+    assert!(svg.contains(r#"stroke="none""#), "{}", svg);
+
+    // The canvas fills the stroke outline instead of stroking
+    let style: Value = serde_json::from_str(&editor.get_selected_style_json()).expect("style json");
+    assert_eq!(style["strokeAlign"], "outside");
+    let commands: Value = serde_json::from_str(&editor.get_render_commands_json()).expect("commands json");
+    let commands = commands.as_array().cloned().unwrap_or_default();
+    assert!(!commands.iter().any(|c| c["type"] == "Stroke"));
+    assert_eq!(commands.iter().filter(|c| c["type"] == "Fill").count(), 2);
+
+    run(&mut editor, r#"[{"op":"undo"},{"op":"select","ids":["obj_1"]}]"#);
+    let style: Value = serde_json::from_str(&editor.get_selected_style_json()).expect("style json");
+    assert_eq!(style["strokeAlign"], "center");
+}
+
+#[test]
+fn test_add_path_from_svg() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"save_snapshot"},
+            {"op":"add_path_from_svg","d":"m10 10 h20 v20 s-5 10 -10 0 t-10 0 a5 5 0 0 1 0 -10 z"},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"add_path_from_svg","d":"M0 0 L10"},
+            {"op":"get_history"},
+            {"op":"undo"},
+            {"op":"export_scene"}]"#,
+    );
+    assert_eq!(batch.responses[1], Response::ok("obj_1"));
+    assert_eq!(
+        batch.responses[2],
+        Response::ok("M10,10 L30,10 L30,30 C30,30 25,40 20,30 Q20,30 10,30 A5,5 0 0 1 10,20 Z")
+    );
+    assert!(matches!(&batch.responses[3], Response::Error { message } if message.contains("offset")));
+    // Like the other add_* calls, the caller's snapshot is the only undo step
+    let Response::Ok { result: history } = &batch.responses[4] else {
+        panic!("no history");
+    };
+    assert_eq!(history["entries"].as_array().map(Vec::len), Some(1));
+    let Response::Ok { result } = &batch.responses[6] else {
+        panic!("export failed");
+    };
+    assert_eq!(result["roots"].as_array().map(Vec::len), Some(0));
+}
+
+#[test]
+fn test_keyframe_animation() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"set_keyframe","id":"obj_1","property":"position","time":0},
+            {"op":"set_keyframe","id":"obj_1","property":"position","time":2,"value":[100,40],"easing":"ease_in_out"},
+            {"op":"set_keyframe","id":"obj_1","property":"opacity","time":2,"value":0.2},
+            {"op":"set_keyframe","id":"obj_1","property":"opacity","time":0,"value":"red"},
+            {"op":"set_keyframe","id":"obj_1","property":"opacity","time":0,"value":1},
+            {"op":"set_time","time":1},
+            {"op":"get_scene_bounds"},
+            {"op":"set_playback","duration":2,"fps":24,"looping":true},
+            {"op":"export_scene"},
+            {"op":"remove_keyframe","id":"obj_1","property":"position","time":2},
+            {"op":"get_keyframes","id":"obj_1"},
+            {"op":"set_time","time":2},
+            {"op":"get_scene_bounds"}]"#,
+    );
+    assert_eq!(batch.responses[1], Response::ok(Value::Null));
+    assert_eq!(batch.responses[2], Response::ok(Value::Null));
+    assert!(matches!(batch.responses[4], Response::Error { .. }));
+    assert_eq!(batch.responses[5], Response::ok(Value::Null));
+    // Halfway through an ease-in-out is halfway along the path
+    let Response::Ok { result } = &batch.responses[7] else {
+        panic!("bounds failed");
+    };
+    assert_eq!(result[0].as_f64(), Some(50.0));
+    assert_eq!(result[1].as_f64(), Some(20.0));
+
+    // Keyframes, style and playback settings are saved with the document
+    let Response::Ok { result: scene } = &batch.responses[9] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["timeline"]["fps"].as_f64(), Some(24.0));
+    assert_eq!(scene["timeline"]["current_time"].as_f64(), Some(1.0));
+    assert_eq!(scene["timeline"]["tracks"][0]["keyframes"][1]["easing"], "ease_in_out");
+    let opacity = scene["roots"][0]["Leaf"]["style"]["opacity"].as_f64().unwrap_or_default();
+    assert!((opacity - 0.6).abs() < 1e-9, "{}", opacity);
+
+    // With one position keyframe left the object stays at its value
+    let Response::Ok { result: tracks } = &batch.responses[11] else {
+        panic!("keyframes failed");
+    };
+    assert_eq!(tracks.as_array().map(Vec::len), Some(2));
+    let Response::Ok { result } = &batch.responses[13] else {
+        panic!("bounds failed");
+    };
+    assert_eq!(result[0].as_f64(), Some(0.0));
+}
+
+#[test]
+fn test_import_migrates_old_documents() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"import_scene","scene":{"roots":[{"Leaf":{"id":"obj_1",
+                "object":{"Path":{"commands":[{"type":"MoveTo","x":0,"y":0},{"type":"LineTo","x":10,"y":0}]}},
+                "transform":{"a":1,"b":0,"c":0,"d":1,"tx":0,"ty":0},
+                "style":{"fill_color":null,"stroke_color":"black","stroke_width":1}}}],"id_counter":1}},
+            {"op":"export_scene"},
+            {"op":"import_scene","scene":{"format_version":99,"roots":[],"id_counter":0}}]"#,
+    );
+    let Response::Ok { result: report } = &batch.responses[0] else {
+        panic!("import failed: {:?}", batch.responses[0]);
+    };
+    assert_eq!(report["detected_version"], 0);
+    assert_eq!(report["current_version"], crate::migration::FORMAT_VERSION);
+    assert_eq!(report["applied"].as_array().map(Vec::len), Some(2));
+    let Response::Ok { result: scene } = &batch.responses[1] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["format_version"], crate::migration::FORMAT_VERSION);
+    // Paths saved before is_closed existed were closed
+    assert_eq!(scene["roots"][0]["Leaf"]["object"]["Path"]["is_closed"], true);
+    let Response::Error { message } = &batch.responses[2] else {
+        panic!("newer document was accepted");
+    };
+    assert!(message.contains("unsupported_version"), "{}", message);
+    assert_eq!(editor.get_last_import_report(), "{}");
+}
+
+#[test]
+fn test_apply_operations_is_one_undo_step() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"apply_operations","operations":[
+                {"op":"add","object":{"Circle":{"cx":0,"cy":0,"r":5}}},
+                {"op":"delete","ids":["obj_1"]}]},
+            {"op":"get_selected_ids"},
+            {"op":"apply_operations","operations":[
+                {"op":"delete","ids":["obj_2"]},
+                {"op":"transform","ids":["obj_1"],"matrix":{"a":1,"b":0,"c":0,"d":1,"tx":1,"ty":0}}]},
+            {"op":"export_scene"},
+            {"op":"undo"},
+            {"op":"export_scene"}]"#,
+    );
+    assert_eq!(batch.responses[2], Response::ok(vec!["obj_2"]));
+    // Deleted objects leave the selection
+    assert_eq!(batch.responses[3], Response::ok(Vec::<String>::new()));
+    let Response::Error { message } = &batch.responses[4] else {
+        panic!("batch with an unknown object was applied");
+    };
+    let error: Value = serde_json::from_str(message).unwrap();
+    assert_eq!(error["index"], 1);
+    // The failed batch changed nothing: obj_2 is still there
+    let Response::Ok { result: scene } = &batch.responses[5] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"][0]["Leaf"]["id"], "obj_2");
+    let Response::Ok { result: scene } = &batch.responses[7] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"].as_array().map(Vec::len), Some(1));
+    assert_eq!(scene["roots"][0]["Leaf"]["id"], "obj_1");
+}
+
+#[test]
+fn test_history_lists_labeled_steps_and_jumps_between_them() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":20,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1","obj_2"]},
+            {"op":"nudge_selected","direction":"right","step":5},
+            {"op":"save_snapshot","label":"Recolor"},
+            {"op":"boolean_op_selected","operation":"union"},
+            {"op":"select","ids":["obj_3"]},
+            {"op":"delete_selected"},
+            {"op":"get_history"},
+            {"op":"jump_to_history","index":1},
+            {"op":"get_history"},
+            {"op":"export_scene"},
+            {"op":"jump_to_history","index":4},
+            {"op":"export_scene"},
+            {"op":"jump_to_history","index":5}]"#,
+    );
+    let history = |index: usize| match &batch.responses[index] {
+        Response::Ok { result } => result.clone(),
+        other => panic!("no history: {:?}", other),
+    };
+    assert_eq!(
+        history(8),
+        serde_json::json!({
+            "entries": ["Nudge 2 objects", "Recolor", "Boolean union", "Delete object"],
+            "current": 4
+        })
+    );
+    // Jumping back keeps the later steps to redo
+    assert_eq!(batch.responses[9], Response::ok(Value::Null));
+    assert_eq!(history(10)["entries"].as_array().map(Vec::len), Some(4));
+    assert_eq!(history(10)["current"], 1);
+    let Response::Ok { result: scene } = &batch.responses[11] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"].as_array().map(Vec::len), Some(2));
+    let Response::Ok { result: scene } = &batch.responses[13] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"].as_array().map(Vec::len), Some(0));
+    assert!(matches!(batch.responses[14], Response::Error { .. }));
+}
+
+#[test]
+fn test_saved_history_survives_a_reload() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"save_snapshot","label":"Add circle"},
+            {"op":"add_circle","cx":0,"cy":0,"r":5},
+            {"op":"select","ids":["obj_2"]},
+            {"op":"delete_selected"},
+            {"op":"undo"},
+            {"op":"export_scene","history_bytes":100000}]"#,
+    );
+    let Response::Ok { result: document } = &batch.responses[6] else {
+        panic!("export failed");
+    };
+    assert_eq!(document["history"]["undo"].as_array().map(Vec::len), Some(1));
+
+    let mut reloaded = Editor::new();
+    let batch = run(
+        &mut reloaded,
+        &serde_json::json!([
+            { "op": "import_scene", "scene": document },
+            { "op": "get_history" },
+            { "op": "undo" },
+            { "op": "export_scene" },
+        ])
+        .to_string(),
+    );
+    assert_eq!(
+        batch.responses[1],
+        Response::ok(serde_json::json!({ "entries": ["Add circle", "Delete object"], "current": 1 }))
+    );
+    let Response::Ok { result: scene } = &batch.responses[3] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"].as_array().map(Vec::len), Some(1));
+    assert!(scene.get("history").is_none());
+}
+
+#[test]
+fn test_history_that_fails_to_restore_is_dropped() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"import_scene","scene":{"roots":[],"id_counter":1,"history":{"undo":[{"label":"a","settings":4,"roots":[]}]}}},
+            {"op":"get_history"},
+            {"op":"export_scene"}]"#,
+    );
+    assert!(matches!(batch.responses[1], Response::Ok { .. }));
+    let Response::Ok { result: history } = &batch.responses[2] else {
+        panic!("get_history failed");
+    };
+    assert!(!history["entries"].as_array().is_some_and(|entries| entries.contains(&serde_json::json!("a"))));
+    let Response::Ok { result: scene } = &batch.responses[3] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"].as_array().map(Vec::len), Some(0));
+}
+
+#[test]
+fn test_history_stays_within_its_memory_budget() {
+    let mut editor = Editor::new();
+    for i in 0..20 {
+        editor.add_rectangle(i as f64 * 20.0, 0.0, 10.0, 10.0);
+    }
+    let step = |editor: &mut Editor, id: &str| {
+        let commands = serde_json::json!([
+            { "op": "select", "ids": [id] },
+            { "op": "nudge_selected", "direction": "down", "step": 1 },
+            { "op": "select", "ids": [] },
+        ]);
+        run(editor, &commands.to_string());
+        editor.history_memory_usage()
+    };
+    // Steps after the first store only the nudged object
+    let first = step(&mut editor, "obj_1");
+    let second = step(&mut editor, "obj_2");
+    assert!(second - first < first / 10, "{} then {}", first, second);
+
+    let batch = run(
+        &mut editor,
+        &format!(r#"[{{"op":"set_max_history_bytes","max_bytes":{}}},{{"op":"get_history_memory_usage"}}]"#, first),
+    );
+    let Response::Ok { result: usage } = &batch.responses[1] else {
+        panic!("no memory usage");
+    };
+    assert!(usage.as_u64().is_some_and(|usage| usage as usize <= first));
+    assert_eq!(editor.undo_stack_size(), 1);
+    // The latest step is kept even past the budget
+    editor.set_max_history_bytes(0);
+    assert_eq!(editor.undo_stack_size(), 1);
+
+    // The running total matches a recount after undoing and redoing
+    editor.set_max_history_bytes(usize::MAX);
+    for id in ["obj_3", "obj_4", "obj_5"] {
+        step(&mut editor, id);
+    }
+    editor.undo();
+    editor.undo();
+    editor.redo();
+    let recount = crate::history::memory_usage(editor.undo_stack.iter().chain(&editor.redo_stack));
+    assert_eq!(editor.history_memory_usage(), recount);
+}
+
+#[test]
+fn test_reparent_keeps_objects_in_place() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":100,"y":0,"width":10,"height":10},
+            {"op":"step_and_repeat","id":"obj_1","rows":1,"cols":2,"dx":20,"dy":0}]"#,
+    );
+    let Response::Ok { result: group } = &batch.responses[2] else {
+        panic!("no group");
+    };
+    let group = group.as_str().unwrap_or_default().to_string();
+    let bounds = |batch: &ResponseBatch, index: usize| match &batch.responses[index] {
+        Response::Ok { result } => serde_json::from_value::<Vec<f64>>(result.clone()).unwrap(),
+        other => panic!("no bounds: {:?}", other),
+    };
+    let batch = run(
+        &mut editor,
+        &serde_json::json!([
+            { "op": "select", "ids": [group] },
+            { "op": "rotate_selected", "angle_degrees": 90 },
+            { "op": "get_object_bounds", "id": "obj_2" },
+            { "op": "reparent", "id": "obj_2", "parent_id": group, "index": 0 },
+            { "op": "get_object_bounds", "id": "obj_2" },
+            // Nested objects can be moved like top-level ones
+            { "op": "set_position", "id": "obj_2", "x": 0, "y": 200 },
+            { "op": "get_object_bounds", "id": "obj_2" },
+            { "op": "reparent", "id": group, "parent_id": "obj_2", "index": 0 },
+            { "op": "undo" },
+            { "op": "undo" },
+            { "op": "export_scene" },
+        ])
+        .to_string(),
+    );
+    let close = |a: &[f64], b: &[f64]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-6);
+    assert!(close(&bounds(&batch, 2), &bounds(&batch, 4)), "{:?} {:?}", bounds(&batch, 2), bounds(&batch, 4));
+    assert!(close(&bounds(&batch, 6)[..2], &[0.0, 200.0]));
+    assert!(matches!(batch.responses[7], Response::Error { .. }));
+    // Two undo steps take obj_2 back out of the group
+    let Response::Ok { result: scene } = &batch.responses[10] else {
+        panic!("export failed");
+    };
+    assert_eq!(scene["roots"].as_array().map(Vec::len), Some(2));
+}
+
+#[test]
+fn test_selection_state_summarizes_the_selection() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_circle","cx":50,"cy":5,"r":5},
+            {"op":"get_selection_state"},
+            {"op":"select","ids":["obj_2","obj_1"]},
+            {"op":"get_selection_state"}]"#,
+    );
+    let Response::Ok { result: empty } = &batch.responses[2] else {
+        panic!("state failed");
+    };
+    assert_eq!(empty["ids"], serde_json::json!([]));
+    assert_eq!(empty["bounds"], Value::Null);
+    let Response::Ok { result: state } = &batch.responses[4] else {
+        panic!("state failed");
+    };
+    assert_eq!(state["ids"], serde_json::json!(["obj_1", "obj_2"]));
+    assert_eq!(state["types"], serde_json::json!(["rectangle", "circle"]));
+    assert_eq!(state["bounds"], serde_json::json!([0.0, 0.0, 55.0, 10.0]));
+    assert_eq!(state["style"]["mixed"], serde_json::json!([]));
+    assert!(state["style"]["fill"].is_string());
+}
+
+#[test]
+fn test_malformed_input_reports_located_errors() {
+    let mut editor = Editor::new();
+    let error = editor.add_path_checked(r#"[{"type":"MoveTo","x":0,"y":0},{"type":"LineTo","x":1,}]"#).unwrap_err();
+    assert_eq!(error.code, ImportErrorCode::ParseError);
+    assert_eq!(error.location.and_then(|location| location.column), Some(55));
+    assert!(editor.scene.roots.is_empty());
+    let error: Value = serde_json::from_str(&editor.get_last_import_error()).unwrap();
+    assert_eq!(error["location"]["line"], 1);
+
+    assert_eq!(editor.add_path_checked(r#"[{"type":"MoveTo","x":0,"y":0}]"#), Ok("obj_1".to_string()));
+    assert_eq!(editor.get_last_import_error(), "");
+
+    let error = editor
+        .import_scene_checked(
+            r#"{"roots":[{"Instance":{"id":"a","symbol_id":"missing",
+                "transform":{"a":1,"b":0,"c":0,"d":1,"tx":0,"ty":0}}}],"id_counter":1}"#,
+        )
+        .unwrap_err();
+    assert_eq!(error.code, ImportErrorCode::InvalidSymbol);
+    assert_eq!(error.location.and_then(|location| location.object_id).as_deref(), Some("a"));
+    // The current scene is kept
+    assert_eq!(editor.scene.roots.len(), 1);
+}
+
+#[test]
+fn test_export_svg_page_options() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":10,"y":20,"width":30,"height":40},
+            {"op":"set_canvas_size","width":200,"height":100},
+            {"op":"export_svg"},
+            {"op":"export_svg","options":{"background":false,"crop":true,"padding":4}}]"#,
+    );
+    let svg = |index: usize| match &batch.responses[index] {
+        Response::Ok { result } => result.as_str().unwrap_or_default().to_string(),
+        other => panic!("export failed: {:?}", other),
+    };
+    assert!(svg(2).contains(r#"viewBox="0 0 200 100""#));
+    assert!(svg(2).contains("#1e1e1e"));
+    // Content 9..41 x 19..61 including the stroke, plus 4 padding
+    assert!(svg(3).contains(r#"viewBox="5 15 40 50""#));
+    assert!(!svg(3).contains("#1e1e1e"));
+}
+
+#[test]
+fn test_pattern_fill() {
+    let mut editor = Editor::new();
+    run(&mut editor, r#"[{"op":"add_circle","cx":5,"cy":5,"r":3},{"op":"select","ids":["obj_1"]}]"#);
+    assert!(!editor.create_symbol_from_selection("Dot").is_empty());
+    let symbols: Value = serde_json::from_str(&editor.get_symbols()).expect("symbols json");
+    let symbol_id = symbols[0]["id"].as_str().unwrap_or_default().to_string();
+
+    let batch = serde_json::json!([
+        { "op": "add_rectangle", "x": 0, "y": 0, "width": 100, "height": 50 },
+        { "op": "select_at", "x": 50, "y": 40 },
+        { "op": "get_selected_ids" },
+        { "op": "set_fill_pattern", "pattern": {
+            "source": { "kind": "symbol", "symbol_id": "sym_404" } } },
+        { "op": "set_fill_pattern", "pattern": {
+            "source": { "kind": "symbol", "symbol_id": symbol_id }, "spacing": [4, 4], "rotation": 45 } },
+        { "op": "export_svg" }
+    ]);
+    let batch = run(&mut editor, &batch.to_string());
+    let Response::Ok { result: ids } = &batch.responses[2] else {
+        panic!("no selection: {:?}", batch.responses[2]);
+    };
+    assert!(matches!(batch.responses[3], Response::Error { .. }));
+    assert_eq!(batch.responses[4], Response::ok(Value::Null));
+
+    let rect_id = ids[0].as_str().unwrap_or_default();
+    let svg = match &batch.responses[5] {
+        Response::Ok { result } => result.as_str().unwrap_or_default().to_string(),
+        other => panic!("export failed: {:?}", other),
+    };
+    let pattern_id = format!("{}-pattern", rect_id);
+    assert!(svg.contains(&format!(r#"<pattern id="{}" patternUnits="userSpaceOnUse" width="10" height="10""#, pattern_id)), "{}", svg);
+    assert!(svg.contains(&format!(r#"fill="url(#{})""#, pattern_id)), "{}", svg);
+    assert!(svg.contains(r#"<g transform="translate(-2,-2)">"#), "{}", svg);
+
+    // The canvas gets the tile after the fallback color
+    let commands: Value = serde_json::from_str(&editor.get_render_commands_json()).expect("commands json");
+    let commands = commands.as_array().cloned().unwrap_or_default();
+    let pattern = commands.iter().position(|c| c["type"] == "SetFillPattern").expect("pattern command");
+    assert_eq!(commands[pattern - 1]["type"], "SetFillStyle");
+    assert_eq!(commands[pattern]["width"], 10.0);
+    assert!(commands[pattern]["commands"].as_array().is_some_and(|tile| !tile.is_empty()));
+
+    let batch = run(&mut editor, r#"[{"op":"set_fill_pattern"},{"op":"undo"},{"op":"undo"}]"#);
+    assert!(batch.responses.iter().all(|response| matches!(response, Response::Ok { .. })));
+    assert!(!editor.get_render_commands_json().contains("SetFillPattern"));
+}
+
+#[test]
+fn test_text_objects() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    let batch = run(&mut editor, r#"[{"op":"add_text","x":10,"y":50,"content":"Hi"},{"op":"register_font","data":[1,2,3]}]"#);
+    assert!(batch.responses.iter().all(|response| matches!(response, Response::Error { .. })));
+    assert_eq!(editor.register_font(ROBOTO), "font_1");
+
+    let batch = run(
+        &mut editor,
+        r##"[{"op":"add_text","x":10,"y":50,"content":"Hi"},
+            {"op":"get_selected_ids"},
+            {"op":"get_object_bounds","id":"obj_1"},
+            {"op":"hit_test","x":20,"y":40},
+            {"op":"set_font_size","id":"obj_1","size":48},
+            {"op":"get_object_bounds","id":"obj_1"},
+            {"op":"set_text_content","id":"obj_1","content":"Hello"},
+            {"op":"set_text_font","id":"obj_1","family":"Comic Sans"},
+            {"op":"get_text_properties","id":"obj_1"},
+            {"op":"update_style","fill":"#ff0000","stroke":"none","stroke_width":0},
+            {"op":"export_svg"},
+            {"op":"undo"},
+            {"op":"get_text_properties","id":"obj_1"}]"##,
+    );
+    assert_eq!(batch.responses[0], Response::ok("obj_1"));
+    assert_eq!(batch.responses[1], Response::ok(serde_json::json!(["obj_1"])));
+    let height = |response: &Response| match response {
+        Response::Ok { result } => result[3].as_f64().unwrap_or_default() - result[1].as_f64().unwrap_or_default(),
+        other => panic!("no bounds: {:?}", other),
+    };
+    let small = height(&batch.responses[2]);
+    assert!(small > 10.0 && small < 24.0, "{}", small);
+    assert_eq!(batch.responses[3], Response::ok("obj_1"));
+    assert!((height(&batch.responses[5]) - small * 2.0).abs() < 1e-6);
+    assert!(matches!(batch.responses[7], Response::Error { .. }));
+    let Response::Ok { result: properties } = &batch.responses[8] else {
+        panic!("no text properties");
+    };
+    assert_eq!(properties["content"], "Hello");
+    assert_eq!(properties["size"], 48.0);
+    assert_eq!(properties["font_missing"], false);
+    assert_eq!((&properties["font_family"], &properties["font_weight"]), (&serde_json::json!("Roboto"), &serde_json::json!(400)));
+    assert_eq!(properties["font_id"], "font_1");
+    let Response::Ok { result } = &batch.responses[10] else {
+        panic!("export failed");
+    };
+    let svg = result.as_str().unwrap_or_default();
+    assert!(svg.contains(r#"aria-label="Hello""#), "{}", svg);
+    assert!(svg.contains(r##"fill="#ff0000""##), "{}", svg);
+    let Response::Ok { result: properties } = &batch.responses[12] else {
+        panic!("no text properties");
+    };
+    assert_eq!(properties["content"], "Hi");
+
+    // Text survives a save and load without its font
+    let json = editor.export_scene_to_json();
+    let mut reloaded = Editor::new();
+    assert!(reloaded.import_scene_checked(&json).is_ok());
+    assert_eq!(reloaded.hit_test(20.0, 20.0), "obj_1");
+    assert!(!reloaded.set_text_content("obj_1", "Hey"));
+    // It names its font by family, so any session's Roboto shapes it again
+    assert_eq!(reloaded.register_font(ROBOTO), "font_1");
+    assert!(reloaded.set_text_content("obj_1", "Hey"));
+    assert!(reloaded.set_text_font("obj_1", "roboto", 700, false));
+    assert!(!reloaded.set_text_font("obj_1", "Noto Sans JP", 400, false));
+}
+
+#[test]
+fn test_path_operations_leave_text_alone() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    let text = editor.add_text(10.0, 50.0, "Hi");
+    let rect = editor.add_rectangle(0.0, 0.0, 40.0, 40.0);
+    assert!(editor.rotate_selected(30.0));
+    let properties = editor.get_text_properties(&text);
+
+    assert!(!editor.reset_bounding_box(&text));
+    assert_eq!(editor.split_path_at(&text, 20.0, 40.0), "[]");
+    editor.select_ids(&serde_json::json!([text, rect]).to_string());
+    assert_eq!(editor.boolean_op_selected("union"), "");
+    let erased: Value = serde_json::from_str(&editor.erase_stroke(r#"[{"x":0,"y":40},{"x":60,"y":40}]"#, 5.0)).unwrap();
+    assert_eq!(erased["changed"], serde_json::json!([rect]));
+    assert_eq!(editor.get_text_properties(&text), properties);
+}
+
+#[test]
+fn test_text_editing_with_caret() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    assert!(!editor.insert_text("x"));
+    editor.register_font(ROBOTO);
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_text","x":10,"y":50,"content":"Hello"},
+            {"op":"begin_text_edit","id":"obj_1"},
+            {"op":"move_caret","unit":"word","forward":false},
+            {"op":"insert_text","text":"Oh, "},
+            {"op":"move_caret","unit":"line_edge","forward":true},
+            {"op":"insert_text","text":"\nWorld"},
+            {"op":"delete_backward"},
+            {"op":"move_caret","unit":"line","forward":false},
+            {"op":"delete_forward"},
+            {"op":"move_caret","unit":"sentence","forward":true},
+            {"op":"get_text_edit_state"},
+            {"op":"get_object_bounds","id":"obj_1"}]"#,
+    );
+    assert!(batch.responses[..9].iter().all(|response| !matches!(response, Response::Error { .. })));
+    assert!(matches!(batch.responses[9], Response::Error { .. }));
+    let Response::Ok { result: state } = &batch.responses[10] else {
+        panic!("no edit state");
+    };
+    assert_eq!(state["content"], "Oh, ello\nWorl");
+    assert_eq!(state["caret"], 4);
+    assert_eq!((state["line"].as_u64(), state["column"].as_u64()), (Some(0), Some(4)));
+    let Response::Ok { result: bounds } = &batch.responses[11] else {
+        panic!("no bounds");
+    };
+    // The second line extends below the first baseline
+    assert!(bounds[3].as_f64().unwrap_or_default() > 70.0);
+
+    // The whole run of edits is one undo step, and the object keeps its outline
+    assert!(editor.undo());
+    let properties: Value = serde_json::from_str(&editor.get_text_properties("obj_1")).unwrap_or_default();
+    assert_eq!(properties["content"], "Hello");
+    assert!(editor.insert_text("!"));
+    assert!(editor.undo());
+    assert!(editor.end_text_edit());
+    assert_eq!(editor.get_text_edit_state(), "");
+    assert!(!editor.delete_backward());
+}
+
+#[test]
+fn test_wrapped_and_aligned_text() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_text","x":10,"y":50,"content":"one two three four"},
+            {"op":"set_text_width","id":"obj_1","width":80},
+            {"op":"set_text_align","id":"obj_1","align":"right"},
+            {"op":"set_text_indent","id":"obj_1","indent":12},
+            {"op":"set_text_align","id":"obj_1","align":"middle"},
+            {"op":"set_text_width","id":"obj_1","width":-1},
+            {"op":"get_text_properties","id":"obj_1"},
+            {"op":"get_text_layout","id":"obj_1"},
+            {"op":"begin_text_edit","id":"obj_1"},
+            {"op":"move_caret","unit":"line","forward":false},
+            {"op":"get_text_edit_state"}]"#,
+    );
+    assert!(batch.responses[..4].iter().all(|response| !matches!(response, Response::Error { .. })));
+    assert!(matches!(batch.responses[4], Response::Error { .. }));
+    assert!(matches!(batch.responses[5], Response::Error { .. }));
+    let Response::Ok { result: properties } = &batch.responses[6] else {
+        panic!("no text properties");
+    };
+    assert_eq!((properties["width"].as_f64(), properties["align"].as_str()), (Some(80.0), Some("right")));
+    assert_eq!(properties["indent"], 12.0);
+    let Response::Ok { result: lines } = &batch.responses[7] else {
+        panic!("no layout");
+    };
+    let lines = lines.as_array().cloned().unwrap_or_default();
+    assert!(lines.len() > 1);
+    for line in &lines {
+        // Right aligned within x..x + width
+        let right = line["x"].as_f64().unwrap_or_default() + line["width"].as_f64().unwrap_or_default();
+        assert!((right - 90.0).abs() < 1e-6, "{}", line);
+    }
+    assert_eq!(lines[1]["baseline"].as_f64(), Some(50.0 + 24.0 * crate::text_engine::LINE_HEIGHT));
+
+    // Caret lines follow the wrapping
+    let Response::Ok { result: state } = &batch.responses[10] else {
+        panic!("no edit state");
+    };
+    assert_eq!(state["line"].as_u64(), Some(lines.len() as u64 - 2));
+
+    // Saved documents keep the paragraph style
+    let json = editor.export_scene_to_json();
+    let saved: Value = serde_json::from_str(&json).unwrap_or_default();
+    let paragraph = &saved["roots"][0]["Leaf"]["object"]["Text"]["paragraph"];
+    assert_eq!(paragraph, &serde_json::json!({ "width": 80.0, "align": "right", "indent": 12.0 }));
+    let mut reloaded = Editor::new();
+    assert!(reloaded.import_scene_checked(&json).is_ok());
+}
+
+#[test]
+fn test_text_typography() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_text","x":0,"y":50,"content":"Office 2024\nline"},
+            {"op":"get_object_bounds","id":"obj_1"},
+            {"op":"set_text_tracking","id":"obj_1","tracking":200},
+            {"op":"set_text_leading","id":"obj_1","leading":60},
+            {"op":"get_object_bounds","id":"obj_1"},
+            {"op":"set_text_features","id":"obj_1","features":"smcp, tnum,-liga"},
+            {"op":"set_text_features","id":"obj_1","features":"smcp,???"},
+            {"op":"get_text_properties","id":"obj_1"},
+            {"op":"set_text_kerning","id":"obj_1","kerning":{"0":-150,"4":80}},
+            {"op":"get_text_properties","id":"obj_1"}]"#,
+    );
+    assert!([2, 3, 5].iter().all(|&index| batch.responses[index] == Response::ok(Value::Null)));
+    assert!(matches!(batch.responses[6], Response::Error { .. }));
+    let bounds = |response: &Response| match response {
+        Response::Ok { result } => (result[2].as_f64().unwrap_or_default(), result[3].as_f64().unwrap_or_default()),
+        other => panic!("no bounds: {:?}", other),
+    };
+    let (plain_right, plain_bottom) = bounds(&batch.responses[1]);
+    let (right, bottom) = bounds(&batch.responses[4]);
+    // "Office 2024" shapes to 9 clusters ("ffi" is a ligature), so 8 gaps are tracked
+    assert!((right - plain_right - 24.0 * 0.2 * 8.0).abs() < 1e-3, "{} {}", plain_right, right);
+    assert!((bottom - plain_bottom - (60.0 - 24.0 * 1.2)).abs() < 1e-6);
+    let Response::Ok { result: properties } = &batch.responses[7] else {
+        panic!("no text properties");
+    };
+    assert_eq!(properties["tracking"], 200.0);
+    assert_eq!(properties["leading"], 60.0);
+    assert_eq!(properties["features"], serde_json::json!(["smcp", "tnum", "-liga"]));
+    assert_eq!(batch.responses[8], Response::ok(Value::Null));
+    let Response::Ok { result: properties } = &batch.responses[9] else {
+        panic!("no text properties");
+    };
+    assert_eq!(properties["kerning"], serde_json::json!({ "0": -150.0, "4": 80.0 }));
+    assert!(!editor.set_text_kerning("obj_1", r#"{"first":10}"#));
+    assert!(editor.set_text_kerning("obj_1", "{}"));
+}
+
+#[test]
+fn test_missing_fonts_are_listed_and_repaired() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    let id = editor.add_text(0.0, 50.0, "Tokyo 東京");
+    let roboto_only = editor.get_object_bounds(&id);
+
+    // Loaded without its font the text keeps its outlines and is flagged
+    let mut reloaded = Editor::new();
+    assert!(reloaded.import_scene_checked(&editor.export_scene_to_json()).is_ok());
+    let batch = run(&mut reloaded, r#"[{"op":"list_missing_fonts"},{"op":"get_text_properties","id":"obj_1"}]"#);
+    let missing = serde_json::json!([{ "family": "Roboto", "weight": 400, "italic": false, "ids": ["obj_1"] }]);
+    assert_eq!(batch.responses[0], Response::ok(missing.clone()));
+    let Response::Ok { result: properties } = &batch.responses[1] else {
+        panic!("no text properties");
+    };
+    assert_eq!((&properties["font_missing"], &properties["font_id"]), (&Value::Bool(true), &Value::Null));
+    assert!(!reloaded.set_text_content(&id, "Kyoto"));
+
+    // Another family repairs nothing; the text's own family lays it out again, now
+    // with the kanji from the fallback font
+    reloaded.register_font(NOTO_JP);
+    assert_eq!(reloaded.list_missing_fonts(), missing.to_string());
+    assert_eq!(reloaded.get_object_bounds(&id), roboto_only);
+    reloaded.register_font(ROBOTO);
+    assert_eq!(reloaded.list_missing_fonts(), "[]");
+    assert_ne!(reloaded.get_object_bounds(&id), roboto_only);
+    // Repairing is not an edit of its own
+    assert!(!reloaded.can_undo());
+    assert!(reloaded.set_text_content(&id, "Kyoto"));
+}
+
+#[test]
+fn test_font_registry_and_fallback() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+    let mut editor = Editor::new();
+    assert_eq!(editor.register_font(ROBOTO), "font_1");
+    let id = editor.add_text(0.0, 50.0, "Tokyo 東京");
+    let roboto_only = editor.get_object_bounds(&id);
+    assert_eq!(editor.register_font(NOTO_JP), "font_2");
+    // Reshaping picks up the new fallback font for the kanji
+    assert!(editor.set_text_content(&id, "Tokyo 東京"));
+    assert_ne!(editor.get_object_bounds(&id), roboto_only);
+    let layout: Value = serde_json::from_str(&editor.get_text_layout(&id)).unwrap_or_default();
+    let glyphs = layout[0]["glyphs"].as_array().cloned().unwrap_or_default();
+    let fonts: Vec<u64> = glyphs.iter().filter_map(|glyph| glyph["font"].as_u64()).collect();
+    assert_eq!(fonts, vec![0, 0, 0, 0, 0, 0, 1, 1]);
+
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"get_fonts"},
+            {"op":"find_font","family":"Noto Sans JP","weight":700},
+            {"op":"find_font","family":"Helvetica","weight":400},
+            {"op":"unregister_font","font_id":"font_1"},
+            {"op":"get_text_properties","id":"obj_1"},
+            {"op":"get_glyph_cache_stats"}]"#,
+    );
+    let Response::Ok { result: fonts } = &batch.responses[0] else {
+        panic!("no fonts");
+    };
+    assert_eq!(fonts[0]["family"], "Roboto");
+    assert_eq!(fonts[1]["family"], "Noto Sans JP");
+    assert_eq!(batch.responses[1], Response::ok("font_2"));
+    assert!(matches!(batch.responses[2], Response::Error { .. }));
+    assert_eq!(batch.responses[3], Response::ok(Value::Null));
+    let Response::Ok { result: properties } = &batch.responses[4] else {
+        panic!("no text properties");
+    };
+    assert_eq!(properties["font_missing"], true);
+    // Reshaping reused the Latin glyphs; only the kanji of the remaining font stay cached
+    let Response::Ok { result: stats } = &batch.responses[5] else {
+        panic!("no cache stats");
+    };
+    assert_eq!(stats["entries"], 2);
+    assert!(stats["hits"].as_u64().is_some_and(|hits| hits >= 5));
+}
+
+#[test]
+fn test_render_delta_follows_a_drag() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":200,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":400,"y":0,"width":10,"height":10},
+            {"op":"get_render_commands_delta"},
+            {"op":"get_render_commands_delta"}]"#,
+    );
+    let delta = |response: &Response| match response {
+        Response::Ok { result } => result.clone(),
+        Response::Error { message } => panic!("{message}"),
+    };
+    let first = delta(&batch.responses[3]);
+    assert_eq!((first["full"].as_bool(), first["layers"].as_array().map(Vec::len)), (Some(true), Some(3)));
+    let idle = delta(&batch.responses[4]);
+    assert_eq!((idle["full"].as_bool(), idle["region"].is_null()), (Some(false), true));
+
+    // Each drag step repaints only the dragged object, over where it was and is
+    editor.select_at(205.0, 5.0);
+    editor.begin_move_drag(205.0, 5.0, false);
+    editor.update_move_drag(225.0, 5.0, false);
+    let step = delta(&run(&mut editor, r#"[{"op":"get_render_commands_delta"}]"#).responses[0]);
+    assert_eq!(step["full"].as_bool(), Some(false));
+    assert_eq!(step["layers"].as_array().map(Vec::len), Some(1));
+    assert_eq!(step["layers"][0]["layer_id"], "obj_2");
+    let region: Vec<f64> = serde_json::from_value(step["region"].clone()).unwrap_or_default();
+    assert!(region[0] < 200.0 && region[2] > 230.0 && region[2] < 400.0);
+    editor.end_drag();
+
+    // Panning the view draws everything again
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"get_render_commands_delta"},
+            {"op":"set_viewport","pan_x":10,"pan_y":0,"zoom":1},
+            {"op":"get_render_commands_delta"},
+            {"op":"invalidate_render_delta"},
+            {"op":"get_render_commands_delta"}]"#,
+    );
+    assert_eq!(delta(&batch.responses[2])["full"].as_bool(), Some(true));
+    assert_eq!(delta(&batch.responses[4])["full"].as_bool(), Some(true));
+
+    // The binary buffer encodes the same frame, each color once
+    let batch = run(&mut editor, r#"[{"op":"get_render_commands_binary"}]"#);
+    let buffer: Vec<f64> = serde_json::from_value(delta(&batch.responses[0])).unwrap_or_default();
+    assert_eq!(buffer, crate::render_buffer::encode(&crate::renderer::generate_render_commands(&editor.scene)));
+
+    // Triangles for the GPU: two per rectangle fill, plus their strokes
+    let batch = run(&mut editor, r#"[{"op":"tessellate","tolerance":0.25}]"#);
+    let mesh = delta(&batch.responses[0]);
+    let vertices = mesh["vertices"].as_array().map_or(0, Vec::len);
+    let indices = mesh["indices"].as_array().map_or(0, Vec::len);
+    assert!(vertices % 6 == 0 && indices % 3 == 0 && indices >= 3 * 2 * 3 * 2);
+
+    // Only the dragged object's commands were ever regenerated
+    let stats = delta(&run(&mut editor, r#"[{"op":"get_render_cache_stats"}]"#).responses[0]);
+    assert_eq!((stats["entries"].as_u64(), stats["misses"].as_u64()), (Some(3), Some(4)));
+}
+
+#[test]
+fn test_viewport_and_screen_space_tolerances() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":100,"height":100},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"hit_handle","x":102,"y":-1},
+            {"op":"set_viewport","pan_x":50,"pan_y":20,"zoom":4},
+            {"op":"set_viewport","pan_x":50,"pan_y":20,"zoom":0},
+            {"op":"screen_to_world","x":250,"y":420},
+            {"op":"world_to_screen","x":50,"y":100},
+            {"op":"set_screen_space_tolerances","enabled":true},
+            {"op":"hit_handle","x":102,"y":-1},
+            {"op":"hit_handle","x":100.5,"y":-0.5},
+            {"op":"zoom_viewport_at","screen_x":250,"screen_y":420,"factor":0.5},
+            {"op":"get_viewport"}]"#,
+    );
+    // Handles are 8 units wide, so (102, -1) is on the top right one
+    assert_eq!(batch.responses[2], Response::ok(1));
+    assert!(matches!(batch.responses[4], Response::Error { .. }));
+    assert_eq!(batch.responses[5], Response::ok(serde_json::json!([50.0, 100.0])));
+    assert_eq!(batch.responses[6], Response::ok(serde_json::json!([250.0, 420.0])));
+    // At 400% the handles are 8 pixels: 2 world units
+    assert_eq!(batch.responses[8], Response::ok(-1));
+    assert_eq!(batch.responses[9], Response::ok(1));
+    // Zooming out at a point keeps it over the same world point
+    assert_eq!(batch.responses[11], Response::ok(serde_json::json!({ "pan_x": 150.0, "pan_y": 220.0, "zoom": 2.0 })));
+    let overlay: Value = serde_json::from_str(&editor.get_selection_overlay_json()).unwrap_or_default();
+    assert_eq!(overlay[0]["handle_size"].as_f64(), Some(4.0));
+
+    // A move waits for the mouse to travel 3 pixels, then follows it from the start
+    editor.begin_move_drag(50.0, 50.0, false);
+    editor.update_move_drag(51.0, 50.0, false);
+    assert_eq!(editor.get_object_bounds("obj_1"), serde_json::json!([0.0, 0.0, 100.0, 100.0]).to_string());
+    editor.update_move_drag(52.0, 50.0, false);
+    editor.end_drag();
+    assert_eq!(editor.get_object_bounds("obj_1"), serde_json::json!([2.0, 0.0, 102.0, 100.0]).to_string());
+
+    // Pen anchors snap within 8 pixels: 4 world units at 200%
+    for x in [0.0, 200.0] {
+        editor.pen_down(x, 0.0);
+        editor.pen_up(x, 0.0);
+    }
+    assert_eq!(editor.pen_snap_point(3.0, 0.0), "[0.0,0.0]");
+    assert_eq!(editor.pen_snap_point(5.0, 0.0), "[]");
+    editor.set_screen_space_tolerances(false);
+    assert_eq!(editor.pen_snap_point(5.0, 0.0), "[0.0,0.0]");
+}
+
+#[test]
+fn test_vertical_text() {
+    const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(NOTO_JP);
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_text","x":100,"y":50,"content":"日本"},
+            {"op":"set_text_writing_mode","id":"obj_1","mode":"vertical"},
+            {"op":"set_text_writing_mode","id":"obj_1","mode":"diagonal"},
+            {"op":"caret_rect","id":"obj_1","index":1},
+            {"op":"text_hit","id":"obj_1","x":95,"y":90}]"#,
+    );
+    assert!(matches!(batch.responses[1], Response::Ok { .. }));
+    assert!(matches!(batch.responses[2], Response::Error { .. }));
+    // The caret lies across the column, one em down
+    let Response::Ok { result } = &batch.responses[3] else {
+        panic!("no caret: {:?}", batch.responses[3]);
+    };
+    assert_eq!((result["y1"].as_f64(), result["y2"].as_f64()), (Some(74.0), Some(74.0)));
+    let (x1, x2) = (result["x1"].as_f64().unwrap_or_default(), result["x2"].as_f64().unwrap_or_default());
+    assert!(x1 < 90.0 && x2 > 110.0, "{} {}", x1, x2);
+    assert!(matches!(&batch.responses[4], Response::Ok { result } if result["index"] == 2));
+
+    // The glyphs run down from the origin and the mode is saved with the paragraph
+    let layout: Value = serde_json::from_str(&editor.get_text_layout("obj_1")).unwrap_or_default();
+    assert_eq!((layout[0]["vertical"].as_bool(), layout[0]["baseline"].as_f64()), (Some(true), Some(100.0)));
+    assert_eq!(layout[0]["glyphs"][1]["inline"].as_f64(), Some(74.0));
+    let properties: Value = serde_json::from_str(&editor.get_text_properties("obj_1")).unwrap_or_default();
+    assert_eq!(properties["writing_mode"], "vertical");
+    let saved: Value = serde_json::from_str(&editor.export_scene_to_json()).unwrap_or_default();
+    assert_eq!(saved["roots"][0]["Leaf"]["object"]["Text"]["paragraph"]["writing_mode"], "vertical");
+
+    assert!(editor.undo());
+    let properties: Value = serde_json::from_str(&editor.get_text_properties("obj_1")).unwrap_or_default();
+    assert_eq!(properties["writing_mode"], "horizontal");
+}
+
+#[test]
+fn test_text_hit_and_caret_rect() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_text","x":0,"y":0,"content":"Hello\nWorld"},
+            {"op":"caret_rect","id":"obj_1","index":0},
+            {"op":"caret_rect","id":"obj_1","index":6},
+            {"op":"move_selected","dx":100,"dy":50},
+            {"op":"caret_rect","id":"obj_1","index":6},
+            {"op":"text_hit","id":"obj_1","x":100,"y":80},
+            {"op":"text_hit","id":"obj_1","x":400,"y":40},
+            {"op":"text_hit","id":"obj_9","x":0,"y":0}]"#,
+    );
+    let caret = |response: &Response| match response {
+        Response::Ok { result } => {
+            let number = |key: &str| result[key].as_f64().unwrap_or(f64::NAN);
+            (result["index"].as_u64().unwrap_or(99), number("x1"), number("y1"), number("x2"), number("y2"))
+        }
+        other => panic!("no caret: {:?}", other),
+    };
+    // The caret spans the font's ascent above the baseline to its descent below
+    let (index, x1, y1, x2, y2) = caret(&batch.responses[1]);
+    assert_eq!((index, x1, x2), (0, 0.0, 0.0));
+    assert!(y1 < -20.0 && y2 > 4.0 && y2 < 8.0, "{} {}", y1, y2);
+    // Index 6 starts the second line
+    let (_, _, second_top, _, _) = caret(&batch.responses[2]);
+    assert!((second_top - y1 - 24.0 * crate::text_engine::LINE_HEIGHT).abs() < 1e-9);
+    let (_, moved_x, moved_top, _, _) = caret(&batch.responses[4]);
+    assert_eq!((moved_x, moved_top), (100.0, second_top + 50.0));
+
+    // A click just below the first line lands on the second one, far right at the end of the first
+    assert_eq!(caret(&batch.responses[5]).0, 6);
+    assert_eq!(caret(&batch.responses[6]).0, 5);
+    assert!(matches!(batch.responses[7], Response::Error { .. }));
+
+    // The hit index places the edit caret
+    assert!(editor.begin_text_edit("obj_1"));
+    assert!(editor.set_caret(2));
+    assert!(editor.insert_text("y"));
+    let properties: Value = serde_json::from_str(&editor.get_text_properties("obj_1")).unwrap_or_default();
+    assert_eq!(properties["content"], "Heyllo\nWorld");
+}
+
+#[test]
+fn test_convert_text_to_outlines() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_text","x":10,"y":20,"content":"Hi yo"},
+            {"op":"convert_text_to_outlines","id":"obj_1"},
+            {"op":"convert_text_to_outlines","id":"obj_1"}]"#,
+    );
+    assert!(matches!(batch.responses[1], Response::Ok { .. }));
+    // Once converted it is no longer text
+    assert!(matches!(batch.responses[2], Response::Error { .. }));
+    let saved: Value = serde_json::from_str(&editor.export_scene_to_json()).unwrap_or_default();
+    let leaf = &saved["roots"][0]["Leaf"];
+    assert_eq!(leaf["id"], "obj_1");
+    assert_eq!(leaf["object"]["Path"]["is_closed"], true);
+    assert!(leaf["object"]["Path"]["commands"].as_array().is_some_and(|commands| commands.len() > 10));
+
+    // Undo brings the text back; per glyph gives a group of named letters, blanks skipped
+    assert!(editor.undo());
+    assert!(editor.begin_text_edit("obj_1"));
+    assert!(editor.convert_text_to_outlines("obj_1", true));
+    assert!(!editor.insert_text("x"));
+    let saved: Value = serde_json::from_str(&editor.export_scene_to_json()).unwrap_or_default();
+    let group = &saved["roots"][0]["Group"];
+    assert_eq!(group["id"], "obj_1");
+    let names: Vec<&str> = group["children"]
+        .as_array()
+        .map(|children| children.iter().filter_map(|child| child["Leaf"]["name"].as_str()).collect())
+        .unwrap_or_default();
+    assert_eq!(names, ["H", "i", "y", "o"]);
+    assert_eq!(editor.get_selected_ids_json(), r#"["obj_1"]"#);
+}
+
+#[test]
+fn test_color_glyphs_convert_to_colored_paths() {
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    let mut editor = Editor::new();
+    editor.register_font(ROBOTO);
+    run(&mut editor, r#"[{"op":"add_text","x":0,"y":0,"content":"a"}]"#);
+    // Give the text a color glyph layer, as a color font would
+    let mut saved: Value = serde_json::from_str(&editor.export_scene_to_json()).unwrap_or_default();
+    let text = &mut saved["roots"][0]["Leaf"]["object"]["Text"];
+    text["color_layers"] = serde_json::json!([{ "color": "#ff0000", "commands": text["outline"].clone() }]);
+    assert!(editor.import_scene_checked(&saved.to_string()).is_ok());
+
+    assert!(editor.convert_text_to_outlines("obj_1", false));
+    let saved: Value = serde_json::from_str(&editor.export_scene_to_json()).unwrap_or_default();
+    let group = &saved["roots"][0]["Group"];
+    assert_eq!(group["id"], "obj_1");
+    let fills: Vec<&str> = group["children"]
+        .as_array()
+        .map(|children| children.iter().filter_map(|child| child["Leaf"]["style"]["fill_color"].as_str()).collect())
+        .unwrap_or_default();
+    assert_eq!(fills.len(), 2);
+    assert_eq!(fills[1], "#ff0000");
+}
+
+#[test]
+fn test_trace_image() {
+    let mut pixmap = tiny_skia::Pixmap::new(40, 30).expect("pixmap");
+    pixmap.fill(tiny_skia::Color::WHITE);
+    let mut paint = tiny_skia::Paint::default();
+    paint.set_color(tiny_skia::Color::BLACK);
+    let square = tiny_skia::Rect::from_xywh(5.0, 5.0, 20.0, 20.0).expect("rect");
+    pixmap.fill_rect(square, &paint, tiny_skia::Transform::identity(), None);
+    let png = pixmap.encode_png().expect("png");
+
+    let mut editor = Editor::new();
+    let batch = serde_json::json!([
+        { "op": "trace_image", "png": png, "options": { "scale": 0.5, "x": 10 } },
+        { "op": "get_selected_ids" },
+        { "op": "get_object_bounds", "id": "obj_1" },
+        { "op": "undo" },
+        { "op": "trace_image", "png": [1, 2, 3] }
+    ]);
+    let batch = run(&mut editor, &batch.to_string());
+    assert_eq!(batch.responses[0], Response::ok(serde_json::json!(["obj_1"])));
+    assert_eq!(batch.responses[1], Response::ok(serde_json::json!(["obj_1"])));
+    assert_eq!(batch.responses[2], Response::ok(serde_json::json!([12.5, 2.5, 22.5, 12.5])));
+    assert_eq!(batch.responses[4], Response::ok(serde_json::json!([])));
+    assert_eq!(editor.get_path_d("obj_1"), "");
+}
+
+#[test]
+fn test_copy_and_paste_style() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r##"[{"op":"copy_style_from_selection"},
+            {"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":20,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":40,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"update_style","fill":"#ff0000","stroke":"none","stroke_width":3},
+            {"op":"set_stroke_line_style","cap":"round","join":"bevel"},
+            {"op":"copy_style_from_selection"},
+            {"op":"select","ids":["obj_2","obj_3"]},
+            {"op":"paste_style_to_selection","style":{"fill_color":"#ff0000"}},
+            {"op":"paste_style_to_selection","style":{"fill_color":"#00ff00","stroke_color":null,"stroke_width":3,
+                "line_cap":"round","opacity":0.25,"shared_style":"style_9"}}]"##,
+    );
+    assert_eq!(batch.responses[0], Response::ok(""));
+    let Response::Ok { result: token } = &batch.responses[7] else {
+        panic!("copy failed");
+    };
+    assert_eq!(token["fill_color"], "#ff0000");
+    assert_eq!(token["line_join"], "bevel");
+    assert!(matches!(batch.responses[9], Response::Error { .. }));
+    assert_eq!(batch.responses[10], Response::ok(Value::Null));
+
+    let style: Value = serde_json::from_str(&editor.copy_style_from_selection()).expect("style json");
+    assert_eq!(style["fill_color"], "#00ff00");
+    assert_eq!(style["opacity"], 0.25);
+    assert!(style.get("shared_style").is_none());
+
+    // Both objects changed in a single step
+    assert!(editor.paste_style_to_selection(&token.to_string()));
+    assert!(editor.undo());
+    editor.select_ids(r#"["obj_3"]"#);
+    let style: Value = serde_json::from_str(&editor.copy_style_from_selection()).expect("style json");
+    assert_eq!(style["fill_color"], "#00ff00");
+    assert!(editor.undo());
+    editor.select_ids(r#"["obj_3"]"#);
+    let style: Value = serde_json::from_str(&editor.copy_style_from_selection()).expect("style json");
+    assert_eq!(style["fill_color"], "#3b82f6");
+}
+
+#[test]
+fn test_eyedropper() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r##"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":20,"y":0,"width":10,"height":10},
+            {"op":"add_circle","cx":25,"cy":5,"r":2},
+            {"op":"select","ids":["obj_3"]},
+            {"op":"update_style","fill":"#ff0000","stroke":"none","stroke_width":0},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"sample_style_at","x":25,"y":5},
+            {"op":"sample_style_at","x":100,"y":100},
+            {"op":"apply_sampled_style_to_selection","x":100,"y":100},
+            {"op":"apply_sampled_style_to_selection","x":25,"y":5},
+            {"op":"sample_style_at","x":5,"y":5},
+            {"op":"undo"},
+            {"op":"sample_style_at","x":5,"y":5}]"##,
+    );
+    // The top-most object wins
+    let Response::Ok { result: sampled } = &batch.responses[6] else {
+        panic!("sample failed");
+    };
+    assert_eq!(sampled["fill_color"], "#ff0000");
+    assert_eq!(batch.responses[7], Response::ok(""));
+    assert!(matches!(batch.responses[8], Response::Error { .. }));
+    assert_eq!(batch.responses[9], Response::ok(Value::Null));
+    assert_eq!(batch.responses[10], batch.responses[6]);
+    let Response::Ok { result: restored } = &batch.responses[12] else {
+        panic!("sample failed");
+    };
+    assert_eq!(restored["fill_color"], "#3b82f6");
+}
+
+#[test]
+fn test_css_colors_in_style_apis() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r##"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"select","ids":["obj_1"]},
+            {"op":"update_style","fill":"rgb(255, 102, 0)","stroke":"hsl(240, 100%, 50%)","stroke_width":1},
+            {"op":"update_style","fill":"tomato","stroke":"none","stroke_width":1},
+            {"op":"set_background_color","color":"rgba(0, 0, 0, 0.5)"},
+            {"op":"add_swatch","name":"Sky","color":"#0af"},
+            {"op":"get_swatches"},
+            {"op":"parse_color","color":"hsla(0, 100%, 50%, 0.5)"},
+            {"op":"adjust_color","color":"#ff0000","operation":"lighten","amount":0.25},
+            {"op":"adjust_color","color":"#ff0000","operation":"alpha","amount":0.5},
+            {"op":"adjust_color","color":"#ff0000","operation":"spin","amount":10},
+            {"op":"export_svg"}]"##,
+    );
+    assert!(matches!(batch.responses[3], Response::Error { .. }));
+    assert_eq!(batch.responses[6], Response::ok(serde_json::json!([{ "id": "swatch_2", "name": "Sky", "color": "#00aaff" }])));
+    let Response::Ok { result: parsed } = &batch.responses[7] else {
+        panic!("parse failed");
+    };
+    assert_eq!(parsed["hex"], "#ff0000");
+    assert_eq!(parsed["a"], 0.5);
+    assert_eq!(batch.responses[8], Response::ok("#ff8080"));
+    assert_eq!(batch.responses[9], Response::ok("rgba(255, 0, 0, 0.5)"));
+    assert!(matches!(batch.responses[10], Response::Error { .. }));
+
+    // Colors are stored normalized; the failed update left the style alone
+    let Response::Ok { result } = &batch.responses[11] else {
+        panic!("export failed");
+    };
+    let svg = result.as_str().unwrap_or_default();
+    assert!(svg.contains(r##"fill="#ff6600""##), "{}", svg);
+    assert!(svg.contains(r##"stroke="#0000ff""##), "{}", svg);
+    let properties: Value = serde_json::from_str(&editor.get_document_properties()).expect("properties json");
+    assert_eq!(properties["background"], "#000000");
+}
+
+#[test]
+fn test_swatches_and_shared_styles() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r##"[{"op":"add_swatch","name":"Brand","color":"#ff6600"},
+            {"op":"add_swatch","name":"Bad","color":"orange"},
+            {"op":"update_swatch","id":"swatch_1","name":"Brand orange","color":"#ff6611"},
+            {"op":"get_swatches"},
+            {"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":20,"y":0,"width":10,"height":10},
+            {"op":"add_rectangle","x":40,"y":0,"width":10,"height":10},
+            {"op":"add_shared_style","name":"Card","style":{"fill_color":"#ffffff","stroke_color":null,"stroke_width":0}},
+            {"op":"add_shared_style","name":"Bad","style":{"fill_color":"#ffffff"}},
+            {"op":"select","ids":["obj_3","obj_4"]},
+            {"op":"apply_shared_style","id":"style_5"},
+            {"op":"update_shared_style","id":"style_5","style":{"fill_color":"#00ff00","stroke_color":null,"stroke_width":0,"opacity":0.5}},
+            {"op":"get_shared_styles"},
+            {"op":"select","ids":["obj_4"]},
+            {"op":"detach_shared_style"},
+            {"op":"remove_shared_style","id":"style_5"},
+            {"op":"export_scene"}]"##,
+    );
+    assert_eq!(batch.responses[0], Response::ok("swatch_1"));
+    assert!(matches!(batch.responses[1], Response::Error { .. }));
+    assert_eq!(
+        batch.responses[3],
+        Response::ok(serde_json::json!([{ "id": "swatch_1", "name": "Brand orange", "color": "#ff6611" }]))
+    );
+    assert_eq!(batch.responses[7], Response::ok("style_5"));
+    assert!(matches!(batch.responses[8], Response::Error { .. }));
+    let Response::Ok { result: styles } = &batch.responses[12] else {
+        panic!("no styles");
+    };
+    assert_eq!(styles[0]["uses"], 2);
+    assert_eq!(styles[0]["style"]["fill_color"], "#00ff00");
+    assert_eq!(batch.responses[14], Response::ok(1));
+
+    // Linked objects follow the edit; removing the style only unlinks them
+    let Response::Ok { result: scene } = &batch.responses[16] else {
+        panic!("export failed");
+    };
+    let roots = scene["roots"].as_array().cloned().unwrap_or_default();
+    let style = |index: usize| roots[index]["Leaf"]["style"].clone();
+    assert_eq!(style(0)["fill_color"], "#3b82f6");
+    assert_eq!(style(1)["fill_color"], "#00ff00");
+    assert_eq!(style(1)["opacity"], 0.5);
+    assert_eq!(style(2)["fill_color"], "#00ff00");
+    assert!(style(1).get("shared_style").is_none());
+    assert!(scene.get("shared_styles").is_none());
+    assert_eq!(scene["swatches"][0]["color"], "#ff6611");
+
+    run(&mut editor, r#"[{"op":"undo"},{"op":"undo"}]"#);
+    let styles: Value = serde_json::from_str(&editor.get_shared_styles()).expect("styles json");
+    assert_eq!(styles[0]["uses"], 2);
+}
+
+#[test]
+fn test_artboards() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_artboard","name":"Icon 16","x":0,"y":0,"width":16,"height":16},
+            {"op":"add_artboard","name":"Icon 32","x":100,"y":0,"width":32,"height":32},
+            {"op":"add_artboard","x":0,"y":0,"width":0,"height":10},
+            {"op":"add_rectangle","x":2,"y":2,"width":10,"height":10},
+            {"op":"add_rectangle","x":104,"y":4,"width":20,"height":20},
+            {"op":"get_artboard_objects","id":"artboard_2"},
+            {"op":"export_artboard_svg","id":"artboard_2"},
+            {"op":"set_artboard_frame","id":"artboard_1","x":90,"y":0,"width":50,"height":50},
+            {"op":"get_artboard_objects","id":"artboard_2"},
+            {"op":"reorder_artboard","id":"artboard_2","index":0},
+            {"op":"get_artboard_objects","id":"artboard_2"},
+            {"op":"rename_artboard","id":"artboard_2","name":"Large"},
+            {"op":"remove_artboard","id":"artboard_1"},
+            {"op":"list_artboards"},
+            {"op":"undo"},
+            {"op":"list_artboards"},
+            {"op":"export_artboard_svg","id":"artboard_9"}]"#,
+    );
+    assert_eq!(batch.responses[0], Response::ok("artboard_1"));
+    assert!(matches!(batch.responses[2], Response::Error { .. }));
+    assert_eq!(batch.responses[5], Response::ok(serde_json::json!(["obj_4"])));
+    match &batch.responses[6] {
+        Response::Ok { result } => {
+            let svg = result.as_str().unwrap_or_default();
+            assert!(svg.contains(r#"viewBox="100 0 32 32""#), "{}", svg);
+            assert!(svg.contains("obj_4") && !svg.contains("obj_3"), "{}", svg);
+        }
+        other => panic!("export failed: {:?}", other),
+    }
+    // obj_4 lies on both frames and belongs to whichever comes first
+    assert_eq!(batch.responses[8], Response::ok(serde_json::json!([])));
+    assert_eq!(batch.responses[10], Response::ok(serde_json::json!(["obj_4"])));
+    assert_eq!(
+        batch.responses[13],
+        Response::ok(serde_json::json!([
+            { "id": "artboard_2", "name": "Large", "x": 100.0, "y": 0.0, "width": 32.0, "height": 32.0 }
+        ]))
+    );
+    match &batch.responses[15] {
+        Response::Ok { result } => assert_eq!(result.as_array().map(|a| a.len()), Some(2)),
+        other => panic!("unexpected response: {:?}", other),
+    }
+    assert!(matches!(batch.responses[16], Response::Error { .. }));
+    assert!(!editor.export_artboard_to_png("artboard_2").is_empty());
+}
+
+#[test]
+fn test_document_properties() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"set_units","units":"in"},
+            {"op":"set_canvas_size","width":2,"height":1},
+            {"op":"set_dpi","dpi":300},
+            {"op":"set_background_color","color":"none"},
+            {"op":"get_document_properties"},
+            {"op":"set_units","units":"pt"},
+            {"op":"set_canvas_size","width":0,"height":1},
+            {"op":"set_background_color","color":"blue"},
+            {"op":"undo"},
+            {"op":"get_document_properties"}]"#,
+    );
+    assert_eq!(
+        batch.responses[4],
+        Response::ok(serde_json::json!({
+            "width": 2.0, "height": 1.0, "width_px": 192.0, "height_px": 96.0,
+            "background": null, "units": "in", "dpi": 300.0
+        }))
+    );
+    assert!(matches!(batch.responses[5], Response::Error { .. }));
+    assert!(matches!(batch.responses[6], Response::Error { .. }));
+    assert!(matches!(batch.responses[7], Response::Error { .. }));
+    // Undoing the background brings the default page color back
+    match &batch.responses[9] {
+        Response::Ok { result } => assert_eq!(result["background"], "#1e1e1e"),
+        other => panic!("unexpected response: {:?}", other),
+    }
+
+    // Saved with the document
+    let saved = editor.export_scene_to_json();
+    let mut restored = Editor::new();
+    assert!(restored.import_scene_checked(&saved).is_ok());
+    assert_eq!(restored.get_document_properties(), editor.get_document_properties());
+    assert_eq!(restored.export_to_png().len(), editor.export_to_png().len());
+}
+
+#[test]
+fn test_quadratic_and_arc_paths() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+            {"op":"set_path_d","id":"obj_1","d":"M0,0 Q50,100 100,0 A50,50 0 0 0 0,0 Z"},
+            {"op":"get_path_d","id":"obj_1"},
+            {"op":"get_object_bounds","id":"obj_1"},
+            {"op":"hit_test","x":50,"y":30},
+            {"op":"hit_test","x":50,"y":-30},
+            {"op":"hit_test","x":50,"y":60}]"#,
+    );
+    assert_eq!(batch.responses[2], Response::ok("M0,0 Q50,100 100,0 A50,50 0 0 0 0,0 Z"));
+    // The quadratic peaks at y = 50 and the half circle reaches y = -50
+    let Response::Ok { result } = &batch.responses[3] else {
+        panic!("no bounds");
+    };
+    let bounds: Vec<f64> = serde_json::from_value(result.clone()).unwrap();
+    assert!((bounds[1] + 50.0).abs() < 0.1 && (bounds[3] - 50.0).abs() < 1e-9, "{:?}", bounds);
+    assert_eq!(batch.responses[4], Response::ok("obj_1"));
+    assert_eq!(batch.responses[5], Response::ok("obj_1"));
+    assert_eq!(batch.responses[6], Response::ok(""));
+
+    // Editing promotes both to cubic curves; the half circle gains a smooth anchor
+    let points: Vec<serde_json::Value> = serde_json::from_str(&editor.get_path_points("obj_1")).unwrap();
+    assert_eq!(points.len(), 4);
+    assert_eq!(editor.insert_path_point("obj_1", 0, 0.5), Some(1));
+    let Some(SceneNode::Leaf { object: VectorObject::Path { commands, anchor_types, .. }, .. }) = editor.scene.get_node_by_id("obj_1") else {
+        panic!("path is gone");
+    };
+    assert!(!geometry::has_quads_or_arcs(commands));
+    assert_eq!(anchor_types.len(), 5);
+}
+
+#[test]
+fn test_numeric_transform_setters() {
+    let mut editor = Editor::new();
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"add_rectangle","x":0,"y":0,"width":40,"height":20},
+            {"op":"set_size","id":"obj_1","width":80,"height":10},
+            {"op":"set_rotation","id":"obj_1","degrees":30},
+            {"op":"set_position","id":"obj_1","x":100,"y":200},
+            {"op":"get_transform_properties","id":"obj_1"},
+            {"op":"get_object_bounds","id":"obj_1"}]"#,
+    );
+    let value = |index: usize| match &batch.responses[index] {
+        Response::Ok { result } => result.clone(),
+        other => panic!("unexpected {:?}", other),
+    };
+    let props = value(4);
+    for (key, expected) in [("width", 80.0), ("height", 10.0), ("rotation", 30.0), ("x", 100.0), ("y", 200.0)] {
+        let actual = props[key].as_f64().unwrap();
+        assert!((actual - expected).abs() < 1e-9, "{} = {}", key, actual);
+    }
+    assert!((value(5)[0].as_f64().unwrap() - 100.0).abs() < 1e-9);
+}
+
+#[test]
+fn test_brush_speed_narrows_stroke() {
+    let mut editor = Editor::new();
+    // Timed samples narrow the stroke with the pen's speed (4 units/ms: half width)
+    let batch = run(
+        &mut editor,
+        r#"[{"op":"set_brush_width","width":10},
+            {"op":"brush_down","x":0,"y":0,"pressure":1,"time":100},
+            {"op":"brush_move","x":20,"y":0,"pressure":1,"time":105},
+            {"op":"get_brush_preview"},
+            {"op":"brush_up"}]"#,
+    );
+    assert_eq!(batch.responses[3], Response::ok(serde_json::json!([
+        { "x": 0.0, "y": 0.0, "radius": 5.0 },
+        { "x": 20.0, "y": 0.0, "radius": 2.5 }
+    ])));
+    assert!(matches!(&batch.responses[4], Response::Ok { result: Value::String(_) }));
+}
@@ -0,0 +1,161 @@
+//! Eraser Module - Removing paint along a freehand stroke
+//!
+//! The eraser sweeps a disc along the pointer samples. Filled shapes lose the
+//! swept region through a boolean subtract; open paths are cut where the
+//! region crosses them and keep only the parts outside it.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{LineCap, LineJoin, PathCommand};
+use crate::path_ops::{self, BooleanOp, Polygon, FLATTEN_TOLERANCE};
+use crate::stroke;
+
+/// One pointer sample of an eraser stroke
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct EraserSample {
+    pub x: f64,
+    pub y: f64,
+}
+
+/// What erasing did to one object
+#[derive(Debug, Clone, PartialEq)]
+pub enum Erased {
+    /// The region does not touch the object
+    Untouched,
+    /// Nothing is left of the object
+    Removed,
+    /// The remaining filled region of a closed shape
+    Shape(Vec<PathCommand>),
+    /// The remaining pieces of an open path, in path order
+    Pieces(Vec<Vec<PathCommand>>),
+}
+
+/// Region swept by a disc of the given radius along the samples
+pub fn eraser_region(samples: &[EraserSample], radius: f64) -> Vec<Polygon> {
+    if radius <= 0.0 || !radius.is_finite() {
+        return Vec::new();
+    }
+    let points: Vec<(f64, f64)> = samples
+        .iter()
+        .filter(|sample| sample.x.is_finite() && sample.y.is_finite())
+        .map(|sample| (sample.x, sample.y))
+        .collect();
+    let Some(&(x, y)) = points.first() else {
+        return Vec::new();
+    };
+    if points.len() == 1 {
+        return vec![stroke::circle((x, y), radius)];
+    }
+    let mut commands = vec![PathCommand::MoveTo { x, y }];
+    commands.extend(points[1..].iter().map(|&(x, y)| PathCommand::LineTo { x, y }));
+    stroke::outline_stroke(&commands, false, radius * 2.0, LineCap::Round, LineJoin::Round, stroke::CANVAS_MITER_LIMIT)
+}
+
+/// Region mapped through a transform (e.g. from world into an object's coordinates)
+pub fn transform_region(region: &[Polygon], transform: &TransformMatrix) -> Vec<Polygon> {
+    region
+        .iter()
+        .map(|polygon| polygon.iter().map(|&(x, y)| transform.transform_point(x, y)).collect())
+        .collect()
+}
+
+/// Erase a region from an object's path (both in the same coordinates)
+/// Closed shapes keep their filled area outside the region; open paths are split.
+/// Touched paths come back flattened to line segments.
+pub fn erase(commands: &[PathCommand], is_closed: bool, region: &[Polygon]) -> Erased {
+    if region.is_empty() {
+        return Erased::Untouched;
+    }
+    if is_closed {
+        let shape = path_ops::flatten(commands, FLATTEN_TOLERANCE);
+        let operands = [shape, region.to_vec()];
+        if path_ops::boolean(BooleanOp::Intersect, &operands).is_empty() {
+            return Erased::Untouched;
+        }
+        let remaining = path_ops::boolean(BooleanOp::Subtract, &operands);
+        if remaining.is_empty() {
+            return Erased::Removed;
+        }
+        return Erased::Shape(path_ops::polygons_to_commands(&remaining));
+    }
+
+    let mut touched = false;
+    let mut pieces = Vec::new();
+    for (mut points, closed) in path_ops::flatten_subpaths(commands, FLATTEN_TOLERANCE) {
+        if closed {
+            if let Some(&first) = points.first() {
+                points.push(first);
+            }
+        }
+        let runs = path_ops::clip_polyline(&points, region);
+        touched |= runs.len() != 1 || runs[0] != points;
+        pieces.extend(runs.into_iter().map(|run| polyline_commands(&run)));
+    }
+    if !touched {
+        Erased::Untouched
+    } else if pieces.is_empty() {
+        Erased::Removed
+    } else {
+        Erased::Pieces(pieces)
+    }
+}
+
+fn polyline_commands(points: &[(f64, f64)]) -> Vec<PathCommand> {
+    points
+        .iter()
+        .enumerate()
+        .map(|(i, &(x, y))| if i == 0 { PathCommand::MoveTo { x, y } } else { PathCommand::LineTo { x, y } })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(points: &[(f64, f64)]) -> Vec<EraserSample> {
+        points.iter().map(|&(x, y)| EraserSample { x, y }).collect()
+    }
+
+    fn rect(x: f64, y: f64, width: f64, height: f64) -> Vec<PathCommand> {
+        vec![
+            PathCommand::MoveTo { x, y },
+            PathCommand::LineTo { x: x + width, y },
+            PathCommand::LineTo { x: x + width, y: y + height },
+            PathCommand::LineTo { x, y: y + height },
+            PathCommand::ClosePath,
+        ]
+    }
+
+    #[test]
+    fn test_eraser_cuts_closed_shape_in_two() {
+        // A vertical swipe through the middle of a wide rectangle
+        let region = eraser_region(&samples(&[(50.0, -20.0), (50.0, 40.0)]), 5.0);
+        let Erased::Shape(commands) = erase(&rect(0.0, 0.0, 100.0, 20.0), true, &region) else {
+            panic!("expected a remaining shape");
+        };
+        let subpaths = commands.iter().filter(|command| matches!(command, PathCommand::MoveTo { .. })).count();
+        assert_eq!(subpaths, 2);
+    }
+
+    #[test]
+    fn test_eraser_removes_covered_shape() {
+        let region = eraser_region(&samples(&[(0.0, 5.0), (10.0, 5.0)]), 20.0);
+        assert_eq!(erase(&rect(0.0, 0.0, 10.0, 10.0), true, &region), Erased::Removed);
+        let far = eraser_region(&samples(&[(500.0, 500.0)]), 5.0);
+        assert_eq!(erase(&rect(0.0, 0.0, 10.0, 10.0), true, &far), Erased::Untouched);
+    }
+
+    #[test]
+    fn test_eraser_splits_open_path() {
+        let line = vec![PathCommand::MoveTo { x: 0.0, y: 0.0 }, PathCommand::LineTo { x: 100.0, y: 0.0 }];
+        let region = eraser_region(&samples(&[(50.0, -20.0), (50.0, 20.0)]), 5.0);
+        let Erased::Pieces(pieces) = erase(&line, false, &region) else {
+            panic!("expected pieces");
+        };
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0][0], PathCommand::MoveTo { x: 0.0, y: 0.0 });
+        assert_eq!(pieces[1].last(), Some(&PathCommand::LineTo { x: 100.0, y: 0.0 }));
+        assert_eq!(erase(&line, false, &eraser_region(&samples(&[(50.0, 50.0)]), 5.0)), Erased::Untouched);
+    }
+}
@@ -0,0 +1,298 @@
+//! Fonts Module - Loaded font files and their fallback order
+//!
+//! Fonts are registered by the frontend and are not saved with the document.
+//! Registered fonts get session IDs; text names its font by family, weight and
+//! style and is shaped with the closest registered font of that family.
+//! Characters that font has no glyph for are taken from the other registered
+//! fonts, in registration order, so mixed-script text does not render as
+//! missing-glyph boxes.
+//!
+//! Glyph outlines are cached per font, size and glyph, so reshaping text (while
+//! typing, or after a style change) only extracts glyphs it has not drawn before.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use ttf_parser::{name_id, Face};
+
+use crate::core::scene::{TextFont, TextObject};
+use crate::text_engine::{self, TextLine, TextOutline};
+
+/// Family, weight and style read from a font file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FontInfo {
+    pub id: String,
+    pub family: String,
+    /// Style name from the font ("Regular", "Bold Italic", ...)
+    pub subfamily: String,
+    /// CSS weight (100..900)
+    pub weight: u16,
+    pub italic: bool,
+}
+
+/// A registered font file
+#[derive(Debug, Clone)]
+struct LoadedFont {
+    info: FontInfo,
+    data: Vec<u8>,
+    /// Normalized variation axis coordinates glyphs are outlined at (the default instance)
+    coords: Vec<i16>,
+}
+
+/// Identity of a cached glyph outline
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct GlyphKey {
+    font_id: String,
+    /// Em size (bits of the f64)
+    size: u64,
+    glyph_id: u16,
+    /// Turned for vertical text
+    sideways: bool,
+    coords: Vec<i16>,
+}
+
+/// Glyph outline cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct GlyphCacheStats {
+    /// Glyphs cached
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of lookups served from the cache (0 before the first lookup)
+    pub hit_rate: f64,
+}
+
+/// Fonts available to text objects, in registration order
+#[derive(Debug, Clone, Default)]
+pub struct FontRegistry {
+    fonts: Vec<LoadedFont>,
+    next_id: usize,
+    /// Glyph outlines with the pen at the origin
+    glyphs: HashMap<GlyphKey, TextOutline>,
+    hits: u64,
+    misses: u64,
+}
+
+impl FontRegistry {
+    pub fn new() -> Self {
+        FontRegistry::default()
+    }
+
+    /// Register a TTF/OTF file; returns its ID, or None if the data is not a font
+    pub fn register(&mut self, data: &[u8]) -> Option<String> {
+        let face = Face::parse(data, 0).ok()?;
+        self.next_id += 1;
+        let id = format!("font_{}", self.next_id);
+        let family = font_name(&face, &[name_id::TYPOGRAPHIC_FAMILY, name_id::FAMILY]).unwrap_or_else(|| id.clone());
+        let subfamily =
+            font_name(&face, &[name_id::TYPOGRAPHIC_SUBFAMILY, name_id::SUBFAMILY]).unwrap_or_else(|| "Regular".to_string());
+        let info = FontInfo { id: id.clone(), family, subfamily, weight: face.weight().to_number(), italic: face.is_italic() };
+        let coords = face.variation_coordinates().iter().map(|coord| coord.get()).collect();
+        self.fonts.push(LoadedFont { info, data: data.to_vec(), coords });
+        Some(id)
+    }
+
+    /// Remove a font and its cached glyphs; text using it keeps its last outlines.
+    /// Returns false for an unknown ID
+    pub fn unregister(&mut self, id: &str) -> bool {
+        let count = self.fonts.len();
+        self.fonts.retain(|font| font.info.id != id);
+        self.glyphs.retain(|key, _| key.font_id != id);
+        self.fonts.len() < count
+    }
+
+    pub fn data(&self, id: &str) -> Option<&[u8]> {
+        self.fonts.iter().find(|font| font.info.id == id).map(|font| font.data.as_slice())
+    }
+
+    /// Family, weight and style of the first registered font (the default for new text)
+    pub fn first_font(&self) -> Option<TextFont> {
+        self.fonts.first().map(|font| font.info.text_font())
+    }
+
+    /// Family, weight and style of a registered font
+    pub fn text_font(&self, id: &str) -> Option<TextFont> {
+        self.fonts.iter().find(|font| font.info.id == id).map(|font| font.info.text_font())
+    }
+
+    pub fn infos(&self) -> Vec<&FontInfo> {
+        self.fonts.iter().map(|font| &font.info).collect()
+    }
+
+    /// Font data to shape text set in `id` with: that font first, then the other
+    /// registered fonts as fallbacks. None if `id` is not registered
+    pub fn fallback_chain(&self, id: &str) -> Option<Vec<&[u8]>> {
+        Some(self.chain_fonts(id)?.into_iter().map(|font| font.data.as_slice()).collect())
+    }
+
+    fn chain_fonts(&self, id: &str) -> Option<Vec<&LoadedFont>> {
+        let primary = self.fonts.iter().find(|font| font.info.id == id)?;
+        let fallbacks = self.fonts.iter().filter(|font| font.info.id != id);
+        Some(std::iter::once(primary).chain(fallbacks).collect())
+    }
+
+    /// Fallback chain of the font a text object resolves to (see [`FontRegistry::resolve`])
+    pub fn text_chain(&self, text: &TextObject) -> Option<Vec<&[u8]>> {
+        self.fallback_chain(self.resolve(&text.font)?)
+    }
+
+    /// Outline of a text object laid out with its fallback chain (see
+    /// [`text_engine::text_outline`]), glyphs taken from the cache.
+    /// None if no font of its family is registered
+    pub fn text_outline(&mut self, text: &TextObject) -> Option<TextOutline> {
+        let font_id = self.resolve(&text.font)?.to_string();
+        let lines = text_engine::layout_text(&self.fallback_chain(&font_id)?, text)?;
+        self.lines_outline(&font_id, &lines, text.size, text.x, text.y)
+    }
+
+    /// Outline of lines laid out with the fallback chain of `font_id` (see
+    /// [`text_engine::lines_outline`]), glyphs taken from the cache
+    pub fn lines_outline(&mut self, font_id: &str, lines: &[TextLine], size: f64, x: f64, y: f64) -> Option<TextOutline> {
+        let chain: Vec<(String, Vec<i16>)> =
+            self.chain_fonts(font_id)?.into_iter().map(|font| (font.info.id.clone(), font.coords.clone())).collect();
+        let mut outline = TextOutline::default();
+        for glyph in lines.iter().flat_map(|line| &line.glyphs) {
+            let (font_id, coords) = chain.get(glyph.font)?;
+            let key = GlyphKey {
+                font_id: font_id.clone(),
+                size: size.to_bits(),
+                glyph_id: glyph.glyph_id,
+                sideways: glyph.sideways,
+                coords: coords.clone(),
+            };
+            let cached = match self.glyphs.get(&key) {
+                Some(cached) => {
+                    self.hits += 1;
+                    cached
+                }
+                None => {
+                    self.misses += 1;
+                    let extracted = text_engine::glyph_outline(self.data(font_id)?, glyph.glyph_id, size, glyph.sideways)?;
+                    self.glyphs.entry(key).or_insert(extracted)
+                }
+            };
+            let placed = cached.translated(x + glyph.x, y + glyph.y);
+            outline.commands.extend(placed.commands);
+            outline.color_layers.extend(placed.color_layers);
+        }
+        Some(outline)
+    }
+
+    pub fn glyph_cache_stats(&self) -> GlyphCacheStats {
+        let lookups = self.hits + self.misses;
+        GlyphCacheStats {
+            entries: self.glyphs.len(),
+            hits: self.hits,
+            misses: self.misses,
+            hit_rate: if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 },
+        }
+    }
+
+    /// Registered font closest to a family, weight and style
+    /// The family must match (ignoring case); then the style, then the nearest weight.
+    pub fn find(&self, family: &str, weight: u16, italic: bool) -> Option<&str> {
+        self.fonts
+            .iter()
+            .filter(|font| font.info.family.eq_ignore_ascii_case(family))
+            .min_by_key(|font| (font.info.italic != italic, font.info.weight.abs_diff(weight)))
+            .map(|font| font.info.id.as_str())
+    }
+
+    /// ID of the registered font text set in `font` is shaped with (see [`FontRegistry::find`])
+    pub fn resolve(&self, font: &TextFont) -> Option<&str> {
+        self.find(&font.family, font.weight, font.italic)
+    }
+}
+
+impl FontInfo {
+    fn text_font(&self) -> TextFont {
+        TextFont { family: self.family.clone(), weight: self.weight, italic: self.italic }
+    }
+}
+
+/// First readable name among `ids`, in order of preference
+fn font_name(face: &Face, ids: &[u16]) -> Option<String> {
+    ids.iter().find_map(|&id| {
+        face.names().into_iter().filter(|name| name.name_id == id && name.is_unicode()).find_map(|name| name.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+    const ROBOTO_ITALIC: &[u8] = include_bytes!("../../public/Roboto-Italic-VariableFont_wdth,wght.ttf");
+    const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+
+    #[test]
+    fn test_registry_reads_metadata() {
+        let mut fonts = FontRegistry::new();
+        assert_eq!(fonts.register(&[1, 2, 3]), None);
+        assert_eq!(fonts.register(ROBOTO).as_deref(), Some("font_1"));
+        assert_eq!(fonts.register(ROBOTO_ITALIC).as_deref(), Some("font_2"));
+        let infos = fonts.infos();
+        assert_eq!(infos[0].family, "Roboto");
+        assert_eq!((infos[0].weight, infos[0].italic), (400, false));
+        assert!(infos[1].italic);
+        assert_eq!(fonts.find("roboto", 700, true), Some("font_2"));
+        assert_eq!(fonts.find("Roboto", 400, false), Some("font_1"));
+        assert_eq!(fonts.find("Noto Sans JP", 400, false), None);
+    }
+
+    #[test]
+    fn test_fallback_chain_starts_with_the_requested_font() {
+        let mut fonts = FontRegistry::new();
+        fonts.register(ROBOTO);
+        fonts.register(NOTO_JP);
+        let chain = fonts.fallback_chain("font_2").unwrap();
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain[0].len(), NOTO_JP.len());
+        assert!(fonts.fallback_chain("font_9").is_none());
+
+        // IDs are not reused after a font is removed
+        assert!(fonts.unregister("font_1"));
+        assert!(!fonts.unregister("font_1"));
+        assert_eq!(fonts.register(ROBOTO).as_deref(), Some("font_3"));
+        assert_eq!(fonts.first_font().map(|font| font.family), Some("Noto Sans JP".to_string()));
+    }
+
+    #[test]
+    fn test_glyph_cache_reuses_outlines() {
+        let mut fonts = FontRegistry::new();
+        fonts.register(ROBOTO);
+        let text = TextObject {
+            content: "noon".to_string(),
+            font: TextFont { family: "Roboto".to_string(), weight: 400, italic: false },
+            size: 24.0,
+            x: 10.0,
+            y: 30.0,
+            paragraph: Default::default(),
+            typography: Default::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        // Cached glyphs are placed exactly like freshly extracted ones
+        let outline = fonts.text_outline(&text).unwrap();
+        assert_eq!(Some(&outline), text_engine::text_outline(&[ROBOTO], &text).as_ref());
+        let stats = fonts.glyph_cache_stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 2, 2));
+
+        // Drawing it again hits for every glyph; another size is new glyphs
+        fonts.text_outline(&text).unwrap();
+        fonts.text_outline(&TextObject { size: 12.0, ..text.clone() }).unwrap();
+        let stats = fonts.glyph_cache_stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (4, 8, 4));
+        assert!((stats.hit_rate - 8.0 / 12.0).abs() < 1e-12);
+
+        // Removing the font drops its glyphs
+        assert!(fonts.unregister("font_1"));
+        assert_eq!(fonts.glyph_cache_stats().entries, 0);
+        assert!(fonts.text_outline(&text).is_none());
+        // Text resolves to the closest registered font of its family
+        fonts.register(ROBOTO_ITALIC);
+        let italic = TextObject { font: TextFont { italic: true, ..text.font.clone() }, ..text.clone() };
+        assert_eq!(fonts.resolve(&italic.font), Some("font_2"));
+        assert_eq!(fonts.resolve(&text.font), Some("font_2"));
+        assert!(fonts.text_outline(&text).is_some());
+    }
+}
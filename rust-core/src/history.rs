@@ -0,0 +1,413 @@
+//! History Module - Labeled undo steps
+//!
+//! Every undo step carries a label naming the edit it undoes ("Delete 3 objects",
+//! "Boolean union"), so a history panel can list the steps and jump between them.
+//! The most recent steps can be saved with the document, under "history", and are
+//! restored when it is imported.
+//!
+//! Steps are whole scenes, but a top-level node that did not change between
+//! neighbouring steps is stored once and shared between them, so a step costs about
+//! as much memory as the nodes its edit touched. Each stored part remembers its size
+//! (as serialized), which lets the editor hold the history to a byte budget. Saved
+//! history keeps the sharing: every part is written once and steps refer to parts by
+//! index.
+
+use serde::{Deserialize, Serialize, Serializer};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::rc::Rc;
+use tsify::Tsify;
+
+use crate::core::scene::{SceneGraph, SceneNode};
+use crate::migration;
+use crate::validation::{self, ImportError, ImportErrorCode, ImportLimits};
+
+/// Label of steps recorded without one (e.g. by a plain save_snapshot)
+pub const DEFAULT_LABEL: &str = "Edit";
+
+/// One undo or redo step: the scene on the other side of an edit
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    pub label: String,
+    /// The scene without its top-level nodes (see [`SceneGraph::clone_settings`])
+    settings: Rc<Stored<SceneGraph>>,
+    roots: Vec<Rc<Stored<SceneNode>>>,
+    /// Kept out of the settings, so generating IDs does not stop them being shared
+    id_counter: u64,
+}
+
+/// A part of a scene held by history entries
+#[derive(Debug)]
+struct Stored<T> {
+    value: T,
+    bytes: usize,
+}
+
+impl HistoryEntry {
+    /// Record a scene, sharing the parts that are unchanged from `neighbour` (the
+    /// step next to it in the history)
+    pub fn new(label: impl Into<String>, scene: &SceneGraph, neighbour: Option<&HistoryEntry>) -> Self {
+        let settings = scene.clone_settings();
+        let previous: HashMap<&str, &Rc<Stored<SceneNode>>> = neighbour
+            .map(|entry| entry.roots.iter().map(|stored| (stored.value.id().as_str(), stored)).collect())
+            .unwrap_or_default();
+        let roots = scene
+            .roots
+            .iter()
+            .map(|node| share(node, previous.get(node.id().as_str()).copied()))
+            .collect();
+        HistoryEntry {
+            label: label.into(),
+            settings: match neighbour {
+                Some(entry) if entry.settings.value == settings => Rc::clone(&entry.settings),
+                _ => Rc::new(Stored { bytes: serialized_size(&settings), value: settings }),
+            },
+            roots,
+            id_counter: scene.id_counter(),
+        }
+    }
+
+    /// The recorded scene
+    pub fn to_scene(&self) -> SceneGraph {
+        let mut scene = self.settings.value.clone_settings();
+        scene.roots = self.roots.iter().map(|stored| stored.value.clone()).collect();
+        scene.set_id_counter(self.id_counter);
+        scene
+    }
+
+    /// Estimated bytes held by this entry alone, which dropping it frees
+    pub fn unshared_bytes(&self) -> usize {
+        let settings = std::iter::once((Rc::strong_count(&self.settings), self.settings.bytes));
+        let roots = self.roots.iter().map(|stored| (Rc::strong_count(stored), stored.bytes));
+        settings.chain(roots).filter(|(owners, _)| *owners == 1).map(|(_, bytes)| bytes).sum()
+    }
+
+    /// Identity and size of each stored part
+    fn parts(&self) -> impl Iterator<Item = (*const (), usize)> + '_ {
+        let settings = std::iter::once((Rc::as_ptr(&self.settings) as *const (), self.settings.bytes));
+        settings.chain(self.roots.iter().map(|stored| (Rc::as_ptr(stored) as *const (), stored.bytes)))
+    }
+}
+
+/// The neighbour's copy of a node if it is unchanged, or a new one
+fn share(node: &SceneNode, previous: Option<&Rc<Stored<SceneNode>>>) -> Rc<Stored<SceneNode>> {
+    match previous {
+        Some(stored) if stored.value == *node => Rc::clone(stored),
+        _ => Rc::new(Stored { value: node.clone(), bytes: serialized_size(node) }),
+    }
+}
+
+/// Length of a value's JSON, as an estimate of the memory it holds
+fn serialized_size<T: Serialize>(value: &T) -> usize {
+    struct Counter(usize);
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+    let mut counter = Counter(0);
+    match serde_json::to_writer(&mut counter, value) {
+        Ok(()) => counter.0,
+        Err(_) => 0,
+    }
+}
+
+/// Estimated bytes held by history entries, counting shared parts once
+pub fn memory_usage<'a>(entries: impl IntoIterator<Item = &'a HistoryEntry>) -> usize {
+    let mut seen = HashSet::new();
+    entries
+        .into_iter()
+        .flat_map(HistoryEntry::parts)
+        .filter(|(part, _)| seen.insert(*part))
+        .map(|(_, bytes)| bytes)
+        .sum()
+}
+
+/// The history as a panel lists it
+#[derive(Debug, Clone, PartialEq, Serialize, Tsify)]
+pub struct HistoryListing {
+    /// Labels of every step, oldest first: the undoable steps, then the redoable ones
+    pub entries: Vec<String>,
+    /// Number of steps applied; entries from this index on have been undone
+    pub current: usize,
+}
+
+/// List an undo stack and a redo stack (both with the next step to take last)
+pub fn listing(undo_stack: &[HistoryEntry], redo_stack: &[HistoryEntry]) -> HistoryListing {
+    HistoryListing {
+        entries: undo_stack
+            .iter()
+            .chain(redo_stack.iter().rev())
+            .map(|entry| entry.label.clone())
+            .collect(),
+        current: undo_stack.len(),
+    }
+}
+
+/// History saved in a document: both stacks in stack order (next step to take last)
+/// Serializes as { settings: [...], nodes: [...], undo: [step, ...], redo: [step, ...] },
+/// each step { label, settings, roots, id_counter } indexing into the shared parts.
+#[derive(Debug, Clone)]
+pub struct SavedHistory {
+    pub undo: Vec<HistoryEntry>,
+    pub redo: Vec<HistoryEntry>,
+}
+
+/// A saved step: its parts as indices into the saved settings and nodes
+#[derive(Serialize, Deserialize)]
+struct SavedStep {
+    label: String,
+    settings: usize,
+    roots: Vec<usize>,
+    #[serde(default)]
+    id_counter: u64,
+}
+
+/// Estimated bytes a saved step takes besides its parts
+fn step_overhead(entry: &HistoryEntry) -> usize {
+    64 + entry.label.len() + 8 * entry.roots.len()
+}
+
+impl Serialize for SavedHistory {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Saved<'a> {
+            settings: Vec<&'a SceneGraph>,
+            nodes: Vec<&'a SceneNode>,
+            undo: Vec<SavedStep>,
+            redo: Vec<SavedStep>,
+        }
+        fn steps<'a>(entries: &'a [HistoryEntry], settings: &mut Parts<'a, SceneGraph>, nodes: &mut Parts<'a, SceneNode>) -> Vec<SavedStep> {
+            entries
+                .iter()
+                .map(|entry| SavedStep {
+                    label: entry.label.clone(),
+                    settings: settings.index(&entry.settings),
+                    roots: entry.roots.iter().map(|stored| nodes.index(stored)).collect(),
+                    id_counter: entry.id_counter,
+                })
+                .collect()
+        }
+        let (mut settings, mut nodes) = (Parts::default(), Parts::default());
+        let undo = steps(&self.undo, &mut settings, &mut nodes);
+        let redo = steps(&self.redo, &mut settings, &mut nodes);
+        Saved { settings: settings.values, nodes: nodes.values, undo, redo }.serialize(serializer)
+    }
+}
+
+/// Distinct stored parts in the order they were first met
+struct Parts<'a, T> {
+    indices: HashMap<*const Stored<T>, usize>,
+    values: Vec<&'a T>,
+}
+
+impl<T> Default for Parts<'_, T> {
+    fn default() -> Self {
+        Parts { indices: HashMap::new(), values: Vec::new() }
+    }
+}
+
+impl<'a, T> Parts<'a, T> {
+    fn index(&mut self, stored: &'a Rc<Stored<T>>) -> usize {
+        let values = &mut self.values;
+        *self.indices.entry(Rc::as_ptr(stored)).or_insert_with(|| {
+            values.push(&stored.value);
+            values.len() - 1
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct StoredHistory {
+    #[serde(default)]
+    settings: Vec<Value>,
+    #[serde(default)]
+    nodes: Vec<Value>,
+    #[serde(default)]
+    undo: Vec<SavedStep>,
+    #[serde(default)]
+    redo: Vec<SavedStep>,
+}
+
+/// The steps nearest the current position whose saved size (parts shared between
+/// steps counted once) fits in `max_bytes`: undo steps first, then redo steps
+pub fn saved(undo_stack: &[HistoryEntry], redo_stack: &[HistoryEntry], max_bytes: usize) -> SavedHistory {
+    let mut seen = HashSet::new();
+    let mut left = max_bytes;
+    let mut fits = |entry: &HistoryEntry| {
+        let new_parts: Vec<(*const (), usize)> = entry.parts().filter(|(part, _)| !seen.contains(part)).collect();
+        let bytes = step_overhead(entry) + new_parts.iter().map(|(_, bytes)| bytes).sum::<usize>();
+        if bytes > left {
+            return false;
+        }
+        left -= bytes;
+        seen.extend(new_parts.into_iter().map(|(part, _)| part));
+        true
+    };
+    let mut nearest = |stack: &[HistoryEntry]| {
+        let kept = stack.iter().rev().take_while(|entry| fits(entry)).count();
+        stack[stack.len() - kept..].to_vec()
+    };
+    SavedHistory { undo: nearest(undo_stack), redo: nearest(redo_stack) }
+}
+
+/// Read saved history back, migrating and checking each step's scene like an
+/// imported document
+pub fn restore(history: Value, limits: &ImportLimits) -> Result<SavedHistory, ImportError> {
+    let stored: StoredHistory = serde_json::from_value(history).map_err(|e| {
+        ImportError::new(ImportErrorCode::ParseError, format!("invalid history: {}", e))
+    })?;
+    let entries = |steps: Vec<SavedStep>| {
+        let mut entries: Vec<HistoryEntry> = Vec::new();
+        for SavedStep { label, settings, roots, id_counter } in steps {
+            let missing = || ImportError::new(ImportErrorCode::ParseError, format!("history step '{}' refers to a missing part", label));
+            let Some(Value::Object(mut scene)) = stored.settings.get(settings).cloned() else {
+                return Err(missing());
+            };
+            let roots = roots.iter().map(|&index| stored.nodes.get(index).cloned()).collect::<Option<Vec<Value>>>().ok_or_else(missing)?;
+            scene.insert("roots".to_string(), Value::Array(roots));
+            scene.insert("id_counter".to_string(), Value::from(id_counter));
+            let mut scene = Value::Object(scene);
+            migration::migrate(&mut scene)?;
+            let scene = serde_json::from_value::<SceneGraph>(scene).map_err(|e| {
+                ImportError::new(ImportErrorCode::ParseError, format!("invalid history step '{}': {}", label, e))
+            })?;
+            validation::validate_scene(&scene, limits)?;
+            let entry = HistoryEntry::new(label, &scene, entries.last());
+            entries.push(entry);
+        }
+        Ok::<_, ImportError>(entries)
+    };
+    Ok(SavedHistory { undo: entries(stored.undo)?, redo: entries(stored.redo)? })
+}
+
+/// History label for an edit of `count` objects ("Move 3 objects")
+pub fn objects_label(action: &str, count: usize) -> String {
+    match count {
+        1 => format!("{} object", action),
+        _ => format!("{} {} objects", action, count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::VectorObject;
+
+    fn entry(label: &str) -> HistoryEntry {
+        HistoryEntry::new(label, &SceneGraph::new(), None)
+    }
+
+    fn rect(scene: &mut SceneGraph, x: f64) -> String {
+        let id = scene.generate_id();
+        scene.add_object(id.clone(), VectorObject::Rectangle { x, y: 0.0, width: 10.0, height: 10.0 }, TransformMatrix::identity());
+        id
+    }
+
+    #[test]
+    fn test_unchanged_nodes_are_shared_between_steps() {
+        let mut scene = SceneGraph::new();
+        for i in 0..10 {
+            rect(&mut scene, i as f64 * 20.0);
+        }
+        let first = HistoryEntry::new("a", &scene, None);
+        let alone = memory_usage([&first]);
+        assert!(alone > 0);
+
+        // Moving one of ten nodes stores only that node again
+        *scene.roots[3].transform_mut() = TransformMatrix::translate(5.0, 0.0);
+        let second = HistoryEntry::new("b", &scene, Some(&first));
+        let both = memory_usage([&first, &second]);
+        assert!(both > alone && both < alone + alone / 5, "{} then {}", alone, both);
+
+        // Sharing never changes what a step restores
+        assert_eq!(second.to_scene(), scene);
+        assert_ne!(first.to_scene(), scene);
+    }
+
+    #[test]
+    fn test_generated_ids_keep_settings_shared() {
+        let mut scene = SceneGraph::new();
+        let first = HistoryEntry::new("a", &scene, None);
+        let id = rect(&mut scene, 0.0);
+        let second = HistoryEntry::new("b", &scene, Some(&first));
+        // Only the new node is stored again, and dropping the step frees just that
+        let node = serialized_size(&scene.roots[0]);
+        assert_eq!(memory_usage([&first, &second]), memory_usage([&first]) + node);
+        assert_eq!(second.unshared_bytes(), node);
+
+        // IDs generated after an undo continue from the step's counter
+        assert_ne!(second.to_scene().generate_id(), id);
+        assert_eq!(first.to_scene().generate_id(), id);
+    }
+
+    #[test]
+    fn test_listing_puts_redo_steps_after_the_current_position() {
+        let listing = listing(&[entry("a"), entry("b")], &[entry("d"), entry("c")]);
+        assert_eq!(listing.entries, vec!["a", "b", "c", "d"]);
+        assert_eq!(listing.current, 2);
+    }
+
+    #[test]
+    fn test_saved_history_keeps_the_nearest_steps_and_restores() {
+        let stack = |labels: &[&str]| labels.iter().map(|label| entry(label)).collect::<Vec<_>>();
+        let (undo, redo) = (stack(&["a", "b", "c"]), stack(&["e", "d"]));
+        let labels = |entries: &[HistoryEntry]| entries.iter().map(|entry| entry.label.clone()).collect::<Vec<_>>();
+        // Room for four steps: the undo steps nearest the current position come first
+        let step = undo[0].settings.bytes + step_overhead(&undo[0]);
+        let saved = saved(&undo, &redo, 4 * step);
+        assert_eq!(labels(&saved.undo), vec!["a", "b", "c"]);
+        assert_eq!(labels(&saved.redo), vec!["d"]);
+        let saved = super::saved(&undo, &redo, 2 * step);
+        assert_eq!(labels(&saved.undo), vec!["b", "c"]);
+        assert!(saved.redo.is_empty());
+
+        let restored = restore(serde_json::to_value(&saved).unwrap(), &ImportLimits::default()).unwrap();
+        assert_eq!(labels(&restored.undo), vec!["b", "c"]);
+        assert!(restored.redo.is_empty());
+    }
+
+    #[test]
+    fn test_saved_history_stores_shared_nodes_once() {
+        let mut scene = SceneGraph::new();
+        for i in 0..10 {
+            rect(&mut scene, i as f64 * 20.0);
+        }
+        let mut entries = vec![HistoryEntry::new("a", &scene, None)];
+        for i in 0..5 {
+            *scene.roots[i].transform_mut() = TransformMatrix::translate(5.0, 0.0);
+            let entry = HistoryEntry::new("b", &scene, entries.last());
+            entries.push(entry);
+        }
+        let saved = saved(&entries, &[], usize::MAX);
+        let json = serde_json::to_value(&saved).unwrap();
+        // Ten nodes, then one more for each step that moved one
+        assert_eq!(json["nodes"].as_array().map(Vec::len), Some(15));
+        let full_copies: usize = entries.iter().map(|entry| serialized_size(&entry.to_scene())).sum();
+        assert!(json.to_string().len() < full_copies / 2);
+
+        let restored = restore(json, &ImportLimits::default()).unwrap();
+        assert!(restored.undo.iter().zip(&entries).all(|(restored, entry)| restored.to_scene() == entry.to_scene()));
+        assert_eq!(memory_usage(&restored.undo), memory_usage(&entries));
+    }
+
+    #[test]
+    fn test_restore_checks_each_step() {
+        let history = serde_json::json!({ "settings": [{ "roots": [] }], "undo": [{ "label": "a", "settings": 0, "roots": [3] }] });
+        assert_eq!(restore(history, &ImportLimits::default()).unwrap_err().code, ImportErrorCode::ParseError);
+        let history = serde_json::json!({ "settings": [{ "format_version": 999, "roots": [] }], "undo": [{ "label": "a", "settings": 0, "roots": [] }] });
+        assert_eq!(restore(history, &ImportLimits::default()).unwrap_err().code, ImportErrorCode::UnsupportedVersion);
+    }
+
+    #[test]
+    fn test_objects_label() {
+        assert_eq!(objects_label("Delete", 1), "Delete object");
+        assert_eq!(objects_label("Move", 3), "Move 3 objects");
+    }
+}
@@ -2,8 +2,10 @@
 //!
 //! Provides precise hit testing for vector objects using inverse transforms.
 
+use crate::core::geometry;
 use crate::core::math::TransformMatrix;
-use crate::core::scene::{PathCommand, VectorObject};
+use crate::core::scene::{FillRule, PathCommand, VectorObject};
+use crate::path_ops::{self, FLATTEN_TOLERANCE};
 
 /// Check if a point is inside a rectangle (in local coordinates)
 pub fn point_in_rect(x: f64, y: f64, rect_x: f64, rect_y: f64, width: f64, height: f64) -> bool {
@@ -20,6 +22,16 @@ pub fn point_in_ellipse(x: f64, y: f64, cx: f64, cy: f64, rx: f64, ry: f64) -> b
     dx * dx + dy * dy <= 1.0
 }
 
+/// Check if a point is inside a circle (in local coordinates)
+pub fn point_in_circle(x: f64, y: f64, cx: f64, cy: f64, r: f64) -> bool {
+    if r <= 0.0 {
+        return false;
+    }
+    let dx = x - cx;
+    let dy = y - cy;
+    dx * dx + dy * dy <= r * r
+}
+
 /// Check if a point is inside a path's bounding box (in local coordinates)
 /// Uses a simple bounding box approach - calculates min/max from all points in path
 pub fn point_in_path_bounds(x: f64, y: f64, commands: &[PathCommand]) -> bool {
@@ -40,7 +52,8 @@ pub fn point_in_path_bounds(x: f64, y: f64, commands: &[PathCommand]) -> bool {
         max_y = max_y.max(py);
     };
     
-    for cmd in commands {
+    // Quadratics and arcs are measured by the control points of their cubic form
+    for cmd in &geometry::to_cubic_commands(commands) {
         match cmd {
             PathCommand::MoveTo { x, y } => update_bounds(*x, *y),
             PathCommand::LineTo { x, y } => update_bounds(*x, *y),
@@ -50,7 +63,7 @@ pub fn point_in_path_bounds(x: f64, y: f64, commands: &[PathCommand]) -> bool {
                 update_bounds(*x2, *y2);
                 update_bounds(*x, *y);
             }
-            PathCommand::ClosePath => {}
+            PathCommand::QuadTo { .. } | PathCommand::ArcTo { .. } | PathCommand::ClosePath => {}
         }
     }
     
@@ -58,12 +71,23 @@ pub fn point_in_path_bounds(x: f64, y: f64, commands: &[PathCommand]) -> bool {
     x >= min_x && x <= max_x && y >= min_y && y <= max_y
 }
 
+/// Check if a point is inside a path's filled area (in local coordinates)
+/// Open subpaths are closed, as they are when filled.
+pub fn point_in_path(x: f64, y: f64, commands: &[PathCommand], fill_rule: FillRule) -> bool {
+    if !point_in_path_bounds(x, y, commands) {
+        return false;
+    }
+    let polygons = path_ops::flatten(commands, FLATTEN_TOLERANCE);
+    fill_rule.is_inside(path_ops::winding_number(&polygons, (x, y)))
+}
+
 /// Test if a world point hits a vector object with the given transform
 pub fn hit_test_object(
     world_x: f64,
     world_y: f64,
     object: &VectorObject,
     world_transform: &TransformMatrix,
+    fill_rule: FillRule,
 ) -> bool {
     // Get inverse transform to convert world coordinates to local coordinates
     let inverse = match world_transform.inverse() {
@@ -82,9 +106,15 @@ pub fn hit_test_object(
         VectorObject::Ellipse { cx, cy, rx, ry } => {
             point_in_ellipse(local_x, local_y, *cx, *cy, *rx, *ry)
         }
+        VectorObject::Circle { cx, cy, r } => {
+            point_in_circle(local_x, local_y, *cx, *cy, *r)
+        }
         VectorObject::Path { commands, .. } => {
-            point_in_path_bounds(local_x, local_y, commands)
+            point_in_path(local_x, local_y, commands, fill_rule)
         }
+        // Anywhere on the text's box, not only on the glyphs, so text is easy to click
+        VectorObject::Text(_) => geometry::object_world_bounds(object, &TransformMatrix::identity())
+            .is_some_and(|bounds| bounds.contains_point(local_x, local_y)),
     }
 }
 
@@ -109,6 +139,13 @@ mod tests {
         assert!(!point_in_ellipse(81.0, 50.0, 50.0, 50.0, 30.0, 20.0)); // outside
     }
 
+    #[test]
+    fn test_point_in_circle() {
+        assert!(point_in_circle(10.0, 10.0, 10.0, 10.0, 5.0)); // center
+        assert!(point_in_circle(13.0, 14.0, 10.0, 10.0, 5.0)); // on the edge (3-4-5)
+        assert!(!point_in_circle(14.0, 14.0, 10.0, 10.0, 5.0)); // inside bounding box corner only
+    }
+
     #[test]
     fn test_hit_test_rotated_rect() {
         // Rectangle at origin, 100x50, rotated 45 degrees around origin
@@ -123,9 +160,46 @@ mod tests {
         // Point at local (50, 25) should hit
         // In world coords after 45 deg rotation: approximately (17.7, 53.0)
         let (wx, wy) = transform.transform_point(50.0, 25.0);
-        assert!(hit_test_object(wx, wy, &rect, &transform));
+        assert!(hit_test_object(wx, wy, &rect, &transform, FillRule::NonZero));
 
         // Point far away should not hit
-        assert!(!hit_test_object(1000.0, 1000.0, &rect, &transform));
+        assert!(!hit_test_object(1000.0, 1000.0, &rect, &transform, FillRule::NonZero));
+    }
+
+    #[test]
+    fn test_point_in_path_fill_rules() {
+        // Outer square with an inner square wound the same way
+        let square = |x: f64, y: f64, size: f64| {
+            vec![
+                PathCommand::MoveTo { x, y },
+                PathCommand::LineTo { x: x + size, y },
+                PathCommand::LineTo { x: x + size, y: y + size },
+                PathCommand::LineTo { x, y: y + size },
+                PathCommand::ClosePath,
+            ]
+        };
+        let mut commands = square(0.0, 0.0, 100.0);
+        commands.extend(square(25.0, 25.0, 50.0));
+
+        assert!(point_in_path(50.0, 50.0, &commands, FillRule::NonZero));
+        assert!(!point_in_path(50.0, 50.0, &commands, FillRule::EvenOdd));
+        assert!(point_in_path(10.0, 10.0, &commands, FillRule::EvenOdd));
+        assert!(!point_in_path(150.0, 50.0, &commands, FillRule::NonZero));
+    }
+
+    #[test]
+    fn test_point_in_path_misses_concave_notch() {
+        // An L shape: inside its bounding box but outside the filled area
+        let commands = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::LineTo { x: 100.0, y: 0.0 },
+            PathCommand::LineTo { x: 100.0, y: 20.0 },
+            PathCommand::LineTo { x: 20.0, y: 20.0 },
+            PathCommand::LineTo { x: 20.0, y: 100.0 },
+            PathCommand::LineTo { x: 0.0, y: 100.0 },
+        ];
+        assert!(point_in_path_bounds(80.0, 80.0, &commands));
+        assert!(!point_in_path(80.0, 80.0, &commands, FillRule::NonZero));
+        assert!(point_in_path(10.0, 80.0, &commands, FillRule::NonZero));
     }
 }
@@ -3,36 +3,175 @@
 //! This is the core engine for the vector graphics editor.
 //! It handles all geometry calculations, scene management, and rendering commands.
 
-use std::collections::HashSet;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use tsify::Tsify;
 use wasm_bindgen::prelude::*;
 
+pub mod align;
+pub mod animation;
+pub mod artboard;
+pub mod assets;
+pub mod brush;
+#[cfg(feature = "canvas")]
+pub mod canvas_renderer;
+pub mod color;
+pub mod chunked_import;
+pub mod clipboard;
 pub mod core;
+pub mod document;
 pub mod drag_state;
+pub mod draw_order;
+pub mod eraser;
+pub mod fonts;
+pub mod history;
 pub mod hit_test;
+pub mod library;
+pub mod migration;
+pub mod operations;
+pub mod path_data;
+pub mod path_edit;
+pub mod path_ops;
+pub mod pattern;
+pub mod pdf;
 pub mod pen_state;
+pub mod protocol;
+pub mod raster;
+pub mod render_buffer;
+pub mod render_delta;
 pub mod renderer;
+pub mod revision;
+pub mod roundtrip;
+pub mod selection_state;
+pub mod shape_match;
+pub mod snap;
 pub mod spatial;
+pub mod stroke;
+pub mod tessellate;
+pub mod trace;
+pub mod text_edit;
 pub mod text_engine;
+pub mod validation;
+pub mod viewport;
 
+#[cfg(test)]
+mod editor_tests;
+
+use crate::align::AlignMode;
+use crate::animation::{AnimatedProperty, Easing, KeyValue, Keyframe};
+use crate::artboard::Artboard;
+use crate::brush::BrushStroke;
+use crate::chunked_import::ChunkedImport;
+use crate::clipboard::ClipboardFragment;
+use crate::color::{normalize_color, Color, ColorSpace};
+use crate::core::geometry;
 use crate::core::math::TransformMatrix;
-use crate::core::scene::{PathCommand, SceneGraph, SceneNode, VectorObject};
+use crate::core::scene::{
+    AnchorType, FillRule, LeafView, LineCap, LineJoin, ObjectId, ObjectStyle, ParagraphStyle, PathCommand, SceneGraph,
+    SceneNode, ShapeRendering, StrokeAlign, TextAlign, TextFont, TextObject, Typography, VectorObject, WritingMode,
+};
+use crate::document::Units;
 use crate::drag_state::{DragMode, DragState, HandleIndex};
+use crate::eraser::{Erased, EraserSample};
+use crate::fonts::FontRegistry;
+use crate::history::{HistoryEntry, HistoryListing, SavedHistory};
 use crate::hit_test::hit_test_object;
+use crate::library::{SharedStyle, Swatch};
+use crate::migration::MigrationReport;
+use crate::operations::{Operation, OperationError};
+use crate::path_edit::{AnchorSelection, HandleSide, PathCut, PathPoint, PathPointKind};
+use crate::path_ops::BooleanOp;
+use crate::pattern::{PatternFill, PatternSource};
 use crate::pen_state::PenState;
-use crate::renderer::SelectionOverlay;
+use crate::render_delta::FrameTracker;
+use crate::renderer::{CommandCache, ExportOptions, SelectionOverlay, SvgPageOptions};
+use crate::revision::TrackedScene;
+use crate::roundtrip::FidelityReport;
+use crate::selection_state::SelectionState;
+use crate::snap::{GridSettings, GuideAxis, RulerGuide, SmartGuides, SnapTarget, Snapper};
 use crate::spatial::BoundingBox;
+use crate::text_edit::{CaretPosition, CaretUnit, TextEditSession};
+use crate::text_engine::{KerningOverrides, TextLine, TextOutline};
+use crate::trace::TraceOptions;
+use crate::validation::{ImportError, ImportErrorCode, ImportLimits};
+use crate::viewport::Viewport;
+
+/// Offset applied to pasted content so it doesn't hide the original
+const PASTE_OFFSET: f64 = 10.0;
+
+/// Distance within which pen clicks snap to earlier anchors of the path being drawn
+const PEN_SNAP_THRESHOLD: f64 = 8.0;
+
+/// Distance within which a pen click on the first anchor closes the path
+const PEN_CLOSE_THRESHOLD: f64 = 15.0;
+
+/// Side of the square selection handles
+const HANDLE_SIZE: f64 = 8.0;
+
+/// Distance the mouse must travel before a move drag moves (screen space tolerances only)
+const DRAG_THRESHOLD: f64 = 3.0;
+
+/// Em size of new text objects
+const DEFAULT_FONT_SIZE: f64 = 24.0;
+
+/// Width of brush strokes at full pressure until set_brush_width is called
+const DEFAULT_BRUSH_WIDTH: f64 = 8.0;
 
 /// Editor state that holds the entire scene
 #[wasm_bindgen]
 pub struct Editor {
-    scene: SceneGraph,
+    scene: TrackedScene,
     selected_ids: HashSet<String>,
     drag_state: DragState,
     pen_state: PenState,
     // History for undo/redo
-    undo_stack: Vec<SceneGraph>,
-    redo_stack: Vec<SceneGraph>,
+    undo_stack: Vec<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
     max_history: usize,
+    max_history_bytes: usize,
+    // Estimated bytes held by both stacks, shared parts counted once
+    history_bytes: usize,
+    // Bulk mode: snapshots are suppressed until end_bulk_mode
+    history_suspended: bool,
+    // Hardening for untrusted documents
+    import_limits: ImportLimits,
+    last_import_error: Option<ImportError>,
+    last_import_report: Option<MigrationReport>,
+    // Import being received in chunks
+    pending_import: Option<ChunkedImport>,
+    // Number formatting for SVG export
+    export_options: ExportOptions,
+    // Selection moved by the previous nudge (consecutive nudges share one undo entry)
+    nudge_selection: Option<HashSet<String>>,
+    // Offset of the last duplicate (for "duplicate again")
+    last_duplicate_offset: Option<(f64, f64)>,
+    // Whether resizing past the pivot mirrors the selection
+    resize_flip: bool,
+    // Snapping to other objects during move/resize drags
+    smart_guides: SmartGuides,
+    // Brush stroke being drawn and the nominal width of new strokes
+    brush: Option<BrushStroke>,
+    brush_width: f64,
+    // Anchors selected in path edit mode (direct selection)
+    anchor_selection: Option<AnchorSelection>,
+    // Loaded font files, in fallback order (not saved with the document)
+    fonts: FontRegistry,
+    // Text object being edited with a caret
+    text_edit: Option<TextEditSession>,
+    // Pan and zoom of the canvas view (not saved with the document)
+    viewport: Viewport,
+    // Whether tool tolerances are screen pixels (constant at any zoom) rather than world units
+    screen_space_tolerances: bool,
+    // What the previous frame drew (for delta rendering)
+    frame: FrameTracker,
+    // Render commands of leaves, reused until their revision changes
+    render_cache: CommandCache,
+    // Called with the selection state when it changes, and the state it last received
+    selection_observer: Option<js_sys::Function>,
+    observed_selection: Option<SelectionState>,
+    // Replays frames on a canvas context passed from JS
+    #[cfg(feature = "canvas")]
+    canvas: canvas_renderer::CanvasRenderer,
 }
 
 #[wasm_bindgen]
@@ -44,13 +183,38 @@ impl Editor {
         console_error_panic_hook::set_once();
         
         Editor {
-            scene: SceneGraph::new(),
+            scene: TrackedScene::new(SceneGraph::new()),
             selected_ids: HashSet::new(),
             drag_state: DragState::new(),
             pen_state: PenState::new(),
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             max_history: 50, // Keep up to 50 undo states
+            max_history_bytes: 64 * 1024 * 1024,
+            history_bytes: 0,
+            history_suspended: false,
+            import_limits: ImportLimits::default(),
+            last_import_error: None,
+            last_import_report: None,
+            pending_import: None,
+            export_options: ExportOptions::default(),
+            nudge_selection: None,
+            last_duplicate_offset: None,
+            resize_flip: true,
+            smart_guides: SmartGuides::new(),
+            brush: None,
+            brush_width: DEFAULT_BRUSH_WIDTH,
+            anchor_selection: None,
+            fonts: FontRegistry::new(),
+            text_edit: None,
+            viewport: Viewport::default(),
+            screen_space_tolerances: false,
+            frame: FrameTracker::new(),
+            render_cache: CommandCache::new(),
+            selection_observer: None,
+            observed_selection: None,
+            #[cfg(feature = "canvas")]
+            canvas: canvas_renderer::CanvasRenderer::new(),
         }
     }
 
@@ -58,7 +222,7 @@ impl Editor {
     pub fn add_rectangle(&mut self, x: f64, y: f64, width: f64, height: f64) -> String {
         let id = self.scene.generate_id();
         let rect = VectorObject::Rectangle { x, y, width, height };
-        self.scene.add_object(id.clone(), rect, TransformMatrix::identity());
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), rect, TransformMatrix::identity()));
         id
     }
 
@@ -66,10 +230,41 @@ impl Editor {
     pub fn add_ellipse(&mut self, cx: f64, cy: f64, rx: f64, ry: f64) -> String {
         let id = self.scene.generate_id();
         let ellipse = VectorObject::Ellipse { cx, cy, rx, ry };
-        self.scene.add_object(id.clone(), ellipse, TransformMatrix::identity());
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), ellipse, TransformMatrix::identity()));
         id
     }
 
+    /// Add a circle to the scene
+    pub fn add_circle(&mut self, cx: f64, cy: f64, r: f64) -> String {
+        let id = self.scene.generate_id();
+        let circle = VectorObject::Circle { cx, cy, r: r.abs() };
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), circle, TransformMatrix::identity()));
+        id
+    }
+
+    /// Get the radius of a circle (undefined if the ID is not a circle)
+    pub fn get_circle_radius(&self, id: &str) -> Option<f64> {
+        match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { object: VectorObject::Circle { r, .. }, .. }) => Some(*r),
+            _ => None,
+        }
+    }
+
+    /// Set the radius of a circle, keeping its center fixed
+    /// Records its own undo step. Returns false if the ID is not a circle
+    /// or the radius is not a finite, non-negative number
+    pub fn set_circle_radius(&mut self, id: &str, radius: f64) -> bool {
+        if !radius.is_finite() || radius < 0.0 || self.get_circle_radius(id).is_none() {
+            return false;
+        }
+        self.record_undo("Radius");
+        if let Some(SceneNode::Leaf { object: VectorObject::Circle { r, .. }, .. }) = self.scene.edit_node(id) {
+            *r = radius;
+            return true;
+        }
+        false
+    }
+
     /// Add a rotated rectangle to the scene (for testing hit detection)
     /// cx, cy: center position, width, height: size, angle_degrees: rotation in degrees
     pub fn add_rotated_rectangle(&mut self, cx: f64, cy: f64, width: f64, height: f64, angle_degrees: f64) -> String {
@@ -89,18 +284,26 @@ impl Editor {
         // Combined transform: first rotate around origin, then translate to position
         let transform = translation.multiply(&rotation);
         
-        self.scene.add_object(id.clone(), rect, transform);
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), rect, transform));
         id
     }
 
     /// Add a path from JSON commands string
     /// Each command: {"type": "MoveTo", "x": 0, "y": 0} etc.
-    pub fn add_path(&mut self, commands_json: &str) -> String {
-        let id = self.scene.generate_id();
-        let commands: Vec<PathCommand> = serde_json::from_str(commands_json).unwrap_or_default();
-        let path = VectorObject::Path { commands, is_closed: true };
-        self.scene.add_object(id.clone(), path, TransformMatrix::identity());
-        id
+    /// The commands are validated like imported documents.
+    /// Returns the new ID; malformed commands throw an ImportError
+    /// ({ code, message, location? }, also available from get_last_import_error)
+    pub fn add_path(&mut self, commands_json: &str) -> Result<String, JsValue> {
+        self.add_path_checked(commands_json).map_err(|error| to_js_value(&error))
+    }
+
+    /// Add a path from an SVG path data string ("M0,0 L10,0 Q... A... Z")
+    /// Accepts the full path grammar, absolute and relative. The path is closed if the
+    /// data contains a close command, and is validated like imported documents.
+    /// Returns the new ID; invalid data throws an ImportError
+    /// located at its offset in the data (also available from get_last_import_error)
+    pub fn add_path_from_svg(&mut self, d: &str) -> Result<String, JsValue> {
+        self.add_path_from_svg_checked(d).map_err(|error| to_js_value(&error))
     }
 
     /// Add a heart-shaped path at the specified center position (for testing)
@@ -128,20 +331,128 @@ impl Editor {
             PathCommand::ClosePath,
         ];
         
-        let path = VectorObject::Path { commands, is_closed: true };
+        let path = VectorObject::Path { commands, is_closed: true, anchor_types: Vec::new() };
         // Position at center
         let transform = TransformMatrix::translate(cx, cy);
-        self.scene.add_object(id.clone(), path, transform);
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, transform));
         id
     }
 
-    pub fn get_render_commands(&self) -> String {
-        let commands = renderer::generate_render_commands(&self.scene);
+    /// Get the render commands as an array of { type, ...operands } objects
+    #[wasm_bindgen(unchecked_return_type = "RenderCommand[]")]
+    pub fn get_render_commands(&mut self) -> JsValue {
+        let commands = renderer::generate_render_commands_cached(&self.scene, &mut self.render_cache);
+        to_js_value(&commands)
+    }
+
+    /// get_render_commands as a JSON string
+    pub fn get_render_commands_json(&mut self) -> String {
+        let commands = renderer::generate_render_commands_cached(&self.scene, &mut self.render_cache);
         serde_json::to_string(&commands).unwrap_or_else(|_| "[]".to_string())
     }
 
-    /// Get selection overlay commands as JSON string
-    pub fn get_selection_overlay(&self) -> String {
+    /// Get the render commands encoded as numbers (a Float64Array in JS) instead of JSON,
+    /// read in place without parsing; see the render_buffer module for the encoding
+    pub fn get_render_commands_binary(&mut self) -> Vec<f64> {
+        let commands = renderer::generate_render_commands_cached(&self.scene, &mut self.render_cache);
+        render_buffer::encode(&commands)
+    }
+
+    /// Triangulate the scene for a WebGL/WebGPU backend: vertices (x, y, r, g, b, a as a
+    /// Float32Array) and triangle indices (a Uint32Array), in painting order.
+    /// `tolerance` is the largest curve flattening error in world units
+    pub fn tessellate(&self, tolerance: f64) -> tessellate::Mesh {
+        tessellate::tessellate_scene(&self.scene, tolerance)
+    }
+
+    /// Get render commands split per layer (top-level node), bottom layer first
+    /// Returns JSON: [{ layer_id: string, commands: RenderCommand[] }]
+    pub fn get_render_commands_by_layer(&mut self) -> String {
+        let layers = renderer::generate_render_commands_by_layer_cached(&self.scene, &mut self.render_cache);
+        serde_json::to_string(&layers).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the render commands for what changed since the previous call: the region to
+    /// clear and the layers (top-level nodes) to repaint in it, those that changed and the
+    /// unchanged ones overlapping it. The first call, and the next one after
+    /// invalidate_render_delta or a viewport change, draws everything.
+    /// Returns JSON: { full: bool, region: [min_x, min_y, max_x, max_y] | null,
+    /// layers: [{ layer_id, commands }] } (region in world units, layers bottom first)
+    pub fn get_render_commands_delta(&mut self) -> String {
+        let delta = self.frame.next_frame(&self.scene, &mut self.render_cache);
+        serde_json::to_string(&delta).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Make the next get_render_commands_delta draw everything (e.g. after the canvas was
+    /// resized or cleared by the frontend)
+    pub fn invalidate_render_delta(&mut self) {
+        self.frame.invalidate();
+    }
+
+    /// Counters of the cache render commands are generated with: objects unchanged
+    /// since they were last drawn reuse their commands
+    /// Returns JSON: { entries, hits, misses, hit_rate }
+    pub fn get_render_cache_stats(&self) -> String {
+        serde_json::to_string(&self.render_cache.stats()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Flag a group as a static subtree whose rendered output may be cached as a bitmap
+    /// Render commands for the group are wrapped in BeginCache/EndCache markers.
+    /// Records its own undo step. Returns false if the ID does not refer to a group
+    pub fn set_cache_as_bitmap(&mut self, id: &str, enabled: bool) -> bool {
+        if !matches!(self.scene.get_node_by_id(id), Some(SceneNode::Group { .. })) {
+            return false;
+        }
+        self.record_undo("Cache as bitmap");
+        if let Some(SceneNode::Group { cache_as_bitmap, .. }) = self.scene.edit_node(id) {
+            *cache_as_bitmap = enabled;
+            return true;
+        }
+        false
+    }
+
+    /// Check whether a group is flagged for bitmap caching
+    pub fn get_cache_as_bitmap(&self, id: &str) -> bool {
+        self.scene
+            .get_node_by_id(id)
+            .is_some_and(|node| node.is_cached_as_bitmap())
+    }
+
+    /// Set an object's rendering hint: "auto" | "crisp-edges"
+    /// Crisp objects are aligned to the pixel grid when drawn and exported with
+    /// shape-rendering="crispEdges". Records its own undo step.
+    /// Returns false if the ID is not an object or the hint is unknown
+    pub fn set_shape_rendering(&mut self, id: &str, hint: &str) -> bool {
+        let Some(hint) = ShapeRendering::from_name(hint) else {
+            return false;
+        };
+        if !matches!(self.scene.get_node_by_id(id), Some(SceneNode::Leaf { .. })) {
+            return false;
+        }
+        self.record_undo("Shape rendering");
+        if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+            style.shape_rendering = hint;
+            return true;
+        }
+        false
+    }
+
+    /// Get an object's rendering hint ("auto" | "crisp-edges"), or "" if not found
+    pub fn get_shape_rendering(&self, id: &str) -> String {
+        match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { style, .. }) => style.shape_rendering.name().to_string(),
+            _ => String::new(),
+        }
+    }
+
+    /// Get the selection overlays: [{ id, corners: [[x, y] x4], handle_size }]
+    #[wasm_bindgen(unchecked_return_type = "SelectionOverlay[]")]
+    pub fn get_selection_overlay(&self) -> JsValue {
+        to_js_value(&self.generate_selection_overlays())
+    }
+
+    /// get_selection_overlay as a JSON string
+    pub fn get_selection_overlay_json(&self) -> String {
         let overlays = self.generate_selection_overlays();
         serde_json::to_string(&overlays).unwrap_or_else(|_| "[]".to_string())
     }
@@ -151,19 +462,89 @@ impl Editor {
         self.scene.object_count()
     }
 
+    /// Get the world-space axis-aligned bounds of an object (tight for curves and rotations)
+    /// Returns JSON: [min_x, min_y, max_x, max_y] or "[]" if not found / empty
+    pub fn get_object_bounds(&self, id: &str) -> String {
+        let bounds = self
+            .scene
+            .get_node_with_parent_transform(id)
+            .and_then(|(node, parent_transform)| geometry::node_world_bounds(&self.scene, node, &parent_transform));
+        match bounds {
+            Some(bounds) => serde_json::to_string(&bounds.to_array()).unwrap_or_else(|_| "[]".to_string()),
+            None => "[]".to_string(),
+        }
+    }
+
+    /// Get the values shown in the properties panel for an object
+    /// x, y: top-left of the world bounds; width, height: size of the object's own box
+    /// (its local bounds scaled by the transform); rotation: degrees, clockwise on screen;
+    /// skew: degrees the object's vertical axis leans (see TransformMatrix::decompose)
+    /// Returns JSON: { x, y, width, height, rotation, skew } or "{}" if not found / empty
+    pub fn get_transform_properties(&self, id: &str) -> String {
+        let Some((world, local, transform)) = self.object_frame(id) else {
+            return "{}".to_string();
+        };
+        let (sx, sy) = axis_scales(&transform);
+        let components = transform.decompose();
+        serde_json::json!({
+            "x": world.min_x,
+            "y": world.min_y,
+            "width": local.width() * sx,
+            "height": local.height() * sy,
+            "rotation": components.rotation.to_degrees(),
+            "skew": components.skew.to_degrees(),
+        })
+        .to_string()
+    }
+
+    /// Move an object so the top-left of its world bounds is at (x, y)
+    /// Records its own undo step. Returns false if not found
+    pub fn set_position(&mut self, id: &str, x: f64, y: f64) -> bool {
+        let Some((world, _, transform)) = self.object_frame(id) else {
+            return false;
+        };
+        let Some(translation) = self.scene.parent_space_matrix(id, &TransformMatrix::translate(x - world.min_x, y - world.min_y)) else {
+            return false;
+        };
+        self.set_node_transform(id, translation.multiply(&transform), "Move")
+    }
+
+    /// Resize an object's own box to width × height, keeping its top-left corner in place
+    /// Scaling happens along the object's axes, so rotated objects stay rotated.
+    /// Records its own undo step. Returns false if not found, empty, or the size is invalid
+    pub fn set_size(&mut self, id: &str, width: f64, height: f64) -> bool {
+        let Some((_, local, transform)) = self.object_frame(id) else {
+            return false;
+        };
+        let (sx, sy) = axis_scales(&transform);
+        let (current_w, current_h) = (local.width() * sx, local.height() * sy);
+        if current_w <= 1e-12 || current_h <= 1e-12 || !(width > 0.0 && height > 0.0) {
+            return false;
+        }
+        let scale = TransformMatrix::scale_around(width / current_w, height / current_h, local.min_x, local.min_y);
+        self.set_node_transform(id, transform.multiply(&scale), "Resize")
+    }
+
+    /// Set an object's rotation (degrees, clockwise on screen) around the center of its box
+    /// Records its own undo step. Returns false if not found
+    pub fn set_rotation(&mut self, id: &str, degrees: f64) -> bool {
+        let Some((_, local, transform)) = self.object_frame(id) else {
+            return false;
+        };
+        if !degrees.is_finite() {
+            return false;
+        }
+        let current = transform.c.atan2(transform.a);
+        let (cx, cy) = local.center();
+        let (wx, wy) = transform.transform_point(cx, cy);
+        let rotation = TransformMatrix::rotate_around(-(degrees.to_radians() - current), wx, wy);
+        self.set_node_transform(id, rotation.multiply(&transform), "Rotate")
+    }
+
     /// Hit test at a point, returns the ID of the top-most object hit (or empty string)
     pub fn hit_test(&self, x: f64, y: f64) -> String {
-        // Iterate leaves in reverse order (top-most first)
-        let leaves: Vec<_> = self.scene.iter_leaves();
-        for (object, transform, _style) in leaves.into_iter().rev() {
-            if hit_test_object(x, y, object, &transform) {
-                // Find the ID by matching the object
-                if let Some(id) = self.find_id_for_object(object) {
-                    return id;
-                }
-            }
-        }
-        String::new()
+        // Leaves inside groups and instances select their top-level node
+        self.leaf_at(x, y).map(|leaf| leaf.root_id.clone()).unwrap_or_default()
     }
 
     /// Select object at point (replaces current selection)
@@ -173,6 +554,7 @@ impl Editor {
         if !id.is_empty() {
             self.selected_ids.insert(id.clone());
         }
+        self.notify_selection_observer();
         id
     }
 
@@ -186,63 +568,205 @@ impl Editor {
                 self.selected_ids.insert(id.clone());
             }
         }
+        self.notify_selection_observer();
         id
     }
 
+    /// Replace the selection with the given objects
+    /// ids_json: JSON array of IDs; unknown IDs are ignored
+    /// Returns JSON array of the IDs actually selected
+    pub fn select_ids(&mut self, ids_json: &str) -> String {
+        let ids: Vec<String> = serde_json::from_str(ids_json).unwrap_or_default();
+        self.selected_ids = ids
+            .into_iter()
+            .filter(|id| self.scene.roots.iter().any(|node| node.id() == id))
+            .collect();
+        self.notify_selection_observer();
+        self.get_selected_ids_json()
+    }
+
     /// Deselect all objects
     pub fn deselect_all(&mut self) {
         self.selected_ids.clear();
+        self.notify_selection_observer();
+    }
+
+    /// Get selected IDs as an array of strings
+    #[wasm_bindgen(unchecked_return_type = "string[]")]
+    pub fn get_selected_ids(&self) -> JsValue {
+        let ids: Vec<&String> = self.selected_ids.iter().collect();
+        to_js_value(&ids)
     }
 
-    /// Get selected IDs as JSON array
-    pub fn get_selected_ids(&self) -> String {
+    /// get_selected_ids as a JSON array
+    pub fn get_selected_ids_json(&self) -> String {
         let ids: Vec<&String> = self.selected_ids.iter().collect();
         serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string())
     }
 
+    /// Get the selection with its combined bounds, object types and common style values
+    #[wasm_bindgen(unchecked_return_type = "SelectionState")]
+    pub fn get_selection_state(&self) -> JsValue {
+        to_js_value(&selection_state::selection_state(&self.scene, &self.selected_ids))
+    }
+
+    /// get_selection_state as a JSON string
+    /// Returns: { ids: string[], bounds: [min_x, min_y, max_x, max_y] | null, types: string[],
+    /// style: { fill, stroke, stroke_width, stroke_align, opacity, shared_style, mixed: string[] } }
+    pub fn get_selection_state_json(&self) -> String {
+        let state = selection_state::selection_state(&self.scene, &self.selected_ids);
+        serde_json::to_string(&state).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Call `observer` with the selection state (as get_selection_state) now and whenever
+    /// it changes: when objects are selected or deselected (including by undo, redo and
+    /// delete), or selected objects are moved, dragged or restyled. It is not called
+    /// when a call leaves the state as it was. Pass undefined to stop observing
+    pub fn set_selection_observer(
+        &mut self,
+        #[wasm_bindgen(unchecked_param_type = "((state: SelectionState) => void) | undefined")]
+        observer: Option<js_sys::Function>,
+    ) {
+        self.selection_observer = observer;
+        self.observed_selection = None;
+        self.notify_selection_observer();
+    }
+
     /// Check if any object is selected
     pub fn has_selection(&self) -> bool {
         !self.selected_ids.is_empty()
     }
 
-    /// Get style of first selected object as JSON
-    /// Returns: { fill: "#color" | null, stroke: "#color" | null, strokeWidth: number }
-    pub fn get_selected_style(&self) -> String {
-        if let Some(id) = self.selected_ids.iter().next() {
-            if let Some(node) = self.scene.get_node_by_id(id) {
-                if let SceneNode::Leaf { style, .. } = node {
-                    let json = serde_json::json!({
-                        "fill": style.fill_color,
-                        "stroke": style.stroke_color,
-                        "strokeWidth": style.stroke_width,
-                    });
-                    return serde_json::to_string(&json).unwrap_or_else(|_| "{}".to_string());
-                }
-            }
+    /// Get style of first selected object (null if no leaf is selected)
+    #[wasm_bindgen(unchecked_return_type = "SelectedStyle | null")]
+    pub fn get_selected_style(&self) -> JsValue {
+        to_js_value(&self.selected_style())
+    }
+
+    /// get_selected_style as a JSON string ({} if no leaf is selected)
+    /// Returns: { fill: "#color" | null, stroke: "#color" | null, strokeWidth: number,
+    /// strokeAlign: "center" | "inside" | "outside", fillPattern: { source, spacing, scale, rotation } | null }
+    pub fn get_selected_style_json(&self) -> String {
+        match self.selected_style() {
+            Some(style) => serde_json::to_string(&style).unwrap_or_else(|_| "{}".to_string()),
+            None => "{}".to_string(),
         }
-        "{}".to_string()
     }
 
     /// Update style of all selected objects
-    pub fn update_style(&mut self, fill: &str, stroke: &str, stroke_width: f64) {
-        let fill_color = if fill == "none" || fill.is_empty() { None } else { Some(fill.to_string()) };
-        let stroke_color = if stroke == "none" || stroke.is_empty() { None } else { Some(stroke.to_string()) };
-        
+    /// Colors may be any CSS form Color::parse accepts and are stored as "#rrggbb";
+    /// "" or "none" removes the fill or stroke.
+    /// Returns false (changing nothing) if a color cannot be parsed
+    pub fn update_style(&mut self, fill: &str, stroke: &str, stroke_width: f64) -> bool {
+        let (Some(fill_color), Some(stroke_color)) = (style_color(fill), style_color(stroke)) else {
+            return false;
+        };
+
+        for id in &self.selected_ids.clone() {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                style.fill_color = fill_color.clone();
+                style.stroke_color = stroke_color.clone();
+                style.stroke_width = stroke_width;
+            }
+        }
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Set the stroke cap ("butt" | "round" | "square") and join ("miter" | "round" | "bevel")
+    /// of the selected objects. Returns false if a name is unknown
+    pub fn set_stroke_line_style(&mut self, cap: &str, join: &str) -> bool {
+        let (Some(cap), Some(join)) = (LineCap::from_name(cap), LineJoin::from_name(join)) else {
+            return false;
+        };
+        if !self.selected_ids.is_empty() {
+            self.record_undo("Stroke style");
+        }
+        for id in &self.selected_ids.clone() {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                style.line_cap = cap;
+                style.line_join = join;
+            }
+        }
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Set the fill rule ("nonzero" | "evenodd") of the selected objects
+    /// Returns false if the name is unknown
+    pub fn set_fill_rule(&mut self, rule: &str) -> bool {
+        let Some(rule) = FillRule::from_name(rule) else {
+            return false;
+        };
+        if !self.selected_ids.is_empty() {
+            self.record_undo("Fill rule");
+        }
+        for id in &self.selected_ids.clone() {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                style.fill_rule = rule;
+            }
+        }
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Set where the stroke sits on the outline of the selected objects
+    /// ("center" | "inside" | "outside"). Open paths always stroke centered.
+    /// Returns false if the name is unknown
+    pub fn set_stroke_align(&mut self, align: &str) -> bool {
+        let Some(align) = StrokeAlign::from_name(align) else {
+            return false;
+        };
+        if !self.selected_ids.is_empty() {
+            self.record_undo("Stroke alignment");
+        }
+        for id in &self.selected_ids.clone() {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                style.stroke_align = align;
+            }
+        }
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Fill the selected objects with a repeating tile, or clear it with ""
+    /// pattern_json: { source: { kind: "symbol", symbol_id } | { kind: "image", href, width, height },
+    /// spacing: [x, y] (default [0, 0]), scale (default 1), rotation (degrees, clockwise) }
+    /// The fill color stays as the fallback for PDF export and for image tiles in PNG
+    /// export; objects without a fill are left alone. Records its own undo step.
+    /// Returns false for invalid JSON, invalid numbers or an unknown symbol
+    pub fn set_fill_pattern(&mut self, pattern_json: &str) -> bool {
+        let pattern = if pattern_json.trim().is_empty() {
+            None
+        } else {
+            match serde_json::from_str::<PatternFill>(pattern_json) {
+                Ok(pattern) if pattern.is_valid() => Some(pattern),
+                _ => return false,
+            }
+        };
+        if let Some(PatternSource::Symbol { symbol_id }) = pattern.as_ref().map(|pattern| &pattern.source) {
+            if self.scene.get_symbol(symbol_id).is_none() {
+                return false;
+            }
+        }
+        if !self.selected_ids.is_empty() {
+            self.record_undo("Pattern fill");
+        }
         for id in &self.selected_ids.clone() {
-            if let Some(node) = self.scene.get_node_by_id_mut(id) {
-                if let SceneNode::Leaf { style, .. } = node {
-                    style.fill_color = fill_color.clone();
-                    style.stroke_color = stroke_color.clone();
-                    style.stroke_width = stroke_width;
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                if style.fill_color.is_some() {
+                    style.fill_pattern = pattern.clone();
                 }
             }
         }
+        self.notify_selection_observer();
+        true
     }
 
     /// Bring the first selected object to the front (top of z-order)
     pub fn bring_to_front(&mut self) -> bool {
         if let Some(id) = self.selected_ids.iter().next().cloned() {
-            return self.scene.bring_to_front(&id);
+            return self.scene.edit_roots(|scene| scene.bring_to_front(&id));
         }
         false
     }
@@ -250,745 +774,4015 @@ impl Editor {
     /// Send the first selected object to the back (bottom of z-order)
     pub fn send_to_back(&mut self) -> bool {
         if let Some(id) = self.selected_ids.iter().next().cloned() {
-            return self.scene.send_to_back(&id);
+            return self.scene.edit_roots(|scene| scene.send_to_back(&id));
         }
         false
     }
 
+    /// Move an object into a group, or to the top level if parent_id is ""
+    /// index: z-order position among its new siblings (0 = bottom, past the end = top)
+    /// The object keeps its place on the page. Records its own undo step. Returns false
+    /// if either is not found, the parent is not a group or is inside the object
+    pub fn reparent(&mut self, id: &str, parent_id: &str, index: usize) -> bool {
+        let new_parent = (!parent_id.is_empty()).then_some(parent_id);
+        let mut scene = (*self.scene).clone();
+        if !scene.reparent(id, new_parent, index) {
+            return false;
+        }
+        self.record_undo(if new_parent.is_some() { "Move into group" } else { "Move out of group" });
+        self.scene.replace(scene);
+        true
+    }
+
     // ==============================================
-    // Persistence APIs (Save/Load)
+    // Clipboard APIs
     // ==============================================
 
-    /// Export the entire scene to a JSON string
-    pub fn export_scene_to_json(&self) -> String {
-        serde_json::to_string_pretty(&self.scene).unwrap_or_else(|_| "{}".to_string())
-    }
-
-    /// Import a scene from a JSON string, replacing the current scene
-    /// Returns true if successful, false if parsing failed
-    pub fn import_scene_from_json(&mut self, json: &str) -> bool {
-        match serde_json::from_str::<SceneGraph>(json) {
-            Ok(scene) => {
-                self.scene = scene;
-                self.selected_ids.clear();
-                self.drag_state.end();
-                self.pen_state = PenState::Idle;
-                true
-            }
-            Err(_) => false,
+    /// Copy the selection as a self-contained JSON fragment (objects, transforms,
+    /// styles and referenced symbols) for the system clipboard
+    /// Returns "" if nothing is selected
+    pub fn copy_selection(&self) -> String {
+        if self.selected_ids.is_empty() {
+            return String::new();
         }
+        let ids: Vec<String> = self.selected_ids.iter().cloned().collect();
+        let fragment = clipboard::copy_fragment(&self.scene, &ids);
+        serde_json::to_string(&fragment).unwrap_or_default()
     }
 
-    /// Clear the entire scene
-    pub fn clear_scene(&mut self) {
-        self.scene = SceneGraph::new();
-        self.selected_ids.clear();
-        self.drag_state.end();
-        self.pen_state = PenState::Idle;
+    /// Copy the selection and remove it from the scene (records its own undo step)
+    /// Returns the fragment JSON, or "" if nothing is selected
+    pub fn cut_selection(&mut self) -> String {
+        let fragment = self.copy_selection();
+        if !fragment.is_empty() {
+            self.delete_selected();
+        }
+        fragment
     }
 
-    /// Export the scene to SVG format
-    pub fn export_to_svg(&self, width: u32, height: u32) -> String {
-        crate::renderer::generate_svg(&self.scene, width, height)
+    /// Delete the selected objects (records its own undo step)
+    /// Returns false if nothing was selected
+    pub fn delete_selected(&mut self) -> bool {
+        if self.selected_ids.is_empty() {
+            return false;
+        }
+        self.record_undo(self.selection_label("Delete"));
+        let ids: Vec<String> = self.selected_ids.drain().collect();
+        let removed = !self.scene.edit_roots(|scene| scene.remove_roots(&ids)).is_empty();
+        self.notify_selection_observer();
+        removed
     }
 
-    // ==============================================
-    // Undo/Redo APIs
-    // ==============================================
-
-    /// Save a snapshot of the current scene for undo
-    /// Call this BEFORE making a destructive change
-    pub fn save_snapshot(&mut self) {
-        // Clone current scene and push to undo stack
-        self.undo_stack.push(self.scene.clone());
-        
-        // Clear redo stack when new action is performed
-        self.redo_stack.clear();
-        
-        // Limit history size
-        while self.undo_stack.len() > self.max_history {
-            self.undo_stack.remove(0);
+    /// Paste a fragment produced by copy_selection (possibly from another document)
+    /// IDs are remapped to avoid collisions; content goes to the top of the z-order,
+    /// slightly offset, and becomes the selection. Records its own undo step.
+    /// Returns JSON array of the new IDs, or "[]" if the fragment was rejected
+    /// (details are available from get_last_import_error)
+    pub fn paste(&mut self, fragment_json: &str) -> String {
+        match self.parse_clipboard(fragment_json) {
+            Some(fragment) => self.insert_clipboard(fragment, PASTE_OFFSET, PASTE_OFFSET),
+            None => "[]".to_string(),
         }
     }
 
-    /// Undo the last operation
-    /// Returns true if undo was performed, false if nothing to undo
-    pub fn undo(&mut self) -> bool {
-        if let Some(previous_scene) = self.undo_stack.pop() {
-            // Save current state to redo stack
-            self.redo_stack.push(self.scene.clone());
-            
-            // Restore previous state
-            self.scene = previous_scene;
-            self.selected_ids.clear();
-            self.drag_state.end();
-            
-            true
-        } else {
-            false
+    /// Paste a fragment at exactly the coordinates it was copied from
+    /// Otherwise behaves like paste
+    pub fn paste_in_place(&mut self, fragment_json: &str) -> String {
+        match self.parse_clipboard(fragment_json) {
+            Some(fragment) => self.insert_clipboard(fragment, 0.0, 0.0),
+            None => "[]".to_string(),
         }
     }
 
-    /// Redo the last undone operation
-    /// Returns true if redo was performed, false if nothing to redo
-    pub fn redo(&mut self) -> bool {
-        if let Some(next_scene) = self.redo_stack.pop() {
-            // Save current state to undo stack
-            self.undo_stack.push(self.scene.clone());
-            
-            // Restore next state
-            self.scene = next_scene;
-            self.selected_ids.clear();
-            self.drag_state.end();
-            
-            true
-        } else {
-            false
+    /// Paste a fragment with its bounding box centered on (x, y) (context-menu "Paste here")
+    /// Otherwise behaves like paste
+    pub fn paste_at(&mut self, fragment_json: &str, x: f64, y: f64) -> String {
+        match self.parse_clipboard(fragment_json) {
+            Some(fragment) => {
+                let (cx, cy) = fragment.bounds().map(|bounds| bounds.center()).unwrap_or((x, y));
+                self.insert_clipboard(fragment, x - cx, y - cy)
+            }
+            None => "[]".to_string(),
         }
     }
 
-    /// Check if undo is available
-    pub fn can_undo(&self) -> bool {
-        !self.undo_stack.is_empty()
+    /// Copy the style of the first selected object (fill, stroke, line style, opacity,
+    /// pattern and shared style link) as a token for paste_style_to_selection
+    /// Returns the style JSON, or "" if no object is selected
+    pub fn copy_style_from_selection(&self) -> String {
+        self.selected_ids
+            .iter()
+            .find_map(|id| match self.scene.get_node_by_id(id) {
+                Some(SceneNode::Leaf { style, .. }) => serde_json::to_string(style).ok(),
+                _ => None,
+            })
+            .unwrap_or_default()
     }
 
-    /// Check if redo is available
-    pub fn can_redo(&self) -> bool {
-        !self.redo_stack.is_empty()
+    /// Apply a style token from copy_style_from_selection to every selected object
+    /// The link to a shared style is kept only if this document has that style.
+    /// Records one undo step. Returns false for an invalid token or a pattern
+    /// of a symbol this document doesn't have
+    pub fn paste_style_to_selection(&mut self, token: &str) -> bool {
+        let Some(mut pasted) = parse_style(&self.scene, token) else {
+            return false;
+        };
+        let linked = pasted.shared_style.as_ref().is_some_and(|id| self.scene.shared_styles.iter().any(|s| s.id == *id));
+        if !linked {
+            pasted.shared_style = None;
+        }
+        if !self.selected_ids.is_empty() {
+            self.record_undo("Paste style");
+        }
+        for id in &self.selected_ids.clone() {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                *style = pasted.clone();
+            }
+        }
+        self.notify_selection_observer();
+        true
     }
 
-    /// Get the size of the undo stack
-    pub fn undo_stack_size(&self) -> usize {
-        self.undo_stack.len()
+    /// Eyedropper: the style of the top-most object drawn at a point, including
+    /// objects inside groups and symbol instances
+    /// Returns the style JSON (a token for paste_style_to_selection), or "" if nothing is hit
+    pub fn sample_style_at(&self, x: f64, y: f64) -> String {
+        self.leaf_at(x, y).and_then(|leaf| serde_json::to_string(leaf.style).ok()).unwrap_or_default()
     }
 
-    /// Get the size of the redo stack
-    pub fn redo_stack_size(&self) -> usize {
-        self.redo_stack.len()
+    /// Eyedropper: give the selected objects the style of the object at a point
+    /// Records one undo step. Returns false if nothing is hit or nothing is selected
+    pub fn apply_sampled_style_to_selection(&mut self, x: f64, y: f64) -> bool {
+        let token = self.sample_style_at(x, y);
+        !token.is_empty() && !self.selected_ids.is_empty() && self.paste_style_to_selection(&token)
     }
 
-    /// Move selected objects by delta
-    /// Note: For precise movement, use begin_move_drag/update_move_drag/end_drag instead
-    pub fn move_selected(&mut self, dx: f64, dy: f64) {
-        for id in &self.selected_ids.clone() {
-            if let Some(node) = self.scene.get_node_by_id_mut(id) {
-                if let SceneNode::Leaf { transform, .. } = node {
-                    // Apply translation to existing transform
-                    let translation = TransformMatrix::translate(dx, dy);
-                    *transform = translation.multiply(transform);
-                }
+    // ==============================================
+    // Symbol APIs
+    // ==============================================
+
+    /// Move the selected objects into the symbol library and replace them with an instance
+    /// Returns the new instance ID (which becomes the selection) or "" if nothing is selected
+    pub fn create_symbol_from_selection(&mut self, name: &str) -> String {
+        let ids: Vec<String> = self.selected_ids.iter().cloned().collect();
+        match self.scene.edit().create_symbol(name, &ids) {
+            Some((_, instance_id)) => {
+                self.selected_ids.clear();
+                self.selected_ids.insert(instance_id.clone());
+                self.notify_selection_observer();
+                instance_id
             }
+            None => String::new(),
         }
     }
 
-    /// Begin a move drag operation - saves initial transforms
-    pub fn begin_move_drag(&mut self, start_x: f64, start_y: f64) {
-        let mut initial_transforms = std::collections::HashMap::new();
-        for id in &self.selected_ids {
-            if let Some(node) = self.scene.get_node_by_id(id) {
-                if let SceneNode::Leaf { transform, .. } = node {
-                    initial_transforms.insert(id.clone(), *transform);
-                }
+    /// Expand an instance back into editable objects (the symbol stays in the library)
+    /// Returns JSON array of the new object IDs (which become the selection), or "[]"
+    pub fn detach_instance(&mut self, id: &str) -> String {
+        match self.scene.edit().detach_instance(id) {
+            Some(new_ids) => {
+                self.selected_ids = new_ids.iter().cloned().collect();
+                self.notify_selection_observer();
+                serde_json::to_string(&new_ids).unwrap_or_else(|_| "[]".to_string())
             }
+            None => "[]".to_string(),
         }
-        self.drag_state.begin(
-            DragMode::Moving,
-            start_x,
-            start_y,
-            initial_transforms,
-            (0.0, 0.0), // No pivot needed for move
-        );
     }
 
-    /// Update move drag - applies delta from baseline (no cumulative error)
-    pub fn update_move_drag(&mut self, current_x: f64, current_y: f64) {
-        if !self.drag_state.is_active() || self.drag_state.mode != DragMode::Moving {
-            return;
+    /// List the symbol library
+    /// Returns JSON: [{ id, name }]
+    pub fn get_symbols(&self) -> String {
+        let symbols: Vec<serde_json::Value> = self
+            .scene
+            .symbols
+            .iter()
+            .map(|symbol| serde_json::json!({ "id": symbol.id, "name": symbol.name }))
+            .collect();
+        serde_json::to_string(&symbols).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Audit the symbols, fonts and pattern images the document uses before export or sharing
+    /// Returns JSON: { symbols: [{ id, name, uses, bytes, unused, oversized }],
+    /// missing_symbols: [{ instance_id, symbol_id }], fonts: [{ family, weight, italic,
+    /// uses, font_id, missing }], images: [{ href, uses, bytes, oversized }], has_issues }
+    pub fn audit_assets(&self) -> String {
+        serde_json::to_string(&assets::audit(&self.scene, &self.fonts)).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    // ==============================================
+    // Naming APIs
+    // ==============================================
+
+    /// Set the user-facing name of an object (empty string clears the name)
+    /// Records its own undo step. Returns true if the object was found
+    pub fn set_name(&mut self, id: &str, name: &str) -> bool {
+        if self.scene.get_node_by_id(id).is_none() {
+            return false;
         }
-        
-        let (dx, dy) = self.drag_state.delta(current_x, current_y);
-        let translation = TransformMatrix::translate(dx, dy);
-        
-        for id in &self.selected_ids.clone() {
-            if let Some(initial) = self.drag_state.get_initial_transform(id) {
-                if let Some(node) = self.scene.get_node_by_id_mut(id) {
-                    if let SceneNode::Leaf { transform, .. } = node {
-                        // Apply translation to INITIAL transform (not current!)
-                        *transform = translation.multiply(initial);
-                    }
-                }
-            }
+        self.record_undo("Rename");
+        if let Some(node) = self.scene.edit_node(id) {
+            let name = if name.is_empty() { None } else { Some(name.to_string()) };
+            node.set_name(name);
+            return true;
         }
+        false
     }
 
-    /// End drag operation
-    pub fn end_drag(&mut self) {
-        self.drag_state.end();
+    /// Get the name of an object (empty string if unnamed or not found)
+    pub fn get_name(&self, id: &str) -> String {
+        self.scene
+            .get_node_by_id(id)
+            .and_then(|node| node.name())
+            .unwrap_or_default()
+            .to_string()
     }
 
-    /// Check if a drag operation is in progress
-    pub fn is_dragging(&self) -> bool {
-        self.drag_state.is_active()
+    /// Find objects by name pattern (`*` and `?` wildcards, case-insensitive)
+    /// Returns JSON array of matching IDs in scene order
+    pub fn find_by_name(&self, pattern: &str) -> String {
+        let ids = self.scene.find_by_name(pattern);
+        serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string())
     }
 
-    /// Begin a resize drag operation
-    /// handle_index: 0=TopLeft, 1=TopRight, 2=BottomRight, 3=BottomLeft
-    pub fn begin_resize_drag(&mut self, start_x: f64, start_y: f64, handle_index: u8) {
-        let handle = match handle_index {
-            0 => HandleIndex::TopLeft,
-            1 => HandleIndex::TopRight,
-            2 => HandleIndex::BottomRight,
-            3 => HandleIndex::BottomLeft,
-            _ => return,
-        };
+    // ==============================================
+    // Search APIs
+    // ==============================================
 
-        // Get initial transforms and calculate pivot (opposite corner)
-        let mut initial_transforms = std::collections::HashMap::new();
-        let mut pivot = (0.0, 0.0);
+    /// Find objects whose outline looks like the given object's, regardless of position and size
+    /// tolerance: maximum Hausdorff distance between normalized outlines (RMS radius 1);
+    /// about 0.05 finds near-exact duplicates. Rotated copies do not match.
+    /// Returns JSON array of top-level IDs, most similar first (the object itself excluded)
+    pub fn find_similar_shapes(&self, id: &str, tolerance: f64) -> String {
+        let mut segments_by_root: Vec<(&String, Vec<_>)> = Vec::new();
+        for leaf in self.scene.iter_leaf_views() {
+            let segments = shape_match::outline_segments(leaf.object, &leaf.world_transform);
+            match segments_by_root.last_mut() {
+                Some((root_id, existing)) if *root_id == leaf.root_id => existing.extend(segments),
+                _ => segments_by_root.push((leaf.root_id, segments)),
+            }
+        }
+        let samples: Vec<(&String, Vec<(f64, f64)>)> = segments_by_root
+            .iter()
+            .filter_map(|(root_id, segments)| Some((*root_id, shape_match::normalized_samples(segments)?)))
+            .collect();
+
+        let reference = match samples.iter().find(|(root_id, _)| root_id.as_str() == id) {
+            Some((_, reference)) => reference,
+            None => return "[]".to_string(),
+        };
+        let mut matches: Vec<(&String, f64)> = samples
+            .iter()
+            .filter(|(root_id, _)| root_id.as_str() != id)
+            .map(|(root_id, other)| (*root_id, shape_match::hausdorff_distance(reference, other)))
+            .filter(|(_, distance)| *distance <= tolerance)
+            .collect();
+        matches.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let ids: Vec<&String> = matches.into_iter().map(|(root_id, _)| root_id).collect();
+        serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    // ==============================================
+    // Artboard APIs
+    // ==============================================
+
+    /// Add an artboard: a named frame exported as its own page
+    /// Objects belong to the first artboard containing the center of their bounds.
+    /// Records its own undo step.
+    /// Returns the artboard ID, or "" for an empty or non-finite frame
+    pub fn add_artboard(&mut self, name: &str, x: f64, y: f64, width: f64, height: f64) -> String {
+        if !artboard::is_valid_frame(x, y, width, height) {
+            return String::new();
+        }
+        self.record_undo("Add artboard");
+        let id = self.scene.edit_settings().generate_artboard_id();
+        self.scene.edit_settings().artboards.push(Artboard { id: id.clone(), name: name.to_string(), x, y, width, height });
+        id
+    }
+
+    /// Move and resize an artboard (its objects stay where they are)
+    /// Records its own undo step. Returns false for an unknown ID or an invalid frame
+    pub fn set_artboard_frame(&mut self, id: &str, x: f64, y: f64, width: f64, height: f64) -> bool {
+        if !artboard::is_valid_frame(x, y, width, height) || !self.scene.artboards.iter().any(|a| a.id == id) {
+            return false;
+        }
+        self.record_undo("Resize artboard");
+        if let Some(artboard) = self.scene.edit_settings().artboards.iter_mut().find(|artboard| artboard.id == id) {
+            (artboard.x, artboard.y, artboard.width, artboard.height) = (x, y, width, height);
+        }
+        true
+    }
+
+    /// Rename an artboard; records its own undo step. Returns false for an unknown ID
+    pub fn rename_artboard(&mut self, id: &str, name: &str) -> bool {
+        if !self.scene.artboards.iter().any(|artboard| artboard.id == id) {
+            return false;
+        }
+        self.record_undo("Rename artboard");
+        if let Some(artboard) = self.scene.edit_settings().artboards.iter_mut().find(|artboard| artboard.id == id) {
+            artboard.name = name.to_string();
+        }
+        true
+    }
+
+    /// Move an artboard to `index` in the artboard order (clamped to the end)
+    /// The order is the page order of exports and decides which of overlapping artboards
+    /// an object belongs to. Records its own undo step. Returns false for an unknown ID
+    pub fn reorder_artboard(&mut self, id: &str, index: usize) -> bool {
+        let Some(from) = self.scene.artboards.iter().position(|artboard| artboard.id == id) else {
+            return false;
+        };
+        self.record_undo("Reorder artboards");
+        let artboard = self.scene.edit_settings().artboards.remove(from);
+        let index = index.min(self.scene.artboards.len());
+        self.scene.edit_settings().artboards.insert(index, artboard);
+        true
+    }
+
+    /// Remove an artboard (its objects stay in the document)
+    /// Records its own undo step. Returns false for an unknown ID
+    pub fn remove_artboard(&mut self, id: &str) -> bool {
+        if !self.scene.artboards.iter().any(|artboard| artboard.id == id) {
+            return false;
+        }
+        self.record_undo("Delete artboard");
+        self.scene.edit_settings().artboards.retain(|artboard| artboard.id != id);
+        true
+    }
+
+    /// List the artboards in order as JSON: [{ id, name, x, y, width, height }, ...]
+    pub fn list_artboards(&self) -> String {
+        serde_json::to_string(&self.scene.artboards).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get the IDs of the top-level objects on an artboard, in paint order
+    /// Returns JSON array of IDs ("[]" for an unknown artboard)
+    pub fn get_artboard_objects(&self, id: &str) -> String {
+        let objects = artboard::artboard_objects(&self.scene, id).unwrap_or_default();
+        serde_json::to_string(&objects).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Export one artboard to SVG: its objects only, with the artboard as the page
+    /// Returns "" for an unknown artboard
+    pub fn export_artboard_to_svg(&self, id: &str) -> String {
+        match artboard::artboard_scene(&self.scene, id) {
+            Some((page, frame)) => crate::renderer::generate_svg_with_view_box(&page, &frame, &self.export_options),
+            None => String::new(),
+        }
+    }
+
+    /// Rasterize one artboard to PNG at the document's DPI
+    /// Returns the encoded PNG bytes, or an empty array for an unknown artboard
+    pub fn export_artboard_to_png(&self, id: &str) -> Vec<u8> {
+        artboard::artboard_scene(&self.scene, id)
+            .and_then(|(page, frame)| raster::render_png_view(&page, &frame, self.scene.document.raster_scale()))
+            .unwrap_or_default()
+    }
+
+    // ==============================================
+    // Text APIs
+    // ==============================================
+
+    /// Load a font file (TTF/OTF) for text objects
+    /// Fonts are not saved with the document: text keeps its last shaped outlines
+    /// and names its font by family, weight and style. Text whose family was missing
+    /// (see list_missing_fonts) is laid out again with the new font, and so is text
+    /// that can now take missing characters from it: characters missing from a text's
+    /// font are taken from the other registered fonts, in registration order.
+    /// Returns the font ID, or "" if the data is not a font
+    pub fn register_font(&mut self, font_data: &[u8]) -> String {
+        let Some(id) = self.fonts.register(font_data) else {
+            return String::new();
+        };
+        self.reshape_texts();
+        id
+    }
+
+    /// Unload a font; text left without a font of its family keeps its outlines, other
+    /// text is laid out again with the fonts that remain. Returns false for an unknown ID
+    pub fn unregister_font(&mut self, font_id: &str) -> bool {
+        if !self.fonts.unregister(font_id) {
+            return false;
+        }
+        self.reshape_texts();
+        true
+    }
+
+    /// List the registered fonts, in fallback order
+    /// Returns JSON: [{ id, family, subfamily, weight, italic }]
+    pub fn get_fonts(&self) -> String {
+        serde_json::to_string(&self.fonts.infos()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Registered font of a family closest to a weight (100..900) and style
+    /// Returns the font ID, or "" if no font of the family is registered
+    pub fn find_font(&self, family: &str, weight: u16, italic: bool) -> String {
+        self.fonts.find(family, weight, italic).map(str::to_string).unwrap_or_default()
+    }
+
+    /// Fonts the document's text is set in that have no registered font of their family,
+    /// in document order (text in symbol definitions included). Such text draws with the
+    /// outlines of its last shaping and cannot be edited until a font of the family is
+    /// registered, which lays it out again.
+    /// Returns JSON: [{ family, weight, italic, ids: [text IDs] }]
+    pub fn list_missing_fonts(&self) -> String {
+        let mut missing: Vec<(&TextFont, Vec<&ObjectId>)> = Vec::new();
+        for (id, text) in self.scene.text_objects() {
+            if self.fonts.resolve(&text.font).is_some() {
+                continue;
+            }
+            match missing.iter_mut().find(|(font, _)| **font == text.font) {
+                Some((_, ids)) => ids.push(id),
+                None => missing.push((&text.font, vec![id])),
+            }
+        }
+        let missing: Vec<serde_json::Value> = missing
+            .into_iter()
+            .map(|(font, ids)| {
+                serde_json::json!({ "family": font.family, "weight": font.weight, "italic": font.italic, "ids": ids })
+            })
+            .collect();
+        serde_json::to_string(&missing).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Counters of the glyph outline cache text is reshaped with
+    /// Returns JSON: { entries, hits, misses, hit_rate }
+    pub fn get_glyph_cache_stats(&self) -> String {
+        serde_json::to_string(&self.fonts.glyph_cache_stats()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Add a line of text with its baseline starting at (x, y), in the first registered
+    /// font at the default size. The text is selected. Records its own undo step.
+    /// Returns the new ID, or "" if no font is registered
+    pub fn add_text(&mut self, x: f64, y: f64, content: &str) -> String {
+        let Some(font) = self.fonts.first_font() else {
+            return String::new();
+        };
+        if !(x.is_finite() && y.is_finite()) {
+            return String::new();
+        }
+        let mut text = TextObject {
+            content: content.to_string(),
+            font,
+            size: DEFAULT_FONT_SIZE,
+            x,
+            y,
+            paragraph: ParagraphStyle::default(),
+            typography: Typography::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        let Some(outline) = self.fonts.text_outline(&text) else {
+            return String::new();
+        };
+        text.outline = outline.commands;
+        text.color_layers = outline.color_layers;
+        let text = VectorObject::Text(Box::new(text));
+        self.record_undo("Add text");
+        let id = self.scene.generate_id();
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), text, TransformMatrix::identity()));
+        if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(&id) {
+            style.stroke_color = None;
+        }
+        self.selected_ids.clear();
+        self.selected_ids.insert(id.clone());
+        self.notify_selection_observer();
+        id
+    }
+
+    /// Replace the characters of a text object and reshape it
+    /// Records its own undo step. Returns false if it is not text or its font is not loaded
+    pub fn set_text_content(&mut self, id: &str, content: &str) -> bool {
+        self.edit_text(id, |text| text.content = content.to_string())
+    }
+
+    /// Set a text object in another font family, weight (100..900) and style and reshape it
+    /// with the closest registered font of the family (see find_font). Records its own
+    /// undo step. Returns false if it is not text or no font of the family is registered
+    pub fn set_text_font(&mut self, id: &str, family: &str, weight: u16, italic: bool) -> bool {
+        let font = TextFont { family: family.to_string(), weight, italic };
+        self.edit_text(id, |text| text.font = font)
+    }
+
+    /// Set the em size of a text object and reshape it
+    /// Records its own undo step. Returns false if it is not text, its font is not
+    /// loaded or the size is not positive
+    pub fn set_font_size(&mut self, id: &str, size: f64) -> bool {
+        if !(size.is_finite() && size > 0.0) {
+            return false;
+        }
+        self.edit_text(id, |text| text.size = size)
+    }
+
+    /// Make a text object wrap its lines at `width` (area text), or only at newlines
+    /// for a width of 0 (point text). Records its own undo step.
+    /// Returns false if it is not text, its font is not loaded or the width is negative
+    pub fn set_text_width(&mut self, id: &str, width: f64) -> bool {
+        if !(width.is_finite() && width >= 0.0) {
+            return false;
+        }
+        self.edit_text(id, |text| text.paragraph.width = (width > 0.0).then_some(width))
+    }
+
+    /// Align the lines of a text object: "left" | "center" | "right" | "justify"
+    /// Records its own undo step. Returns false if it is not text, its font is not
+    /// loaded or the alignment is unknown
+    pub fn set_text_align(&mut self, id: &str, align: &str) -> bool {
+        let Some(align) = TextAlign::from_name(align) else {
+            return false;
+        };
+        self.edit_text(id, |text| text.paragraph.align = align)
+    }
+
+    /// Lay out a text object in horizontal lines or vertical columns: "horizontal" | "vertical"
+    /// Records its own undo step. Returns false if it is not text, its font is not
+    /// loaded or the mode is unknown
+    pub fn set_text_writing_mode(&mut self, id: &str, mode: &str) -> bool {
+        let Some(mode) = WritingMode::from_name(mode) else {
+            return false;
+        };
+        self.edit_text(id, |text| text.paragraph.writing_mode = mode)
+    }
+
+    /// Indent the first line of each paragraph of a text object (negative for a hanging indent)
+    /// Records its own undo step. Returns false if it is not text or its font is not loaded
+    pub fn set_text_indent(&mut self, id: &str, indent: f64) -> bool {
+        if !indent.is_finite() {
+            return false;
+        }
+        self.edit_text(id, |text| text.paragraph.indent = indent)
+    }
+
+    /// Set the distance between baselines of a text object, or 0 for automatic leading
+    /// (1.2 em). Records its own undo step. Returns false if it is not text, its font
+    /// is not loaded or the leading is negative
+    pub fn set_text_leading(&mut self, id: &str, leading: f64) -> bool {
+        if !(leading.is_finite() && leading >= 0.0) {
+            return false;
+        }
+        self.edit_text(id, |text| text.paragraph.leading = (leading > 0.0).then_some(leading))
+    }
+
+    /// Set the letter spacing of a text object in thousandths of an em (negative tightens)
+    /// Records its own undo step. Returns false if it is not text or its font is not loaded
+    pub fn set_text_tracking(&mut self, id: &str, tracking: f64) -> bool {
+        if !tracking.is_finite() {
+            return false;
+        }
+        self.edit_text(id, |text| text.typography.tracking = tracking)
+    }
+
+    /// Set the OpenType features of a text object as a comma separated list of settings
+    /// ("smcp, tnum, -liga, kern=0"; "" for the font defaults). Records its own undo step.
+    /// Returns false if it is not text, its font is not loaded or a setting is invalid
+    pub fn set_text_features(&mut self, id: &str, features: &str) -> bool {
+        let features: Vec<String> = features
+            .split(',')
+            .map(str::trim)
+            .filter(|feature| !feature.is_empty())
+            .map(str::to_string)
+            .collect();
+        if text_engine::parse_features(&features).is_none() {
+            return false;
+        }
+        self.edit_text(id, |text| text.typography.features = features)
+    }
+
+    /// Set the manual kerning of a text object, replacing the previous overrides
+    /// kerning_json: { "<pair index>": delta, ... } where pair i sits between characters
+    /// i and i + 1 of the content and delta is in units of the text's primary font ("{}"
+    /// clears it). Records its own undo step. Returns false if it is not text, its font
+    /// is not loaded or the JSON is invalid
+    pub fn set_text_kerning(&mut self, id: &str, kerning_json: &str) -> bool {
+        let Ok(kerning) = serde_json::from_str::<KerningOverrides>(kerning_json) else {
+            return false;
+        };
+        if !kerning.values().all(|delta| delta.is_finite()) {
+            return false;
+        }
+        self.edit_text(id, |text| text.typography.kerning = kerning)
+    }
+
+    /// Properties of a text object
+    /// Returns JSON: { content, font_family, font_weight, font_italic, font_id, size, x, y,
+    /// width, align, indent, leading, writing_mode, tracking, features, kerning, font_missing }
+    /// (font_id is the registered font it is shaped with, null and font_missing true if no
+    /// font of its family is registered; width and leading are null when automatic),
+    /// or "" if it is not text
+    pub fn get_text_properties(&self, id: &str) -> String {
+        match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { object: VectorObject::Text(text), .. }) => {
+                serde_json::json!({
+                    "content": text.content,
+                    "font_family": text.font.family,
+                    "font_weight": text.font.weight,
+                    "font_italic": text.font.italic,
+                    "font_id": self.fonts.resolve(&text.font),
+                    "size": text.size,
+                    "x": text.x,
+                    "y": text.y,
+                    "width": text.paragraph.width,
+                    "align": text.paragraph.align.name(),
+                    "indent": text.paragraph.indent,
+                    "leading": text.paragraph.leading,
+                    "writing_mode": text.paragraph.writing_mode.name(),
+                    "tracking": text.typography.tracking,
+                    "features": text.typography.features,
+                    "kerning": text.typography.kerning,
+                    "font_missing": self.fonts.resolve(&text.font).is_none(),
+                })
+                .to_string()
+            }
+            _ => String::new(),
+        }
+    }
+
+    /// Replace a text object with its letterforms as editable paths, keeping its ID, style,
+    /// transform and name: one path for the whole text, or (per_glyph) a group with a path
+    /// per glyph named after its characters. Color glyph layers become paths filled with
+    /// their colors (grouped with the plain outline). Merged outlines need no font;
+    /// per-glyph ones need the text's font loaded. The result is selected. Records its own
+    /// undo step. Returns false if it is not text, draws nothing or (per glyph) its font
+    /// is not loaded
+    pub fn convert_text_to_outlines(&mut self, id: &str, per_glyph: bool) -> bool {
+        let Some(SceneNode::Leaf { object: VectorObject::Text(text), style, .. }) = self.scene.get_node_by_id(id) else {
+            return false;
+        };
+        if text.outline.is_empty() && text.color_layers.is_empty() {
+            return false;
+        }
+        let style = style.clone();
+        let pieces: Vec<(Option<String>, TextOutline)> = if per_glyph {
+            let Some(glyphs) =
+                self.fonts.text_chain(text).and_then(|fonts| text_engine::glyph_outlines(&fonts, text))
+            else {
+                return false;
+            };
+            // Each glyph is named after the characters up to the next glyph (ligatures have several)
+            let chars: Vec<char> = text.content.chars().collect();
+            let ends: Vec<usize> = glyphs.iter().skip(1).map(|(index, _)| *index).chain([chars.len()]).collect();
+            glyphs
+                .into_iter()
+                .zip(ends)
+                .map(|((start, outline), end)| {
+                    let name: String = chars.get(start..end.max(start)).unwrap_or_default().iter().collect();
+                    (Some(name.trim().to_string()), outline)
+                })
+                .collect()
+        } else {
+            vec![(None, TextOutline { commands: text.outline.clone(), color_layers: text.color_layers.clone() })]
+        };
+
+        self.record_undo("Convert text to outlines");
+        let mut pieces: Vec<(Option<String>, Vec<SceneNode>)> =
+            pieces.into_iter().map(|(name, outline)| (name, self.text_outline_paths(outline, &style))).collect();
+        let children = if per_glyph {
+            pieces
+                .into_iter()
+                .map(|(name, mut paths)| match (paths.pop(), paths.is_empty()) {
+                    (Some(SceneNode::Leaf { id, object, transform, style, .. }), true) => {
+                        SceneNode::Leaf { id, object, transform, style, name }
+                    }
+                    (last, _) => SceneNode::Group {
+                        id: self.scene.generate_id(),
+                        children: paths.into_iter().chain(last).collect(),
+                        transform: TransformMatrix::identity(),
+                        name,
+                        cache_as_bitmap: false,
+                    },
+                })
+                .collect()
+        } else {
+            pieces.pop().map(|(_, paths)| paths).unwrap_or_default()
+        };
+
+        // A plain outline replaces the text's object in place
+        let in_place = match children.as_slice() {
+            [SceneNode::Leaf { object, .. }] if !per_glyph => Some(object.clone()),
+            _ => None,
+        };
+        if let Some(node) = self.scene.edit_node(id) {
+            if let SceneNode::Leaf { object, transform, name, .. } = node {
+                if let Some(path) = in_place {
+                    *object = path;
+                } else {
+                    let (transform, name) = (*transform, name.take());
+                    *node = SceneNode::Group { id: id.to_string(), children, transform, name, cache_as_bitmap: false };
+                }
+            }
+        }
+        if self.text_edit.as_ref().is_some_and(|session| session.id == id) {
+            self.text_edit = None;
+        }
+        self.selected_ids.clear();
+        self.selected_ids.insert(id.to_string());
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Laid out lines of a text object, in local coordinates
+    /// Returns JSON: [{ start, end, x, baseline, width, vertical, glyphs: [{ glyph_id,
+    /// char_index, x, y, inline, advance, rtl, sideways }] }] (start/end are character
+    /// indices, glyphs are in visual order; a vertical column's x and width run down and
+    /// its baseline is the x of its center line), or "" if it is not text or its font is
+    /// not loaded
+    pub fn get_text_layout(&self, id: &str) -> String {
+        let Some(SceneNode::Leaf { object: VectorObject::Text(text), .. }) = self.scene.get_node_by_id(id) else {
+            return String::new();
+        };
+        let Some(mut lines) = self
+            .fonts
+            .text_chain(text)
+            .and_then(|fonts| text_engine::layout_text(&fonts, text))
+        else {
+            return String::new();
+        };
+        for line in &mut lines {
+            if line.vertical {
+                line.x += text.y;
+                line.baseline += text.x;
+            } else {
+                line.x += text.x;
+                line.baseline += text.y;
+            }
+            for glyph in &mut line.glyphs {
+                glyph.x += text.x;
+                glyph.y += text.y;
+                glyph.inline += if line.vertical { text.y } else { text.x };
+            }
+        }
+        serde_json::to_string(&lines).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Character boundary of a text object nearest to a world point (for placing the
+    /// caret with a click or extending a selection by dragging)
+    /// Returns JSON: { index, line, x1, y1, x2, y2 } where (x1, y1)-(x2, y2) is the caret
+    /// from its top to its bottom in world coordinates (across a vertical column from
+    /// its left to its right), or "" if it is not text or its
+    /// font is not loaded
+    pub fn text_hit(&self, id: &str, x: f64, y: f64) -> String {
+        let Some(geometry) = self.text_geometry(id) else {
+            return String::new();
+        };
+        let Some((x, y)) = geometry.transform.inverse().map(|inverse| inverse.transform_point(x, y)) else {
+            return String::new();
+        };
+        let (ascent, descent) = geometry.metrics;
+        // A vertical column reaches half its width left of its center line
+        let vertical = geometry.lines.first().is_some_and(|line| line.vertical);
+        let reach = if vertical { (ascent + descent) / 2.0 } else { descent };
+        text_edit::caret_from_point(&geometry.lines, x - geometry.origin.0, y - geometry.origin.1, reach)
+            .map(|caret| geometry.caret_json(&caret))
+            .unwrap_or_default()
+    }
+
+    /// Caret before character `index` of a text object (clamped to the end of the text)
+    /// Returns JSON: { index, line, x1, y1, x2, y2 } as for text_hit, or "" if it is not
+    /// text or its font is not loaded
+    pub fn caret_rect(&self, id: &str, index: u32) -> String {
+        let Some(geometry) = self.text_geometry(id) else {
+            return String::new();
+        };
+        text_edit::caret_position(&geometry.lines, index as usize)
+            .map(|caret| geometry.caret_json(&caret))
+            .unwrap_or_default()
+    }
+
+    /// Start editing a text object in place, with the caret at the end of its content
+    /// Selects the text. Returns false if it is not text or its font is not loaded
+    pub fn begin_text_edit(&mut self, id: &str) -> bool {
+        let Some(SceneNode::Leaf { object: VectorObject::Text(text), .. }) = self.scene.get_node_by_id(id) else {
+            return false;
+        };
+        if self.fonts.resolve(&text.font).is_none() {
+            return false;
+        }
+        self.text_edit = Some(TextEditSession::new(id.to_string(), text));
+        self.selected_ids.clear();
+        self.selected_ids.insert(id.to_string());
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Insert text at the caret (line breaks start new lines)
+    /// Consecutive edits share one undo step. Returns false if no text is being edited
+    pub fn insert_text(&mut self, text: &str) -> bool {
+        self.edit_at_caret(|content, caret| Some(text_edit::insert(content, caret, text)))
+    }
+
+    /// Delete the character before the caret (Backspace)
+    /// Returns false if no text is being edited or the caret is at the start
+    pub fn delete_backward(&mut self) -> bool {
+        self.edit_at_caret(text_edit::delete_backward)
+    }
+
+    /// Delete the character after the caret (Delete)
+    /// Returns false if no text is being edited or the caret is at the end
+    pub fn delete_forward(&mut self) -> bool {
+        self.edit_at_caret(text_edit::delete_forward)
+    }
+
+    /// Move the caret backward or forward by "char" | "word" | "line" | "line_edge"
+    /// ("line" keeps the column, "line_edge" goes to the start or end of the line)
+    /// Returns false if no text is being edited or the unit is unknown
+    pub fn move_caret(&mut self, unit: &str, forward: bool) -> bool {
+        let Some(unit) = CaretUnit::from_name(unit) else {
+            return false;
+        };
+        let Some(text) = self.edited_text() else {
+            return false;
+        };
+        let (content, lines) = (text.content.clone(), self.text_lines(text));
+        if let Some(session) = &mut self.text_edit {
+            session.caret = text_edit::move_caret(&content, &lines, session.caret, unit, forward);
+        }
+        true
+    }
+
+    /// Put the caret before the character at `index` (clamped to the end of the content)
+    /// Returns false if no text is being edited
+    pub fn set_caret(&mut self, index: u32) -> bool {
+        let Some(length) = self.edited_text().map(|text| text.content.chars().count()) else {
+            return false;
+        };
+        if let Some(session) = &mut self.text_edit {
+            session.caret = (index as usize).min(length);
+        }
+        true
+    }
+
+    /// State of the text being edited
+    /// Returns JSON: { id, content, caret, line, column } (caret in characters, lines as
+    /// laid out), or "" if none
+    pub fn get_text_edit_state(&self) -> String {
+        let (Some(session), Some(text)) = (&self.text_edit, self.edited_text()) else {
+            return String::new();
+        };
+        let caret = session.caret.min(text.content.chars().count());
+        let (line, column) = text_edit::line_and_column(&self.text_lines(text), caret);
+        serde_json::json!({
+            "id": session.id,
+            "content": text.content,
+            "caret": caret,
+            "line": line,
+            "column": column,
+        })
+        .to_string()
+    }
+
+    /// Stop editing text; returns false if no text was being edited
+    pub fn end_text_edit(&mut self) -> bool {
+        self.text_edit.take().is_some()
+    }
+
+    // ==============================================
+    // Library APIs (swatches and shared styles)
+    // ==============================================
+
+    /// Add a named color swatch (any CSS color, stored as "#rrggbb"); records its own undo step
+    /// Returns the swatch ID, or "" if the color cannot be parsed
+    pub fn add_swatch(&mut self, name: &str, color: &str) -> String {
+        let Some(color) = normalize_color(color) else {
+            return String::new();
+        };
+        self.record_undo("Add swatch");
+        let id = self.scene.edit_settings().generate_swatch_id();
+        self.scene.edit_settings().swatches.push(Swatch { id: id.clone(), name: name.to_string(), color });
+        id
+    }
+
+    /// Rename and recolor a swatch; records its own undo step
+    /// Returns false for an unknown ID or an invalid color
+    pub fn update_swatch(&mut self, id: &str, name: &str, color: &str) -> bool {
+        let Some(color) = normalize_color(color) else {
+            return false;
+        };
+        if !self.scene.swatches.iter().any(|swatch| swatch.id == id) {
+            return false;
+        }
+        self.record_undo("Edit swatch");
+        if let Some(swatch) = self.scene.edit_settings().swatches.iter_mut().find(|swatch| swatch.id == id) {
+            swatch.name = name.to_string();
+            swatch.color = color;
+        }
+        true
+    }
+
+    /// Remove a swatch; records its own undo step. Returns false for an unknown ID
+    pub fn remove_swatch(&mut self, id: &str) -> bool {
+        if !self.scene.swatches.iter().any(|swatch| swatch.id == id) {
+            return false;
+        }
+        self.record_undo("Delete swatch");
+        self.scene.edit_settings().swatches.retain(|swatch| swatch.id != id);
+        true
+    }
+
+    /// List the swatches
+    /// Returns JSON: [{ id, name, color }]
+    pub fn get_swatches(&self) -> String {
+        serde_json::to_string(&self.scene.swatches).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Add a shared style from a style JSON ({ fill_color, stroke_color, stroke_width, ... }
+    /// as in the saved scene). Records its own undo step.
+    /// Returns the style ID, or "" for invalid JSON or values
+    pub fn add_shared_style(&mut self, name: &str, style_json: &str) -> String {
+        let Some(style) = parse_shared_style(&self.scene, style_json) else {
+            return String::new();
+        };
+        self.record_undo("Add shared style");
+        let id = self.scene.edit_settings().generate_style_id();
+        self.scene.edit_settings().shared_styles.push(SharedStyle { id: id.clone(), name: name.to_string(), style });
+        id
+    }
+
+    /// Turn the style of the first selected object into a shared style and link the object
+    /// Records its own undo step. Returns the style ID, or "" if no object is selected
+    pub fn create_shared_style_from_selection(&mut self, name: &str) -> String {
+        let Some((object_id, style)) = self.selected_ids.iter().find_map(|id| match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { style, .. }) => Some((id.clone(), style.clone())),
+            _ => None,
+        }) else {
+            return String::new();
+        };
+        self.record_undo("Create shared style");
+        let id = self.scene.edit_settings().generate_style_id();
+        let shared = SharedStyle { id: id.clone(), name: name.to_string(), style: ObjectStyle { shared_style: None, ..style } };
+        if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(&object_id) {
+            library::link_style(style, &shared);
+        }
+        self.scene.edit_settings().shared_styles.push(shared);
+        id
+    }
+
+    /// Replace a shared style's values and restyle every object linked to it
+    /// Records its own undo step. Returns false for an unknown ID or invalid JSON
+    pub fn update_shared_style(&mut self, id: &str, style_json: &str) -> bool {
+        let Some(style) = parse_shared_style(&self.scene, style_json) else {
+            return false;
+        };
+        let Some(index) = self.scene.shared_styles.iter().position(|shared| shared.id == id) else {
+            return false;
+        };
+        self.record_undo("Edit shared style");
+        self.scene.edit_settings().shared_styles[index].style = style;
+        let shared = self.scene.shared_styles[index].clone();
+        library::propagate_style(self.scene.edit(), &shared);
+        true
+    }
+
+    /// Rename a shared style; records its own undo step. Returns false for an unknown ID
+    pub fn rename_shared_style(&mut self, id: &str, name: &str) -> bool {
+        if !self.scene.shared_styles.iter().any(|shared| shared.id == id) {
+            return false;
+        }
+        self.record_undo("Rename shared style");
+        if let Some(shared) = self.scene.edit_settings().shared_styles.iter_mut().find(|shared| shared.id == id) {
+            shared.name = name.to_string();
+        }
+        true
+    }
+
+    /// Remove a shared style; linked objects keep their look but are unlinked
+    /// Records its own undo step. Returns false for an unknown ID
+    pub fn remove_shared_style(&mut self, id: &str) -> bool {
+        if !self.scene.shared_styles.iter().any(|shared| shared.id == id) {
+            return false;
+        }
+        self.record_undo("Delete shared style");
+        library::detach_style(self.scene.edit(), id);
+        self.scene.edit_settings().shared_styles.retain(|shared| shared.id != id);
+        true
+    }
+
+    /// List the shared styles with the number of objects linked to each
+    /// Returns JSON: [{ id, name, style, uses }]
+    pub fn get_shared_styles(&self) -> String {
+        let styles: Vec<serde_json::Value> = self
+            .scene
+            .shared_styles
+            .iter()
+            .map(|shared| {
+                let uses = library::style_uses(&self.scene, &shared.id);
+                serde_json::json!({ "id": shared.id, "name": shared.name, "style": shared.style, "uses": uses })
+            })
+            .collect();
+        serde_json::to_string(&styles).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Give the selected objects a shared style and link them to it
+    /// Records its own undo step. Returns false for an unknown ID
+    pub fn apply_shared_style(&mut self, id: &str) -> bool {
+        let Some(shared) = self.scene.shared_styles.iter().find(|shared| shared.id == id).cloned() else {
+            return false;
+        };
+        if !self.selected_ids.is_empty() {
+            self.record_undo("Apply shared style");
+        }
+        for object_id in &self.selected_ids.clone() {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(object_id) {
+                library::link_style(style, &shared);
+            }
+        }
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Unlink the selected objects from their shared style, keeping their look
+    /// Records its own undo step. Returns the number of objects unlinked
+    pub fn detach_shared_style(&mut self) -> u32 {
+        let linked: Vec<ObjectId> = self
+            .selected_ids
+            .iter()
+            .filter(|id| {
+                matches!(self.scene.get_node_by_id(id), Some(SceneNode::Leaf { style, .. }) if style.shared_style.is_some())
+            })
+            .cloned()
+            .collect();
+        if linked.is_empty() {
+            return 0;
+        }
+        self.record_undo("Detach shared style");
+        for id in &linked {
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(id) {
+                style.shared_style = None;
+            }
+        }
+        self.notify_selection_observer();
+        linked.len() as u32
+    }
+
+    // ==============================================
+    // Command Protocol (automation / headless builds)
+    // ==============================================
+
+    /// Run a batch of protocol commands (see the protocol module for the format)
+    /// Returns the JSON response batch with one response per command
+    pub fn execute_ops(&mut self, batch_json: &str) -> String {
+        protocol::execute_batch_json(self, batch_json)
+    }
+
+    // ==============================================
+    // Persistence APIs (Save/Load)
+    // ==============================================
+
+    /// Export the entire scene to a JSON string
+    pub fn export_scene_to_json(&self) -> String {
+        serde_json::to_string_pretty(&*self.scene).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Export the scene to a JSON string with its undo history, so a reloaded document
+    /// can still undo recent changes
+    /// max_bytes: about the most bytes the history may add to the document; the steps
+    /// nearest the current state are kept first
+    /// The steps are saved under "history", with objects shared between steps stored
+    /// once; import_scene_from_json restores them. The document is written compactly and
+    /// the history is kept small enough for the whole file to stay within the import
+    /// size limit.
+    pub fn export_scene_to_json_with_history(&self, max_bytes: usize) -> String {
+        let mut document = match serde_json::to_value(&*self.scene) {
+            Ok(document) => document,
+            Err(_) => return "{}".to_string(),
+        };
+        let document_bytes = document.to_string().len();
+        let max_bytes = max_bytes.min(self.import_limits.max_input_bytes.saturating_sub(document_bytes));
+        let history = history::saved(&self.undo_stack, &self.redo_stack, max_bytes);
+        if let (serde_json::Value::Object(fields), Ok(history)) = (&mut document, serde_json::to_value(&history)) {
+            fields.insert("history".to_string(), history);
+        }
+        serde_json::to_string(&document).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Import a scene from a JSON string, replacing the current scene
+    /// The document is checked against the import limits before it is accepted. Saved
+    /// history (see export_scene_to_json_with_history) replaces the undo history; history
+    /// that cannot be restored is dropped and the document still loads.
+    /// Throws an ImportError ({ code, message, location? }, also available from
+    /// get_last_import_error) if parsing or validation failed; the current scene is kept
+    pub fn import_scene_from_json(&mut self, json: &str) -> Result<(), JsValue> {
+        self.import_scene_checked(json).map_err(|error| to_js_value(&error))
+    }
+
+    /// Start importing a scene JSON document that arrives in chunks (for multi-megabyte files)
+    /// total_size: length of the whole document in bytes
+    /// Cancels any import in progress. Throws an ImportError if the size exceeds the
+    /// import limits
+    pub fn import_begin(&mut self, total_size: usize) -> Result<(), JsValue> {
+        self.import_begin_checked(total_size).map_err(|error| to_js_value(&error))
+    }
+
+    /// Feed the next chunk of an import started with import_begin
+    /// Size and nesting limits are checked as data arrives, and the objects the chunk
+    /// completes are parsed and checked, so no single call does all the work.
+    /// Returns the progress (0..1); throws an ImportError if the import failed, which
+    /// abandons it
+    pub fn import_chunk(&mut self, bytes: &[u8]) -> Result<f64, JsValue> {
+        self.import_chunk_checked(bytes).map_err(|error| to_js_value(&error))
+    }
+
+    /// Get the progress of the import in progress as JSON
+    /// Returns: { received, total, progress } or "{}" if no import is in progress
+    pub fn get_import_progress(&self) -> String {
+        match &self.pending_import {
+            Some(import) => serde_json::json!({
+                "received": import.received(),
+                "total": import.total(),
+                "progress": import.progress(),
+            })
+            .to_string(),
+            None => "{}".to_string(),
+        }
+    }
+
+    /// Check the received document as a whole and replace the scene, like
+    /// import_scene_from_json
+    /// Throws an ImportError on failure; the current scene is kept
+    pub fn import_finish(&mut self) -> Result<(), JsValue> {
+        self.import_finish_checked().map_err(|error| to_js_value(&error))
+    }
+
+    /// Abandon the import in progress
+    pub fn import_cancel(&mut self) {
+        self.pending_import = None;
+    }
+
+    /// Export the scene to a format, re-import it into a scratch document and compare
+    /// format: "json" (SVG and binary have no importer yet and report as unsupported)
+    /// Leaves the current document untouched.
+    /// Returns JSON fidelity report: { format, supported, checked_formats, unchecked_formats,
+    /// passed, objects_compared, max_deviation, tolerance, differences: [{ id, field, detail }], error? }
+    pub fn roundtrip_check(&self, format: &str) -> String {
+        let tolerance = match self.export_options.precision {
+            Some(digits) => 10f64.powi(-(digits as i32)),
+            None => roundtrip::DEFAULT_TOLERANCE,
+        };
+        let report = match format {
+            "json" => match self.parse_scene_json(&self.export_scene_to_json()) {
+                Ok((restored, _, _)) => roundtrip::compare_scenes(format, &self.scene, &restored, tolerance),
+                Err(error) => FidelityReport::failed(format, true, tolerance, error.message),
+            },
+            "svg" | "binary" => {
+                FidelityReport::failed(format, false, tolerance, format!("no importer for {}", format))
+            }
+            _ => FidelityReport::failed(format, false, tolerance, format!("unknown format {}", format)),
+        };
+        serde_json::to_string(&report).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Get the error from the last failed import as JSON
+    /// Returns: { code: "parse_error" | "too_many_nodes" | ..., message: string,
+    /// location?: { line?, column?, offset?, object_id? } } or "" if none
+    pub fn get_last_import_error(&self) -> String {
+        match &self.last_import_error {
+            Some(error) => serde_json::to_string(error).unwrap_or_default(),
+            None => String::new(),
+        }
+    }
+
+    /// Get how the last successful import was brought up to the current document format
+    /// Returns JSON: { detected_version, current_version, applied: [description] },
+    /// or "{}" if the last import failed or nothing was imported
+    pub fn get_last_import_report(&self) -> String {
+        match &self.last_import_report {
+            Some(report) => serde_json::to_string(report).unwrap_or_else(|_| "{}".to_string()),
+            None => "{}".to_string(),
+        }
+    }
+
+    /// Configure the resource limits applied to imported documents
+    pub fn set_import_limits(&mut self, max_input_bytes: usize, max_nodes: usize, max_path_commands: usize, max_depth: usize) {
+        self.import_limits = ImportLimits {
+            max_input_bytes,
+            max_nodes,
+            max_path_commands,
+            max_depth,
+        };
+    }
+
+    /// Clear the entire scene
+    pub fn clear_scene(&mut self) {
+        self.scene.replace(SceneGraph::new());
+        self.selected_ids.clear();
+        self.drag_state.end();
+        self.pen_state = PenState::Idle;
+        self.notify_selection_observer();
+    }
+
+    /// Export the document's canvas to SVG, with the document background
+    pub fn export_to_svg(&self) -> String {
+        crate::renderer::generate_svg_with_page(&self.scene, &SvgPageOptions::default(), &self.export_options)
+    }
+
+    /// Export the document to SVG with page options
+    /// options_json: {"background": bool (default true), "crop": bool, "padding": number}
+    /// background: false leaves the page transparent; crop fits the viewBox to the
+    /// drawing plus padding instead of the canvas.
+    /// Returns "" for invalid options
+    pub fn export_to_svg_with_options(&self, options_json: &str) -> String {
+        let Ok(page) = serde_json::from_str::<SvgPageOptions>(options_json) else {
+            return String::new();
+        };
+        crate::renderer::generate_svg_with_page(&self.scene, &page, &self.export_options)
+    }
+
+    /// Rasterize the document's canvas to PNG at the document's DPI
+    /// Returns the encoded PNG bytes, or an empty array if the bitmap cannot be created
+    pub fn export_to_png(&self) -> Vec<u8> {
+        let document = &self.scene.document;
+        raster::render_png(&self.scene, document.width, document.height, document.raster_scale()).unwrap_or_default()
+    }
+
+    /// Export the scene as a single-page vector PDF of width_mm x height_mm
+    /// The page's top-left corner is the document origin; one unit is 1/96 inch.
+    /// Returns "" for an invalid page size
+    pub fn export_to_pdf(&self, width_mm: f64, height_mm: f64) -> String {
+        pdf::generate_pdf(&self.scene, width_mm, height_mm, &self.export_options).unwrap_or_default()
+    }
+
+    /// Set the document's working color space: "srgb" (default) or "linear_srgb"
+    /// PNG export blends translucent colors in this space and SVG export declares it
+    /// (color-interpolation); the canvas view always blends like a browser, in sRGB.
+    /// Returns false for an unknown name
+    pub fn set_color_space(&mut self, name: &str) -> bool {
+        match ColorSpace::from_name(name) {
+            Some(space) => {
+                self.scene.edit_settings().color_space = space;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Set the canvas size in the document's units (see set_units)
+    /// The canvas is the page exported to SVG and PNG. Records its own undo step.
+    /// Returns false unless both sides are positive
+    pub fn set_canvas_size(&mut self, width: f64, height: f64) -> bool {
+        let units = self.scene.document.units;
+        let (width, height) = (units.to_document(width), units.to_document(height));
+        if !(width.is_finite() && width > 0.0 && height.is_finite() && height > 0.0) {
+            return false;
+        }
+        self.record_undo("Canvas size");
+        self.scene.edit_settings().document.width = width;
+        self.scene.edit_settings().document.height = height;
+        true
+    }
+
+    /// Set the page background: a CSS color (stored as "#rrggbb"), or "" / "none"
+    /// for a transparent page. Records its own undo step. Returns false for an invalid color
+    pub fn set_background_color(&mut self, color: &str) -> bool {
+        let Some(background) = style_color(color) else {
+            return false;
+        };
+        self.record_undo("Background color");
+        self.scene.edit_settings().document.background = background;
+        true
+    }
+
+    /// Set the unit system lengths are shown and entered in: "px" | "mm" | "in"
+    /// Scene coordinates stay in CSS pixels. Records its own undo step.
+    /// Returns false for an unknown name
+    pub fn set_units(&mut self, name: &str) -> bool {
+        let Some(units) = Units::from_name(name) else {
+            return false;
+        };
+        self.record_undo("Units");
+        self.scene.edit_settings().document.units = units;
+        true
+    }
+
+    /// Set the resolution of raster exports in pixels per inch (96 = one pixel per unit)
+    /// Records its own undo step. Returns false unless positive
+    pub fn set_dpi(&mut self, dpi: f64) -> bool {
+        if !(dpi.is_finite() && dpi > 0.0) {
+            return false;
+        }
+        self.record_undo("Resolution");
+        self.scene.edit_settings().document.dpi = dpi;
+        true
+    }
+
+    /// Get the document properties
+    /// Returns JSON: { width, height (in units), width_px, height_px, background (null =
+    /// transparent), units, dpi }
+    pub fn get_document_properties(&self) -> String {
+        let document = &self.scene.document;
+        serde_json::json!({
+            "width": document.units.from_document(document.width),
+            "height": document.units.from_document(document.height),
+            "width_px": document.width,
+            "height_px": document.height,
+            "background": document.background,
+            "units": document.units.name(),
+            "dpi": document.dpi,
+        })
+        .to_string()
+    }
+
+    /// Get the document's working color space name
+    pub fn get_color_space(&self) -> String {
+        self.scene.color_space.name().to_string()
+    }
+
+    /// Parse a CSS color ("#rgb[a]", "#rrggbb[aa]", "rgb[a]()", "hsl[a]()") for color pickers
+    /// Returns JSON: { hex: "#rrggbb", css, r, g, b, a (0..1), h (degrees), s, l (0..1) },
+    /// or "" if the color cannot be parsed
+    pub fn parse_color(&self, color: &str) -> String {
+        let Some(parsed) = Color::parse(color) else {
+            return String::new();
+        };
+        let (h, s, l) = parsed.to_hsl();
+        serde_json::json!({
+            "hex": parsed.to_hex(),
+            "css": parsed.to_css(),
+            "r": parsed.r,
+            "g": parsed.g,
+            "b": parsed.b,
+            "a": parsed.a,
+            "h": h,
+            "s": s,
+            "l": l,
+        })
+        .to_string()
+    }
+
+    /// Adjust a CSS color: operation "lighten" | "darken" | "saturate" | "desaturate"
+    /// (amount in 0..1 added to or taken from the HSL lightness or saturation) or
+    /// "alpha" (amount is the new alpha)
+    /// Returns the result as "#rrggbb" or "rgba(...)", or "" for an invalid color or operation
+    pub fn adjust_color(&self, color: &str, operation: &str, amount: f64) -> String {
+        let Some(parsed) = Color::parse(color).filter(|_| amount.is_finite()) else {
+            return String::new();
+        };
+        let adjusted = match operation {
+            "lighten" => parsed.lighten(amount),
+            "darken" => parsed.darken(amount),
+            "saturate" => parsed.saturate(amount),
+            "desaturate" => parsed.desaturate(amount),
+            "alpha" => parsed.with_alpha(amount),
+            _ => return String::new(),
+        };
+        adjusted.to_css()
+    }
+
+    /// Set the number of fractional digits written in exported documents
+    /// digits < 0: shortest exact representation (default)
+    /// Numbers are always written with a `.` decimal separator and without exponents.
+    pub fn set_export_precision(&mut self, digits: i32) {
+        self.export_options.precision = usize::try_from(digits).ok();
+    }
+
+    /// Export the scene to SVG with the viewBox fitted to the drawing
+    /// padding: extra space added around the content on every side
+    pub fn export_to_svg_cropped(&self, padding: f64) -> String {
+        crate::renderer::generate_svg_cropped(&self.scene, padding, &self.export_options)
+    }
+
+    /// Get the union of all object bounds in world space (for "fit canvas to drawing")
+    /// Returns JSON: [min_x, min_y, max_x, max_y] or "[]" if the scene is empty
+    pub fn get_scene_bounds(&self) -> String {
+        match geometry::scene_bounds(&self.scene) {
+            Some(bounds) => serde_json::to_string(&bounds.to_array()).unwrap_or_else(|_| "[]".to_string()),
+            None => "[]".to_string(),
+        }
+    }
+
+    /// Compute a pen-travel optimized stroke order for plotter / G-code export
+    /// Sibling paths are reordered (and may be drawn reversed); the scene z-order is not changed.
+    /// Returns: { order: [{ id, reversed }], travel_before: number, travel_after: number }
+    pub fn optimize_draw_order(&self) -> String {
+        let origin = (0.0, 0.0);
+        let steps = draw_order::plot_order(&self.scene.roots, TransformMatrix::identity(), origin);
+        let scene_steps = draw_order::plot_order_unoptimized(&self.scene.roots, TransformMatrix::identity());
+
+        let json = serde_json::json!({
+            "order": steps,
+            "travel_before": draw_order::plot_travel(&self.scene.roots, &scene_steps, origin),
+            "travel_after": draw_order::plot_travel(&self.scene.roots, &steps, origin),
+        });
+        serde_json::to_string(&json).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    // ==============================================
+    // Undo/Redo APIs
+    // ==============================================
+
+    /// Save a snapshot of the current scene for undo
+    /// Call this BEFORE making a destructive change
+    pub fn save_snapshot(&mut self) {
+        self.record_undo(history::DEFAULT_LABEL);
+    }
+
+    /// Save a snapshot for undo, labeled with the edit about to be made ("Move",
+    /// "Change fill") for the history list
+    pub fn save_named_snapshot(&mut self, label: &str) {
+        self.record_undo(label);
+    }
+
+    /// List the undo history: { entries: [label, ...], current }
+    /// Entries run oldest first; those from index `current` on have been undone and can
+    /// be redone.
+    #[wasm_bindgen(unchecked_return_type = "HistoryListing")]
+    pub fn get_history(&self) -> JsValue {
+        to_js_value(&self.history_listing())
+    }
+
+    /// get_history as a JSON string
+    pub fn get_history_json(&self) -> String {
+        serde_json::to_string(&self.history_listing()).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Undo or redo until `index` steps of the history are applied (0 = before the
+    /// first listed step)
+    /// Returns false if the index is past the end of the history
+    pub fn jump_to_history(&mut self, index: usize) -> bool {
+        if index > self.undo_stack.len() + self.redo_stack.len() {
+            return false;
+        }
+        while self.undo_stack.len() > index && self.undo() {}
+        while self.undo_stack.len() < index && self.redo() {}
+        true
+    }
+
+    /// Set the maximum number of undo states (older states are dropped immediately)
+    pub fn set_max_history(&mut self, max_history: usize) {
+        self.max_history = max_history;
+        self.trim_history();
+        let excess_redo = self.redo_stack.len().saturating_sub(max_history);
+        let dropped: Vec<HistoryEntry> = self.redo_stack.drain(..excess_redo).collect();
+        self.free_history(dropped);
+    }
+
+    /// Get the maximum number of undo states
+    pub fn get_max_history(&self) -> usize {
+        self.max_history
+    }
+
+    /// Set the memory budget of the undo history in bytes (applied immediately)
+    /// When a new step would exceed it, the oldest steps are dropped; the latest step
+    /// is always kept.
+    pub fn set_max_history_bytes(&mut self, max_bytes: usize) {
+        self.max_history_bytes = max_bytes;
+        self.trim_history();
+    }
+
+    /// Get the memory budget of the undo history in bytes
+    pub fn get_max_history_bytes(&self) -> usize {
+        self.max_history_bytes
+    }
+
+    /// Estimated memory held by the undo and redo history in bytes
+    /// Objects unchanged between steps are stored once and counted once.
+    pub fn history_memory_usage(&self) -> usize {
+        self.history_bytes
+    }
+
+    /// Start a bulk operation (e.g. generating thousands of shapes)
+    /// Records a single undo entry for the state before the operation, then suppresses
+    /// snapshots until end_bulk_mode, so per-item save_snapshot calls cost nothing.
+    pub fn begin_bulk_mode(&mut self) {
+        if !self.history_suspended {
+            self.record_undo("Bulk edit");
+            self.history_suspended = true;
+        }
+    }
+
+    /// Finish a bulk operation; the whole operation undoes as one step
+    pub fn end_bulk_mode(&mut self) {
+        self.history_suspended = false;
+    }
+
+    /// Check if a bulk operation is in progress
+    pub fn is_bulk_mode(&self) -> bool {
+        self.history_suspended
+    }
+
+    /// Apply a list of edits to top-level objects in one call
+    /// operations: JSON array of
+    ///   { op: "add", object: { Rectangle: {...} } | ..., transform?, style?, name? }
+    ///   { op: "set_style", ids: [...], style: { fill_color?, stroke_width?, ... } }
+    ///   { op: "transform", ids: [...], matrix: { a, b, c, d, tx, ty } }
+    ///   { op: "reorder", id, index }
+    ///   { op: "delete", ids: [...] }
+    /// Styles are patches in the saved scene's field names. Either every operation
+    /// applies, as one undo step (and one change for the next frame), or none does.
+    /// Returns the IDs of the added objects; throws an OperationError ({ index, message })
+    /// naming the operation that failed
+    pub fn apply_operations(&mut self, operations_json: &str) -> Result<Vec<String>, JsValue> {
+        let operations = validation::check_json_input(operations_json, &self.import_limits)
+            .and_then(|()| serde_json::from_str::<Vec<Operation>>(operations_json).map_err(|e| ImportError::json(&e)))
+            .map_err(|error| to_js_value(&OperationError { index: None, message: error.message }))?;
+        self.apply_operation_list(operations).map_err(|error| to_js_value(&error))
+    }
+
+    /// Undo the last operation
+    /// Returns true if undo was performed, false if nothing to undo
+    pub fn undo(&mut self) -> bool {
+        if let Some(previous) = self.undo_stack.pop() {
+            // Save current state to redo stack, under the label of the edit it redoes
+            let current = HistoryEntry::new(previous.label.clone(), &self.scene, Some(&previous));
+            self.history_bytes += current.unshared_bytes();
+            self.redo_stack.push(current);
+            
+            // Restore previous state
+            self.scene.replace(previous.to_scene());
+            self.free_history([previous]);
+            self.nudge_selection = None;
+            if let Some(session) = &mut self.text_edit {
+                session.recorded = false;
+            }
+            self.selected_ids.clear();
+            self.drag_state.end();
+            self.notify_selection_observer();
+            
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Redo the last undone operation
+    /// Returns true if redo was performed, false if nothing to redo
+    pub fn redo(&mut self) -> bool {
+        if let Some(next) = self.redo_stack.pop() {
+            // Save current state to undo stack
+            let current = HistoryEntry::new(next.label.clone(), &self.scene, Some(&next));
+            self.history_bytes += current.unshared_bytes();
+            self.undo_stack.push(current);
+            
+            // Restore next state
+            self.scene.replace(next.to_scene());
+            self.free_history([next]);
+            self.nudge_selection = None;
+            if let Some(session) = &mut self.text_edit {
+                session.recorded = false;
+            }
+            self.selected_ids.clear();
+            self.drag_state.end();
+            self.notify_selection_observer();
+            
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Check if undo is available
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Check if redo is available
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Get the size of the undo stack
+    pub fn undo_stack_size(&self) -> usize {
+        self.undo_stack.len()
+    }
+
+    /// Get the size of the redo stack
+    pub fn redo_stack_size(&self) -> usize {
+        self.redo_stack.len()
+    }
+
+    /// Move selected objects by delta
+    /// Note: For precise movement, use begin_move_drag/update_move_drag/end_drag instead
+    pub fn move_selected(&mut self, dx: f64, dy: f64) {
+        let translation = TransformMatrix::translate(dx, dy);
+        for id in &self.selected_ids.clone() {
+            // Apply translation to existing transform
+            self.transform_in_world(id, &translation, None);
+        }
+        self.notify_selection_observer();
+    }
+
+    /// Align the selected objects to the bounds of the whole selection
+    /// mode: "left" | "right" | "center_h" | "top" | "bottom" | "center_v"
+    /// Records its own undo step.
+    /// Returns false for an unknown mode or an empty selection
+    pub fn align_selected(&mut self, mode: &str) -> bool {
+        match self.selection_world_bounds() {
+            Some(target) => self.align_selection_to(mode, &target),
+            None => false,
+        }
+    }
+
+    /// Align each selected object to the canvas (0, 0)-(width, height)
+    /// Same modes and return value as align_selected
+    pub fn align_selected_to_canvas(&mut self, mode: &str, width: f64, height: f64) -> bool {
+        self.align_selection_to(mode, &BoundingBox::new(0.0, 0.0, width, height))
+    }
+
+    /// Mirror the selection left-to-right around the center of its bounding box
+    /// Records its own undo step. Returns false if nothing is selected
+    pub fn flip_selected_horizontal(&mut self) -> bool {
+        self.transform_selection_around_center("Flip horizontal", |cx, cy| TransformMatrix::scale_around(-1.0, 1.0, cx, cy))
+    }
+
+    /// Mirror the selection top-to-bottom around the center of its bounding box
+    /// Records its own undo step. Returns false if nothing is selected
+    pub fn flip_selected_vertical(&mut self) -> bool {
+        self.transform_selection_around_center("Flip vertical", |cx, cy| TransformMatrix::scale_around(1.0, -1.0, cx, cy))
+    }
+
+    /// Rotate the selection around the center of its bounding box
+    /// angle_degrees: clockwise on screen (90 = "rotate 90° CW"), like rotate drags
+    /// Records its own undo step. Returns false if nothing is selected
+    pub fn rotate_selected(&mut self, angle_degrees: f64) -> bool {
+        if !angle_degrees.is_finite() {
+            return false;
+        }
+        let angle = angle_degrees.to_radians();
+        self.transform_selection_around_center("Rotate", |cx, cy| TransformMatrix::rotate_around(-angle, cx, cy))
+    }
+
+    /// Combine the selected objects with a boolean operation and replace them with the result
+    /// op: "union" | "subtract" | "intersect" | "exclude". Subtract removes every other
+    /// operand from the bottom-most one. The result is a single path in world coordinates
+    /// (curves are flattened) that takes the style and z-position of the bottom-most
+    /// operand for subtract and of the top-most one otherwise; it becomes the selection.
+    /// Records its own undo step.
+    /// Returns the new ID, or "" if the operation is unknown, fewer than two objects are
+    /// selected, a selected object is not a plain shape or path (a group, instance or
+    /// text; convert text to outlines first), or the result is empty
+    pub fn boolean_op_selected(&mut self, op: &str) -> String {
+        let Some(op) = BooleanOp::from_name(op) else {
+            return String::new();
+        };
+        // Operands in z-order, bottom first
+        let mut operands = Vec::new();
+        for (index, node) in self.scene.roots.iter().enumerate() {
+            if !self.selected_ids.contains(node.id()) {
+                continue;
+            }
+            let SceneNode::Leaf { object, transform, style, .. } = node else {
+                return String::new();
+            };
+            if matches!(object, VectorObject::Text(_)) {
+                return String::new();
+            }
+            let commands = geometry::transform_path_commands(&geometry::object_to_path_commands(object), transform);
+            operands.push((index, style.clone(), path_ops::flatten(&commands, path_ops::FLATTEN_TOLERANCE)));
+        }
+        if operands.len() < 2 {
+            return String::new();
+        }
+
+        let polygons: Vec<_> = operands.iter().map(|(_, _, polygons)| polygons.clone()).collect();
+        let result = path_ops::boolean(op, &polygons);
+        if result.is_empty() {
+            return String::new();
+        }
+        let source = if op == BooleanOp::Subtract { 0 } else { operands.len() - 1 };
+        let (source_index, style, _) = operands.swap_remove(source);
+        // Removing the operands shifts the source's slot down by the operands below it
+        let insert_at = source_index - operands.iter().filter(|(index, _, _)| *index < source_index).count();
+
+        self.record_undo(format!("Boolean {}", op.name()));
+        let ids: Vec<String> = self.selected_ids.drain().collect();
+        self.scene.edit().remove_roots(&ids);
+        let id = self.scene.generate_id();
+        self.scene.edit().roots.insert(
+            insert_at,
+            SceneNode::Leaf {
+                id: id.clone(),
+                object: VectorObject::Path {
+                    commands: path_ops::polygons_to_commands(&result),
+                    is_closed: true,
+                    anchor_types: Vec::new(),
+                },
+                transform: TransformMatrix::identity(),
+                style,
+                name: None,
+            },
+        );
+        self.selected_ids.insert(id.clone());
+        self.notify_selection_observer();
+        id
+    }
+
+    /// Replace an object's stroke with a filled path of the stroke's outline
+    /// The outline follows the stroke width, caps and joins (miters are limited like on
+    /// the canvas); curves are flattened. The stroke color becomes the fill and the
+    /// object loses its stroke. Records its own undo step.
+    /// Returns false if the object is not found or has no visible stroke
+    pub fn outline_stroke(&mut self, id: &str) -> bool {
+        let Some(SceneNode::Leaf { object, style, .. }) = self.scene.get_node_by_id(id) else {
+            return false;
+        };
+        if style.stroke_color.is_none() {
+            return false;
+        }
+        let is_closed = !matches!(object, VectorObject::Path { is_closed: false, .. });
+        let outline = stroke::outline_stroke(
+            &geometry::object_to_path_commands(object),
+            is_closed,
+            style.stroke_width,
+            style.line_cap,
+            style.line_join,
+            stroke::CANVAS_MITER_LIMIT,
+        );
+        if outline.is_empty() || self.scene.get_node_by_id(id).is_none() {
+            return false;
+        }
+
+        self.record_undo("Outline stroke");
+        if let Some(SceneNode::Leaf { object, style, .. }) = self.scene.edit_node(id) {
+            *object = VectorObject::Path {
+                commands: path_ops::polygons_to_commands(&outline),
+                is_closed: true,
+                anchor_types: Vec::new(),
+            };
+            style.fill_color = style.stroke_color.take();
+        }
+        true
+    }
+
+    /// Add a copy of an object grown (distance > 0) or shrunk (distance < 0) by a distance
+    /// Works on the filled region in the object's own coordinates; corners are mitered
+    /// and curves are flattened. The copy keeps the object's style and transform, goes
+    /// directly above it and becomes the selection. Records its own undo step.
+    /// Returns the new ID, or "" if the object is not a root shape or nothing is left
+    pub fn offset_path(&mut self, id: &str, distance: f64) -> String {
+        let Some(index) = self.scene.roots.iter().position(|node| node.id() == id) else {
+            return String::new();
+        };
+        let SceneNode::Leaf { object, transform, style, .. } = &self.scene.roots[index] else {
+            return String::new();
+        };
+        let result = stroke::offset_path(&geometry::object_to_path_commands(object), distance);
+        if result.is_empty() {
+            return String::new();
+        }
+        let (transform, style) = (*transform, style.clone());
+
+        self.record_undo("Offset path");
+        let new_id = self.scene.generate_id();
+        self.scene.edit().roots.insert(
+            index + 1,
+            SceneNode::Leaf {
+                id: new_id.clone(),
+                object: VectorObject::Path {
+                    commands: path_ops::polygons_to_commands(&result),
+                    is_closed: true,
+                    anchor_types: Vec::new(),
+                },
+                transform,
+                style,
+                name: None,
+            },
+        );
+        self.selected_ids.clear();
+        self.selected_ids.insert(new_id.clone());
+        self.notify_selection_observer();
+        new_id
+    }
+
+    /// Re-fit an object's bounding box to the world axes (Illustrator's "Reset Bounding Box")
+    /// Rotation and skew are baked into the geometry, leaving only translation and a
+    /// uniform scale in the transform, so the object looks the same but its selection box
+    /// becomes axis-aligned. Rectangles and ellipses become paths; groups pass the
+    /// rotation on to their children. Instances are left alone (their symbol is shared),
+    /// and so is text, which would lose its content (convert it to outlines first).
+    /// Records its own undo step.
+    /// Returns false if the object was not found, is an instance or text, or has no rotation
+    pub fn reset_bounding_box(&mut self, id: &str) -> bool {
+        let transform = match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Instance { .. }) | Some(SceneNode::Leaf { object: VectorObject::Text(_), .. }) | None => {
+                return false
+            }
+            Some(node) => *node.transform(),
+        };
+        if transform.b.abs() < 1e-12 && transform.c.abs() < 1e-12 {
+            return false;
+        }
+
+        // Keep translation and uniform scale (so strokes render the same); bake the rest
+        let scale = transform.determinant().abs().sqrt();
+        if scale < 1e-12 {
+            return false;
+        }
+        let (tx, ty) = transform.translation();
+        let kept = TransformMatrix::translate(tx, ty).multiply(&TransformMatrix::scale(scale, scale));
+        let baked = TransformMatrix { tx: 0.0, ty: 0.0, ..TransformMatrix::scale(1.0 / scale, 1.0 / scale).multiply(&transform) };
+
+        self.record_undo("Reset bounding box");
+        match self.scene.edit_node(id) {
+            Some(SceneNode::Leaf { object, transform, .. }) => {
+                let commands = geometry::object_to_path_commands(object);
+                let (is_closed, anchor_types) = match object {
+                    VectorObject::Path { is_closed, anchor_types, .. } => (*is_closed, anchor_types.clone()),
+                    _ => (true, Vec::new()),
+                };
+                *object = VectorObject::Path {
+                    commands: geometry::transform_path_commands(&commands, &baked),
+                    is_closed,
+                    anchor_types,
+                };
+                *transform = kept;
+            }
+            Some(SceneNode::Group { children, transform, .. }) => {
+                for child in children {
+                    let child_transform = child.transform_mut();
+                    *child_transform = baked.multiply(child_transform);
+                }
+                *transform = kept;
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Move the selection by a keyboard nudge
+    /// direction: "up" | "down" | "left" | "right"; step: distance (1 by default, 10 with Shift)
+    /// With the grid enabled, the selection's top-left corner lands on grid lines instead.
+    /// Consecutive nudges of the same selection are recorded as a single undo entry.
+    /// Returns false if nothing was moved
+    pub fn nudge_selected(&mut self, direction: &str, step: f64) -> bool {
+        let (ux, uy) = match direction {
+            "up" => (0.0, -1.0),
+            "down" => (0.0, 1.0),
+            "left" => (-1.0, 0.0),
+            "right" => (1.0, 0.0),
+            _ => return false,
+        };
+        if self.selected_ids.is_empty() || !step.is_finite() {
+            return false;
+        }
+
+        let (mut dx, mut dy) = (ux * step, uy * step);
+        if let Some(bounds) = self.selection_world_bounds() {
+            (dx, dy) = self.snapper().snap_nudge((bounds.min_x, bounds.min_y), (dx, dy));
+        }
+
+        if self.nudge_selection.as_ref() != Some(&self.selected_ids) {
+            self.record_undo(self.selection_label("Nudge"));
+            self.nudge_selection = Some(self.selected_ids.clone());
+        }
+        self.move_selected(dx, dy);
+        true
+    }
+
+    /// Configure the document grid
+    /// size: spacing of the major lines; subdivisions: snapping divisions between them (min 1)
+    /// When enabled, move/resize drags, pen anchors and nudges snap to grid intersections.
+    pub fn set_grid(&mut self, size: f64, subdivisions: u32, enabled: bool) {
+        self.scene.edit_settings().grid = GridSettings { size, subdivisions: subdivisions.max(1), enabled };
+    }
+
+    /// Get the document grid as JSON: { size, subdivisions, enabled }
+    pub fn get_grid(&self) -> String {
+        serde_json::to_string(&self.scene.grid).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// Snap a shape being drag-created from (anchor_x, anchor_y) to the pointer (x, y)
+    /// The pointer snaps to other objects' edges and centers, the new shape's center to
+    /// their centers, and its width/height to theirs, within `threshold` world units.
+    /// Axes with no nearby object fall back to the grid.
+    /// Returns JSON: { point: [x, y], hints: [{ axis, kind: "edge" | "center" | "width" | "height",
+    /// target_id, value }] } so the UI can show e.g. "W matches obj_12"
+    pub fn snap_shape_creation(&self, anchor_x: f64, anchor_y: f64, x: f64, y: f64, threshold: f64) -> String {
+        let (point, hints) = snap::snap_creation_box((anchor_x, anchor_y), (x, y), &self.snap_targets(), threshold);
+
+        let grid_point = self.snapper().snap_point(x, y);
+        let snapped_x = hints.iter().any(|hint| hint.axis == "x");
+        let snapped_y = hints.iter().any(|hint| hint.axis == "y");
+        let point = [
+            if snapped_x { point.0 } else { grid_point.0 },
+            if snapped_y { point.1 } else { grid_point.1 },
+        ];
+        serde_json::json!({ "point": point, "hints": hints }).to_string()
+    }
+
+    /// Add a ruler guide stored with the document
+    /// axis: "horizontal" (the line y = position) | "vertical" (x = position)
+    /// Guides attract drags, pen anchors and new shapes, and are never exported.
+    /// Returns the guide ID, or "" for an unknown axis
+    pub fn add_guide(&mut self, axis: &str, position: f64) -> String {
+        let axis = match GuideAxis::from_name(axis) {
+            Some(axis) if position.is_finite() => axis,
+            _ => return String::new(),
+        };
+        let id = self.scene.edit_settings().generate_guide_id();
+        self.scene.edit_settings().guides.push(RulerGuide { id: id.clone(), axis, position });
+        id
+    }
+
+    /// Remove a ruler guide; returns false if no guide has this ID
+    pub fn remove_guide(&mut self, id: &str) -> bool {
+        let count = self.scene.guides.len();
+        self.scene.edit_settings().guides.retain(|guide| guide.id != id);
+        self.scene.guides.len() != count
+    }
+
+    /// List the ruler guides as JSON: [{ id, axis: "horizontal" | "vertical", position }, ...]
+    pub fn list_guides(&self) -> String {
+        serde_json::to_string(&self.scene.guides).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Duplicate the selected objects, offset by (dx, dy), and select the copies
+    /// Records its own undo step.
+    /// Returns JSON array of the new IDs
+    pub fn duplicate_selected(&mut self, dx: f64, dy: f64) -> String {
+        if self.selected_ids.is_empty() {
+            return "[]".to_string();
+        }
+        self.record_undo(self.selection_label("Duplicate"));
+        let new_ids = self.duplicate_selection(dx, dy);
+        self.last_duplicate_offset = Some((dx, dy));
+        serde_json::to_string(&new_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Duplicate the selection again with the offset of the last duplicate ("transform again")
+    /// Returns JSON array of the new IDs, or "[]" if there is nothing to repeat
+    pub fn repeat_duplicate(&mut self) -> String {
+        match self.last_duplicate_offset {
+            Some((dx, dy)) => self.duplicate_selected(dx, dy),
+            None => "[]".to_string(),
+        }
+    }
+
+    /// Replace a root object with a grid of copies of it (step and repeat)
+    /// Cell (row, col) is offset by (col * dx, row * dy); with rotate_step (degrees,
+    /// clockwise) each successive cell is also rotated that much more around the source's
+    /// center, which gives rotated arrays. Copies of an instance stay instances of the
+    /// same symbol. The source and its copies are wrapped in a new group that becomes
+    /// the selection. Records its own undo step.
+    /// Returns the group ID, or "" if the object is not a root, the grid is empty or
+    /// larger than the node limit
+    pub fn step_and_repeat(&mut self, id: &str, rows: u32, cols: u32, dx: f64, dy: f64, rotate_step: f64) -> String {
+        let cells = rows as usize * cols as usize;
+        if cells == 0 || cells > self.import_limits.max_nodes || ![dx, dy, rotate_step].iter().all(|v| v.is_finite()) {
+            return String::new();
+        }
+        if !self.scene.roots.iter().any(|node| node.id() == id) {
+            return String::new();
+        }
+        let (cx, cy) = match self.object_frame(id) {
+            Some((world, _, _)) => world.center(),
+            None => return String::new(),
+        };
+
+        // Cell (0, 0) is the source itself
+        let placements: Vec<TransformMatrix> = (1..cells)
+            .map(|index| {
+                let (row, col) = ((index / cols as usize) as f64, (index % cols as usize) as f64);
+                let angle = (rotate_step * index as f64).to_radians();
+                TransformMatrix::translate(col * dx, row * dy).multiply(&TransformMatrix::rotate_around(-angle, cx, cy))
+            })
+            .collect();
+
+        self.record_undo("Step and repeat");
+        match self.scene.edit().repeat_root(id, &placements) {
+            Some(group_id) => {
+                self.selected_ids.clear();
+                self.selected_ids.insert(group_id.clone());
+                self.notify_selection_observer();
+                group_id
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Begin a move drag operation - saves initial transforms
+    /// duplicate: drag copies of the selection instead of the originals (Alt-drag);
+    /// the copies become the selection
+    pub fn begin_move_drag(&mut self, start_x: f64, start_y: f64, duplicate: bool) {
+        if duplicate {
+            self.duplicate_selection(0.0, 0.0);
+        }
+        let mut initial_transforms = std::collections::HashMap::new();
+        for id in &self.selected_ids {
+            if let Some(transform) = self.scene.get_node_by_id(id).map(SceneNode::transform) {
+                initial_transforms.insert(id.clone(), *transform);
+            }
+        }
+        let start_bounds = self.selection_world_bounds();
+        let top_left = start_bounds
+            .map(|bounds| (bounds.min_x, bounds.min_y))
+            .unwrap_or((start_x, start_y));
+        self.drag_state.begin(
+            DragMode::Moving,
+            start_x,
+            start_y,
+            initial_transforms,
+            top_left, // Reference point for grid snapping
+        );
+        self.drag_state.start_bounds = start_bounds;
+        if self.screen_space_tolerances {
+            self.drag_state.threshold = self.tolerance(DRAG_THRESHOLD);
+        }
+        self.smart_guides.prepare(self.snap_targets());
+    }
+
+    /// Update move drag - applies delta from baseline (no cumulative error)
+    /// constrained: lock movement to the horizontal, vertical or 45° direction (Shift held)
+    pub fn update_move_drag(&mut self, current_x: f64, current_y: f64, constrained: bool) {
+        if !self.drag_state.is_active() || self.drag_state.mode != DragMode::Moving {
+            return;
+        }
+        if !self.drag_state.past_threshold(current_x, current_y) {
+            return;
+        }
+        
+        let (mut dx, mut dy) = self.drag_state.delta(current_x, current_y);
+        if constrained {
+            (dx, dy) = drag_state::constrain_delta(dx, dy);
+        }
+        (dx, dy) = self.snapper().snap_move(self.drag_state.pivot, (dx, dy));
+        if let Some(bounds) = self.drag_state.start_bounds {
+            let moved = bounds.transform(&TransformMatrix::translate(dx, dy));
+            let (guide_x, guide_y) = self.snapper().snap_bounds_to_guides(&moved);
+            dx += guide_x.unwrap_or(0.0);
+            dy += guide_y.unwrap_or(0.0);
+
+            let moved = bounds.transform(&TransformMatrix::translate(dx, dy));
+            let (snap_x, snap_y) = self.smart_guides.snap_bounds(&moved);
+            dx += snap_x.unwrap_or(0.0);
+            dy += snap_y.unwrap_or(0.0);
+        }
+        let translation = TransformMatrix::translate(dx, dy);
+        
+        for id in &self.selected_ids.clone() {
+            if let Some(initial) = self.drag_state.get_initial_transform(id).copied() {
+                // Apply translation to INITIAL transform (not current!)
+                self.transform_in_world(id, &translation, Some(initial));
+            }
+        }
+        self.notify_selection_observer();
+    }
+
+    /// End drag operation
+    pub fn end_drag(&mut self) {
+        self.drag_state.end();
+        self.smart_guides.clear();
+    }
+
+    /// Check if a drag operation is in progress
+    pub fn is_dragging(&self) -> bool {
+        self.drag_state.is_active()
+    }
+
+    /// Begin a resize drag operation
+    /// handle_index: 0=TopLeft, 1=TopRight, 2=BottomRight, 3=BottomLeft,
+    ///               4=Top, 5=Right, 6=Bottom, 7=Left
+    pub fn begin_resize_drag(&mut self, start_x: f64, start_y: f64, handle_index: u8) {
+        let handle = match HandleIndex::from_index(handle_index) {
+            Some(handle) => handle,
+            None => return,
+        };
+
+        // Get initial transforms and calculate pivot (opposite handle)
+        let mut initial_transforms = std::collections::HashMap::new();
+        let mut pivot = (0.0, 0.0);
+        let mut axes = ((0.0, 0.0), (0.0, 0.0));
+        let mut center = (0.0, 0.0);
+        
+        // Use the same selection box that get_handle_positions reports
+        if let Some(overlay) = self.generate_selection_overlays().first() {
+            let corners = &overlay.corners;
+            pivot = handle.opposite().position(corners);
+            axes = (
+                (corners[1].0 - corners[0].0, corners[1].1 - corners[0].1),
+                (corners[3].0 - corners[0].0, corners[3].1 - corners[0].1),
+            );
+            center = ((corners[0].0 + corners[2].0) / 2.0, (corners[0].1 + corners[2].1) / 2.0);
+            
+            // Store initial transforms for all selected objects
+            for sel_id in &self.selected_ids {
+                if let Some(transform) = self.scene.get_node_by_id(sel_id).map(SceneNode::transform) {
+                    initial_transforms.insert(sel_id.clone(), *transform);
+                }
+            }
+        }
+
+        self.drag_state.begin(
+            DragMode::Resizing(handle),
+            start_x,
+            start_y,
+            initial_transforms,
+            pivot,
+        );
+        self.drag_state.axes = axes;
+        self.drag_state.center = center;
+        self.smart_guides.prepare(self.snap_targets());
+    }
+
+    /// Update resize drag - scales from pivot point
+    /// Corner handles scale width and height independently; edge handles scale one axis
+    /// from_center: scale symmetrically around the selection center (Alt held)
+    /// instead of the opposite handle
+    /// keep_aspect: lock the aspect ratio (Shift held); resizing is free-form otherwise
+    /// Both modifiers may change between calls during a drag.
+    pub fn update_resize_drag(&mut self, current_x: f64, current_y: f64, from_center: bool, keep_aspect: bool) {
+        let handle = match &self.drag_state.mode {
+            DragMode::Resizing(h) => *h,
+            _ => return,
+        };
+
+        // Scale around the pivot in the selection box's own frame
+        let pivot = if from_center { self.drag_state.center } else { self.drag_state.pivot };
+        let (mut x, mut y) = self.snapper().snap_point(current_x, current_y);
+        let (snap_x, snap_y) = self.smart_guides.snap_bounds(&BoundingBox::new(x, y, x, y));
+        x += snap_x.unwrap_or(0.0);
+        y += snap_y.unwrap_or(0.0);
+        let scale_matrix = drag_state::resize_matrix(
+            handle,
+            pivot,
+            self.drag_state.axes,
+            self.drag_state.start_point,
+            (x, y),
+            keep_aspect,
+            self.resize_flip,
+        );
+        
+        for id in &self.selected_ids.clone() {
+            if let Some(initial) = self.drag_state.get_initial_transform(id).copied() {
+                // Apply scale to INITIAL transform
+                self.transform_in_world(id, &scale_matrix, Some(initial));
+            }
+        }
+        self.notify_selection_observer();
+    }
+
+    /// Configure smart guides: snapping of move/resize drags to other objects' edges,
+    /// centers and equal spacing within `threshold` world units (disabled by default)
+    pub fn set_smart_guides(&mut self, enabled: bool, threshold: f64) {
+        self.smart_guides.enabled = enabled;
+        self.smart_guides.threshold = threshold.max(0.0);
+    }
+
+    /// Get the guide lines of the current drag's snap (for drawing)
+    /// Returns JSON: [{ kind: "edge" | "center" | "spacing", x1, y1, x2, y2 }, ...]
+    pub fn get_smart_guides(&self) -> String {
+        serde_json::to_string(self.smart_guides.active_guides()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Enable or disable flipping when a resize handle is dragged past the pivot
+    /// When disabled, the selection stops shrinking at 10% of its size. Enabled by default.
+    pub fn set_resize_flip(&mut self, enabled: bool) {
+        self.resize_flip = enabled;
+    }
+
+    /// Get handle positions for the first selected object (for hit testing in frontend)
+    /// Returns JSON: 8 points [[x,y], ...] in handle index order
+    /// (corners TL, TR, BR, BL, then edge midpoints T, R, B, L) or "[]" if no selection
+    pub fn get_handle_positions(&self) -> String {
+        if let Some(overlay) = self.generate_selection_overlays().first() {
+            let positions: Vec<(f64, f64)> = HandleIndex::ALL
+                .iter()
+                .map(|handle| handle.position(&overlay.corners))
+                .collect();
+            serde_json::to_string(&positions).unwrap_or_else(|_| "[]".to_string())
+        } else {
+            "[]".to_string()
+        }
+    }
+
+    /// Index of the selection handle at a world point (within half the handle size of
+    /// its center, see get_selection_overlay), or -1 if none. The nearest handle wins
+    pub fn hit_handle(&self, x: f64, y: f64) -> i32 {
+        let Some(overlay) = self.generate_selection_overlays().into_iter().next() else {
+            return -1;
+        };
+        let reach = overlay.handle_size / 2.0;
+        HandleIndex::ALL
+            .iter()
+            .enumerate()
+            .map(|(index, handle)| {
+                let (hx, hy) = handle.position(&overlay.corners);
+                (index, (x - hx).abs().max((y - hy).abs()))
+            })
+            .filter(|(_, distance)| *distance <= reach)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(-1, |(index, _)| index as i32)
+    }
+
+    /// Get the center point of the selection bounding box
+    /// Returns JSON: [x, y] or "[]" if no selection
+    pub fn get_selection_center(&self) -> String {
+        if let Some(overlay) = self.generate_selection_overlays().first() {
+            // Calculate center from corners
+            let corners = &overlay.corners;
+            let cx = (corners[0].0 + corners[1].0 + corners[2].0 + corners[3].0) / 4.0;
+            let cy = (corners[0].1 + corners[1].1 + corners[2].1 + corners[3].1) / 4.0;
+            serde_json::to_string(&[cx, cy]).unwrap_or_else(|_| "[]".to_string())
+        } else {
+            "[]".to_string()
+        }
+    }
+
+    /// Begin a rotation drag operation
+    /// Uses the center of the bounding box as pivot
+    pub fn begin_rotate_drag(&mut self, start_x: f64, start_y: f64) {
+        // Get initial transforms and calculate center as pivot
+        let mut initial_transforms = std::collections::HashMap::new();
+        let mut center = (0.0, 0.0);
+        
+        // Calculate center from selection overlay
+        if let Some(overlay) = self.generate_selection_overlays().first() {
+            let corners = &overlay.corners;
+            center = (
+                (corners[0].0 + corners[1].0 + corners[2].0 + corners[3].0) / 4.0,
+                (corners[0].1 + corners[1].1 + corners[2].1 + corners[3].1) / 4.0,
+            );
+            
+            // Store initial transforms for all selected objects
+            for id in &self.selected_ids {
+                if let Some(transform) = self.scene.get_node_by_id(id).map(SceneNode::transform) {
+                    initial_transforms.insert(id.clone(), *transform);
+                }
+            }
+        }
+
+        self.drag_state.begin(
+            DragMode::Rotating,
+            start_x,
+            start_y,
+            initial_transforms,
+            center, // Pivot is the center
+        );
+    }
+
+    /// Update rotation drag - rotates around center
+    pub fn update_rotate_drag(&mut self, current_x: f64, current_y: f64) {
+        self.apply_rotate_drag(current_x, current_y, 0.0);
+    }
+
+    /// Update rotation drag with the angle quantized to multiples of snap_degrees
+    /// (e.g. 15 or 45 while Shift is held)
+    pub fn update_rotate_drag_snapped(&mut self, current_x: f64, current_y: f64, snap_degrees: f64) {
+        self.apply_rotate_drag(current_x, current_y, snap_degrees);
+    }
+
+    /// Get the rotation applied by the current rotate drag in degrees (for an on-canvas readout)
+    /// Clockwise on screen is positive; 0 when no rotate drag is active
+    pub fn get_drag_rotation(&self) -> f64 {
+        if self.drag_state.mode == DragMode::Rotating {
+            self.drag_state.angle.to_degrees()
+        } else {
+            0.0
+        }
+    }
+
+    // ==============================================
+    // Viewport APIs
+    // ==============================================
+
+    /// Set the canvas view: the screen position of the world origin (pixels) and the
+    /// zoom (screen pixels per world unit, 0.01 to 256). Not an undo step.
+    /// Returns false for values out of range
+    pub fn set_viewport(&mut self, pan_x: f64, pan_y: f64, zoom: f64) -> bool {
+        match Viewport::new(pan_x, pan_y, zoom) {
+            Some(viewport) => {
+                self.viewport = viewport;
+                self.frame.invalidate();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Zoom the view by `factor` around a screen point, which stays over the same world
+    /// point (wheel zoom at the cursor). Returns false for a factor that is not positive
+    pub fn zoom_viewport_at(&mut self, screen_x: f64, screen_y: f64, factor: f64) -> bool {
+        match self.viewport.zoomed_at(screen_x, screen_y, factor) {
+            Some(viewport) => {
+                self.viewport = viewport;
+                self.frame.invalidate();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the canvas view
+    /// Returns JSON: { pan_x, pan_y, zoom }
+    pub fn get_viewport(&self) -> String {
+        serde_json::to_string(&self.viewport).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    /// World point shown at a screen point
+    /// Returns JSON: [x, y]
+    pub fn screen_to_world(&self, x: f64, y: f64) -> String {
+        serde_json::to_string(&self.viewport.screen_to_world(x, y)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Screen point a world point is shown at
+    /// Returns JSON: [x, y]
+    pub fn world_to_screen(&self, x: f64, y: f64) -> String {
+        serde_json::to_string(&self.viewport.world_to_screen(x, y)).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Measure tool tolerances in screen pixels instead of world units, so they keep
+    /// their size on screen at any zoom: selection handle size, pen anchor snapping and
+    /// path closing distances. A move drag then also waits until the mouse has moved a
+    /// few pixels, so a click does not nudge the selection. Off by default
+    pub fn set_screen_space_tolerances(&mut self, enabled: bool) {
+        self.screen_space_tolerances = enabled;
+    }
+
+    // ==============================================
+    // Pen Tool APIs
+    // ==============================================
+
+    /// Handle pen tool mouse down
+    /// Returns true if near start point (for closing path)
+    pub fn pen_down(&mut self, x: f64, y: f64) -> bool {
+        let close_threshold = self.tolerance(PEN_CLOSE_THRESHOLD);
+
+        // Land exactly on earlier anchors of the path being drawn, otherwise on the grid
+        let (x, y) = self
+            .pen_state
+            .snap_to_anchor(x, y, self.tolerance(PEN_SNAP_THRESHOLD))
+            .unwrap_or_else(|| self.snapper().snap_point(x, y));
         
-        // Get the first selected object's opposite corner as pivot
-        if let Some(id) = self.selected_ids.iter().next() {
-            if let Some(overlay) = self.generate_selection_overlays().iter().find(|o| &o.id == id) {
-                let opposite_idx = handle.opposite() as usize;
-                pivot = overlay.corners[opposite_idx];
+        match &self.pen_state {
+            PenState::Idle => {
+                // Start a new path
+                self.pen_state = PenState::Drawing {
+                    commands: vec![PathCommand::MoveTo { x, y }],
+                    start_point: (x, y),
+                    last_anchor: (x, y),
+                    drag_start_anchor: None,
+                    drag_handle: None,
+                    is_dragging: false,
+                };
+                false
+            }
+            PenState::Drawing { start_point, commands, .. } => {
+                // Check if closing the path
+                if commands.len() >= 2 {
+                    let dx = x - start_point.0;
+                    let dy = y - start_point.1;
+                    if (dx * dx + dy * dy).sqrt() < close_threshold {
+                        return true; // Signal that we should close
+                    }
+                }
                 
-                // Store initial transforms for all selected objects
-                for sel_id in &self.selected_ids {
-                    if let Some(node) = self.scene.get_node_by_id(sel_id) {
-                        if let SceneNode::Leaf { transform, .. } = node {
-                            initial_transforms.insert(sel_id.clone(), *transform);
-                        }
+                // Mark with FIXED endpoint position (drag_start_anchor)
+                if let PenState::Drawing { is_dragging, drag_handle, drag_start_anchor, .. } = &mut self.pen_state {
+                    *is_dragging = false;
+                    *drag_start_anchor = Some((x, y)); // FIXED endpoint!
+                    *drag_handle = Some((x, y)); // Initially same as click position
+                }
+                false
+            }
+        }
+    }
+
+    /// Snap a point to the anchors of the in-progress pen path (for hover feedback)
+    /// Returns JSON: [x, y] of the anchor the next click would land on, or "[]" if none is in range
+    pub fn pen_snap_point(&self, x: f64, y: f64) -> String {
+        match self.pen_state.snap_to_anchor(x, y, self.tolerance(PEN_SNAP_THRESHOLD)) {
+            Some(point) => serde_json::to_string(&point).unwrap_or_else(|_| "[]".to_string()),
+            None => "[]".to_string(),
+        }
+    }
+
+    /// Handle pen tool mouse move (for dragging to create curves)
+    pub fn pen_move(&mut self, x: f64, y: f64) {
+        if let PenState::Drawing { drag_handle, is_dragging, .. } = &mut self.pen_state {
+            *drag_handle = Some((x, y));
+            *is_dragging = true;
+        }
+    }
+
+    /// Handle pen tool mouse up - confirm the anchor
+    pub fn pen_up(&mut self, _x: f64, _y: f64) {
+        let new_state = match &self.pen_state {
+            PenState::Drawing { commands, start_point, last_anchor, drag_start_anchor, drag_handle, is_dragging } => {
+                let mut new_commands = commands.clone();
+                
+                if *is_dragging {
+                    // Use drag_start_anchor as the FIXED endpoint
+                    if let (Some((end_x, end_y)), Some((cp2x, cp2y))) = (drag_start_anchor, drag_handle) {
+                        // CP1 = start point (C-curve: straight exit from start)
+                        // CP2 = mouse position during drag
+                        let cp1x = last_anchor.0;
+                        let cp1y = last_anchor.1;
+                        
+                        new_commands.push(PathCommand::CurveTo {
+                            x1: cp1x, y1: cp1y,
+                            x2: *cp2x, y2: *cp2y,
+                            x: *end_x, y: *end_y,
+                        });
+                        
+                        Some(PenState::Drawing {
+                            commands: new_commands,
+                            start_point: *start_point,
+                            last_anchor: (*end_x, *end_y), // New anchor is at endpoint
+                            drag_start_anchor: None,
+                            drag_handle: None,
+                            is_dragging: false,
+                        })
+                    } else {
+                        None
+                    }
+                } else if let Some((end_x, end_y)) = drag_start_anchor {
+                    // Simple click - add a line to where user clicked
+                    new_commands.push(PathCommand::LineTo { x: *end_x, y: *end_y });
+                    
+                    Some(PenState::Drawing {
+                        commands: new_commands,
+                        start_point: *start_point,
+                        last_anchor: (*end_x, *end_y),
+                        drag_start_anchor: None,
+                        drag_handle: None,
+                        is_dragging: false,
+                    })
+                } else {
+                    None
+                }
+            }
+            PenState::Idle => None,
+        };
+        
+        if let Some(state) = new_state {
+            self.pen_state = state;
+        }
+    }
+
+    /// Close the current path and add it to the scene (is_closed = true)
+    /// Called when user clicks on start point
+    pub fn pen_close(&mut self) -> String {
+        if let PenState::Drawing { mut commands, .. } = std::mem::take(&mut self.pen_state) {
+            commands.push(PathCommand::ClosePath);
+            
+            let id = self.scene.generate_id();
+            let path = VectorObject::Path { commands, is_closed: true, anchor_types: Vec::new() };
+            self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, TransformMatrix::identity()));
+            
+            self.pen_state = PenState::Idle;
+            return id;
+        }
+        String::new()
+    }
+
+    /// Finish the current path without closing it (is_closed = false)
+    /// Called when user presses Enter key
+    pub fn pen_finish(&mut self) -> String {
+        if let PenState::Drawing { commands, .. } = std::mem::take(&mut self.pen_state) {
+            // Don't add ClosePath command - leave path open
+            if commands.len() < 2 {
+                // Need at least 2 points to make a valid open path
+                self.pen_state = PenState::Idle;
+                return String::new();
+            }
+            
+            let id = self.scene.generate_id();
+            let path = VectorObject::Path { commands, is_closed: false, anchor_types: Vec::new() };
+            self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, TransformMatrix::identity()));
+            
+            self.pen_state = PenState::Idle;
+            return id;
+        }
+        String::new()
+    }
+
+    /// Cancel pen drawing without saving
+    pub fn pen_cancel(&mut self) {
+        self.pen_state = PenState::Idle;
+    }
+
+    /// Check if pen tool is currently drawing
+    pub fn is_pen_drawing(&self) -> bool {
+        self.pen_state.is_drawing()
+    }
+
+    /// Get current pen path preview for rendering (null when not drawing)
+    #[wasm_bindgen(unchecked_return_type = "PenPreview | null")]
+    pub fn get_pen_preview(&self) -> JsValue {
+        to_js_value(&self.pen_state.preview())
+    }
+
+    /// get_pen_preview as a JSON string ({} when not drawing)
+    /// Returns: { commands: [...], last_anchor: [x, y], handle: [x, y] | null, is_dragging: bool, preview_curve: {...} | null }
+    pub fn get_pen_preview_json(&self) -> String {
+        match self.pen_state.preview() {
+            Some(preview) => serde_json::to_string(&preview).unwrap_or_else(|_| "{}".to_string()),
+            None => "{}".to_string(),
+        }
+    }
+
+    // ==============================================
+    // Brush Tool APIs
+    // ==============================================
+
+    /// Set the width of new brush strokes at full pressure
+    pub fn set_brush_width(&mut self, width: f64) -> bool {
+        if width <= 0.0 || !width.is_finite() {
+            return false;
+        }
+        self.brush_width = width;
+        true
+    }
+
+    pub fn get_brush_width(&self) -> f64 {
+        self.brush_width
+    }
+
+    /// Start a brush stroke at (x, y) with pen pressure in [0, 1]
+    /// Devices without pressure should pass 0.5 (what browsers report for mice).
+    /// time: the pointer event's timeStamp in milliseconds, or undefined. When the samples
+    /// are timed, faster parts of the stroke draw thinner and smoother
+    pub fn brush_down(&mut self, x: f64, y: f64, pressure: f64, time: Option<f64>) {
+        let mut stroke = BrushStroke::new(self.brush_width);
+        stroke.add_sample(x, y, pressure, time);
+        self.brush = Some(stroke);
+    }
+
+    /// Add a sample to the current brush stroke (time as for brush_down)
+    /// Returns true if the preview changed
+    pub fn brush_move(&mut self, x: f64, y: f64, pressure: f64, time: Option<f64>) -> bool {
+        match self.brush.as_mut() {
+            Some(stroke) => stroke.add_sample(x, y, pressure, time),
+            None => false,
+        }
+    }
+
+    /// Finish the brush stroke and commit its outline as a filled path
+    /// Returns the new object's ID, or "" if nothing was drawn
+    pub fn brush_up(&mut self) -> String {
+        let Some(stroke) = self.brush.take() else {
+            return String::new();
+        };
+        let outline = stroke.outline();
+        if outline.is_empty() {
+            return String::new();
+        }
+
+        self.record_undo("Brush stroke");
+        let id = self.scene.generate_id();
+        let path = VectorObject::Path {
+            commands: path_ops::polygons_to_commands(&outline),
+            is_closed: true,
+            anchor_types: Vec::new(),
+        };
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, TransformMatrix::identity()));
+        if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(&id) {
+            style.stroke_color = None;
+        }
+        id
+    }
+
+    /// Cancel brush drawing without saving
+    pub fn brush_cancel(&mut self) {
+        self.brush = None;
+    }
+
+    /// Check if the brush tool is currently drawing
+    pub fn is_brush_drawing(&self) -> bool {
+        self.brush.is_some()
+    }
+
+    /// Get the current brush stroke preview as JSON for rendering
+    /// Returns: [{ x, y, radius }, ...] (one disc per sample, smoothed and narrowed by speed)
+    pub fn get_brush_preview(&self) -> String {
+        let Some(stroke) = &self.brush else {
+            return "[]".to_string();
+        };
+        serde_json::to_string(&stroke.profile()).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    // ==============================================
+    // Eraser Tool APIs
+    // ==============================================
+
+    /// Erase along a stroke from every root shape it touches
+    /// samples_json: JSON array of world points [{ "x": 0, "y": 0 }, ...]; the eraser is a
+    /// disc of the given radius swept along them. Filled shapes lose the swept region,
+    /// open paths are split into separate paths, and objects with nothing left are
+    /// deleted. Touched paths are flattened to line segments. Text is left alone
+    /// (convert it to outlines to erase it). Records its own undo step.
+    /// Returns: { changed: [...], created: [...], removed: [...] }, or "" for invalid input
+    pub fn erase_stroke(&mut self, samples_json: &str, radius: f64) -> String {
+        let Ok(samples) = serde_json::from_str::<Vec<EraserSample>>(samples_json) else {
+            return String::new();
+        };
+        let region = eraser::eraser_region(&samples, radius);
+        let Some(reach) = region.iter().flatten().fold(None, |bounds: Option<BoundingBox>, &(x, y)| {
+            let point = BoundingBox::new(x, y, x, y);
+            Some(bounds.map_or(point, |bounds| bounds.union(&point)))
+        }) else {
+            return String::new();
+        };
+
+        // Work out every edit first so nothing changes if no object is touched
+        let mut edits = Vec::new();
+        for (index, node) in self.scene.roots.iter().enumerate() {
+            let SceneNode::Leaf { object, transform, .. } = node else {
+                continue;
+            };
+            if matches!(object, VectorObject::Text(_)) {
+                continue;
+            }
+            let Some(bounds) = geometry::object_world_bounds(object, transform) else {
+                continue;
+            };
+            let overlaps = bounds.min_x <= reach.max_x
+                && reach.min_x <= bounds.max_x
+                && bounds.min_y <= reach.max_y
+                && reach.min_y <= bounds.max_y;
+            let Some(inverse) = transform.inverse().filter(|_| overlaps) else {
+                continue;
+            };
+            let is_closed = !matches!(object, VectorObject::Path { is_closed: false, .. });
+            let local_region = eraser::transform_region(&region, &inverse);
+            match eraser::erase(&geometry::object_to_path_commands(object), is_closed, &local_region) {
+                Erased::Untouched => {}
+                erased => edits.push((index, erased)),
+            }
+        }
+
+        let (mut changed, mut created, mut removed) = (Vec::new(), Vec::new(), Vec::new());
+        if !edits.is_empty() {
+            self.record_undo("Erase");
+        }
+        // Back to front so earlier indices stay valid while pieces are inserted
+        for (index, erased) in edits.into_iter().rev() {
+            let SceneNode::Leaf { id, object, transform, style, .. } = &mut self.scene.edit().roots[index] else {
+                continue;
+            };
+            match erased {
+                Erased::Untouched => {}
+                Erased::Removed => {
+                    let id = id.clone();
+                    self.scene.edit().roots.remove(index);
+                    self.selected_ids.remove(&id);
+                    removed.push(id);
+                }
+                Erased::Shape(commands) => {
+                    *object = VectorObject::Path { commands, is_closed: true, anchor_types: Vec::new() };
+                    changed.push(id.clone());
+                }
+                Erased::Pieces(pieces) => {
+                    let mut pieces = pieces.into_iter();
+                    if let Some(first) = pieces.next() {
+                        *object = VectorObject::Path { commands: first, is_closed: false, anchor_types: Vec::new() };
+                    }
+                    changed.push(id.clone());
+                    let (transform, style) = (*transform, style.clone());
+                    for (offset, commands) in pieces.enumerate() {
+                        let id = self.scene.generate_id();
+                        self.scene.edit().roots.insert(
+                            index + 1 + offset,
+                            SceneNode::Leaf {
+                                id: id.clone(),
+                                object: VectorObject::Path { commands, is_closed: false, anchor_types: Vec::new() },
+                                transform,
+                                style: style.clone(),
+                                name: None,
+                            },
+                        );
+                        created.push(id);
                     }
                 }
             }
         }
+        self.notify_selection_observer();
+        let result = serde_json::json!({ "changed": changed, "created": created, "removed": removed });
+        serde_json::to_string(&result).unwrap_or_else(|_| "{}".to_string())
+    }
+
+    // ==============================================
+    // Image Trace APIs
+    // ==============================================
+
+    /// Vectorize a PNG into filled paths, one per gray level, and select them
+    /// options_json: { threshold (0..255, default 128), levels (1..8 gray levels, default 1),
+    /// min_area (pixels, default 4), smoothing (0 = polygons .. 1.33, default 1),
+    /// scale (world units per pixel), x, y (world position of the top-left corner) }
+    /// Records its own undo step.
+    /// Returns JSON array of the new IDs, bottom to top ("[]" if nothing was traced)
+    pub fn trace_image(&mut self, image_bytes: &[u8], options_json: &str) -> String {
+        let options = if options_json.trim().is_empty() {
+            TraceOptions::default()
+        } else {
+            match serde_json::from_str::<TraceOptions>(options_json) {
+                Ok(options) => options,
+                Err(_) => return "[]".to_string(),
+            }
+        };
+        let layers = match trace::trace_png(image_bytes, &options) {
+            Some(layers) if !layers.is_empty() => layers,
+            _ => return "[]".to_string(),
+        };
+
+        self.record_undo("Trace image");
+        let mut new_ids = Vec::new();
+        for layer in layers {
+            let id = self.scene.generate_id();
+            let path = VectorObject::Path { commands: layer.commands, is_closed: true, anchor_types: Vec::new() };
+            self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, TransformMatrix::identity()));
+            if let Some(SceneNode::Leaf { style, .. }) = self.scene.edit_node(&id) {
+                style.fill_color = Some(layer.color);
+                style.stroke_color = None;
+                style.fill_rule = FillRule::EvenOdd;
+            }
+            new_ids.push(id);
+        }
+        self.selected_ids = new_ids.iter().cloned().collect();
+        self.notify_selection_observer();
+        serde_json::to_string(&new_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    // ==============================================
+    // Path Editing APIs (Direct Selection Tool)
+    // ==============================================
+
+    /// Check if the first selected object is a Path
+    pub fn selected_is_path(&self) -> bool {
+        if let Some(id) = self.selected_ids.iter().next() {
+            if let Some(SceneNode::Leaf { object, .. }) = self.scene.get_node_by_id(id) {
+                return matches!(object, VectorObject::Path { .. });
+            }
+        }
+        false
+    }
+
+    /// Get path points for the specified object as JSON
+    /// Returns: [ { "x": f64, "y": f64, "type": "move"|"line"|"curve", "anchor"?: "corner"|"smooth" }, ... ]
+    /// "anchor" is only present once an anchor type has been set on the path.
+    /// Quadratic curves and arcs are listed as the cubic curves editing turns them into.
+    pub fn get_path_points(&self, id: &str) -> String {
+        let Some((commands, anchor_types, transform)) = self.editable_path(id) else {
+            return "[]".to_string();
+        };
+        // Quadratics and arcs were promoted to curves by editable_path; ClosePath has no anchor
+        let points: Vec<PathPoint> = commands
+            .iter()
+            .filter_map(|cmd| match *cmd {
+                PathCommand::MoveTo { x, y } => Some((x, y, PathPointKind::Move)),
+                PathCommand::LineTo { x, y } => Some((x, y, PathPointKind::Line)),
+                PathCommand::CurveTo { x, y, .. } => Some((x, y, PathPointKind::Curve)),
+                _ => None,
+            })
+            .enumerate()
+            .map(|(index, (x, y, kind))| {
+                // Transform local coords to world coords
+                let (x, y) = transform.transform_point(x, y);
+                PathPoint { x, y, kind, anchor: anchor_types.get(index).copied() }
+            })
+            .collect();
+        serde_json::to_string(&points).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Get an object's outline as SVG path data, in the object's own coordinates
+    /// Rectangles and ellipses are written as their equivalent path.
+    /// Returns "" if the object is not found or is not a leaf
+    pub fn get_path_d(&self, id: &str) -> String {
+        match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { object: VectorObject::Path { commands, is_closed, .. }, .. }) => {
+                path_data::to_path_data(commands, *is_closed)
+            }
+            Some(SceneNode::Leaf { object, .. }) => path_data::to_path_data(&geometry::object_to_path_commands(object), true),
+            _ => String::new(),
+        }
+    }
+
+    /// Replace an object's geometry with parsed SVG path data (object coordinates)
+    /// Shapes become paths; the path is closed if the data contains a close command.
+    /// The data is validated like imported documents. Records its own undo step.
+    /// Returns false if the object is not a leaf or the data is invalid
+    /// (details are available from get_last_import_error)
+    pub fn set_path_d(&mut self, id: &str, d: &str) -> bool {
+        if !matches!(self.scene.get_node_by_id(id), Some(SceneNode::Leaf { .. })) {
+            return false;
+        }
+        let path = path_data::parse_path_data(d).and_then(|commands| {
+            let is_closed = commands.contains(&PathCommand::ClosePath);
+            let path = VectorObject::Path { commands, is_closed, anchor_types: Vec::new() };
+            validation::validate_object(id, &path, &self.import_limits)?;
+            Ok(path)
+        });
+        let path = match path {
+            Ok(path) => path,
+            Err(error) => {
+                self.last_import_error = Some(error);
+                return false;
+            }
+        };
+
+        self.record_undo("Edit path data");
+        if let Some(SceneNode::Leaf { object, .. }) = self.scene.edit_node(id) {
+            *object = path;
+        }
+        self.last_import_error = None;
+        true
+    }
+
+    /// Cut a path at the location nearest to a world point (scissors tool)
+    /// Shapes are converted to paths first; text is not cut (convert it to outlines
+    /// first). A closed path is opened at the cut and keeps its ID; an open path is
+    /// split in two, the part after the cut becoming a new path directly above the
+    /// original. Records its own undo step.
+    /// Returns JSON array of the resulting path IDs ("[]" if nothing was cut)
+    pub fn split_path_at(&mut self, id: &str, x: f64, y: f64) -> String {
+        let Some(index) = self.scene.roots.iter().position(|node| node.id() == id) else {
+            return "[]".to_string();
+        };
+        let SceneNode::Leaf { object, transform, style, .. } = &self.scene.roots[index] else {
+            return "[]".to_string();
+        };
+        if matches!(object, VectorObject::Text(_)) {
+            return "[]".to_string();
+        }
+        let Some(inverse) = transform.inverse() else {
+            return "[]".to_string();
+        };
+        let (local_x, local_y) = inverse.transform_point(x, y);
+        let commands = geometry::to_cubic_commands(&geometry::object_to_path_commands(object));
+        let cut = path_edit::nearest_location(&commands, local_x, local_y)
+            .and_then(|location| path_edit::cut_at(&commands, &location));
+        let Some(cut) = cut else {
+            return "[]".to_string();
+        };
+        let (transform, style) = (*transform, style.clone());
+
+        self.record_undo("Split path");
+        let path = |commands: Vec<PathCommand>| {
+            let is_closed = commands.contains(&PathCommand::ClosePath);
+            VectorObject::Path { commands, is_closed, anchor_types: Vec::new() }
+        };
+        let mut ids = vec![id.to_string()];
+        let (first, second) = match cut {
+            PathCut::Opened(commands) => (commands, None),
+            PathCut::Split(first, second) => (first, Some(second)),
+        };
+        if let SceneNode::Leaf { object, .. } = &mut self.scene.edit().roots[index] {
+            *object = path(first);
+        }
+        if let Some(commands) = second {
+            let new_id = self.scene.generate_id();
+            self.scene.edit().roots.insert(
+                index + 1,
+                SceneNode::Leaf { id: new_id.clone(), object: path(commands), transform, style, name: None },
+            );
+            ids.push(new_id);
+        }
+        serde_json::to_string(&ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Join two open paths into one at their nearest end points
+    /// The ends are connected with a straight segment, or with `average` merged into one
+    /// anchor at their midpoint. The result replaces the first path (keeping its style
+    /// and transform) and the second path is deleted. Records its own undo step.
+    /// Returns false unless both are open paths with a single subpath
+    pub fn join_paths(&mut self, id_a: &str, id_b: &str, average: bool) -> bool {
+        let position = |id: &str| self.scene.roots.iter().position(|node| node.id() == id);
+        let (Some(index_a), Some(index_b)) = (position(id_a), position(id_b)) else {
+            return false;
+        };
+        let (
+            SceneNode::Leaf { object: VectorObject::Path { commands: a, is_closed: false, .. }, transform: transform_a, .. },
+            SceneNode::Leaf { object: VectorObject::Path { commands: b, is_closed: false, .. }, transform: transform_b, .. },
+        ) = (&self.scene.roots[index_a], &self.scene.roots[index_b])
+        else {
+            return false;
+        };
+        if index_a == index_b {
+            return false;
+        }
+        // Bring the second path into the first one's coordinates
+        let Some(inverse) = transform_a.inverse() else {
+            return false;
+        };
+        let a = geometry::to_cubic_commands(a);
+        let b = geometry::to_cubic_commands(&geometry::transform_path_commands(b, &inverse.multiply(transform_b)));
+        let Some(joined) = path_edit::join(&a, &b, average) else {
+            return false;
+        };
+
+        self.record_undo("Join paths");
+        if let SceneNode::Leaf { object, .. } = &mut self.scene.edit().roots[index_a] {
+            *object = VectorObject::Path { commands: joined, is_closed: false, anchor_types: Vec::new() };
+        }
+        self.scene.edit().roots.remove(index_b);
+        self.selected_ids.remove(id_b);
+        self.notify_selection_observer();
+        true
+    }
+
+    /// Insert an anchor into a path segment without changing its shape
+    /// Segments are numbered in drawing order, the closing segment of a closed
+    /// subpath included; t in (0, 1) is the parameter along the segment.
+    /// Records its own undo step.
+    /// Returns the new anchor's index in get_path_points, or None
+    pub fn insert_path_point(&mut self, id: &str, segment_index: usize, t: f64) -> Option<u32> {
+        let (commands, _, _) = self.editable_path(id)?;
+        let command_index = path_edit::segment_command_index(&commands, segment_index)?;
+        self.subdivide_path(id, command_index, t)
+    }
+
+    /// Insert an anchor on a path where it passes closest to a world point
+    /// Returns the new anchor's index in get_path_points, or None if the nearest
+    /// location is an existing anchor
+    pub fn insert_path_point_at(&mut self, id: &str, x: f64, y: f64) -> Option<u32> {
+        let (commands, _, transform) = self.editable_path(id)?;
+        let (local_x, local_y) = transform.inverse()?.transform_point(x, y);
+        let location = path_edit::nearest_location(&commands, local_x, local_y)?;
+        self.subdivide_path(id, location.command_index, location.t)
+    }
+
+    /// Set whether a path anchor is a corner or smooth ("corner" | "smooth")
+    /// Making an anchor smooth aligns its handles on one line and mirrors their
+    /// length, giving it handles first if its segments are straight. Corners keep
+    /// their handles. `index` is the anchor's index in get_path_points.
+    /// Records its own undo step. Returns false for unknown paths, anchors or types
+    pub fn set_anchor_type(&mut self, id: &str, index: usize, anchor_type: &str) -> bool {
+        let Some(anchor_type) = AnchorType::from_name(anchor_type) else {
+            return false;
+        };
+        let Some((commands, _, _)) = self.editable_path(id) else {
+            return false;
+        };
+        let anchor_count = commands.iter().filter(|command| **command != PathCommand::ClosePath).count();
+        if index >= anchor_count {
+            return false;
+        }
+        let smoothed = match anchor_type {
+            AnchorType::Smooth => match path_edit::smooth_anchor(&commands, index) {
+                Some(smoothed) => Some(smoothed),
+                None => return false,
+            },
+            AnchorType::Corner => None,
+        };
+        if self.scene.get_node_by_id(id).is_none() {
+            return false;
+        }
+
+        self.record_undo("Anchor type");
+        self.promote_path(id);
+        if let Some(SceneNode::Leaf { object: VectorObject::Path { commands, anchor_types, .. }, .. }) =
+            self.scene.edit_node(id)
+        {
+            anchor_types.resize(anchor_count, AnchorType::Corner);
+            if let Some((smoothed, inserted)) = smoothed {
+                *commands = smoothed;
+                if let Some(inserted) = inserted {
+                    // The new anchor doubles the subpath's start point
+                    let twin = path_edit::subpath_start_anchor(commands, inserted)
+                        .map_or(AnchorType::Corner, |start| if start == index { anchor_type } else { anchor_types[start] });
+                    anchor_types.insert(inserted, twin);
+                }
+            }
+            anchor_types[index] = anchor_type;
+        }
+        true
+    }
 
-        self.drag_state.begin(
-            DragMode::Resizing(handle),
-            start_x,
-            start_y,
-            initial_transforms,
-            pivot,
-        );
+    /// Get path anchors with their bezier handles as JSON (world coordinates)
+    /// Returns: [ { "x", "y", "anchor": "corner"|"smooth", "in": [x, y] | null, "out": [x, y] | null }, ... ]
+    /// in the same order as get_path_points. A handle is null next to a straight segment.
+    pub fn get_path_anchors(&self, id: &str) -> String {
+        let Some((commands, anchor_types, transform)) = self.editable_path(id) else {
+            return "[]".to_string();
+        };
+        let to_world = |(x, y): (f64, f64)| {
+            let (wx, wy) = transform.transform_point(x, y);
+            [wx, wy]
+        };
+        let anchors: Vec<serde_json::Value> = path_edit::anchor_handles(&commands)
+            .into_iter()
+            .enumerate()
+            .map(|(index, anchor)| {
+                let [x, y] = to_world((anchor.x, anchor.y));
+                serde_json::json!({
+                    "x": x,
+                    "y": y,
+                    "anchor": anchor_types.get(index).copied().unwrap_or_default().name(),
+                    "in": anchor.handle_in.map(to_world),
+                    "out": anchor.handle_out.map(to_world),
+                })
+            })
+            .collect();
+        serde_json::to_string(&anchors).unwrap_or_else(|_| "[]".to_string())
     }
 
-    /// Update resize drag - scales from pivot point
-    pub fn update_resize_drag(&mut self, current_x: f64, current_y: f64) {
-        let (handle, pivot) = match &self.drag_state.mode {
-            DragMode::Resizing(h) => (*h, self.drag_state.pivot),
-            _ => return,
+    /// Move a bezier handle of a path anchor to a world position
+    /// which: "in" (control point of the segment arriving at the anchor) or "out".
+    /// With `mirror`, a smooth anchor's opposite handle follows (kept opposite at the
+    /// same length); without it the handles are broken and the anchor becomes a corner.
+    /// Like update_path_point, call save_snapshot when the drag starts.
+    /// Returns false if the path, anchor or handle does not exist
+    pub fn update_path_handle(
+        &mut self,
+        id: &str,
+        anchor_index: usize,
+        which: &str,
+        world_x: f64,
+        world_y: f64,
+        mirror: bool,
+    ) -> bool {
+        let Some(side) = HandleSide::from_name(which) else {
+            return false;
+        };
+        self.promote_path(id);
+        let Some(SceneNode::Leaf { object: VectorObject::Path { commands, anchor_types, .. }, transform, .. }) =
+            self.scene.edit_node(id)
+        else {
+            return false;
+        };
+        let Some(inverse) = transform.inverse() else {
+            return false;
         };
+        let smooth = anchor_types.get(anchor_index) == Some(&AnchorType::Smooth);
+        let local = inverse.transform_point(world_x, world_y);
+        if !path_edit::set_handle(commands, anchor_index, side, local, mirror && smooth) {
+            return false;
+        }
+        if smooth && !mirror {
+            anchor_types[anchor_index] = AnchorType::Corner;
+        }
+        true
+    }
 
-        let (start_x, start_y) = self.drag_state.start_point;
-        
-        // Calculate distance from pivot at start and current positions
-        let start_dx = start_x - pivot.0;
-        let start_dy = start_y - pivot.1;
-        let current_dx = current_x - pivot.0;
-        let current_dy = current_y - pivot.1;
-        
-        // Calculate scale factors with minimum to prevent zero/negative scale
-        let start_dist = (start_dx * start_dx + start_dy * start_dy).sqrt().max(1.0);
-        let current_dist = (current_dx * current_dx + current_dy * current_dy).sqrt().max(1.0);
-        
-        // Uniform scale to maintain aspect ratio
-        let scale = current_dist / start_dist;
-        let scale = scale.max(0.1).min(10.0); // Clamp to reasonable range
-        
-        // Apply scale around pivot to each selected object
-        let scale_matrix = TransformMatrix::scale_around(scale, scale, pivot.0, pivot.1);
-        
-        for id in &self.selected_ids.clone() {
-            if let Some(initial) = self.drag_state.get_initial_transform(id) {
-                if let Some(node) = self.scene.get_node_by_id_mut(id) {
-                    if let SceneNode::Leaf { transform, .. } = node {
-                        // Apply scale to INITIAL transform
-                        *transform = scale_matrix.multiply(initial);
+    /// Close an open path by joining the end of its last subpath to its start
+    /// The closing segment is a straight line, or with `curved` a curve that follows
+    /// the tangents at both ends. Records its own undo step.
+    /// Returns false if the object is not an open path
+    pub fn close_path(&mut self, id: &str, curved: bool) -> bool {
+        let Some((commands, _, _)) = self.editable_path(id) else {
+            return false;
+        };
+        let Some(closed) = path_edit::close_last_subpath(&commands, curved) else {
+            return false;
+        };
+        if self.scene.get_node_by_id(id).is_none() {
+            return false;
+        }
+
+        self.record_undo("Close path");
+        self.promote_path(id);
+        if let Some(SceneNode::Leaf { object: VectorObject::Path { commands, is_closed, .. }, .. }) = self.scene.edit_node(id) {
+            *commands = closed;
+            *is_closed = true;
+        }
+        true
+    }
+
+    /// Open a closed path by removing its closing segments (the inverse of close_path)
+    /// Records its own undo step. Returns false if the object is not a closed path
+    pub fn open_path(&mut self, id: &str) -> bool {
+        let closes = match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { object: VectorObject::Path { commands, .. }, .. }) => commands.contains(&PathCommand::ClosePath),
+            _ => false,
+        };
+        if !closes || self.scene.get_node_by_id(id).is_none() {
+            return false;
+        }
+
+        self.record_undo("Open path");
+        if let Some(SceneNode::Leaf { object: VectorObject::Path { commands, is_closed, .. }, .. }) = self.scene.edit_node(id) {
+            commands.retain(|command| *command != PathCommand::ClosePath);
+            *is_closed = false;
+        }
+        true
+    }
+
+    /// Select anchors of a path for editing, replacing any anchor selection
+    /// indices_json: JSON array of anchor indices (get_path_points order); invalid ones are ignored
+    /// Returns JSON array of the selected indices
+    pub fn select_anchors(&mut self, id: &str, indices_json: &str) -> String {
+        let indices: Vec<usize> = serde_json::from_str(indices_json).unwrap_or_default();
+        let count = self.anchor_count(id);
+        let anchors = indices.into_iter().filter(|index| *index < count).collect();
+        self.set_anchor_selection(id, anchors)
+    }
+
+    /// Select the anchors of a path inside a world-space rectangle (marquee)
+    /// With `add`, they are added to the anchors already selected in that path.
+    /// Returns JSON array of the selected indices
+    pub fn select_anchors_in_rect(&mut self, id: &str, x1: f64, y1: f64, x2: f64, y2: f64, add: bool) -> String {
+        let Some((commands, _, transform)) = self.editable_path(id) else {
+            return self.set_anchor_selection(id, BTreeSet::new());
+        };
+        let marquee = BoundingBox::new(x1.min(x2), y1.min(y2), x1.max(x2), y1.max(y2));
+        let mut anchors: BTreeSet<usize> = path_edit::anchor_handles(&commands)
+            .iter()
+            .enumerate()
+            .filter(|(_, anchor)| {
+                let (x, y) = transform.transform_point(anchor.x, anchor.y);
+                marquee.contains_point(x, y)
+            })
+            .map(|(index, _)| index)
+            .collect();
+        if add {
+            if let Some(selection) = self.anchor_selection.as_ref().filter(|selection| selection.path_id == id) {
+                anchors.extend(selection.anchors.iter().copied());
+            }
+        }
+        self.set_anchor_selection(id, anchors)
+    }
+
+    /// Get the anchor selection as JSON
+    /// Returns: { "id": path ID, "indices": [...] }, or "{}" when no anchors are selected
+    pub fn get_selected_anchors(&self) -> String {
+        match &self.anchor_selection {
+            Some(selection) => serde_json::json!({ "id": selection.path_id, "indices": selection.anchors }).to_string(),
+            None => "{}".to_string(),
+        }
+    }
+
+    /// Deselect all anchors
+    pub fn clear_anchor_selection(&mut self) {
+        self.anchor_selection = None;
+    }
+
+    /// Move the selected anchors (and their handles) together by a world-space delta
+    /// Like update_path_point, call save_snapshot when the drag starts.
+    /// Returns false if no anchors are selected or the path is gone
+    pub fn move_selected_anchors(&mut self, dx: f64, dy: f64) -> bool {
+        let Some(path_id) = self.anchor_selection.as_ref().map(|selection| selection.path_id.clone()) else {
+            return false;
+        };
+        self.promote_path(&path_id);
+        let Some(selection) = &self.anchor_selection else {
+            return false;
+        };
+        let Some(SceneNode::Leaf { object: VectorObject::Path { commands, .. }, transform, .. }) =
+            self.scene.edit_node(&selection.path_id)
+        else {
+            return false;
+        };
+        let Some(inverse) = transform.inverse() else {
+            return false;
+        };
+        // Deltas are vectors: map both ends so the translation cancels
+        let (ox, oy) = inverse.transform_point(0.0, 0.0);
+        let (lx, ly) = inverse.transform_point(dx, dy);
+        path_edit::move_anchors(commands, &selection.anchors, (lx - ox, ly - oy));
+        true
+    }
+
+    /// Update a path point at the given index
+    /// Sets the x, y coordinates of the command at position `index`
+    pub fn update_path_point(&mut self, id: &str, index: usize, world_x: f64, world_y: f64) {
+        self.promote_path(id);
+        if let Some(SceneNode::Leaf { object: VectorObject::Path { commands, .. }, transform, .. }) = self.scene.edit_node(id) {
+            // Transform world coords back to local coords
+            if let Some(inverse) = transform.inverse() {
+                let (local_x, local_y) = inverse.transform_point(world_x, world_y);
+                
+                // Find the command at the given index and update it
+                let mut point_idx = 0;
+                for cmd in commands.iter_mut() {
+                    match cmd {
+                        PathCommand::MoveTo { x, y } => {
+                            if point_idx == index {
+                                *x = local_x;
+                                *y = local_y;
+                                return;
+                            }
+                            point_idx += 1;
+                        }
+                        PathCommand::LineTo { x, y } => {
+                            if point_idx == index {
+                                *x = local_x;
+                                *y = local_y;
+                                return;
+                            }
+                            point_idx += 1;
+                        }
+                        PathCommand::CurveTo { x, y, .. } | PathCommand::QuadTo { x, y, .. } | PathCommand::ArcTo { x, y, .. } => {
+                            // Only update endpoint, not control points
+                            if point_idx == index {
+                                *x = local_x;
+                                *y = local_y;
+                                return;
+                            }
+                            point_idx += 1;
+                        }
+                        PathCommand::ClosePath => {
+                            // No coordinates to update
+                        }
                     }
                 }
             }
         }
     }
 
-    /// Get handle positions for the first selected object (for hit testing in frontend)
-    /// Returns JSON: [[x,y], [x,y], [x,y], [x,y]] or "[]" if no selection
-    pub fn get_handle_positions(&self) -> String {
-        if let Some(overlay) = self.generate_selection_overlays().first() {
-            serde_json::to_string(&overlay.corners).unwrap_or_else(|_| "[]".to_string())
+    // ==============================================
+    // Animation APIs
+    // ==============================================
+
+    /// Set a keyframe on a top-level object
+    /// property: "position" ([x, y]) | "rotation" (degrees, clockwise) | "scale" ([sx, sy]) |
+    /// "opacity" (0..1) | "fill" ("#rrggbb"); opacity and fill need a leaf object
+    /// value_json: the value as JSON, or "" to key the object's current value
+    /// easing: curve towards the next keyframe: "linear" | "ease_in" | "ease_out" |
+    /// "ease_in_out" | "hold" | "cubic-bezier(x1, y1, x2, y2)"
+    /// A keyframe at the same time is replaced and the scene is re-evaluated at the
+    /// current time. Records its own undo step.
+    /// Returns false for an unknown object, property or easing, or a mismatched value
+    pub fn set_keyframe(&mut self, id: &str, property: &str, time: f64, value_json: &str, easing: &str) -> bool {
+        let (Some(property), Some(easing)) = (AnimatedProperty::from_name(property), Easing::from_name(easing)) else {
+            return false;
+        };
+        let Some(node) = self.scene.edit_node(id) else {
+            return false;
+        };
+        if !time.is_finite() || !property.applies_to(node) {
+            return false;
+        }
+        let value = if value_json.trim().is_empty() {
+            animation::current_value(node, property)
         } else {
-            "[]".to_string()
+            // Colors are keyed in their stored "#rrggbb" form
+            serde_json::from_str::<KeyValue>(value_json).ok().map(|value| match value {
+                KeyValue::Color(color) => KeyValue::Color(normalize_color(&color).unwrap_or(color)),
+                other => other,
+            })
+        };
+        let Some(value) = value.filter(|value| property.accepts(value)) else {
+            return false;
+        };
+        self.record_undo("Set keyframe");
+        self.scene.edit().timeline.set_keyframe(id, property, Keyframe { time, value, easing });
+        let now = self.scene.timeline.current_time;
+        animation::evaluate(self.scene.edit(), now);
+        true
+    }
+
+    /// Remove an object's keyframe at a time
+    /// Re-evaluates the scene at the current time. Records its own undo step.
+    /// Returns false if there is no such keyframe
+    pub fn remove_keyframe(&mut self, id: &str, property: &str, time: f64) -> bool {
+        let Some(property) = AnimatedProperty::from_name(property) else {
+            return false;
+        };
+        if !self.scene.timeline.has_keyframe(id, property, time) {
+            return false;
         }
+        self.record_undo("Delete keyframe");
+        self.scene.edit().timeline.remove_keyframe(id, property, time);
+        let now = self.scene.timeline.current_time;
+        animation::evaluate(self.scene.edit(), now);
+        true
     }
 
-    /// Get the center point of the selection bounding box
-    /// Returns JSON: [x, y] or "[]" if no selection
-    pub fn get_selection_center(&self) -> String {
-        if let Some(overlay) = self.generate_selection_overlays().first() {
-            // Calculate center from corners
-            let corners = &overlay.corners;
-            let cx = (corners[0].0 + corners[1].0 + corners[2].0 + corners[3].0) / 4.0;
-            let cy = (corners[0].1 + corners[1].1 + corners[2].1 + corners[3].1) / 4.0;
-            serde_json::to_string(&[cx, cy]).unwrap_or_else(|_| "[]".to_string())
+    /// Get the animation tracks of an object
+    /// Returns JSON: [{ object_id, property, keyframes: [{ time, value, easing? }] }]
+    pub fn get_keyframes(&self, id: &str) -> String {
+        let tracks: Vec<_> = self.scene.timeline.tracks.iter().filter(|track| track.object_id == id).collect();
+        serde_json::to_string(&tracks).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Evaluate the animation at a time (seconds) and write the animated values into the scene
+    /// Meant for scrubbing and playback, so it does not record undo steps.
+    /// Returns false for a non-finite time
+    pub fn set_time(&mut self, time: f64) -> bool {
+        if !time.is_finite() {
+            return false;
+        }
+        animation::evaluate(self.scene.edit(), time);
+        true
+    }
+
+    /// Get the time (seconds) the scene was last evaluated at
+    pub fn get_time(&self) -> f64 {
+        self.scene.timeline.current_time
+    }
+
+    /// Set the playback settings stored with the document
+    /// duration: seconds; fps: frames per second; looping: restart after the end
+    /// Records its own undo step. Returns false for a non-positive duration or fps
+    pub fn set_playback(&mut self, duration: f64, fps: f64, looping: bool) -> bool {
+        if !(duration.is_finite() && duration > 0.0 && fps.is_finite() && fps > 0.0) {
+            return false;
+        }
+        self.record_undo("Playback settings");
+        let timeline = &mut self.scene.edit().timeline;
+        timeline.duration = duration;
+        timeline.fps = fps;
+        timeline.looping = looping;
+        true
+    }
+
+    /// Get the playback settings
+    /// Returns JSON: { duration, fps, looping, current_time }
+    pub fn get_playback(&self) -> String {
+        let timeline = &self.scene.timeline;
+        serde_json::json!({
+            "duration": timeline.duration,
+            "fps": timeline.fps,
+            "looping": timeline.looping,
+            "current_time": timeline.current_time,
+        })
+        .to_string()
+    }
+}
+
+/// Style of the selected object as the properties panel shows it
+#[derive(Debug, Clone, PartialEq, Serialize, Tsify)]
+#[serde(rename_all = "camelCase")]
+pub struct SelectedStyle {
+    pub fill: Option<String>,
+    pub stroke: Option<String>,
+    pub stroke_width: f64,
+    pub stroke_align: StrokeAlign,
+    pub fill_pattern: Option<PatternFill>,
+}
+
+/// Laid out text of an object, for caret placement
+struct TextGeometry {
+    lines: Vec<TextLine>,
+    /// Ascent and descent of the text's font
+    metrics: (f64, f64),
+    /// Start of the first baseline in local coordinates
+    origin: (f64, f64),
+    transform: TransformMatrix,
+}
+
+impl TextGeometry {
+    /// JSON of a caret from the top to the bottom of its line (across a vertical column,
+    /// centered on it), in world coordinates
+    fn caret_json(&self, caret: &CaretPosition) -> String {
+        let (ascent, descent) = self.metrics;
+        let ((x1, y1), (x2, y2)) = if caret.vertical {
+            let (y, center) = (self.origin.1 + caret.x, self.origin.0 + caret.baseline);
+            let half = (ascent + descent) / 2.0;
+            ((center - half, y), (center + half, y))
         } else {
-            "[]".to_string()
+            let (x, baseline) = (self.origin.0 + caret.x, self.origin.1 + caret.baseline);
+            ((x, baseline - ascent), (x, baseline + descent))
+        };
+        let (x1, y1) = self.transform.transform_point(x1, y1);
+        let (x2, y2) = self.transform.transform_point(x2, y2);
+        serde_json::json!({ "index": caret.index, "line": caret.line, "x1": x1, "y1": y1, "x2": x2, "y2": y2 })
+            .to_string()
+    }
+}
+
+// Drawing straight to a canvas (built with the `canvas` feature)
+#[cfg(feature = "canvas")]
+#[wasm_bindgen]
+impl Editor {
+    /// Draw the scene on a canvas context, without passing render commands to JS
+    /// The frontend clears the canvas and draws overlays as usual.
+    /// Returns false if the context rejected a call
+    pub fn render_to_canvas(&mut self, ctx: &web_sys::CanvasRenderingContext2d) -> bool {
+        let commands = renderer::generate_render_commands_cached(&self.scene, &mut self.render_cache);
+        self.canvas.draw(ctx, &commands).is_ok()
+    }
+
+    /// Draw the scene on an OffscreenCanvas context (e.g. in a worker); see render_to_canvas
+    pub fn render_to_offscreen_canvas(&mut self, ctx: &web_sys::OffscreenCanvasRenderingContext2d) -> bool {
+        let commands = renderer::generate_render_commands_cached(&self.scene, &mut self.render_cache);
+        self.canvas.draw(ctx, &commands).is_ok()
+    }
+}
+
+// Private helper methods (not exposed to Wasm)
+impl Editor {
+    /// Send the selection state to the observer if it changed since it was last sent
+    fn notify_selection_observer(&mut self) {
+        let Some(observer) = &self.selection_observer else {
+            return;
+        };
+        let state = selection_state::selection_state(&self.scene, &self.selected_ids);
+        if self.observed_selection.as_ref() == Some(&state) {
+            return;
         }
+        // An exception thrown by the observer is its own business
+        let _ = observer.call1(&JsValue::NULL, &to_js_value(&state));
+        self.observed_selection = Some(state);
     }
 
-    /// Begin a rotation drag operation
-    /// Uses the center of the bounding box as pivot
-    pub fn begin_rotate_drag(&mut self, start_x: f64, start_y: f64) {
-        // Get initial transforms and calculate center as pivot
-        let mut initial_transforms = std::collections::HashMap::new();
-        let mut center = (0.0, 0.0);
-        
-        // Calculate center from selection overlay
-        if let Some(overlay) = self.generate_selection_overlays().first() {
-            let corners = &overlay.corners;
-            center = (
-                (corners[0].0 + corners[1].0 + corners[2].0 + corners[3].0) / 4.0,
-                (corners[0].1 + corners[1].1 + corners[2].1 + corners[3].1) / 4.0,
-            );
-            
-            // Store initial transforms for all selected objects
-            for id in &self.selected_ids {
-                if let Some(node) = self.scene.get_node_by_id(id) {
-                    if let SceneNode::Leaf { transform, .. } = node {
-                        initial_transforms.insert(id.clone(), *transform);
-                    }
+    /// Style of the first selected object (see get_selected_style)
+    fn selected_style(&self) -> Option<SelectedStyle> {
+        let id = self.selected_ids.iter().next()?;
+        let Some(SceneNode::Leaf { style, .. }) = self.scene.get_node_by_id(id) else {
+            return None;
+        };
+        Some(SelectedStyle {
+            fill: style.fill_color.clone(),
+            stroke: style.stroke_color.clone(),
+            stroke_width: style.stroke_width,
+            stroke_align: style.stroke_align,
+            fill_pattern: style.fill_pattern.clone(),
+        })
+    }
+
+    /// A tool tolerance in world units: as given, or screen pixels converted at the
+    /// current zoom when tolerances are in screen space
+    fn tolerance(&self, distance: f64) -> f64 {
+        if self.screen_space_tolerances {
+            self.viewport.world_distance(distance)
+        } else {
+            distance
+        }
+    }
+
+    /// Change a text object's content, font, size or paragraph style and reshape its outline
+    /// Records an undo step. Returns false if it is not text or the font is not loaded
+    fn edit_text(&mut self, id: &str, edit: impl FnOnce(&mut TextObject)) -> bool {
+        let Some(SceneNode::Leaf { object: VectorObject::Text(text), .. }) = self.scene.get_node_by_id(id) else {
+            return false;
+        };
+        let mut text = TextObject::clone(text);
+        edit(&mut text);
+        let Some(outline) = self.fonts.text_outline(&text) else {
+            return false;
+        };
+        text.outline = outline.commands;
+        text.color_layers = outline.color_layers;
+        self.record_undo("Edit text");
+        if let Some(SceneNode::Leaf { object, .. }) = self.scene.edit_node(id) {
+            *object = VectorObject::Text(Box::new(text));
+        }
+        true
+    }
+
+    /// Lay out again every text whose family has a registered font, after the registered
+    /// fonts changed. Text whose outlines come out the same is left alone. Records no undo
+    /// step: the content and format stay as they were
+    fn reshape_texts(&mut self) {
+        let texts: Vec<(ObjectId, TextObject)> =
+            self.scene.text_objects().into_iter().map(|(id, text)| (id.clone(), text.clone())).collect();
+        for (id, text) in texts {
+            let Some(outline) = self.fonts.text_outline(&text) else {
+                continue;
+            };
+            if outline.commands == text.outline && outline.color_layers == text.color_layers {
+                continue;
+            }
+            // Text inside a symbol definition redraws every instance
+            let scene = if self.scene.get_node_by_id(&id).is_some() {
+                self.scene.edit_nodes(std::slice::from_ref(&id))
+            } else {
+                self.scene.edit()
+            };
+            if let Some(text) = scene.text_object_mut(&id) {
+                text.outline = outline.commands;
+                text.color_layers = outline.color_layers;
+            }
+        }
+    }
+
+    /// Paths drawing a text outline: its plain glyphs with the text's style, then each
+    /// color glyph layer filled with its color (the text's fill for foreground layers)
+    fn text_outline_paths(&mut self, outline: TextOutline, style: &ObjectStyle) -> Vec<SceneNode> {
+        let plain = (!outline.commands.is_empty()).then(|| (outline.commands, style.clone()));
+        let layers = outline.color_layers.into_iter().map(|layer| {
+            let fill_color = layer.color.or_else(|| style.fill_color.clone());
+            let style = ObjectStyle {
+                fill_color,
+                stroke_color: None,
+                fill_rule: FillRule::NonZero,
+                fill_pattern: None,
+                shared_style: None,
+                ..style.clone()
+            };
+            (layer.commands, style)
+        });
+        plain
+            .into_iter()
+            .chain(layers)
+            .map(|(commands, style)| SceneNode::Leaf {
+                id: self.scene.generate_id(),
+                object: VectorObject::Path { commands, is_closed: true, anchor_types: Vec::new() },
+                transform: TransformMatrix::identity(),
+                style,
+                name: None,
+            })
+            .collect()
+    }
+
+    /// Character ranges of the laid out lines of a text (its paragraphs if the font is not loaded)
+    fn text_lines(&self, text: &TextObject) -> Vec<(usize, usize)> {
+        self.fonts
+            .text_chain(text)
+            .and_then(|fonts| text_engine::layout_text(&fonts, text))
+            .map(|lines| lines.iter().map(|line| (line.start, line.end)).collect())
+            .unwrap_or_else(|| text_edit::paragraph_lines(&text.content))
+    }
+
+    /// Laid out lines of a text object with its world transform (None if it is not text,
+    /// its font is not loaded or it is only drawn through symbol instances)
+    fn text_geometry(&self, id: &str) -> Option<TextGeometry> {
+        let leaf = self.scene.iter_leaf_views().into_iter().find(|leaf| leaf.id == id && !leaf.instanced)?;
+        let VectorObject::Text(text) = leaf.object else {
+            return None;
+        };
+        let fonts = self.fonts.text_chain(text)?;
+        Some(TextGeometry {
+            lines: text_engine::layout_text(&fonts, text)?,
+            metrics: text_engine::vertical_metrics(fonts.first()?, text.size)?,
+            origin: (text.x, text.y),
+            transform: leaf.world_transform,
+        })
+    }
+
+    /// Text object of the open edit session (None if none or it was removed)
+    fn edited_text(&self) -> Option<&TextObject> {
+        let session = self.text_edit.as_ref()?;
+        match self.scene.get_node_by_id(&session.id) {
+            Some(SceneNode::Leaf { object: VectorObject::Text(text), .. }) => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Edit the content of the text being edited at its caret and reshape the changed lines
+    /// `edit` returns the new caret, or None if nothing changed. The first edit of a
+    /// run records the undo step.
+    fn edit_at_caret(&mut self, edit: impl FnOnce(&mut String, usize) -> Option<usize>) -> bool {
+        let Some(mut text) = self.edited_text().cloned() else {
+            return false;
+        };
+        let Some(mut session) = self.text_edit.take() else {
+            return false;
+        };
+        let caret = session.caret.min(text.content.chars().count());
+        let edited = edit(&mut text.content, caret).and_then(|caret| {
+            let outline = session.outline(&mut self.fonts, &text)?;
+            Some((caret, outline))
+        });
+        let Some((caret, outline)) = edited else {
+            self.text_edit = Some(session);
+            return false;
+        };
+        if !session.recorded {
+            self.record_undo("Typing");
+            session.recorded = true;
+        }
+        session.caret = caret;
+        text.outline = outline.commands;
+        text.color_layers = outline.color_layers;
+        if let Some(SceneNode::Leaf { object, .. }) = self.scene.edit_node(&session.id) {
+            *object = VectorObject::Text(Box::new(text));
+        }
+        self.text_edit = Some(session);
+        true
+    }
+
+    /// Top-most leaf drawn at a point (inside groups and instances too)
+    fn leaf_at(&self, x: f64, y: f64) -> Option<LeafView<'_>> {
+        // Iterate leaves in reverse order (top-most first)
+        self.scene
+            .iter_leaf_views()
+            .into_iter()
+            .rev()
+            .find(|leaf| hit_test_object(x, y, leaf.object, &leaf.world_transform, leaf.style.fill_rule))
+    }
+
+    /// Parse and validate an untrusted scene JSON document, with its saved history if any
+    /// Documents from older builds are migrated to the current format first. Saved
+    /// history that fails to restore is dropped rather than failing the import.
+    fn parse_scene_json(&self, json: &str) -> Result<(SceneGraph, MigrationReport, Option<SavedHistory>), ImportError> {
+        validation::check_json_input(json, &self.import_limits)?;
+        let mut document = serde_json::from_str::<serde_json::Value>(json).map_err(|e| ImportError::json(&e))?;
+        let history = document.as_object_mut().and_then(|fields| fields.remove("history"));
+        let report = migration::migrate(&mut document)?;
+        let scene = serde_json::from_value::<SceneGraph>(document).map_err(|e| ImportError::json(&e))?;
+        validation::validate_scene(&scene, &self.import_limits)?;
+        Ok((scene, report, self.restore_history(history)))
+    }
+
+    /// Saved history of an imported document, or None if it fails to restore
+    fn restore_history(&self, history: Option<serde_json::Value>) -> Option<SavedHistory> {
+        history.and_then(|history| history::restore(history, &self.import_limits).ok())
+    }
+
+    /// Replace the scene with an imported one, or record the import error
+    /// A document saved with its history replaces the undo and redo stacks
+    fn apply_import(
+        &mut self,
+        result: Result<(SceneGraph, MigrationReport, Option<SavedHistory>), ImportError>,
+    ) -> Result<(), ImportError> {
+        match self.record_import_error(result) {
+            Ok((scene, report, history)) => {
+                if let Some(SavedHistory { undo, redo }) = history {
+                    self.undo_stack = undo;
+                    self.redo_stack = redo;
+                    self.history_bytes = history::memory_usage(self.undo_stack.iter().chain(&self.redo_stack));
+                    self.set_max_history(self.max_history);
                 }
+                self.scene.replace(scene);
+                self.last_import_report = Some(report);
+                self.selected_ids.clear();
+                self.drag_state.end();
+                self.pen_state = PenState::Idle;
+                self.notify_selection_observer();
+                Ok(())
             }
+            Err(error) => {
+                self.last_import_report = None;
+                Err(error)
+            }
+        }
+    }
+
+    /// Remember the error of a parse or import for get_last_import_error (cleared on success)
+    fn record_import_error<T>(&mut self, result: Result<T, ImportError>) -> Result<T, ImportError> {
+        self.last_import_error = result.as_ref().err().cloned();
+        result
+    }
+
+    /// Apply operations to a copy of the scene and swap it in if they all succeed
+    fn apply_operation_list(&mut self, operations: Vec<Operation>) -> Result<Vec<String>, OperationError> {
+        let mut scene = (*self.scene).clone();
+        let added = operations::apply(&mut scene, operations, &self.import_limits)?;
+        self.record_undo("Apply operations");
+        self.scene.replace(scene);
+        let scene = &self.scene;
+        self.selected_ids.retain(|id| scene.get_node_by_id(id).is_some());
+        self.notify_selection_observer();
+        Ok(added)
+    }
+
+    /// import_scene_from_json, reporting the error as is
+    fn import_scene_checked(&mut self, json: &str) -> Result<(), ImportError> {
+        let result = self.parse_scene_json(json);
+        self.apply_import(result)
+    }
+
+    /// import_begin, reporting the error as is
+    fn import_begin_checked(&mut self, total_size: usize) -> Result<(), ImportError> {
+        self.pending_import = None;
+        let result = ChunkedImport::begin(total_size, &self.import_limits).map(|import| self.pending_import = Some(import));
+        self.record_import_error(result)
+    }
+
+    /// import_chunk, reporting the error as is
+    fn import_chunk_checked(&mut self, bytes: &[u8]) -> Result<f64, ImportError> {
+        let result = match self.pending_import.as_mut() {
+            Some(import) => import.push(bytes),
+            None => Err(ImportError::new(ImportErrorCode::ParseError, "no import in progress")),
+        };
+        if result.is_err() {
+            self.pending_import = None;
         }
+        self.record_import_error(result)
+    }
+
+    /// import_finish, reporting the error as is
+    fn import_finish_checked(&mut self) -> Result<(), ImportError> {
+        let result = match self.pending_import.take() {
+            Some(import) => import.finish().map(|(scene, report, history)| (scene, report, self.restore_history(history))),
+            None => Err(ImportError::new(ImportErrorCode::ParseError, "no import in progress")),
+        };
+        self.apply_import(result)
+    }
+
+    /// add_path, reporting the error as is
+    fn add_path_checked(&mut self, commands_json: &str) -> Result<String, ImportError> {
+        let path = validation::check_json_input(commands_json, &self.import_limits)
+            .and_then(|()| serde_json::from_str::<Vec<PathCommand>>(commands_json).map_err(|e| ImportError::json(&e)))
+            .and_then(|commands| {
+                let path = VectorObject::Path { commands, is_closed: true, anchor_types: Vec::new() };
+                validation::validate_object("path", &path, &self.import_limits)?;
+                Ok(path)
+            });
+        let path = self.record_import_error(path)?;
+        let id = self.scene.generate_id();
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, TransformMatrix::identity()));
+        Ok(id)
+    }
 
-        self.drag_state.begin(
-            DragMode::Rotating,
-            start_x,
-            start_y,
-            initial_transforms,
-            center, // Pivot is the center
-        );
+    /// add_path_from_svg, reporting the error as is
+    fn add_path_from_svg_checked(&mut self, d: &str) -> Result<String, ImportError> {
+        let path = path_data::parse_path_data(d).and_then(|commands| {
+            let is_closed = commands.contains(&PathCommand::ClosePath);
+            let path = VectorObject::Path { commands, is_closed, anchor_types: Vec::new() };
+            validation::validate_object("path", &path, &self.import_limits)?;
+            Ok(path)
+        });
+        let path = self.record_import_error(path)?;
+        let id = self.scene.generate_id();
+        self.scene.edit_roots(|scene| scene.add_object(id.clone(), path, TransformMatrix::identity()));
+        Ok(id)
     }
 
-    /// Update rotation drag - rotates around center
-    pub fn update_rotate_drag(&mut self, current_x: f64, current_y: f64) {
+    /// Rotate the selection around the drag pivot, optionally snapping the angle
+    fn apply_rotate_drag(&mut self, current_x: f64, current_y: f64, snap_degrees: f64) {
         if self.drag_state.mode != DragMode::Rotating {
             return;
         }
 
         let pivot = self.drag_state.pivot;
-        let (start_x, start_y) = self.drag_state.start_point;
-        
-        // Calculate angles from center to start and current points
-        let start_angle = (start_y - pivot.1).atan2(start_x - pivot.0);
-        let current_angle = (current_y - pivot.1).atan2(current_x - pivot.0);
-        // Negate delta to fix rotation direction (screen Y-axis points down)
-        let delta_angle = -(current_angle - start_angle);
-        
-        // Apply rotation around center to each selected object
-        let rotation_matrix = TransformMatrix::rotate_around(delta_angle, pivot.0, pivot.1);
+        let angle = drag_state::snap_angle(
+            drag_state::sweep_angle(pivot, self.drag_state.start_point, (current_x, current_y)),
+            snap_degrees,
+        );
+        self.drag_state.angle = angle;
+
+        // Negate to fix rotation direction (screen Y-axis points down)
+        let rotation_matrix = TransformMatrix::rotate_around(-angle, pivot.0, pivot.1);
         
         for id in &self.selected_ids.clone() {
-            if let Some(initial) = self.drag_state.get_initial_transform(id) {
-                if let Some(node) = self.scene.get_node_by_id_mut(id) {
-                    if let SceneNode::Leaf { transform, .. } = node {
-                        // Apply rotation to INITIAL transform
-                        *transform = rotation_matrix.multiply(initial);
-                    }
-                }
+            if let Some(initial) = self.drag_state.get_initial_transform(id).copied() {
+                // Apply rotation to INITIAL transform
+                self.transform_in_world(id, &rotation_matrix, Some(initial));
             }
         }
+        self.notify_selection_observer();
     }
 
-    // ==============================================
-    // Pen Tool APIs
-    // ==============================================
+    /// Push the current scene as an undo step labeled with the edit about to be made
+    fn record_undo(&mut self, label: impl Into<String>) {
+        // Any new history entry ends a run of nudges or typing
+        self.nudge_selection = None;
+        if let Some(session) = &mut self.text_edit {
+            session.recorded = false;
+        }
 
-    /// Handle pen tool mouse down
-    /// Returns true if near start point (for closing path)
-    pub fn pen_down(&mut self, x: f64, y: f64) -> bool {
-        const CLOSE_THRESHOLD: f64 = 15.0;
-        
-        match &self.pen_state {
-            PenState::Idle => {
-                // Start a new path
-                self.pen_state = PenState::Drawing {
-                    commands: vec![PathCommand::MoveTo { x, y }],
-                    start_point: (x, y),
-                    last_anchor: (x, y),
-                    drag_start_anchor: None,
-                    drag_handle: None,
-                    is_dragging: false,
-                };
-                false
-            }
-            PenState::Drawing { start_point, commands, .. } => {
-                // Check if closing the path
-                if commands.len() >= 2 {
-                    let dx = x - start_point.0;
-                    let dy = y - start_point.1;
-                    if (dx * dx + dy * dy).sqrt() < CLOSE_THRESHOLD {
-                        return true; // Signal that we should close
-                    }
-                }
-                
-                // Mark with FIXED endpoint position (drag_start_anchor)
-                if let PenState::Drawing { is_dragging, drag_handle, drag_start_anchor, .. } = &mut self.pen_state {
-                    *is_dragging = false;
-                    *drag_start_anchor = Some((x, y)); // FIXED endpoint!
-                    *drag_handle = Some((x, y)); // Initially same as click position
-                }
-                false
-            }
+        // Bulk mode already recorded one entry for the whole operation
+        if self.history_suspended {
+            return;
         }
+
+        let entry = HistoryEntry::new(label, &self.scene, self.undo_stack.last());
+        self.history_bytes += entry.unshared_bytes();
+        self.undo_stack.push(entry);
+
+        // Clear redo stack when new action is performed
+        let redo = std::mem::take(&mut self.redo_stack);
+        self.free_history(redo);
+
+        self.trim_history();
     }
 
-    /// Handle pen tool mouse move (for dragging to create curves)
-    pub fn pen_move(&mut self, x: f64, y: f64) {
-        if let PenState::Drawing { drag_handle, is_dragging, .. } = &mut self.pen_state {
-            *drag_handle = Some((x, y));
-            *is_dragging = true;
+    fn history_listing(&self) -> HistoryListing {
+        history::listing(&self.undo_stack, &self.redo_stack)
+    }
+
+    /// History label for an edit of the selection ("Delete 3 objects")
+    fn selection_label(&self, action: &str) -> String {
+        history::objects_label(action, self.selected_ids.len())
+    }
+
+    /// Drop the oldest undo states beyond max_history, then the steps farthest from
+    /// the current state until the history fits max_history_bytes
+    fn trim_history(&mut self) {
+        let excess = self.undo_stack.len().saturating_sub(self.max_history);
+        let dropped: Vec<HistoryEntry> = self.undo_stack.drain(..excess).collect();
+        self.free_history(dropped);
+        while self.history_bytes > self.max_history_bytes {
+            let dropped = if self.undo_stack.len() > 1 {
+                self.undo_stack.remove(0)
+            } else if !self.redo_stack.is_empty() {
+                self.redo_stack.remove(0)
+            } else {
+                break;
+            };
+            self.free_history([dropped]);
         }
     }
 
-    /// Handle pen tool mouse up - confirm the anchor
-    pub fn pen_up(&mut self, _x: f64, _y: f64) {
-        let new_state = match &self.pen_state {
-            PenState::Drawing { commands, start_point, last_anchor, drag_start_anchor, drag_handle, is_dragging } => {
-                let mut new_commands = commands.clone();
-                
-                if *is_dragging {
-                    // Use drag_start_anchor as the FIXED endpoint
-                    if let (Some((end_x, end_y)), Some((cp2x, cp2y))) = (drag_start_anchor, drag_handle) {
-                        // CP1 = start point (C-curve: straight exit from start)
-                        // CP2 = mouse position during drag
-                        let cp1x = last_anchor.0;
-                        let cp1y = last_anchor.1;
-                        
-                        new_commands.push(PathCommand::CurveTo {
-                            x1: cp1x, y1: cp1y,
-                            x2: *cp2x, y2: *cp2y,
-                            x: *end_x, y: *end_y,
-                        });
-                        
-                        Some(PenState::Drawing {
-                            commands: new_commands,
-                            start_point: *start_point,
-                            last_anchor: (*end_x, *end_y), // New anchor is at endpoint
-                            drag_start_anchor: None,
-                            drag_handle: None,
-                            is_dragging: false,
-                        })
-                    } else {
-                        None
-                    }
-                } else if let Some((end_x, end_y)) = drag_start_anchor {
-                    // Simple click - add a line to where user clicked
-                    new_commands.push(PathCommand::LineTo { x: *end_x, y: *end_y });
-                    
-                    Some(PenState::Drawing {
-                        commands: new_commands,
-                        start_point: *start_point,
-                        last_anchor: (*end_x, *end_y),
-                        drag_start_anchor: None,
-                        drag_handle: None,
-                        is_dragging: false,
-                    })
-                } else {
-                    None
-                }
-            }
-            PenState::Idle => None,
-        };
-        
-        if let Some(state) = new_state {
-            self.pen_state = state;
+    /// Drop history entries taken off the stacks, deducting what they alone held
+    fn free_history(&mut self, entries: impl IntoIterator<Item = HistoryEntry>) {
+        for entry in entries {
+            self.history_bytes = self.history_bytes.saturating_sub(entry.unshared_bytes());
         }
     }
 
-    /// Close the current path and add it to the scene (is_closed = true)
-    /// Called when user clicks on start point
-    pub fn pen_close(&mut self) -> String {
-        if let PenState::Drawing { mut commands, .. } = std::mem::take(&mut self.pen_state) {
-            commands.push(PathCommand::ClosePath);
-            
-            let id = self.scene.generate_id();
-            let path = VectorObject::Path { commands, is_closed: true };
-            self.scene.add_object(id.clone(), path, TransformMatrix::identity());
-            
-            self.pen_state = PenState::Idle;
-            return id;
+    /// Number of anchors in a path (0 if it is not a path)
+    fn anchor_count(&self, id: &str) -> usize {
+        match self.editable_path(id) {
+            Some((commands, _, _)) => commands.iter().filter(|command| **command != PathCommand::ClosePath).count(),
+            None => 0,
         }
-        String::new()
     }
 
-    /// Finish the current path without closing it (is_closed = false)
-    /// Called when user presses Enter key
-    pub fn pen_finish(&mut self) -> String {
-        if let PenState::Drawing { commands, .. } = std::mem::take(&mut self.pen_state) {
-            // Don't add ClosePath command - leave path open
-            if commands.len() < 2 {
-                // Need at least 2 points to make a valid open path
-                self.pen_state = PenState::Idle;
-                return String::new();
+    /// A path's commands, anchor types and transform as path editing sees them
+    /// Quadratics and arcs are promoted to cubics (see path_edit::promote_to_cubic),
+    /// so anchor indices match the path after any edit.
+    fn editable_path(&self, id: &str) -> Option<(Vec<PathCommand>, Vec<AnchorType>, TransformMatrix)> {
+        match self.scene.get_node_by_id(id) {
+            Some(SceneNode::Leaf { object: VectorObject::Path { commands, anchor_types, .. }, transform, .. }) => {
+                let (commands, anchor_types) = path_edit::promote_to_cubic(commands, anchor_types);
+                Some((commands, anchor_types, *transform))
             }
-            
-            let id = self.scene.generate_id();
-            let path = VectorObject::Path { commands, is_closed: false };
-            self.scene.add_object(id.clone(), path, TransformMatrix::identity());
-            
-            self.pen_state = PenState::Idle;
-            return id;
+            _ => None,
         }
-        String::new()
     }
 
-    /// Cancel pen drawing without saving
-    pub fn pen_cancel(&mut self) {
-        self.pen_state = PenState::Idle;
+    /// Store the promoted form of a path (see editable_path) before editing it
+    fn promote_path(&mut self, id: &str) {
+        if let Some(SceneNode::Leaf { object: VectorObject::Path { commands, anchor_types, .. }, .. }) = self.scene.edit_node(id) {
+            if geometry::has_quads_or_arcs(commands) {
+                (*commands, *anchor_types) = path_edit::promote_to_cubic(commands, anchor_types);
+            }
+        }
     }
 
-    /// Check if pen tool is currently drawing
-    pub fn is_pen_drawing(&self) -> bool {
-        self.pen_state.is_drawing()
+    /// Replace the anchor selection, returning the selected indices as JSON
+    fn set_anchor_selection(&mut self, id: &str, anchors: BTreeSet<usize>) -> String {
+        let json = serde_json::to_string(&anchors).unwrap_or_else(|_| "[]".to_string());
+        self.anchor_selection = if anchors.is_empty() {
+            None
+        } else {
+            Some(AnchorSelection { path_id: id.to_string(), anchors })
+        };
+        json
     }
 
-    /// Get current pen path preview as JSON for rendering
-    /// Returns: { commands: [...], last_anchor: [x, y], handle: [x, y] | null, is_dragging: bool, preview_curve: {...} | null }
-    pub fn get_pen_preview(&self) -> String {
-        match &self.pen_state {
-            PenState::Drawing { commands, drag_handle, drag_start_anchor, last_anchor, is_dragging, .. } => {
-                // If dragging, calculate a preview curve
-                // - Start: last_anchor (previous confirmed point)
-                // - End: drag_start_anchor (where user clicked - FIXED!)
-                // - CP2: drag_handle (mouse position - creates curvature!)
-                // - CP1: same as start point (Corner Point - straight exit from start)
-                let preview_curve = if *is_dragging {
-                    if let (Some((end_x, end_y)), Some((cp2x, cp2y))) = (drag_start_anchor, drag_handle) {
-                        // Curve from last_anchor to drag_start_anchor (fixed endpoint)
-                        // CP1 = start point (straight exit, no handle at start = C-curve)
-                        // CP2 = mouse position (controls the curve toward the end)
-                        let cp1x = last_anchor.0;
-                        let cp1y = last_anchor.1;
-                        
-                        Some(serde_json::json!({
-                            "type": "CurveTo",
-                            "x1": cp1x,
-                            "y1": cp1y,
-                            "x2": cp2x,
-                            "y2": cp2y,
-                            "x": end_x,
-                            "y": end_y,
-                        }))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                };
+    /// Split the segment drawn by a path command, returning the new anchor's index
+    fn subdivide_path(&mut self, id: &str, command_index: usize, t: f64) -> Option<u32> {
+        let (commands, _, _) = self.editable_path(id)?;
+        let subdivided = path_edit::subdivide(&commands, command_index, t)?;
+        let anchor = path_edit::anchor_index(&subdivided, command_index);
+        // Splitting a curve leaves a smooth anchor, splitting a line a corner
+        let anchor_type = match commands[command_index] {
+            PathCommand::CurveTo { .. } => AnchorType::Smooth,
+            _ => AnchorType::Corner,
+        };
+        self.scene.get_node_by_id(id)?;
 
-                let preview = serde_json::json!({
-                    "commands": commands,
-                    "last_anchor": [last_anchor.0, last_anchor.1],
-                    "drag_start_anchor": drag_start_anchor,
-                    "handle": drag_handle,
-                    "is_dragging": is_dragging,
-                    "preview_curve": preview_curve,
-                });
-                serde_json::to_string(&preview).unwrap_or_else(|_| "{}".to_string())
+        self.record_undo("Add anchor point");
+        self.promote_path(id);
+        if let Some(SceneNode::Leaf { object: VectorObject::Path { commands, anchor_types, .. }, .. }) = self.scene.edit_node(id) {
+            *commands = subdivided;
+            if anchor < anchor_types.len() || anchor_type == AnchorType::Smooth {
+                if anchor_types.len() < anchor {
+                    anchor_types.resize(anchor, AnchorType::Corner);
+                }
+                anchor_types.insert(anchor, anchor_type);
             }
-            PenState::Idle => "{}".to_string(),
         }
+        u32::try_from(anchor).ok()
     }
 
-    // ==============================================
-    // Path Editing APIs (Direct Selection Tool)
-    // ==============================================
-
-    /// Check if the first selected object is a Path
-    pub fn selected_is_path(&self) -> bool {
-        if let Some(id) = self.selected_ids.iter().next() {
-            if let Some(node) = self.scene.get_node_by_id(id) {
-                if let SceneNode::Leaf { object, .. } = node {
-                    return matches!(object, VectorObject::Path { .. });
-                }
+    /// Validate an untrusted clipboard fragment, recording the error on failure
+    fn parse_clipboard(&mut self, fragment_json: &str) -> Option<ClipboardFragment> {
+        match clipboard::parse_fragment(fragment_json, &self.import_limits) {
+            Ok(fragment) => {
+                self.last_import_error = None;
+                Some(fragment)
+            }
+            Err(error) => {
+                self.last_import_error = Some(error);
+                None
             }
         }
-        false
     }
 
-    /// Get path points for the specified object as JSON
-    /// Returns: [ { "x": f64, "y": f64, "type": "move"|"line"|"curve" }, ... ]
-    pub fn get_path_points(&self, id: &str) -> String {
-        if let Some(node) = self.scene.get_node_by_id(id) {
-            if let SceneNode::Leaf { object, transform, .. } = node {
-                if let VectorObject::Path { commands, .. } = object {
-                    let mut points = Vec::new();
-                    
-                    for cmd in commands {
-                        match cmd {
-                            PathCommand::MoveTo { x, y } => {
-                                // Transform local coords to world coords
-                                let (wx, wy) = transform.transform_point(*x, *y);
-                                points.push(serde_json::json!({
-                                    "x": wx,
-                                    "y": wy,
-                                    "type": "move"
-                                }));
-                            }
-                            PathCommand::LineTo { x, y } => {
-                                let (wx, wy) = transform.transform_point(*x, *y);
-                                points.push(serde_json::json!({
-                                    "x": wx,
-                                    "y": wy,
-                                    "type": "line"
-                                }));
-                            }
-                            PathCommand::CurveTo { x, y, .. } => {
-                                // For now, just return the endpoint (not control points)
-                                let (wx, wy) = transform.transform_point(*x, *y);
-                                points.push(serde_json::json!({
-                                    "x": wx,
-                                    "y": wy,
-                                    "type": "curve"
-                                }));
-                            }
-                            PathCommand::ClosePath => {
-                                // ClosePath has no coordinates
-                            }
-                        }
-                    }
-                    
-                    return serde_json::to_string(&points).unwrap_or_else(|_| "[]".to_string());
-                }
-            }
+    /// Insert a clipboard fragment offset by (dx, dy) and select it
+    fn insert_clipboard(&mut self, fragment: ClipboardFragment, dx: f64, dy: f64) -> String {
+        self.record_undo("Paste");
+        let new_ids = clipboard::paste_fragment(self.scene.edit(), fragment, dx, dy);
+        self.selected_ids = new_ids.iter().cloned().collect();
+        self.notify_selection_observer();
+        serde_json::to_string(&new_ids).unwrap_or_else(|_| "[]".to_string())
+    }
+
+    /// Copy the selected root nodes in place (offset by dx, dy) and select the copies
+    fn duplicate_selection(&mut self, dx: f64, dy: f64) -> Vec<String> {
+        let ids: Vec<String> = self.selected_ids.iter().cloned().collect();
+        let new_ids = self.scene.edit_roots(|scene| scene.duplicate_roots(&ids, dx, dy));
+        self.selected_ids = new_ids.iter().cloned().collect();
+        self.notify_selection_observer();
+        new_ids
+    }
+
+    /// Apply a world-space transform built around the selection's center to every selected object
+    fn transform_selection_around_center(&mut self, label: &str, build: impl FnOnce(f64, f64) -> TransformMatrix) -> bool {
+        let (cx, cy) = match self.selection_world_bounds() {
+            Some(bounds) => bounds.center(),
+            None => return false,
+        };
+        let matrix = build(cx, cy);
+        self.record_undo(label);
+        for id in &self.selected_ids.clone() {
+            self.transform_in_world(id, &matrix, None);
         }
-        "[]".to_string()
+        self.notify_selection_observer();
+        true
     }
 
-    /// Update a path point at the given index
-    /// Sets the x, y coordinates of the command at position `index`
-    pub fn update_path_point(&mut self, id: &str, index: usize, world_x: f64, world_y: f64) {
-        if let Some(node) = self.scene.get_node_by_id_mut(id) {
-            if let SceneNode::Leaf { object, transform, .. } = node {
-                if let VectorObject::Path { commands, .. } = object {
-                    // Transform world coords back to local coords
-                    if let Some(inverse) = transform.inverse() {
-                        let (local_x, local_y) = inverse.transform_point(world_x, world_y);
-                        
-                        // Find the command at the given index and update it
-                        let mut point_idx = 0;
-                        for cmd in commands.iter_mut() {
-                            match cmd {
-                                PathCommand::MoveTo { x, y } => {
-                                    if point_idx == index {
-                                        *x = local_x;
-                                        *y = local_y;
-                                        return;
-                                    }
-                                    point_idx += 1;
-                                }
-                                PathCommand::LineTo { x, y } => {
-                                    if point_idx == index {
-                                        *x = local_x;
-                                        *y = local_y;
-                                        return;
-                                    }
-                                    point_idx += 1;
-                                }
-                                PathCommand::CurveTo { x, y, .. } => {
-                                    // Only update endpoint, not control points
-                                    if point_idx == index {
-                                        *x = local_x;
-                                        *y = local_y;
-                                        return;
-                                    }
-                                    point_idx += 1;
-                                }
-                                PathCommand::ClosePath => {
-                                    // No coordinates to update
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    /// Move each selected object so its world bounds align with `target`
+    fn align_selection_to(&mut self, mode: &str, target: &BoundingBox) -> bool {
+        let mode = match AlignMode::from_name(mode) {
+            Some(mode) => mode,
+            None => return false,
+        };
+        // Offsets are computed up front from each object's world-space bounds
+        let offsets: Vec<(String, (f64, f64))> = self
+            .selected_ids
+            .iter()
+            .filter_map(|id| {
+                let (node, parent_transform) = self.scene.get_node_with_parent_transform(id)?;
+                let bounds = geometry::node_world_bounds(&self.scene, node, &parent_transform)?;
+                Some((id.clone(), align::align_offset(&bounds, target, mode)))
+            })
+            .collect();
+        if offsets.is_empty() {
+            return false;
+        }
+
+        self.record_undo("Align");
+        for (id, (dx, dy)) in offsets {
+            self.transform_in_world(&id, &TransformMatrix::translate(dx, dy), None);
         }
+        self.notify_selection_observer();
+        true
     }
-}
 
-// Private helper methods (not exposed to Wasm)
-impl Editor {
-    fn find_id_for_object(&self, target: &VectorObject) -> Option<String> {
-        for node in &self.scene.roots {
-            if let SceneNode::Leaf { id, object, .. } = node {
-                if std::ptr::eq(object, target) {
-                    return Some(id.clone());
-                }
+    /// World bounds of the root objects outside the selection, as snap targets
+    fn snap_targets(&self) -> Vec<SnapTarget> {
+        let identity = TransformMatrix::identity();
+        self.scene
+            .roots
+            .iter()
+            .filter(|node| !self.selected_ids.contains(node.id()))
+            .filter_map(|node| {
+                let bounds = geometry::node_world_bounds(&self.scene, node, &identity)?;
+                Some(SnapTarget { id: node.id().clone(), bounds })
+            })
+            .collect()
+    }
+
+    /// Snapping rules of the current document
+    fn snapper(&self) -> Snapper<'_> {
+        Snapper::new(self.scene.grid, &self.scene.guides)
+    }
+
+    /// World bounds, local bounds and own transform of a node
+    fn object_frame(&self, id: &str) -> Option<(BoundingBox, BoundingBox, TransformMatrix)> {
+        let (node, parent_transform) = self.scene.get_node_with_parent_transform(id)?;
+        let world = geometry::node_world_bounds(&self.scene, node, &parent_transform)?;
+        let local = self.node_local_bounds(node)?;
+        Some((world, local, *node.transform()))
+    }
+
+    /// Move a node on the page by a world-space transform, applied on top of `base` (a
+    /// transform the node had, e.g. when a drag started) or of its current transform
+    fn transform_in_world(&mut self, id: &str, matrix: &TransformMatrix, base: Option<TransformMatrix>) {
+        let Some(local) = self.scene.parent_space_matrix(id, matrix) else {
+            return;
+        };
+        if let Some(transform) = self.scene.edit_node(id).map(SceneNode::transform_mut) {
+            *transform = local.multiply(&base.unwrap_or(*transform));
+        }
+    }
+
+    /// Replace a node's transform as one undo step, labeled `label`
+    fn set_node_transform(&mut self, id: &str, transform: TransformMatrix, label: &str) -> bool {
+        if self.scene.get_node_by_id(id).is_none() {
+            return false;
+        }
+        self.record_undo(label);
+        if let Some(current) = self.scene.edit_node(id).map(SceneNode::transform_mut) {
+            *current = transform;
+        }
+        true
+    }
+
+    /// World-space bounds of the whole selection
+    fn selection_world_bounds(&self) -> Option<BoundingBox> {
+        self.selected_ids
+            .iter()
+            .filter_map(|id| {
+                let (node, parent_transform) = self.scene.get_node_with_parent_transform(id)?;
+                geometry::node_world_bounds(&self.scene, node, &parent_transform)
+            })
+            .reduce(|acc, b| acc.union(&b))
+    }
+
+    /// Bounds of a node in its own coordinate space (before its transform is applied)
+    fn node_local_bounds(&self, node: &SceneNode) -> Option<BoundingBox> {
+        let identity = TransformMatrix::identity();
+        match node {
+            SceneNode::Leaf { object, .. } => geometry::object_world_bounds(object, &identity),
+            SceneNode::Group { children, .. } => geometry::nodes_world_bounds(&self.scene, children, &identity),
+            SceneNode::Instance { symbol_id, .. } => {
+                let symbol = self.scene.get_symbol(symbol_id)?;
+                geometry::nodes_world_bounds(&self.scene, &symbol.children, &identity)
             }
         }
-        None
     }
 
     fn generate_selection_overlays(&self) -> Vec<SelectionOverlay> {
         let mut overlays = Vec::new();
         
-        for (object, transform, _style) in self.scene.iter_leaves() {
+        for node in &self.scene.roots {
             // Check if this object is selected
-            if let Some(id) = self.find_id_for_object(object) {
-                if self.selected_ids.contains(&id) {
-                    // Get local bounding box
-                    let local_bounds = match object {
-                        VectorObject::Rectangle { x, y, width, height } => {
-                            BoundingBox::from_rect(*x, *y, *width, *height)
-                        }
-                        VectorObject::Ellipse { cx, cy, rx, ry } => {
-                            BoundingBox::from_ellipse(*cx, *cy, *rx, *ry)
-                        }
-                        VectorObject::Path { commands, .. } => {
-                            // Calculate bounding box from all path points
-                            let mut min_x = f64::MAX;
-                            let mut min_y = f64::MAX;
-                            let mut max_x = f64::MIN;
-                            let mut max_y = f64::MIN;
-                            
-                            for cmd in commands {
-                                match cmd {
-                                    PathCommand::MoveTo { x, y } | PathCommand::LineTo { x, y } => {
-                                        min_x = min_x.min(*x);
-                                        min_y = min_y.min(*y);
-                                        max_x = max_x.max(*x);
-                                        max_y = max_y.max(*y);
-                                    }
-                                    PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
-                                        min_x = min_x.min(*x1).min(*x2).min(*x);
-                                        min_y = min_y.min(*y1).min(*y2).min(*y);
-                                        max_x = max_x.max(*x1).max(*x2).max(*x);
-                                        max_y = max_y.max(*y1).max(*y2).max(*y);
-                                    }
-                                    PathCommand::ClosePath => {}
-                                }
-                            }
-                            
-                            if min_x == f64::MAX {
-                                continue; // Empty path
-                            }
-                            BoundingBox { min_x, min_y, max_x, max_y }
-                        }
-                    };
-
-                    // Transform corners to world space
-                    let corners = [
-                        transform.transform_point(local_bounds.min_x, local_bounds.min_y),
-                        transform.transform_point(local_bounds.max_x, local_bounds.min_y),
-                        transform.transform_point(local_bounds.max_x, local_bounds.max_y),
-                        transform.transform_point(local_bounds.min_x, local_bounds.max_y),
-                    ];
-
-                    overlays.push(SelectionOverlay {
-                        id: id.clone(),
-                        corners,
-                    });
-                }
+            if !self.selected_ids.contains(node.id()) {
+                continue;
             }
+            let local_bounds = match self.node_local_bounds(node) {
+                Some(bounds) => bounds,
+                None => continue, // Empty path / group
+            };
+
+            // Transform corners to world space
+            let transform = node.transform();
+            let corners = [
+                transform.transform_point(local_bounds.min_x, local_bounds.min_y),
+                transform.transform_point(local_bounds.max_x, local_bounds.min_y),
+                transform.transform_point(local_bounds.max_x, local_bounds.max_y),
+                transform.transform_point(local_bounds.min_x, local_bounds.max_y),
+            ];
+
+            overlays.push(SelectionOverlay {
+                id: node.id().clone(),
+                corners,
+                handle_size: self.tolerance(HANDLE_SIZE),
+            });
         }
 
         overlays
@@ -1000,3 +4794,43 @@ impl Default for Editor {
         Self::new()
     }
 }
+
+/// A value as a plain JS value (objects, arrays, numbers, strings; null if it cannot be
+/// converted), as JSON.parse of its JSON would give
+fn to_js_value<T: serde::Serialize>(value: &T) -> JsValue {
+    value.serialize(&serde_wasm_bindgen::Serializer::json_compatible()).unwrap_or(JsValue::NULL)
+}
+
+/// Length of the images of the x and y unit vectors under a transform
+fn axis_scales(transform: &TransformMatrix) -> (f64, f64) {
+    (transform.a.hypot(transform.c), transform.b.hypot(transform.d))
+}
+
+/// Parse and check a style JSON (as in the saved scene), normalizing its colors
+/// Returns None for invalid values or colors, or a pattern of an unknown symbol
+fn parse_style(scene: &SceneGraph, style_json: &str) -> Option<ObjectStyle> {
+    let mut style: ObjectStyle = serde_json::from_str(style_json).ok()?;
+    for color in [&mut style.fill_color, &mut style.stroke_color] {
+        if let Some(value) = color.as_deref() {
+            *color = Some(normalize_color(value)?);
+        }
+    }
+    if let Some(PatternSource::Symbol { symbol_id }) = style.fill_pattern.as_ref().map(|pattern| &pattern.source) {
+        scene.get_symbol(symbol_id)?;
+    }
+    library::is_valid_style(&style).then_some(style)
+}
+
+/// Parse a style for the shared style library (any link in it is dropped)
+fn parse_shared_style(scene: &SceneGraph, style_json: &str) -> Option<ObjectStyle> {
+    parse_style(scene, style_json).map(|style| ObjectStyle { shared_style: None, ..style })
+}
+
+/// Parse a style color argument: "" or "none" for no color, otherwise any CSS color
+/// Returns None if the color cannot be parsed
+fn style_color(color: &str) -> Option<Option<String>> {
+    match color {
+        "" | "none" => Some(None),
+        _ => normalize_color(color).map(Some),
+    }
+}
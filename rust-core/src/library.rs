@@ -0,0 +1,175 @@
+//! Library Module - Named color swatches and shared styles
+//!
+//! Both are saved with the document. Swatches are named colors for the color
+//! pickers. Shared styles are named object styles: a linked object keeps a
+//! copy of the style (so every renderer and exporter reads it as usual) plus
+//! the ID it came from, and editing a shared style rewrites the copy in all
+//! linked objects, including those inside groups and symbols.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::scene::{ObjectId, ObjectStyle, SceneGraph, SceneNode};
+
+/// Named color
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Swatch {
+    pub id: ObjectId,
+    pub name: String,
+    /// "#rrggbb"
+    pub color: String,
+}
+
+/// Named style that objects can link to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SharedStyle {
+    pub id: ObjectId,
+    pub name: String,
+    pub style: ObjectStyle,
+}
+
+/// Whether a style can be shared: finite stroke width, opacity in 0..1 and a valid pattern
+pub fn is_valid_style(style: &ObjectStyle) -> bool {
+    style.stroke_width.is_finite()
+        && style.stroke_width >= 0.0
+        && (0.0..=1.0).contains(&style.opacity)
+        && style.fill_pattern.as_ref().is_none_or(|pattern| pattern.is_valid())
+}
+
+/// Copy a shared style into an object style and link it
+pub fn link_style(target: &mut ObjectStyle, shared: &SharedStyle) {
+    *target = ObjectStyle { shared_style: Some(shared.id.clone()), ..shared.style.clone() };
+}
+
+/// Rewrite every object linked to `shared` (in the scene and in symbols)
+/// Returns the number of objects updated.
+pub fn propagate_style(scene: &mut SceneGraph, shared: &SharedStyle) -> usize {
+    let mut relink = |style: &mut ObjectStyle| {
+        if style.shared_style.as_deref() == Some(shared.id.as_str()) {
+            link_style(style, shared);
+            true
+        } else {
+            false
+        }
+    };
+    let mut count = update_nodes(&mut scene.roots, &mut relink);
+    for symbol in &mut scene.symbols {
+        count += update_nodes(&mut symbol.children, &mut relink);
+    }
+    count
+}
+
+/// Unlink every object from a shared style, keeping its current look
+/// Returns the number of objects unlinked.
+pub fn detach_style(scene: &mut SceneGraph, style_id: &str) -> usize {
+    let mut unlink = |style: &mut ObjectStyle| {
+        if style.shared_style.as_deref() == Some(style_id) {
+            style.shared_style = None;
+            true
+        } else {
+            false
+        }
+    };
+    let mut count = update_nodes(&mut scene.roots, &mut unlink);
+    for symbol in &mut scene.symbols {
+        count += update_nodes(&mut symbol.children, &mut unlink);
+    }
+    count
+}
+
+/// Number of objects linked to a shared style (in the scene and in symbols)
+pub fn style_uses(scene: &SceneGraph, style_id: &str) -> usize {
+    fn count(nodes: &[SceneNode], style_id: &str) -> usize {
+        nodes
+            .iter()
+            .map(|node| match node {
+                SceneNode::Leaf { style, .. } => usize::from(style.shared_style.as_deref() == Some(style_id)),
+                SceneNode::Group { children, .. } => count(children, style_id),
+                SceneNode::Instance { .. } => 0,
+            })
+            .sum()
+    }
+    count(&scene.roots, style_id) + scene.symbols.iter().map(|symbol| count(&symbol.children, style_id)).sum::<usize>()
+}
+
+fn update_nodes(nodes: &mut [SceneNode], update: &mut dyn FnMut(&mut ObjectStyle) -> bool) -> usize {
+    nodes
+        .iter_mut()
+        .map(|node| match node {
+            SceneNode::Leaf { style, .. } => usize::from(update(style)),
+            SceneNode::Group { children, .. } => update_nodes(children, update),
+            SceneNode::Instance { .. } => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::{Symbol, VectorObject};
+
+    fn leaf(id: &str) -> SceneNode {
+        SceneNode::Leaf {
+            id: id.to_string(),
+            object: VectorObject::Circle { cx: 0.0, cy: 0.0, r: 1.0 },
+            transform: TransformMatrix::identity(),
+            style: ObjectStyle::default(),
+            name: None,
+        }
+    }
+
+    fn style_of(node: &SceneNode) -> &ObjectStyle {
+        match node {
+            SceneNode::Leaf { style, .. } => style,
+            _ => panic!("not a leaf"),
+        }
+    }
+
+    fn shared(fill: &str) -> SharedStyle {
+        let style = ObjectStyle { fill_color: Some(fill.to_string()), ..ObjectStyle::default() };
+        SharedStyle { id: "style_1".to_string(), name: "Accent".to_string(), style }
+    }
+
+    #[test]
+    fn test_editing_a_shared_style_updates_linked_objects() {
+        let mut scene = SceneGraph::new();
+        let group = SceneNode::Group {
+            id: "g".to_string(),
+            children: vec![leaf("b")],
+            transform: TransformMatrix::identity(),
+            name: None,
+            cache_as_bitmap: false,
+        };
+        scene.roots = vec![leaf("a"), group, leaf("c")];
+        scene.symbols.push(Symbol { id: "sym_1".to_string(), name: "S".to_string(), children: vec![leaf("d")] });
+
+        let accent = shared("#ff0000");
+        let link = |node: &mut SceneNode| {
+            if let SceneNode::Leaf { style, .. } = node {
+                link_style(style, &accent);
+            }
+        };
+        link(&mut scene.roots[0]);
+        link(&mut scene.symbols[0].children[0]);
+        if let SceneNode::Group { children, .. } = &mut scene.roots[1] {
+            link(&mut children[0]);
+        }
+        assert_eq!(style_uses(&scene, "style_1"), 3);
+
+        assert_eq!(propagate_style(&mut scene, &shared("#00ff00")), 3);
+        assert_eq!(style_of(&scene.roots[0]).fill_color.as_deref(), Some("#00ff00"));
+        assert_eq!(style_of(&scene.symbols[0].children[0]).fill_color.as_deref(), Some("#00ff00"));
+        assert_eq!(style_of(&scene.roots[2]).fill_color.as_deref(), Some("#3b82f6"));
+
+        assert_eq!(detach_style(&mut scene, "style_1"), 3);
+        assert_eq!(style_uses(&scene, "style_1"), 0);
+        assert_eq!(style_of(&scene.roots[0]).fill_color.as_deref(), Some("#00ff00"));
+    }
+
+    #[test]
+    fn test_style_validity() {
+        assert!(is_valid_style(&ObjectStyle::default()));
+        assert!(!is_valid_style(&ObjectStyle { stroke_width: f64::NAN, ..ObjectStyle::default() }));
+        assert!(!is_valid_style(&ObjectStyle { opacity: 2.0, ..ObjectStyle::default() }));
+    }
+}
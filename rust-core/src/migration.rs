@@ -0,0 +1,193 @@
+//! Migration Module - Upgrading saved documents to the current format
+//!
+//! Scene documents carry a `format_version`. Files written before versioning
+//! existed have none and count as version 0. On import the raw JSON is walked
+//! through every migration from its version up to `FORMAT_VERSION` before it
+//! is deserialized, so the scene types only ever describe the current schema.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::validation::{ImportError, ImportErrorCode};
+
+/// Format version written by this build
+pub const FORMAT_VERSION: u32 = 2;
+
+/// One schema step: upgrades a document from `from` to `from + 1`
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut Value),
+}
+
+/// Every migration, in version order
+const MIGRATIONS: &[Migration] = &[
+    Migration { from: 0, description: "paths saved without is_closed are closed", apply: close_unversioned_paths },
+    Migration {
+        from: 1,
+        description: "text names its font by family instead of a session font ID",
+        apply: replace_text_font_ids,
+    },
+];
+
+/// What importing did to bring a document up to date
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MigrationReport {
+    /// Version found in the document (0 for documents without one)
+    pub detected_version: u32,
+    /// Version the document was upgraded to
+    pub current_version: u32,
+    /// Descriptions of the migrations applied, in order
+    pub applied: Vec<String>,
+}
+
+/// Upgrade a scene document in place to the current format version
+/// Fails for documents written by a newer build or with a malformed version.
+pub fn migrate(document: &mut Value) -> Result<MigrationReport, ImportError> {
+    let Value::Object(fields) = document else {
+        return Err(ImportError::new(ImportErrorCode::ParseError, "scene document must be a JSON object"));
+    };
+    let detected_version = match fields.get("format_version") {
+        None => 0,
+        Some(version) => version.as_u64().and_then(|version| u32::try_from(version).ok()).ok_or_else(|| {
+            ImportError::new(ImportErrorCode::UnsupportedVersion, format!("invalid format_version {}", version))
+        })?,
+    };
+    if detected_version > FORMAT_VERSION {
+        return Err(ImportError::new(
+            ImportErrorCode::UnsupportedVersion,
+            format!(
+                "document format version {} is newer than the supported version {}",
+                detected_version, FORMAT_VERSION
+            ),
+        ));
+    }
+
+    let mut applied = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.from >= detected_version) {
+        (migration.apply)(document);
+        applied.push(migration.description.to_string());
+    }
+    if let Value::Object(fields) = document {
+        fields.insert("format_version".to_string(), Value::from(FORMAT_VERSION));
+    }
+    Ok(MigrationReport { detected_version, current_version: FORMAT_VERSION, applied })
+}
+
+/// Upgrade one top-level node of a document whose `format_version` field is given
+/// Migrations rewrite each node on its own, so a document's roots can be upgraded one
+/// at a time as they arrive.
+pub fn migrate_root(node: Value, format_version: &Value) -> Result<Value, ImportError> {
+    let mut document = serde_json::json!({ "format_version": format_version, "roots": [node] });
+    migrate(&mut document)?;
+    Ok(document.get_mut("roots").and_then(|roots| roots.get_mut(0)).map(Value::take).unwrap_or_default())
+}
+
+/// Call `visit` on every serialized object of every node (roots and symbols)
+fn for_each_object(document: &mut Value, visit: &mut dyn FnMut(&mut Value)) {
+    fn walk(nodes: Option<&mut Value>, visit: &mut dyn FnMut(&mut Value)) {
+        let Some(Value::Array(nodes)) = nodes else {
+            return;
+        };
+        for node in nodes {
+            if let Some(leaf) = node.get_mut("Leaf") {
+                if let Some(object) = leaf.get_mut("object") {
+                    visit(object);
+                }
+            } else if let Some(group) = node.get_mut("Group") {
+                walk(group.get_mut("children"), visit);
+            }
+        }
+    }
+
+    walk(document.get_mut("roots"), visit);
+    if let Some(Value::Array(symbols)) = document.get_mut("symbols") {
+        for symbol in symbols {
+            walk(symbol.get_mut("children"), visit);
+        }
+    }
+}
+
+/// Version 0 → 1: `is_closed` was added to paths; older paths were always closed
+fn close_unversioned_paths(document: &mut Value) {
+    for_each_object(document, &mut |object| {
+        if let Some(Value::Object(path)) = object.get_mut("Path") {
+            path.entry("is_closed").or_insert(Value::Bool(true));
+        }
+    });
+}
+
+/// Version 1 → 2: text named its font by the session ID of a registered font, which
+/// means nothing once the session ends. The family is unknown, so the text keeps its
+/// outlines and reports its font as missing until it is set in a registered family.
+fn replace_text_font_ids(document: &mut Value) {
+    for_each_object(document, &mut |object| {
+        if let Some(Value::Object(text)) = object.get_mut("Text") {
+            if text.remove("font_id").is_some() {
+                text.entry("font").or_insert(serde_json::json!({ "family": "" }));
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn path_leaf(path: Value) -> Value {
+        json!({ "Leaf": { "id": "p", "object": { "Path": path }, "transform": {}, "style": {} } })
+    }
+
+    #[test]
+    fn test_unversioned_document_is_migrated() {
+        let mut document = json!({
+            "roots": [
+                path_leaf(json!({ "commands": [] })),
+                { "Group": { "id": "g", "children": [path_leaf(json!({ "commands": [], "is_closed": false }))] } }
+            ],
+            "symbols": [{ "id": "s", "name": "S", "children": [path_leaf(json!({ "commands": [] }))] }]
+        });
+        let report = migrate(&mut document).expect("migrated");
+        assert_eq!(report.detected_version, 0);
+        assert_eq!(report.current_version, FORMAT_VERSION);
+        assert_eq!(report.applied.len(), MIGRATIONS.len());
+        assert_eq!(document["format_version"], json!(FORMAT_VERSION));
+        assert_eq!(document["roots"][0]["Leaf"]["object"]["Path"]["is_closed"], json!(true));
+        // Explicit values are kept
+        assert_eq!(document["roots"][1]["Group"]["children"][0]["Leaf"]["object"]["Path"]["is_closed"], json!(false));
+        assert_eq!(document["symbols"][0]["children"][0]["Leaf"]["object"]["Path"]["is_closed"], json!(true));
+    }
+
+    #[test]
+    fn test_text_font_ids_are_dropped() {
+        let text = json!({ "Text": { "content": "Hi", "font_id": "font_1", "size": 12.0, "x": 0.0, "y": 0.0 } });
+        let mut document = json!({
+            "format_version": 1,
+            "roots": [{ "Leaf": { "id": "t", "object": text, "transform": {}, "style": {} } }]
+        });
+        let report = migrate(&mut document).expect("migrated");
+        assert_eq!(report.applied, vec![MIGRATIONS[1].description.to_string()]);
+        let text = &document["roots"][0]["Leaf"]["object"]["Text"];
+        assert_eq!(text.get("font_id"), None);
+        assert_eq!(text["font"], json!({ "family": "" }));
+    }
+
+    #[test]
+    fn test_current_document_is_untouched() {
+        let mut document = json!({ "format_version": FORMAT_VERSION, "roots": [path_leaf(json!({ "commands": [] }))] });
+        let report = migrate(&mut document).expect("migrated");
+        assert_eq!(report.detected_version, FORMAT_VERSION);
+        assert!(report.applied.is_empty());
+        assert_eq!(document["roots"][0]["Leaf"]["object"]["Path"].get("is_closed"), None);
+    }
+
+    #[test]
+    fn test_rejects_newer_or_malformed_versions() {
+        let mut newer = json!({ "format_version": FORMAT_VERSION + 1, "roots": [] });
+        assert_eq!(migrate(&mut newer).map_err(|e| e.code), Err(ImportErrorCode::UnsupportedVersion));
+        let mut malformed = json!({ "format_version": "one", "roots": [] });
+        assert_eq!(migrate(&mut malformed).map_err(|e| e.code), Err(ImportErrorCode::UnsupportedVersion));
+        assert_eq!(migrate(&mut json!([])).map_err(|e| e.code), Err(ImportErrorCode::ParseError));
+    }
+}
@@ -0,0 +1,207 @@
+//! Operations Module - Scripted bulk edits applied in one call
+//!
+//! A list of operations (add, set_style, transform, reorder, delete) is applied to a
+//! copy of the scene, so either every operation succeeds or the scene is left as it
+//! was. The editor swaps the copy in as a single undo step, saving scripts and
+//! importers a WASM call per edit. Styles and transforms reach objects inside groups;
+//! reorder and delete address top-level objects.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tsify::Tsify;
+
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{ObjectId, ObjectStyle, SceneGraph, SceneNode, VectorObject};
+use crate::validation::{self, ImportLimits};
+
+/// One edit of a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    /// Add an object on top of the z-order; `style` is a style patch over the default
+    Add {
+        object: VectorObject,
+        #[serde(default)]
+        transform: TransformMatrix,
+        #[serde(default)]
+        style: Map<String, Value>,
+        #[serde(default)]
+        name: Option<String>,
+    },
+    /// Merge style fields (as in the saved scene: fill_color, stroke_width, ...) into
+    /// each object's style; a null color removes it
+    SetStyle { ids: Vec<ObjectId>, style: Map<String, Value> },
+    /// Move each object on the page by a transform, applied on top of its own
+    Transform { ids: Vec<ObjectId>, matrix: TransformMatrix },
+    /// Move an object to a z-order index (0 = bottom, past the end = top)
+    Reorder { id: ObjectId, index: usize },
+    Delete { ids: Vec<ObjectId> },
+}
+
+/// Why a batch was rejected
+#[derive(Debug, Clone, PartialEq, Serialize, Tsify)]
+pub struct OperationError {
+    /// Position of the failing operation (null if the list itself is malformed)
+    pub index: Option<usize>,
+    pub message: String,
+}
+
+/// Apply operations in order, stopping at the first that fails
+/// Returns the IDs of the added objects. On error the scene may be partly edited, so
+/// callers apply the batch to a copy.
+pub fn apply(
+    scene: &mut SceneGraph,
+    operations: Vec<Operation>,
+    limits: &ImportLimits,
+) -> Result<Vec<ObjectId>, OperationError> {
+    let mut added = Vec::new();
+    for (index, operation) in operations.into_iter().enumerate() {
+        apply_operation(scene, operation, limits, &mut added)
+            .map_err(|message| OperationError { index: Some(index), message })?;
+    }
+    Ok(added)
+}
+
+fn apply_operation(
+    scene: &mut SceneGraph,
+    operation: Operation,
+    limits: &ImportLimits,
+    added: &mut Vec<ObjectId>,
+) -> Result<(), String> {
+    match operation {
+        Operation::Add { object, transform, style, name } => {
+            let id = scene.generate_id();
+            validation::validate_object(&id, &object, limits).map_err(|error| error.message)?;
+            check_matrix(&transform)?;
+            let style = patched_style(scene, &ObjectStyle::default(), &style)?;
+            scene.add_object(id.clone(), object, transform);
+            if let Some(SceneNode::Leaf { style: current, name: current_name, .. }) = scene.get_node_by_id_mut(&id) {
+                *current = style;
+                *current_name = name;
+            }
+            added.push(id);
+        }
+        Operation::SetStyle { ids, style } => {
+            for id in &ids {
+                let current = match scene.get_node_by_id_mut(id) {
+                    Some(SceneNode::Leaf { style, .. }) => style.clone(),
+                    Some(_) => return Err(format!("'{}' has no style", id)),
+                    None => return Err(unknown(id)),
+                };
+                let patched = patched_style(scene, &current, &style)?;
+                if let Some(SceneNode::Leaf { style, .. }) = scene.get_node_by_id_mut(id) {
+                    *style = patched;
+                }
+            }
+        }
+        Operation::Transform { ids, matrix } => {
+            check_matrix(&matrix)?;
+            for id in &ids {
+                let transform = *scene.get_node_by_id(id).ok_or_else(|| unknown(id))?.transform();
+                let local = scene.parent_space_matrix(id, &matrix).ok_or_else(|| format!("'{}' is inside a group with a singular transform", id))?;
+                if let Some(current) = scene.get_node_by_id_mut(id).map(SceneNode::transform_mut) {
+                    *current = local.multiply(&transform);
+                }
+            }
+        }
+        Operation::Reorder { id, index } => {
+            let from = scene.roots.iter().position(|node| *node.id() == id).ok_or_else(|| unknown(&id))?;
+            let node = scene.roots.remove(from);
+            scene.roots.insert(index.min(scene.roots.len()), node);
+        }
+        Operation::Delete { ids } => {
+            if let Some(id) = ids.iter().find(|id| !scene.roots.iter().any(|node| node.id() == *id)) {
+                return Err(unknown(id));
+            }
+            scene.remove_roots(&ids);
+        }
+    }
+    Ok(())
+}
+
+fn unknown(id: &str) -> String {
+    format!("unknown object '{}'", id)
+}
+
+fn check_matrix(matrix: &TransformMatrix) -> Result<(), String> {
+    let values = [matrix.a, matrix.b, matrix.c, matrix.d, matrix.tx, matrix.ty];
+    if values.iter().all(|value| value.is_finite()) {
+        Ok(())
+    } else {
+        Err("transform has a non-finite value".to_string())
+    }
+}
+
+/// A style with the fields of a patch replaced, checked like pasted styles
+fn patched_style(scene: &SceneGraph, style: &ObjectStyle, patch: &Map<String, Value>) -> Result<ObjectStyle, String> {
+    let mut value = serde_json::to_value(style).map_err(|error| error.to_string())?;
+    if let Value::Object(fields) = &mut value {
+        fields.extend(patch.iter().map(|(key, value)| (key.clone(), value.clone())));
+    }
+    crate::parse_style(scene, &value.to_string()).ok_or_else(|| format!("invalid style {}", Value::Object(patch.clone())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn operations(json: &str) -> Vec<Operation> {
+        serde_json::from_str(json).unwrap()
+    }
+
+    #[test]
+    fn test_applies_every_kind_of_operation() {
+        let mut scene = SceneGraph::new();
+        let added = apply(
+            &mut scene,
+            operations(
+                r##"[{"op":"add","object":{"Rectangle":{"x":0,"y":0,"width":10,"height":10}},"name":"a"},
+                    {"op":"add","object":{"Circle":{"cx":0,"cy":0,"r":5}},"style":{"fill_color":"#f00"}},
+                    {"op":"add","object":{"Circle":{"cx":0,"cy":0,"r":1}}},
+                    {"op":"set_style","ids":["obj_1"],"style":{"stroke_color":null,"stroke_width":4}},
+                    {"op":"transform","ids":["obj_1","obj_2"],"matrix":{"a":1,"b":0,"c":0,"d":1,"tx":5,"ty":0}},
+                    {"op":"reorder","id":"obj_2","index":0},
+                    {"op":"delete","ids":["obj_3"]}]"##,
+            ),
+            &ImportLimits::default(),
+        )
+        .unwrap();
+        assert_eq!(added, vec!["obj_1", "obj_2", "obj_3"]);
+
+        let ids: Vec<&str> = scene.roots.iter().map(|node| node.id().as_str()).collect();
+        assert_eq!(ids, vec!["obj_2", "obj_1"]);
+        let Some(SceneNode::Leaf { style, transform, name, .. }) = scene.get_node_by_id("obj_1") else {
+            panic!("rectangle missing");
+        };
+        assert_eq!((style.stroke_color.as_deref(), style.stroke_width), (None, 4.0));
+        assert_eq!(style.fill_color, ObjectStyle::default().fill_color);
+        assert_eq!(transform.tx, 5.0);
+        assert_eq!(name.as_deref(), Some("a"));
+        let Some(SceneNode::Leaf { style, .. }) = scene.get_node_by_id("obj_2") else {
+            panic!("circle missing");
+        };
+        // Colors are normalized like any style
+        assert_eq!(style.fill_color.as_deref(), Some("#ff0000"));
+    }
+
+    #[test]
+    fn test_reports_the_failing_operation() {
+        let mut scene = SceneGraph::new();
+        let error = apply(
+            &mut scene,
+            operations(
+                r#"[{"op":"add","object":{"Circle":{"cx":0,"cy":0,"r":5}}},
+                    {"op":"set_style","ids":["obj_1"],"style":{"fill_color":"not a color"}}]"#,
+            ),
+            &ImportLimits::default(),
+        )
+        .unwrap_err();
+        assert_eq!(error.index, Some(1));
+
+        let error = apply(&mut scene, operations(r#"[{"op":"delete","ids":["obj_1","missing"]}]"#), &ImportLimits::default())
+            .unwrap_err();
+        assert_eq!(error, OperationError { index: Some(0), message: "unknown object 'missing'".to_string() });
+        // Nothing of a rejected delete is removed
+        assert!(scene.get_node_by_id("obj_1").is_some());
+    }
+}
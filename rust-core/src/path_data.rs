@@ -0,0 +1,349 @@
+//! Path Data Module - SVG path `d` strings
+//!
+//! Writes path commands as SVG path data and parses path data back into
+//! commands. The parser accepts the full SVG grammar (absolute and relative
+//! commands, implicit repeats, H/V, smooth and quadratic curves, arcs) and
+//! normalizes everything to the editor's absolute path commands.
+
+use crate::core::scene::PathCommand;
+use crate::renderer::format_number;
+use crate::validation::{ImportError, ImportErrorCode};
+
+/// Write commands as a path data string ("M0,0 L10,0 C... Z")
+/// ClosePath is only written for closed paths, matching SVG export.
+pub fn to_path_data(commands: &[PathCommand], is_closed: bool) -> String {
+    let n = |value: f64| format_number(value, None);
+    let mut parts = Vec::with_capacity(commands.len());
+    for cmd in commands {
+        match *cmd {
+            PathCommand::MoveTo { x, y } => parts.push(format!("M{},{}", n(x), n(y))),
+            PathCommand::LineTo { x, y } => parts.push(format!("L{},{}", n(x), n(y))),
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                parts.push(format!("C{},{} {},{} {},{}", n(x1), n(y1), n(x2), n(y2), n(x), n(y)))
+            }
+            PathCommand::QuadTo { x1, y1, x, y } => parts.push(format!("Q{},{} {},{}", n(x1), n(y1), n(x), n(y))),
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => parts.push(format!(
+                "A{},{} {} {} {} {},{}",
+                n(rx),
+                n(ry),
+                n(rotation),
+                u8::from(large_arc),
+                u8::from(sweep),
+                n(x),
+                n(y)
+            )),
+            PathCommand::ClosePath => {
+                if is_closed {
+                    parts.push("Z".to_string());
+                }
+            }
+        }
+    }
+    parts.join(" ")
+}
+
+/// Parse a path data string into absolute commands
+/// Smooth curves get their reflected control points; arcs with a zero radius become lines.
+pub fn parse_path_data(d: &str) -> Result<Vec<PathCommand>, ImportError> {
+    let mut parser = Parser { bytes: d.as_bytes(), pos: 0 };
+    let mut commands = Vec::new();
+    let mut current = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    // Reflection sources for S/s and T/t
+    let mut last_cubic_control: Option<(f64, f64)> = None;
+    let mut last_quad_control: Option<(f64, f64)> = None;
+    let mut command: Option<u8> = None;
+
+    loop {
+        parser.skip_separators();
+        let Some(next) = parser.peek() else {
+            break;
+        };
+        let letter = if next.is_ascii_alphabetic() {
+            parser.pos += 1;
+            next
+        } else {
+            // Numbers without a command letter repeat the previous command
+            match command {
+                Some(b'M') => b'L',
+                Some(b'm') => b'l',
+                Some(b'Z' | b'z') | None => return Err(parser.error("expected a command")),
+                Some(previous) => previous,
+            }
+        };
+        if command.is_none() && !matches!(letter, b'M' | b'm') {
+            return Err(parser.error("path data must start with a move command"));
+        }
+        command = Some(letter);
+
+        let relative = letter.is_ascii_lowercase();
+        let offset = |(x, y): (f64, f64)| if relative { (current.0 + x, current.1 + y) } else { (x, y) };
+        let mut cubic_control = None;
+        let mut quad_control = None;
+
+        match letter.to_ascii_uppercase() {
+            b'M' => {
+                let (x, y) = offset(parser.pair()?);
+                commands.push(PathCommand::MoveTo { x, y });
+                current = (x, y);
+                start = current;
+            }
+            b'L' => {
+                let (x, y) = offset(parser.pair()?);
+                commands.push(PathCommand::LineTo { x, y });
+                current = (x, y);
+            }
+            b'H' => {
+                let value = parser.number()?;
+                let x = if relative { current.0 + value } else { value };
+                commands.push(PathCommand::LineTo { x, y: current.1 });
+                current.0 = x;
+            }
+            b'V' => {
+                let value = parser.number()?;
+                let y = if relative { current.1 + value } else { value };
+                commands.push(PathCommand::LineTo { x: current.0, y });
+                current.1 = y;
+            }
+            b'C' => {
+                let (x1, y1) = offset(parser.pair()?);
+                let (x2, y2) = offset(parser.pair()?);
+                let (x, y) = offset(parser.pair()?);
+                commands.push(PathCommand::CurveTo { x1, y1, x2, y2, x, y });
+                cubic_control = Some((x2, y2));
+                current = (x, y);
+            }
+            b'S' => {
+                let (x1, y1) = reflect(last_cubic_control, current);
+                let (x2, y2) = offset(parser.pair()?);
+                let (x, y) = offset(parser.pair()?);
+                commands.push(PathCommand::CurveTo { x1, y1, x2, y2, x, y });
+                cubic_control = Some((x2, y2));
+                current = (x, y);
+            }
+            b'Q' => {
+                let control = offset(parser.pair()?);
+                let end = offset(parser.pair()?);
+                commands.push(PathCommand::QuadTo { x1: control.0, y1: control.1, x: end.0, y: end.1 });
+                quad_control = Some(control);
+                current = end;
+            }
+            b'T' => {
+                let control = reflect(last_quad_control, current);
+                let end = offset(parser.pair()?);
+                commands.push(PathCommand::QuadTo { x1: control.0, y1: control.1, x: end.0, y: end.1 });
+                quad_control = Some(control);
+                current = end;
+            }
+            b'A' => {
+                let (rx, ry) = parser.pair()?;
+                let rotation = parser.number()?;
+                let large_arc = parser.flag()?;
+                let sweep = parser.flag()?;
+                let (x, y) = offset(parser.pair()?);
+                // Out-of-range radii are corrected when drawn (SVG 1.1 appendix F.6.6);
+                // an arc to the current point draws nothing
+                if rx == 0.0 || ry == 0.0 {
+                    commands.push(PathCommand::LineTo { x, y });
+                } else if (x, y) != current {
+                    commands.push(PathCommand::ArcTo { rx: rx.abs(), ry: ry.abs(), rotation, large_arc, sweep, x, y });
+                }
+                current = (x, y);
+            }
+            b'Z' => {
+                commands.push(PathCommand::ClosePath);
+                current = start;
+            }
+            _ => return Err(parser.error(&format!("unknown command '{}'", letter as char))),
+        }
+        last_cubic_control = cubic_control;
+        last_quad_control = quad_control;
+    }
+    Ok(commands)
+}
+
+/// Reflect the previous control point through the current point (or use the current point)
+fn reflect(control: Option<(f64, f64)>, current: (f64, f64)) -> (f64, f64) {
+    match control {
+        Some((cx, cy)) => (2.0 * current.0 - cx, 2.0 * current.1 - cy),
+        None => current,
+    }
+}
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r' | b'\x0c' | b',')) {
+            self.pos += 1;
+        }
+    }
+
+    fn error(&self, message: &str) -> ImportError {
+        ImportError::new(ImportErrorCode::ParseError, format!("invalid path data at offset {}: {}", self.pos, message))
+            .at_offset(self.pos)
+    }
+
+    fn pair(&mut self) -> Result<(f64, f64), ImportError> {
+        Ok((self.number()?, self.number()?))
+    }
+
+    /// Read a number: sign, digits, optional fraction and exponent ("-1.5e3", ".5")
+    fn number(&mut self) -> Result<f64, ImportError> {
+        self.skip_separators();
+        let begin = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let digits_start = self.pos;
+        self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            self.skip_digits();
+        }
+        if self.pos == digits_start || &self.bytes[digits_start..self.pos] == b"." {
+            self.pos = begin;
+            return Err(self.error("expected a number"));
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let before_exponent = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            let exponent_start = self.pos;
+            self.skip_digits();
+            if self.pos == exponent_start {
+                // Not an exponent after all
+                self.pos = before_exponent;
+            }
+        }
+        std::str::from_utf8(&self.bytes[begin..self.pos])
+            .ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| self.error("invalid number"))
+    }
+
+    /// Arc flags are a single 0 or 1 and may be written without separators ("a5 5 0 0110 10")
+    fn flag(&mut self) -> Result<bool, ImportError> {
+        self.skip_separators();
+        match self.peek() {
+            Some(b'0') => {
+                self.pos += 1;
+                Ok(false)
+            }
+            Some(b'1') => {
+                self.pos += 1;
+                Ok(true)
+            }
+            _ => Err(self.error("expected an arc flag (0 or 1)")),
+        }
+    }
+
+    fn skip_digits(&mut self) {
+        while matches!(self.peek(), Some(b'0'..=b'9')) {
+            self.pos += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn end_point(cmd: &PathCommand) -> (f64, f64) {
+        match *cmd {
+            PathCommand::MoveTo { x, y }
+            | PathCommand::LineTo { x, y }
+            | PathCommand::CurveTo { x, y, .. }
+            | PathCommand::QuadTo { x, y, .. }
+            | PathCommand::ArcTo { x, y, .. } => (x, y),
+            PathCommand::ClosePath => panic!("ClosePath has no end point"),
+        }
+    }
+
+    #[test]
+    fn test_write_and_parse_roundtrip() {
+        let commands = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.5, y: -2.0 },
+            PathCommand::CurveTo { x1: 1.0, y1: 2.0, x2: 3.0, y2: 4.0, x: 5.0, y: 6.0 },
+            PathCommand::ClosePath,
+        ];
+        let d = to_path_data(&commands, true);
+        assert_eq!(d, "M0,0 L10.5,-2 C1,2 3,4 5,6 Z");
+        assert_eq!(parse_path_data(&d).unwrap(), commands);
+        assert_eq!(to_path_data(&commands, false), "M0,0 L10.5,-2 C1,2 3,4 5,6");
+    }
+
+    #[test]
+    fn test_relative_and_implicit_commands() {
+        let commands = parse_path_data("m10 10 20 0 v5 h-5-5z").unwrap();
+        let points: Vec<(f64, f64)> = commands[..5].iter().map(end_point).collect();
+        assert_eq!(points, vec![(10.0, 10.0), (30.0, 10.0), (30.0, 15.0), (25.0, 15.0), (20.0, 15.0)]);
+        assert_eq!(commands[5], PathCommand::ClosePath);
+    }
+
+    #[test]
+    fn test_compact_numbers() {
+        let commands = parse_path_data("M.5.5L1e1-2.5").unwrap();
+        assert_eq!(commands[0], PathCommand::MoveTo { x: 0.5, y: 0.5 });
+        assert_eq!(commands[1], PathCommand::LineTo { x: 10.0, y: -2.5 });
+    }
+
+    #[test]
+    fn test_smooth_and_quadratic_curves() {
+        let commands = parse_path_data("M0 0 C0 10 10 10 10 0 S20 -10 20 0 Q25 10 30 0 T40 0").unwrap();
+        // S reflects the previous second control point (10, 10) through (10, 0)
+        assert_eq!(commands[2], PathCommand::CurveTo { x1: 10.0, y1: -10.0, x2: 20.0, y2: -10.0, x: 20.0, y: 0.0 });
+        assert_eq!(commands[3], PathCommand::QuadTo { x1: 25.0, y1: 10.0, x: 30.0, y: 0.0 });
+        // T reflects the previous quadratic control point through (30, 0)
+        assert_eq!(commands[4], PathCommand::QuadTo { x1: 35.0, y1: -10.0, x: 40.0, y: 0.0 });
+    }
+
+    #[test]
+    fn test_arcs_keep_their_parameters() {
+        let commands = parse_path_data("M0 0a10 10 30 0120 0 A0 5 0 0 0 30 5 A5 5 0 0 0 30 5").unwrap();
+        assert_eq!(
+            commands[1],
+            PathCommand::ArcTo { rx: 10.0, ry: 10.0, rotation: 30.0, large_arc: false, sweep: true, x: 20.0, y: 0.0 }
+        );
+        // A zero radius is a straight line and an arc to the current point is dropped
+        assert_eq!(commands[2], PathCommand::LineTo { x: 30.0, y: 5.0 });
+        assert_eq!(commands.len(), 3);
+        assert_eq!(to_path_data(&commands[..2], false), "M0,0 A10,10 30 0 1 20,0");
+    }
+
+    #[test]
+    fn test_write_and_parse_quadratics_and_arcs() {
+        let commands = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::QuadTo { x1: 5.0, y1: 10.0, x: 10.0, y: 0.0 },
+            PathCommand::ArcTo { rx: 5.0, ry: 3.0, rotation: 0.0, large_arc: true, sweep: false, x: 20.0, y: 0.0 },
+        ];
+        let d = to_path_data(&commands, false);
+        assert_eq!(d, "M0,0 Q5,10 10,0 A5,3 0 1 0 20,0");
+        assert_eq!(parse_path_data(&d).unwrap(), commands);
+    }
+
+    #[test]
+    fn test_rejects_malformed_data() {
+        for d in ["L0 0", "M0 0 L10", "M0 0 X1 1", "M0 0 A1 1 0 2 0 5 5", "M0 0 L1e999 0", "M0 0 Z 1 1"] {
+            let error = parse_path_data(d).unwrap_err();
+            assert_eq!(error.code, ImportErrorCode::ParseError, "{}", d);
+        }
+        assert!(parse_path_data("").unwrap().is_empty());
+
+        // Errors carry the offset where parsing stopped
+        let error = parse_path_data("M0 0 L10").unwrap_err();
+        assert_eq!(error.location.and_then(|location| location.offset), Some(8));
+    }
+}
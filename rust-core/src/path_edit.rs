@@ -0,0 +1,976 @@
+//! Path Edit Module - Editing path geometry at arbitrary locations
+//!
+//! Locations on a path are addressed by the index of the command that draws
+//! the segment and a parameter t along it (a ClosePath draws the straight
+//! segment back to its subpath's start). Cutting or subdividing a segment uses
+//! de Casteljau's construction, so the visible shape does not change.
+//!
+//! Editing works on paths of lines and cubic curves: quadratics and arcs are
+//! promoted to cubics first (see `promote_to_cubic`).
+
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet};
+use tsify::Tsify;
+
+use crate::core::geometry;
+use crate::core::scene::{AnchorType, PathCommand};
+
+type Point = (f64, f64);
+
+/// An anchor of a path as the direct selection tool shows it (in world space)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Tsify)]
+pub struct PathPoint {
+    pub x: f64,
+    pub y: f64,
+    #[serde(rename = "type")]
+    pub kind: PathPointKind,
+    /// Only present once an anchor type has been set on the path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub anchor: Option<AnchorType>,
+}
+
+/// Command an anchor ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum PathPointKind {
+    Move,
+    Line,
+    Curve,
+}
+
+/// Samples per cubic segment before refining the nearest parameter
+const NEAREST_SAMPLES: usize = 64;
+
+/// Parameters closer than this to a segment's end are treated as its anchors
+const END_EPSILON: f64 = 1e-6;
+
+/// A location on a path
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathLocation {
+    /// Index of the LineTo, CurveTo or ClosePath command drawing the segment
+    pub command_index: usize,
+    pub t: f64,
+    pub x: f64,
+    pub y: f64,
+    pub distance: f64,
+}
+
+/// Result of cutting a path at an anchor
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathCut {
+    /// A closed subpath was opened; the path now starts and ends at the cut
+    Opened(Vec<PathCommand>),
+    /// An open subpath was cut in two; everything before the cut goes first
+    Split(Vec<PathCommand>, Vec<PathCommand>),
+}
+
+/// Anchors selected for editing in one path (direct selection)
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AnchorSelection {
+    pub path_id: String,
+    /// Anchor indices in get_path_points order
+    pub anchors: BTreeSet<usize>,
+}
+
+/// One drawn segment of a path
+#[derive(Debug, Clone, Copy)]
+enum Segment {
+    Line(Point, Point),
+    Cubic(Point, Point, Point, Point),
+}
+
+impl Segment {
+    fn point_at(&self, t: f64) -> Point {
+        match *self {
+            Segment::Line(a, b) => (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t),
+            Segment::Cubic(p0, p1, p2, p3) => {
+                (geometry::cubic_at(p0.0, p1.0, p2.0, p3.0, t), geometry::cubic_at(p0.1, p1.1, p2.1, p3.1, t))
+            }
+        }
+    }
+
+    /// Parameter of the point nearest to (x, y)
+    fn nearest_t(&self, x: f64, y: f64) -> f64 {
+        let distance2 = |t: f64| {
+            let (px, py) = self.point_at(t);
+            (px - x).powi(2) + (py - y).powi(2)
+        };
+        match *self {
+            Segment::Line(a, b) => {
+                let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+                let length2 = dx * dx + dy * dy;
+                if length2 == 0.0 {
+                    return 0.0;
+                }
+                (((x - a.0) * dx + (y - a.1) * dy) / length2).clamp(0.0, 1.0)
+            }
+            Segment::Cubic(..) => {
+                // Coarse sampling picks the right basin, golden-section search refines it
+                let step = 1.0 / NEAREST_SAMPLES as f64;
+                let best = (0..=NEAREST_SAMPLES)
+                    .map(|i| i as f64 * step)
+                    .min_by(|a, b| distance2(*a).total_cmp(&distance2(*b)))
+                    .unwrap_or(0.0);
+                let (mut low, mut high) = ((best - step).max(0.0), (best + step).min(1.0));
+                let ratio = (5f64.sqrt() - 1.0) / 2.0;
+                for _ in 0..48 {
+                    let m1 = high - ratio * (high - low);
+                    let m2 = low + ratio * (high - low);
+                    if distance2(m1) < distance2(m2) {
+                        high = m2;
+                    } else {
+                        low = m1;
+                    }
+                }
+                let refined = (low + high) / 2.0;
+                if distance2(refined) < distance2(best) { refined } else { best }
+            }
+        }
+    }
+}
+
+/// Segments of a path with the index of the command drawing each one
+fn segments(commands: &[PathCommand]) -> Vec<(usize, Segment)> {
+    let mut segments = Vec::new();
+    let mut position = (0.0, 0.0);
+    let mut start = (0.0, 0.0);
+    for (index, command) in commands.iter().enumerate() {
+        match *command {
+            PathCommand::MoveTo { x, y } => {
+                position = (x, y);
+                start = position;
+            }
+            PathCommand::LineTo { x, y } => {
+                segments.push((index, Segment::Line(position, (x, y))));
+                position = (x, y);
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                segments.push((index, Segment::Cubic(position, (x1, y1), (x2, y2), (x, y))));
+                position = (x, y);
+            }
+            // Not editable until promoted
+            PathCommand::QuadTo { x, y, .. } | PathCommand::ArcTo { x, y, .. } => position = (x, y),
+            PathCommand::ClosePath => {
+                if position != start {
+                    segments.push((index, Segment::Line(position, start)));
+                }
+                position = start;
+            }
+        }
+    }
+    segments
+}
+
+/// Nearest location on a path to (x, y), or None if the path draws nothing
+pub fn nearest_location(commands: &[PathCommand], x: f64, y: f64) -> Option<PathLocation> {
+    segments(commands)
+        .into_iter()
+        .map(|(command_index, segment)| {
+            let t = segment.nearest_t(x, y);
+            let (px, py) = segment.point_at(t);
+            PathLocation { command_index, t, x: px, y: py, distance: (px - x).hypot(py - y) }
+        })
+        .min_by(|a, b| a.distance.total_cmp(&b.distance))
+}
+
+/// Split a cubic bezier at t (de Casteljau); returns the control points of both halves
+pub fn split_cubic(p0: Point, p1: Point, p2: Point, p3: Point, t: f64) -> ([Point; 4], [Point; 4]) {
+    let lerp = |a: Point, b: Point| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+    let (q0, q1, q2) = (lerp(p0, p1), lerp(p1, p2), lerp(p2, p3));
+    let (r0, r1) = (lerp(q0, q1), lerp(q1, q2));
+    let s = lerp(r0, r1);
+    ([p0, q0, r0, s], [s, r1, q2, p3])
+}
+
+/// Insert an anchor into the segment drawn by `command_index` at parameter t
+/// The shape is unchanged; the new anchor is the end point of the command at
+/// `command_index` in the result. Returns None if the index does not draw a
+/// segment or t is not strictly inside it.
+pub fn subdivide(commands: &[PathCommand], command_index: usize, t: f64) -> Option<Vec<PathCommand>> {
+    if !(END_EPSILON..=1.0 - END_EPSILON).contains(&t) {
+        return None;
+    }
+    let (_, segment) = segments(commands).into_iter().find(|(index, _)| *index == command_index)?;
+    let (mx, my) = segment.point_at(t);
+    let replacement = match (segment, &commands[command_index]) {
+        (Segment::Cubic(p0, p1, p2, p3), _) => {
+            let ([_, a1, a2, a3], [_, b1, b2, b3]) = split_cubic(p0, p1, p2, p3, t);
+            vec![
+                PathCommand::CurveTo { x1: a1.0, y1: a1.1, x2: a2.0, y2: a2.1, x: a3.0, y: a3.1 },
+                PathCommand::CurveTo { x1: b1.0, y1: b1.1, x2: b2.0, y2: b2.1, x: b3.0, y: b3.1 },
+            ]
+        }
+        (Segment::Line(..), PathCommand::ClosePath) => vec![PathCommand::LineTo { x: mx, y: my }, PathCommand::ClosePath],
+        (Segment::Line(_, (x, y)), _) => vec![PathCommand::LineTo { x: mx, y: my }, PathCommand::LineTo { x, y }],
+    };
+    let mut result = commands.to_vec();
+    result.splice(command_index..=command_index, replacement);
+    Some(result)
+}
+
+/// The same path with quadratics and arcs promoted to cubic curves
+/// Quadratics keep their anchor; arcs longer than a quarter turn gain smooth anchors
+/// where their pieces meet, and the anchor types are renumbered to match.
+pub fn promote_to_cubic(commands: &[PathCommand], anchor_types: &[AnchorType]) -> (Vec<PathCommand>, Vec<AnchorType>) {
+    let mut promoted = Vec::with_capacity(commands.len());
+    let mut types = Vec::with_capacity(commands.len());
+    let mut anchors = 0;
+    let (mut start, mut current) = ((0.0, 0.0), (0.0, 0.0));
+    for command in commands {
+        let Some(end) = command.end_point() else {
+            promoted.push(PathCommand::ClosePath);
+            current = start;
+            continue;
+        };
+        match *command {
+            PathCommand::QuadTo { x1, y1, x, y } => promoted.push(geometry::quad_to_cubic(current, (x1, y1), (x, y))),
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+                let pieces = geometry::arc_to_curves(current, (rx, ry), rotation, large_arc, sweep, (x, y));
+                if pieces.is_empty() {
+                    // An arc back to its start draws nothing but still holds an anchor
+                    promoted.push(PathCommand::LineTo { x, y });
+                } else {
+                    types.extend(std::iter::repeat_n(AnchorType::Smooth, pieces.len() - 1));
+                    promoted.extend(pieces);
+                }
+            }
+            PathCommand::MoveTo { .. } => {
+                promoted.push(command.clone());
+                start = end;
+            }
+            _ => promoted.push(command.clone()),
+        }
+        types.push(anchor_types.get(anchors).copied().unwrap_or_default());
+        anchors += 1;
+        current = end;
+    }
+    if types.len() == anchors {
+        // No anchors were added, so the types still line up
+        return (promoted, anchor_types.to_vec());
+    }
+    (promoted, types)
+}
+
+/// Index of the command drawing the n-th segment (closing segments included)
+pub fn segment_command_index(commands: &[PathCommand], segment_index: usize) -> Option<usize> {
+    segments(commands).get(segment_index).map(|(command_index, _)| *command_index)
+}
+
+/// Index of a command's end point among the path's anchors (every command but ClosePath)
+pub fn anchor_index(commands: &[PathCommand], command_index: usize) -> usize {
+    commands[..command_index].iter().filter(|command| **command != PathCommand::ClosePath).count()
+}
+
+/// Anchor index of the MoveTo starting the subpath an anchor belongs to
+pub fn subpath_start_anchor(commands: &[PathCommand], anchor: usize) -> Option<usize> {
+    let mut start = None;
+    for (index, command) in commands.iter().filter(|command| **command != PathCommand::ClosePath).enumerate() {
+        if matches!(command, PathCommand::MoveTo { .. }) {
+            start = Some(index);
+        }
+        if index == anchor {
+            return start;
+        }
+    }
+    None
+}
+
+/// Cut a path at a location, inserting an anchor there if needed
+/// Returns None when the location is the start or end of an open subpath.
+pub fn cut_at(commands: &[PathCommand], location: &PathLocation) -> Option<PathCut> {
+    let (commands, anchor) = if location.t <= END_EPSILON {
+        (commands.to_vec(), location.command_index.checked_sub(1)?)
+    } else if location.t >= 1.0 - END_EPSILON {
+        (commands.to_vec(), location.command_index)
+    } else {
+        (subdivide(commands, location.command_index, location.t)?, location.command_index)
+    };
+    cut_at_anchor(&commands, anchor)
+}
+
+/// Cut a path at the end point of the command at `anchor`
+fn cut_at_anchor(commands: &[PathCommand], anchor: usize) -> Option<PathCut> {
+    let (start, end) = subpath_range(commands, anchor)?;
+    let start_point = end_point(&commands[start])?;
+
+    if let Some(close) = commands[start..end].iter().position(|command| *command == PathCommand::ClosePath) {
+        let close = start + close;
+        // A cut on the ClosePath itself lands on the subpath's start
+        let anchor = if anchor >= close { start } else { anchor };
+        let (x, y) = end_point(&commands[anchor])?;
+        let mut opened = vec![PathCommand::MoveTo { x, y }];
+        opened.extend_from_slice(&commands[anchor + 1..close]);
+        let last = if close > start + 1 { end_point(&commands[close - 1]) } else { Some(start_point) };
+        if last != Some(start_point) {
+            opened.push(PathCommand::LineTo { x: start_point.0, y: start_point.1 });
+        }
+        opened.extend_from_slice(&commands[start + 1..=anchor]);
+
+        let mut result = commands[..start].to_vec();
+        result.extend(opened);
+        // Anything after the ClosePath (before the next MoveTo) is dropped with it
+        result.extend_from_slice(&commands[end..]);
+        return Some(PathCut::Opened(result));
+    }
+
+    if anchor == start || anchor + 1 >= end {
+        return None;
+    }
+    let (x, y) = end_point(&commands[anchor])?;
+    let first = commands[..=anchor].to_vec();
+    let mut second = vec![PathCommand::MoveTo { x, y }];
+    second.extend_from_slice(&commands[anchor + 1..]);
+    Some(PathCut::Split(first, second))
+}
+
+/// Join two open paths at their nearest end points
+/// The result runs through `a` into `b` (either may be reversed to meet). The ends
+/// are connected with a straight segment, or with `average` moved to their midpoint
+/// and merged into one anchor (handles move along). Returns None unless both paths
+/// are a single open subpath.
+pub fn join(a: &[PathCommand], b: &[PathCommand], average: bool) -> Option<Vec<PathCommand>> {
+    let (a_start, a_end) = open_ends(a)?;
+    let (b_start, b_end) = open_ends(b)?;
+    let distance = |p: Point, q: Point| (p.0 - q.0).hypot(p.1 - q.1);
+    // (reverse a, reverse b) so that a ends where b starts
+    let pairs = [
+        (distance(a_end, b_start), false, false),
+        (distance(a_end, b_end), false, true),
+        (distance(a_start, b_start), true, false),
+        (distance(a_start, b_end), true, true),
+    ];
+    let (_, reverse_a, reverse_b) = pairs.into_iter().min_by(|p, q| p.0.total_cmp(&q.0))?;
+    let mut a = if reverse_a { reverse(a) } else { a.to_vec() };
+    let mut b = if reverse_b { reverse(b) } else { b.to_vec() };
+    let (from, to) = (end_point(a.last()?)?, end_point(&b[0])?);
+
+    if average {
+        let mid = ((from.0 + to.0) / 2.0, (from.1 + to.1) / 2.0);
+        move_end(a.last_mut()?, mid);
+        if let Some(PathCommand::CurveTo { x1, y1, .. }) = b.get_mut(1) {
+            *x1 += mid.0 - to.0;
+            *y1 += mid.1 - to.1;
+        }
+    } else if from != to {
+        a.push(PathCommand::LineTo { x: to.0, y: to.1 });
+    }
+    a.extend_from_slice(&b[1..]);
+    Some(a)
+}
+
+/// Make an anchor smooth: its handles become collinear and mirrored
+/// Straight segments next to the anchor become curves (with handles a third of
+/// the way along, so they still look straight) to give it handles. When the
+/// anchor closes a subpath through an implicit closing line, that line becomes an
+/// explicit segment, adding an anchor at the returned index.
+/// Returns None if the anchor does not exist or has no segments.
+pub fn smooth_anchor(commands: &[PathCommand], anchor: usize) -> Option<(Vec<PathCommand>, Option<usize>)> {
+    let mut commands = commands.to_vec();
+    let index = anchor_command_index(&commands, anchor)?;
+    let point = end_point(&commands[index])?;
+    let (start, end) = subpath_range(&commands, index)?;
+    let start_point = end_point(&commands[start])?;
+    let close = commands[start..end].iter().position(|command| *command == PathCommand::ClosePath).map(|offset| start + offset);
+    let (mut incoming, mut outgoing) = adjacent_segments(&commands, index);
+    let mut inserted = None;
+
+    // Implicit closing lines become explicit segments so the anchor can get a handle on that side
+    if let (None, Some(close)) = (incoming, close) {
+        if index == start && close > start + 1 {
+            commands.insert(close, PathCommand::LineTo { x: point.0, y: point.1 });
+            inserted = Some(anchor_index(&commands, close));
+            incoming = Some(close);
+        }
+    }
+    if outgoing.is_none() && commands.get(index + 1) == Some(&PathCommand::ClosePath) && point != start_point {
+        commands.insert(index + 1, PathCommand::LineTo { x: start_point.0, y: start_point.1 });
+        inserted = Some(anchor + 1);
+        outgoing = Some(index + 1);
+    }
+    if incoming.is_none() && outgoing.is_none() {
+        return None;
+    }
+
+    // Give both sides handles, then align them
+    let handle_in = incoming.and_then(|incoming| {
+        let from = end_point(&commands[incoming - 1])?;
+        to_curve(&mut commands[incoming], from);
+        match commands[incoming] {
+            PathCommand::CurveTo { x2, y2, .. } => Some((x2, y2)),
+            _ => None,
+        }
+    });
+    let handle_out = outgoing.and_then(|outgoing| {
+        to_curve(&mut commands[outgoing], point);
+        match commands[outgoing] {
+            PathCommand::CurveTo { x1, y1, .. } => Some((x1, y1)),
+            _ => None,
+        }
+    });
+    let length = |handle: Option<Point>| handle.map(|(x, y)| (x - point.0).hypot(y - point.1));
+    let (direction, reach) = match (handle_in, handle_out) {
+        (Some(h_in), Some(h_out)) => ((h_out.0 - h_in.0, h_out.1 - h_in.1), (length(handle_in)? + length(handle_out)?) / 2.0),
+        (Some(h_in), None) => ((point.0 - h_in.0, point.1 - h_in.1), length(handle_in)?),
+        (None, Some(h_out)) => ((h_out.0 - point.0, h_out.1 - point.1), length(handle_out)?),
+        (None, None) => return None,
+    };
+    let norm = direction.0.hypot(direction.1);
+    if norm > 1e-12 {
+        let (dx, dy) = (direction.0 / norm * reach, direction.1 / norm * reach);
+        if let Some(PathCommand::CurveTo { x2, y2, .. }) = incoming.map(|incoming| &mut commands[incoming]) {
+            (*x2, *y2) = (point.0 - dx, point.1 - dy);
+        }
+        if let Some(PathCommand::CurveTo { x1, y1, .. }) = outgoing.map(|outgoing| &mut commands[outgoing]) {
+            (*x1, *y1) = (point.0 + dx, point.1 + dy);
+        }
+    }
+    Some((commands, inserted))
+}
+
+/// Which handle of an anchor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleSide {
+    /// Control point of the segment arriving at the anchor
+    In,
+    /// Control point of the segment leaving the anchor
+    Out,
+}
+
+impl HandleSide {
+    /// Parse a handle name: "in" | "out"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "in" => Some(HandleSide::In),
+            "out" => Some(HandleSide::Out),
+            _ => None,
+        }
+    }
+}
+
+/// An anchor with its handles (None where the adjacent segment is straight or missing)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnchorHandles {
+    pub x: f64,
+    pub y: f64,
+    pub handle_in: Option<Point>,
+    pub handle_out: Option<Point>,
+}
+
+/// Every anchor of a path with its handles, in get_path_points order
+/// The start of a closed subpath whose last segment ends on it shows that
+/// segment's handle as its in handle (and that end point shows the first
+/// segment's handle as its out handle), so both copies show the whole anchor.
+pub fn anchor_handles(commands: &[PathCommand]) -> Vec<AnchorHandles> {
+    commands
+        .iter()
+        .enumerate()
+        .filter_map(|(index, command)| {
+            let (x, y) = end_point(command)?;
+            let (incoming, outgoing) = adjacent_segments(commands, index);
+            let handle_in = incoming.and_then(|incoming| match commands[incoming] {
+                PathCommand::CurveTo { x2, y2, .. } => Some((x2, y2)),
+                _ => None,
+            });
+            let handle_out = outgoing.and_then(|outgoing| match commands[outgoing] {
+                PathCommand::CurveTo { x1, y1, .. } => Some((x1, y1)),
+                _ => None,
+            });
+            Some(AnchorHandles { x, y, handle_in, handle_out })
+        })
+        .collect()
+}
+
+/// Move one handle of an anchor; with `mirror` the opposite handle is placed
+/// opposite it at the same distance. Returns false if the handle does not exist.
+pub fn set_handle(commands: &mut [PathCommand], anchor: usize, side: HandleSide, (hx, hy): Point, mirror: bool) -> bool {
+    let Some(index) = anchor_command_index(commands, anchor) else {
+        return false;
+    };
+    let Some(point) = end_point(&commands[index]) else {
+        return false;
+    };
+    let (incoming, outgoing) = adjacent_segments(commands, index);
+    let (moved, opposite) = match side {
+        HandleSide::In => (incoming, outgoing),
+        HandleSide::Out => (outgoing, incoming),
+    };
+    let mirrored = (2.0 * point.0 - hx, 2.0 * point.1 - hy);
+    let place = |command: &mut PathCommand, side: HandleSide, (x, y): Point| match (command, side) {
+        (PathCommand::CurveTo { x2, y2, .. }, HandleSide::In) => {
+            (*x2, *y2) = (x, y);
+            true
+        }
+        (PathCommand::CurveTo { x1, y1, .. }, HandleSide::Out) => {
+            (*x1, *y1) = (x, y);
+            true
+        }
+        _ => false,
+    };
+    let Some(moved) = moved else {
+        return false;
+    };
+    if !place(&mut commands[moved], side, (hx, hy)) {
+        return false;
+    }
+    if let (true, Some(opposite)) = (mirror, opposite) {
+        let other = if side == HandleSide::In { HandleSide::Out } else { HandleSide::In };
+        place(&mut commands[opposite], other, mirrored);
+    }
+    true
+}
+
+/// Move anchors by (dx, dy) together with their handles
+/// The closing copy of a subpath's start moves with it, so closed shapes stay closed.
+pub fn move_anchors(commands: &mut [PathCommand], anchors: &BTreeSet<usize>, (dx, dy): Point) {
+    let mut points = HashSet::new();
+    let mut handles_in = HashSet::new();
+    let mut handles_out = HashSet::new();
+    for &anchor in anchors {
+        let Some(index) = anchor_command_index(commands, anchor) else {
+            continue;
+        };
+        points.insert(index);
+        let (incoming, outgoing) = adjacent_segments(commands, index);
+        if let Some(incoming) = incoming {
+            // An incoming segment other than the anchor's own command ends on its twin
+            points.insert(incoming);
+            handles_in.insert(incoming);
+        }
+        if let Some(outgoing) = outgoing {
+            // Likewise an outgoing segment that does not follow the anchor starts at its twin
+            points.insert(outgoing - 1);
+            handles_out.insert(outgoing);
+        }
+    }
+    for (index, command) in commands.iter_mut().enumerate() {
+        match command {
+            PathCommand::MoveTo { x, y }
+            | PathCommand::LineTo { x, y }
+            | PathCommand::QuadTo { x, y, .. }
+            | PathCommand::ArcTo { x, y, .. } => {
+                if points.contains(&index) {
+                    *x += dx;
+                    *y += dy;
+                }
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                if points.contains(&index) {
+                    *x += dx;
+                    *y += dy;
+                }
+                if handles_in.contains(&index) {
+                    *x2 += dx;
+                    *y2 += dy;
+                }
+                if handles_out.contains(&index) {
+                    *x1 += dx;
+                    *y1 += dy;
+                }
+            }
+            PathCommand::ClosePath => {}
+        }
+    }
+}
+
+/// Command index of an anchor (anchors are every command but ClosePath)
+fn anchor_command_index(commands: &[PathCommand], anchor: usize) -> Option<usize> {
+    commands
+        .iter()
+        .enumerate()
+        .filter(|(_, command)| **command != PathCommand::ClosePath)
+        .nth(anchor)
+        .map(|(index, _)| index)
+}
+
+/// Range of the subpath containing a command: its MoveTo up to the next MoveTo
+fn subpath_range(commands: &[PathCommand], index: usize) -> Option<(usize, usize)> {
+    let start = commands[..=index].iter().rposition(|command| matches!(command, PathCommand::MoveTo { .. }))?;
+    let end = commands[index + 1..]
+        .iter()
+        .position(|command| matches!(command, PathCommand::MoveTo { .. }))
+        .map_or(commands.len(), |offset| index + 1 + offset);
+    Some((start, end))
+}
+
+/// Segments (LineTo or CurveTo commands) arriving at and leaving the anchor drawn by a command
+/// Where a closed subpath's last segment ends on its start, the two copies of that
+/// anchor share their segments.
+fn adjacent_segments(commands: &[PathCommand], index: usize) -> (Option<usize>, Option<usize>) {
+    let is_segment = |command: &PathCommand| matches!(command, PathCommand::LineTo { .. } | PathCommand::CurveTo { .. });
+    let (Some(point), Some((start, end))) = (end_point(&commands[index]), subpath_range(commands, index)) else {
+        return (None, None);
+    };
+    let close = commands[start..end].iter().position(|command| *command == PathCommand::ClosePath).map(|offset| start + offset);
+    let start_point = end_point(&commands[start]);
+
+    let incoming = if is_segment(&commands[index]) {
+        Some(index)
+    } else {
+        close.filter(|close| *close > start + 1 && end_point(&commands[close - 1]) == Some(point)).map(|close| close - 1)
+    };
+    let outgoing = match commands.get(index + 1) {
+        Some(command) if is_segment(command) && index + 1 < end => Some(index + 1),
+        Some(PathCommand::ClosePath) if start_point == Some(point) && index > start => {
+            Some(start + 1).filter(|next| commands.get(*next).is_some_and(is_segment))
+        }
+        _ => None,
+    };
+    (incoming, outgoing)
+}
+
+/// Turn a straight segment into the equivalent curve
+fn to_curve(command: &mut PathCommand, (from_x, from_y): Point) {
+    if let PathCommand::LineTo { x, y } = *command {
+        let (dx, dy) = ((x - from_x) / 3.0, (y - from_y) / 3.0);
+        *command = PathCommand::CurveTo { x1: from_x + dx, y1: from_y + dy, x2: x - dx, y2: y - dy, x, y };
+    }
+}
+
+/// Close the last subpath of a path
+/// With `curved`, the closing segment is a curve leaving the end and arriving at the
+/// start along their tangents; otherwise it is a straight line (a plain ClosePath).
+/// Returns None if the last subpath is already closed or has no segments.
+pub fn close_last_subpath(commands: &[PathCommand], curved: bool) -> Option<Vec<PathCommand>> {
+    let start = commands.iter().rposition(|command| matches!(command, PathCommand::MoveTo { .. }))?;
+    let (first, last) = (end_point(&commands[start])?, end_point(commands.last()?)?);
+    if commands.len() < start + 2 {
+        return None;
+    }
+    let mut closed = commands.to_vec();
+    if curved && first != last {
+        let reach = (first.0 - last.0).hypot(first.1 - last.1) / 3.0;
+        // Tangent at the end: from its incoming handle (or previous anchor) to the end
+        let before_end = match commands[commands.len() - 1] {
+            PathCommand::CurveTo { x2, y2, .. } if (x2, y2) != last => (x2, y2),
+            _ => end_point(&commands[commands.len() - 2])?,
+        };
+        // Tangent at the start: from the start to its outgoing handle (or next anchor)
+        let after_start = match commands[start + 1] {
+            PathCommand::CurveTo { x1, y1, .. } if (x1, y1) != first => (x1, y1),
+            ref command => end_point(command)?,
+        };
+        let unit = |(ax, ay): Point, (bx, by): Point| {
+            let length = (bx - ax).hypot(by - ay);
+            if length > 1e-12 { ((bx - ax) / length, (by - ay) / length) } else { (0.0, 0.0) }
+        };
+        let (ox, oy) = unit(before_end, last);
+        let (ix, iy) = unit(first, after_start);
+        closed.push(PathCommand::CurveTo {
+            x1: last.0 + ox * reach,
+            y1: last.1 + oy * reach,
+            x2: first.0 - ix * reach,
+            y2: first.1 - iy * reach,
+            x: first.0,
+            y: first.1,
+        });
+    }
+    closed.push(PathCommand::ClosePath);
+    Some(closed)
+}
+
+/// Start and end point of a path made of one open subpath
+fn open_ends(commands: &[PathCommand]) -> Option<(Point, Point)> {
+    let single_open = commands.iter().skip(1).all(|command| matches!(command, PathCommand::LineTo { .. } | PathCommand::CurveTo { .. }));
+    if !single_open || commands.len() < 2 {
+        return None;
+    }
+    Some((end_point(&commands[0])?, end_point(commands.last()?)?))
+}
+
+/// The same open subpath traced from its end
+fn reverse(commands: &[PathCommand]) -> Vec<PathCommand> {
+    let Some((x, y)) = commands.last().and_then(end_point) else {
+        return Vec::new();
+    };
+    let mut reversed = vec![PathCommand::MoveTo { x, y }];
+    for i in (1..commands.len()).rev() {
+        let Some((x, y)) = end_point(&commands[i - 1]) else {
+            continue;
+        };
+        reversed.push(match commands[i] {
+            PathCommand::CurveTo { x1, y1, x2, y2, .. } => PathCommand::CurveTo { x1: x2, y1: y2, x2: x1, y2: y1, x, y },
+            _ => PathCommand::LineTo { x, y },
+        });
+    }
+    reversed
+}
+
+/// Move the end point of a command, carrying its incoming handle along
+fn move_end(command: &mut PathCommand, (to_x, to_y): Point) {
+    match command {
+        PathCommand::MoveTo { x, y }
+        | PathCommand::LineTo { x, y }
+        | PathCommand::QuadTo { x, y, .. }
+        | PathCommand::ArcTo { x, y, .. } => {
+            *x = to_x;
+            *y = to_y;
+        }
+        PathCommand::CurveTo { x2, y2, x, y, .. } => {
+            *x2 += to_x - *x;
+            *y2 += to_y - *y;
+            *x = to_x;
+            *y = to_y;
+        }
+        PathCommand::ClosePath => {}
+    }
+}
+
+fn end_point(command: &PathCommand) -> Option<Point> {
+    command.end_point()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square() -> Vec<PathCommand> {
+        vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.0, y: 10.0 },
+            PathCommand::LineTo { x: 0.0, y: 10.0 },
+            PathCommand::ClosePath,
+        ]
+    }
+
+    #[test]
+    fn test_promote_to_cubic() {
+        let commands = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::QuadTo { x1: 15.0, y1: 30.0, x: 30.0, y: 0.0 },
+            PathCommand::ArcTo { rx: 10.0, ry: 10.0, rotation: 0.0, large_arc: false, sweep: true, x: 50.0, y: 0.0 },
+            PathCommand::LineTo { x: 50.0, y: 20.0 },
+        ];
+        let types = [AnchorType::Corner, AnchorType::Smooth, AnchorType::Corner, AnchorType::Corner];
+        let (promoted, promoted_types) = promote_to_cubic(&commands, &types);
+        assert_eq!(promoted[1], PathCommand::CurveTo { x1: 10.0, y1: 20.0, x2: 20.0, y2: 20.0, x: 30.0, y: 0.0 });
+        // The half circle becomes two quarters meeting at a new smooth anchor
+        assert_eq!(promoted.len(), 5);
+        assert_eq!(promoted_types, vec![AnchorType::Corner, AnchorType::Smooth, AnchorType::Smooth, AnchorType::Corner, AnchorType::Corner]);
+
+        // Without added anchors the types are kept as they are
+        let (promoted, promoted_types) = promote_to_cubic(&commands[..2], &[]);
+        assert!(matches!(promoted[1], PathCommand::CurveTo { .. }));
+        assert!(promoted_types.is_empty());
+        assert_eq!(promote_to_cubic(&square(), &[]).0, square());
+    }
+
+    #[test]
+    fn test_nearest_location_on_lines_and_close() {
+        let location = nearest_location(&square(), 4.0, -3.0).unwrap();
+        assert_eq!((location.command_index, location.x, location.y), (1, 4.0, 0.0));
+        assert!((location.distance - 3.0).abs() < 1e-12);
+        // The closing segment runs from (0, 10) back to (0, 0)
+        let closing = nearest_location(&square(), -1.0, 2.0).unwrap();
+        assert_eq!(closing.command_index, 4);
+        assert!((closing.t - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_split_cubic_halves_meet_on_curve() {
+        let (p0, p1, p2, p3) = ((0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0));
+        let (first, second) = split_cubic(p0, p1, p2, p3, 0.3);
+        let on_curve = (geometry::cubic_at(0.0, 0.0, 10.0, 10.0, 0.3), geometry::cubic_at(0.0, 10.0, 10.0, 0.0, 0.3));
+        assert_eq!(first[3], second[0]);
+        assert!((first[3].0 - on_curve.0).abs() < 1e-12 && (first[3].1 - on_curve.1).abs() < 1e-12);
+        // The first half traced at 0.5 is the original at 0.15
+        let x = geometry::cubic_at(first[0].0, first[1].0, first[2].0, first[3].0, 0.5);
+        assert!((x - geometry::cubic_at(0.0, 0.0, 10.0, 10.0, 0.15)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_nearest_location_on_curve() {
+        let arc = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::CurveTo { x1: 0.0, y1: 10.0, x2: 10.0, y2: 10.0, x: 10.0, y: 0.0 },
+        ];
+        // The curve is symmetric; its apex is at t = 0.5, (5, 7.5)
+        let location = nearest_location(&arc, 5.0, 20.0).unwrap();
+        assert!((location.t - 0.5).abs() < 1e-6);
+        assert!((location.y - 7.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_cut_closed_path_opens_it() {
+        let location = nearest_location(&square(), 10.0, 5.0).unwrap();
+        let Some(PathCut::Opened(commands)) = cut_at(&square(), &location) else {
+            panic!("expected an opened path");
+        };
+        assert_eq!(
+            commands,
+            vec![
+                PathCommand::MoveTo { x: 10.0, y: 5.0 },
+                PathCommand::LineTo { x: 10.0, y: 10.0 },
+                PathCommand::LineTo { x: 0.0, y: 10.0 },
+                PathCommand::LineTo { x: 0.0, y: 0.0 },
+                PathCommand::LineTo { x: 10.0, y: 0.0 },
+                PathCommand::LineTo { x: 10.0, y: 5.0 },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cut_open_path_in_two() {
+        let line = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.0, y: 10.0 },
+        ];
+        let location = nearest_location(&line, 10.0, 0.0).unwrap();
+        let Some(PathCut::Split(first, second)) = cut_at(&line, &location) else {
+            panic!("expected two parts");
+        };
+        assert_eq!(first, line[..2].to_vec());
+        assert_eq!(second, vec![PathCommand::MoveTo { x: 10.0, y: 0.0 }, PathCommand::LineTo { x: 10.0, y: 10.0 }]);
+        // Cutting at an end of an open path changes nothing
+        let end = nearest_location(&line, 20.0, 20.0).unwrap();
+        assert_eq!(cut_at(&line, &end), None);
+    }
+
+    #[test]
+    fn test_join_connects_nearest_ends() {
+        let a = vec![PathCommand::MoveTo { x: 0.0, y: 0.0 }, PathCommand::LineTo { x: 10.0, y: 0.0 }];
+        let b = vec![
+            PathCommand::MoveTo { x: 30.0, y: 0.0 },
+            PathCommand::CurveTo { x1: 30.0, y1: 5.0, x2: 20.0, y2: 5.0, x: 12.0, y: 0.0 },
+        ];
+        // b ends next to a's end, so it is traced backwards
+        assert_eq!(
+            join(&a, &b, false).unwrap(),
+            vec![
+                PathCommand::MoveTo { x: 0.0, y: 0.0 },
+                PathCommand::LineTo { x: 10.0, y: 0.0 },
+                PathCommand::LineTo { x: 12.0, y: 0.0 },
+                PathCommand::CurveTo { x1: 20.0, y1: 5.0, x2: 30.0, y2: 5.0, x: 30.0, y: 0.0 },
+            ]
+        );
+        // Averaging meets at (11, 0) and shifts the curve's handle with its anchor
+        assert_eq!(
+            join(&a, &b, true).unwrap(),
+            vec![
+                PathCommand::MoveTo { x: 0.0, y: 0.0 },
+                PathCommand::LineTo { x: 11.0, y: 0.0 },
+                PathCommand::CurveTo { x1: 19.0, y1: 5.0, x2: 30.0, y2: 5.0, x: 30.0, y: 0.0 },
+            ]
+        );
+        assert_eq!(join(&a, &square(), false), None);
+    }
+
+    #[test]
+    fn test_subdivide_keeps_shape() {
+        let arc = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::CurveTo { x1: 0.0, y1: 10.0, x2: 10.0, y2: 10.0, x: 10.0, y: 0.0 },
+        ];
+        let split = subdivide(&arc, 1, 0.5).unwrap();
+        assert_eq!(split.len(), 3);
+        assert_eq!(split[1], PathCommand::CurveTo { x1: 0.0, y1: 5.0, x2: 2.5, y2: 7.5, x: 5.0, y: 7.5 });
+        assert_eq!(split[2], PathCommand::CurveTo { x1: 7.5, y1: 7.5, x2: 10.0, y2: 5.0, x: 10.0, y: 0.0 });
+        assert_eq!(subdivide(&arc, 1, 1.0), None);
+
+        // The closing segment of the square is the fourth one
+        let command_index = segment_command_index(&square(), 3).unwrap();
+        let split = subdivide(&square(), command_index, 0.5).unwrap();
+        assert_eq!(split[4], PathCommand::LineTo { x: 0.0, y: 5.0 });
+        assert_eq!(split[5], PathCommand::ClosePath);
+        assert_eq!(anchor_index(&split, command_index), 4);
+    }
+
+    #[test]
+    fn test_smooth_anchor_aligns_handles() {
+        // A corner at (10, 0) between two curves with uneven, kinked handles
+        let path = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::CurveTo { x1: 0.0, y1: 5.0, x2: 10.0, y2: 4.0, x: 10.0, y: 0.0 },
+            PathCommand::CurveTo { x1: 12.0, y1: 0.0, x2: 20.0, y2: 5.0, x: 20.0, y: 0.0 },
+        ];
+        let (smooth, inserted) = smooth_anchor(&path, 1).unwrap();
+        assert_eq!(inserted, None);
+        let (PathCommand::CurveTo { x2, y2, .. }, PathCommand::CurveTo { x1, y1, .. }) = (&smooth[1], &smooth[2]) else {
+            panic!("expected curves");
+        };
+        // Mirrored: the anchor is the midpoint of its handles, which are 3 apart on average
+        assert!(((x1 + x2) / 2.0 - 10.0).abs() < 1e-12 && ((y1 + y2) / 2.0).abs() < 1e-12);
+        assert!(((x1 - 10.0).hypot(*y1) - 3.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_smooth_anchor_on_square_corner() {
+        // Smoothing the last corner makes the closing line explicit
+        let (smooth, inserted) = smooth_anchor(&square(), 3).unwrap();
+        assert_eq!(inserted, Some(4));
+        assert_eq!(smooth.len(), 6);
+        let (PathCommand::CurveTo { x2, y2, .. }, PathCommand::CurveTo { x1, y1, x, y, .. }) = (&smooth[3], &smooth[4]) else {
+            panic!("expected curves");
+        };
+        assert_eq!((*x, *y), (0.0, 0.0));
+        // The handles of (0, 10) are mirrored along the diagonal between its neighbours
+        assert!((x1 + x2).abs() < 1e-9 && (y1 + y2 - 20.0).abs() < 1e-9);
+        assert!((x1 - (y1 - 10.0)).abs() < 1e-9);
+        assert!((x1.hypot(y1 - 10.0) - 10.0 / 3.0).abs() < 1e-9);
+        assert_eq!(smooth_anchor(&[PathCommand::MoveTo { x: 0.0, y: 0.0 }], 0), None);
+    }
+
+    #[test]
+    fn test_anchor_handles_and_mirroring() {
+        let circle = geometry::object_to_path_commands(&crate::core::scene::VectorObject::Circle { cx: 0.0, cy: 0.0, r: 10.0 });
+        let anchors = anchor_handles(&circle);
+        assert_eq!(anchors.len(), 5);
+        // The start at 3 o'clock and its closing copy both show the handles of the two arcs meeting there
+        assert_eq!(anchors[0].handle_in, anchors[4].handle_in);
+        assert_eq!(anchors[0].handle_out, anchors[4].handle_out);
+        assert!(anchors[0].handle_in.is_some() && anchors[0].handle_out.is_some());
+
+        let mut commands = circle;
+        assert!(set_handle(&mut commands, 1, HandleSide::Out, (-10.0, 15.0), true));
+        let anchor = anchor_handles(&commands)[1];
+        assert_eq!(anchor.handle_out, Some((-10.0, 15.0)));
+        assert_eq!(anchor.handle_in, Some((10.0, 5.0)));
+
+        let mut square = square();
+        assert!(!set_handle(&mut square, 1, HandleSide::In, (5.0, 5.0), false));
+    }
+
+    #[test]
+    fn test_move_anchors_moves_handles_and_twins() {
+        let mut commands = geometry::object_to_path_commands(&crate::core::scene::VectorObject::Circle { cx: 0.0, cy: 0.0, r: 10.0 });
+        let before = anchor_handles(&commands);
+        move_anchors(&mut commands, &BTreeSet::from([0]), (5.0, 0.0));
+        let after = anchor_handles(&commands);
+        // The start and its closing copy move together with both of their handles
+        for index in [0, 4] {
+            assert_eq!((after[index].x, after[index].y), (15.0, 0.0));
+            assert_eq!(after[index].handle_in.unwrap().0, before[index].handle_in.unwrap().0 + 5.0);
+            assert_eq!(after[index].handle_out.unwrap().0, before[index].handle_out.unwrap().0 + 5.0);
+        }
+        assert_eq!(after[2], before[2]);
+
+        // Moving a whole side of a square
+        let mut square = square();
+        move_anchors(&mut square, &BTreeSet::from([1, 2]), (5.0, 0.0));
+        assert_eq!(square[1], PathCommand::LineTo { x: 15.0, y: 0.0 });
+        assert_eq!(square[2], PathCommand::LineTo { x: 15.0, y: 10.0 });
+        assert_eq!(square[3], PathCommand::LineTo { x: 0.0, y: 10.0 });
+    }
+
+    #[test]
+    fn test_close_last_subpath() {
+        let open = vec![
+            PathCommand::MoveTo { x: 0.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.0, y: 0.0 },
+            PathCommand::LineTo { x: 10.0, y: 10.0 },
+        ];
+        let straight = close_last_subpath(&open, false).unwrap();
+        assert_eq!(straight.last(), Some(&PathCommand::ClosePath));
+        assert_eq!(straight.len(), 4);
+
+        // The curve leaves (10, 10) heading down and arrives at (0, 0) heading right
+        let curved = close_last_subpath(&open, true).unwrap();
+        let reach = 200f64.sqrt() / 3.0;
+        assert_eq!(
+            curved[3],
+            PathCommand::CurveTo { x1: 10.0, y1: 10.0 + reach, x2: -reach, y2: 0.0, x: 0.0, y: 0.0 }
+        );
+        assert_eq!(close_last_subpath(&straight, false), None);
+    }
+}
@@ -0,0 +1,476 @@
+//! Path Ops Module - Boolean operations on filled outlines
+//!
+//! Operands are flattened to polygons (curves within `FLATTEN_TOLERANCE`) and
+//! every edge is split where it crosses or touches another edge. Each piece is
+//! then kept or dropped by testing which operands contain the points just to
+//! its left and right (nonzero fill rule): a piece is part of the result's
+//! boundary when the result covers exactly one of its two sides. The kept
+//! pieces are oriented with the result on their left and chained into loops,
+//! so outer boundaries and holes come out with opposite windings.
+
+use std::collections::HashMap;
+
+use crate::core::geometry;
+use crate::core::scene::PathCommand;
+
+/// Maximum distance between a curve and its flattened polygon
+pub const FLATTEN_TOLERANCE: f64 = 0.05;
+
+/// Closed polygon (the closing edge back to the first point is implicit)
+pub type Polygon = Vec<(f64, f64)>;
+
+/// Distance used to probe either side of an edge
+const SIDE_PROBE: f64 = 1e-6;
+
+/// Grid used to merge split points that should coincide
+const VERTEX_QUANTUM: f64 = 1e-7;
+
+/// Boolean operation between filled outlines
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BooleanOp {
+    /// Area covered by any operand
+    Union,
+    /// Area of the first operand not covered by any other
+    Subtract,
+    /// Area covered by all operands
+    Intersect,
+    /// Area covered by an odd number of operands
+    Exclude,
+}
+
+impl BooleanOp {
+    /// Parse an operation name: "union" | "subtract" | "intersect" | "exclude"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "union" => Some(BooleanOp::Union),
+            "subtract" => Some(BooleanOp::Subtract),
+            "intersect" => Some(BooleanOp::Intersect),
+            "exclude" => Some(BooleanOp::Exclude),
+            _ => None,
+        }
+    }
+
+    /// The name from_name parses
+    pub fn name(self) -> &'static str {
+        match self {
+            BooleanOp::Union => "union",
+            BooleanOp::Subtract => "subtract",
+            BooleanOp::Intersect => "intersect",
+            BooleanOp::Exclude => "exclude",
+        }
+    }
+
+    fn covers(self, inside: &[bool]) -> bool {
+        match self {
+            BooleanOp::Union => inside.iter().any(|&i| i),
+            BooleanOp::Subtract => inside.first().copied().unwrap_or(false) && !inside[1..].iter().any(|&i| i),
+            BooleanOp::Intersect => !inside.is_empty() && inside.iter().all(|&i| i),
+            BooleanOp::Exclude => inside.iter().filter(|&&i| i).count() % 2 == 1,
+        }
+    }
+}
+
+/// Flatten path commands into closed polygons, one per subpath
+/// Open subpaths are closed, as they are when filled.
+pub fn flatten(commands: &[PathCommand], tolerance: f64) -> Vec<Polygon> {
+    flatten_subpaths(commands, tolerance)
+        .into_iter()
+        .filter_map(|(polygon, _)| (polygon.len() >= 3).then_some(polygon))
+        .collect()
+}
+
+/// Flatten path commands into polylines, one per subpath with at least two points
+/// The flag tells whether the subpath ends with a ClosePath.
+pub fn flatten_subpaths(commands: &[PathCommand], tolerance: f64) -> Vec<(Polygon, bool)> {
+    let mut subpaths = Vec::new();
+    let mut current: Polygon = Vec::new();
+    let mut position = (0.0, 0.0);
+    let mut finish = |polyline: &mut Polygon, closed: bool| {
+        if polyline.len() >= 2 {
+            subpaths.push((std::mem::take(polyline), closed));
+        } else {
+            polyline.clear();
+        }
+    };
+    for cmd in geometry::to_cubic_commands(commands) {
+        match cmd {
+            PathCommand::MoveTo { x, y } => {
+                finish(&mut current, false);
+                current.push((x, y));
+                position = (x, y);
+            }
+            PathCommand::LineTo { x, y } => {
+                if current.is_empty() {
+                    current.push(position);
+                }
+                current.push((x, y));
+                position = (x, y);
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                if current.is_empty() {
+                    current.push(position);
+                }
+                let p0 = position;
+                // Second differences bound how far the curve strays from its chords
+                let dd = ((p0.0 - 2.0 * x1 + x2).hypot(p0.1 - 2.0 * y1 + y2))
+                    .max((x1 - 2.0 * x2 + x).hypot(y1 - 2.0 * y2 + y));
+                let steps = (0.75 * dd / tolerance.max(1e-9)).sqrt().ceil().clamp(1.0, 256.0) as usize;
+                for step in 1..=steps {
+                    let t = step as f64 / steps as f64;
+                    current.push((geometry::cubic_at(p0.0, x1, x2, x, t), geometry::cubic_at(p0.1, y1, y2, y, t)));
+                }
+                position = (x, y);
+            }
+            // Converted to cubics above
+            PathCommand::QuadTo { .. } | PathCommand::ArcTo { .. } => {}
+            PathCommand::ClosePath => {
+                if let Some(&first) = current.first() {
+                    position = first;
+                }
+                finish(&mut current, true);
+            }
+        }
+    }
+    finish(&mut current, false);
+    subpaths
+}
+
+/// Apply a boolean operation to operands given as polygon sets (in z-order, bottom first)
+pub fn boolean(op: BooleanOp, operands: &[Vec<Polygon>]) -> Vec<Polygon> {
+    let edges: Vec<Edge> = operands
+        .iter()
+        .flatten()
+        .flat_map(|polygon| (0..polygon.len()).map(move |i| (polygon[i], polygon[(i + 1) % polygon.len()])))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    // Split every edge at the points where other edges cross or touch it
+    let mut splits: Vec<Vec<f64>> = vec![vec![0.0, 1.0]; edges.len()];
+    for i in 0..edges.len() {
+        for j in (i + 1)..edges.len() {
+            for (t, u) in edge_intersections(edges[i], edges[j]) {
+                splits[i].push(t);
+                splits[j].push(u);
+            }
+        }
+    }
+
+    let mut kept = EdgeMap::new();
+    for (&(a, b), ts) in edges.iter().zip(splits.iter_mut()) {
+        ts.sort_by(|x, y| x.total_cmp(y));
+        for pair in ts.windows(2) {
+            let start = lerp(a, b, pair[0]);
+            let end = lerp(a, b, pair[1]);
+            let (start_key, end_key) = (key(start), key(end));
+            if start_key == end_key {
+                continue;
+            }
+            let (dx, dy) = (end.0 - start.0, end.1 - start.1);
+            let length = dx.hypot(dy);
+            let mid = ((start.0 + end.0) / 2.0, (start.1 + end.1) / 2.0);
+            // Left of the direction of travel (y down: left is (dy, -dx))
+            let (nx, ny) = (dy / length * SIDE_PROBE, -dx / length * SIDE_PROBE);
+            let left = op.covers(&inside_flags(operands, (mid.0 + nx, mid.1 + ny)));
+            let right = op.covers(&inside_flags(operands, (mid.0 - nx, mid.1 - ny)));
+            match (left, right) {
+                (true, false) => {
+                    kept.insert((start_key, end_key), (start, end));
+                }
+                (false, true) => {
+                    kept.insert((end_key, start_key), (end, start));
+                }
+                _ => {}
+            }
+        }
+    }
+    let loops = chain_edges(kept);
+    loops.into_iter().map(simplify).filter(|polygon| polygon.len() >= 3).collect()
+}
+
+/// Path commands for a set of polygons (one closed subpath each)
+pub fn polygons_to_commands(polygons: &[Polygon]) -> Vec<PathCommand> {
+    let mut commands = Vec::new();
+    for polygon in polygons {
+        let Some(&(x, y)) = polygon.first() else {
+            continue;
+        };
+        commands.push(PathCommand::MoveTo { x, y });
+        commands.extend(polygon[1..].iter().map(|&(x, y)| PathCommand::LineTo { x, y }));
+        commands.push(PathCommand::ClosePath);
+    }
+    commands
+}
+
+/// Parts of a polyline that lie outside a region (nonzero winding)
+/// Runs are split where the polyline enters the region; a polyline that never
+/// touches the region comes back as a single unchanged run.
+pub fn clip_polyline(points: &[(f64, f64)], region: &[Polygon]) -> Vec<Vec<(f64, f64)>> {
+    let region_edges: Vec<Edge> = region
+        .iter()
+        .flat_map(|polygon| (0..polygon.len()).map(move |i| (polygon[i], polygon[(i + 1) % polygon.len()])))
+        .filter(|(a, b)| a != b)
+        .collect();
+
+    let mut runs = Vec::new();
+    let mut current: Vec<Point> = Vec::new();
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        if a == b {
+            continue;
+        }
+        let mut ts = vec![0.0, 1.0];
+        ts.extend(region_edges.iter().flat_map(|&edge| edge_intersections((a, b), edge)).map(|(t, _)| t));
+        ts.sort_by(|x, y| x.total_cmp(y));
+        ts.dedup();
+        for interval in ts.windows(2) {
+            let (start, end) = (lerp(a, b, interval[0]), lerp(a, b, interval[1]));
+            if winding_number(region, lerp(a, b, (interval[0] + interval[1]) / 2.0)) != 0 {
+                if current.len() >= 2 {
+                    runs.push(std::mem::take(&mut current));
+                }
+                current.clear();
+                continue;
+            }
+            if current.is_empty() {
+                current.push(start);
+            }
+            current.push(end);
+        }
+    }
+    if current.len() >= 2 {
+        runs.push(current);
+    }
+    runs
+}
+
+type Point = (f64, f64);
+type Edge = (Point, Point);
+type Key = (i64, i64);
+/// Directed boundary edges keyed by their quantized end points
+type EdgeMap = HashMap<(Key, Key), Edge>;
+
+fn key(point: (f64, f64)) -> Key {
+    ((point.0 / VERTEX_QUANTUM).round() as i64, (point.1 / VERTEX_QUANTUM).round() as i64)
+}
+
+fn lerp(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+/// Parameters (t on the first edge, u on the second) where two edges cross, touch or overlap
+fn edge_intersections(e1: Edge, e2: Edge) -> Vec<(f64, f64)> {
+    let ((ax, ay), (bx, by)) = e1;
+    let ((cx, cy), (dx, dy)) = e2;
+    let r = (bx - ax, by - ay);
+    let s = (dx - cx, dy - cy);
+    let denominator = r.0 * s.1 - r.1 * s.0;
+    let (r_len2, s_len2) = (r.0 * r.0 + r.1 * r.1, s.0 * s.0 + s.1 * s.1);
+    let qp = (cx - ax, cy - ay);
+
+    if denominator.abs() > 1e-12 * (r_len2 * s_len2).sqrt() {
+        let t = (qp.0 * s.1 - qp.1 * s.0) / denominator;
+        let u = (qp.0 * r.1 - qp.1 * r.0) / denominator;
+        let eps = 1e-12;
+        if (-eps..=1.0 + eps).contains(&t) && (-eps..=1.0 + eps).contains(&u) {
+            return vec![(t.clamp(0.0, 1.0), u.clamp(0.0, 1.0))];
+        }
+        return Vec::new();
+    }
+
+    // Parallel: only collinear overlaps matter; split each edge at the other's end points
+    let offset = (qp.0 * r.1 - qp.1 * r.0).abs() / r_len2.sqrt();
+    if offset > VERTEX_QUANTUM {
+        return Vec::new();
+    }
+    let project_on_r = |(px, py): (f64, f64)| ((px - ax) * r.0 + (py - ay) * r.1) / r_len2;
+    let project_on_s = |(px, py): (f64, f64)| ((px - cx) * s.0 + (py - cy) * s.1) / s_len2;
+    let mut hits = Vec::new();
+    for point in [(cx, cy), (dx, dy)] {
+        let t = project_on_r(point);
+        if (0.0..=1.0).contains(&t) {
+            hits.push((t, project_on_s(point).clamp(0.0, 1.0)));
+        }
+    }
+    for point in [(ax, ay), (bx, by)] {
+        let u = project_on_s(point);
+        if (0.0..=1.0).contains(&u) {
+            hits.push((project_on_r(point).clamp(0.0, 1.0), u));
+        }
+    }
+    hits
+}
+
+/// Whether a point is inside each operand (nonzero winding)
+fn inside_flags(operands: &[Vec<Polygon>], point: (f64, f64)) -> Vec<bool> {
+    operands.iter().map(|polygons| winding_number(polygons, point) != 0).collect()
+}
+
+/// How many times the polygons wind around a point (counter-clockwise in y-up terms is positive)
+pub fn winding_number(polygons: &[Polygon], (px, py): (f64, f64)) -> i32 {
+    let mut winding = 0;
+    for polygon in polygons {
+        for i in 0..polygon.len() {
+            let (ax, ay) = polygon[i];
+            let (bx, by) = polygon[(i + 1) % polygon.len()];
+            let side = (bx - ax) * (py - ay) - (px - ax) * (by - ay);
+            if ay <= py && by > py && side > 0.0 {
+                winding += 1;
+            } else if ay > py && by <= py && side < 0.0 {
+                winding -= 1;
+            }
+        }
+    }
+    winding
+}
+
+/// Join directed edges into closed loops
+/// Every vertex of a region boundary has as many outgoing as incoming edges,
+/// so following unused outgoing edges always returns to the starting vertex.
+fn chain_edges(edges: EdgeMap) -> Vec<Polygon> {
+    let mut outgoing: HashMap<Key, Vec<(Key, Point)>> = HashMap::new();
+    let mut ordered: Vec<_> = edges.into_iter().collect();
+    // HashMap order is random; sort so results are reproducible
+    ordered.sort_by_key(|(keys, _)| *keys);
+    for ((from, to), (start, _)) in ordered {
+        outgoing.entry(from).or_default().push((to, start));
+    }
+
+    let mut starts: Vec<Key> = outgoing.keys().copied().collect();
+    starts.sort();
+    let mut loops = Vec::new();
+    for start in starts {
+        while let Some((mut next, point)) = outgoing.get_mut(&start).and_then(Vec::pop) {
+            let mut polygon = vec![point];
+            while next != start {
+                let Some((after, point)) = outgoing.get_mut(&next).and_then(Vec::pop) else {
+                    break; // Broken chain (should not happen); drop the partial loop
+                };
+                polygon.push(point);
+                next = after;
+            }
+            if next == start {
+                loops.push(polygon);
+            }
+        }
+    }
+    loops
+}
+
+/// Remove vertices that lie on a straight line between their neighbours
+fn simplify(polygon: Polygon) -> Polygon {
+    let mut points = polygon;
+    let mut changed = true;
+    while changed && points.len() >= 3 {
+        changed = false;
+        let mut i = 0;
+        while i < points.len() && points.len() >= 3 {
+            let prev = points[(i + points.len() - 1) % points.len()];
+            let here = points[i];
+            let next = points[(i + 1) % points.len()];
+            let (ax, ay) = (here.0 - prev.0, here.1 - prev.1);
+            let (bx, by) = (next.0 - here.0, next.1 - here.1);
+            let cross = ax * by - ay * bx;
+            if cross.abs() <= 1e-9 * ax.hypot(ay) * bx.hypot(by) && ax * bx + ay * by > 0.0 {
+                points.remove(i);
+                changed = true;
+            } else {
+                i += 1;
+            }
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x: f64, y: f64, size: f64) -> Vec<Polygon> {
+        vec![vec![(x, y), (x + size, y), (x + size, y + size), (x, y + size)]]
+    }
+
+    /// Signed area (positive for loops with the region on their left, y down)
+    fn area(polygons: &[Polygon]) -> f64 {
+        polygons
+            .iter()
+            .map(|p| {
+                (0..p.len())
+                    .map(|i| {
+                        let (a, b) = (p[i], p[(i + 1) % p.len()]);
+                        a.1 * b.0 - a.0 * b.1
+                    })
+                    .sum::<f64>()
+                    / 2.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_overlapping_squares() {
+        let a = square(0.0, 0.0, 10.0);
+        let b = square(5.0, 5.0, 10.0);
+        let operands = [a, b];
+        assert!((area(&boolean(BooleanOp::Union, &operands)) - 175.0).abs() < 1e-9);
+        assert!((area(&boolean(BooleanOp::Intersect, &operands)) - 25.0).abs() < 1e-9);
+        assert!((area(&boolean(BooleanOp::Subtract, &operands)) - 75.0).abs() < 1e-9);
+        assert!((area(&boolean(BooleanOp::Exclude, &operands)) - 150.0).abs() < 1e-9);
+
+        // Collinear vertices are merged: the union is a single 8-corner outline
+        let union = boolean(BooleanOp::Union, &operands);
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].len(), 8);
+    }
+
+    #[test]
+    fn test_shared_edges_merge() {
+        let operands = [square(0.0, 0.0, 10.0), square(10.0, 0.0, 10.0)];
+        let union = boolean(BooleanOp::Union, &operands);
+        assert_eq!(union.len(), 1);
+        assert_eq!(union[0].len(), 4);
+        assert!((area(&union) - 200.0).abs() < 1e-9);
+        assert!(boolean(BooleanOp::Intersect, &operands).is_empty());
+    }
+
+    #[test]
+    fn test_subtract_inner_square_leaves_hole() {
+        let operands = [square(0.0, 0.0, 30.0), square(10.0, 10.0, 10.0)];
+        let result = boolean(BooleanOp::Subtract, &operands);
+        assert_eq!(result.len(), 2);
+        // Outer loop and hole have opposite orientation
+        assert!((area(&result) - 800.0).abs() < 1e-9);
+        assert_eq!(winding_number(&result, (15.0, 15.0)), 0);
+        assert_ne!(winding_number(&result, (5.0, 5.0)), 0);
+    }
+
+    #[test]
+    fn test_flatten_circle_within_tolerance() {
+        let commands = geometry::object_to_path_commands(&crate::core::scene::VectorObject::Circle { cx: 0.0, cy: 0.0, r: 100.0 });
+        let polygons = flatten(&commands, FLATTEN_TOLERANCE);
+        assert_eq!(polygons.len(), 1);
+        for &(x, y) in &polygons[0] {
+            assert!((x.hypot(y) - 100.0).abs() < FLATTEN_TOLERANCE);
+        }
+        // The area is close to πr² (the bezier circle itself is slightly larger)
+        let expected = std::f64::consts::PI * 100.0 * 100.0;
+        assert!((area(&polygons).abs() - expected).abs() / expected < 1e-3);
+    }
+
+    #[test]
+    fn test_clip_polyline() {
+        let region = square(4.0, -1.0, 2.0);
+        let runs = clip_polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 5.0)], &region);
+        assert_eq!(runs, vec![vec![(0.0, 0.0), (4.0, 0.0)], vec![(6.0, 0.0), (10.0, 0.0), (10.0, 5.0)]]);
+
+        let untouched = [(0.0, 5.0), (10.0, 5.0)];
+        assert_eq!(clip_polyline(&untouched, &region), vec![untouched.to_vec()]);
+        assert!(clip_polyline(&[(4.5, 0.0), (5.5, 0.0)], &region).is_empty());
+    }
+
+    #[test]
+    fn test_disjoint_operands() {
+        let operands = [square(0.0, 0.0, 10.0), square(20.0, 0.0, 10.0)];
+        assert_eq!(boolean(BooleanOp::Union, &operands).len(), 2);
+        assert!(boolean(BooleanOp::Intersect, &operands).is_empty());
+        assert_eq!(boolean(BooleanOp::Subtract, &operands).len(), 1);
+    }
+}
@@ -0,0 +1,176 @@
+//! Pattern Module - Repeating tile fills
+//!
+//! A pattern fill repeats a tile across an object's fill area. The tile shows
+//! either a symbol from the document's library (a small scene fragment) or an
+//! image. Tiles live in the object's local space: the cell is the tile content
+//! plus spacing, then scaled and rotated around the local origin, the same way
+//! in the canvas renderer, SVG and PNG export. Objects keep their fill color as
+//! the fallback where a tile cannot be drawn (PDF export, image tiles in PNG export).
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{ObjectId, SceneGraph, SceneNode};
+
+/// What a pattern tile shows
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PatternSource {
+    /// A symbol from the document's library, drawn from the top-left of its bounds
+    Symbol { symbol_id: ObjectId },
+    /// An image (URL or data URL) drawn at width x height tile units
+    Image { href: String, width: f64, height: f64 },
+}
+
+/// Repeating fill of an object
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct PatternFill {
+    pub source: PatternSource,
+    /// Gap between neighbouring tiles, horizontally and vertically, in tile units
+    #[serde(default)]
+    pub spacing: [f64; 2],
+    /// Size of a tile unit in the object's local units
+    #[serde(default = "unit_scale")]
+    pub scale: f64,
+    /// Rotation of the tile grid in degrees, clockwise on screen
+    #[serde(default)]
+    pub rotation: f64,
+}
+
+fn unit_scale() -> f64 {
+    1.0
+}
+
+/// A pattern resolved against the scene
+#[derive(Debug, Clone)]
+pub struct PatternTile<'a> {
+    /// Repeat cell size in tile units (content plus spacing)
+    pub width: f64,
+    pub height: f64,
+    /// Tile units to the object's local space
+    pub transform: TransformMatrix,
+    pub content: TileContent<'a>,
+}
+
+/// What is drawn at the top-left of each cell
+#[derive(Debug, Clone, Copy)]
+pub enum TileContent<'a> {
+    /// Symbol children, moved so `origin` (their bounds' top-left) is at the cell's corner
+    Nodes { nodes: &'a [SceneNode], origin: (f64, f64) },
+    Image { href: &'a str, width: f64, height: f64 },
+}
+
+impl PatternFill {
+    /// Whether every number is finite and the tile has a positive size
+    pub fn is_valid(&self) -> bool {
+        let [gap_x, gap_y] = self.spacing;
+        let sized = match &self.source {
+            PatternSource::Symbol { .. } => true,
+            PatternSource::Image { width, height, .. } => {
+                width.is_finite() && height.is_finite() && *width > 0.0 && *height > 0.0
+            }
+        };
+        sized
+            && [gap_x, gap_y, self.scale, self.rotation].iter().all(|value| value.is_finite())
+            && gap_x >= 0.0
+            && gap_y >= 0.0
+            && self.scale > 0.0
+    }
+
+    /// Resolve the tile: None for a missing or empty symbol or invalid numbers
+    pub fn tile<'a>(&'a self, scene: &'a SceneGraph) -> Option<PatternTile<'a>> {
+        if !self.is_valid() {
+            return None;
+        }
+        let (content, size) = match &self.source {
+            PatternSource::Symbol { symbol_id } => {
+                let symbol = scene.get_symbol(symbol_id)?;
+                let bounds = geometry::nodes_world_bounds(scene, &symbol.children, &TransformMatrix::identity())?;
+                let origin = (bounds.min_x, bounds.min_y);
+                (TileContent::Nodes { nodes: &symbol.children, origin }, (bounds.width(), bounds.height()))
+            }
+            PatternSource::Image { href, width, height } => {
+                (TileContent::Image { href, width: *width, height: *height }, (*width, *height))
+            }
+        };
+        let (width, height) = (size.0 + self.spacing[0], size.1 + self.spacing[1]);
+        if width <= 0.0 || height <= 0.0 {
+            return None;
+        }
+        // TransformMatrix::rotate turns counterclockwise on screen
+        let rotation = TransformMatrix::rotate(-self.rotation.to_radians());
+        let transform = rotation.multiply(&TransformMatrix::scale(self.scale, self.scale));
+        Some(PatternTile { width, height, transform, content })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::{ObjectStyle, Symbol, VectorObject};
+
+    fn scene_with_symbol() -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        scene.symbols.push(Symbol {
+            id: "sym_1".to_string(),
+            name: "Dot".to_string(),
+            children: vec![SceneNode::Leaf {
+                id: "dot".to_string(),
+                object: VectorObject::Circle { cx: 10.0, cy: 10.0, r: 4.0 },
+                transform: TransformMatrix::identity(),
+                style: ObjectStyle::default(),
+                name: None,
+            }],
+        });
+        scene
+    }
+
+    #[test]
+    fn test_symbol_tile_uses_bounds_plus_spacing() {
+        let scene = scene_with_symbol();
+        let pattern = PatternFill {
+            source: PatternSource::Symbol { symbol_id: "sym_1".to_string() },
+            spacing: [2.0, 4.0],
+            scale: 2.0,
+            rotation: 90.0,
+        };
+        let tile = pattern.tile(&scene).expect("tile");
+        assert_eq!((tile.width, tile.height), (10.0, 12.0));
+        assert!(matches!(tile.content, TileContent::Nodes { origin: (6.0, 6.0), .. }));
+        // One tile unit to the right ends up two units down
+        let (x, y) = tile.transform.transform_point(1.0, 0.0);
+        assert!(x.abs() < 1e-9 && (y - 2.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_invalid_patterns_do_not_resolve() {
+        let scene = scene_with_symbol();
+        let missing = PatternFill {
+            source: PatternSource::Symbol { symbol_id: "sym_9".to_string() },
+            spacing: [0.0, 0.0],
+            scale: 1.0,
+            rotation: 0.0,
+        };
+        assert!(missing.tile(&scene).is_none());
+        let image = PatternFill {
+            source: PatternSource::Image { href: "tile.png".to_string(), width: 8.0, height: 8.0 },
+            ..missing.clone()
+        };
+        assert!(image.tile(&scene).is_some());
+        assert!(!PatternFill { scale: 0.0, ..image.clone() }.is_valid());
+        assert!(!PatternFill { spacing: [-1.0, 0.0], ..image.clone() }.is_valid());
+        let empty = PatternSource::Image { href: String::new(), width: 0.0, height: 8.0 };
+        assert!(!PatternFill { source: empty, ..image }.is_valid());
+    }
+
+    #[test]
+    fn test_pattern_json_defaults() {
+        let pattern: PatternFill =
+            serde_json::from_str(r#"{"source":{"kind":"symbol","symbol_id":"sym_1"}}"#).expect("valid pattern");
+        assert_eq!(pattern.spacing, [0.0, 0.0]);
+        assert_eq!(pattern.scale, 1.0);
+        assert_eq!(pattern.rotation, 0.0);
+    }
+}
@@ -0,0 +1,319 @@
+//! PDF Module - Vector PDF export
+//!
+//! Writes a single-page PDF whose content stream mirrors the scene graph:
+//! groups and instances become nested `q ... Q` blocks with their transform,
+//! leaves become filled and/or stroked paths. Document units are CSS pixels
+//! (1/96 inch), so a shape keeps its physical size whatever the page size.
+
+use crate::color::parse_hex_color;
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{FillRule, LineCap, LineJoin, ObjectStyle, PathCommand, SceneGraph, SceneNode, VectorObject};
+use crate::renderer::{format_number, ExportOptions};
+use crate::stroke;
+
+/// PDF points (1/72 inch) per millimetre
+pub const POINTS_PER_MM: f64 = 72.0 / 25.4;
+
+/// PDF points per document unit (CSS pixel, 1/96 inch)
+pub const POINTS_PER_UNIT: f64 = 0.75;
+
+/// Largest page side allowed by common PDF readers, in points
+const MAX_PAGE_POINTS: f64 = 14400.0;
+
+/// Canvas 2D default miter limit, so joins look the same as on screen
+const MITER_LIMIT: f64 = 10.0;
+
+/// Generate a PDF document for a page of width_mm x height_mm
+/// The page's top-left corner is the document origin. There is no background,
+/// so the drawing prints on white paper. Returns None for an invalid page size.
+pub fn generate_pdf(scene: &SceneGraph, width_mm: f64, height_mm: f64, options: &ExportOptions) -> Option<String> {
+    let (width, height) = (width_mm * POINTS_PER_MM, height_mm * POINTS_PER_MM);
+    let valid = |side: f64| side.is_finite() && side > 0.0 && side <= MAX_PAGE_POINTS;
+    if !valid(width) || !valid(height) {
+        return None;
+    }
+    let n = |value: f64| format_number(value, options.precision);
+
+    // Flip to the document's y-down coordinates and scale units to points
+    let mut content = format!("{} 0 0 {} 0 {} cm\n", n(POINTS_PER_UNIT), n(-POINTS_PER_UNIT), n(height));
+    let mut opacities = Vec::new();
+    for root in &scene.roots {
+        write_node(&mut content, &mut opacities, scene, root, &n);
+    }
+
+    // Translucent objects refer to one graphics state per distinct opacity
+    let resources = if opacities.is_empty() {
+        String::new()
+    } else {
+        let states: Vec<String> = opacities
+            .iter()
+            .enumerate()
+            .map(|(index, opacity)| format!("/GS{} << /ca {} /CA {} >>", index, opacity, opacity))
+            .collect();
+        format!(" /Resources << /ExtGState << {} >> >>", states.join(" "))
+    };
+
+    let objects = [
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        "<< /Type /Pages /Kids [3 0 R] /Count 1 >>".to_string(),
+        format!("<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}]{} /Contents 4 0 R >>", n(width), n(height), resources),
+        format!("<< /Length {} >>\nstream\n{}endstream", content.len(), content),
+    ];
+
+    let mut pdf = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.push_str(&format!("{} 0 obj\n{}\nendobj\n", index + 1, object));
+    }
+
+    // Cross-reference table: fixed 20-byte entries with each object's byte offset
+    let xref = pdf.len();
+    pdf.push_str(&format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1));
+    for offset in offsets {
+        pdf.push_str(&format!("{:010} 00000 n \n", offset));
+    }
+    pdf.push_str(&format!(
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    ));
+    Some(pdf)
+}
+
+fn write_node(content: &mut String, opacities: &mut Vec<String>, scene: &SceneGraph, node: &SceneNode, n: &dyn Fn(f64) -> String) {
+    let children = match node {
+        SceneNode::Leaf { object, transform, style, .. } => {
+            write_leaf(content, opacities, object, transform, style, n);
+            return;
+        }
+        SceneNode::Group { children, .. } => children.as_slice(),
+        SceneNode::Instance { symbol_id, .. } => match scene.get_symbol(symbol_id) {
+            Some(symbol) => symbol.children.as_slice(),
+            None => return,
+        },
+    };
+    content.push_str("q\n");
+    push_transform(content, node.transform(), n);
+    for child in children {
+        write_node(content, opacities, scene, child, n);
+    }
+    content.push_str("Q\n");
+}
+
+fn write_leaf(
+    content: &mut String,
+    opacities: &mut Vec<String>,
+    object: &VectorObject,
+    transform: &TransformMatrix,
+    style: &ObjectStyle,
+    n: &dyn Fn(f64) -> String,
+) {
+    let fill = style.fill_color.as_deref().and_then(parse_hex_color);
+    let stroke = style.stroke_color.as_deref().and_then(parse_hex_color).filter(|_| style.stroke_width > 0.0);
+    // Inside and outside strokes are painted as filled outlines after the fill
+    let aligned_stroke = stroke.and_then(|rgb| Some((rgb, stroke::aligned_stroke_outline(object, style)?)));
+    let stroke = stroke.filter(|_| aligned_stroke.is_none());
+    let paint = match (fill, stroke, style.fill_rule) {
+        (Some(_), Some(_), FillRule::NonZero) => "B",
+        (Some(_), Some(_), FillRule::EvenOdd) => "B*",
+        (Some(_), None, FillRule::NonZero) => "f",
+        (Some(_), None, FillRule::EvenOdd) => "f*",
+        (None, Some(_), _) => "S",
+        (None, None, _) if aligned_stroke.is_some() => "n",
+        (None, None, _) => return,
+    };
+
+    content.push_str("q\n");
+    push_transform(content, transform, n);
+    if style.opacity < 1.0 {
+        let opacity = n(style.opacity.max(0.0));
+        let index = opacities.iter().position(|existing| *existing == opacity).unwrap_or(opacities.len());
+        if index == opacities.len() {
+            opacities.push(opacity);
+        }
+        content.push_str(&format!("/GS{} gs\n", index));
+    }
+    if let Some([r, g, b]) = fill {
+        content.push_str(&format!("{} {} {} rg\n", n(r), n(g), n(b)));
+    }
+    if let Some([r, g, b]) = stroke {
+        let cap = match style.line_cap {
+            LineCap::Butt => 0,
+            LineCap::Round => 1,
+            LineCap::Square => 2,
+        };
+        let join = match style.line_join {
+            LineJoin::Miter => 0,
+            LineJoin::Round => 1,
+            LineJoin::Bevel => 2,
+        };
+        content.push_str(&format!(
+            "{} {} {} RG\n{} w\n{} J\n{} j\n{} M\n",
+            n(r), n(g), n(b), n(style.stroke_width), cap, join, n(MITER_LIMIT)
+        ));
+    }
+
+    // Open paths stay open, as on the canvas
+    let is_closed = !matches!(object, VectorObject::Path { is_closed: false, .. });
+    for command in geometry::to_cubic_commands(&geometry::object_to_path_commands(object)) {
+        match command {
+            PathCommand::MoveTo { x, y } => content.push_str(&format!("{} {} m\n", n(x), n(y))),
+            PathCommand::LineTo { x, y } => content.push_str(&format!("{} {} l\n", n(x), n(y))),
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => content.push_str(&format!(
+                "{} {} {} {} {} {} c\n",
+                n(x1), n(y1), n(x2), n(y2), n(x), n(y)
+            )),
+            PathCommand::ClosePath if is_closed => content.push_str("h\n"),
+            _ => {}
+        }
+    }
+    content.push_str(paint);
+    content.push('\n');
+    if let Some(([r, g, b], outline)) = aligned_stroke {
+        content.push_str(&format!("{} {} {} rg\n", n(r), n(g), n(b)));
+        for polygon in &outline {
+            for (i, &(x, y)) in polygon.iter().enumerate() {
+                content.push_str(&format!("{} {} {}\n", n(x), n(y), if i == 0 { "m" } else { "l" }));
+            }
+            content.push_str("h\n");
+        }
+        content.push_str("f\n");
+    }
+    content.push_str("Q\n");
+}
+
+fn push_transform(content: &mut String, transform: &TransformMatrix, n: &dyn Fn(f64) -> String) {
+    // PDF matrices are [a b c d e f] with x' = a x + c y + e, like the canvas order
+    content.push_str(&format!(
+        "{} {} {} {} {} {} cm\n",
+        n(transform.a), n(transform.c), n(transform.b), n(transform.d), n(transform.tx), n(transform.ty)
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::Symbol;
+
+    fn stream(pdf: &str) -> &str {
+        let start = pdf.find("stream\n").map_or(0, |i| i + "stream\n".len());
+        let end = pdf.find("endstream").unwrap_or(pdf.len());
+        &pdf[start..end]
+    }
+
+    #[test]
+    fn test_pdf_structure_and_cross_references() {
+        let pdf = generate_pdf(&SceneGraph::new(), 210.0, 297.0, &ExportOptions { precision: Some(2) }).expect("pdf");
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // A4 in points
+        assert!(pdf.contains("/MediaBox [0 0 595.28 841.89]"));
+
+        // Every xref entry points at its object header
+        let xref = pdf.find("xref\n").expect("xref");
+        for (index, line) in pdf[xref..].lines().skip(3).take(4).enumerate() {
+            let offset: usize = line[..10].parse().expect("offset");
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", index + 1)), "object {}", index + 1);
+        }
+        let startxref = pdf.rsplit("startxref\n").next().and_then(|tail| tail.lines().next());
+        assert_eq!(startxref, Some(xref.to_string().as_str()));
+
+        // The stream length matches the content
+        let content = stream(&pdf);
+        assert!(pdf.contains(&format!("/Length {}", content.len())));
+
+        assert!(generate_pdf(&SceneGraph::new(), 0.0, 297.0, &ExportOptions::default()).is_none());
+        assert!(generate_pdf(&SceneGraph::new(), f64::NAN, 297.0, &ExportOptions::default()).is_none());
+    }
+
+    #[test]
+    fn test_pdf_paints_styled_paths() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Rectangle { x: 10.0, y: 20.0, width: 30.0, height: 40.0 },
+            TransformMatrix::translate(5.0, 0.0),
+        );
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Path {
+                commands: vec![PathCommand::MoveTo { x: 0.0, y: 0.0 }, PathCommand::QuadTo { x1: 5.0, y1: 10.0, x: 10.0, y: 0.0 }],
+                is_closed: false,
+                anchor_types: Vec::new(),
+            },
+            TransformMatrix::identity(),
+        );
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.get_mut(0) {
+            style.fill_color = Some("#ff0000".to_string());
+            style.stroke_color = Some("#0000ff".to_string());
+            style.fill_rule = FillRule::EvenOdd;
+            style.line_join = LineJoin::Round;
+        }
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.get_mut(1) {
+            style.fill_color = None;
+            style.stroke_color = Some("#00ff00".to_string());
+            style.stroke_width = 3.0;
+        }
+
+        let pdf = generate_pdf(&scene, 100.0, 100.0, &ExportOptions::default()).expect("pdf");
+        let content = stream(&pdf);
+        assert!(content.contains("1 0 0 1 5 0 cm\n1 0 0 rg\n0 0 1 RG\n2 w\n0 J\n1 j\n10 M\n"));
+        assert!(content.contains("10 20 m\n40 20 l\n40 60 l\n10 60 l\nh\nB*\nQ\n"));
+        // The quadratic is written as its cubic and the open path is not closed
+        assert!(content.contains("0 1 0 RG\n3 w\n"));
+        assert!(content.contains("c\nS\nQ\n"));
+        assert!(!content.contains("h\nS"));
+    }
+
+    #[test]
+    fn test_pdf_opacity_uses_graphics_states() {
+        let mut scene = SceneGraph::new();
+        for _ in 0..3 {
+            let id = scene.generate_id();
+            scene.add_object(id, VectorObject::Circle { cx: 0.0, cy: 0.0, r: 5.0 }, TransformMatrix::identity());
+        }
+        for (node, opacity) in scene.roots.iter_mut().zip([0.5, 1.0, 0.5]) {
+            if let SceneNode::Leaf { style, .. } = node {
+                style.opacity = opacity;
+            }
+        }
+
+        let pdf = generate_pdf(&scene, 50.0, 50.0, &ExportOptions::default()).expect("pdf");
+        assert!(pdf.contains("/Resources << /ExtGState << /GS0 << /ca 0.5 /CA 0.5 >> >> >>"));
+        assert_eq!(stream(&pdf).matches("/GS0 gs\n").count(), 2);
+    }
+
+    #[test]
+    fn test_pdf_nests_groups_and_instances() {
+        let mut scene = SceneGraph::new();
+        let leaf = SceneNode::Leaf {
+            id: "dot".to_string(),
+            object: VectorObject::Circle { cx: 0.0, cy: 0.0, r: 5.0 },
+            transform: TransformMatrix::identity(),
+            style: ObjectStyle::default(),
+            name: None,
+        };
+        scene.symbols.push(Symbol { id: "sym".to_string(), name: "Dot".to_string(), children: vec![leaf] });
+        scene.roots.push(SceneNode::Group {
+            id: "group".to_string(),
+            children: vec![SceneNode::Instance {
+                id: "inst".to_string(),
+                symbol_id: "sym".to_string(),
+                transform: TransformMatrix::translate(0.0, 7.0),
+                name: None,
+            }],
+            transform: TransformMatrix::scale(2.0, 2.0),
+            name: None,
+            cache_as_bitmap: false,
+        });
+
+        let pdf = generate_pdf(&scene, 50.0, 50.0, &ExportOptions::default()).expect("pdf");
+        let content = stream(&pdf);
+        assert!(content.contains("q\n2 0 0 2 0 0 cm\nq\n1 0 0 1 0 7 cm\nq\n1 0 0 1 0 0 cm\n"));
+        assert_eq!(content.matches("q\n").count(), content.matches("Q\n").count());
+    }
+}
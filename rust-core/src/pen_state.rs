@@ -2,12 +2,16 @@
 //!
 //! Manages the state of the pen tool for drawing bezier paths.
 
+use serde::Serialize;
+use tsify::Tsify;
+
 use crate::core::scene::PathCommand;
 
 /// Pen tool state
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub enum PenState {
     /// Not currently drawing
+    #[default]
     Idle,
     /// Actively drawing a path
     Drawing {
@@ -26,10 +30,18 @@ pub enum PenState {
     },
 }
 
-impl Default for PenState {
-    fn default() -> Self {
-        PenState::Idle
-    }
+/// What the frontend draws of the path being drawn
+#[derive(Debug, Clone, PartialEq, Serialize, Tsify)]
+pub struct PenPreview {
+    /// Path commands built so far
+    pub commands: Vec<PathCommand>,
+    pub last_anchor: (f64, f64),
+    pub drag_start_anchor: Option<(f64, f64)>,
+    /// Mouse position while dragging out a curve
+    pub handle: Option<(f64, f64)>,
+    pub is_dragging: bool,
+    /// Curve the drag would add (a CurveTo from last_anchor)
+    pub preview_curve: Option<PathCommand>,
 }
 
 impl PenState {
@@ -37,6 +49,40 @@ impl PenState {
         PenState::Idle
     }
 
+    /// Preview of the path being drawn, None when idle
+    pub fn preview(&self) -> Option<PenPreview> {
+        let PenState::Drawing { commands, drag_handle, drag_start_anchor, last_anchor, is_dragging, .. } = self else {
+            return None;
+        };
+        // If dragging, calculate a preview curve
+        // - Start: last_anchor (previous confirmed point)
+        // - End: drag_start_anchor (where user clicked - FIXED!)
+        // - CP2: drag_handle (mouse position - creates curvature!)
+        // - CP1: same as start point (Corner Point - straight exit from start)
+        let preview_curve = match (*is_dragging, drag_start_anchor, drag_handle) {
+            // Curve from last_anchor to drag_start_anchor (fixed endpoint)
+            // CP1 = start point (straight exit, no handle at start = C-curve)
+            // CP2 = mouse position (controls the curve toward the end)
+            (true, Some((end_x, end_y)), Some((cp2x, cp2y))) => Some(PathCommand::CurveTo {
+                x1: last_anchor.0,
+                y1: last_anchor.1,
+                x2: *cp2x,
+                y2: *cp2y,
+                x: *end_x,
+                y: *end_y,
+            }),
+            _ => None,
+        };
+        Some(PenPreview {
+            commands: commands.clone(),
+            last_anchor: *last_anchor,
+            drag_start_anchor: *drag_start_anchor,
+            handle: *drag_handle,
+            is_dragging: *is_dragging,
+            preview_curve,
+        })
+    }
+
     /// Check if we're currently drawing
     pub fn is_drawing(&self) -> bool {
         matches!(self, PenState::Drawing { .. })
@@ -58,6 +104,38 @@ impl PenState {
         }
     }
 
+    /// Anchor points of the in-progress path (empty when idle)
+    pub fn anchor_points(&self) -> Vec<(f64, f64)> {
+        let commands = match self {
+            PenState::Drawing { commands, .. } => commands,
+            PenState::Idle => return Vec::new(),
+        };
+        commands
+            .iter()
+            .filter_map(|cmd| match cmd {
+                PathCommand::MoveTo { x, y }
+                | PathCommand::LineTo { x, y }
+                | PathCommand::CurveTo { x, y, .. }
+                | PathCommand::QuadTo { x, y, .. }
+                | PathCommand::ArcTo { x, y, .. } => Some((*x, *y)),
+                PathCommand::ClosePath => None,
+            })
+            .collect()
+    }
+
+    /// Snap a point to the nearest earlier anchor of the in-progress path within `threshold`
+    /// The last anchor is excluded (snapping to it would add a zero-length segment).
+    pub fn snap_to_anchor(&self, x: f64, y: f64, threshold: f64) -> Option<(f64, f64)> {
+        let anchors = self.anchor_points();
+        let earlier = &anchors[..anchors.len().saturating_sub(1)];
+        earlier
+            .iter()
+            .map(|&(ax, ay)| ((ax, ay), ((x - ax).powi(2) + (y - ay).powi(2)).sqrt()))
+            .filter(|(_, distance)| *distance < threshold)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(anchor, _)| anchor)
+    }
+
     /// Check if a point is near the start point (for closing path)
     pub fn is_near_start(&self, x: f64, y: f64, threshold: f64) -> bool {
         match self {
@@ -102,4 +180,25 @@ mod tests {
         assert!(state.is_near_start(105.0, 100.0, 10.0));
         assert!(!state.is_near_start(200.0, 200.0, 10.0));
     }
+
+    #[test]
+    fn test_snap_to_earlier_anchor() {
+        let state = PenState::Drawing {
+            commands: vec![
+                PathCommand::MoveTo { x: 0.0, y: 0.0 },
+                PathCommand::LineTo { x: 100.0, y: 0.0 },
+                PathCommand::CurveTo { x1: 100.0, y1: 0.0, x2: 120.0, y2: 50.0, x: 100.0, y: 100.0 },
+            ],
+            start_point: (0.0, 0.0),
+            last_anchor: (100.0, 100.0),
+            drag_start_anchor: None,
+            drag_handle: None,
+            is_dragging: false,
+        };
+
+        assert_eq!(state.snap_to_anchor(103.0, 2.0, 8.0), Some((100.0, 0.0)));
+        // The last anchor is not a snap target
+        assert_eq!(state.snap_to_anchor(101.0, 99.0, 8.0), None);
+        assert_eq!(state.snap_to_anchor(50.0, 50.0, 8.0), None);
+    }
 }
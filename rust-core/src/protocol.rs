@@ -0,0 +1,1230 @@
+//! Protocol Module - Versioned command/response messages for driving an editor
+//!
+//! Every editor operation has a serde-serializable `Command`, so a batch can be
+//! sent as JSON through `Editor::execute_ops` in the browser, or over a socket
+//! by a native headless build (automation, render farms, integration tests).
+//!
+//! Wire format (JSON):
+//!   request:  {"version": 3, "commands": [{"op": "add_rectangle", "x": 0, ...}, ...]}
+//!   response: {"version": 3, "responses": [{"status": "ok", "result": ...}, ...]}
+//! Commands run in order; a failing command reports an error and the batch continues.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::core::scene::PathCommand;
+use crate::eraser::EraserSample;
+use crate::operations::Operation;
+use crate::pattern::PatternFill;
+use crate::renderer::SvgPageOptions;
+use crate::trace::TraceOptions;
+use crate::Editor;
+
+/// Current protocol version; batches with another version are rejected
+/// Version 2: export_svg takes page options and the document's canvas size
+/// instead of width/height.
+/// Version 3: export_scene takes history_bytes (a size budget) instead of history_steps.
+pub const PROTOCOL_VERSION: u32 = 3;
+
+/// A versioned list of commands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandBatch {
+    pub version: u32,
+    pub commands: Vec<Command>,
+}
+
+/// One response per command, in the same order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseBatch {
+    pub version: u32,
+    pub responses: Vec<Response>,
+}
+
+/// Editor operations (same names and arguments as the Wasm API)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Command {
+    // Shapes
+    AddRectangle { x: f64, y: f64, width: f64, height: f64 },
+    AddEllipse { cx: f64, cy: f64, rx: f64, ry: f64 },
+    AddCircle { cx: f64, cy: f64, r: f64 },
+    AddPath { commands: Vec<PathCommand> },
+    AddPathFromSvg { d: String },
+    TraceImage { png: Vec<u8>, #[serde(default)] options: TraceOptions },
+    AddText { x: f64, y: f64, content: String },
+    AddRotatedRectangle { cx: f64, cy: f64, width: f64, height: f64, angle_degrees: f64 },
+    AddHeartPath { cx: f64, cy: f64, size: f64 },
+
+    // Selection
+    Select { ids: Vec<String> },
+    SelectAt { x: f64, y: f64 },
+    AddToSelectionAt { x: f64, y: f64 },
+    DeselectAll,
+    GetSelectedIds,
+    GetSelectionState,
+    HitTest { x: f64, y: f64 },
+    HitHandle { x: f64, y: f64 },
+    HasSelection,
+    GetSelectionOverlay,
+    GetSelectedStyle,
+    GetHandlePositions,
+    GetSelectionCenter,
+    ObjectCount,
+
+    // Viewport
+    SetViewport { pan_x: f64, pan_y: f64, zoom: f64 },
+    ZoomViewportAt { screen_x: f64, screen_y: f64, factor: f64 },
+    GetViewport,
+    ScreenToWorld { x: f64, y: f64 },
+    WorldToScreen { x: f64, y: f64 },
+    SetScreenSpaceTolerances { enabled: bool },
+
+    // Rendering
+    GetRenderCommands,
+    GetRenderCommandsByLayer,
+    GetRenderCommandsBinary,
+    GetRenderCommandsDelta,
+    InvalidateRenderDelta,
+    GetRenderCacheStats,
+    Tessellate { tolerance: f64 },
+    SetCacheAsBitmap { id: String, enabled: bool },
+    GetCacheAsBitmap { id: String },
+
+    // Editing the selection
+    MoveSelected { dx: f64, dy: f64 },
+    NudgeSelected { direction: String, step: f64 },
+    AlignSelected { mode: String },
+    AlignSelectedToCanvas { mode: String, width: f64, height: f64 },
+    DuplicateSelected { dx: f64, dy: f64 },
+    RepeatDuplicate,
+    CutSelection,
+    StepAndRepeat { id: String, rows: u32, cols: u32, dx: f64, dy: f64, #[serde(default)] rotate_step: f64 },
+    DeleteSelected,
+    BringToFront,
+    SendToBack,
+    Reparent { id: String, #[serde(default)] parent_id: String, index: usize },
+    FlipSelectedHorizontal,
+    FlipSelectedVertical,
+    RotateSelected { angle_degrees: f64 },
+    BooleanOpSelected { operation: String },
+    UpdateStyle { fill: String, stroke: String, stroke_width: f64 },
+    SetStrokeLineStyle { cap: String, join: String },
+    SetFillRule { rule: String },
+    SetStrokeAlign { align: String },
+    SetFillPattern { pattern: Option<PatternFill> },
+
+    // Drags and tools (pointer positions in world units)
+    BeginMoveDrag { start_x: f64, start_y: f64, #[serde(default)] duplicate: bool },
+    UpdateMoveDrag { current_x: f64, current_y: f64, #[serde(default)] constrained: bool },
+    BeginResizeDrag { start_x: f64, start_y: f64, handle_index: u8 },
+    UpdateResizeDrag {
+        current_x: f64,
+        current_y: f64,
+        #[serde(default)]
+        from_center: bool,
+        #[serde(default)]
+        keep_aspect: bool,
+    },
+    BeginRotateDrag { start_x: f64, start_y: f64 },
+    UpdateRotateDrag { current_x: f64, current_y: f64 },
+    UpdateRotateDragSnapped { current_x: f64, current_y: f64, snap_degrees: f64 },
+    GetDragRotation,
+    EndDrag,
+    IsDragging,
+    SetSmartGuides { enabled: bool, threshold: f64 },
+    GetSmartGuides,
+    SetResizeFlip { enabled: bool },
+    PenDown { x: f64, y: f64 },
+    PenSnapPoint { x: f64, y: f64 },
+    PenMove { x: f64, y: f64 },
+    PenUp { x: f64, y: f64 },
+    PenClose,
+    PenFinish,
+    PenCancel,
+    IsPenDrawing,
+    GetPenPreview,
+    SetBrushWidth { width: f64 },
+    GetBrushWidth,
+    BrushDown { x: f64, y: f64, pressure: f64, #[serde(default)] time: Option<f64> },
+    BrushMove { x: f64, y: f64, pressure: f64, #[serde(default)] time: Option<f64> },
+    BrushUp,
+    BrushCancel,
+    IsBrushDrawing,
+    GetBrushPreview,
+
+    // Objects
+    SetName { id: String, name: String },
+    GetName { id: String },
+    FindByName { pattern: String },
+    FindSimilarShapes { id: String, tolerance: f64 },
+    GetCircleRadius { id: String },
+    SetCircleRadius { id: String, radius: f64 },
+    SetShapeRendering { id: String, hint: String },
+    GetShapeRendering { id: String },
+    OutlineStroke { id: String },
+    OffsetPath { id: String, distance: f64 },
+    EraseStroke { samples: Vec<EraserSample>, radius: f64 },
+    ResetBoundingBox { id: String },
+    GetObjectBounds { id: String },
+    GetTransformProperties { id: String },
+    SetPosition { id: String, x: f64, y: f64 },
+    SetSize { id: String, width: f64, height: f64 },
+    SetRotation { id: String, degrees: f64 },
+    SelectedIsPath,
+    GetPathPoints { id: String },
+    UpdatePathPoint { id: String, index: usize, world_x: f64, world_y: f64 },
+    GetPathD { id: String },
+    SetPathD { id: String, d: String },
+    SplitPathAt { id: String, x: f64, y: f64 },
+    JoinPaths { id_a: String, id_b: String, #[serde(default)] average: bool },
+    InsertPathPoint { id: String, segment_index: usize, t: f64 },
+    InsertPathPointAt { id: String, x: f64, y: f64 },
+    SetAnchorType { id: String, index: usize, anchor_type: String },
+    GetPathAnchors { id: String },
+    ClosePath { id: String, #[serde(default)] curved: bool },
+    OpenPath { id: String },
+    SelectAnchors { id: String, indices: Vec<usize> },
+    SelectAnchorsInRect { id: String, x1: f64, y1: f64, x2: f64, y2: f64, #[serde(default)] add: bool },
+    GetSelectedAnchors,
+    ClearAnchorSelection,
+    MoveSelectedAnchors { dx: f64, dy: f64 },
+    UpdatePathHandle { id: String, anchor_index: usize, which: String, x: f64, y: f64, #[serde(default)] mirror: bool },
+
+    // Text
+    RegisterFont { data: Vec<u8> },
+    UnregisterFont { font_id: String },
+    GetFonts,
+    FindFont { family: String, weight: u16, #[serde(default)] italic: bool },
+    GetGlyphCacheStats,
+    ListMissingFonts,
+    SetTextContent { id: String, content: String },
+    SetTextFont { id: String, family: String, #[serde(default = "regular_weight")] weight: u16, #[serde(default)] italic: bool },
+    SetFontSize { id: String, size: f64 },
+    SetTextWidth { id: String, width: f64 },
+    SetTextAlign { id: String, align: String },
+    SetTextIndent { id: String, indent: f64 },
+    SetTextWritingMode { id: String, mode: String },
+    SetTextLeading { id: String, leading: f64 },
+    SetTextTracking { id: String, tracking: f64 },
+    SetTextFeatures { id: String, features: String },
+    SetTextKerning { id: String, kerning: Value },
+    GetTextProperties { id: String },
+    GetTextLayout { id: String },
+    ConvertTextToOutlines {
+        id: String,
+        #[serde(default)]
+        per_glyph: bool,
+    },
+    TextHit { id: String, x: f64, y: f64 },
+    CaretRect { id: String, index: u32 },
+    BeginTextEdit { id: String },
+    InsertText { text: String },
+    DeleteBackward,
+    DeleteForward,
+    MoveCaret { unit: String, forward: bool },
+    SetCaret { index: u32 },
+    GetTextEditState,
+    EndTextEdit,
+
+    // Clipboard (fragments are passed inline as JSON values)
+    CopySelection,
+    Paste { fragment: Value },
+    PasteInPlace { fragment: Value },
+    PasteAt { fragment: Value, x: f64, y: f64 },
+    CopyStyleFromSelection,
+    PasteStyleToSelection { style: Value },
+    SampleStyleAt { x: f64, y: f64 },
+    ApplySampledStyleToSelection { x: f64, y: f64 },
+
+    // Library (styles are passed inline as JSON values)
+    CreateSymbolFromSelection { #[serde(default)] name: String },
+    DetachInstance { id: String },
+    GetSymbols,
+    AuditAssets,
+    AddSwatch { #[serde(default)] name: String, color: String },
+    UpdateSwatch { id: String, name: String, color: String },
+    RemoveSwatch { id: String },
+    GetSwatches,
+    ParseColor { color: String },
+    AdjustColor { color: String, operation: String, amount: f64 },
+    AddSharedStyle { #[serde(default)] name: String, style: Value },
+    CreateSharedStyleFromSelection { #[serde(default)] name: String },
+    UpdateSharedStyle { id: String, style: Value },
+    RenameSharedStyle { id: String, name: String },
+    RemoveSharedStyle { id: String },
+    GetSharedStyles,
+    ApplySharedStyle { id: String },
+    DetachSharedStyle,
+
+    // History (label omitted = "Edit")
+    SaveSnapshot { #[serde(default)] label: Option<String> },
+    Undo,
+    Redo,
+    GetHistory,
+    JumpToHistory { index: usize },
+    SetMaxHistory { max_history: usize },
+    GetMaxHistory,
+    SetMaxHistoryBytes { max_bytes: usize },
+    GetMaxHistoryBytes,
+    GetHistoryMemoryUsage,
+    CanUndo,
+    CanRedo,
+    UndoStackSize,
+    RedoStackSize,
+    BeginBulkMode,
+    EndBulkMode,
+    IsBulkMode,
+    ApplyOperations { operations: Vec<Operation> },
+
+    // Animation (value omitted = key the current value)
+    SetKeyframe {
+        id: String,
+        property: String,
+        time: f64,
+        #[serde(default)]
+        value: Option<Value>,
+        #[serde(default = "linear_easing")]
+        easing: String,
+    },
+    RemoveKeyframe { id: String, property: String, time: f64 },
+    GetKeyframes { id: String },
+    SetTime { time: f64 },
+    GetTime,
+    SetPlayback { duration: f64, fps: f64, #[serde(default)] looping: bool },
+    GetPlayback,
+
+    // Documents (history_bytes = also save the undo steps that fit in that many bytes,
+    // restored on import)
+    ImportScene { scene: Value },
+    ImportBegin { total_size: usize },
+    ImportChunk { bytes: Vec<u8> },
+    GetImportProgress,
+    ImportFinish,
+    ImportCancel,
+    SetImportLimits { max_input_bytes: usize, max_nodes: usize, max_path_commands: usize, max_depth: usize },
+    ExportScene { #[serde(default)] history_bytes: Option<usize> },
+    ExportSvg { #[serde(default)] options: SvgPageOptions },
+    ExportToSvgCropped { padding: f64 },
+    ExportToPng,
+    ExportToPdf { width_mm: f64, height_mm: f64 },
+    SetExportPrecision { digits: i32 },
+    OptimizeDrawOrder,
+    SetColorSpace { name: String },
+    GetColorSpace,
+    SetCanvasSize { width: f64, height: f64 },
+    SetBackgroundColor { color: String },
+    SetUnits { units: String },
+    SetDpi { dpi: f64 },
+    GetDocumentProperties,
+    AddArtboard { #[serde(default)] name: String, x: f64, y: f64, width: f64, height: f64 },
+    SetArtboardFrame { id: String, x: f64, y: f64, width: f64, height: f64 },
+    RenameArtboard { id: String, name: String },
+    ReorderArtboard { id: String, index: usize },
+    RemoveArtboard { id: String },
+    ListArtboards,
+    GetArtboardObjects { id: String },
+    ExportArtboardSvg { id: String },
+    ExportArtboardToPng { id: String },
+    SetGrid { size: f64, subdivisions: u32, enabled: bool },
+    GetGrid,
+    SnapShapeCreation { anchor_x: f64, anchor_y: f64, x: f64, y: f64, threshold: f64 },
+    AddGuide { axis: String, position: f64 },
+    RemoveGuide { id: String },
+    ListGuides,
+    GetSceneBounds,
+    ClearScene,
+    RoundtripCheck { format: String },
+}
+
+fn linear_easing() -> String {
+    "linear".to_string()
+}
+
+fn regular_weight() -> u16 {
+    400
+}
+
+/// Outcome of a single command
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum Response {
+    Ok {
+        #[serde(default)]
+        result: Value,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl Response {
+    pub(crate) fn ok(result: impl Into<Value>) -> Self {
+        Response::Ok { result: result.into() }
+    }
+
+    /// Wrap a JSON string returned by an editor method
+    fn json(result: &str) -> Self {
+        match serde_json::from_str(result) {
+            Ok(value) => Response::Ok { result: value },
+            Err(_) => Response::Ok { result: Value::String(result.to_string()) },
+        }
+    }
+
+    /// Report a boolean editor result; `false` becomes an error
+    fn check(success: bool, message: &str) -> Self {
+        if success {
+            Response::Ok { result: Value::Null }
+        } else {
+            Response::Error { message: message.to_string() }
+        }
+    }
+}
+
+/// Parse and run a JSON command batch, returning the JSON response batch
+pub fn execute_batch_json(editor: &mut Editor, json: &str) -> String {
+    let responses = match serde_json::from_str::<CommandBatch>(json) {
+        Ok(batch) if batch.version == PROTOCOL_VERSION => {
+            batch.commands.into_iter().map(|command| execute(editor, command)).collect()
+        }
+        Ok(batch) => vec![Response::Error {
+            message: format!("unsupported protocol version {} (expected {})", batch.version, PROTOCOL_VERSION),
+        }],
+        Err(e) => vec![Response::Error { message: e.to_string() }],
+    };
+    let batch = ResponseBatch { version: PROTOCOL_VERSION, responses };
+    serde_json::to_string(&batch).unwrap_or_else(|_| "{}".to_string())
+}
+
+/// Run a single command against the editor
+pub fn execute(editor: &mut Editor, command: Command) -> Response {
+    match command {
+        Command::AddRectangle { x, y, width, height } => Response::ok(editor.add_rectangle(x, y, width, height)),
+        Command::AddEllipse { cx, cy, rx, ry } => Response::ok(editor.add_ellipse(cx, cy, rx, ry)),
+        Command::AddCircle { cx, cy, r } => Response::ok(editor.add_circle(cx, cy, r)),
+        Command::AddPath { commands } => match serde_json::to_string(&commands) {
+            Ok(json) => match editor.add_path_checked(&json) {
+                Ok(id) => Response::ok(id),
+                Err(_) => Response::Error { message: editor.get_last_import_error() },
+            },
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::AddPathFromSvg { d } => match editor.add_path_from_svg_checked(&d) {
+            Ok(id) => Response::ok(id),
+            Err(_) => Response::Error { message: editor.get_last_import_error() },
+        },
+
+        Command::TraceImage { png, options } => match serde_json::to_string(&options) {
+            Ok(json) => Response::json(&editor.trace_image(&png, &json)),
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::AddText { x, y, content } => match editor.add_text(x, y, &content) {
+            id if id.is_empty() => Response::Error { message: "no font registered".to_string() },
+            id => Response::ok(id),
+        },
+        Command::AddRotatedRectangle { cx, cy, width, height, angle_degrees } => {
+            Response::ok(editor.add_rotated_rectangle(cx, cy, width, height, angle_degrees))
+        }
+        Command::AddHeartPath { cx, cy, size } => Response::ok(editor.add_heart_path(cx, cy, size)),
+        Command::Select { ids } => match serde_json::to_string(&ids) {
+            Ok(json) => Response::json(&editor.select_ids(&json)),
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::SelectAt { x, y } => Response::ok(editor.select_at(x, y)),
+        Command::AddToSelectionAt { x, y } => Response::ok(editor.add_to_selection_at(x, y)),
+        Command::DeselectAll => {
+            editor.deselect_all();
+            Response::ok(Value::Null)
+        }
+        Command::GetSelectedIds => Response::json(&editor.get_selected_ids_json()),
+        Command::GetSelectionState => Response::json(&editor.get_selection_state_json()),
+        Command::HitTest { x, y } => Response::ok(editor.hit_test(x, y)),
+        Command::HitHandle { x, y } => Response::ok(editor.hit_handle(x, y)),
+        Command::HasSelection => Response::ok(editor.has_selection()),
+        Command::GetSelectionOverlay => Response::json(&editor.get_selection_overlay_json()),
+        Command::GetSelectedStyle => Response::json(&editor.get_selected_style_json()),
+        Command::GetHandlePositions => Response::json(&editor.get_handle_positions()),
+        Command::GetSelectionCenter => Response::json(&editor.get_selection_center()),
+        Command::ObjectCount => Response::ok(editor.object_count()),
+
+        Command::SetViewport { pan_x, pan_y, zoom } => {
+            Response::check(editor.set_viewport(pan_x, pan_y, zoom), "invalid pan or zoom")
+        }
+        Command::ZoomViewportAt { screen_x, screen_y, factor } => {
+            Response::check(editor.zoom_viewport_at(screen_x, screen_y, factor), "invalid zoom factor")
+        }
+        Command::GetViewport => Response::json(&editor.get_viewport()),
+        Command::ScreenToWorld { x, y } => Response::json(&editor.screen_to_world(x, y)),
+        Command::WorldToScreen { x, y } => Response::json(&editor.world_to_screen(x, y)),
+        Command::SetScreenSpaceTolerances { enabled } => {
+            editor.set_screen_space_tolerances(enabled);
+            Response::ok(Value::Null)
+        }
+
+        Command::GetRenderCommands => Response::json(&editor.get_render_commands_json()),
+        Command::GetRenderCommandsByLayer => Response::json(&editor.get_render_commands_by_layer()),
+        Command::GetRenderCommandsBinary => Response::ok(editor.get_render_commands_binary()),
+        Command::GetRenderCommandsDelta => Response::json(&editor.get_render_commands_delta()),
+        Command::InvalidateRenderDelta => {
+            editor.invalidate_render_delta();
+            Response::ok(Value::Null)
+        }
+        Command::GetRenderCacheStats => Response::json(&editor.get_render_cache_stats()),
+        Command::Tessellate { tolerance } => match serde_json::to_value(editor.tessellate(tolerance)) {
+            Ok(mesh) => Response::ok(mesh),
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::SetCacheAsBitmap { id, enabled } => {
+            Response::check(editor.set_cache_as_bitmap(&id, enabled), "object is not a group")
+        }
+        Command::GetCacheAsBitmap { id } => Response::ok(editor.get_cache_as_bitmap(&id)),
+
+        Command::MoveSelected { dx, dy } => {
+            editor.move_selected(dx, dy);
+            Response::ok(Value::Null)
+        }
+        Command::NudgeSelected { direction, step } => {
+            Response::check(editor.nudge_selected(&direction, step), "nothing to nudge")
+        }
+        Command::AlignSelected { mode } => Response::check(editor.align_selected(&mode), "cannot align selection"),
+        Command::AlignSelectedToCanvas { mode, width, height } => Response::check(
+            editor.align_selected_to_canvas(&mode, width, height),
+            "cannot align selection",
+        ),
+        Command::DuplicateSelected { dx, dy } => Response::json(&editor.duplicate_selected(dx, dy)),
+        Command::RepeatDuplicate => Response::json(&editor.repeat_duplicate()),
+        Command::CutSelection => match editor.cut_selection() {
+            fragment if fragment.is_empty() => Response::check(false, "nothing selected"),
+            fragment => Response::json(&fragment),
+        },
+        Command::StepAndRepeat { id, rows, cols, dx, dy, rotate_step } => {
+            let group_id = editor.step_and_repeat(&id, rows, cols, dx, dy, rotate_step);
+            if group_id.is_empty() {
+                Response::check(false, "object not found or empty grid")
+            } else {
+                Response::ok(group_id)
+            }
+        }
+        Command::DeleteSelected => Response::check(editor.delete_selected(), "nothing selected"),
+        Command::BringToFront => Response::check(editor.bring_to_front(), "nothing selected"),
+        Command::SendToBack => Response::check(editor.send_to_back(), "nothing selected"),
+        Command::Reparent { id, parent_id, index } => Response::check(
+            editor.reparent(&id, &parent_id, index),
+            "unknown object or group, or the group is inside the object",
+        ),
+        Command::FlipSelectedHorizontal => Response::check(editor.flip_selected_horizontal(), "nothing selected"),
+        Command::FlipSelectedVertical => Response::check(editor.flip_selected_vertical(), "nothing selected"),
+        Command::RotateSelected { angle_degrees } => {
+            Response::check(editor.rotate_selected(angle_degrees), "nothing selected")
+        }
+        Command::BooleanOpSelected { operation } => {
+            let id = editor.boolean_op_selected(&operation);
+            if id.is_empty() {
+                Response::check(false, "cannot combine selection")
+            } else {
+                Response::ok(id)
+            }
+        }
+        Command::UpdateStyle { fill, stroke, stroke_width } => {
+            Response::check(editor.update_style(&fill, &stroke, stroke_width), "invalid color")
+        }
+        Command::SetStrokeLineStyle { cap, join } => {
+            Response::check(editor.set_stroke_line_style(&cap, &join), "unknown cap or join")
+        }
+        Command::SetFillRule { rule } => Response::check(editor.set_fill_rule(&rule), "unknown fill rule"),
+        Command::SetStrokeAlign { align } => Response::check(editor.set_stroke_align(&align), "unknown stroke alignment"),
+        Command::SetFillPattern { pattern } => match pattern.map(|pattern| serde_json::to_string(&pattern)) {
+            Some(Ok(json)) => Response::check(editor.set_fill_pattern(&json), "invalid pattern or unknown symbol"),
+            Some(Err(e)) => Response::Error { message: e.to_string() },
+            None => Response::check(editor.set_fill_pattern(""), "invalid pattern"),
+        },
+
+        Command::BeginMoveDrag { start_x, start_y, duplicate } => {
+            editor.begin_move_drag(start_x, start_y, duplicate);
+            Response::ok(Value::Null)
+        }
+        Command::UpdateMoveDrag { current_x, current_y, constrained } => {
+            editor.update_move_drag(current_x, current_y, constrained);
+            Response::ok(Value::Null)
+        }
+        Command::BeginResizeDrag { start_x, start_y, handle_index } => {
+            editor.begin_resize_drag(start_x, start_y, handle_index);
+            Response::ok(Value::Null)
+        }
+        Command::UpdateResizeDrag { current_x, current_y, from_center, keep_aspect } => {
+            editor.update_resize_drag(current_x, current_y, from_center, keep_aspect);
+            Response::ok(Value::Null)
+        }
+        Command::BeginRotateDrag { start_x, start_y } => {
+            editor.begin_rotate_drag(start_x, start_y);
+            Response::ok(Value::Null)
+        }
+        Command::UpdateRotateDrag { current_x, current_y } => {
+            editor.update_rotate_drag(current_x, current_y);
+            Response::ok(Value::Null)
+        }
+        Command::UpdateRotateDragSnapped { current_x, current_y, snap_degrees } => {
+            editor.update_rotate_drag_snapped(current_x, current_y, snap_degrees);
+            Response::ok(Value::Null)
+        }
+        Command::GetDragRotation => Response::ok(editor.get_drag_rotation()),
+        Command::EndDrag => {
+            editor.end_drag();
+            Response::ok(Value::Null)
+        }
+        Command::IsDragging => Response::ok(editor.is_dragging()),
+        Command::SetSmartGuides { enabled, threshold } => {
+            editor.set_smart_guides(enabled, threshold);
+            Response::ok(Value::Null)
+        }
+        Command::GetSmartGuides => Response::json(&editor.get_smart_guides()),
+        Command::SetResizeFlip { enabled } => {
+            editor.set_resize_flip(enabled);
+            Response::ok(Value::Null)
+        }
+        Command::PenDown { x, y } => Response::ok(editor.pen_down(x, y)),
+        Command::PenSnapPoint { x, y } => Response::json(&editor.pen_snap_point(x, y)),
+        Command::PenMove { x, y } => {
+            editor.pen_move(x, y);
+            Response::ok(Value::Null)
+        }
+        Command::PenUp { x, y } => {
+            editor.pen_up(x, y);
+            Response::ok(Value::Null)
+        }
+        Command::PenClose => match editor.pen_close() {
+            id if id.is_empty() => Response::check(false, "no path being drawn"),
+            id => Response::ok(id),
+        },
+        Command::PenFinish => match editor.pen_finish() {
+            id if id.is_empty() => Response::check(false, "no path of at least two points being drawn"),
+            id => Response::ok(id),
+        },
+        Command::PenCancel => {
+            editor.pen_cancel();
+            Response::ok(Value::Null)
+        }
+        Command::IsPenDrawing => Response::ok(editor.is_pen_drawing()),
+        Command::GetPenPreview => Response::json(&editor.get_pen_preview_json()),
+        Command::SetBrushWidth { width } => Response::check(editor.set_brush_width(width), "invalid brush width"),
+        Command::GetBrushWidth => Response::ok(editor.get_brush_width()),
+        Command::BrushDown { x, y, pressure, time } => {
+            editor.brush_down(x, y, pressure, time);
+            Response::ok(Value::Null)
+        }
+        Command::BrushMove { x, y, pressure, time } => Response::ok(editor.brush_move(x, y, pressure, time)),
+        Command::BrushUp => match editor.brush_up() {
+            id if id.is_empty() => Response::check(false, "nothing drawn"),
+            id => Response::ok(id),
+        },
+        Command::BrushCancel => {
+            editor.brush_cancel();
+            Response::ok(Value::Null)
+        }
+        Command::IsBrushDrawing => Response::ok(editor.is_brush_drawing()),
+        Command::GetBrushPreview => Response::json(&editor.get_brush_preview()),
+
+        Command::SetName { id, name } => Response::check(editor.set_name(&id, &name), "object not found"),
+        Command::GetName { id } => Response::ok(editor.get_name(&id)),
+        Command::FindByName { pattern } => Response::json(&editor.find_by_name(&pattern)),
+        Command::FindSimilarShapes { id, tolerance } => Response::json(&editor.find_similar_shapes(&id, tolerance)),
+        Command::GetCircleRadius { id } => match editor.get_circle_radius(&id) {
+            Some(radius) => Response::ok(radius),
+            None => Response::check(false, "object is not a circle"),
+        },
+        Command::SetCircleRadius { id, radius } => {
+            Response::check(editor.set_circle_radius(&id, radius), "object is not a circle or invalid radius")
+        }
+        Command::OutlineStroke { id } => Response::check(editor.outline_stroke(&id), "object not found or not stroked"),
+        Command::OffsetPath { id, distance } => {
+            let new_id = editor.offset_path(&id, distance);
+            if new_id.is_empty() {
+                Response::check(false, "object not found or offset is empty")
+            } else {
+                Response::ok(new_id)
+            }
+        }
+        Command::EraseStroke { samples, radius } => match serde_json::to_string(&samples) {
+            Ok(json) => match editor.erase_stroke(&json, radius) {
+                result if result.is_empty() => Response::check(false, "no samples or invalid radius"),
+                result => Response::json(&result),
+            },
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::SetShapeRendering { id, hint } => {
+            Response::check(editor.set_shape_rendering(&id, &hint), "object not found or unknown hint")
+        }
+        Command::GetShapeRendering { id } => match editor.get_shape_rendering(&id) {
+            hint if hint.is_empty() => Response::check(false, "object not found"),
+            hint => Response::ok(hint),
+        },
+        Command::ResetBoundingBox { id } => {
+            Response::check(editor.reset_bounding_box(&id), "object not found or not rotated")
+        }
+        Command::GetObjectBounds { id } => Response::json(&editor.get_object_bounds(&id)),
+        Command::GetTransformProperties { id } => Response::json(&editor.get_transform_properties(&id)),
+        Command::SetPosition { id, x, y } => Response::check(editor.set_position(&id, x, y), "object not found"),
+        Command::SetSize { id, width, height } => {
+            Response::check(editor.set_size(&id, width, height), "object not found or invalid size")
+        }
+        Command::SetRotation { id, degrees } => {
+            Response::check(editor.set_rotation(&id, degrees), "object not found or invalid angle")
+        }
+        Command::SelectedIsPath => Response::ok(editor.selected_is_path()),
+        Command::GetPathPoints { id } => Response::json(&editor.get_path_points(&id)),
+        Command::UpdatePathPoint { id, index, world_x, world_y } => {
+            editor.update_path_point(&id, index, world_x, world_y);
+            Response::ok(Value::Null)
+        }
+        Command::GetPathD { id } => Response::ok(editor.get_path_d(&id)),
+        Command::SetPathD { id, d } => {
+            if editor.get_path_d(&id).is_empty() {
+                Response::check(false, "object not found")
+            } else if editor.set_path_d(&id, &d) {
+                Response::ok(Value::Null)
+            } else {
+                Response::Error { message: editor.get_last_import_error() }
+            }
+        }
+        Command::SplitPathAt { id, x, y } => match editor.split_path_at(&id, x, y) {
+            ids if ids == "[]" => Response::check(false, "object not found or cut at an end of the path"),
+            ids => Response::json(&ids),
+        },
+        Command::JoinPaths { id_a, id_b, average } => {
+            Response::check(editor.join_paths(&id_a, &id_b, average), "objects must be two different open paths")
+        }
+        Command::InsertPathPoint { id, segment_index, t } => match editor.insert_path_point(&id, segment_index, t) {
+            Some(index) => Response::ok(index),
+            None => Response::check(false, "path or segment not found, or t is not inside the segment"),
+        },
+        Command::InsertPathPointAt { id, x, y } => match editor.insert_path_point_at(&id, x, y) {
+            Some(index) => Response::ok(index),
+            None => Response::check(false, "path not found or the point is an existing anchor"),
+        },
+        Command::SetAnchorType { id, index, anchor_type } => Response::check(
+            editor.set_anchor_type(&id, index, &anchor_type),
+            "path or anchor not found, or unknown anchor type",
+        ),
+        Command::GetPathAnchors { id } => Response::json(&editor.get_path_anchors(&id)),
+        Command::ClosePath { id, curved } => Response::check(editor.close_path(&id, curved), "object is not an open path"),
+        Command::OpenPath { id } => Response::check(editor.open_path(&id), "object is not a closed path"),
+        Command::SelectAnchors { id, indices } => match serde_json::to_string(&indices) {
+            Ok(json) => Response::json(&editor.select_anchors(&id, &json)),
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::SelectAnchorsInRect { id, x1, y1, x2, y2, add } => {
+            Response::json(&editor.select_anchors_in_rect(&id, x1, y1, x2, y2, add))
+        }
+        Command::GetSelectedAnchors => Response::json(&editor.get_selected_anchors()),
+        Command::ClearAnchorSelection => {
+            editor.clear_anchor_selection();
+            Response::ok(Value::Null)
+        }
+        Command::MoveSelectedAnchors { dx, dy } => {
+            Response::check(editor.move_selected_anchors(dx, dy), "no anchors selected")
+        }
+        Command::UpdatePathHandle { id, anchor_index, which, x, y, mirror } => Response::check(
+            editor.update_path_handle(&id, anchor_index, &which, x, y, mirror),
+            "path, anchor or handle not found",
+        ),
+
+        Command::RegisterFont { data } => match editor.register_font(&data) {
+            id if id.is_empty() => Response::Error { message: "not a font file".to_string() },
+            id => Response::ok(id),
+        },
+        Command::UnregisterFont { font_id } => Response::check(editor.unregister_font(&font_id), "unknown font"),
+        Command::GetFonts => Response::json(&editor.get_fonts()),
+        Command::GetGlyphCacheStats => Response::json(&editor.get_glyph_cache_stats()),
+        Command::ListMissingFonts => Response::json(&editor.list_missing_fonts()),
+        Command::FindFont { family, weight, italic } => match editor.find_font(&family, weight, italic) {
+            id if id.is_empty() => Response::Error { message: "no font of that family".to_string() },
+            id => Response::ok(id),
+        },
+        Command::SetTextContent { id, content } => {
+            Response::check(editor.set_text_content(&id, &content), "not a text object or font not loaded")
+        }
+        Command::SetTextFont { id, family, weight, italic } => {
+            Response::check(editor.set_text_font(&id, &family, weight, italic), "not a text object or font not loaded")
+        }
+        Command::SetFontSize { id, size } => {
+            Response::check(editor.set_font_size(&id, size), "not a text object, font not loaded or invalid size")
+        }
+        Command::SetTextWidth { id, width } => {
+            Response::check(editor.set_text_width(&id, width), "not a text object, font not loaded or invalid width")
+        }
+        Command::SetTextAlign { id, align } => Response::check(
+            editor.set_text_align(&id, &align),
+            "not a text object, font not loaded or unknown alignment",
+        ),
+        Command::SetTextIndent { id, indent } => {
+            Response::check(editor.set_text_indent(&id, indent), "not a text object, font not loaded or invalid indent")
+        }
+        Command::SetTextWritingMode { id, mode } => Response::check(
+            editor.set_text_writing_mode(&id, &mode),
+            "not a text object, font not loaded or unknown writing mode",
+        ),
+        Command::SetTextLeading { id, leading } => Response::check(
+            editor.set_text_leading(&id, leading),
+            "not a text object, font not loaded or invalid leading",
+        ),
+        Command::SetTextTracking { id, tracking } => Response::check(
+            editor.set_text_tracking(&id, tracking),
+            "not a text object, font not loaded or invalid tracking",
+        ),
+        Command::SetTextFeatures { id, features } => Response::check(
+            editor.set_text_features(&id, &features),
+            "not a text object, font not loaded or invalid feature setting",
+        ),
+        Command::SetTextKerning { id, kerning } => Response::check(
+            editor.set_text_kerning(&id, &kerning.to_string()),
+            "not a text object, font not loaded or invalid kerning",
+        ),
+        Command::GetTextProperties { id } => match editor.get_text_properties(&id) {
+            json if json.is_empty() => Response::Error { message: "not a text object".to_string() },
+            json => Response::json(&json),
+        },
+        Command::GetTextLayout { id } => match editor.get_text_layout(&id) {
+            json if json.is_empty() => Response::Error { message: "not a text object or font not loaded".to_string() },
+            json => Response::json(&json),
+        },
+        Command::ConvertTextToOutlines { id, per_glyph } => Response::check(
+            editor.convert_text_to_outlines(&id, per_glyph),
+            "not a text object, nothing to outline or font not loaded",
+        ),
+        Command::TextHit { id, x, y } => match editor.text_hit(&id, x, y) {
+            json if json.is_empty() => Response::Error { message: "not a text object or font not loaded".to_string() },
+            json => Response::json(&json),
+        },
+        Command::CaretRect { id, index } => match editor.caret_rect(&id, index) {
+            json if json.is_empty() => Response::Error { message: "not a text object or font not loaded".to_string() },
+            json => Response::json(&json),
+        },
+        Command::BeginTextEdit { id } => {
+            Response::check(editor.begin_text_edit(&id), "not a text object or font not loaded")
+        }
+        Command::InsertText { text } => Response::check(editor.insert_text(&text), "no text being edited"),
+        Command::DeleteBackward => {
+            Response::check(editor.delete_backward(), "no text being edited or caret at the start")
+        }
+        Command::DeleteForward => Response::check(editor.delete_forward(), "no text being edited or caret at the end"),
+        Command::MoveCaret { unit, forward } => {
+            Response::check(editor.move_caret(&unit, forward), "no text being edited or unknown unit")
+        }
+        Command::SetCaret { index } => Response::check(editor.set_caret(index), "no text being edited"),
+        Command::GetTextEditState => match editor.get_text_edit_state() {
+            json if json.is_empty() => Response::Error { message: "no text being edited".to_string() },
+            json => Response::json(&json),
+        },
+        Command::EndTextEdit => Response::check(editor.end_text_edit(), "no text being edited"),
+
+        Command::CopySelection => Response::json(&editor.copy_selection()),
+        Command::Paste { fragment } => paste_response(editor, |editor| editor.paste(&fragment.to_string())),
+        Command::PasteInPlace { fragment } => {
+            paste_response(editor, |editor| editor.paste_in_place(&fragment.to_string()))
+        }
+        Command::PasteAt { fragment, x, y } => {
+            paste_response(editor, |editor| editor.paste_at(&fragment.to_string(), x, y))
+        }
+        Command::CopyStyleFromSelection => Response::json(&editor.copy_style_from_selection()),
+        Command::PasteStyleToSelection { style } => {
+            Response::check(editor.paste_style_to_selection(&style.to_string()), "invalid style")
+        }
+        Command::SampleStyleAt { x, y } => Response::json(&editor.sample_style_at(x, y)),
+        Command::ApplySampledStyleToSelection { x, y } => Response::check(
+            editor.apply_sampled_style_to_selection(x, y),
+            "nothing to sample or nothing selected",
+        ),
+
+        Command::CreateSymbolFromSelection { name } => match editor.create_symbol_from_selection(&name) {
+            id if id.is_empty() => Response::check(false, "nothing selected"),
+            id => Response::ok(id),
+        },
+        Command::DetachInstance { id } => match editor.detach_instance(&id) {
+            ids if ids == "[]" => Response::check(false, "object is not an instance"),
+            ids => Response::json(&ids),
+        },
+        Command::GetSymbols => Response::json(&editor.get_symbols()),
+        Command::AuditAssets => Response::json(&editor.audit_assets()),
+
+        Command::AddSwatch { name, color } => {
+            let id = editor.add_swatch(&name, &color);
+            if id.is_empty() {
+                Response::Error { message: "invalid swatch color".to_string() }
+            } else {
+                Response::ok(id)
+            }
+        }
+        Command::UpdateSwatch { id, name, color } => {
+            Response::check(editor.update_swatch(&id, &name, &color), "unknown swatch or invalid color")
+        }
+        Command::RemoveSwatch { id } => Response::check(editor.remove_swatch(&id), "unknown swatch"),
+        Command::GetSwatches => Response::json(&editor.get_swatches()),
+        Command::ParseColor { color } => match editor.parse_color(&color) {
+            json if json.is_empty() => Response::Error { message: "invalid color".to_string() },
+            json => Response::json(&json),
+        },
+        Command::AdjustColor { color, operation, amount } => match editor.adjust_color(&color, &operation, amount) {
+            adjusted if adjusted.is_empty() => Response::Error { message: "invalid color or operation".to_string() },
+            adjusted => Response::ok(adjusted),
+        },
+        Command::AddSharedStyle { name, style } => {
+            let id = editor.add_shared_style(&name, &style.to_string());
+            if id.is_empty() {
+                Response::Error { message: "invalid style".to_string() }
+            } else {
+                Response::ok(id)
+            }
+        }
+        Command::CreateSharedStyleFromSelection { name } => {
+            let id = editor.create_shared_style_from_selection(&name);
+            if id.is_empty() {
+                Response::Error { message: "nothing selected".to_string() }
+            } else {
+                Response::ok(id)
+            }
+        }
+        Command::UpdateSharedStyle { id, style } => Response::check(
+            editor.update_shared_style(&id, &style.to_string()),
+            "unknown shared style or invalid style",
+        ),
+        Command::RenameSharedStyle { id, name } => {
+            Response::check(editor.rename_shared_style(&id, &name), "unknown shared style")
+        }
+        Command::RemoveSharedStyle { id } => Response::check(editor.remove_shared_style(&id), "unknown shared style"),
+        Command::GetSharedStyles => Response::json(&editor.get_shared_styles()),
+        Command::ApplySharedStyle { id } => Response::check(editor.apply_shared_style(&id), "unknown shared style"),
+        Command::DetachSharedStyle => Response::ok(editor.detach_shared_style()),
+
+        Command::SaveSnapshot { label } => {
+            match label {
+                Some(label) => editor.save_named_snapshot(&label),
+                None => editor.save_snapshot(),
+            }
+            Response::ok(Value::Null)
+        }
+        Command::Undo => Response::check(editor.undo(), "nothing to undo"),
+        Command::Redo => Response::check(editor.redo(), "nothing to redo"),
+        Command::GetHistory => Response::json(&editor.get_history_json()),
+        Command::JumpToHistory { index } => Response::check(editor.jump_to_history(index), "history index out of range"),
+        Command::SetMaxHistory { max_history } => {
+            editor.set_max_history(max_history);
+            Response::ok(Value::Null)
+        }
+        Command::GetMaxHistory => Response::ok(editor.get_max_history()),
+        Command::SetMaxHistoryBytes { max_bytes } => {
+            editor.set_max_history_bytes(max_bytes);
+            Response::ok(Value::Null)
+        }
+        Command::GetMaxHistoryBytes => Response::ok(editor.get_max_history_bytes()),
+        Command::GetHistoryMemoryUsage => Response::ok(editor.history_memory_usage()),
+        Command::CanUndo => Response::ok(editor.can_undo()),
+        Command::CanRedo => Response::ok(editor.can_redo()),
+        Command::UndoStackSize => Response::ok(editor.undo_stack_size()),
+        Command::RedoStackSize => Response::ok(editor.redo_stack_size()),
+        Command::BeginBulkMode => {
+            editor.begin_bulk_mode();
+            Response::ok(Value::Null)
+        }
+        Command::EndBulkMode => {
+            editor.end_bulk_mode();
+            Response::ok(Value::Null)
+        }
+        Command::IsBulkMode => Response::ok(editor.is_bulk_mode()),
+        Command::ApplyOperations { operations } => match editor.apply_operation_list(operations) {
+            Ok(ids) => Response::ok(ids),
+            Err(error) => Response::Error { message: serde_json::to_string(&error).unwrap_or(error.message) },
+        },
+
+        Command::SetKeyframe { id, property, time, value, easing } => {
+            let value_json = value.map(|value| value.to_string()).unwrap_or_default();
+            Response::check(
+                editor.set_keyframe(&id, &property, time, &value_json, &easing),
+                "unknown object, property or easing, or invalid value",
+            )
+        }
+        Command::RemoveKeyframe { id, property, time } => {
+            Response::check(editor.remove_keyframe(&id, &property, time), "no keyframe at that time")
+        }
+        Command::GetKeyframes { id } => Response::json(&editor.get_keyframes(&id)),
+        Command::SetTime { time } => Response::check(editor.set_time(time), "invalid time"),
+        Command::GetTime => Response::ok(editor.get_time()),
+        Command::SetPlayback { duration, fps, looping } => {
+            Response::check(editor.set_playback(duration, fps, looping), "duration and fps must be positive")
+        }
+        Command::GetPlayback => Response::json(&editor.get_playback()),
+
+        Command::ImportScene { scene } => {
+            match editor.import_scene_checked(&scene.to_string()) {
+                Ok(()) => Response::json(&editor.get_last_import_report()),
+                Err(_) => Response::Error { message: editor.get_last_import_error() },
+            }
+        }
+        Command::ImportBegin { total_size } => match editor.import_begin_checked(total_size) {
+            Ok(()) => Response::ok(Value::Null),
+            Err(_) => Response::Error { message: editor.get_last_import_error() },
+        },
+        Command::ImportChunk { bytes } => match editor.import_chunk_checked(&bytes) {
+            Ok(progress) => Response::ok(progress),
+            Err(_) => Response::Error { message: editor.get_last_import_error() },
+        },
+        Command::GetImportProgress => Response::json(&editor.get_import_progress()),
+        Command::ImportFinish => match editor.import_finish_checked() {
+            Ok(()) => Response::json(&editor.get_last_import_report()),
+            Err(_) => Response::Error { message: editor.get_last_import_error() },
+        },
+        Command::ImportCancel => {
+            editor.import_cancel();
+            Response::ok(Value::Null)
+        }
+        Command::SetImportLimits { max_input_bytes, max_nodes, max_path_commands, max_depth } => {
+            editor.set_import_limits(max_input_bytes, max_nodes, max_path_commands, max_depth);
+            Response::ok(Value::Null)
+        }
+        Command::ExportScene { history_bytes } => Response::json(&match history_bytes {
+            Some(max_bytes) => editor.export_scene_to_json_with_history(max_bytes),
+            None => editor.export_scene_to_json(),
+        }),
+        Command::ExportSvg { options } => match serde_json::to_string(&options) {
+            Ok(json) => Response::ok(editor.export_to_svg_with_options(&json)),
+            Err(e) => Response::Error { message: e.to_string() },
+        },
+        Command::ExportToSvgCropped { padding } => Response::ok(editor.export_to_svg_cropped(padding)),
+        Command::ExportToPng => match editor.export_to_png() {
+            png if png.is_empty() => Response::check(false, "cannot create the bitmap"),
+            png => Response::ok(png),
+        },
+        Command::ExportToPdf { width_mm, height_mm } => match editor.export_to_pdf(width_mm, height_mm) {
+            pdf if pdf.is_empty() => Response::check(false, "invalid page size"),
+            pdf => Response::ok(pdf),
+        },
+        Command::SetExportPrecision { digits } => {
+            editor.set_export_precision(digits);
+            Response::ok(Value::Null)
+        }
+        Command::OptimizeDrawOrder => Response::json(&editor.optimize_draw_order()),
+        Command::SetColorSpace { name } => Response::check(editor.set_color_space(&name), "unknown color space"),
+        Command::GetColorSpace => Response::ok(editor.get_color_space()),
+        Command::SetCanvasSize { width, height } => {
+            Response::check(editor.set_canvas_size(width, height), "canvas size must be positive")
+        }
+        Command::SetBackgroundColor { color } => {
+            Response::check(editor.set_background_color(&color), "invalid background color")
+        }
+        Command::SetUnits { units } => Response::check(editor.set_units(&units), "unknown units"),
+        Command::SetDpi { dpi } => Response::check(editor.set_dpi(dpi), "dpi must be positive"),
+        Command::GetDocumentProperties => Response::json(&editor.get_document_properties()),
+        Command::AddArtboard { name, x, y, width, height } => {
+            let id = editor.add_artboard(&name, x, y, width, height);
+            if id.is_empty() {
+                Response::Error { message: "artboard size must be positive".to_string() }
+            } else {
+                Response::ok(id)
+            }
+        }
+        Command::SetArtboardFrame { id, x, y, width, height } => Response::check(
+            editor.set_artboard_frame(&id, x, y, width, height),
+            "unknown artboard or invalid frame",
+        ),
+        Command::RenameArtboard { id, name } => Response::check(editor.rename_artboard(&id, &name), "unknown artboard"),
+        Command::ReorderArtboard { id, index } => {
+            Response::check(editor.reorder_artboard(&id, index), "unknown artboard")
+        }
+        Command::RemoveArtboard { id } => Response::check(editor.remove_artboard(&id), "unknown artboard"),
+        Command::ListArtboards => Response::json(&editor.list_artboards()),
+        Command::GetArtboardObjects { id } => Response::json(&editor.get_artboard_objects(&id)),
+        Command::ExportArtboardSvg { id } => {
+            let svg = editor.export_artboard_to_svg(&id);
+            if svg.is_empty() {
+                Response::Error { message: "unknown artboard".to_string() }
+            } else {
+                Response::ok(svg)
+            }
+        }
+        Command::ExportArtboardToPng { id } => match editor.export_artboard_to_png(&id) {
+            png if png.is_empty() => Response::check(false, "unknown artboard"),
+            png => Response::ok(png),
+        },
+        Command::SetGrid { size, subdivisions, enabled } => {
+            editor.set_grid(size, subdivisions, enabled);
+            Response::ok(Value::Null)
+        }
+        Command::GetGrid => Response::json(&editor.get_grid()),
+        Command::SnapShapeCreation { anchor_x, anchor_y, x, y, threshold } => {
+            Response::json(&editor.snap_shape_creation(anchor_x, anchor_y, x, y, threshold))
+        }
+        Command::AddGuide { axis, position } => match editor.add_guide(&axis, position) {
+            id if id.is_empty() => Response::check(false, "unknown axis"),
+            id => Response::ok(id),
+        },
+        Command::RemoveGuide { id } => Response::check(editor.remove_guide(&id), "unknown guide"),
+        Command::ListGuides => Response::json(&editor.list_guides()),
+        Command::GetSceneBounds => Response::json(&editor.get_scene_bounds()),
+        Command::ClearScene => {
+            editor.clear_scene();
+            Response::ok(Value::Null)
+        }
+        Command::RoundtripCheck { format } => Response::json(&editor.roundtrip_check(&format)),
+    }
+}
+
+/// Run a paste variant, turning a rejected fragment into an error response
+fn paste_response(editor: &mut Editor, paste: impl FnOnce(&mut Editor) -> String) -> Response {
+    let ids = paste(editor);
+    let error = editor.get_last_import_error();
+    if error.is_empty() {
+        Response::json(&ids)
+    } else {
+        Response::Error { message: error }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(editor: &mut Editor, commands: &str) -> ResponseBatch {
+        let json = format!(r#"{{"version":{},"commands":{}}}"#, PROTOCOL_VERSION, commands);
+        serde_json::from_str(&execute_batch_json(editor, &json)).unwrap()
+    }
+
+    /// Wasm methods with no command that calls them, and why
+    const WITHOUT_COMMANDS: &[(&str, &str)] = &[
+        ("new", "creates the editor"),
+        ("execute_ops", "runs command batches"),
+        ("set_selection_observer", "takes a JavaScript callback"),
+        ("render_to_canvas", "draws on a browser canvas"),
+        ("render_to_offscreen_canvas", "draws on a browser canvas"),
+        ("get_render_commands", "JavaScript object form of get_render_commands_json"),
+        ("get_selected_ids", "JavaScript object form of get_selected_ids_json"),
+        ("get_selection_state", "JavaScript object form of get_selection_state_json"),
+        ("get_history", "JavaScript object form of get_history_json"),
+        ("get_selection_overlay", "JavaScript object form of get_selection_overlay_json"),
+        ("get_selected_style", "JavaScript object form of get_selected_style_json"),
+        ("get_pen_preview", "JavaScript object form of get_pen_preview_json"),
+        ("add_path", "throwing form of add_path_checked"),
+        ("apply_operations", "throwing form of apply_operation_list"),
+        ("add_path_from_svg", "throwing form of add_path_from_svg_checked"),
+        ("import_scene_from_json", "throwing form of import_scene_checked"),
+        ("import_begin", "throwing form of import_begin_checked"),
+        ("import_chunk", "throwing form of import_chunk_checked"),
+        ("import_finish", "throwing form of import_finish_checked"),
+        ("export_to_svg", "export_svg with the default options"),
+    ];
+
+
+    #[test]
+    fn test_batch_runs_commands_in_order() {
+        let mut editor = Editor::new();
+        let batch = run(
+            &mut editor,
+            r#"[{"op":"add_rectangle","x":0,"y":0,"width":10,"height":10},
+                {"op":"select","ids":["obj_1"]},
+                {"op":"move_selected","dx":5,"dy":0},
+                {"op":"get_object_bounds","id":"obj_1"}]"#,
+        );
+        assert_eq!(batch.responses.len(), 4);
+        assert_eq!(batch.responses[0], Response::ok("obj_1"));
+        assert_eq!(batch.responses[3], Response::ok(serde_json::json!([5.0, 0.0, 15.0, 10.0])));
+    }
+
+    #[test]
+    fn test_failures_are_reported_per_command() {
+        let mut editor = Editor::new();
+        let batch = run(&mut editor, r#"[{"op":"undo"},{"op":"paste","fragment":{"nodes":[]}}]"#);
+        assert!(matches!(batch.responses[0], Response::Error { .. }));
+        assert!(matches!(batch.responses[1], Response::Error { .. }));
+    }
+
+    #[test]
+    fn test_rejects_other_versions() {
+        let mut editor = Editor::new();
+        let json = execute_batch_json(&mut editor, r#"{"version":99,"commands":[{"op":"clear_scene"}]}"#);
+        let batch: ResponseBatch = serde_json::from_str(&json).unwrap();
+        assert!(matches!(batch.responses.as_slice(), [Response::Error { .. }]));
+
+        // Version 1 export_svg took width/height, which version 2 no longer reads
+        let json = execute_batch_json(&mut editor, r#"{"version":1,"commands":[{"op":"export_svg","width":800,"height":600}]}"#);
+        let batch: ResponseBatch = serde_json::from_str(&json).unwrap();
+        assert!(matches!(batch.responses.as_slice(), [Response::Error { .. }]));
+    }
+
+    #[test]
+    fn test_every_wasm_method_has_a_command() {
+        let protocol = include_str!("protocol.rs");
+        let commands = &protocol[..protocol.find("#[cfg(test)]").unwrap_or(protocol.len())];
+        let mut missing = Vec::new();
+        for block in include_str!("lib.rs").split("#[wasm_bindgen]\nimpl Editor {").skip(1) {
+            let block = &block[..block.find("\n}\n").unwrap_or(block.len())];
+            for name in block.lines().filter_map(|line| line.strip_prefix("    pub fn ")?.split('(').next()) {
+                let called = commands.contains(&format!("editor.{}(", name));
+                if !called && !WITHOUT_COMMANDS.iter().any(|(method, _)| *method == name) {
+                    missing.push(name);
+                }
+            }
+        }
+        assert!(missing.is_empty(), "methods without a command: {:?}", missing);
+    }
+
+    #[test]
+    fn test_chunked_import_commands() {
+        let mut source = Editor::new();
+        source.add_rectangle(0.0, 0.0, 10.0, 10.0);
+        source.add_circle(50.0, 50.0, 5.0);
+        let bytes = source.export_scene_to_json().into_bytes();
+        let (first, second) = bytes.split_at(bytes.len() / 2);
+
+        let mut editor = Editor::new();
+        let batch = run(
+            &mut editor,
+            &serde_json::json!([
+                { "op": "import_begin", "total_size": bytes.len() },
+                { "op": "import_chunk", "bytes": first },
+                { "op": "get_import_progress" },
+                { "op": "import_chunk", "bytes": second },
+                { "op": "import_finish" },
+                { "op": "object_count" },
+                { "op": "import_finish" },
+            ])
+            .to_string(),
+        );
+        assert_eq!(batch.responses[1], Response::ok(first.len() as f64 / bytes.len() as f64));
+        let Response::Ok { result: progress } = &batch.responses[2] else {
+            panic!("progress failed");
+        };
+        assert_eq!(progress["received"], serde_json::json!(first.len()));
+        assert!(matches!(batch.responses[4], Response::Ok { .. }));
+        assert_eq!(batch.responses[5], Response::ok(2));
+        assert!(matches!(batch.responses[6], Response::Error { .. }));
+    }
+
+    #[test]
+    fn test_tool_commands() {
+        let mut editor = Editor::new();
+        let batch = run(
+            &mut editor,
+            r#"[{"op":"pen_down","x":0,"y":0},
+                {"op":"pen_up","x":0,"y":0},
+                {"op":"pen_down","x":50,"y":0},
+                {"op":"pen_up","x":50,"y":0},
+                {"op":"is_pen_drawing"},
+                {"op":"pen_finish"},
+                {"op":"add_guide","axis":"diagonal","position":5},
+                {"op":"add_guide","axis":"vertical","position":5},
+                {"op":"list_guides"},
+                {"op":"set_grid","size":10,"subdivisions":2,"enabled":true},
+                {"op":"get_grid"},
+                {"op":"brush_up"}]"#,
+        );
+        assert_eq!(batch.responses[4], Response::ok(true));
+        let Response::Ok { result: Value::String(path_id) } = &batch.responses[5] else {
+            panic!("pen_finish failed");
+        };
+        assert!(editor.scene.get_node_by_id(path_id).is_some());
+        assert!(matches!(batch.responses[6], Response::Error { .. }));
+        let Response::Ok { result: guides } = &batch.responses[8] else {
+            panic!("list_guides failed");
+        };
+        assert_eq!(guides.as_array().map(Vec::len), Some(1));
+        assert_eq!(batch.responses[10], Response::ok(serde_json::json!({ "size": 10.0, "subdivisions": 2, "enabled": true })));
+        assert!(matches!(batch.responses[11], Response::Error { .. }));
+    }
+}
@@ -0,0 +1,568 @@
+//! Raster Module - Headless bitmap export
+//!
+//! Replays the same render command stream the frontend draws on its canvas,
+//! using tiny-skia, so PNG exports do not depend on screenshotting the canvas
+//! and work without a browser. Shapes are blended in the document's color
+//! space: on the stored sRGB values, as a canvas does, or in linear light
+//! (see ColorSpace). Symbol pattern tiles are drawn; image tiles keep the fill
+//! color as their fallback. PNGs are tagged as sRGB.
+
+use tiny_skia::{
+    ColorU8, FillRule, FilterQuality, LineCap, LineJoin, Mask, Paint, Path, PathBuilder, PathStroker, Pattern, Pixmap,
+    Rect, SpreadMode, Stroke, Transform,
+};
+
+use crate::color::{parse_hex_color, srgb_to_linear, Color, ColorSpace};
+use crate::core::scene::SceneGraph;
+use crate::renderer::{self, RenderCommand};
+use crate::spatial::BoundingBox;
+
+/// Canvas 2D default miter limit
+const MITER_LIMIT: f32 = 10.0;
+
+/// Largest bitmap side we agree to allocate
+const MAX_SIDE: f64 = 16384.0;
+
+/// Largest side of a pattern tile bitmap
+const MAX_TILE_SIDE: f64 = 2048.0;
+
+/// Drawing state mirrored from the canvas context
+struct CanvasState {
+    transform: Transform,
+    fill: FillStyle,
+    stroke: [u8; 3],
+    alpha: f32,
+    stroke_style: Stroke,
+    path: PathBuilder,
+}
+
+/// What fills are painted with
+enum FillStyle {
+    Color([u8; 3]),
+    /// A pattern tile bitmap, repeated; `to_local` maps its pixels to the object's space
+    Pattern { tile: Pixmap, to_local: Transform },
+}
+
+/// Where shapes are blended
+enum Surface {
+    /// Straight into the bitmap, blending the stored sRGB values
+    Srgb(Pixmap),
+    /// Into a linear-light buffer, converted to sRGB at the end
+    Linear(Box<LinearSurface>),
+}
+
+/// Premultiplied linear color of a fill: flat, or sampled from a tile (with the
+/// transform from device pixels to tile pixels)
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Color([f32; 4]),
+    Tile(&'a Pixmap, Transform),
+}
+
+struct LinearSurface {
+    width: u32,
+    height: u32,
+    /// Premultiplied linear-light RGBA
+    pixels: Vec<[f32; 4]>,
+    /// Coverage of the shape being drawn (cleared again as it is blended)
+    coverage: Mask,
+    /// Linear value of each 8-bit sRGB channel value
+    to_linear: [f32; 256],
+}
+
+/// Rasterize the scene into a bitmap of width x height document units
+/// scale: device pixels per document unit (e.g. 2 for a high-DPI export)
+/// The page is filled with the document background, if it has one.
+/// Returns None for an empty or oversized bitmap.
+pub fn rasterize(scene: &SceneGraph, width: f64, height: f64, scale: f64) -> Option<Pixmap> {
+    rasterize_view(scene, &BoundingBox::from_rect(0.0, 0.0, width, height), scale)
+}
+
+/// Rasterize a world-space region of the scene (e.g. an artboard)
+/// The top-left of `view` maps to the top-left pixel of the bitmap.
+pub fn rasterize_view(scene: &SceneGraph, view: &BoundingBox, scale: f64) -> Option<Pixmap> {
+    let (pixel_width, pixel_height) = ((view.width() * scale).round(), (view.height() * scale).round());
+    let valid = |side: f64| side.is_finite() && side <= MAX_SIDE;
+    if !scale.is_finite() || !valid(pixel_width) || !valid(pixel_height) {
+        return None;
+    }
+    let mut surface = Surface::new(pixel_width.max(0.0) as u32, pixel_height.max(0.0) as u32, scene.color_space)?;
+    if let Some(rgb) = scene.document.background.as_deref().and_then(parse_rgb8) {
+        surface.fill_opaque(rgb);
+    }
+
+    let device = Transform::from_scale(scale as f32, scale as f32).pre_translate(-view.min_x as f32, -view.min_y as f32);
+    draw(&mut surface, device, &renderer::generate_render_commands(scene));
+    surface.finish()
+}
+
+/// Rasterize the scene and encode it as PNG
+/// Returns None if the bitmap cannot be created or encoded.
+pub fn render_png(scene: &SceneGraph, width: f64, height: f64, scale: f64) -> Option<Vec<u8>> {
+    encode_png(&rasterize(scene, width, height, scale)?)
+}
+
+/// Rasterize a world-space region of the scene and encode it as PNG
+pub fn render_png_view(scene: &SceneGraph, view: &BoundingBox, scale: f64) -> Option<Vec<u8>> {
+    encode_png(&rasterize_view(scene, view, scale)?)
+}
+
+/// Encode a bitmap as PNG with an sRGB chunk, so viewers do not guess its color space
+fn encode_png(pixmap: &Pixmap) -> Option<Vec<u8>> {
+    let mut png = pixmap.encode_png().ok()?;
+    // Signature (8 bytes), then IHDR: length, type, 13 data bytes, CRC
+    let after_header = 8 + 4 + 4 + 13 + 4;
+    if png.len() < after_header {
+        return None;
+    }
+    // Rendering intent 0: perceptual
+    let chunk = [b's', b'R', b'G', b'B', 0];
+    let mut srgb = 1u32.to_be_bytes().to_vec();
+    srgb.extend_from_slice(&chunk);
+    srgb.extend_from_slice(&crc32(&chunk).to_be_bytes());
+    png.splice(after_header..after_header, srgb);
+    Some(png)
+}
+
+/// CRC-32 of a PNG chunk's type and data
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 };
+        }
+    }
+    !crc
+}
+
+fn draw(surface: &mut Surface, device: Transform, commands: &[RenderCommand]) {
+    let mut state = CanvasState {
+        transform: device,
+        fill: FillStyle::Color([0, 0, 0]),
+        stroke: [0, 0, 0],
+        alpha: 1.0,
+        stroke_style: Stroke { width: 1.0, miter_limit: MITER_LIMIT, ..Stroke::default() },
+        path: PathBuilder::new(),
+    };
+    for command in commands {
+        apply(surface, &mut state, device, command);
+    }
+}
+
+fn apply(surface: &mut Surface, state: &mut CanvasState, device: Transform, command: &RenderCommand) {
+    let f = |value: f64| value as f32;
+    match *command {
+        RenderCommand::SetTransform { a, b, c, d, e, f: ty } => {
+            state.transform = device.pre_concat(Transform::from_row(f(a), f(b), f(c), f(d), f(e), f(ty)));
+        }
+        RenderCommand::ResetTransform => state.transform = device,
+        RenderCommand::BeginPath => state.path = PathBuilder::new(),
+        RenderCommand::Rect { x, y, width, height } => {
+            // Drawn edge by edge so negative sizes behave as on a canvas
+            let path = &mut state.path;
+            path.move_to(f(x), f(y));
+            path.line_to(f(x + width), f(y));
+            path.line_to(f(x + width), f(y + height));
+            path.line_to(f(x), f(y + height));
+            path.close();
+        }
+        RenderCommand::Ellipse { cx, cy, rx, ry } => {
+            if let Some(oval) = Rect::from_ltrb(f(cx - rx), f(cy - ry), f(cx + rx), f(cy + ry)) {
+                state.path.push_oval(oval);
+            }
+        }
+        RenderCommand::MoveTo { x, y } => state.path.move_to(f(x), f(y)),
+        RenderCommand::LineTo { x, y } => state.path.line_to(f(x), f(y)),
+        RenderCommand::BezierCurveTo { cp1x, cp1y, cp2x, cp2y, x, y } => {
+            state.path.cubic_to(f(cp1x), f(cp1y), f(cp2x), f(cp2y), f(x), f(y));
+        }
+        RenderCommand::QuadraticCurveTo { cpx, cpy, x, y } => state.path.quad_to(f(cpx), f(cpy), f(x), f(y)),
+        RenderCommand::ClosePath => state.path.close(),
+        RenderCommand::SetFillStyle { ref color } => {
+            // Like a canvas, an unparseable color leaves the previous one in place
+            if let Some(rgb) = parse_rgb8(color) {
+                state.fill = FillStyle::Color(rgb);
+            }
+        }
+        // Image tiles keep the fill color that precedes them as a flat fallback
+        RenderCommand::SetFillPattern { image: Some(_), .. } => {}
+        RenderCommand::SetFillPattern { width, height, ref commands, image: None, a, b, c, d, e, f: ty, .. } => {
+            let to_local = Transform::from_row(f(a), f(b), f(c), f(d), f(e), f(ty));
+            if let Some(fill) = pattern_fill(surface, state.transform, to_local, width, height, commands) {
+                state.fill = fill;
+            }
+        }
+        RenderCommand::SetStrokeStyle { ref color } => {
+            if let Some(rgb) = parse_rgb8(color) {
+                state.stroke = rgb;
+            }
+        }
+        RenderCommand::SetLineWidth { width } => {
+            if width.is_finite() && width > 0.0 {
+                state.stroke_style.width = f(width);
+            }
+        }
+        RenderCommand::SetLineCap { ref cap } => {
+            state.stroke_style.line_cap = match cap.as_str() {
+                "round" => LineCap::Round,
+                "square" => LineCap::Square,
+                _ => LineCap::Butt,
+            };
+        }
+        RenderCommand::SetLineJoin { ref join } => {
+            state.stroke_style.line_join = match join.as_str() {
+                "round" => LineJoin::Round,
+                "bevel" => LineJoin::Bevel,
+                _ => LineJoin::Miter,
+            };
+        }
+        RenderCommand::SetGlobalAlpha { alpha } => {
+            if (0.0..=1.0).contains(&alpha) {
+                state.alpha = f(alpha);
+            }
+        }
+        RenderCommand::Fill { ref rule } => {
+            let rule = if rule == "evenodd" { FillRule::EvenOdd } else { FillRule::Winding };
+            if let Some(path) = state.path.clone().finish() {
+                surface.fill_path(&path, &state.fill, state.alpha, rule, state.transform);
+            }
+        }
+        RenderCommand::Stroke => {
+            if let Some(path) = state.path.clone().finish() {
+                surface.stroke_path(&path, state.stroke, state.alpha, &state.stroke_style, state.transform);
+            }
+        }
+        // Bitmap caching is a display optimization; exports always draw everything
+        RenderCommand::BeginCache { .. } | RenderCommand::EndCache { .. } => {}
+    }
+}
+
+/// Draw a pattern tile at the resolution it will appear at under `transform`
+/// The bitmap is rounded up to whole pixels and stretched back to the cell size.
+fn pattern_fill(
+    surface: &Surface,
+    transform: Transform,
+    to_local: Transform,
+    width: f64,
+    height: f64,
+    commands: &[RenderCommand],
+) -> Option<FillStyle> {
+    let scale = f64::from(PathStroker::compute_resolution_scale(&transform.pre_concat(to_local)));
+    let side = |units: f64| (units * scale).ceil().clamp(1.0, MAX_TILE_SIDE);
+    let (pixel_width, pixel_height) = (side(width), side(height));
+    let mut tile = Surface::new(pixel_width as u32, pixel_height as u32, surface.color_space())?;
+    let device = Transform::from_scale((pixel_width / width) as f32, (pixel_height / height) as f32);
+    draw(&mut tile, device, commands);
+    Some(FillStyle::Pattern {
+        tile: tile.finish()?,
+        to_local: to_local.pre_scale((width / pixel_width) as f32, (height / pixel_height) as f32),
+    })
+}
+
+impl Surface {
+    fn new(width: u32, height: u32, space: ColorSpace) -> Option<Self> {
+        match space {
+            ColorSpace::Srgb => Pixmap::new(width, height).map(Surface::Srgb),
+            ColorSpace::LinearSrgb => LinearSurface::new(width, height).map(|linear| Surface::Linear(Box::new(linear))),
+        }
+    }
+
+    fn color_space(&self) -> ColorSpace {
+        match self {
+            Surface::Srgb(_) => ColorSpace::Srgb,
+            Surface::Linear(_) => ColorSpace::LinearSrgb,
+        }
+    }
+
+    fn fill_opaque(&mut self, rgb: [u8; 3]) {
+        match self {
+            Surface::Srgb(pixmap) => pixmap.fill(tiny_skia::Color::from_rgba8(rgb[0], rgb[1], rgb[2], 255)),
+            Surface::Linear(linear) => {
+                let [r, g, b] = rgb.map(|channel| linear.to_linear[usize::from(channel)]);
+                linear.pixels.fill([r, g, b, 1.0]);
+            }
+        }
+    }
+
+    fn fill_path(&mut self, path: &Path, fill: &FillStyle, alpha: f32, rule: FillRule, transform: Transform) {
+        match self {
+            Surface::Srgb(pixmap) => {
+                let paint = match fill {
+                    FillStyle::Color(rgb) => solid_paint(*rgb, alpha),
+                    FillStyle::Pattern { tile, to_local } => Paint {
+                        shader: Pattern::new(tile.as_ref(), SpreadMode::Repeat, FilterQuality::Bilinear, alpha, *to_local),
+                        anti_alias: true,
+                        ..Paint::default()
+                    },
+                };
+                pixmap.fill_path(path, &paint, rule, transform, None);
+            }
+            Surface::Linear(linear) => linear.fill_path(path, fill, alpha, rule, transform),
+        }
+    }
+
+    fn stroke_path(&mut self, path: &Path, rgb: [u8; 3], alpha: f32, stroke: &Stroke, transform: Transform) {
+        match self {
+            Surface::Srgb(pixmap) => pixmap.stroke_path(path, &solid_paint(rgb, alpha), stroke, transform, None),
+            Surface::Linear(linear) => {
+                let resolution = PathStroker::compute_resolution_scale(&transform);
+                if let Some(outline) = path.stroke(stroke, resolution) {
+                    linear.fill_path(&outline, &FillStyle::Color(rgb), alpha, FillRule::Winding, transform);
+                }
+            }
+        }
+    }
+
+    fn finish(self) -> Option<Pixmap> {
+        match self {
+            Surface::Srgb(pixmap) => Some(pixmap),
+            Surface::Linear(linear) => linear.finish(),
+        }
+    }
+}
+
+impl LinearSurface {
+    fn new(width: u32, height: u32) -> Option<Self> {
+        let coverage = Mask::new(width, height)?;
+        let mut pixels = Vec::new();
+        // A failed allocation is an export that cannot be made, not a crash
+        let count = width as usize * height as usize;
+        pixels.try_reserve_exact(count).ok()?;
+        pixels.resize(count, [0.0; 4]);
+        let to_linear = std::array::from_fn(|value| srgb_to_linear(value as f64 / 255.0) as f32);
+        Some(LinearSurface { width, height, pixels, coverage, to_linear })
+    }
+
+    fn fill_path(&mut self, path: &Path, fill: &FillStyle, alpha: f32, rule: FillRule, transform: Transform) {
+        let Some(path) = path.clone().transform(transform) else {
+            return;
+        };
+        let source = match fill {
+            FillStyle::Color(rgb) => {
+                let [r, g, b] = rgb.map(|channel| self.to_linear[usize::from(channel)]);
+                Source::Color([r, g, b, 1.0])
+            }
+            FillStyle::Pattern { tile, to_local } => match transform.pre_concat(*to_local).invert() {
+                Some(to_tile) => Source::Tile(tile, to_tile),
+                None => return,
+            },
+        };
+        self.coverage.fill_path(&path, rule, true, Transform::identity());
+
+        // Anti-aliasing reaches at most one pixel past the outline's bounds
+        let bounds = path.bounds();
+        let span = |low: f32, high: f32, size: u32| {
+            let low = (low.floor() - 1.0).max(0.0) as u32;
+            let high = ((high.ceil() + 1.0).max(0.0) as u32).min(size);
+            low.min(high)..high
+        };
+        for y in span(bounds.top(), bounds.bottom(), self.height) {
+            for x in span(bounds.left(), bounds.right(), self.width) {
+                let index = y as usize * self.width as usize + x as usize;
+                let covered = std::mem::take(&mut self.coverage.data_mut()[index]);
+                if covered == 0 {
+                    continue;
+                }
+                let color = match source {
+                    Source::Color(color) => color,
+                    Source::Tile(tile, to_tile) => self.sample(tile, to_tile, x, y),
+                };
+                let weight = f32::from(covered) / 255.0 * alpha;
+                let pixel = &mut self.pixels[index];
+                let keep = 1.0 - color[3] * weight;
+                for channel in 0..4 {
+                    pixel[channel] = color[channel] * weight + pixel[channel] * keep;
+                }
+            }
+        }
+    }
+
+    /// Premultiplied linear color of the repeated tile under the center of a device pixel
+    fn sample(&self, tile: &Pixmap, to_tile: Transform, x: u32, y: u32) -> [f32; 4] {
+        let mut point = tiny_skia::Point::from_xy(x as f32 + 0.5, y as f32 + 0.5);
+        to_tile.map_point(&mut point);
+        let (width, height) = (tile.width() as f32, tile.height() as f32);
+        let column = (point.x.rem_euclid(width) as u32).min(tile.width() - 1);
+        let row = (point.y.rem_euclid(height) as u32).min(tile.height() - 1);
+        let Some(pixel) = tile.pixel(column, row).map(|pixel| pixel.demultiply()) else {
+            return [0.0; 4];
+        };
+        let alpha = f32::from(pixel.alpha()) / 255.0;
+        let channel = |value: u8| self.to_linear[usize::from(value)] * alpha;
+        [channel(pixel.red()), channel(pixel.green()), channel(pixel.blue()), alpha]
+    }
+
+    fn finish(self) -> Option<Pixmap> {
+        let mut pixmap = Pixmap::new(self.width, self.height)?;
+        for (target, pixel) in pixmap.pixels_mut().iter_mut().zip(&self.pixels) {
+            let alpha = pixel[3].clamp(0.0, 1.0);
+            if alpha <= 0.0 {
+                continue;
+            }
+            let color = Color::from_linear([pixel[0], pixel[1], pixel[2]].map(|channel| f64::from(channel / alpha)));
+            let [r, g, b] = color.channels().map(|channel| (channel * 255.0).round() as u8);
+            *target = ColorU8::from_rgba(r, g, b, (alpha * 255.0).round() as u8).premultiply();
+        }
+        Some(pixmap)
+    }
+}
+
+fn parse_rgb8(color: &str) -> Option<[u8; 3]> {
+    let rgb = parse_hex_color(color)?;
+    Some(rgb.map(|channel| (channel * 255.0).round() as u8))
+}
+
+fn solid_paint(rgb: [u8; 3], alpha: f32) -> Paint<'static> {
+    let mut color = tiny_skia::Color::from_rgba8(rgb[0], rgb[1], rgb[2], 255);
+    color.apply_opacity(alpha);
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::{ObjectStyle, SceneNode, Symbol, VectorObject};
+    use crate::pattern::{PatternFill, PatternSource};
+
+    /// Default document background
+    const BACKGROUND: [u8; 3] = [0x1e, 0x1e, 0x1e];
+
+    fn pixel(pixmap: &Pixmap, x: u32, y: u32) -> [u8; 3] {
+        let color = pixmap.pixel(x, y).map(|p| [p.red(), p.green(), p.blue()]);
+        color.unwrap_or_default()
+    }
+
+    fn scene_with_rect(fill: &str) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Rectangle { x: 10.0, y: 10.0, width: 20.0, height: 10.0 },
+            TransformMatrix::identity(),
+        );
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.fill_color = Some(fill.to_string());
+            style.stroke_color = None;
+        }
+        scene
+    }
+
+    #[test]
+    fn test_rasterize_fills_shapes_over_background() {
+        let pixmap = rasterize(&scene_with_rect("#ff0000"), 50.0, 40.0, 1.0).expect("bitmap");
+        assert_eq!((pixmap.width(), pixmap.height()), (50, 40));
+        assert_eq!(pixel(&pixmap, 20, 15), [255, 0, 0]);
+        assert_eq!(pixel(&pixmap, 5, 5), BACKGROUND);
+        assert_eq!(pixel(&pixmap, 35, 15), BACKGROUND);
+    }
+
+    #[test]
+    fn test_rasterize_scale() {
+        let pixmap = rasterize(&scene_with_rect("#00ff00"), 50.0, 40.0, 2.0).expect("bitmap");
+        assert_eq!((pixmap.width(), pixmap.height()), (100, 80));
+        assert_eq!(pixel(&pixmap, 58, 38), [0, 255, 0]);
+        assert_eq!(pixel(&pixmap, 62, 38), BACKGROUND);
+        assert!(rasterize(&scene_with_rect("#00ff00"), 50.0, 40.0, 0.0).is_none());
+        assert!(rasterize(&scene_with_rect("#00ff00"), 50.0, 40.0, f64::NAN).is_none());
+        assert!(rasterize(&SceneGraph::new(), 0.0, 40.0, 1.0).is_none());
+    }
+
+    #[test]
+    fn test_rasterize_applies_opacity() {
+        let mut scene = scene_with_rect("#ffffff");
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.opacity = 0.5;
+        }
+        let pixmap = rasterize(&scene, 50.0, 40.0, 1.0).expect("bitmap");
+        // Half of white over the dark background
+        let [r, g, b] = pixel(&pixmap, 20, 15);
+        assert!(r == g && g == b && (r as i32 - 0x8f).abs() <= 1, "{}", r);
+    }
+
+    #[test]
+    fn test_rasterize_transparent_document() {
+        let mut scene = scene_with_rect("#ff0000");
+        scene.document.background = None;
+        let pixmap = rasterize(&scene, 50.0, 40.0, 1.0).expect("bitmap");
+        assert_eq!(pixmap.pixel(5, 5).map(|p| p.alpha()), Some(0));
+        assert_eq!(pixmap.pixel(20, 15).map(|p| p.alpha()), Some(255));
+    }
+
+    #[test]
+    fn test_rasterize_view_offsets_region() {
+        let view = BoundingBox::from_rect(10.0, 10.0, 10.0, 10.0);
+        let pixmap = rasterize_view(&scene_with_rect("#ff0000"), &view, 2.0).expect("bitmap");
+        assert_eq!((pixmap.width(), pixmap.height()), (20, 20));
+        assert_eq!(pixel(&pixmap, 1, 1), [255, 0, 0]);
+        assert_eq!(pixel(&pixmap, 10, 18), [255, 0, 0]);
+    }
+
+    #[test]
+    fn test_linear_color_space_blends_in_linear_light() {
+        let mut scene = scene_with_rect("#ffffff");
+        scene.document.background = Some("#000000".to_string());
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.opacity = 0.5;
+        }
+        // Half of white over black: 0.5 on the stored values, ~0.735 in linear light
+        let gamma = pixel(&rasterize(&scene, 50.0, 40.0, 1.0).expect("bitmap"), 20, 15);
+        scene.color_space = ColorSpace::LinearSrgb;
+        let linear = rasterize(&scene, 50.0, 40.0, 1.0).expect("bitmap");
+        assert!((gamma[0] as i32 - 128).abs() <= 1, "{:?}", gamma);
+        assert!((pixel(&linear, 20, 15)[0] as i32 - 188).abs() <= 1, "{:?}", pixel(&linear, 20, 15));
+        // Opaque colors come out unchanged
+        assert_eq!(pixel(&linear, 5, 5), [0, 0, 0]);
+        let mut opaque = scene_with_rect("#336699");
+        opaque.color_space = ColorSpace::LinearSrgb;
+        let opaque = rasterize(&opaque, 50.0, 40.0, 1.0).expect("bitmap");
+        assert_eq!(pixel(&opaque, 20, 15), [0x33, 0x66, 0x99]);
+    }
+
+    #[test]
+    fn test_rasterize_draws_symbol_patterns() {
+        let mut scene = scene_with_rect("#ffffff");
+        scene.symbols.push(Symbol {
+            id: "sym_1".to_string(),
+            name: "Square".to_string(),
+            children: vec![SceneNode::Leaf {
+                id: "square".to_string(),
+                object: VectorObject::Rectangle { x: 0.0, y: 0.0, width: 4.0, height: 4.0 },
+                transform: TransformMatrix::identity(),
+                style: ObjectStyle { fill_color: Some("#ff0000".to_string()), stroke_color: None, ..ObjectStyle::default() },
+                name: None,
+            }],
+        });
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.fill_pattern = Some(PatternFill {
+                source: PatternSource::Symbol { symbol_id: "sym_1".to_string() },
+                spacing: [4.0, 4.0],
+                scale: 1.0,
+                rotation: 0.0,
+            });
+        }
+        for space in [ColorSpace::Srgb, ColorSpace::LinearSrgb] {
+            scene.color_space = space;
+            let pixmap = rasterize(&scene, 50.0, 40.0, 1.0).expect("bitmap");
+            // 4-unit squares every 8 units from the object's origin; the gaps show through
+            assert_eq!(pixel(&pixmap, 17, 17), [255, 0, 0], "{:?}", space);
+            assert_eq!(pixel(&pixmap, 21, 13), BACKGROUND, "{:?}", space);
+            assert_eq!(pixel(&pixmap, 35, 15), BACKGROUND, "{:?}", space);
+        }
+    }
+
+    #[test]
+    fn test_render_png_round_trips() {
+        let png = render_png(&scene_with_rect("#0000ff"), 50.0, 40.0, 1.0).expect("png");
+        assert!(png.starts_with(b"\x89PNG\r\n\x1a\n"));
+        // The sRGB chunk follows the header
+        assert_eq!(&png[33..42], b"\0\0\0\x01sRGB\0");
+        assert_eq!(crc32(b"IEND"), 0xAE42_6082);
+        let decoded = Pixmap::decode_png(&png).expect("decodable png");
+        assert_eq!(pixel(&decoded, 20, 15), [0, 0, 255]);
+    }
+}
@@ -0,0 +1,276 @@
+//! Render Buffer Module - Render commands as a flat array of numbers
+//!
+//! Parsing a JSON string of commands every frame costs the frontend parse time and
+//! garbage. The same commands are also encoded as f64 values, which reach JS as a
+//! Float64Array that is read in place.
+//!
+//! Encoding (version 1):
+//!
+//! ```text
+//! header   VERSION, string_count, then per string: length, UTF-16 code units...
+//! command  opcode, operands...   (repeated to the end of the buffer)
+//! ```
+//!
+//! Strings (colors, cap/join/fill rule names, cache IDs and keys) are stored once in
+//! the header and referenced by index. Operands per opcode, in the order of the
+//! fields of [`RenderCommand`]:
+//!
+//! | opcode | command          | operands                                                   |
+//! |--------|------------------|------------------------------------------------------------|
+//! | 0      | SetTransform     | a, b, c, d, e, f                                           |
+//! | 1      | BeginPath        |                                                            |
+//! | 2      | Rect             | x, y, width, height                                        |
+//! | 3      | Ellipse          | cx, cy, rx, ry                                             |
+//! | 4      | MoveTo           | x, y                                                       |
+//! | 5      | LineTo           | x, y                                                       |
+//! | 6      | BezierCurveTo    | cp1x, cp1y, cp2x, cp2y, x, y                               |
+//! | 7      | QuadraticCurveTo | cpx, cpy, x, y                                             |
+//! | 8      | ClosePath        |                                                            |
+//! | 9      | SetFillStyle     | color string                                               |
+//! | 10     | SetFillPattern   | key string, width, height, a, b, c, d, e, f, image href    |
+//! |        |                  | string (-1 if none), image width, image height, length of  |
+//! |        |                  | the tile's commands, then the tile's commands              |
+//! | 11     | SetStrokeStyle   | color string                                               |
+//! | 12     | SetLineWidth     | width                                                      |
+//! | 13     | SetLineCap       | cap string                                                 |
+//! | 14     | SetLineJoin      | join string                                                |
+//! | 15     | SetGlobalAlpha   | alpha                                                      |
+//! | 16     | Fill             | rule string                                                |
+//! | 17     | Stroke           |                                                            |
+//! | 18     | ResetTransform   |                                                            |
+//! | 19     | BeginCache       | id string, key string                                      |
+//! | 20     | EndCache         | id string                                                  |
+
+use std::collections::HashMap;
+
+use crate::renderer::RenderCommand;
+
+/// Version written at the start of every buffer
+pub const VERSION: f64 = 1.0;
+
+/// Encode render commands (see the module documentation for the layout)
+pub fn encode(commands: &[RenderCommand]) -> Vec<f64> {
+    let mut strings = StringTable::default();
+    let mut body = Vec::new();
+    encode_commands(&mut body, &mut strings, commands);
+
+    let mut buffer = vec![VERSION, strings.values.len() as f64];
+    for value in &strings.values {
+        let units: Vec<u16> = value.encode_utf16().collect();
+        buffer.push(units.len() as f64);
+        buffer.extend(units.into_iter().map(f64::from));
+    }
+    buffer.extend(body);
+    buffer
+}
+
+/// Strings of a buffer, each stored once
+#[derive(Default)]
+struct StringTable {
+    values: Vec<String>,
+    indices: HashMap<String, usize>,
+}
+
+impl StringTable {
+    fn index(&mut self, value: &str) -> f64 {
+        if let Some(&index) = self.indices.get(value) {
+            return index as f64;
+        }
+        let index = self.values.len();
+        self.values.push(value.to_string());
+        self.indices.insert(value.to_string(), index);
+        index as f64
+    }
+}
+
+fn encode_commands(buffer: &mut Vec<f64>, strings: &mut StringTable, commands: &[RenderCommand]) {
+    for command in commands {
+        match command {
+            RenderCommand::SetTransform { a, b, c, d, e, f } => buffer.extend([0.0, *a, *b, *c, *d, *e, *f]),
+            RenderCommand::BeginPath => buffer.push(1.0),
+            RenderCommand::Rect { x, y, width, height } => buffer.extend([2.0, *x, *y, *width, *height]),
+            RenderCommand::Ellipse { cx, cy, rx, ry } => buffer.extend([3.0, *cx, *cy, *rx, *ry]),
+            RenderCommand::MoveTo { x, y } => buffer.extend([4.0, *x, *y]),
+            RenderCommand::LineTo { x, y } => buffer.extend([5.0, *x, *y]),
+            RenderCommand::BezierCurveTo { cp1x, cp1y, cp2x, cp2y, x, y } => {
+                buffer.extend([6.0, *cp1x, *cp1y, *cp2x, *cp2y, *x, *y])
+            }
+            RenderCommand::QuadraticCurveTo { cpx, cpy, x, y } => buffer.extend([7.0, *cpx, *cpy, *x, *y]),
+            RenderCommand::ClosePath => buffer.push(8.0),
+            RenderCommand::SetFillStyle { color } => buffer.extend([9.0, strings.index(color)]),
+            RenderCommand::SetFillPattern { key, width, height, commands, image, a, b, c, d, e, f } => {
+                let (href, image_width, image_height) = match image {
+                    Some(image) => (strings.index(&image.href), image.width, image.height),
+                    None => (-1.0, 0.0, 0.0),
+                };
+                buffer.extend([10.0, strings.index(key), *width, *height, *a, *b, *c, *d, *e, *f]);
+                buffer.extend([href, image_width, image_height]);
+                // Tile commands follow their length, so a reader can skip them
+                let length_at = buffer.len();
+                buffer.push(0.0);
+                encode_commands(buffer, strings, commands);
+                buffer[length_at] = (buffer.len() - length_at - 1) as f64;
+            }
+            RenderCommand::SetStrokeStyle { color } => buffer.extend([11.0, strings.index(color)]),
+            RenderCommand::SetLineWidth { width } => buffer.extend([12.0, *width]),
+            RenderCommand::SetLineCap { cap } => buffer.extend([13.0, strings.index(cap)]),
+            RenderCommand::SetLineJoin { join } => buffer.extend([14.0, strings.index(join)]),
+            RenderCommand::SetGlobalAlpha { alpha } => buffer.extend([15.0, *alpha]),
+            RenderCommand::Fill { rule } => buffer.extend([16.0, strings.index(rule)]),
+            RenderCommand::Stroke => buffer.push(17.0),
+            RenderCommand::ResetTransform => buffer.push(18.0),
+            RenderCommand::BeginCache { id, key } => buffer.extend([19.0, strings.index(id), strings.index(key)]),
+            RenderCommand::EndCache { id } => buffer.extend([20.0, strings.index(id)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::renderer::PatternImage;
+
+    /// Reference reader of the format, as the frontend decodes it
+    fn decode(buffer: &[f64]) -> Option<Vec<RenderCommand>> {
+        if buffer.first() != Some(&VERSION) {
+            return None;
+        }
+        let mut position = 2;
+        let mut strings = Vec::new();
+        for _ in 0..*buffer.get(1)? as usize {
+            let length = *buffer.get(position)? as usize;
+            let units: Vec<u16> = buffer.get(position + 1..position + 1 + length)?.iter().map(|&unit| unit as u16).collect();
+            strings.push(String::from_utf16(&units).ok()?);
+            position += 1 + length;
+        }
+        decode_commands(buffer.get(position..)?, &strings)
+    }
+
+    fn decode_commands(buffer: &[f64], strings: &[String]) -> Option<Vec<RenderCommand>> {
+        let mut commands = Vec::new();
+        let mut position = 0;
+        while position < buffer.len() {
+            let opcode = buffer[position] as u32;
+            let n = |index: usize| buffer.get(position + 1 + index).copied();
+            let s = |index: usize| strings.get(n(index)? as usize).cloned();
+            let (command, operands) = match opcode {
+                0 => (RenderCommand::SetTransform { a: n(0)?, b: n(1)?, c: n(2)?, d: n(3)?, e: n(4)?, f: n(5)? }, 6),
+                1 => (RenderCommand::BeginPath, 0),
+                2 => (RenderCommand::Rect { x: n(0)?, y: n(1)?, width: n(2)?, height: n(3)? }, 4),
+                3 => (RenderCommand::Ellipse { cx: n(0)?, cy: n(1)?, rx: n(2)?, ry: n(3)? }, 4),
+                4 => (RenderCommand::MoveTo { x: n(0)?, y: n(1)? }, 2),
+                5 => (RenderCommand::LineTo { x: n(0)?, y: n(1)? }, 2),
+                6 => {
+                    let command =
+                        RenderCommand::BezierCurveTo { cp1x: n(0)?, cp1y: n(1)?, cp2x: n(2)?, cp2y: n(3)?, x: n(4)?, y: n(5)? };
+                    (command, 6)
+                }
+                7 => (RenderCommand::QuadraticCurveTo { cpx: n(0)?, cpy: n(1)?, x: n(2)?, y: n(3)? }, 4),
+                8 => (RenderCommand::ClosePath, 0),
+                9 => (RenderCommand::SetFillStyle { color: s(0)? }, 1),
+                10 => {
+                    let length = n(12)? as usize;
+                    let start = position + 14;
+                    let image = match n(9)? {
+                        href if href < 0.0 => None,
+                        _ => Some(PatternImage { href: s(9)?, width: n(10)?, height: n(11)? }),
+                    };
+                    let command = RenderCommand::SetFillPattern {
+                        key: s(0)?,
+                        width: n(1)?,
+                        height: n(2)?,
+                        commands: decode_commands(buffer.get(start..start + length)?, strings)?,
+                        image,
+                        a: n(3)?,
+                        b: n(4)?,
+                        c: n(5)?,
+                        d: n(6)?,
+                        e: n(7)?,
+                        f: n(8)?,
+                    };
+                    (command, 13 + length)
+                }
+                11 => (RenderCommand::SetStrokeStyle { color: s(0)? }, 1),
+                12 => (RenderCommand::SetLineWidth { width: n(0)? }, 1),
+                13 => (RenderCommand::SetLineCap { cap: s(0)? }, 1),
+                14 => (RenderCommand::SetLineJoin { join: s(0)? }, 1),
+                15 => (RenderCommand::SetGlobalAlpha { alpha: n(0)? }, 1),
+                16 => (RenderCommand::Fill { rule: s(0)? }, 1),
+                17 => (RenderCommand::Stroke, 0),
+                18 => (RenderCommand::ResetTransform, 0),
+                19 => (RenderCommand::BeginCache { id: s(0)?, key: s(1)? }, 2),
+                20 => (RenderCommand::EndCache { id: s(0)? }, 1),
+                _ => return None,
+            };
+            commands.push(command);
+            position += 1 + operands;
+        }
+        Some(commands)
+    }
+
+    #[test]
+    fn test_commands_round_trip() {
+        let tile = vec![
+            RenderCommand::SetFillStyle { color: "#ff0000".to_string() },
+            RenderCommand::Rect { x: 0.0, y: 0.0, width: 4.0, height: 4.0 },
+            RenderCommand::Fill { rule: "nonzero".to_string() },
+        ];
+        let commands = vec![
+            RenderCommand::BeginCache { id: "obj_1".to_string(), key: "00ff".to_string() },
+            RenderCommand::SetTransform { a: 1.0, b: 0.0, c: 0.0, d: 1.0, e: 10.5, f: -3.0 },
+            RenderCommand::SetFillStyle { color: "#ff0000".to_string() },
+            RenderCommand::SetFillPattern {
+                key: "tile".to_string(),
+                width: 8.0,
+                height: 8.0,
+                commands: tile,
+                image: None,
+                a: 1.0,
+                b: 0.0,
+                c: 0.0,
+                d: 1.0,
+                e: 0.0,
+                f: 0.0,
+            },
+            RenderCommand::SetFillPattern {
+                key: "image".to_string(),
+                width: 8.0,
+                height: 8.0,
+                commands: Vec::new(),
+                image: Some(PatternImage { href: "data:image/png;base64,AA==".to_string(), width: 8.0, height: 8.0 }),
+                a: 2.0,
+                b: 0.0,
+                c: 0.0,
+                d: 2.0,
+                e: 1.0,
+                f: 1.0,
+            },
+            RenderCommand::SetStrokeStyle { color: "rgba(0, 0, 0, 0.5)".to_string() },
+            RenderCommand::SetLineWidth { width: 2.0 },
+            RenderCommand::SetLineCap { cap: "round".to_string() },
+            RenderCommand::SetLineJoin { join: "miter".to_string() },
+            RenderCommand::SetGlobalAlpha { alpha: 0.25 },
+            RenderCommand::BeginPath,
+            RenderCommand::MoveTo { x: 0.0, y: 0.0 },
+            RenderCommand::LineTo { x: 5.0, y: 0.0 },
+            RenderCommand::BezierCurveTo { cp1x: 1.0, cp1y: 2.0, cp2x: 3.0, cp2y: 4.0, x: 5.0, y: 6.0 },
+            RenderCommand::QuadraticCurveTo { cpx: 1.0, cpy: 1.0, x: 2.0, y: 0.0 },
+            RenderCommand::ClosePath,
+            RenderCommand::Ellipse { cx: 1.0, cy: 1.0, rx: 2.0, ry: 3.0 },
+            RenderCommand::Fill { rule: "evenodd".to_string() },
+            RenderCommand::Stroke,
+            RenderCommand::ResetTransform,
+            RenderCommand::EndCache { id: "obj_1".to_string() },
+        ];
+        let buffer = encode(&commands);
+        assert_eq!(decode(&buffer), Some(commands));
+    }
+
+    #[test]
+    fn test_strings_are_stored_once() {
+        let fill = RenderCommand::SetFillStyle { color: "#ü0".to_string() };
+        let buffer = encode(&[fill.clone(), RenderCommand::Stroke, fill]);
+        // Header: version, one string of three code units; then 9 0, 17, 9 0
+        assert_eq!(buffer, vec![VERSION, 1.0, 3.0, 35.0, 252.0, 48.0, 9.0, 0.0, 17.0, 9.0, 0.0]);
+    }
+}
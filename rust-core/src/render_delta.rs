@@ -0,0 +1,231 @@
+//! Render Delta Module - Redrawing only what changed between frames
+//!
+//! The tracker remembers what each layer (top-level node) drew in the previous
+//! frame: its revision and its painted bounds. The next frame compares revisions,
+//! so only the layers edited since are measured again, and reports the region that
+//! changed with the commands needed to repaint it: the changed layers plus the
+//! unchanged ones overlapping the region, bottom first. Dragging one object among
+//! thousands then regenerates and repaints a few layers instead of the whole canvas.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::core::geometry;
+use crate::core::scene::{SceneGraph, SceneNode};
+use crate::renderer::{self, CommandCache, LayerCommands};
+use crate::revision::TrackedScene;
+use crate::spatial::BoundingBox;
+
+/// Extra margin (world units) around painted bounds, for antialiasing
+const ANTIALIAS_MARGIN: f64 = 1.0;
+
+/// What a layer drew in a frame
+#[derive(Debug, Clone)]
+struct DrawnLayer {
+    id: String,
+    revision: u64,
+    bounds: Option<BoundingBox>,
+}
+
+/// Part of a frame to redraw
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderDelta {
+    /// Redraw the whole canvas from `layers` (first frame, after an invalidation, or
+    /// when layers changed order)
+    pub full: bool,
+    /// World region to clear and repaint with `layers` as [min_x, min_y, max_x, max_y];
+    /// None when nothing changed or the frame is full
+    pub region: Option<[f64; 4]>,
+    /// Layers to draw over the cleared region, bottom first (every layer if full)
+    pub layers: Vec<LayerCommands>,
+}
+
+/// Layers drawn in the previous frame
+#[derive(Debug, Clone, Default)]
+pub struct FrameTracker {
+    previous: Option<Vec<DrawnLayer>>,
+}
+
+impl FrameTracker {
+    pub fn new() -> Self {
+        FrameTracker::default()
+    }
+
+    /// Forget the previous frame, so the next one is drawn in full (after the canvas was
+    /// resized, panned or zoomed)
+    pub fn invalidate(&mut self) {
+        self.previous = None;
+    }
+
+    /// Compare the scene with the previous frame and remember it as drawn
+    pub fn next_frame(&mut self, scene: &TrackedScene, cache: &mut CommandCache) -> RenderDelta {
+        let previous = self.previous.take();
+        let before: HashMap<&str, &DrawnLayer> =
+            previous.iter().flatten().map(|layer| (layer.id.as_str(), layer)).collect();
+        // Unchanged layers keep the bounds they were drawn with
+        let drawn: Vec<DrawnLayer> = scene
+            .roots
+            .iter()
+            .map(|root| {
+                let revision = scene.revisions().of(root.id());
+                let bounds = match before.get(root.id().as_str()) {
+                    Some(old) if old.revision == revision => old.bounds,
+                    _ => layer_bounds(scene, root),
+                };
+                DrawnLayer { id: root.id().clone(), revision, bounds }
+            })
+            .collect();
+
+        let Some(previous) = previous.as_deref().filter(|previous| same_order(previous, &drawn)) else {
+            self.previous = Some(drawn);
+            let layers = renderer::generate_render_commands_by_layer_cached(scene, cache);
+            return RenderDelta { full: true, region: None, layers };
+        };
+
+        // Changed layers invalidate where they were and where they are now
+        let current: HashMap<&str, &DrawnLayer> = drawn.iter().map(|layer| (layer.id.as_str(), layer)).collect();
+        let mut changed = vec![false; drawn.len()];
+        let mut region: Option<BoundingBox> = None;
+        let mut invalidate = |bounds: Option<BoundingBox>| {
+            if let Some(bounds) = bounds {
+                region = Some(region.map_or(bounds, |region| region.union(&bounds)));
+            }
+        };
+        for (index, layer) in drawn.iter().enumerate() {
+            match before.get(layer.id.as_str()) {
+                Some(old) if old.revision == layer.revision => {}
+                Some(old) => {
+                    changed[index] = true;
+                    invalidate(old.bounds);
+                    invalidate(layer.bounds);
+                }
+                None => {
+                    changed[index] = true;
+                    invalidate(layer.bounds);
+                }
+            }
+        }
+        for layer in previous.iter().filter(|layer| !current.contains_key(layer.id.as_str())) {
+            invalidate(layer.bounds);
+        }
+
+        let layers = match region {
+            // Unchanged layers under or over the region are repainted too
+            Some(region) => {
+                let redrawn = scene
+                    .roots
+                    .iter()
+                    .zip(drawn.iter().zip(changed))
+                    .filter(|(_, (layer, changed))| *changed || layer.bounds.is_some_and(|bounds| bounds.intersects(&region)))
+                    .map(|(root, _)| root);
+                renderer::generate_layer_commands_cached(scene, redrawn, cache)
+            }
+            None => Vec::new(),
+        };
+        self.previous = Some(drawn);
+        RenderDelta { full: false, region: region.map(|region| region.to_array()), layers }
+    }
+}
+
+/// Whether the layers both frames have keep their stacking order
+fn same_order(previous: &[DrawnLayer], current: &[DrawnLayer]) -> bool {
+    let positions: HashMap<&str, usize> =
+        current.iter().enumerate().map(|(index, layer)| (layer.id.as_str(), index)).collect();
+    let kept: Vec<usize> = previous.iter().filter_map(|layer| positions.get(layer.id.as_str()).copied()).collect();
+    kept.windows(2).all(|pair| pair[0] < pair[1])
+}
+
+/// Painted bounds of a layer, generous enough for miter joins and strokes outside
+/// the outline
+fn layer_bounds(scene: &SceneGraph, root: &SceneNode) -> Option<BoundingBox> {
+    let mut bounds: Option<BoundingBox> = None;
+    for leaf in scene.layer_leaf_views(root) {
+        let Some(leaf_bounds) = geometry::object_world_bounds(leaf.object, &leaf.world_transform) else {
+            continue;
+        };
+        let stroke = if leaf.style.stroke_color.is_some() {
+            2.0 * leaf.style.stroke_width * leaf.world_transform.determinant().abs().sqrt()
+        } else {
+            0.0
+        };
+        let leaf_bounds = leaf_bounds.expand(stroke + ANTIALIAS_MARGIN);
+        bounds = Some(bounds.map_or(leaf_bounds, |layer| layer.union(&leaf_bounds)));
+    }
+    bounds
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::VectorObject;
+
+    fn rect(scene: &mut TrackedScene, x: f64) -> String {
+        let id = scene.generate_id();
+        scene.edit_roots(|scene| {
+            scene.add_object(id.clone(), VectorObject::Rectangle { x, y: 0.0, width: 10.0, height: 10.0 }, TransformMatrix::identity())
+        });
+        id
+    }
+
+    fn layer_ids(delta: &RenderDelta) -> Vec<&str> {
+        delta.layers.iter().map(|layer| layer.layer_id.as_str()).collect()
+    }
+
+    #[test]
+    fn test_first_frame_is_full_then_empty() {
+        let mut scene = TrackedScene::new(SceneGraph::new());
+        rect(&mut scene, 0.0);
+        rect(&mut scene, 100.0);
+        let (mut tracker, mut cache) = (FrameTracker::new(), CommandCache::new());
+        let delta = tracker.next_frame(&scene, &mut cache);
+        assert!(delta.full);
+        assert_eq!(delta.layers.len(), 2);
+        let delta = tracker.next_frame(&scene, &mut cache);
+        assert!(!delta.full && delta.region.is_none() && delta.layers.is_empty());
+        tracker.invalidate();
+        assert!(tracker.next_frame(&scene, &mut cache).full);
+    }
+
+    #[test]
+    fn test_moved_layer_repaints_old_and_new_area() {
+        let mut scene = TrackedScene::new(SceneGraph::new());
+        let a = rect(&mut scene, 0.0);
+        let b = rect(&mut scene, 15.0);
+        let c = rect(&mut scene, 500.0);
+        let (mut tracker, mut cache) = (FrameTracker::new(), CommandCache::new());
+        tracker.next_frame(&scene, &mut cache);
+
+        if let Some(transform) = scene.edit_node(&a).map(|node| node.transform_mut()) {
+            *transform = TransformMatrix::translate(5.0, 0.0);
+        }
+        let delta = tracker.next_frame(&scene, &mut cache);
+        assert!(!delta.full);
+        assert_eq!(delta.region, Some([-5.0, -5.0, 20.0, 15.0]));
+        // The neighbour it overlaps is repainted on top of it; the far one is not
+        assert_eq!(layer_ids(&delta), vec![a.as_str(), b.as_str()]);
+        assert!(!layer_ids(&delta).contains(&c.as_str()));
+        // Only the moved layer is regenerated, and the far one stays cached
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (3, 1, 4));
+
+        // A removed layer clears where it was
+        scene.edit_roots(|scene| scene.remove_roots(std::slice::from_ref(&c)));
+        let delta = tracker.next_frame(&scene, &mut cache);
+        assert_eq!(delta.region, Some([495.0, -5.0, 515.0, 15.0]));
+        assert!(delta.layers.is_empty());
+    }
+
+    #[test]
+    fn test_reordering_redraws_everything() {
+        let mut scene = TrackedScene::new(SceneGraph::new());
+        let a = rect(&mut scene, 0.0);
+        rect(&mut scene, 100.0);
+        let (mut tracker, mut cache) = (FrameTracker::new(), CommandCache::new());
+        tracker.next_frame(&scene, &mut cache);
+        scene.edit_roots(|scene| scene.roots.rotate_left(1));
+        let delta = tracker.next_frame(&scene, &mut cache);
+        assert!(delta.full);
+        assert_eq!(layer_ids(&delta).last(), Some(&a.as_str()));
+    }
+}
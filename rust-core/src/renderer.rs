@@ -3,12 +3,22 @@
 //! Outputs JSON-serializable commands that the React Canvas component can execute
 
 use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use tsify::Tsify;
 
-
-use crate::core::scene::{SceneGraph, VectorObject};
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{FillRule, LineCap, LineJoin, ObjectId, ObjectStyle, PathCommand, SceneGraph, SceneNode, ShapeRendering, VectorObject};
+use crate::path_ops;
+use crate::pattern::{PatternFill, TileContent};
+use crate::revision::{Revisions, TrackedScene};
+use crate::spatial::BoundingBox;
+use crate::stroke;
 
 /// Render command types that map to Canvas 2D API
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
 #[serde(tag = "type")]
 pub enum RenderCommand {
     SetTransform {
@@ -48,209 +58,976 @@ pub enum RenderCommand {
         x: f64,
         y: f64,
     },
+    QuadraticCurveTo {
+        cpx: f64,
+        cpy: f64,
+        x: f64,
+        y: f64,
+    },
     ClosePath,
     SetFillStyle {
         color: String,
     },
+    /// Fill style for the following fills: a width x height cell repeated in both
+    /// directions (canvas createPattern). The cell shows `commands`, drawn in cell
+    /// space, or `image` stretched over its top-left corner; a..f map cell space to
+    /// the current transform (setTransform order). `key` changes whenever the tile
+    /// content does, so the frontend can keep its tile bitmaps.
+    SetFillPattern {
+        key: String,
+        width: f64,
+        height: f64,
+        commands: Vec<RenderCommand>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        image: Option<PatternImage>,
+        a: f64,
+        b: f64,
+        c: f64,
+        d: f64,
+        e: f64,
+        f: f64,
+    },
     SetStrokeStyle {
         color: String,
     },
     SetLineWidth {
         width: f64,
     },
-    Fill,
+    SetLineCap {
+        cap: String,
+    },
+    SetLineJoin {
+        join: String,
+    },
+    /// Opacity of the following fills and strokes (canvas globalAlpha)
+    SetGlobalAlpha {
+        alpha: f64,
+    },
+    Fill {
+        rule: String,
+    },
     Stroke,
     ResetTransform,
+    /// Start of a subtree whose output may be snapshotted as a bitmap.
+    /// The frontend can reuse a cached bitmap for as long as `key` is unchanged
+    /// and skip the commands up to the matching `EndCache`.
+    BeginCache {
+        id: String,
+        key: String,
+    },
+    /// End of a cacheable subtree
+    EndCache {
+        id: String,
+    },
+}
+
+/// Image shown in a pattern cell
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct PatternImage {
+    pub href: String,
+    pub width: f64,
+    pub height: f64,
 }
 
 /// Selection overlay data for drawing bounding boxes
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
 pub struct SelectionOverlay {
     pub id: String,
     /// Corners in world space: [top-left, top-right, bottom-right, bottom-left]
     pub corners: [(f64, f64); 4],
+    /// Side of the square resize handles in world units
+    pub handle_size: f64,
+}
+
+/// Render commands for one layer (a top-level node of the scene)
+#[derive(Debug, Clone, Serialize, Deserialize, Tsify)]
+pub struct LayerCommands {
+    pub layer_id: String,
+    pub commands: Vec<RenderCommand>,
+}
+
+/// Commands generated for leaves in earlier frames
+/// Entries are keyed by a leaf's revision and world transform, so a frame only regenerates
+/// the leaves that were edited or moved. Entries whose revision the scene has moved past
+/// are dropped. Leaves inside an instance are cached per instance, under its revision.
+#[derive(Debug, Clone, Default)]
+pub struct CommandCache {
+    entries: HashMap<u64, CachedLeaf>,
+    hits: u64,
+    misses: u64,
+}
+
+#[derive(Debug, Clone)]
+struct CachedLeaf {
+    commands: Vec<RenderCommand>,
+    /// The leaf, or the instance drawing it, and its revision when generated
+    owner: ObjectId,
+    revision: u64,
+}
+
+/// Render command cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct CommandCacheStats {
+    /// Leaves cached
+    pub entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    /// Share of leaves served from the cache (0 before the first frame)
+    pub hit_rate: f64,
+}
+
+impl CommandCache {
+    pub fn new() -> Self {
+        CommandCache::default()
+    }
+
+    pub fn stats(&self) -> CommandCacheStats {
+        let lookups = self.hits + self.misses;
+        CommandCacheStats {
+            entries: self.entries.len(),
+            hits: self.hits,
+            misses: self.misses,
+            hit_rate: if lookups == 0 { 0.0 } else { self.hits as f64 / lookups as f64 },
+        }
+    }
+
+    /// Drop the leaves whose revision changed since they were generated
+    fn end_frame(&mut self, revisions: &Revisions) {
+        self.entries.retain(|_, cached| revisions.of(&cached.owner) == cached.revision);
+    }
+}
+
+/// A command cache with the revisions of the scene it draws
+struct Reuse<'a> {
+    cache: &'a mut CommandCache,
+    revisions: &'a Revisions,
+    /// Outermost instance whose symbol is being drawn
+    instance: Option<&'a ObjectId>,
+}
+
+impl Reuse<'_> {
+    /// Commands of a leaf, generated only if no earlier frame drew it in this state
+    fn leaf_commands(&mut self, id: &ObjectId, object: &VectorObject, transform: &TransformMatrix, style: &ObjectStyle) -> &[RenderCommand] {
+        let owner = self.instance.unwrap_or(id);
+        let revision = self.revisions.of(owner);
+        match self.cache.entries.entry(leaf_key(owner, revision, id, transform)) {
+            Entry::Occupied(entry) => {
+                self.cache.hits += 1;
+                &entry.into_mut().commands
+            }
+            Entry::Vacant(entry) => {
+                self.cache.misses += 1;
+                let mut commands = Vec::new();
+                push_leaf_commands(&mut commands, object, transform, style, None);
+                &entry.insert(CachedLeaf { commands, owner: owner.clone(), revision }).commands
+            }
+        }
+    }
+}
+
+/// Hash of what identifies a leaf's commands: its owner's revision, its ID and its world transform
+fn leaf_key(owner: &str, revision: u64, id: &str, transform: &TransformMatrix) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (owner, revision, id).hash(&mut hasher);
+    hash_transform(transform, &mut hasher);
+    hasher.finish()
+}
+
+fn hash_transform(transform: &TransformMatrix, hasher: &mut DefaultHasher) {
+    for value in [transform.a, transform.b, transform.c, transform.d, transform.tx, transform.ty] {
+        value.to_bits().hash(hasher);
+    }
 }
 
 /// Generate render commands from the scene graph
 pub fn generate_render_commands(scene: &SceneGraph) -> Vec<RenderCommand> {
     let mut commands = Vec::new();
+    push_node_commands(&mut commands, scene, &scene.roots, TransformMatrix::identity(), true, None);
+    commands
+}
 
-    for (object, transform, style) in scene.iter_leaves() {
-        // Set transform
-        commands.push(RenderCommand::SetTransform {
-            a: transform.a,
-            b: transform.c, // Note: Canvas uses different row/column order
-            c: transform.b,
-            d: transform.d,
-            e: transform.tx,
-            f: transform.ty,
-        });
+/// Generate render commands, reusing the commands of leaves unchanged since the last
+/// frame generated with `cache` (same output as [`generate_render_commands`])
+pub fn generate_render_commands_cached(scene: &TrackedScene, cache: &mut CommandCache) -> Vec<RenderCommand> {
+    let mut commands = Vec::new();
+    let mut reuse = Reuse { cache, revisions: scene.revisions(), instance: None };
+    push_node_commands(&mut commands, scene, &scene.roots, TransformMatrix::identity(), true, Some(&mut reuse));
+    cache.end_frame(scene.revisions());
+    commands
+}
 
-        // Set style
-        if let Some(ref fill) = style.fill_color {
-            commands.push(RenderCommand::SetFillStyle { color: fill.clone() });
+/// Generate render commands split per layer, bottom layer first
+/// Each stream is self-contained so layers can be drawn to separate canvases and composited.
+pub fn generate_render_commands_by_layer(scene: &SceneGraph) -> Vec<LayerCommands> {
+    scene.roots.iter().map(|root| layer_commands(scene, root, None)).collect()
+}
+
+/// Per-layer render commands (see [`generate_render_commands_by_layer`]) reusing cached leaves
+pub fn generate_render_commands_by_layer_cached(scene: &TrackedScene, cache: &mut CommandCache) -> Vec<LayerCommands> {
+    generate_layer_commands_cached(scene, &scene.roots, cache)
+}
+
+/// Render commands of some layers (top-level nodes of `scene`), reusing cached leaves
+pub fn generate_layer_commands_cached<'a>(
+    scene: &'a TrackedScene,
+    roots: impl IntoIterator<Item = &'a SceneNode>,
+    cache: &mut CommandCache,
+) -> Vec<LayerCommands> {
+    let mut reuse = Reuse { cache, revisions: scene.revisions(), instance: None };
+    let layers = roots.into_iter().map(|root| layer_commands(scene, root, Some(&mut reuse))).collect();
+    cache.end_frame(scene.revisions());
+    layers
+}
+
+fn layer_commands<'a>(scene: &'a SceneGraph, root: &'a SceneNode, reuse: Option<&mut Reuse<'a>>) -> LayerCommands {
+    let mut commands = Vec::new();
+    push_node_commands(&mut commands, scene, std::slice::from_ref(root), TransformMatrix::identity(), true, reuse);
+    LayerCommands { layer_id: root.id().clone(), commands }
+}
+
+/// Walk the scene graph, wrapping bitmap-cacheable groups in cache markers
+/// Pattern tiles are drawn with `patterns` off: objects inside a tile use their fill color.
+/// Leaves filled with a pattern bypass `reuse`, as their commands depend on other nodes.
+fn push_node_commands<'a>(
+    commands: &mut Vec<RenderCommand>,
+    scene: &'a SceneGraph,
+    nodes: &'a [SceneNode],
+    parent_transform: TransformMatrix,
+    patterns: bool,
+    mut reuse: Option<&mut Reuse<'a>>,
+) {
+    for node in nodes {
+        match node {
+            SceneNode::Leaf { id, object, transform, style, .. } => {
+                let pattern = match &style.fill_pattern {
+                    Some(pattern) if patterns => pattern_command(scene, pattern),
+                    _ => None,
+                };
+                let world_transform = parent_transform.multiply(transform);
+                match (reuse.as_deref_mut(), pattern) {
+                    (Some(reuse), None) => commands.extend_from_slice(reuse.leaf_commands(id, object, &world_transform, style)),
+                    (_, pattern) => push_leaf_commands(commands, object, &world_transform, style, pattern),
+                }
+            }
+            SceneNode::Group { id, children, transform, cache_as_bitmap, .. } => {
+                let world_transform = parent_transform.multiply(transform);
+                if *cache_as_bitmap {
+                    commands.push(RenderCommand::BeginCache {
+                        id: id.clone(),
+                        key: cache_key(scene, node, &world_transform, reuse.as_deref()),
+                    });
+                    push_node_commands(commands, scene, children, world_transform, patterns, reuse.as_deref_mut());
+                    commands.push(RenderCommand::EndCache { id: id.clone() });
+                } else {
+                    push_node_commands(commands, scene, children, world_transform, patterns, reuse.as_deref_mut());
+                }
+            }
+            SceneNode::Instance { id, symbol_id, transform, .. } => {
+                if let Some(symbol) = scene.get_symbol(symbol_id) {
+                    let world_transform = parent_transform.multiply(transform);
+                    match reuse.as_deref_mut() {
+                        Some(reuse) if reuse.instance.is_none() => {
+                            reuse.instance = Some(id);
+                            push_node_commands(commands, scene, &symbol.children, world_transform, patterns, Some(&mut *reuse));
+                            reuse.instance = None;
+                        }
+                        reuse => push_node_commands(commands, scene, &symbol.children, world_transform, patterns, reuse),
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Key for the bitmap of a cacheable subtree: changes whenever the subtree, a symbol
+/// it shows or its placement changes
+/// Drawing with a cache, the key comes from the subtree's revision; otherwise from its
+/// content.
+fn cache_key(scene: &SceneGraph, node: &SceneNode, world_transform: &TransformMatrix, reuse: Option<&Reuse>) -> String {
+    let mut hasher = DefaultHasher::new();
+    match reuse {
+        Some(reuse) => {
+            let owner = reuse.instance.unwrap_or(node.id());
+            (owner, reuse.revisions.of(owner), node.id()).hash(&mut hasher);
+        }
+        None => {
+            serde_json::to_string(node).unwrap_or_default().hash(&mut hasher);
+            let mut shown = Vec::new();
+            collect_symbols(scene, std::slice::from_ref(node), &mut shown);
+            for symbol_id in shown {
+                serde_json::to_string(&scene.get_symbol(symbol_id)).unwrap_or_default().hash(&mut hasher);
+            }
         }
-        if let Some(ref stroke) = style.stroke_color {
-            commands.push(RenderCommand::SetStrokeStyle { color: stroke.clone() });
+    }
+    hash_transform(world_transform, &mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// IDs of the symbols drawn by nodes, directly or through other symbols, each once
+fn collect_symbols<'a>(scene: &'a SceneGraph, nodes: &'a [SceneNode], shown: &mut Vec<&'a ObjectId>) {
+    for node in nodes {
+        match node {
+            SceneNode::Leaf { .. } => {}
+            SceneNode::Group { children, .. } => collect_symbols(scene, children, shown),
+            SceneNode::Instance { symbol_id, .. } => {
+                if shown.contains(&symbol_id) {
+                    continue;
+                }
+                shown.push(symbol_id);
+                if let Some(symbol) = scene.get_symbol(symbol_id) {
+                    collect_symbols(scene, &symbol.children, shown);
+                }
+            }
         }
-        commands.push(RenderCommand::SetLineWidth { width: style.stroke_width });
+    }
+}
 
-        // Begin path
+/// The fill style command of a pattern, or None if it does not resolve
+fn pattern_command(scene: &SceneGraph, pattern: &PatternFill) -> Option<RenderCommand> {
+    let tile = pattern.tile(scene)?;
+    let mut tile_commands = Vec::new();
+    let mut image = None;
+    match tile.content {
+        TileContent::Nodes { nodes, origin } => {
+            let to_cell = TransformMatrix::translate(-origin.0, -origin.1);
+            push_node_commands(&mut tile_commands, scene, nodes, to_cell, false, None);
+        }
+        TileContent::Image { href, width, height } => {
+            image = Some(PatternImage { href: href.to_string(), width, height });
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_string(&(tile.width, tile.height, &tile_commands, &image)).unwrap_or_default().hash(&mut hasher);
+    let transform = tile.transform;
+    Some(RenderCommand::SetFillPattern {
+        key: format!("{:016x}", hasher.finish()),
+        width: tile.width,
+        height: tile.height,
+        commands: tile_commands,
+        image,
+        a: transform.a,
+        b: transform.c,
+        c: transform.b,
+        d: transform.d,
+        e: transform.tx,
+        f: transform.ty,
+    })
+}
+
+/// Emit the commands that draw a single leaf object
+/// `pattern` replaces the fill color for consumers that support patterns.
+fn push_leaf_commands(
+    commands: &mut Vec<RenderCommand>,
+    object: &VectorObject,
+    transform: &TransformMatrix,
+    style: &ObjectStyle,
+    pattern: Option<RenderCommand>,
+) {
+    let color_layers = object.color_layers();
+    let drawable = object.styled_geometry();
+    let aligned;
+    let object = match style.shape_rendering {
+        ShapeRendering::CrispEdges => match pixel_aligned(&drawable, transform, style.stroke_width) {
+            Some(object) => {
+                aligned = object;
+                &aligned
+            }
+            None => &drawable,
+        },
+        ShapeRendering::Auto => &drawable,
+    };
+
+    // Set transform
+    commands.push(RenderCommand::SetTransform {
+        a: transform.a,
+        b: transform.c, // Note: Canvas uses different row/column order
+        c: transform.b,
+        d: transform.d,
+        e: transform.tx,
+        f: transform.ty,
+    });
+
+    // Set style
+    if let Some(ref fill) = style.fill_color {
+        commands.push(RenderCommand::SetFillStyle { color: fill.clone() });
+        commands.extend(pattern);
+    }
+    if let Some(ref stroke) = style.stroke_color {
+        commands.push(RenderCommand::SetStrokeStyle { color: stroke.clone() });
+    }
+    commands.push(RenderCommand::SetLineWidth { width: style.stroke_width });
+    commands.push(RenderCommand::SetLineCap { cap: style.line_cap.name().to_string() });
+    commands.push(RenderCommand::SetLineJoin { join: style.line_join.name().to_string() });
+    let translucent = style.opacity < 1.0;
+    if translucent {
+        commands.push(RenderCommand::SetGlobalAlpha { alpha: style.opacity.max(0.0) });
+    }
+
+    // Begin path
+    commands.push(RenderCommand::BeginPath);
+
+    // Draw shape
+    match object {
+        VectorObject::Rectangle { x, y, width, height } => {
+            commands.push(RenderCommand::Rect {
+                x: *x,
+                y: *y,
+                width: *width,
+                height: *height,
+            });
+        }
+        VectorObject::Ellipse { cx, cy, rx, ry } => {
+            commands.push(RenderCommand::Ellipse {
+                cx: *cx,
+                cy: *cy,
+                rx: *rx,
+                ry: *ry,
+            });
+        }
+        VectorObject::Circle { cx, cy, r } => {
+            commands.push(RenderCommand::Ellipse {
+                cx: *cx,
+                cy: *cy,
+                rx: *r,
+                ry: *r,
+            });
+        }
+        VectorObject::Path { commands: path_commands, is_closed, .. } => {
+            push_path_commands(commands, path_commands, *is_closed);
+        }
+        // Replaced by its outline path above
+        VectorObject::Text(_) => {}
+    }
+
+    // Fill and stroke
+    if style.fill_color.is_some() {
+        commands.push(RenderCommand::Fill { rule: style.fill_rule.name().to_string() });
+    }
+    match (&style.stroke_color, stroke::aligned_stroke_outline(object, style)) {
+        // Inside and outside strokes are drawn as filled outlines
+        (Some(color), Some(outline)) => {
+            commands.push(RenderCommand::SetFillStyle { color: color.clone() });
+            commands.push(RenderCommand::BeginPath);
+            for polygon in &outline {
+                for (i, &(x, y)) in polygon.iter().enumerate() {
+                    commands.push(if i == 0 { RenderCommand::MoveTo { x, y } } else { RenderCommand::LineTo { x, y } });
+                }
+                commands.push(RenderCommand::ClosePath);
+            }
+            commands.push(RenderCommand::Fill { rule: FillRule::NonZero.name().to_string() });
+        }
+        (Some(_), None) => commands.push(RenderCommand::Stroke),
+        (None, _) => {}
+    }
+
+    // Color glyphs are painted over the text in their own colors
+    for layer in color_layers {
+        let Some(color) = layer.color.as_ref().or(style.fill_color.as_ref()) else {
+            continue;
+        };
+        commands.push(RenderCommand::SetFillStyle { color: color.clone() });
         commands.push(RenderCommand::BeginPath);
+        push_path_commands(commands, &layer.commands, true);
+        commands.push(RenderCommand::Fill { rule: FillRule::NonZero.name().to_string() });
+    }
+
+    // Reset transform (and opacity) for next object
+    commands.push(RenderCommand::ResetTransform);
+    if translucent {
+        commands.push(RenderCommand::SetGlobalAlpha { alpha: 1.0 });
+    }
+}
 
-        // Draw shape
-        match object {
-            VectorObject::Rectangle { x, y, width, height } => {
-                commands.push(RenderCommand::Rect {
+/// Canvas commands tracing path commands (after BeginPath)
+fn push_path_commands(commands: &mut Vec<RenderCommand>, path_commands: &[PathCommand], is_closed: bool) {
+    let mut start = (0.0, 0.0);
+    let mut current = (0.0, 0.0);
+    for cmd in path_commands {
+        match cmd {
+            PathCommand::MoveTo { x, y } => {
+                commands.push(RenderCommand::MoveTo { x: *x, y: *y });
+                start = (*x, *y);
+            }
+            PathCommand::LineTo { x, y } => {
+                commands.push(RenderCommand::LineTo { x: *x, y: *y });
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                commands.push(RenderCommand::BezierCurveTo {
+                    cp1x: *x1,
+                    cp1y: *y1,
+                    cp2x: *x2,
+                    cp2y: *y2,
                     x: *x,
                     y: *y,
-                    width: *width,
-                    height: *height,
                 });
             }
-            VectorObject::Ellipse { cx, cy, rx, ry } => {
-                commands.push(RenderCommand::Ellipse {
-                    cx: *cx,
-                    cy: *cy,
-                    rx: *rx,
-                    ry: *ry,
-                });
+            PathCommand::QuadTo { x1, y1, x, y } => {
+                commands.push(RenderCommand::QuadraticCurveTo { cpx: *x1, cpy: *y1, x: *x, y: *y });
             }
-            VectorObject::Path { commands: path_commands, is_closed } => {
-                for cmd in path_commands {
-                    match cmd {
-                        crate::core::scene::PathCommand::MoveTo { x, y } => {
-                            commands.push(RenderCommand::MoveTo { x: *x, y: *y });
-                        }
-                        crate::core::scene::PathCommand::LineTo { x, y } => {
-                            commands.push(RenderCommand::LineTo { x: *x, y: *y });
-                        }
-                        crate::core::scene::PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
-                            commands.push(RenderCommand::BezierCurveTo {
-                                cp1x: *x1,
-                                cp1y: *y1,
-                                cp2x: *x2,
-                                cp2y: *y2,
-                                x: *x,
-                                y: *y,
-                            });
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+                // Canvas has no endpoint-form arc, so draw its cubic form
+                for curve in geometry::arc_to_curves(current, (*rx, *ry), *rotation, *large_arc, *sweep, (*x, *y)) {
+                    commands.push(match curve {
+                        PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                            RenderCommand::BezierCurveTo { cp1x: x1, cp1y: y1, cp2x: x2, cp2y: y2, x, y }
                         }
-                        crate::core::scene::PathCommand::ClosePath => {
-                            // Only add ClosePath if is_closed is true
-                            if *is_closed {
-                                commands.push(RenderCommand::ClosePath);
-                            }
-                        }
-                    }
+                        // Zero radii give a straight line
+                        _ => RenderCommand::LineTo { x: *x, y: *y },
+                    });
                 }
             }
+            PathCommand::ClosePath => {
+                // Only add ClosePath if is_closed is true
+                if is_closed {
+                    commands.push(RenderCommand::ClosePath);
+                }
+                current = start;
+            }
+        }
+        if let Some(end) = cmd.end_point() {
+            current = end;
         }
+    }
+}
 
-        // Fill and stroke
-        if style.fill_color.is_some() {
-            commands.push(RenderCommand::Fill);
+/// Copy of an object with its points moved onto the device pixel grid
+/// Outlines land on pixel centers when the stroke covers an odd number of pixels and on
+/// pixel edges otherwise, so strokes and fills cover whole pixels. Only possible for
+/// transforms without rotation or skew; returns None for others.
+fn pixel_aligned(object: &VectorObject, transform: &TransformMatrix, stroke_width: f64) -> Option<VectorObject> {
+    if transform.b != 0.0 || transform.c != 0.0 || transform.a == 0.0 || transform.d == 0.0 {
+        return None;
+    }
+    let snap = |value: f64, scale: f64, offset: f64| {
+        let device = value * scale + offset;
+        let odd = (stroke_width * scale.abs()).round() as i64 % 2 == 1;
+        let snapped = if odd { (device - 0.5).round() + 0.5 } else { device.round() };
+        (snapped - offset) / scale
+    };
+    let x = |value: f64| snap(value, transform.a, transform.tx);
+    let y = |value: f64| snap(value, transform.d, transform.ty);
+
+    Some(match *object {
+        VectorObject::Rectangle { x: left, y: top, width, height } => {
+            let (x0, y0) = (x(left), y(top));
+            VectorObject::Rectangle { x: x0, y: y0, width: x(left + width) - x0, height: y(top + height) - y0 }
+        }
+        VectorObject::Ellipse { cx, cy, rx, ry } => {
+            let (x0, x1, y0, y1) = (x(cx - rx), x(cx + rx), y(cy - ry), y(cy + ry));
+            VectorObject::Ellipse { cx: (x0 + x1) / 2.0, cy: (y0 + y1) / 2.0, rx: (x1 - x0).abs() / 2.0, ry: (y1 - y0).abs() / 2.0 }
         }
-        if style.stroke_color.is_some() {
-            commands.push(RenderCommand::Stroke);
+        VectorObject::Circle { cx, cy, r } => {
+            let (x0, x1) = (x(cx - r), x(cx + r));
+            let (y0, y1) = (y(cy - r), y(cy + r));
+            VectorObject::Ellipse { cx: (x0 + x1) / 2.0, cy: (y0 + y1) / 2.0, rx: (x1 - x0).abs() / 2.0, ry: (y1 - y0).abs() / 2.0 }
         }
+        VectorObject::Path { ref commands, is_closed, ref anchor_types } => VectorObject::Path {
+            commands: commands
+                .iter()
+                .map(|cmd| match *cmd {
+                    PathCommand::MoveTo { x: px, y: py } => PathCommand::MoveTo { x: x(px), y: y(py) },
+                    PathCommand::LineTo { x: px, y: py } => PathCommand::LineTo { x: x(px), y: y(py) },
+                    PathCommand::CurveTo { x1, y1, x2, y2, x: px, y: py } => {
+                        PathCommand::CurveTo { x1: x(x1), y1: y(y1), x2: x(x2), y2: y(y2), x: x(px), y: y(py) }
+                    }
+                    PathCommand::QuadTo { x1, y1, x: px, y: py } => PathCommand::QuadTo { x1: x(x1), y1: y(y1), x: x(px), y: y(py) },
+                    // Only the end point snaps; the radii follow from it
+                    PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x: px, y: py } => {
+                        PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x: x(px), y: y(py) }
+                    }
+                    PathCommand::ClosePath => PathCommand::ClosePath,
+                })
+                .collect(),
+            is_closed,
+            anchor_types: anchor_types.clone(),
+        },
+        VectorObject::Text(_) => return None,
+    })
+}
 
-        // Reset transform for next object
-        commands.push(RenderCommand::ResetTransform);
+/// Options controlling how numbers are written in exported documents
+/// Numbers always use `.` as the decimal separator and never use exponent
+/// notation, regardless of the user's locale, as SVG and PDF consumers require.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// Maximum number of fractional digits (None = shortest exact representation)
+    pub precision: Option<usize>,
+}
+
+/// Page options for a single SVG export
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SvgPageOptions {
+    /// Emit the document background (false = transparent background)
+    pub background: bool,
+    /// Fit the viewBox to the drawing instead of the document's canvas
+    pub crop: bool,
+    /// Extra space around the content when cropping
+    pub padding: f64,
+}
+
+impl Default for SvgPageOptions {
+    fn default() -> Self {
+        SvgPageOptions { background: true, crop: false, padding: 0.0 }
     }
+}
 
-    commands
+/// Format a number for SVG/PDF output
+/// Locale-independent, no exponent notation, no "-0", trailing zeros trimmed.
+/// Non-finite values are written as 0 so the document stays parseable.
+pub fn format_number(value: f64, precision: Option<usize>) -> String {
+    if !value.is_finite() {
+        return "0".to_string();
+    }
+    // Rust's float formatting is locale-independent and never emits an exponent
+    let mut text = match precision {
+        Some(digits) => {
+            let fixed = format!("{:.*}", digits, value);
+            if fixed.contains('.') {
+                fixed.trim_end_matches('0').trim_end_matches('.').to_string()
+            } else {
+                fixed
+            }
+        }
+        None => format!("{}", value),
+    };
+    if text == "-0" {
+        text = "0".to_string();
+    }
+    text
 }
 
 /// Generate SVG string from the scene graph
-pub fn generate_svg(scene: &SceneGraph, width: u32, height: u32) -> String {
+pub fn generate_svg(scene: &SceneGraph, width: u32, height: u32, options: &ExportOptions) -> String {
+    let view_box = BoundingBox::from_rect(0.0, 0.0, width as f64, height as f64);
+    generate_svg_with_view_box(scene, &view_box, options)
+}
+
+/// Generate SVG string with the viewBox cropped to the visible content plus padding
+/// Falls back to an empty 0x0 document when the scene has no content.
+pub fn generate_svg_cropped(scene: &SceneGraph, padding: f64, options: &ExportOptions) -> String {
+    generate_svg_with_view_box(scene, &cropped_view_box(scene, padding), options)
+}
+
+/// Generate SVG string for the document's page, with page options (background, cropping)
+pub fn generate_svg_with_page(scene: &SceneGraph, page: &SvgPageOptions, options: &ExportOptions) -> String {
+    let view_box = if page.crop {
+        cropped_view_box(scene, page.padding)
+    } else {
+        BoundingBox::from_rect(0.0, 0.0, scene.document.width, scene.document.height)
+    };
+    write_svg_document(scene, &view_box, page.background, options)
+}
+
+/// Visible content bounds plus padding, or an empty box for an empty scene
+fn cropped_view_box(scene: &SceneGraph, padding: f64) -> BoundingBox {
+    let padding = if padding.is_finite() { padding.max(0.0) } else { 0.0 };
+    geometry::scene_visual_bounds(scene)
+        .map(|bounds| bounds.expand(padding))
+        .unwrap_or_else(|| BoundingBox::new(0.0, 0.0, 0.0, 0.0))
+}
+
+/// Generate SVG string for an arbitrary world-space view box
+pub fn generate_svg_with_view_box(scene: &SceneGraph, view_box: &BoundingBox, options: &ExportOptions) -> String {
+    write_svg_document(scene, view_box, true, options)
+}
+
+fn write_svg_document(scene: &SceneGraph, view_box: &BoundingBox, background: bool, options: &ExportOptions) -> String {
     let mut svg = String::new();
+    let (width, height) = (view_box.width(), view_box.height());
+    let n = |value: f64| format_number(value, options.precision);
     
     // SVG header
     svg.push_str(&format!(
         r#"<?xml version="1.0" encoding="UTF-8"?>
-<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 {} {}" width="{}" height="{}">
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}" color-interpolation="{}">
 "#,
-        width, height, width, height
+        n(view_box.min_x), n(view_box.min_y), n(width), n(height), n(width), n(height),
+        scene.color_space.svg_color_interpolation()
     ));
     
-    // Background
+    // Background (documents without one stay transparent)
+    if let Some(color) = scene.document.background.as_deref().filter(|_| background) {
+        svg.push_str(&format!(
+            "  <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+            n(view_box.min_x), n(view_box.min_y), n(width), n(height), escape_xml(color)
+        ));
+    }
+    
+    // Export the scene graph, groups and instances as nested <g> elements
+    for root in &scene.roots {
+        write_svg_node(&mut svg, scene, root, None, 1, true, &n);
+    }
+    
+    // Close SVG
+    svg.push_str("</svg>\n");
+    
+    svg
+}
+
+/// Write a node and its subtree as SVG elements with transforms relative to the parent
+/// Leaves inside instances share IDs across instances, so everything drawn through an
+/// instance gets the instance's ID as a prefix. Inside pattern tiles `patterns` is off,
+/// as on the canvas.
+fn write_svg_node(
+    svg: &mut String,
+    scene: &SceneGraph,
+    node: &SceneNode,
+    prefix: Option<&str>,
+    depth: usize,
+    patterns: bool,
+    n: &dyn Fn(f64) -> String,
+) {
+    let indent = "  ".repeat(depth);
+    let id = match prefix {
+        Some(prefix) => format!("{}-{}", prefix, node.id()),
+        None => node.id().clone(),
+    };
+    let transform = node.transform();
+    let transform_attr = format!(
+        "matrix({},{},{},{},{},{})",
+        n(transform.a), n(transform.c), n(transform.b), n(transform.d), n(transform.tx), n(transform.ty)
+    );
+
+    // Identity attributes (object ID and optional user-facing name)
+    let mut id_attr = format!("id=\"{}\"", escape_xml(&id));
+    if let Some(name) = node.name() {
+        id_attr.push_str(&format!(" data-name=\"{}\"", escape_xml(name)));
+    }
+
+    let (children, prefix) = match node {
+        SceneNode::Leaf { object, style, .. } => {
+            let pattern = style.fill_pattern.as_ref().filter(|_| patterns && style.fill_color.is_some());
+            match pattern.and_then(|pattern| write_svg_pattern(svg, scene, pattern, &id, depth, n)) {
+                Some(fill) => {
+                    let patterned = ObjectStyle { fill_color: Some(fill), ..style.clone() };
+                    write_svg_leaf(svg, &indent, id_attr, object, &transform_attr, &patterned, n);
+                }
+                None => write_svg_leaf(svg, &indent, id_attr, object, &transform_attr, style, n),
+            }
+            return;
+        }
+        SceneNode::Group { children, .. } => (children.as_slice(), prefix),
+        SceneNode::Instance { symbol_id, .. } => match scene.get_symbol(symbol_id) {
+            Some(symbol) => (symbol.children.as_slice(), Some(id.as_str())),
+            None => return,
+        },
+    };
+    svg.push_str(&format!("{}<g {} transform=\"{}\">\n", indent, id_attr, transform_attr));
+    for child in children {
+        write_svg_node(svg, scene, child, prefix, depth + 1, patterns, n);
+    }
+    svg.push_str(&format!("{}</g>\n", indent));
+}
+
+/// Write a `<pattern>` definition for an object's pattern fill
+/// Returns the fill value referencing it, or None if the pattern does not resolve.
+fn write_svg_pattern(
+    svg: &mut String,
+    scene: &SceneGraph,
+    pattern: &PatternFill,
+    id: &str,
+    depth: usize,
+    n: &dyn Fn(f64) -> String,
+) -> Option<String> {
+    let tile = pattern.tile(scene)?;
+    let indent = "  ".repeat(depth);
+    let pattern_id = format!("{}-pattern", id);
+    let m = tile.transform;
+    svg.push_str(&format!("{}<defs>\n", indent));
     svg.push_str(&format!(
-        "  <rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"#1e1e1e\"/>\n",
-        width, height
+        "{}  <pattern id=\"{}\" patternUnits=\"userSpaceOnUse\" width=\"{}\" height=\"{}\" patternTransform=\"matrix({},{},{},{},{},{})\">\n",
+        indent, escape_xml(&pattern_id), n(tile.width), n(tile.height),
+        n(m.a), n(m.c), n(m.b), n(m.d), n(m.tx), n(m.ty)
     ));
+    match tile.content {
+        TileContent::Nodes { nodes, origin } => {
+            svg.push_str(&format!("{}    <g transform=\"translate({},{})\">\n", indent, n(-origin.0), n(-origin.1)));
+            for node in nodes {
+                write_svg_node(svg, scene, node, Some(&pattern_id), depth + 3, false, n);
+            }
+            svg.push_str(&format!("{}    </g>\n", indent));
+        }
+        TileContent::Image { href, width, height } => {
+            svg.push_str(&format!(
+                "{}    <image href=\"{}\" width=\"{}\" height=\"{}\" preserveAspectRatio=\"none\"/>\n",
+                indent, escape_xml(href), n(width), n(height)
+            ));
+        }
+    }
+    svg.push_str(&format!("{}  </pattern>\n{}</defs>\n", indent, indent));
+    Some(format!("url(#{})", escape_xml(&pattern_id)))
+}
+
+/// Write a leaf object as an SVG shape element
+fn write_svg_leaf(
+    svg: &mut String,
+    indent: &str,
+    mut id_attr: String,
+    object: &VectorObject,
+    transform_attr: &str,
+    style: &ObjectStyle,
+    n: &dyn Fn(f64) -> String,
+) {
+    // Rendering hint rides along with the identity attributes
+    if style.shape_rendering == ShapeRendering::CrispEdges {
+        id_attr.push_str(" shape-rendering=\"crispEdges\"");
+    }
+    // Text is written as its outlines, labelled with the text for screen readers and search
+    if let VectorObject::Text(text) = object {
+        id_attr.push_str(&format!(" aria-label=\"{}\"", escape_xml(&text.content)));
+    }
+    let color_layers = object.color_layers();
+    let drawable = object.styled_geometry();
+    let object = drawable.as_ref();
+
+    // Build style attributes (inside and outside strokes follow the shape as filled outlines)
+    let aligned_stroke = stroke::aligned_stroke_outline(object, style);
+    let fill = style.fill_color.clone().unwrap_or_else(|| "none".to_string());
+    let stroke = match &style.stroke_color {
+        Some(color) if aligned_stroke.is_none() => color.clone(),
+        _ => "none".to_string(),
+    };
+    let stroke_width = n(style.stroke_width);
+    let mut line_attrs = String::new();
+    if style.line_cap != LineCap::Butt {
+        line_attrs.push_str(&format!(" stroke-linecap=\"{}\"", style.line_cap.name()));
+    }
+    if style.line_join != LineJoin::Miter {
+        line_attrs.push_str(&format!(" stroke-linejoin=\"{}\"", style.line_join.name()));
+    }
+    if style.fill_rule != FillRule::NonZero {
+        line_attrs.push_str(&format!(" fill-rule=\"{}\"", style.fill_rule.name()));
+    }
+    let opacity_attr = if style.opacity < 1.0 {
+        format!(" opacity=\"{}\"", n(style.opacity.max(0.0)))
+    } else {
+        String::new()
+    };
+    line_attrs.push_str(&opacity_attr);
     
-    // Export each object
-    for (object, transform, style) in scene.iter_leaves() {
-        // Build transform attribute
-        let transform_attr = format!(
-            "matrix({},{},{},{},{},{})",
-            transform.a, transform.c, transform.b, transform.d, transform.tx, transform.ty
-        );
-        
-        // Build style attributes
-        let fill = style.fill_color.as_ref()
-            .map(|c| c.clone())
-            .unwrap_or_else(|| "none".to_string());
-        let stroke = style.stroke_color.as_ref()
-            .map(|c| c.clone())
-            .unwrap_or_else(|| "none".to_string());
-        let stroke_width = style.stroke_width;
-        
-        match object {
-            VectorObject::Rectangle { x, y, width, height } => {
-                svg.push_str(&format!(
-                    r#"  <rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="{}" stroke-width="{}" transform="{}"/>
+    match object {
+        VectorObject::Rectangle { x, y, width, height } => {
+            svg.push_str(&format!(
+                r#"{}<rect {} x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="{}" stroke-width="{}"{} transform="{}"/>
+"#,
+                indent, id_attr, n(*x), n(*y), n(*width), n(*height), fill, stroke, stroke_width, line_attrs, transform_attr
+            ));
+        }
+        VectorObject::Ellipse { cx, cy, rx, ry } => {
+            svg.push_str(&format!(
+                r#"{}<ellipse {} cx="{}" cy="{}" rx="{}" ry="{}" fill="{}" stroke="{}" stroke-width="{}"{} transform="{}"/>
 "#,
-                    x, y, width, height, fill, stroke, stroke_width, transform_attr
+                indent, id_attr, n(*cx), n(*cy), n(*rx), n(*ry), fill, stroke, stroke_width, line_attrs, transform_attr
+            ));
+        }
+        VectorObject::Circle { cx, cy, r } => {
+            svg.push_str(&format!(
+                r#"{}<circle {} cx="{}" cy="{}" r="{}" fill="{}" stroke="{}" stroke-width="{}"{} transform="{}"/>
+"#,
+                indent, id_attr, n(*cx), n(*cy), n(*r), fill, stroke, stroke_width, line_attrs, transform_attr
+            ));
+        }
+        VectorObject::Path { commands: path_commands, is_closed, .. } => {
+            let d = svg_path_data(path_commands, *is_closed, n);
+            svg.push_str(&format!(
+                r#"{}<path {} d="{}" fill="{}" stroke="{}" stroke-width="{}"{} transform="{}"/>
+"#,
+                indent, id_attr, d, fill, stroke, stroke_width, line_attrs, transform_attr
+            ));
+        }
+        // Replaced by its outline path above
+        VectorObject::Text(_) => {}
+    }
+
+    // The aligned stroke follows its shape, without an ID of its own
+    if let (Some(outline), Some(color)) = (aligned_stroke, &style.stroke_color) {
+        let d = path_ops::polygons_to_commands(&outline)
+            .iter()
+            .map(|command| match command {
+                PathCommand::MoveTo { x, y } => format!("M{},{}", n(*x), n(*y)),
+                PathCommand::LineTo { x, y } => format!("L{},{}", n(*x), n(*y)),
+                _ => "Z".to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join(" ");
+        svg.push_str(&format!(
+            "{}<path d=\"{}\" fill=\"{}\" data-stroke-align=\"{}\"{} transform=\"{}\"/>\n",
+            indent, d, escape_xml(color), style.stroke_align.name(), opacity_attr, transform_attr
+        ));
+    }
+
+    // Color glyph layers follow the text in their own colors
+    for layer in color_layers {
+        let Some(color) = layer.color.as_ref().or(style.fill_color.as_ref()) else {
+            continue;
+        };
+        svg.push_str(&format!(
+            "{}<path d=\"{}\" fill=\"{}\"{} transform=\"{}\"/>\n",
+            indent, svg_path_data(&layer.commands, true, n), escape_xml(color), opacity_attr, transform_attr
+        ));
+    }
+}
+
+/// SVG path data of path commands
+fn svg_path_data(path_commands: &[PathCommand], is_closed: bool, n: &dyn Fn(f64) -> String) -> String {
+    let mut d = String::new();
+    for cmd in path_commands {
+        match cmd {
+            PathCommand::MoveTo { x, y } => {
+                d.push_str(&format!("M{},{} ", n(*x), n(*y)));
+            }
+            PathCommand::LineTo { x, y } => {
+                d.push_str(&format!("L{},{} ", n(*x), n(*y)));
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                d.push_str(&format!(
+                    "C{},{} {},{} {},{} ",
+                    n(*x1), n(*y1), n(*x2), n(*y2), n(*x), n(*y)
                 ));
             }
-            VectorObject::Ellipse { cx, cy, rx, ry } => {
-                svg.push_str(&format!(
-                    r#"  <ellipse cx="{}" cy="{}" rx="{}" ry="{}" fill="{}" stroke="{}" stroke-width="{}" transform="{}"/>
-"#,
-                    cx, cy, rx, ry, fill, stroke, stroke_width, transform_attr
+            PathCommand::QuadTo { x1, y1, x, y } => {
+                d.push_str(&format!("Q{},{} {},{} ", n(*x1), n(*y1), n(*x), n(*y)));
+            }
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+                d.push_str(&format!(
+                    "A{},{} {} {} {} {},{} ",
+                    n(*rx), n(*ry), n(*rotation), u8::from(*large_arc), u8::from(*sweep), n(*x), n(*y)
                 ));
             }
-            VectorObject::Path { commands: path_commands, is_closed } => {
-                let mut d = String::new();
-                for cmd in path_commands {
-                    match cmd {
-                        crate::core::scene::PathCommand::MoveTo { x, y } => {
-                            d.push_str(&format!("M{},{} ", x, y));
-                        }
-                        crate::core::scene::PathCommand::LineTo { x, y } => {
-                            d.push_str(&format!("L{},{} ", x, y));
-                        }
-                        crate::core::scene::PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
-                            d.push_str(&format!("C{},{} {},{} {},{} ", x1, y1, x2, y2, x, y));
-                        }
-                        crate::core::scene::PathCommand::ClosePath => {
-                            // Only add Z if is_closed is true
-                            if *is_closed {
-                                d.push_str("Z ");
-                            }
-                        }
-                    }
+            PathCommand::ClosePath => {
+                // Only add Z if is_closed is true
+                if is_closed {
+                    d.push_str("Z ");
                 }
-                svg.push_str(&format!(
-                    r#"  <path d="{}" fill="{}" stroke="{}" stroke-width="{}" transform="{}"/>
-"#,
-                    d.trim(), fill, stroke, stroke_width, transform_attr
-                ));
             }
         }
     }
-    
-    // Close SVG
-    svg.push_str("</svg>\n");
-    
-    svg
+    d.trim().to_string()
+}
+
+/// Escape text for use inside an XML attribute value
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::math::TransformMatrix;
+    use crate::core::scene::{ColorLayer, Symbol, TextFont, TextObject};
 
     #[test]
     fn test_generate_rectangle_commands() {
@@ -269,4 +1046,429 @@ mod tests {
         let has_rect = commands.iter().any(|cmd| matches!(cmd, RenderCommand::Rect { .. }));
         assert!(has_rect);
     }
+
+    #[test]
+    fn test_crisp_edges_align_to_pixel_grid() {
+        let rect = VectorObject::Rectangle { x: 10.3, y: 20.0, width: 99.4, height: 50.2 };
+        let style = ObjectStyle { stroke_width: 1.0, shape_rendering: ShapeRendering::CrispEdges, ..ObjectStyle::default() };
+
+        // 1px stroke: edges move to pixel centers
+        let mut commands = Vec::new();
+        push_leaf_commands(&mut commands, &rect, &TransformMatrix::identity(), &style, None);
+        assert!(commands.contains(&RenderCommand::Rect { x: 10.5, y: 20.5, width: 99.0, height: 50.0 }));
+
+        // 2 device pixels (1 unit at 2x zoom): edges move to pixel boundaries
+        let mut commands = Vec::new();
+        push_leaf_commands(&mut commands, &rect, &TransformMatrix::scale(2.0, 2.0), &style, None);
+        assert!(commands.contains(&RenderCommand::Rect { x: 10.5, y: 20.0, width: 99.0, height: 50.0 }));
+
+        // Rotated objects are left alone
+        assert!(pixel_aligned(&rect, &TransformMatrix::rotate(0.3), 1.0).is_none());
+    }
+
+    #[test]
+    fn test_crisp_edges_svg_attribute() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id, VectorObject::Rectangle { x: 0.0, y: 0.0, width: 1.0, height: 1.0 }, TransformMatrix::identity());
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.shape_rendering = ShapeRendering::CrispEdges;
+        }
+        let svg = generate_svg(&scene, 10, 10, &ExportOptions::default());
+        assert!(svg.contains(r#"shape-rendering="crispEdges""#));
+    }
+
+    #[test]
+    fn test_cached_group_is_wrapped_and_keyed() {
+        let mut scene = SceneGraph::new();
+        scene.roots.push(SceneNode::Group {
+            id: "bg".to_string(),
+            children: vec![SceneNode::Leaf {
+                id: "obj_1".to_string(),
+                object: VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 },
+                transform: TransformMatrix::identity(),
+                style: ObjectStyle::default(),
+                name: None,
+            }],
+            transform: TransformMatrix::identity(),
+            name: None,
+            cache_as_bitmap: true,
+        });
+
+        let commands = generate_render_commands(&scene);
+        let key = match commands.first() {
+            Some(RenderCommand::BeginCache { id, key }) if id == "bg" => key.clone(),
+            other => panic!("expected BeginCache, got {:?}", other),
+        };
+        assert!(matches!(commands.last(), Some(RenderCommand::EndCache { id }) if id == "bg"));
+        assert!(commands.iter().any(|cmd| matches!(cmd, RenderCommand::Rect { .. })));
+
+        // Editing the subtree invalidates the key
+        if let SceneNode::Group { children, .. } = &mut scene.roots[0] {
+            if let SceneNode::Leaf { style, .. } = &mut children[0] {
+                style.stroke_width = 5.0;
+            }
+        }
+        let commands = generate_render_commands(&scene);
+        assert!(matches!(&commands[0], RenderCommand::BeginCache { key: new_key, .. } if *new_key != key));
+    }
+
+    #[test]
+    fn test_cached_group_key_follows_symbol_edits() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id.clone(), VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 }, TransformMatrix::identity());
+        let Some((symbol_id, instance)) = scene.create_symbol("tile", &[id]) else {
+            panic!("symbol not created");
+        };
+        let children = scene.remove_roots(&[instance]);
+        scene.roots.push(SceneNode::Group {
+            id: "bg".to_string(),
+            children,
+            transform: TransformMatrix::identity(),
+            name: None,
+            cache_as_bitmap: true,
+        });
+        let other = scene.generate_id();
+        scene.add_object(other.clone(), VectorObject::Circle { cx: 50.0, cy: 0.0, r: 5.0 }, TransformMatrix::identity());
+        let mut scene = TrackedScene::new(scene);
+        let mut cache = CommandCache::new();
+        let key = |commands: &[RenderCommand]| match commands.first() {
+            Some(RenderCommand::BeginCache { key, .. }) => key.clone(),
+            other => panic!("expected BeginCache, got {:?}", other),
+        };
+        let cached = key(&generate_render_commands_cached(&scene, &mut cache));
+        let uncached = key(&generate_render_commands(&scene));
+
+        // Editing another object keeps the bitmap
+        if let Some(SceneNode::Leaf { style, .. }) = scene.edit_node(&other) {
+            style.fill_color = Some("#ff0000".to_string());
+        }
+        assert_eq!(key(&generate_render_commands_cached(&scene, &mut cache)), cached);
+
+        // Editing the symbol the group shows invalidates it, with or without a cache
+        if let Some(symbol) = scene.edit().symbols.iter_mut().find(|symbol| symbol.id == symbol_id) {
+            if let SceneNode::Leaf { style, .. } = &mut symbol.children[0] {
+                style.fill_color = Some("#00ff00".to_string());
+            }
+        }
+        assert_ne!(key(&generate_render_commands_cached(&scene, &mut cache)), cached);
+        assert_ne!(key(&generate_render_commands(&scene)), uncached);
+    }
+
+    #[test]
+    fn test_cropped_svg_view_box_fits_content() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Rectangle { x: 100.0, y: 50.0, width: 20.0, height: 10.0 },
+            TransformMatrix::identity(),
+        );
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.stroke_width = 2.0;
+        }
+
+        // Content bounds 99..121 x 49..61 (stroke included), plus 5 padding
+        let svg = generate_svg_cropped(&scene, 5.0, &ExportOptions::default());
+        assert!(svg.contains(r#"viewBox="94 44 32 22""#));
+    }
+
+    #[test]
+    fn test_opacity_is_rendered_and_exported() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id, VectorObject::Circle { cx: 0.0, cy: 0.0, r: 5.0 }, TransformMatrix::identity());
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.opacity = 0.25;
+        }
+        let commands = generate_render_commands(&scene);
+        let alphas: Vec<f64> = commands
+            .iter()
+            .filter_map(|command| match command {
+                RenderCommand::SetGlobalAlpha { alpha } => Some(*alpha),
+                _ => None,
+            })
+            .collect();
+        // Set before drawing and restored afterwards
+        assert_eq!(alphas, vec![0.25, 1.0]);
+        assert!(matches!(commands.last(), Some(RenderCommand::SetGlobalAlpha { .. })));
+        assert!(generate_svg(&scene, 10, 10, &ExportOptions::default()).contains(r#" opacity="0.25""#));
+    }
+
+    #[test]
+    fn test_color_glyph_layers_are_painted_over_text() {
+        let square = |x: f64| {
+            vec![
+                PathCommand::MoveTo { x, y: 0.0 },
+                PathCommand::LineTo { x: x + 5.0, y: 0.0 },
+                PathCommand::LineTo { x: x + 5.0, y: 5.0 },
+                PathCommand::ClosePath,
+            ]
+        };
+        let text = TextObject {
+            content: "a😀".to_string(),
+            font: TextFont { family: "Roboto".to_string(), weight: 400, italic: false },
+            size: 10.0,
+            x: 0.0,
+            y: 0.0,
+            paragraph: Default::default(),
+            typography: Default::default(),
+            outline: square(0.0),
+            color_layers: vec![
+                ColorLayer { color: Some("#ffcc00".to_string()), commands: square(10.0) },
+                ColorLayer { color: None, commands: square(12.0) },
+            ],
+        };
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id, VectorObject::Text(Box::new(text)), TransformMatrix::identity());
+        if let Some(SceneNode::Leaf { style, .. }) = scene.roots.first_mut() {
+            style.fill_color = Some("#123456".to_string());
+        }
+
+        // The text fill, then each layer in its color (foreground layers in the text's)
+        let fills: Vec<String> = generate_render_commands(&scene)
+            .into_iter()
+            .filter_map(|command| match command {
+                RenderCommand::SetFillStyle { color } => Some(color),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(fills, vec!["#123456", "#ffcc00", "#123456"]);
+        let svg = generate_svg(&scene, 20, 20, &ExportOptions::default());
+        assert_eq!(svg.matches("<path").count(), 3);
+        assert!(svg.contains(r##"d="M10,0 L15,0 L15,5 Z" fill="#ffcc00""##));
+        // Bounds and hit testing see the layers too
+        let bounds = scene.roots.first().and_then(|node| match node {
+            SceneNode::Leaf { object, .. } => geometry::object_world_bounds(object, &TransformMatrix::identity()),
+            _ => None,
+        });
+        assert_eq!(bounds.map(|bounds| bounds.max_x), Some(17.0));
+    }
+
+    #[test]
+    fn test_svg_page_options() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Rectangle { x: 100.0, y: 50.0, width: 20.0, height: 10.0 },
+            TransformMatrix::identity(),
+        );
+        scene.document.width = 300.0;
+        scene.document.height = 200.0;
+        let options = ExportOptions::default();
+
+        let svg = generate_svg_with_page(&scene, &SvgPageOptions::default(), &options);
+        assert_eq!(svg, generate_svg(&scene, 300, 200, &options));
+
+        let transparent = SvgPageOptions { background: false, ..SvgPageOptions::default() };
+        let svg = generate_svg_with_page(&scene, &transparent, &options);
+        assert!(svg.contains(r#"viewBox="0 0 300 200""#));
+        assert!(!svg.contains("#1e1e1e"));
+
+        // The default stroke widens the content to 99..121 x 49..61
+        let cropped = SvgPageOptions { background: false, crop: true, padding: 3.0 };
+        let svg = generate_svg_with_page(&scene, &cropped, &options);
+        assert!(svg.contains(r#"viewBox="96 46 28 18""#));
+        assert!(!svg.contains("#1e1e1e"));
+
+        // The background comes from the document
+        scene.document.background = Some("#ffffff".to_string());
+        let svg = generate_svg_with_page(&scene, &SvgPageOptions::default(), &options);
+        assert!(svg.contains(r##"<rect x="0" y="0" width="300" height="200" fill="#ffffff"/>"##));
+        scene.document.background = None;
+        assert!(!generate_svg_with_page(&scene, &SvgPageOptions::default(), &options).contains("<rect x=\"0\""));
+    }
+
+    #[test]
+    fn test_svg_emits_id_and_escaped_name() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id.clone(),
+            VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 },
+            TransformMatrix::identity(),
+        );
+        if let Some(node) = scene.get_node_by_id_mut(&id) {
+            node.set_name(Some("Tom & \"Jerry\"".to_string()));
+        }
+
+        let svg = generate_svg(&scene, 100, 100, &ExportOptions::default());
+        assert!(svg.contains(r#"id="obj_1" data-name="Tom &amp; &quot;Jerry&quot;""#));
+    }
+
+    #[test]
+    fn test_svg_keeps_group_hierarchy() {
+        let leaf = |id: &str, x: f64| SceneNode::Leaf {
+            id: id.to_string(),
+            object: VectorObject::Rectangle { x, y: 0.0, width: 10.0, height: 10.0 },
+            transform: TransformMatrix::identity(),
+            style: ObjectStyle::default(),
+            name: None,
+        };
+        let mut scene = SceneGraph::new();
+        scene.symbols.push(Symbol { id: "sym_1".to_string(), name: "Box".to_string(), children: vec![leaf("obj_3", 0.0)] });
+        scene.roots.push(SceneNode::Group {
+            id: "group_1".to_string(),
+            children: vec![
+                leaf("obj_1", 5.0),
+                SceneNode::Instance {
+                    id: "inst_1".to_string(),
+                    symbol_id: "sym_1".to_string(),
+                    transform: TransformMatrix::translate(0.0, 50.0),
+                    name: None,
+                },
+            ],
+            transform: TransformMatrix::translate(100.0, 0.0),
+            name: Some("Icons".to_string()),
+            cache_as_bitmap: false,
+        });
+        scene.roots.push(leaf("obj_2", 0.0));
+
+        let svg = generate_svg(&scene, 100, 100, &ExportOptions::default());
+        let lines: Vec<&str> = svg.lines().skip(3).map(str::trim_end).collect();
+        assert!(lines[0].starts_with(r#"  <g id="group_1" data-name="Icons" transform="matrix(1,0,0,1,100,0)">"#));
+        // Children keep transforms relative to their group
+        assert!(lines[1].starts_with(r#"    <rect id="obj_1" x="5""#) && lines[1].ends_with(r#"transform="matrix(1,0,0,1,0,0)"/>"#));
+        assert!(lines[2].starts_with(r#"    <g id="inst_1" transform="matrix(1,0,0,1,0,50)">"#));
+        assert!(lines[3].starts_with(r#"      <rect id="inst_1-obj_3""#));
+        assert_eq!(&lines[4..6], ["    </g>", "  </g>"]);
+        assert!(lines[6].starts_with(r#"  <rect id="obj_2""#));
+    }
+
+    #[test]
+    fn test_format_number_is_plain_decimal() {
+        assert_eq!(format_number(1e-7, None), "0.0000001");
+        assert_eq!(format_number(1e21, None), "1000000000000000000000");
+        assert_eq!(format_number(1e-7, Some(3)), "0");
+        assert_eq!(format_number(-1e-7, Some(3)), "0");
+        assert_eq!(format_number(1.23456, Some(2)), "1.23");
+        assert_eq!(format_number(2.5, Some(0)), "2");
+        assert_eq!(format_number(10.0, Some(3)), "10");
+        assert_eq!(format_number(f64::NAN, None), "0");
+    }
+
+    #[test]
+    fn test_svg_path_data_has_no_exponent() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Path {
+                commands: vec![
+                    PathCommand::MoveTo { x: 1e-9, y: 0.0 },
+                    PathCommand::LineTo { x: 12.345678, y: -0.0 },
+                ],
+                is_closed: false,
+                anchor_types: Vec::new(),
+            },
+            TransformMatrix::identity(),
+        );
+
+        let options = ExportOptions { precision: Some(2) };
+        let svg = generate_svg(&scene, 100, 100, &options);
+        assert!(svg.contains(r#"d="M0,0 L12.35,0""#));
+    }
+
+    #[test]
+    fn test_circle_exports_circle_element() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id, VectorObject::Circle { cx: 10.0, cy: 20.0, r: 5.0 }, TransformMatrix::identity());
+
+        let svg = generate_svg(&scene, 100, 100, &ExportOptions::default());
+        assert!(svg.contains(r#"<circle id="obj_1" cx="10" cy="20" r="5""#));
+    }
+
+    #[test]
+    fn test_layer_streams_match_full_stream() {
+        let mut scene = SceneGraph::new();
+        for i in 0..3 {
+            let id = scene.generate_id();
+            scene.add_object(
+                id,
+                VectorObject::Rectangle { x: i as f64 * 10.0, y: 0.0, width: 5.0, height: 5.0 },
+                TransformMatrix::identity(),
+            );
+        }
+
+        let layers = generate_render_commands_by_layer(&scene);
+        let ids: Vec<&str> = layers.iter().map(|layer| layer.layer_id.as_str()).collect();
+        assert_eq!(ids, vec!["obj_1", "obj_2", "obj_3"]);
+
+        let concatenated: usize = layers.iter().map(|layer| layer.commands.len()).sum();
+        assert_eq!(concatenated, generate_render_commands(&scene).len());
+    }
+
+    #[test]
+    fn test_typescript_declarations_match_serialized_shapes() {
+        use crate::core::scene::ObjectStyle;
+        // Tagged by "type", optional where serde skips the field, null for None
+        assert!(RenderCommand::DECL.starts_with("export type RenderCommand = { type: \"SetTransform\"; a: number;"));
+        assert!(RenderCommand::DECL.contains("| { type: \"SetFillStyle\"; color: string } |"));
+        assert!(RenderCommand::DECL.contains("commands: RenderCommand[]; image?: PatternImage;"));
+        assert!(SelectionOverlay::DECL.contains("corners: [[number, number], [number, number], [number, number], [number, number]];"));
+        assert!(ObjectStyle::DECL.contains("fill_color: string | null;"));
+        assert!(ObjectStyle::DECL.contains("line_cap?: LineCap;"));
+    }
+
+    #[test]
+    fn test_command_cache_regenerates_only_changed_leaves() {
+        let mut scene = SceneGraph::new();
+        for i in 0..3 {
+            let id = scene.generate_id();
+            scene.add_object(
+                id,
+                VectorObject::Rectangle { x: i as f64 * 10.0, y: 0.0, width: 5.0, height: 5.0 },
+                TransformMatrix::identity(),
+            );
+        }
+        let mut scene = TrackedScene::new(scene);
+        let mut cache = CommandCache::new();
+        assert_eq!(generate_render_commands_cached(&scene, &mut cache), generate_render_commands(&scene));
+        assert_eq!((cache.stats().hits, cache.stats().misses), (0, 3));
+
+        // Moving one object and restyling another regenerates just those two
+        if let Some(transform) = scene.edit_node("obj_2").map(SceneNode::transform_mut) {
+            *transform = TransformMatrix::translate(0.0, 7.0);
+        }
+        if let Some(SceneNode::Leaf { style, .. }) = scene.edit_node("obj_3") {
+            style.fill_color = Some("#ff0000".to_string());
+        }
+        let layers = generate_render_commands_by_layer_cached(&scene, &mut cache);
+        let expected = generate_render_commands_by_layer(&scene);
+        assert!(layers.iter().zip(&expected).all(|(layer, expected)| layer.commands == expected.commands));
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (3, 1, 5));
+
+        // Removed objects leave the cache with the next frame
+        scene.edit_roots(|scene| scene.remove_roots(&["obj_1".to_string(), "obj_2".to_string()]));
+        generate_render_commands_cached(&scene, &mut cache);
+        assert_eq!(cache.stats().entries, 1);
+    }
+
+    #[test]
+    fn test_command_cache_keeps_instances_apart() {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id.clone(), VectorObject::Rectangle { x: 0.0, y: 0.0, width: 5.0, height: 5.0 }, TransformMatrix::identity());
+        let Some((_, instance)) = scene.create_symbol("tile", &[id]) else {
+            panic!("symbol not created");
+        };
+        let copies = scene.duplicate_roots(std::slice::from_ref(&instance), 10.0, 0.0);
+        let mut scene = TrackedScene::new(scene);
+        let mut cache = CommandCache::new();
+        generate_render_commands_cached(&scene, &mut cache);
+        assert_eq!((cache.stats().entries, cache.stats().misses), (2, 2));
+
+        // Moving one instance regenerates only what it draws
+        if let Some(transform) = scene.edit_node(&copies[0]).map(SceneNode::transform_mut) {
+            *transform = TransformMatrix::translate(30.0, 0.0);
+        }
+        assert_eq!(generate_render_commands_cached(&scene, &mut cache), generate_render_commands(&scene));
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.hits, stats.misses), (2, 1, 3));
+    }
 }
@@ -0,0 +1,226 @@
+//! Revision Module - Which parts of the scene changed
+//!
+//! The editor's scene can only be written through a [`TrackedScene`], which keeps a
+//! revision per node: a number that changes whenever the node, or anything drawn
+//! inside it, is edited. Render caches key their entries on revisions instead of
+//! hashing node contents every frame.
+//!
+//! An edit of particular nodes (a drag, a restyle) changes the revisions of those
+//! nodes, their ancestors and their contents; adding, removing or reordering
+//! top-level nodes changes only the revisions of the nodes added and removed. Any other edit (editing symbols, replacing the scene) changes
+//! every revision.
+
+use std::collections::{HashMap, HashSet};
+use std::mem;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::core::scene::{ObjectId, SceneGraph, SceneNode};
+
+/// Source of revisions; never hands out the same one twice, so revisions of different
+/// scenes or of different states of one node are always distinct
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+fn next_revision() -> u64 {
+    NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Revisions of the nodes of a scene
+#[derive(Debug, Clone)]
+pub struct Revisions {
+    /// Revision of every node not edited on its own since the scene last changed as a whole
+    epoch: u64,
+    nodes: HashMap<ObjectId, u64>,
+}
+
+impl Revisions {
+    fn new() -> Self {
+        Revisions { epoch: next_revision(), nodes: HashMap::new() }
+    }
+
+    /// Revision of a node; equal revisions mean it is unchanged
+    pub fn of(&self, id: &str) -> u64 {
+        self.nodes.get(id).copied().unwrap_or(self.epoch)
+    }
+}
+
+/// A scene that records which of its nodes each edit changes
+/// Reads go through `Deref`; writes through one of the `edit` methods, each for
+/// edits of a certain extent.
+#[derive(Debug, Clone)]
+pub struct TrackedScene {
+    scene: SceneGraph,
+    revisions: Revisions,
+}
+
+impl TrackedScene {
+    pub fn new(scene: SceneGraph) -> Self {
+        TrackedScene { scene, revisions: Revisions::new() }
+    }
+
+    pub fn revisions(&self) -> &Revisions {
+        &self.revisions
+    }
+
+    /// Write access for an edit of any extent: every revision changes
+    pub fn edit(&mut self) -> &mut SceneGraph {
+        self.revisions = Revisions::new();
+        &mut self.scene
+    }
+
+    /// Swap in another scene, returning the current one
+    pub fn replace(&mut self, scene: SceneGraph) -> SceneGraph {
+        mem::replace(self.edit(), scene)
+    }
+
+    /// Write access for an edit of the given nodes (at any depth) and their contents only
+    /// The caller must leave every other node, symbols included, as it is.
+    pub fn edit_nodes(&mut self, ids: &[ObjectId]) -> &mut SceneGraph {
+        let mut changed = Vec::new();
+        for id in ids {
+            collect_path(&self.scene.roots, id, &mut changed);
+        }
+        self.changed(changed);
+        &mut self.scene
+    }
+
+    /// Add, remove or reorder top-level nodes, leaving the nodes kept as they are
+    /// Only the revisions of the nodes added and removed change.
+    pub fn edit_roots<T>(&mut self, edit: impl FnOnce(&mut SceneGraph) -> T) -> T {
+        let before = node_ids(&self.scene.roots);
+        let result = edit(&mut self.scene);
+        let after = node_ids(&self.scene.roots);
+        self.changed(before.symmetric_difference(&after).cloned().collect());
+        result
+    }
+
+    /// Write access for an edit that changes no node or symbol (document settings,
+    /// guides, artboards, swatches, shared style definitions), so no revision changes
+    pub fn edit_settings(&mut self) -> &mut SceneGraph {
+        &mut self.scene
+    }
+
+    /// Write access to one node, for an edit of it and its contents only
+    pub fn edit_node(&mut self, id: &str) -> Option<&mut SceneNode> {
+        self.edit_nodes(&[id.to_string()]).get_node_by_id_mut(id)
+    }
+
+    /// Give nodes a new revision
+    fn changed(&mut self, ids: Vec<ObjectId>) {
+        let revision = next_revision();
+        for id in ids {
+            self.revisions.nodes.insert(id, revision);
+        }
+    }
+
+    /// Generate a unique object ID (changes no revision)
+    pub fn generate_id(&mut self) -> ObjectId {
+        self.scene.generate_id()
+    }
+}
+
+impl Deref for TrackedScene {
+    type Target = SceneGraph;
+
+    fn deref(&self) -> &SceneGraph {
+        &self.scene
+    }
+}
+
+/// Push the IDs of a node, its contents and its ancestors; false if it is not in `nodes`
+fn collect_path(nodes: &[SceneNode], id: &str, path: &mut Vec<ObjectId>) -> bool {
+    for node in nodes {
+        if node.id() == id {
+            collect_contents(node, path);
+            return true;
+        }
+        if let SceneNode::Group { id: group_id, children, .. } = node {
+            if collect_path(children, id, path) {
+                path.push(group_id.clone());
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// Push the IDs of a node and the nodes inside it
+/// Symbol contents are left out: what an instance draws is covered by its own revision.
+fn collect_contents(node: &SceneNode, ids: &mut Vec<ObjectId>) {
+    ids.push(node.id().clone());
+    if let SceneNode::Group { children, .. } = node {
+        for child in children {
+            collect_contents(child, ids);
+        }
+    }
+}
+
+fn node_ids(nodes: &[SceneNode]) -> HashSet<ObjectId> {
+    let mut ids = Vec::new();
+    for node in nodes {
+        collect_contents(node, &mut ids);
+    }
+    ids.into_iter().collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+    use crate::core::scene::{ObjectStyle, VectorObject};
+
+    fn leaf(id: &str) -> SceneNode {
+        SceneNode::Leaf {
+            id: id.to_string(),
+            object: VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 },
+            transform: TransformMatrix::identity(),
+            style: ObjectStyle::default(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_node_edits_change_the_node_its_ancestors_and_contents() {
+        let mut scene = SceneGraph::new();
+        scene.roots.push(SceneNode::Group {
+            id: "outer".to_string(),
+            children: vec![
+                SceneNode::Group {
+                    id: "inner".to_string(),
+                    children: vec![leaf("a")],
+                    transform: TransformMatrix::identity(),
+                    name: None,
+                    cache_as_bitmap: false,
+                },
+                leaf("b"),
+            ],
+            transform: TransformMatrix::identity(),
+            name: None,
+            cache_as_bitmap: false,
+        });
+        scene.roots.push(leaf("c"));
+        let mut scene = TrackedScene::new(scene);
+        let ids = ["outer", "inner", "a", "b", "c"];
+        let before: Vec<u64> = ids.iter().map(|id| scene.revisions().of(id)).collect();
+
+        if let Some(node) = scene.edit_node("inner") {
+            *node.transform_mut() = TransformMatrix::translate(1.0, 0.0);
+        }
+        let after: Vec<u64> = ids.iter().map(|id| scene.revisions().of(id)).collect();
+        let changed: Vec<bool> = before.iter().zip(&after).map(|(before, after)| before != after).collect();
+        assert_eq!(changed, vec![true, true, true, false, false]);
+
+        // Adding a node changes only its revision
+        let added = scene.edit_roots(|scene| {
+            let id = scene.generate_id();
+            scene.add_object(id.clone(), VectorObject::Circle { cx: 0.0, cy: 0.0, r: 1.0 }, TransformMatrix::identity());
+            id
+        });
+        assert!(ids.iter().zip(&after).all(|(id, revision)| scene.revisions().of(id) == *revision));
+        assert_ne!(scene.revisions().of(&added), scene.revisions().of("c"));
+
+        // Other edits change everything
+        scene.edit();
+        assert!(ids.iter().zip(&after).all(|(id, revision)| scene.revisions().of(id) != *revision));
+    }
+}
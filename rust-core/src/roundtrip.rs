@@ -0,0 +1,278 @@
+//! Roundtrip Module - Format fidelity checks
+//!
+//! Compares a scene with the scene obtained by exporting it and importing the
+//! result again. Objects are matched by ID; geometry is compared on world-space
+//! outlines, styles field by field and text by its content, font and format,
+//! numbers within a tolerance.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{ObjectStyle, PathCommand, SceneGraph, VectorObject};
+
+/// Tolerance used when exports are written at full precision
+pub const DEFAULT_TOLERANCE: f64 = 1e-6;
+
+/// Formats that can be exported and imported again, so checked
+pub const CHECKED_FORMATS: &[&str] = &["json"];
+
+/// Formats that can be exported but have no importer, so cannot be checked
+pub const UNCHECKED_FORMATS: &[&str] = &["svg", "binary"];
+
+/// One mismatch between the original and the re-imported scene
+#[derive(Debug, Clone, Serialize)]
+pub struct Difference {
+    pub id: String,
+    /// "missing" | "extra" | "geometry" | "fill" | "stroke" | "stroke_width" | "opacity" |
+    /// "fill_rule" | "fill_pattern" | "stroke_align" | "line_cap" | "line_join" |
+    /// "shape_rendering" | "shared_style" | "text" | "font" | "text_format"
+    pub field: &'static str,
+    pub detail: String,
+}
+
+/// Result of a roundtrip check
+#[derive(Debug, Clone, Serialize)]
+pub struct FidelityReport {
+    pub format: String,
+    /// Whether the format can be both exported and imported
+    pub supported: bool,
+    /// Formats a roundtrip check supports, and those it cannot check for want of an importer
+    pub checked_formats: &'static [&'static str],
+    pub unchecked_formats: &'static [&'static str],
+    pub passed: bool,
+    pub objects_compared: usize,
+    /// Largest coordinate deviation found between matching outlines
+    pub max_deviation: f64,
+    pub tolerance: f64,
+    pub differences: Vec<Difference>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl FidelityReport {
+    /// Report for a format that cannot be checked (no importer, export failure, ...)
+    pub fn failed(format: &str, supported: bool, tolerance: f64, error: String) -> Self {
+        FidelityReport {
+            format: format.to_string(),
+            supported,
+            checked_formats: CHECKED_FORMATS,
+            unchecked_formats: UNCHECKED_FORMATS,
+            passed: false,
+            objects_compared: 0,
+            max_deviation: 0.0,
+            tolerance,
+            differences: Vec::new(),
+            error: Some(error),
+        }
+    }
+}
+
+/// Compare two scenes leaf by leaf
+pub fn compare_scenes(format: &str, original: &SceneGraph, restored: &SceneGraph, tolerance: f64) -> FidelityReport {
+    let mut differences = Vec::new();
+    let mut max_deviation: f64 = 0.0;
+    let mut objects_compared = 0;
+
+    // Leaves inside instances share IDs, so key them by their top-level node too
+    let restored_leaves: HashMap<(&str, &str), _> = restored
+        .iter_leaf_views()
+        .into_iter()
+        .map(|leaf| ((leaf.root_id.as_str(), leaf.id.as_str()), leaf))
+        .collect();
+
+    for leaf in original.iter_leaf_views() {
+        let key = (leaf.root_id.as_str(), leaf.id.as_str());
+        let Some(other) = restored_leaves.get(&key) else {
+            differences.push(Difference { id: leaf.id.clone(), field: "missing", detail: "not found after import".into() });
+            continue;
+        };
+        objects_compared += 1;
+
+        let expected = world_outline(leaf.object, &leaf.world_transform);
+        let actual = world_outline(other.object, &other.world_transform);
+        if expected.len() != actual.len() {
+            differences.push(Difference {
+                id: leaf.id.clone(),
+                field: "geometry",
+                detail: format!("outline has {} points, expected {}", actual.len(), expected.len()),
+            });
+        } else {
+            let deviation = expected
+                .iter()
+                .zip(&actual)
+                .map(|(a, b)| (a.0 - b.0).abs().max((a.1 - b.1).abs()))
+                .fold(0.0, f64::max);
+            max_deviation = max_deviation.max(deviation);
+            if deviation > tolerance {
+                differences.push(Difference {
+                    id: leaf.id.clone(),
+                    field: "geometry",
+                    detail: format!("points deviate by up to {}", deviation),
+                });
+            }
+        }
+
+        compare_styles(leaf.id, leaf.style, other.style, tolerance, &mut differences);
+        if let (VectorObject::Text(text), VectorObject::Text(other)) = (leaf.object, other.object) {
+            differences.extend(mismatch(leaf.id, "text", &text.content, &other.content));
+            differences.extend(mismatch(leaf.id, "font", &text.font, &other.font));
+            let format = |text: &crate::core::scene::TextObject| (text.size, text.paragraph.clone(), text.typography.clone());
+            differences.extend(mismatch(leaf.id, "text_format", &format(text), &format(other)));
+        } else if matches!(leaf.object, VectorObject::Text(_)) != matches!(other.object, VectorObject::Text(_)) {
+            differences.push(Difference { id: leaf.id.clone(), field: "text", detail: "text became a shape or a shape text".into() });
+        }
+    }
+
+    if objects_compared < restored_leaves.len() {
+        let original_keys: Vec<(String, String)> = original
+            .iter_leaf_views()
+            .into_iter()
+            .map(|leaf| (leaf.root_id.clone(), leaf.id.clone()))
+            .collect();
+        for (root_id, id) in restored_leaves.keys() {
+            if !original_keys.iter().any(|(r, i)| r == root_id && i == id) {
+                differences.push(Difference { id: id.to_string(), field: "extra", detail: "not in the original".into() });
+            }
+        }
+    }
+
+    FidelityReport {
+        format: format.to_string(),
+        supported: true,
+        checked_formats: CHECKED_FORMATS,
+        unchecked_formats: UNCHECKED_FORMATS,
+        passed: differences.is_empty(),
+        objects_compared,
+        max_deviation,
+        tolerance,
+        differences,
+        error: None,
+    }
+}
+
+/// Compare every field of two styles, numbers within the tolerance
+fn compare_styles(id: &str, style: &ObjectStyle, other: &ObjectStyle, tolerance: f64, differences: &mut Vec<Difference>) {
+    let numbers = [("stroke_width", style.stroke_width, other.stroke_width), ("opacity", style.opacity, other.opacity)];
+    for (field, expected, actual) in numbers {
+        if (expected - actual).abs() > tolerance {
+            differences.push(Difference { id: id.to_string(), field, detail: format!("{} became {}", expected, actual) });
+        }
+    }
+    differences.extend(
+        [
+            mismatch(id, "fill", &style.fill_color, &other.fill_color),
+            mismatch(id, "stroke", &style.stroke_color, &other.stroke_color),
+            mismatch(id, "fill_rule", &style.fill_rule, &other.fill_rule),
+            mismatch(id, "fill_pattern", &style.fill_pattern, &other.fill_pattern),
+            mismatch(id, "stroke_align", &style.stroke_align, &other.stroke_align),
+            mismatch(id, "line_cap", &style.line_cap, &other.line_cap),
+            mismatch(id, "line_join", &style.line_join, &other.line_join),
+            mismatch(id, "shape_rendering", &style.shape_rendering, &other.shape_rendering),
+            mismatch(id, "shared_style", &style.shared_style, &other.shared_style),
+        ]
+        .into_iter()
+        .flatten(),
+    );
+}
+
+/// Difference for a field whose value changed
+fn mismatch<T: PartialEq + Debug>(id: &str, field: &'static str, expected: &T, actual: &T) -> Option<Difference> {
+    (expected != actual).then(|| Difference { id: id.to_string(), field, detail: format!("{:?} became {:?}", expected, actual) })
+}
+
+/// Outline points of an object in world space (anchors and control points)
+fn world_outline(object: &VectorObject, transform: &TransformMatrix) -> Vec<(f64, f64)> {
+    let commands = geometry::transform_path_commands(&geometry::object_to_path_commands(object), transform);
+    let mut points = Vec::with_capacity(commands.len());
+    for cmd in geometry::to_cubic_commands(&commands) {
+        match cmd {
+            PathCommand::MoveTo { x, y } | PathCommand::LineTo { x, y } => points.push((x, y)),
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => points.extend([(x1, y1), (x2, y2), (x, y)]),
+            PathCommand::QuadTo { .. } | PathCommand::ArcTo { .. } | PathCommand::ClosePath => {}
+        }
+    }
+    points
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene_with_rect(x: f64) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(
+            id,
+            VectorObject::Rectangle { x, y: 0.0, width: 10.0, height: 10.0 },
+            TransformMatrix::identity(),
+        );
+        scene
+    }
+
+    #[test]
+    fn test_identical_scenes_pass() {
+        let report = compare_scenes("json", &scene_with_rect(0.0), &scene_with_rect(0.0), DEFAULT_TOLERANCE);
+        assert!(report.passed);
+        assert_eq!(report.objects_compared, 1);
+    }
+
+    #[test]
+    fn test_reports_geometry_and_missing_objects() {
+        let report = compare_scenes("svg", &scene_with_rect(0.0), &scene_with_rect(0.01), 0.001);
+        assert!(!report.passed);
+        assert_eq!(report.differences[0].field, "geometry");
+        assert!((report.max_deviation - 0.01).abs() < 1e-9);
+
+        let report = compare_scenes("svg", &scene_with_rect(0.0), &SceneGraph::new(), 0.001);
+        assert_eq!(report.differences[0].field, "missing");
+    }
+
+    fn style_mut(scene: &mut SceneGraph) -> &mut ObjectStyle {
+        match &mut scene.roots[0] {
+            crate::core::scene::SceneNode::Leaf { style, .. } => style,
+            _ => unreachable!("scene_with_rect adds a leaf"),
+        }
+    }
+
+    #[test]
+    fn test_reports_every_style_field() {
+        let original = scene_with_rect(0.0);
+        let mut restored = scene_with_rect(0.0);
+        let style = style_mut(&mut restored);
+        style.opacity = 0.5;
+        style.line_cap = crate::core::scene::LineCap::Round;
+        style.shared_style = Some("style_1".to_string());
+        let report = compare_scenes("json", &original, &restored, DEFAULT_TOLERANCE);
+        let fields: Vec<_> = report.differences.iter().map(|difference| difference.field).collect();
+        assert_eq!(fields, vec!["opacity", "line_cap", "shared_style"]);
+    }
+
+    #[test]
+    fn test_reports_text_content_and_font() {
+        let text_scene = |content: &str, family: &str| {
+            let mut scene = SceneGraph::new();
+            let id = scene.generate_id();
+            let text = crate::core::scene::TextObject {
+                content: content.to_string(),
+                font: crate::core::scene::TextFont { family: family.to_string(), weight: 400, italic: false },
+                size: 12.0,
+                x: 0.0,
+                y: 0.0,
+                paragraph: Default::default(),
+                typography: Default::default(),
+                outline: Vec::new(),
+                color_layers: Vec::new(),
+            };
+            scene.add_object(id, VectorObject::Text(Box::new(text)), TransformMatrix::identity());
+            scene
+        };
+        let report = compare_scenes("json", &text_scene("Hi", "Inter"), &text_scene("Ho", "Lato"), DEFAULT_TOLERANCE);
+        let fields: Vec<_> = report.differences.iter().map(|difference| difference.field).collect();
+        assert_eq!(fields, vec!["text", "font"]);
+        assert_eq!(report.checked_formats, CHECKED_FORMATS);
+        assert!(report.unchecked_formats.contains(&"svg"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Selection State Module - Everything the inspector shows about the selection
+//!
+//! One payload describes the selection: its IDs, combined bounds, the kinds of objects
+//! it holds and the style values they share. The editor pushes it to an observer when
+//! it changes, so panels render from it instead of asking for each piece separately.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use tsify::Tsify;
+
+use crate::core::geometry;
+use crate::core::scene::{ObjectStyle, SceneGraph, SceneNode, StrokeAlign, VectorObject};
+use crate::spatial::BoundingBox;
+
+/// The selection as the inspector sees it
+#[derive(Debug, Clone, PartialEq, Serialize, Tsify)]
+pub struct SelectionState {
+    /// Selected IDs, sorted
+    pub ids: Vec<String>,
+    /// Combined world bounds as [min_x, min_y, max_x, max_y]; null if nothing is selected
+    pub bounds: Option<[f64; 4]>,
+    /// Kind of each selected object, in the order of `ids`
+    pub types: Vec<ObjectKind>,
+    /// Style values the selected objects have in common
+    pub style: CommonStyle,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Tsify)]
+#[serde(rename_all = "lowercase")]
+pub enum ObjectKind {
+    Rectangle,
+    Ellipse,
+    Circle,
+    Path,
+    Text,
+    Group,
+    Instance,
+}
+
+/// Style of the selected objects (groups and instances have none)
+/// A value is set when every selected object has it; it is null when no selected
+/// object has a style, or when they differ, in which case its name is in `mixed`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Tsify)]
+pub struct CommonStyle {
+    pub fill: Option<String>,
+    pub stroke: Option<String>,
+    pub stroke_width: Option<f64>,
+    pub stroke_align: Option<StrokeAlign>,
+    pub opacity: Option<f64>,
+    /// Linked shared style
+    pub shared_style: Option<String>,
+    /// Names of the values that differ between the selected objects
+    pub mixed: Vec<String>,
+}
+
+/// Describe the selected nodes of a scene (unknown IDs are left out)
+pub fn selection_state(scene: &SceneGraph, selected_ids: &HashSet<String>) -> SelectionState {
+    let mut ids: Vec<&String> = selected_ids.iter().collect();
+    ids.sort();
+
+    let mut state_ids = Vec::new();
+    let mut types = Vec::new();
+    let mut styles = Vec::new();
+    let mut bounds: Option<BoundingBox> = None;
+    for id in ids {
+        let Some((node, parent_transform)) = scene.get_node_with_parent_transform(id) else {
+            continue;
+        };
+        state_ids.push(id.clone());
+        types.push(node_kind(node));
+        if let SceneNode::Leaf { style, .. } = node {
+            styles.push(style);
+        }
+        if let Some(node_bounds) = geometry::node_world_bounds(scene, node, &parent_transform) {
+            bounds = Some(bounds.map_or(node_bounds, |bounds| bounds.union(&node_bounds)));
+        }
+    }
+
+    SelectionState {
+        ids: state_ids,
+        bounds: bounds.map(|bounds| bounds.to_array()),
+        types,
+        style: common_style(&styles),
+    }
+}
+
+fn node_kind(node: &SceneNode) -> ObjectKind {
+    match node {
+        SceneNode::Leaf { object, .. } => match object {
+            VectorObject::Rectangle { .. } => ObjectKind::Rectangle,
+            VectorObject::Ellipse { .. } => ObjectKind::Ellipse,
+            VectorObject::Circle { .. } => ObjectKind::Circle,
+            VectorObject::Path { .. } => ObjectKind::Path,
+            VectorObject::Text(_) => ObjectKind::Text,
+        },
+        SceneNode::Group { .. } => ObjectKind::Group,
+        SceneNode::Instance { .. } => ObjectKind::Instance,
+    }
+}
+
+fn common_style(styles: &[&ObjectStyle]) -> CommonStyle {
+    let mut mixed = Vec::new();
+    CommonStyle {
+        fill: common(styles, "fill", &mut mixed, |style| style.fill_color.clone()).flatten(),
+        stroke: common(styles, "stroke", &mut mixed, |style| style.stroke_color.clone()).flatten(),
+        stroke_width: common(styles, "stroke_width", &mut mixed, |style| style.stroke_width),
+        stroke_align: common(styles, "stroke_align", &mut mixed, |style| style.stroke_align),
+        opacity: common(styles, "opacity", &mut mixed, |style| style.opacity),
+        shared_style: common(styles, "shared_style", &mut mixed, |style| style.shared_style.clone()).flatten(),
+        mixed,
+    }
+}
+
+/// The value every style has; None if there are no styles, or if they differ (then
+/// `name` is added to `mixed`)
+fn common<T: PartialEq>(
+    styles: &[&ObjectStyle],
+    name: &str,
+    mixed: &mut Vec<String>,
+    value: impl Fn(&ObjectStyle) -> T,
+) -> Option<T> {
+    let mut values = styles.iter().map(|style| value(style));
+    let first = values.next()?;
+    if values.all(|other| other == first) {
+        Some(first)
+    } else {
+        mixed.push(name.to_string());
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::math::TransformMatrix;
+
+    fn rect(scene: &mut SceneGraph, x: f64, fill: Option<&str>) -> String {
+        let id = scene.generate_id();
+        scene.add_object(id.clone(), VectorObject::Rectangle { x, y: 0.0, width: 10.0, height: 10.0 }, TransformMatrix::identity());
+        if let Some(SceneNode::Leaf { style, .. }) = scene.get_node_by_id_mut(&id) {
+            style.fill_color = fill.map(str::to_string);
+        }
+        id
+    }
+
+    #[test]
+    fn test_state_of_an_empty_selection() {
+        let state = selection_state(&SceneGraph::new(), &HashSet::from(["missing".to_string()]));
+        assert!(state.ids.is_empty() && state.types.is_empty());
+        assert_eq!(state.bounds, None);
+        assert_eq!(state.style, CommonStyle::default());
+    }
+
+    #[test]
+    fn test_shared_and_mixed_style_values() {
+        let mut scene = SceneGraph::new();
+        let a = rect(&mut scene, 0.0, Some("#ff0000"));
+        let b = rect(&mut scene, 20.0, None);
+        let state = selection_state(&scene, &HashSet::from([b.clone(), a.clone()]));
+        assert_eq!(state.ids, vec![a.clone(), b.clone()]);
+        assert_eq!(state.types, vec![ObjectKind::Rectangle, ObjectKind::Rectangle]);
+        assert_eq!(state.bounds, Some([0.0, 0.0, 30.0, 10.0]));
+        // One has no fill: mixed, not "no fill"
+        assert_eq!(state.style.fill, None);
+        assert_eq!(state.style.mixed, vec!["fill".to_string()]);
+        assert_eq!(state.style.stroke_width, Some(ObjectStyle::default().stroke_width));
+
+        // A group has no style of its own
+        let children = scene.remove_roots(&[a, b]);
+        scene.roots.push(SceneNode::Group {
+            id: "g".to_string(),
+            children,
+            transform: TransformMatrix::translate(5.0, 0.0),
+            name: None,
+            cache_as_bitmap: false,
+        });
+        let state = selection_state(&scene, &HashSet::from(["g".to_string()]));
+        assert_eq!(state.types, vec![ObjectKind::Group]);
+        assert_eq!(state.bounds, Some([5.0, 0.0, 35.0, 10.0]));
+        assert_eq!(state.style, CommonStyle::default());
+    }
+}
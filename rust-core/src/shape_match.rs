@@ -0,0 +1,144 @@
+//! Shape Match Module - Approximate comparison of outlines
+//!
+//! Outlines are flattened, resampled at even arc-length steps and normalized
+//! (centroid at the origin, RMS radius 1), so position and uniform scale do not
+//! matter. Two shapes are compared by the symmetric Hausdorff distance of their
+//! samples, which also ignores where each outline starts. Rotation is not
+//! normalized: a rotated copy counts as a different shape.
+
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{PathCommand, VectorObject};
+
+/// Samples taken along each outline
+pub const SAMPLE_COUNT: usize = 64;
+
+/// Straight line segment between two points
+pub type Segment = ((f64, f64), (f64, f64));
+
+/// Line segments per cubic bezier when flattening
+const CURVE_STEPS: usize = 16;
+
+/// Line segments of an object's world-space outline (closed subpaths include their closing edge)
+pub fn outline_segments(object: &VectorObject, transform: &TransformMatrix) -> Vec<Segment> {
+    let commands = geometry::transform_path_commands(&geometry::object_to_path_commands(object), transform);
+    let mut segments = Vec::new();
+    let mut start = (0.0, 0.0);
+    let mut current = (0.0, 0.0);
+    for cmd in geometry::to_cubic_commands(&commands) {
+        match cmd {
+            PathCommand::MoveTo { x, y } => {
+                start = (x, y);
+                current = start;
+            }
+            PathCommand::LineTo { x, y } => {
+                segments.push((current, (x, y)));
+                current = (x, y);
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                let mut previous = current;
+                for step in 1..=CURVE_STEPS {
+                    let t = step as f64 / CURVE_STEPS as f64;
+                    let point = (
+                        geometry::cubic_at(current.0, x1, x2, x, t),
+                        geometry::cubic_at(current.1, y1, y2, y, t),
+                    );
+                    segments.push((previous, point));
+                    previous = point;
+                }
+                current = (x, y);
+            }
+            // Converted to cubics above
+            PathCommand::QuadTo { .. } | PathCommand::ArcTo { .. } => {}
+            PathCommand::ClosePath => {
+                if current != start {
+                    segments.push((current, start));
+                }
+                current = start;
+            }
+        }
+    }
+    segments
+}
+
+/// Evenly spaced, normalized samples along a set of segments
+/// Returns None for degenerate outlines (no length or no extent)
+pub fn normalized_samples(segments: &[Segment]) -> Option<Vec<(f64, f64)>> {
+    let length = |(a, b): &Segment| ((b.0 - a.0).powi(2) + (b.1 - a.1).powi(2)).sqrt();
+    let total: f64 = segments.iter().map(length).sum();
+    if total <= 1e-12 {
+        return None;
+    }
+
+    let step = total / SAMPLE_COUNT as f64;
+    let mut samples = Vec::with_capacity(SAMPLE_COUNT);
+    let mut walked = 0.0;
+    let mut next = 0.0;
+    for segment in segments {
+        let segment_length = length(segment);
+        while next < walked + segment_length && samples.len() < SAMPLE_COUNT {
+            let t = (next - walked) / segment_length;
+            let ((ax, ay), (bx, by)) = *segment;
+            samples.push((ax + (bx - ax) * t, ay + (by - ay) * t));
+            next += step;
+        }
+        walked += segment_length;
+    }
+
+    let n = samples.len() as f64;
+    let cx = samples.iter().map(|p| p.0).sum::<f64>() / n;
+    let cy = samples.iter().map(|p| p.1).sum::<f64>() / n;
+    let radius = (samples.iter().map(|p| (p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sum::<f64>() / n).sqrt();
+    if radius <= 1e-12 {
+        return None;
+    }
+    Some(samples.into_iter().map(|(x, y)| ((x - cx) / radius, (y - cy) / radius)).collect())
+}
+
+/// Symmetric Hausdorff distance between two point sets
+pub fn hausdorff_distance(a: &[(f64, f64)], b: &[(f64, f64)]) -> f64 {
+    let directed = |from: &[(f64, f64)], to: &[(f64, f64)]| {
+        from.iter()
+            .map(|p| to.iter().map(|q| (p.0 - q.0).powi(2) + (p.1 - q.1).powi(2)).fold(f64::INFINITY, f64::min))
+            .fold(0.0, f64::max)
+            .sqrt()
+    };
+    directed(a, b).max(directed(b, a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn samples(object: &VectorObject, transform: &TransformMatrix) -> Vec<(f64, f64)> {
+        normalized_samples(&outline_segments(object, transform)).unwrap()
+    }
+
+    #[test]
+    fn test_moved_and_scaled_copies_match() {
+        let rect = VectorObject::Rectangle { x: 0.0, y: 0.0, width: 40.0, height: 20.0 };
+        let a = samples(&rect, &TransformMatrix::identity());
+        let b = samples(&rect, &TransformMatrix::translate(300.0, 50.0).multiply(&TransformMatrix::scale(2.5, 2.5)));
+        assert!(hausdorff_distance(&a, &b) < 1e-9);
+    }
+
+    #[test]
+    fn test_different_shapes_are_far_apart() {
+        let rect = VectorObject::Rectangle { x: 0.0, y: 0.0, width: 40.0, height: 20.0 };
+        let ellipse = VectorObject::Ellipse { cx: 0.0, cy: 0.0, rx: 20.0, ry: 10.0 };
+        let square = VectorObject::Rectangle { x: 0.0, y: 0.0, width: 20.0, height: 20.0 };
+        let identity = TransformMatrix::identity();
+        let base = samples(&rect, &identity);
+
+        let to_ellipse = hausdorff_distance(&base, &samples(&ellipse, &identity));
+        let to_square = hausdorff_distance(&base, &samples(&square, &identity));
+        assert!(to_ellipse > 0.1);
+        assert!(to_square > to_ellipse);
+    }
+
+    #[test]
+    fn test_degenerate_outline_has_no_samples() {
+        let dot = VectorObject::Rectangle { x: 5.0, y: 5.0, width: 0.0, height: 0.0 };
+        assert!(normalized_samples(&outline_segments(&dot, &TransformMatrix::identity())).is_none());
+    }
+}
@@ -0,0 +1,577 @@
+//! Snap Module - Document grid and snapping rules
+//!
+//! Pure functions that quantize positions and movements, and a `Snapper` shared by
+//! the interactive tools (move/resize drags, pen anchors, nudges). The editor decides
+//! which reference point (e.g. the selection's top-left corner) gets snapped.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+use crate::core::math::TransformMatrix;
+use crate::core::scene::ObjectId;
+use crate::spatial::simple_index::SimpleIndex;
+use crate::spatial::{BoundingBox, SpatialEntry, SpatialQuery};
+
+/// Document grid used for snapping (stored with the scene)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GridSettings {
+    /// Spacing of the major grid lines in world units
+    pub size: f64,
+    /// Number of divisions between major lines; snapping uses the subdivided spacing
+    pub subdivisions: u32,
+    pub enabled: bool,
+}
+
+impl Default for GridSettings {
+    fn default() -> Self {
+        GridSettings { size: 10.0, subdivisions: 1, enabled: false }
+    }
+}
+
+impl GridSettings {
+    /// Whether snapping should be applied (enabled with a usable spacing)
+    pub fn is_active(&self) -> bool {
+        self.enabled && self.size.is_finite() && self.size > 0.0
+    }
+
+    /// Distance between the lines points snap to
+    pub fn spacing(&self) -> f64 {
+        self.size / self.subdivisions.max(1) as f64
+    }
+}
+
+/// Distance in world units within which ruler guides attract points and edges
+pub const GUIDE_SNAP_DISTANCE: f64 = 5.0;
+
+/// Orientation of a ruler guide
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuideAxis {
+    /// The line y = position
+    Horizontal,
+    /// The line x = position
+    Vertical,
+}
+
+impl GuideAxis {
+    /// Parse the API name of an axis ("horizontal" or "vertical")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "horizontal" => Some(GuideAxis::Horizontal),
+            "vertical" => Some(GuideAxis::Vertical),
+            _ => None,
+        }
+    }
+}
+
+/// Persistent ruler guide stored with the document (never exported)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RulerGuide {
+    pub id: ObjectId,
+    pub axis: GuideAxis,
+    pub position: f64,
+}
+
+/// Applies the document's snapping rules; inactive snapping leaves values unchanged
+/// Ruler guides take priority over the grid.
+#[derive(Debug, Clone, Copy)]
+pub struct Snapper<'a> {
+    grid: GridSettings,
+    guides: &'a [RulerGuide],
+}
+
+impl<'a> Snapper<'a> {
+    pub fn new(grid: GridSettings, guides: &'a [RulerGuide]) -> Self {
+        Snapper { grid, guides }
+    }
+
+    /// Snap a point to a nearby ruler guide on each axis, otherwise to the nearest grid intersection
+    pub fn snap_point(&self, x: f64, y: f64) -> (f64, f64) {
+        let spacing = self.grid.spacing();
+        let snap_grid = |value: f64| if self.grid.is_active() { snap_to_grid(value, spacing) } else { value };
+        (
+            self.nearest_guide(GuideAxis::Vertical, &[x]).map_or_else(|| snap_grid(x), |delta| x + delta),
+            self.nearest_guide(GuideAxis::Horizontal, &[y]).map_or_else(|| snap_grid(y), |delta| y + delta),
+        )
+    }
+
+    /// Translation that brings an edge or the center of `bounds` onto a nearby ruler guide,
+    /// per axis (None where no guide is in range)
+    pub fn snap_bounds_to_guides(&self, bounds: &BoundingBox) -> (Option<f64>, Option<f64>) {
+        let (cx, cy) = bounds.center();
+        (
+            self.nearest_guide(GuideAxis::Vertical, &[bounds.min_x, cx, bounds.max_x]),
+            self.nearest_guide(GuideAxis::Horizontal, &[bounds.min_y, cy, bounds.max_y]),
+        )
+    }
+
+    /// Smallest offset moving one of `values` onto a guide of the given axis
+    fn nearest_guide(&self, axis: GuideAxis, values: &[f64]) -> Option<f64> {
+        self.guides
+            .iter()
+            .filter(|guide| guide.axis == axis)
+            .flat_map(|guide| values.iter().map(move |value| guide.position - value))
+            .filter(|delta| delta.abs() <= GUIDE_SNAP_DISTANCE)
+            .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+    }
+
+    /// Adjust a movement so the reference point `origin` ends on a grid intersection
+    pub fn snap_move(&self, origin: (f64, f64), delta: (f64, f64)) -> (f64, f64) {
+        let (x, y) = self.snap_point(origin.0 + delta.0, origin.1 + delta.1);
+        (x - origin.0, y - origin.1)
+    }
+
+    /// Adjust a keyboard nudge so the reference point `origin` advances to the next grid line
+    pub fn snap_nudge(&self, origin: (f64, f64), delta: (f64, f64)) -> (f64, f64) {
+        if !self.grid.is_active() {
+            return delta;
+        }
+        let spacing = self.grid.spacing();
+        (snap_nudge(origin.0, delta.0, spacing), snap_nudge(origin.1, delta.1, spacing))
+    }
+}
+
+/// Snap a coordinate to the nearest grid line
+pub fn snap_to_grid(value: f64, grid_size: f64) -> f64 {
+    (value / grid_size).round() * grid_size
+}
+
+/// Distance to move a coordinate for a nudge of `delta` with grid snapping
+/// The moved coordinate lands on a grid line; if rounding would leave it where it is
+/// (or move it backwards), it advances to the next grid line in the nudge direction.
+pub fn snap_nudge(position: f64, delta: f64, grid_size: f64) -> f64 {
+    if delta == 0.0 {
+        return 0.0;
+    }
+    let mut target = snap_to_grid(position + delta, grid_size);
+    let progress = (target - position) * delta.signum();
+    if progress <= 1e-9 {
+        target += grid_size * delta.signum();
+    }
+    target - position
+}
+
+/// Another object's world bounds, offered as a snap target
+#[derive(Debug, Clone)]
+pub struct SnapTarget {
+    pub id: ObjectId,
+    pub bounds: BoundingBox,
+}
+
+/// What a snapped coordinate lines up with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapKind {
+    /// An edge of the target
+    Edge,
+    /// The target's center line
+    Center,
+    /// Same width as the target
+    Width,
+    /// Same height as the target
+    Height,
+}
+
+/// A snap applied to one axis, reported so the UI can show feedback ("W matches obj_12")
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SnapHint {
+    /// "x" or "y"
+    pub axis: &'static str,
+    pub kind: SnapKind,
+    pub target_id: ObjectId,
+    /// Coordinate of the matched edge/center, or the matched size
+    pub value: f64,
+}
+
+/// Snap the free corner of a shape being drag-created from `anchor` to `current`
+/// Each axis independently snaps its moving edge to target edges and centers, its
+/// center to target centers, or its size to a target's size, whichever is closest
+/// within `threshold`. Returns the snapped corner and the hints that were applied.
+pub fn snap_creation_box(
+    anchor: (f64, f64),
+    current: (f64, f64),
+    targets: &[SnapTarget],
+    threshold: f64,
+) -> ((f64, f64), Vec<SnapHint>) {
+    let mut hints = Vec::new();
+    let x_spans = targets.iter().map(|t| (&t.id, t.bounds.min_x, t.bounds.max_x));
+    let x = match snap_creation_axis(anchor.0, current.0, x_spans, threshold, "x", SnapKind::Width) {
+        Some((x, hint)) => {
+            hints.push(hint);
+            x
+        }
+        None => current.0,
+    };
+    let y_spans = targets.iter().map(|t| (&t.id, t.bounds.min_y, t.bounds.max_y));
+    let y = match snap_creation_axis(anchor.1, current.1, y_spans, threshold, "y", SnapKind::Height) {
+        Some((y, hint)) => {
+            hints.push(hint);
+            y
+        }
+        None => current.1,
+    };
+    ((x, y), hints)
+}
+
+/// Closest snap of the moving coordinate on one axis, given the targets' spans on that axis
+fn snap_creation_axis<'a>(
+    anchor: f64,
+    current: f64,
+    spans: impl Iterator<Item = (&'a ObjectId, f64, f64)>,
+    threshold: f64,
+    axis: &'static str,
+    size_kind: SnapKind,
+) -> Option<(f64, SnapHint)> {
+    let direction = if current < anchor { -1.0 } else { 1.0 };
+    let mut best: Option<(f64, f64, SnapHint)> = None;
+    for (id, min, max) in spans {
+        let center = (min + max) / 2.0;
+        let candidates = [
+            (min, SnapKind::Edge, min),
+            (max, SnapKind::Edge, max),
+            (center, SnapKind::Center, center),
+            // The new shape's center on the target's center
+            (2.0 * center - anchor, SnapKind::Center, center),
+            (anchor + direction * (max - min), size_kind, max - min),
+        ];
+        for (position, kind, value) in candidates {
+            let distance = (position - current).abs();
+            if distance <= threshold && best.as_ref().is_none_or(|(d, _, _)| distance < *d) {
+                let hint = SnapHint { axis, kind, target_id: id.clone(), value };
+                best = Some((distance, position, hint));
+            }
+        }
+    }
+    best.map(|(_, position, hint)| (position, hint))
+}
+
+/// A smart guide line to draw while snapping, from (x1, y1) to (x2, y2)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Guide {
+    /// Edge or center alignment, or "spacing" for the equal gaps being matched
+    pub kind: GuideKind,
+    pub x1: f64,
+    pub y1: f64,
+    pub x2: f64,
+    pub y2: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuideKind {
+    Edge,
+    Center,
+    Spacing,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Axis {
+    X,
+    Y,
+}
+
+impl Axis {
+    fn span(self, bounds: &BoundingBox) -> (f64, f64) {
+        match self {
+            Axis::X => (bounds.min_x, bounds.max_x),
+            Axis::Y => (bounds.min_y, bounds.max_y),
+        }
+    }
+
+    fn cross(self) -> Axis {
+        match self {
+            Axis::X => Axis::Y,
+            Axis::Y => Axis::X,
+        }
+    }
+
+    /// Guide perpendicular to this axis at `position`, spanning `from..to` along the cross axis
+    fn line(self, kind: GuideKind, position: f64, from: f64, to: f64) -> Guide {
+        match self {
+            Axis::X => Guide { kind, x1: position, y1: from, x2: position, y2: to },
+            Axis::Y => Guide { kind, x1: from, y1: position, x2: to, y2: position },
+        }
+    }
+}
+
+/// Smart guides: snapping of dragged bounds to other objects' edges, centers and spacing
+/// Targets are captured into a spatial index when a drag begins.
+#[derive(Debug, Default)]
+pub struct SmartGuides {
+    pub enabled: bool,
+    /// Snap distance in world units
+    pub threshold: f64,
+    index: SimpleIndex,
+    targets: HashMap<ObjectId, BoundingBox>,
+    active: Vec<Guide>,
+}
+
+impl SmartGuides {
+    pub fn new() -> Self {
+        SmartGuides { enabled: false, threshold: 5.0, ..Default::default() }
+    }
+
+    /// Index the objects that the dragged selection can snap to
+    pub fn prepare(&mut self, targets: Vec<SnapTarget>) {
+        let entries = targets
+            .iter()
+            .map(|target| SpatialEntry {
+                id: target.id.clone(),
+                bounds: target.bounds,
+                world_transform: TransformMatrix::identity(),
+            })
+            .collect();
+        self.index.rebuild(entries);
+        self.targets = targets.into_iter().map(|target| (target.id, target.bounds)).collect();
+        self.active.clear();
+    }
+
+    /// Forget the targets and guides (at the end of a drag)
+    pub fn clear(&mut self) {
+        self.index.clear();
+        self.targets.clear();
+        self.active.clear();
+    }
+
+    /// Guides produced by the last snap
+    pub fn active_guides(&self) -> &[Guide] {
+        &self.active
+    }
+
+    /// Snap dragged bounds; returns the extra translation and records the guides to show
+    /// Returns (None, None) per axis that did not snap.
+    pub fn snap_bounds(&mut self, moving: &BoundingBox) -> (Option<f64>, Option<f64>) {
+        self.active.clear();
+        if !self.enabled {
+            return (None, None);
+        }
+
+        // Only objects in the bands around the dragged box can align with it
+        let reach = self.threshold;
+        let vertical_band = BoundingBox::new(moving.min_x - reach, f64::MIN, moving.max_x + reach, f64::MAX);
+        let horizontal_band = BoundingBox::new(f64::MIN, moving.min_y - reach, f64::MAX, moving.max_y + reach);
+        let ids: HashSet<ObjectId> = self
+            .index
+            .query_rect(&vertical_band)
+            .into_iter()
+            .chain(self.index.query_rect(&horizontal_band))
+            .collect();
+        let nearby: Vec<BoundingBox> = ids.iter().filter_map(|id| self.targets.get(id).copied()).collect();
+
+        let dx = best_axis_snap(moving, &nearby, Axis::X, self.threshold);
+        let dy = best_axis_snap(moving, &nearby, Axis::Y, self.threshold);
+
+        // Guides are collected on the snapped box so their extents match what is drawn
+        let (tx, ty) = (dx.unwrap_or(0.0), dy.unwrap_or(0.0));
+        let snapped = BoundingBox::new(moving.min_x + tx, moving.min_y + ty, moving.max_x + tx, moving.max_y + ty);
+        if dx.is_some() {
+            self.active.extend(axis_candidates(&snapped, &nearby, Axis::X, 1e-6).into_iter().map(|(_, g)| g));
+        }
+        if dy.is_some() {
+            self.active.extend(axis_candidates(&snapped, &nearby, Axis::Y, 1e-6).into_iter().map(|(_, g)| g));
+        }
+        (dx, dy)
+    }
+}
+
+/// Smallest translation along an axis that snaps the box to a target, if any
+fn best_axis_snap(moving: &BoundingBox, targets: &[BoundingBox], axis: Axis, threshold: f64) -> Option<f64> {
+    axis_candidates(moving, targets, axis, threshold)
+        .into_iter()
+        .map(|(delta, _)| delta)
+        .min_by(|a, b| a.abs().total_cmp(&b.abs()))
+}
+
+/// Every snap within `threshold` along an axis, as (translation, guide)
+fn axis_candidates(moving: &BoundingBox, targets: &[BoundingBox], axis: Axis, threshold: f64) -> Vec<(f64, Guide)> {
+    let cross = axis.cross();
+    let (m0, m1) = axis.span(moving);
+    let (c0, c1) = cross.span(moving);
+    let width = m1 - m0;
+    let mut candidates = Vec::new();
+
+    // Edge and center alignment
+    for target in targets {
+        let (t0, t1) = axis.span(target);
+        let (tc0, tc1) = cross.span(target);
+        let features = [(t0, GuideKind::Edge), ((t0 + t1) / 2.0, GuideKind::Center), (t1, GuideKind::Edge)];
+        for moving_feature in [m0, (m0 + m1) / 2.0, m1] {
+            for (target_feature, kind) in features {
+                let delta = target_feature - moving_feature;
+                if delta.abs() <= threshold {
+                    let guide = axis.line(kind, target_feature, c0.min(tc0), c1.max(tc1));
+                    candidates.push((delta, guide));
+                }
+            }
+        }
+    }
+
+    // Equal spacing with pairs of targets lined up with the box along the cross axis
+    let overlaps = |target: &BoundingBox| {
+        let (t0, t1) = cross.span(target);
+        t0 <= c1 && t1 >= c0
+    };
+    let middle = (c0 + c1) / 2.0;
+    for a in targets.iter().filter(|t| overlaps(t)) {
+        for b in targets.iter().filter(|t| overlaps(t)) {
+            let ((a0, a1), (b0, b1)) = (axis.span(a), axis.span(b));
+            let gap = b0 - a1;
+            if gap <= 0.0 {
+                continue;
+            }
+            // After b, before a, or centered between them; each with the two equal gaps
+            let before = a0 - gap - width;
+            let mut placements = vec![
+                (b1 + gap, [(a1, b0), (b1, b1 + gap)]),
+                (before, [(before + width, a0), (a1, b0)]),
+            ];
+            if gap > width {
+                let start = a1 + (gap - width) / 2.0;
+                placements.push((start, [(a1, start), (start + width, b0)]));
+            }
+            for (start, gaps) in placements {
+                let delta = start - m0;
+                if delta.abs() <= threshold {
+                    for (g0, g1) in gaps {
+                        candidates.push((delta, cross.line(GuideKind::Spacing, middle, g0, g1)));
+                    }
+                }
+            }
+        }
+    }
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snap_to_grid() {
+        assert_eq!(snap_to_grid(14.0, 10.0), 10.0);
+        assert_eq!(snap_to_grid(15.0, 10.0), 20.0);
+        assert_eq!(snap_to_grid(-6.0, 10.0), -10.0);
+    }
+
+    #[test]
+    fn test_snap_nudge_always_moves_forward() {
+        // Off-grid: a 1px nudge right lands on the next grid line
+        assert_eq!(snap_nudge(13.0, 1.0, 10.0), 7.0);
+        // On-grid: advances a whole grid step
+        assert_eq!(snap_nudge(20.0, 1.0, 10.0), 10.0);
+        // Left nudge from off-grid snaps back to the previous line
+        assert_eq!(snap_nudge(13.0, -1.0, 10.0), -3.0);
+        // Large steps stay on the grid
+        assert_eq!(snap_nudge(20.0, 10.0, 8.0), 12.0);
+    }
+
+    fn target(id: &str, bounds: BoundingBox) -> SnapTarget {
+        SnapTarget { id: id.to_string(), bounds }
+    }
+
+    #[test]
+    fn test_creation_snaps_edges_and_sizes() {
+        let targets = [target("obj_12", BoundingBox::new(100.0, 0.0, 150.0, 40.0))];
+
+        // Right edge lands on the target's left edge; height matches the target
+        let ((x, y), hints) = snap_creation_box((0.0, 200.0), (97.0, 241.0), &targets, 5.0);
+        assert_eq!((x, y), (100.0, 240.0));
+        assert_eq!(hints[0], SnapHint { axis: "x", kind: SnapKind::Edge, target_id: "obj_12".to_string(), value: 100.0 });
+        assert_eq!(hints[1].kind, SnapKind::Height);
+        assert_eq!(hints[1].value, 40.0);
+
+        // Dragging up-left: width is matched in the negative direction
+        let ((x, _), hints) = snap_creation_box((300.0, 300.0), (252.0, 500.0), &targets, 5.0);
+        assert_eq!(x, 250.0);
+        assert_eq!(hints[0].kind, SnapKind::Width);
+    }
+
+    #[test]
+    fn test_creation_snaps_center_and_ignores_far_targets() {
+        let targets = [target("a", BoundingBox::new(0.0, 0.0, 100.0, 100.0))];
+        // Box from x=20 to 79 has its center near the target's center (50): snaps to 80
+        let ((x, _), hints) = snap_creation_box((20.0, 500.0), (79.0, 900.0), &targets, 3.0);
+        assert_eq!(x, 80.0);
+        assert_eq!(hints.len(), 1);
+        assert_eq!(hints[0].kind, SnapKind::Center);
+        assert_eq!(hints[0].value, 50.0);
+    }
+
+    fn guides_with(targets: &[(&str, BoundingBox)]) -> SmartGuides {
+        let mut guides = SmartGuides::new();
+        guides.enabled = true;
+        guides.prepare(targets.iter().map(|(id, bounds)| target(id, *bounds)).collect());
+        guides
+    }
+
+    #[test]
+    fn test_smart_guides_align_edges_and_centers() {
+        let mut guides = guides_with(&[("a", BoundingBox::new(0.0, 0.0, 100.0, 100.0))]);
+
+        // Left edge 3 units right of the target's left edge, far below it
+        let (dx, dy) = guides.snap_bounds(&BoundingBox::new(3.0, 300.0, 43.0, 340.0));
+        assert_eq!(dx, Some(-3.0));
+        assert_eq!(dy, None);
+        let guide = &guides.active_guides()[0];
+        assert_eq!(guide.kind, GuideKind::Edge);
+        assert_eq!((guide.x1, guide.y1, guide.x2, guide.y2), (0.0, 0.0, 0.0, 340.0));
+
+        // Vertical centers: box centered at y = 52 snaps to 50
+        let (_, dy) = guides.snap_bounds(&BoundingBox::new(200.0, 42.0, 220.0, 62.0));
+        assert_eq!(dy, Some(-2.0));
+        assert!(guides.active_guides().iter().any(|g| g.kind == GuideKind::Center));
+    }
+
+    #[test]
+    fn test_smart_guides_match_equal_spacing() {
+        let mut guides = guides_with(&[
+            ("a", BoundingBox::new(0.0, 0.0, 10.0, 10.0)),
+            ("b", BoundingBox::new(30.0, 0.0, 40.0, 10.0)),
+        ]);
+        // Gap a-b is 20; a box starting at 58 is 2 units off the same gap after b
+        let (dx, _) = guides.snap_bounds(&BoundingBox::new(58.0, 500.0, 68.0, 510.0));
+        assert_eq!(dx, None); // not lined up vertically: no spacing snap
+        let (dx, _) = guides.snap_bounds(&BoundingBox::new(58.0, 0.0, 68.0, 10.0));
+        assert_eq!(dx, Some(2.0));
+        let spacing: Vec<&Guide> = guides.active_guides().iter().filter(|g| g.kind == GuideKind::Spacing).collect();
+        assert_eq!(spacing.len(), 2);
+        assert_eq!((spacing[1].x1, spacing[1].x2), (40.0, 60.0));
+    }
+
+    #[test]
+    fn test_disabled_smart_guides_do_nothing() {
+        let mut guides = guides_with(&[("a", BoundingBox::new(0.0, 0.0, 100.0, 100.0))]);
+        guides.enabled = false;
+        assert_eq!(guides.snap_bounds(&BoundingBox::new(1.0, 1.0, 99.0, 99.0)), (None, None));
+        assert!(guides.active_guides().is_empty());
+    }
+
+    #[test]
+    fn test_ruler_guides_take_priority_over_grid() {
+        let guides = [
+            RulerGuide { id: "guide_1".to_string(), axis: GuideAxis::Vertical, position: 33.0 },
+            RulerGuide { id: "guide_2".to_string(), axis: GuideAxis::Horizontal, position: 7.0 },
+        ];
+        let snapper = Snapper::new(GridSettings { size: 10.0, subdivisions: 1, enabled: true }, &guides);
+        assert_eq!(snapper.snap_point(31.0, 48.0), (33.0, 50.0));
+
+        // The right edge of a box lands on the vertical guide
+        let (dx, dy) = snapper.snap_bounds_to_guides(&BoundingBox::new(0.0, 100.0, 30.0, 120.0));
+        assert_eq!(dx, Some(3.0));
+        assert_eq!(dy, None);
+    }
+
+    #[test]
+    fn test_snapper_uses_subdivisions() {
+        let grid = GridSettings { size: 10.0, subdivisions: 4, enabled: true };
+        let snapper = Snapper::new(grid, &[]);
+        assert_eq!(snapper.snap_point(3.6, 6.1), (2.5, 5.0));
+        assert_eq!(snapper.snap_move((1.0, 1.0), (4.0, 0.2)), (4.0, -1.0));
+
+        // Disabled grid leaves everything alone
+        let snapper = Snapper::new(GridSettings { enabled: false, ..grid }, &[]);
+        assert_eq!(snapper.snap_point(3.6, 6.1), (3.6, 6.1));
+        assert_eq!(snapper.snap_nudge((3.0, 0.0), (1.0, 0.0)), (1.0, 0.0));
+    }
+}
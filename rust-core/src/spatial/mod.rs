@@ -56,6 +56,36 @@ impl BoundingBox {
         ((self.min_x + self.max_x) / 2.0, (self.min_y + self.max_y) / 2.0)
     }
 
+    /// Smallest box containing both boxes
+    pub fn union(&self, other: &BoundingBox) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x.min(other.min_x),
+            min_y: self.min_y.min(other.min_y),
+            max_x: self.max_x.max(other.max_x),
+            max_y: self.max_y.max(other.max_y),
+        }
+    }
+
+    /// Grow the box by a margin on every side
+    pub fn expand(&self, margin: f64) -> BoundingBox {
+        BoundingBox {
+            min_x: self.min_x - margin,
+            min_y: self.min_y - margin,
+            max_x: self.max_x + margin,
+            max_y: self.max_y + margin,
+        }
+    }
+
+    /// Whether the boxes overlap (touching edges count)
+    pub fn intersects(&self, other: &BoundingBox) -> bool {
+        self.min_x <= other.max_x && self.max_x >= other.min_x && self.min_y <= other.max_y && self.max_y >= other.min_y
+    }
+
+    /// Bounds as [min_x, min_y, max_x, max_y]
+    pub fn to_array(&self) -> [f64; 4] {
+        [self.min_x, self.min_y, self.max_x, self.max_y]
+    }
+
     /// Transform bounding box corners and compute new AABB
     pub fn transform(&self, matrix: &TransformMatrix) -> BoundingBox {
         let corners = [
@@ -0,0 +1,316 @@
+//! Stroke Module - Stroke outlines and offset paths
+//!
+//! A stroke is rebuilt as the union of simple pieces: one quad per flattened
+//! segment, one join shape per corner and one cap shape per open end. The
+//! union (see path_ops) removes the overlaps and produces holes where the
+//! stroke encloses unpainted area. Offsetting grows or shrinks a fill region by
+//! adding or removing a stroke of twice the distance around its boundary.
+
+use std::f64::consts::PI;
+
+use crate::core::geometry;
+use crate::core::scene::{LineCap, LineJoin, ObjectStyle, PathCommand, StrokeAlign, VectorObject};
+use crate::path_ops::{self, BooleanOp, Polygon, FLATTEN_TOLERANCE};
+
+/// Miter limit used by the canvas renderer (ratio of miter length to stroke width)
+pub const CANVAS_MITER_LIMIT: f64 = 10.0;
+
+/// Miter limit for offset paths (Illustrator's default)
+pub const OFFSET_MITER_LIMIT: f64 = 4.0;
+
+type Point = (f64, f64);
+
+/// Outline of a stroke as filled polygons
+pub fn outline_stroke(
+    commands: &[PathCommand],
+    is_closed: bool,
+    width: f64,
+    cap: LineCap,
+    join: LineJoin,
+    miter_limit: f64,
+) -> Vec<Polygon> {
+    if width <= 0.0 || !width.is_finite() {
+        return Vec::new();
+    }
+    let half = width / 2.0;
+    let mut pieces = Vec::new();
+    for (points, closed) in path_ops::flatten_subpaths(commands, FLATTEN_TOLERANCE) {
+        stroke_polyline(&mut pieces, &points, closed && is_closed, half, cap, join, miter_limit);
+    }
+    union(pieces)
+}
+
+/// Grow (distance > 0) or shrink (distance < 0) the filled region of a path
+/// Corners are mitered up to OFFSET_MITER_LIMIT and beveled beyond it.
+pub fn offset_path(commands: &[PathCommand], distance: f64) -> Vec<Polygon> {
+    let region = path_ops::flatten(commands, FLATTEN_TOLERANCE);
+    if distance == 0.0 || !distance.is_finite() {
+        return path_ops::boolean(BooleanOp::Union, &[region]);
+    }
+    let half = distance.abs();
+    let mut pieces = Vec::new();
+    for polygon in &region {
+        stroke_polyline(&mut pieces, polygon, true, half, LineCap::Butt, LineJoin::Miter, OFFSET_MITER_LIMIT);
+    }
+    let band = union(pieces);
+    let op = if distance > 0.0 { BooleanOp::Union } else { BooleanOp::Subtract };
+    path_ops::boolean(op, &[region, band])
+}
+
+/// Filled outline of an inside or outside stroke of a closed shape
+/// Canvas, SVG and PDF only stroke centered on the outline, so an aligned stroke is
+/// drawn as this area instead: a centered stroke of twice the width, cut to the part
+/// inside or outside the fill region. Returns None for centered strokes, open paths
+/// and objects without a stroke.
+pub fn aligned_stroke_outline(object: &VectorObject, style: &ObjectStyle) -> Option<Vec<Polygon>> {
+    let open = matches!(object, VectorObject::Path { is_closed: false, .. });
+    if style.stroke_align == StrokeAlign::Center || open || style.stroke_color.is_none() {
+        return None;
+    }
+    let commands = geometry::object_to_path_commands(object);
+    let band = outline_stroke(&commands, true, style.stroke_width * 2.0, LineCap::Butt, style.line_join, CANVAS_MITER_LIMIT);
+    let region = path_ops::flatten(&commands, FLATTEN_TOLERANCE);
+    Some(match style.stroke_align {
+        StrokeAlign::Inside => path_ops::boolean(BooleanOp::Intersect, &[region, band]),
+        _ => path_ops::boolean(BooleanOp::Subtract, &[band, region]),
+    })
+}
+
+/// Union of pieces that may overlap, orienting them alike so their windings add up
+fn union(pieces: Vec<Polygon>) -> Vec<Polygon> {
+    let oriented: Vec<Polygon> = pieces
+        .into_iter()
+        .filter(|piece| signed_area(piece).abs() > 1e-12)
+        .map(|mut piece| {
+            if signed_area(&piece) < 0.0 {
+                piece.reverse();
+            }
+            piece
+        })
+        .collect();
+    if oriented.is_empty() {
+        return Vec::new();
+    }
+    path_ops::boolean(BooleanOp::Union, &[oriented])
+}
+
+fn stroke_polyline(
+    pieces: &mut Vec<Polygon>,
+    points: &[Point],
+    closed: bool,
+    half: f64,
+    cap: LineCap,
+    join: LineJoin,
+    miter_limit: f64,
+) {
+    let mut points: Vec<Point> = points.to_vec();
+    points.dedup();
+    if closed && points.len() > 1 && points.first() == points.last() {
+        points.pop();
+    }
+    if points.len() < 2 {
+        // A lone point only shows up with round or square caps
+        if let (Some(&p), false) = (points.first(), closed) {
+            match cap {
+                LineCap::Round => pieces.push(circle(p, half)),
+                LineCap::Square => pieces.push(vec![
+                    (p.0 - half, p.1 - half),
+                    (p.0 + half, p.1 - half),
+                    (p.0 + half, p.1 + half),
+                    (p.0 - half, p.1 + half),
+                ]),
+                LineCap::Butt => {}
+            }
+        }
+        return;
+    }
+
+    let count = points.len();
+    let segment_count = if closed { count } else { count - 1 };
+    for i in 0..segment_count {
+        let (a, b) = (points[i], points[(i + 1) % count]);
+        let (nx, ny) = normal(a, b);
+        pieces.push(vec![
+            (a.0 + nx * half, a.1 + ny * half),
+            (b.0 + nx * half, b.1 + ny * half),
+            (b.0 - nx * half, b.1 - ny * half),
+            (a.0 - nx * half, a.1 - ny * half),
+        ]);
+    }
+
+    // Joins at interior vertices (every vertex of a closed polyline)
+    let corners = if closed { 0..count } else { 1..count - 1 };
+    for i in corners {
+        let previous = points[(i + count - 1) % count];
+        let vertex = points[i];
+        let next = points[(i + 1) % count];
+        if let Some(piece) = join_piece(previous, vertex, next, half, join, miter_limit) {
+            pieces.push(piece);
+        }
+    }
+
+    if !closed {
+        for (end, inner) in [(points[0], points[1]), (points[count - 1], points[count - 2])] {
+            match cap {
+                LineCap::Butt => {}
+                LineCap::Round => pieces.push(circle(end, half)),
+                LineCap::Square => {
+                    let (nx, ny) = normal(inner, end);
+                    // Extend past the end in the direction of travel
+                    let (ux, uy) = direction(inner, end);
+                    let (dx, dy) = (ux * half, uy * half);
+                    pieces.push(vec![
+                        (end.0 + nx * half, end.1 + ny * half),
+                        (end.0 + nx * half + dx, end.1 + ny * half + dy),
+                        (end.0 - nx * half + dx, end.1 - ny * half + dy),
+                        (end.0 - nx * half, end.1 - ny * half),
+                    ]);
+                }
+            }
+        }
+    }
+}
+
+/// Shape filling the gap on the outer side of a corner
+fn join_piece(previous: Point, vertex: Point, next: Point, half: f64, join: LineJoin, miter_limit: f64) -> Option<Polygon> {
+    let (d0, d1) = (direction(previous, vertex), direction(vertex, next));
+    let cross = d0.0 * d1.1 - d0.1 * d1.0;
+    let dot = d0.0 * d1.0 + d0.1 * d1.1;
+    if cross.abs() < 1e-12 && dot > 0.0 {
+        return None; // Straight continuation
+    }
+    if join == LineJoin::Round {
+        return Some(circle(vertex, half));
+    }
+
+    // The gap opens on the side the path turns away from
+    let side = if cross > 0.0 { -1.0 } else { 1.0 };
+    let (n0, n1) = (normal(previous, vertex), normal(vertex, next));
+    let (n0, n1) = ((n0.0 * side, n0.1 * side), (n1.0 * side, n1.1 * side));
+    let p0 = (vertex.0 + n0.0 * half, vertex.1 + n0.1 * half);
+    let p1 = (vertex.0 + n1.0 * half, vertex.1 + n1.1 * half);
+
+    // Miter length relative to the stroke width is 1 / cos(half the angle between the normals)
+    let cos_half = ((1.0 + n0.0 * n1.0 + n0.1 * n1.1) / 2.0).max(0.0).sqrt();
+    if join == LineJoin::Miter && cos_half > 1e-12 && 1.0 / cos_half <= miter_limit {
+        let (mx, my) = (n0.0 + n1.0, n0.1 + n1.1);
+        let length = mx.hypot(my);
+        let reach = half / cos_half;
+        let tip = (vertex.0 + mx / length * reach, vertex.1 + my / length * reach);
+        return Some(vec![vertex, p0, tip, p1]);
+    }
+    Some(vec![vertex, p0, p1])
+}
+
+fn direction(a: Point, b: Point) -> Point {
+    let length = (b.0 - a.0).hypot(b.1 - a.1);
+    ((b.0 - a.0) / length, (b.1 - a.1) / length)
+}
+
+/// Unit normal of the segment a → b
+fn normal(a: Point, b: Point) -> Point {
+    let (dx, dy) = direction(a, b);
+    (-dy, dx)
+}
+
+/// Polygon approximating a circle within the flattening tolerance
+pub fn circle(center: Point, radius: f64) -> Polygon {
+    let step = 2.0 * (1.0 - FLATTEN_TOLERANCE / radius).clamp(-1.0, 1.0).acos();
+    let count = ((2.0 * PI / step.max(1e-3)).ceil() as usize).clamp(8, 256);
+    (0..count)
+        .map(|i| {
+            let angle = 2.0 * PI * i as f64 / count as f64;
+            (center.0 + radius * angle.cos(), center.1 + radius * angle.sin())
+        })
+        .collect()
+}
+
+fn signed_area(polygon: &Polygon) -> f64 {
+    (0..polygon.len())
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % polygon.len()]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn area(polygons: &[Polygon]) -> f64 {
+        polygons.iter().map(signed_area).sum::<f64>().abs()
+    }
+
+    fn line(points: &[Point]) -> Vec<PathCommand> {
+        let mut commands = vec![PathCommand::MoveTo { x: points[0].0, y: points[0].1 }];
+        commands.extend(points[1..].iter().map(|&(x, y)| PathCommand::LineTo { x, y }));
+        commands
+    }
+
+    fn square_path(size: f64) -> Vec<PathCommand> {
+        let mut commands = line(&[(0.0, 0.0), (size, 0.0), (size, size), (0.0, size)]);
+        commands.push(PathCommand::ClosePath);
+        commands
+    }
+
+    #[test]
+    fn test_straight_line_caps() {
+        let commands = line(&[(0.0, 0.0), (10.0, 0.0)]);
+        let butt = outline_stroke(&commands, false, 2.0, LineCap::Butt, LineJoin::Miter, CANVAS_MITER_LIMIT);
+        assert!((area(&butt) - 20.0).abs() < 1e-9);
+        let square = outline_stroke(&commands, false, 2.0, LineCap::Square, LineJoin::Miter, CANVAS_MITER_LIMIT);
+        assert!((area(&square) - 24.0).abs() < 1e-9);
+        let round = outline_stroke(&commands, false, 2.0, LineCap::Round, LineJoin::Miter, CANVAS_MITER_LIMIT);
+        // The two caps make up a unit circle, drawn within the flattening tolerance
+        assert!((area(&round) - (20.0 + PI)).abs() < 2.0 * PI * FLATTEN_TOLERANCE);
+    }
+
+    #[test]
+    fn test_aligned_strokes_stay_on_one_side() {
+        let rect = VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 10.0 };
+        let style = |stroke_align| ObjectStyle { stroke_width: 1.0, stroke_align, ..ObjectStyle::default() };
+        // Inside: 10x10 minus 8x8; outside (mitered): 12x12 minus 10x10
+        let inside = aligned_stroke_outline(&rect, &style(StrokeAlign::Inside)).expect("inside stroke");
+        assert!((area(&inside) - 36.0).abs() < 1e-9);
+        let outside = aligned_stroke_outline(&rect, &style(StrokeAlign::Outside)).expect("outside stroke");
+        assert!((area(&outside) - 44.0).abs() < 1e-9);
+
+        assert!(aligned_stroke_outline(&rect, &style(StrokeAlign::Center)).is_none());
+        let open = VectorObject::Path { commands: line(&[(0.0, 0.0), (10.0, 0.0)]), is_closed: false, anchor_types: Vec::new() };
+        assert!(aligned_stroke_outline(&open, &style(StrokeAlign::Inside)).is_none());
+    }
+
+    #[test]
+    fn test_closed_square_stroke_is_a_ring() {
+        let outline = outline_stroke(&square_path(10.0), true, 2.0, LineCap::Butt, LineJoin::Miter, CANVAS_MITER_LIMIT);
+        // Mitered corners: outer 12x12 minus inner 8x8
+        assert_eq!(outline.len(), 2);
+        assert!((area(&outline) - 80.0).abs() < 1e-9);
+
+        let beveled = outline_stroke(&square_path(10.0), true, 2.0, LineCap::Butt, LineJoin::Bevel, CANVAS_MITER_LIMIT);
+        // Each bevel cuts a 1x1 half-square off a corner
+        assert!((area(&beveled) - 78.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_sharp_corner_falls_back_to_bevel() {
+        // A very sharp turn exceeds the miter limit
+        let commands = line(&[(0.0, 0.0), (100.0, 0.0), (0.0, 2.0)]);
+        let mitered = outline_stroke(&commands, false, 2.0, LineCap::Butt, LineJoin::Miter, CANVAS_MITER_LIMIT);
+        let beveled = outline_stroke(&commands, false, 2.0, LineCap::Butt, LineJoin::Bevel, CANVAS_MITER_LIMIT);
+        assert!((area(&mitered) - area(&beveled)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_offset_square() {
+        let grown = offset_path(&square_path(10.0), 2.0);
+        assert_eq!(grown.len(), 1);
+        assert!((area(&grown) - 196.0).abs() < 1e-9);
+
+        let shrunk = offset_path(&square_path(10.0), -2.0);
+        assert!((area(&shrunk) - 36.0).abs() < 1e-9);
+        assert!(offset_path(&square_path(10.0), -6.0).is_empty());
+    }
+}
@@ -0,0 +1,279 @@
+//! Tessellate Module - Triangle meshes of the scene for a WebGL/WebGPU backend
+//!
+//! Canvas 2D slows down on huge scenes; a GPU draws them from one vertex and index
+//! buffer. Every leaf is flattened to world space and its fill, stroke and color glyph
+//! layers are turned into triangles (with lyon) colored per vertex. Triangles are in
+//! painting order, so drawing the index buffer in one call with alpha blending and no
+//! depth test composites like the canvas does.
+//!
+//! Pattern fills use their fill color, and named CSS colors (which the color module
+//! does not parse) are skipped.
+
+use lyon::math::point;
+use lyon::path::Path;
+use lyon::tessellation::{
+    BuffersBuilder, FillOptions, FillTessellator, FillVertex, LineCap as TessLineCap, LineJoin as TessLineJoin,
+    StrokeOptions, StrokeTessellator, StrokeVertex, VertexBuffers,
+};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+use crate::color::Color;
+use crate::core::geometry;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{FillRule, LineCap, LineJoin, PathCommand, SceneGraph, VectorObject};
+use crate::path_ops::Polygon;
+use crate::stroke;
+
+/// Floats per vertex: x, y (world units), then r, g, b, a (0..1, not premultiplied)
+pub const VERTEX_FLOATS: usize = 6;
+
+/// Triangles of a scene: `indices` are triples into `vertices`
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Mesh {
+    vertices: Vec<f32>,
+    indices: Vec<u32>,
+}
+
+#[wasm_bindgen]
+impl Mesh {
+    /// Vertex data, [`VERTEX_FLOATS`] floats per vertex (a Float32Array in JS)
+    pub fn vertices(&self) -> Vec<f32> {
+        self.vertices.clone()
+    }
+
+    /// Triangle vertex indices (a Uint32Array in JS)
+    pub fn indices(&self) -> Vec<u32> {
+        self.indices.clone()
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.vertices.len() / VERTEX_FLOATS
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+type Vertex = [f32; VERTEX_FLOATS];
+
+/// Tessellate every visible leaf of a scene
+/// `tolerance` is the largest distance (world units) between curves and their
+/// flattened edges; divide a screen tolerance by the zoom.
+pub fn tessellate_scene(scene: &SceneGraph, tolerance: f64) -> Mesh {
+    let tolerance = if tolerance.is_finite() && tolerance > 0.0 { tolerance } else { FillOptions::DEFAULT_TOLERANCE as f64 };
+    let mut buffers: VertexBuffers<Vertex, u32> = VertexBuffers::new();
+    let mut fills = FillTessellator::new();
+    let mut strokes = StrokeTessellator::new();
+
+    for leaf in scene.iter_leaf_views() {
+        let style = leaf.style;
+        let drawable = leaf.object.styled_geometry();
+        let paint = |color: &str| Color::parse(color).map(|color| color.with_alpha(color.a * style.opacity.clamp(0.0, 1.0)));
+        let transform = &leaf.world_transform;
+        let closed = !matches!(drawable.as_ref(), VectorObject::Path { is_closed: false, .. });
+        let outline = world_path(&geometry::object_to_path_commands(&drawable), transform, closed);
+
+        if let Some(color) = style.fill_color.as_deref().and_then(paint) {
+            let rule = match style.fill_rule {
+                FillRule::NonZero => lyon::tessellation::FillRule::NonZero,
+                FillRule::EvenOdd => lyon::tessellation::FillRule::EvenOdd,
+            };
+            let options = FillOptions::tolerance(tolerance as f32).with_fill_rule(rule);
+            fill(&mut fills, &mut buffers, &outline, &options, color);
+        }
+
+        if let Some(color) = style.stroke_color.as_deref().and_then(paint) {
+            match stroke::aligned_stroke_outline(&drawable, style) {
+                // Inside and outside strokes are filled outlines, as on the canvas
+                Some(polygons) => {
+                    let band = polygons_path(&polygons, transform);
+                    fill(&mut fills, &mut buffers, &band, &FillOptions::tolerance(tolerance as f32), color);
+                }
+                None => {
+                    let scale = transform.determinant().abs().sqrt();
+                    let options = StrokeOptions::tolerance(tolerance as f32)
+                        .with_line_width((style.stroke_width * scale) as f32)
+                        .with_line_cap(match style.line_cap {
+                            LineCap::Butt => TessLineCap::Butt,
+                            LineCap::Round => TessLineCap::Round,
+                            LineCap::Square => TessLineCap::Square,
+                        })
+                        .with_line_join(match style.line_join {
+                            LineJoin::Miter => TessLineJoin::Miter,
+                            LineJoin::Round => TessLineJoin::Round,
+                            LineJoin::Bevel => TessLineJoin::Bevel,
+                        });
+                    let rgba = vertex_color(color);
+                    let mut builder = BuffersBuilder::new(&mut buffers, |vertex: StrokeVertex| {
+                        let position = vertex.position();
+                        [position.x, position.y, rgba[0], rgba[1], rgba[2], rgba[3]]
+                    });
+                    // A degenerate outline draws nothing, as on the canvas
+                    let _ = strokes.tessellate_path(&outline, &options, &mut builder);
+                }
+            }
+        }
+
+        // Color glyphs are painted over the text in their own colors
+        for layer in leaf.object.color_layers() {
+            let Some(color) = layer.color.as_deref().or(style.fill_color.as_deref()).and_then(paint) else {
+                continue;
+            };
+            let path = world_path(&layer.commands, transform, true);
+            fill(&mut fills, &mut buffers, &path, &FillOptions::tolerance(tolerance as f32), color);
+        }
+    }
+
+    Mesh { vertices: buffers.vertices.into_iter().flatten().collect(), indices: buffers.indices }
+}
+
+fn fill(
+    tessellator: &mut FillTessellator,
+    buffers: &mut VertexBuffers<Vertex, u32>,
+    path: &Path,
+    options: &FillOptions,
+    color: Color,
+) {
+    let rgba = vertex_color(color);
+    let mut builder = BuffersBuilder::new(buffers, |vertex: FillVertex| {
+        let position = vertex.position();
+        [position.x, position.y, rgba[0], rgba[1], rgba[2], rgba[3]]
+    });
+    let _ = tessellator.tessellate_path(path, options, &mut builder);
+}
+
+fn vertex_color(color: Color) -> [f32; 4] {
+    [color.r as f32, color.g as f32, color.b as f32, color.a as f32]
+}
+
+/// A path in world space, arcs converted to curves
+/// Subpaths are closed where the path closes them, and the last one also when `closed`.
+fn world_path(commands: &[PathCommand], transform: &TransformMatrix, closed: bool) -> Path {
+    let commands = geometry::transform_path_commands(&geometry::to_cubic_commands(commands), transform);
+    let at = |x: f64, y: f64| point(x as f32, y as f32);
+    let mut builder = Path::builder();
+    let mut open = false;
+    for command in &commands {
+        match *command {
+            PathCommand::MoveTo { x, y } => {
+                if open {
+                    builder.end(false);
+                }
+                builder.begin(at(x, y));
+                open = true;
+            }
+            PathCommand::LineTo { x, y } if open => {
+                builder.line_to(at(x, y));
+            }
+            PathCommand::CurveTo { x1, y1, x2, y2, x, y } if open => {
+                builder.cubic_bezier_to(at(x1, y1), at(x2, y2), at(x, y));
+            }
+            PathCommand::QuadTo { x1, y1, x, y } if open => {
+                builder.quadratic_bezier_to(at(x1, y1), at(x, y));
+            }
+            PathCommand::ClosePath if open => {
+                builder.end(true);
+                open = false;
+            }
+            // Drawing commands before the first MoveTo, and arcs (already converted)
+            _ => {}
+        }
+    }
+    if open {
+        builder.end(closed);
+    }
+    builder.build()
+}
+
+/// Closed polygons (in object space) as a world space path
+fn polygons_path(polygons: &[Polygon], transform: &TransformMatrix) -> Path {
+    let mut builder = Path::builder();
+    for polygon in polygons.iter().filter(|polygon| polygon.len() >= 3) {
+        for (i, &(x, y)) in polygon.iter().enumerate() {
+            let (x, y) = transform.transform_point(x, y);
+            if i == 0 {
+                builder.begin(point(x as f32, y as f32));
+            } else {
+                builder.line_to(point(x as f32, y as f32));
+            }
+        }
+        builder.end(true);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::SceneNode;
+
+    fn triangle_area(mesh: &Mesh) -> f64 {
+        let position = |index: u32| {
+            let offset = index as usize * VERTEX_FLOATS;
+            (mesh.vertices[offset] as f64, mesh.vertices[offset + 1] as f64)
+        };
+        mesh.indices
+            .chunks(3)
+            .map(|triangle| {
+                let (a, b, c) = (position(triangle[0]), position(triangle[1]), position(triangle[2]));
+                ((b.0 - a.0) * (c.1 - a.1) - (c.0 - a.0) * (b.1 - a.1)).abs() / 2.0
+            })
+            .sum()
+    }
+
+    fn scene_with(object: VectorObject, transform: TransformMatrix) -> SceneGraph {
+        let mut scene = SceneGraph::new();
+        let id = scene.generate_id();
+        scene.add_object(id.clone(), object, transform);
+        if let Some(SceneNode::Leaf { style, .. }) = scene.get_node_by_id_mut(&id) {
+            style.fill_color = Some("#ff000080".to_string());
+            style.stroke_color = None;
+        }
+        scene
+    }
+
+    #[test]
+    fn test_rectangle_fill_covers_its_area() {
+        let rect = VectorObject::Rectangle { x: 0.0, y: 0.0, width: 10.0, height: 20.0 };
+        let mesh = tessellate_scene(&scene_with(rect, TransformMatrix::translate(5.0, 5.0)), 0.1);
+        assert_eq!(mesh.triangle_count(), 2);
+        assert!((triangle_area(&mesh) - 200.0).abs() < 1e-6);
+        // World positions and the fill color on every vertex
+        assert!(mesh.vertices.chunks(VERTEX_FLOATS).all(|vertex| (5.0..=15.0).contains(&vertex[0])));
+        assert_eq!(&mesh.vertices[2..6], &[1.0, 0.0, 0.0, 128.0 / 255.0]);
+    }
+
+    #[test]
+    fn test_curves_follow_the_tolerance() {
+        let circle = VectorObject::Circle { cx: 0.0, cy: 0.0, r: 100.0 };
+        let coarse = tessellate_scene(&scene_with(circle.clone(), TransformMatrix::identity()), 1.0);
+        let fine = tessellate_scene(&scene_with(circle, TransformMatrix::identity()), 0.01);
+        assert!(fine.triangle_count() > coarse.triangle_count());
+        let area = std::f64::consts::PI * 100.0 * 100.0;
+        assert!((triangle_area(&fine) - area).abs() / area < 0.001);
+    }
+
+    #[test]
+    fn test_stroke_is_scaled_with_the_object() {
+        let mut scene = scene_with(
+            VectorObject::Path {
+                commands: vec![PathCommand::MoveTo { x: 0.0, y: 0.0 }, PathCommand::LineTo { x: 10.0, y: 0.0 }],
+                is_closed: false,
+                anchor_types: Vec::new(),
+            },
+            TransformMatrix::scale(2.0, 2.0),
+        );
+        if let Some(SceneNode::Leaf { style, .. }) = scene.get_node_by_id_mut("obj_1") {
+            style.fill_color = None;
+            style.stroke_color = Some("#000000".to_string());
+            style.stroke_width = 1.5;
+        }
+        // A 20 x 3 band with butt caps
+        let mesh = tessellate_scene(&scene, 0.1);
+        assert!((triangle_area(&mesh) - 60.0).abs() < 1e-6);
+        assert!(tessellate_scene(&SceneGraph::new(), 0.1).vertices.is_empty());
+    }
+}
@@ -0,0 +1,493 @@
+//! Text Edit Module - Caret editing of a text object
+//!
+//! The caret is a character index into the content, whose paragraphs are
+//! separated by '\n'. While an edit session is open, the laid out outline of
+//! every paragraph is kept, so a keystroke reshapes only the paragraphs it
+//! changed; the others are reused and moved to their new baseline.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::core::scene::{ObjectId, ParagraphStyle, TextObject, Typography, WritingMode};
+use crate::fonts::FontRegistry;
+use crate::text_engine::{self, KerningOverrides, PositionedGlyph, TextLine, TextOutline};
+
+/// How far a caret move goes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CaretUnit {
+    Char,
+    Word,
+    /// To the same column on the previous or next line
+    Line,
+    /// To the start or end of the current line
+    LineEdge,
+}
+
+impl CaretUnit {
+    /// Parse a unit name: "char" | "word" | "line" | "line_edge"
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "char" => Some(CaretUnit::Char),
+            "word" => Some(CaretUnit::Word),
+            "line" => Some(CaretUnit::Line),
+            "line_edge" => Some(CaretUnit::LineEdge),
+            _ => None,
+        }
+    }
+}
+
+/// Text object being edited in place
+#[derive(Debug, Clone)]
+pub struct TextEditSession {
+    pub id: ObjectId,
+    /// Character index of the caret
+    pub caret: usize,
+    /// Whether the session's edits already recorded their undo step
+    /// (consecutive keystrokes share one)
+    pub recorded: bool,
+    /// Font, number of fallback fonts, size, paragraph style and typography the cached
+    /// paragraphs were laid out with
+    shaped_with: (String, usize, f64, ParagraphStyle, Typography),
+    /// Paragraph text → the manual kerning of its pairs, its outline with its first
+    /// baseline at the origin, and its line count
+    paragraphs: HashMap<String, (KerningOverrides, TextOutline, usize)>,
+}
+
+impl TextEditSession {
+    /// Start editing with the caret at the end of the content
+    pub fn new(id: ObjectId, text: &TextObject) -> Self {
+        TextEditSession {
+            id,
+            caret: text.content.chars().count(),
+            recorded: false,
+            shaped_with: Self::format_of(text, "", 0),
+            paragraphs: HashMap::new(),
+        }
+    }
+
+    fn format_of(text: &TextObject, font_id: &str, font_count: usize) -> (String, usize, f64, ParagraphStyle, Typography) {
+        (font_id.to_string(), font_count, text.size, text.paragraph.clone(), text.typography.clone())
+    }
+
+    /// Outline of the text, laying out only paragraphs not seen since the last call
+    /// Glyphs come from the font registry's cache. Returns None if no font of the text's
+    /// family is registered
+    pub fn outline(&mut self, fonts: &mut FontRegistry, text: &TextObject) -> Option<TextOutline> {
+        let font_id = fonts.resolve(&text.font)?.to_string();
+        let shaped_with = Self::format_of(text, &font_id, fonts.fallback_chain(&font_id)?.len());
+        if self.shaped_with != shaped_with {
+            self.shaped_with = shaped_with;
+            self.paragraphs.clear();
+        }
+        let mut paragraphs = HashMap::new();
+        let mut full = TextOutline::default();
+        let mut line_count = 0;
+        let mut start = 0;
+        for paragraph in text.content.split('\n') {
+            // Kerning is indexed through the whole content, so a paragraph that moved may kern differently
+            let kerning = text_engine::paragraph_kerning(text, start, paragraph);
+            start += paragraph.chars().count() + 1;
+            let cached = self.paragraphs.remove(paragraph).or_else(|| paragraphs.remove(paragraph));
+            let (outline, lines) = match cached.filter(|(cached_kerning, _, _)| *cached_kerning == kerning) {
+                Some((_, outline, lines)) => (outline, lines),
+                None => {
+                    let lines =
+                        text_engine::layout_paragraph(&fonts.fallback_chain(&font_id)?, paragraph, &kerning, text)?;
+                    (fonts.lines_outline(&font_id, &lines, text.size, 0.0, 0.0)?, lines.len())
+                }
+            };
+            // Later paragraphs start lower, or in columns further left in vertical text
+            let block = line_count as f64 * text_engine::line_advance(text);
+            let placed = match text.paragraph.writing_mode {
+                WritingMode::Horizontal => outline.translated(text.x, text.y + block),
+                WritingMode::Vertical => outline.translated(text.x - block, text.y),
+            };
+            full.commands.extend(placed.commands);
+            full.color_layers.extend(placed.color_layers);
+            line_count += lines;
+            paragraphs.insert(paragraph.to_string(), (kerning, outline, lines));
+        }
+        self.paragraphs = paragraphs;
+        Some(full)
+    }
+}
+
+/// Byte offset of a character index (the end of the string past the last character)
+fn byte_offset(content: &str, caret: usize) -> usize {
+    content.char_indices().nth(caret).map_or(content.len(), |(offset, _)| offset)
+}
+
+/// Insert text at the caret; returns the caret after the inserted text
+/// Line breaks are normalized to '\n'.
+pub fn insert(content: &mut String, caret: usize, text: &str) -> usize {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    content.insert_str(byte_offset(content, caret), &text);
+    caret + text.chars().count()
+}
+
+/// Delete the character before the caret; returns the new caret, or None at the start
+pub fn delete_backward(content: &mut String, caret: usize) -> Option<usize> {
+    let caret = caret.min(content.chars().count()).checked_sub(1)?;
+    content.remove(byte_offset(content, caret));
+    Some(caret)
+}
+
+/// Delete the character after the caret; returns the caret, or None at the end
+pub fn delete_forward(content: &mut String, caret: usize) -> Option<usize> {
+    if caret >= content.chars().count() {
+        return None;
+    }
+    content.remove(byte_offset(content, caret));
+    Some(caret)
+}
+
+/// Character ranges [start, end) of the paragraphs of a text (lines without wrapping)
+pub fn paragraph_lines(content: &str) -> Vec<(usize, usize)> {
+    let mut start = 0;
+    content
+        .split('\n')
+        .map(|paragraph| {
+            let line = (start, start + paragraph.chars().count());
+            start = line.1 + 1;
+            line
+        })
+        .collect()
+}
+
+/// Line index and column (in characters) of a caret among line ranges
+pub fn line_and_column(lines: &[(usize, usize)], caret: usize) -> (usize, usize) {
+    let line = lines.iter().rposition(|&(start, _)| start <= caret).unwrap_or(0);
+    (line, caret.saturating_sub(lines.get(line).map_or(0, |&(start, _)| start)))
+}
+
+/// Last caret position on a line: before the space a wrapped line ends with
+fn line_end(chars: &[char], lines: &[(usize, usize)], line: usize) -> usize {
+    let Some(&(start, end)) = lines.get(line) else {
+        return chars.len();
+    };
+    let wrapped = lines.get(line + 1).is_some_and(|&(next, _)| next == end);
+    if wrapped && end > start && chars.get(end - 1).is_some_and(|c| c.is_whitespace()) {
+        end - 1
+    } else {
+        end
+    }
+}
+
+/// Caret after moving one unit backward or forward
+/// `lines` are the character ranges of the laid out lines (see [`paragraph_lines`]).
+pub fn move_caret(content: &str, lines: &[(usize, usize)], caret: usize, unit: CaretUnit, forward: bool) -> usize {
+    let chars: Vec<char> = content.chars().collect();
+    let caret = caret.min(chars.len());
+    let (line, column) = line_and_column(lines, caret);
+    match unit {
+        CaretUnit::Char if forward => (caret + 1).min(chars.len()),
+        CaretUnit::Char => caret.saturating_sub(1),
+        CaretUnit::Word => word_boundary(&chars, caret, forward),
+        CaretUnit::Line => {
+            let target = if forward { line + 1 } else { line.wrapping_sub(1) };
+            match lines.get(target) {
+                // Same column on the target line, clamped to its end
+                Some(&(start, _)) => (start + column).min(line_end(&chars, lines, target)),
+                None if forward => chars.len(),
+                None => 0,
+            }
+        }
+        CaretUnit::LineEdge if forward => line_end(&chars, lines, line),
+        CaretUnit::LineEdge => lines.get(line).map_or(0, |&(start, _)| start),
+    }
+}
+
+/// Position of a caret in laid out text (relative to the text origin, like the lines)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct CaretPosition {
+    /// Character index the caret is before
+    pub index: usize,
+    pub line: usize,
+    /// Along the line (y in a vertical column)
+    pub x: f64,
+    /// Baseline of the line (the x of a vertical column's center line)
+    pub baseline: f64,
+    /// In a vertical column: the caret is drawn across it
+    pub vertical: bool,
+}
+
+/// Where the caret before character `index` is drawn (clamped to the end of the text)
+/// On a wrapped line break it is at the start of the next line. None for no lines
+pub fn caret_position(lines: &[TextLine], index: usize) -> Option<CaretPosition> {
+    let last = lines.last()?;
+    let index = index.min(last.end);
+    let line = lines.iter().rposition(|line| line.start <= index).unwrap_or(0);
+    let text_line = &lines[line];
+    let x = caret_x(text_line, index);
+    Some(CaretPosition { index, line, x, baseline: text_line.baseline, vertical: text_line.vertical })
+}
+
+/// Caret nearest to a point (relative to the text origin): on the line whose descent
+/// the point is above (the last line below the text), at the closest character boundary.
+/// In vertical text `descent` is how far a column reaches left of its center line, and
+/// the point picks the column it is right of (the last one past the text)
+pub fn caret_from_point(lines: &[TextLine], x: f64, y: f64, descent: f64) -> Option<CaretPosition> {
+    let vertical = lines.first()?.vertical;
+    // Columns are lines turned clockwise: along them is down, and later ones are left
+    let (along, across) = if vertical { (y, -x) } else { (x, y) };
+    let below = |line: &TextLine| if vertical { -line.baseline } else { line.baseline };
+    let line = lines.iter().position(|line| across <= below(line) + descent).unwrap_or(lines.len() - 1);
+    let text_line = &lines[line];
+    // A wrapped line's end is the start of the next line
+    let wrapped = lines.get(line + 1).is_some_and(|next| next.start == text_line.end);
+    let last = if wrapped && text_line.end > text_line.start { text_line.end - 1 } else { text_line.end };
+    (text_line.start..=last)
+        .map(|index| CaretPosition { index, line, x: caret_x(text_line, index), baseline: text_line.baseline, vertical })
+        .min_by(|a, b| (a.x - along).abs().total_cmp(&(b.x - along).abs()))
+}
+
+/// Position along a line of the caret before `index`: the leading edge of the character's glyph
+/// (its right end in right-to-left text), or the trailing edge of the last character at
+/// the end of the line. Inside a ligature the glyph's advance is shared evenly between
+/// its characters
+fn caret_x(line: &TextLine, index: usize) -> f64 {
+    // Glyphs are in visual order; a cluster runs to the next character index that has a glyph
+    let mut starts: Vec<usize> = line.glyphs.iter().map(|glyph| glyph.char_index).collect();
+    starts.sort_unstable();
+    starts.dedup();
+    let cluster_end = |first: usize| starts.get(starts.partition_point(|&start| start <= first)).copied().unwrap_or(line.end);
+    let edge = |glyph: &PositionedGlyph, fraction: f64| {
+        let fraction = if glyph.rtl { 1.0 - fraction } else { fraction };
+        glyph.inline + glyph.advance * fraction
+    };
+    let (target, at_end) = if index < line.end { (index, false) } else { (index.saturating_sub(1), true) };
+    // The widest glyph of the cluster (not a zero-width mark) carries the caret
+    let glyph = line
+        .glyphs
+        .iter()
+        .filter(|glyph| glyph.char_index <= target && target < cluster_end(glyph.char_index))
+        .max_by(|a, b| a.advance.total_cmp(&b.advance));
+    match glyph {
+        Some(glyph) if index >= line.start => {
+            let characters = (cluster_end(glyph.char_index) - glyph.char_index).max(1) as f64;
+            let position = (target - glyph.char_index) as f64 + if at_end { 1.0 } else { 0.0 };
+            edge(glyph, position / characters)
+        }
+        _ => line.x,
+    }
+}
+
+/// Skip the separators next to the caret, then the word after them
+fn word_boundary(chars: &[char], caret: usize, forward: bool) -> usize {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut caret = caret;
+    if forward {
+        while caret < chars.len() && !is_word(chars[caret]) {
+            caret += 1;
+        }
+        while caret < chars.len() && is_word(chars[caret]) {
+            caret += 1;
+        }
+    } else {
+        while caret > 0 && !is_word(chars[caret - 1]) {
+            caret -= 1;
+        }
+        while caret > 0 && is_word(chars[caret - 1]) {
+            caret -= 1;
+        }
+    }
+    caret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::TextFont;
+
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+
+    #[test]
+    fn test_insert_and_delete_by_character() {
+        let mut content = "héllo".to_string();
+        assert_eq!(insert(&mut content, 2, "XY"), 4);
+        assert_eq!(content, "héXYllo");
+        assert_eq!(delete_backward(&mut content, 2), Some(1));
+        assert_eq!(content, "hXYllo");
+        assert_eq!(delete_backward(&mut content, 0), None);
+        assert_eq!(delete_forward(&mut content, 5), Some(5));
+        assert_eq!(content, "hXYll");
+        assert_eq!(delete_forward(&mut content, 5), None);
+        assert_eq!(insert(&mut content, 1, "\r\n"), 2);
+        assert_eq!(content, "h\nXYll");
+    }
+
+    #[test]
+    fn test_caret_moves_by_word() {
+        let content = "one, two_2  three";
+        let lines = paragraph_lines(content);
+        assert_eq!(move_caret(content, &lines, 0, CaretUnit::Word, true), 3);
+        assert_eq!(move_caret(content, &lines, 3, CaretUnit::Word, true), 10);
+        assert_eq!(move_caret(content, &lines, 12, CaretUnit::Word, false), 5);
+        assert_eq!(move_caret(content, &lines, 2, CaretUnit::Word, false), 0);
+        assert_eq!(move_caret(content, &lines, 17, CaretUnit::Char, true), 17);
+    }
+
+    #[test]
+    fn test_caret_moves_by_line() {
+        let content = "abcd\nx\nlonger";
+        let lines = paragraph_lines(content);
+        assert_eq!(lines, vec![(0, 4), (5, 6), (7, 13)]);
+        // From column 3 on the first line, the short line clamps the column
+        assert_eq!(move_caret(content, &lines, 3, CaretUnit::Line, true), 6);
+        assert_eq!(line_and_column(&lines, 6), (1, 1));
+        assert_eq!(move_caret(content, &lines, 6, CaretUnit::Line, true), 8);
+        assert_eq!(move_caret(content, &lines, 10, CaretUnit::Line, false), 6);
+        assert_eq!(move_caret(content, &lines, 2, CaretUnit::Line, false), 0);
+        assert_eq!(move_caret(content, &lines, 9, CaretUnit::Line, true), 13);
+        assert_eq!(move_caret(content, &lines, 9, CaretUnit::LineEdge, false), 7);
+        assert_eq!(move_caret(content, &lines, 1, CaretUnit::LineEdge, true), 4);
+    }
+
+    #[test]
+    fn test_caret_follows_wrapped_lines() {
+        // "hello world" wrapped after the space
+        let content = "hello world";
+        let lines = vec![(0, 6), (6, 11)];
+        assert_eq!(move_caret(content, &lines, 2, CaretUnit::Line, true), 8);
+        assert_eq!(move_caret(content, &lines, 10, CaretUnit::Line, false), 4);
+        assert_eq!(move_caret(content, &lines, 2, CaretUnit::LineEdge, true), 5);
+        assert_eq!(line_and_column(&lines, 6), (1, 0));
+    }
+
+    #[test]
+    fn test_caret_positions_and_hits() {
+        let text = TextObject {
+            content: "office hours\nnext".to_string(),
+            font: TextFont { family: "Roboto".to_string(), weight: 400, italic: false },
+            size: 100.0,
+            x: 0.0,
+            y: 0.0,
+            paragraph: ParagraphStyle { width: Some(400.0), ..ParagraphStyle::default() },
+            typography: Typography::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        let lines = text_engine::layout_text(&[ROBOTO], &text).unwrap();
+        // "office " wraps before "hours", then the second paragraph
+        assert_eq!(lines.iter().map(|line| (line.start, line.end)).collect::<Vec<_>>(), vec![(0, 7), (7, 12), (13, 17)]);
+
+        let start = caret_position(&lines, 0).unwrap();
+        assert_eq!((start.line, start.x, start.baseline), (0, 0.0, 0.0));
+        // Inside the "ffi" ligature the caret splits its advance
+        let caret_x = |index: usize| caret_position(&lines, index).unwrap().x;
+        assert!(caret_x(1) < caret_x(2) && caret_x(2) < caret_x(3) && caret_x(3) < caret_x(4));
+        assert_eq!(caret_position(&lines, 7).unwrap().line, 1);
+        assert_eq!(caret_position(&lines, 7).unwrap().x, 0.0);
+        let end = caret_position(&lines, 99).unwrap();
+        assert_eq!((end.index, end.line), (17, 2));
+
+        // Clicking past the end of a wrapped line lands before its trailing space
+        let hit = caret_from_point(&lines, 1000.0, -20.0, 25.0).unwrap();
+        assert_eq!((hit.index, hit.line), (6, 0));
+        let hit = caret_from_point(&lines, caret_x(4) + 1.0, 10.0, 25.0).unwrap();
+        assert_eq!(hit.index, 4);
+        // Below the text the last line is hit
+        assert_eq!(caret_from_point(&lines, -50.0, 900.0, 25.0).unwrap().index, 13);
+        assert!(caret_from_point(&[], 0.0, 0.0, 25.0).is_none());
+    }
+
+    #[test]
+    fn test_caret_in_right_to_left_text() {
+        let text = TextObject {
+            content: "שלום ab".to_string(),
+            font: TextFont { family: "Roboto".to_string(), weight: 400, italic: false },
+            size: 100.0,
+            x: 0.0,
+            y: 0.0,
+            paragraph: ParagraphStyle::default(),
+            typography: Typography::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        let lines = text_engine::layout_text(&[ROBOTO], &text).unwrap();
+        let caret_x = |index: usize| caret_position(&lines, index).unwrap().x;
+        // The Hebrew word starts at the right end of the line and moves left as it is typed
+        assert!((caret_x(0) - lines[0].width).abs() < 1e-9);
+        assert!(caret_x(1) < caret_x(0) && caret_x(4) < caret_x(3));
+        // The English word after it sits on its left and runs left to right
+        assert!(caret_x(5) < caret_x(6) && caret_x(6) < caret_x(7) && caret_x(7) < caret_x(4));
+        // Clicking at the right end lands before the first character
+        assert_eq!(caret_from_point(&lines, lines[0].width + 10.0, 0.0, 25.0).unwrap().index, 0);
+        assert_eq!(caret_from_point(&lines, caret_x(2) + 1.0, 0.0, 25.0).unwrap().index, 2);
+    }
+
+    #[test]
+    fn test_caret_in_vertical_text() {
+        const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+        let mut text = TextObject {
+            content: "日本\n語".to_string(),
+            font: TextFont { family: "Noto Sans JP".to_string(), weight: 400, italic: false },
+            size: 100.0,
+            x: 300.0,
+            y: 20.0,
+            paragraph: ParagraphStyle { writing_mode: WritingMode::Vertical, ..ParagraphStyle::default() },
+            typography: Typography::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        let lines = text_engine::layout_text(&[NOTO_JP], &text).unwrap();
+        // The caret moves down the column, then to the next column on the left
+        let caret = caret_position(&lines, 1).unwrap();
+        assert_eq!((caret.x, caret.baseline, caret.vertical), (100.0, 0.0, true));
+        let caret = caret_position(&lines, 3).unwrap();
+        assert_eq!((caret.line, caret.x, caret.baseline), (1, 0.0, -120.0));
+        // A point picks the column it is in, then the nearest boundary down it
+        assert_eq!(caret_from_point(&lines, 10.0, 160.0, 50.0).unwrap().index, 2);
+        assert_eq!(caret_from_point(&lines, -110.0, 40.0, 50.0).unwrap().index, 3);
+        assert_eq!(caret_from_point(&lines, -900.0, 90.0, 50.0).unwrap().index, 4);
+
+        // Cached paragraphs move to their columns
+        let mut fonts = FontRegistry::new();
+        fonts.register(NOTO_JP);
+        let mut session = TextEditSession::new("obj_1".to_string(), &text);
+        session.outline(&mut fonts, &text).unwrap();
+        text.content = "新\n日本\n語".to_string();
+        let incremental = session.outline(&mut fonts, &text).unwrap();
+        let full = text_engine::text_outline(&[NOTO_JP], &text).unwrap();
+        assert_eq!(incremental.commands.len(), full.commands.len());
+        for (a, b) in incremental.commands.iter().zip(&full.commands) {
+            let (a, b) = (a.end_point().unwrap_or_default(), b.end_point().unwrap_or_default());
+            assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_incremental_outline_matches_full_shaping() {
+        let mut text = TextObject {
+            content: "Hello\nWorld".to_string(),
+            font: TextFont { family: "Roboto".to_string(), weight: 400, italic: false },
+            size: 30.0,
+            x: 5.0,
+            y: 40.0,
+            paragraph: ParagraphStyle { width: Some(100.0), ..ParagraphStyle::default() },
+            typography: Typography {
+                tracking: 50.0,
+                features: vec!["smcp".to_string()],
+                kerning: KerningOverrides::from([(1, 300.0), (7, -200.0)]),
+            },
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        };
+        let mut fonts = FontRegistry::new();
+        fonts.register(ROBOTO);
+        let mut session = TextEditSession::new("obj_1".to_string(), &text);
+        assert_eq!(session.caret, 11);
+        session.outline(&mut fonts, &text).unwrap();
+
+        // A new wrapped first paragraph moves the cached ones down
+        text.content = "New words here\nHello\nWorld".to_string();
+        let incremental = session.outline(&mut fonts, &text).unwrap();
+        let full = text_engine::text_outline(&[ROBOTO], &text).unwrap();
+        assert_eq!(incremental.commands.len(), full.commands.len());
+        for (a, b) in incremental.commands.iter().zip(&full.commands) {
+            let (a, b) = (a.end_point().unwrap_or_default(), b.end_point().unwrap_or_default());
+            assert!((a.0 - b.0).abs() < 1e-9 && (a.1 - b.1).abs() < 1e-9);
+        }
+    }
+}
@@ -1,48 +1,864 @@
 use wasm_bindgen::prelude::*;
-use rustybuzz::{Face as RbFace, UnicodeBuffer, shape};
-use ttf_parser::{Face as TtfFace, OutlineBuilder, GlyphId};
-use std::fmt::Write;
+use rustybuzz::{Direction, Face as RbFace, Feature, UnicodeBuffer, shape};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use ttf_parser::colr::{ClipBox, CompositeMode, Paint, Painter};
+use ttf_parser::{Face as TtfFace, OutlineBuilder, GlyphId, RgbaColor, Transform};
+use unicode_bidi::{BidiInfo, Level};
+use unicode_script::{Script, UnicodeScript};
+
+use crate::color::Color;
+use crate::core::scene::{ColorLayer, PathCommand, TextAlign, TextObject, WritingMode};
+
+/// Manual kerning: pair index i (between characters i and i + 1) → extra advance in font units
+/// Applied after shaping, on top of the font's own kerning. Text objects measure it in
+/// units of their primary font.
+pub type KerningOverrides = BTreeMap<usize, f32>;
+
+/// A shaped glyph and its pen position (font units)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ShapedGlyph {
+    pub glyph_id: u16,
+    /// Index of the first character of the glyph's cluster
+    pub char_index: usize,
+    pub x: f32,
+    pub y: f32,
+    /// Advance after shaping, without manual kerning
+    pub advance: f32,
+    /// Adjustment applied by the font's kerning (shaped advance minus nominal advance)
+    pub auto_kerning: f32,
+    /// Manual kerning added after this glyph
+    pub manual_kerning: f32,
+}
 
 #[wasm_bindgen]
 pub fn convert_text_to_svg(font_data: &[u8], text: &str) -> String {
-    // 1. Rustybuzzで配置計算
-    let rb_face = RbFace::from_slice(font_data, 0).expect("フォントの読み込みに失敗しました");
+    convert_text_with_kerning(font_data, text, &KerningOverrides::new())
+}
+
+/// Convert text to SVG path data with manual kerning overrides
+/// kerning_json: {"<pair index>": delta, ...} in font units
+#[wasm_bindgen]
+pub fn convert_text_to_svg_kerned(font_data: &[u8], text: &str, kerning_json: &str) -> String {
+    convert_text_with_kerning(font_data, text, &parse_kerning(kerning_json))
+}
+
+/// Shape text and report each glyph's position and kerning (for pair visualization)
+/// Returns JSON: [{ glyph_id, char_index, x, y, advance, auto_kerning, manual_kerning }, ...]
+/// or "[]" if the font cannot be read
+#[wasm_bindgen]
+pub fn get_shaped_glyphs(font_data: &[u8], text: &str, kerning_json: &str) -> String {
+    let glyphs = shape_text(font_data, text, &parse_kerning(kerning_json)).unwrap_or_default();
+    serde_json::to_string(&glyphs).unwrap_or_else(|_| "[]".to_string())
+}
+
+fn parse_kerning(kerning_json: &str) -> KerningOverrides {
+    serde_json::from_str(kerning_json).unwrap_or_default()
+}
+
+fn convert_text_with_kerning(font_data: &[u8], text: &str, kerning: &KerningOverrides) -> String {
+    // ttf-parserで形状抽出の準備
+    let (Some(glyphs), Ok(ttf_face)) = (shape_text(font_data, text, kerning), TtfFace::parse(font_data, 0)) else {
+        return String::new();
+    };
+
+    let mut path_data = String::new();
+    for glyph in glyphs {
+        let mut builder = SvgPathBuilder {
+            path_data: String::new(),
+            offset_x: glyph.x,
+            offset_y: glyph.y,
+        };
+        if ttf_face.outline_glyph(GlyphId(glyph.glyph_id), &mut builder).is_some() {
+            path_data.push_str(&builder.path_data);
+            path_data.push(' ');
+        }
+    }
+    path_data
+}
+
+/// Shape text with rustybuzz and lay out the glyphs, applying manual kerning
+/// Returns None if the font cannot be read
+pub fn shape_text(font_data: &[u8], text: &str, kerning: &KerningOverrides) -> Option<Vec<ShapedGlyph>> {
+    shape_text_with(font_data, text, kerning, &[], 0.0, Direction::LeftToRight)
+}
+
+/// Shape text with OpenType features, adding `tracking` (font units) between clusters.
+/// Right-to-left text comes back in visual order, its first character last. Top-to-bottom
+/// text advances down (y decreasing in font units), its advances positive.
+/// Returns None if the font cannot be read
+pub fn shape_text_with(
+    font_data: &[u8],
+    text: &str,
+    kerning: &KerningOverrides,
+    features: &[Feature],
+    tracking: f32,
+    direction: Direction,
+) -> Option<Vec<ShapedGlyph>> {
+    // Rustybuzzで配置計算
+    let rb_face = RbFace::from_slice(font_data, 0)?;
     let mut buffer = UnicodeBuffer::new();
     buffer.push_str(text);
+    buffer.set_direction(direction);
+    let rtl = direction == Direction::RightToLeft;
+    let vertical = direction == Direction::TopToBottom;
+    let glyph_buffer = shape(&rb_face, features, buffer);
 
-    let glyph_buffer = shape(&rb_face, &[], buffer);
+    // Clusters are byte offsets; kerning pairs are indexed by character
+    let char_index_of = |byte: usize| text[..byte.min(text.len())].chars().count();
+    let char_count = text.chars().count();
 
-    // 2. ttf-parserで形状抽出の準備
-    let ttf_face = TtfFace::parse(font_data, 0).expect("ttf-parserでの読み込みに失敗しました");
-    
-    let mut path_data = String::new();
-    
-    // ★追加: 現在の描画位置（カーソル位置）
+    let infos = glyph_buffer.glyph_infos();
+    let positions = glyph_buffer.glyph_positions();
+    let mut glyphs = Vec::with_capacity(infos.len());
+    // 現在の描画位置（カーソル位置）
     let mut current_x = 0.0;
     let mut current_y = 0.0;
 
-    // グリフごとに処理
-    for (i, info) in glyph_buffer.glyph_infos().iter().enumerate() {
-        let pos = glyph_buffer.glyph_positions()[i];
+    for (i, (info, pos)) in infos.iter().zip(positions).enumerate() {
         let glyph_id = GlyphId(info.glyph_id as u16);
+        let char_index = char_index_of(info.cluster as usize);
+        // The cluster after this one in the text (before it in a right-to-left run)
+        let next = if rtl { i.checked_sub(1).and_then(|previous| infos.get(previous)) } else { infos.get(i + 1) };
+        let next_char_index = next.map_or(char_count, |next| char_index_of(next.cluster as usize));
 
-        let mut builder = SvgPathBuilder {
-            path_data: String::new(),
-            // ★変更: カーソル位置(current_x)を加算する
-            offset_x: current_x + (pos.x_offset as f32),
-            offset_y: current_y + (pos.y_offset as f32),
+        // Pairs ending inside this glyph's cluster take effect after it (ligatures sum them)
+        let manual_kerning: f32 = if next_char_index > char_index {
+            (char_index..next_char_index.min(char_count.saturating_sub(1)))
+                .filter_map(|pair| kerning.get(&pair))
+                .sum()
+        } else {
+            0.0
+        };
+        let (advance, nominal) = if vertical {
+            (-pos.y_advance as f32, rb_face.glyph_ver_advance(glyph_id).unwrap_or(0) as f32)
+        } else {
+            (pos.x_advance as f32, rb_face.glyph_hor_advance(glyph_id).unwrap_or(0) as f32)
         };
 
-        if let Some(_) = ttf_face.outline_glyph(glyph_id, &mut builder) {
-            write!(&mut path_data, "{} ", builder.path_data).unwrap();
+        glyphs.push(ShapedGlyph {
+            glyph_id: glyph_id.0,
+            char_index,
+            x: current_x + pos.x_offset as f32,
+            y: current_y + pos.y_offset as f32,
+            advance,
+            auto_kerning: advance - nominal,
+            manual_kerning,
+        });
+
+        // Tracking spaces clusters, not the glyphs within one
+        let tracking = if infos.get(i + 1).is_some_and(|next| next.cluster != info.cluster) { tracking } else { 0.0 };
+
+        // 次の文字のためにカーソルを進める
+        if vertical {
+            current_x += pos.x_advance as f32;
+            current_y -= advance + manual_kerning + tracking;
+        } else {
+            current_x += advance + manual_kerning + tracking;
+            current_y += pos.y_advance as f32;
+        }
+    }
+    Some(glyphs)
+}
+
+/// Automatic distance between baselines as a multiple of the em size
+pub const LINE_HEIGHT: f64 = 1.2;
+
+/// Distance between the baselines of a text object
+pub fn line_advance(text: &TextObject) -> f64 {
+    text.paragraph.leading.unwrap_or(text.size * LINE_HEIGHT)
+}
+
+/// Ascent and descent of a font at an em size (both positive, in output units)
+/// Returns None if the font cannot be read
+pub fn vertical_metrics(font_data: &[u8], size: f64) -> Option<(f64, f64)> {
+    let face = TtfFace::parse(font_data, 0).ok()?;
+    let scale = size / f64::from(face.units_per_em());
+    Some((f64::from(face.ascender()) * scale, -f64::from(face.descender()) * scale))
+}
+
+/// Parse OpenType feature settings ("smcp", "-liga", "kern=0", "ss01=1", ...)
+/// Returns None if one of them is not a valid setting
+pub fn parse_features(features: &[String]) -> Option<Vec<Feature>> {
+    features.iter().map(|feature| feature.trim().parse().ok()).collect()
+}
+
+/// A glyph placed on a laid out line (output units, relative to the text origin)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PositionedGlyph {
+    /// Index of the glyph's font in the fallback chain (0 for the text's own font)
+    pub font: usize,
+    pub glyph_id: u16,
+    /// Index of the first character of the glyph's cluster
+    pub char_index: usize,
+    /// Where the glyph's outline is drawn from: its pen position on the baseline, moved
+    /// by the font's offsets (in vertical text, its horizontal origin)
+    pub x: f64,
+    pub y: f64,
+    /// Start of the advance along the line (x, or y down a vertical column), whatever
+    /// the direction
+    pub inline: f64,
+    pub advance: f64,
+    /// Set right to left: the glyph's characters run from its right end to its left
+    pub rtl: bool,
+    /// Turned 90° clockwise in a vertical column (Latin and other horizontal scripts)
+    pub sideways: bool,
+}
+
+/// One line of laid out text
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TextLine {
+    /// Characters [start, end) of the text on the line, line break excluded
+    pub start: usize,
+    pub end: usize,
+    /// Start of the baseline relative to the text origin; in a vertical column, `x` is
+    /// the y its top starts at and `baseline` the x of its center line
+    pub x: f64,
+    pub baseline: f64,
+    /// Advance width of the line without trailing spaces (before justification); the
+    /// height of a vertical column
+    pub width: f64,
+    pub glyphs: Vec<PositionedGlyph>,
+    /// A column of vertical text
+    pub vertical: bool,
+}
+
+/// Lay out a text object in lines: paragraphs split at '\n', wrap at the paragraph
+/// width and are aligned and indented. `fonts` is the fallback chain, the text's own
+/// font first. Positions are in local units relative to the text origin, the first
+/// baseline at y = 0 (vertical text: the first column's center line at x = 0, later
+/// columns on its left). Returns None if the first font cannot be read
+pub fn layout_text(fonts: &[&[u8]], text: &TextObject) -> Option<Vec<TextLine>> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for part in text.content.split('\n') {
+        let block = lines.len() as f64 * line_advance(text);
+        let kerning = paragraph_kerning(text, start, part);
+        for mut line in layout_paragraph(fonts, part, &kerning, text)? {
+            line.start += start;
+            line.end += start;
+            if line.vertical {
+                line.baseline -= block;
+            } else {
+                line.baseline += block;
+            }
+            for glyph in &mut line.glyphs {
+                glyph.char_index += start;
+                if line.vertical {
+                    glyph.x -= block;
+                } else {
+                    glyph.y += block;
+                }
+            }
+            lines.push(line);
         }
+        start += part.chars().count() + 1;
+    }
+    Some(lines)
+}
+
+/// A shaped glyph in output units, in logical order
+struct RunGlyph {
+    font: usize,
+    glyph_id: u16,
+    char_index: usize,
+    /// Bidi embedding level (odd levels are right to left)
+    level: u8,
+    /// Offset of the outline's origin from the pen position, along the line and across
+    /// it (down from the baseline, or right of a vertical center line)
+    along: f64,
+    across: f64,
+    advance: f64,
+    sideways: bool,
+}
+
+/// Characters of a paragraph shaped in one go: same font, direction, script and
+/// orientation
+struct Run {
+    font: usize,
+    level: Level,
+    script: Script,
+    /// Set horizontally and turned in a vertical column
+    sideways: bool,
+    first_char: usize,
+    text: String,
+}
 
-        // ★追加: 次の文字のためにカーソルを進める
-        current_x += pos.x_advance as f32;
-        current_y += pos.y_advance as f32;
+/// Whether a character is turned 90° in vertical text rather than kept upright
+/// (Unicode vertical orientation)
+fn is_sideways(c: char) -> bool {
+    matches!(
+        unicode_vo::char_orientation(c),
+        unicode_vo::Orientation::Rotated | unicode_vo::Orientation::TransformedOrRotated
+    )
+}
+
+/// Characters without a script of their own take the script of the run around them
+fn is_shared_script(script: Script) -> bool {
+    matches!(script, Script::Common | Script::Inherited | Script::Unknown)
+}
+
+/// Extra space between clusters of a text object in output units
+fn tracking_of(text: &TextObject) -> f64 {
+    if text.typography.tracking.is_finite() {
+        text.typography.tracking / 1000.0 * text.size
+    } else {
+        0.0
     }
+}
 
-    path_data
+/// Shape a paragraph into glyphs in logical order, with the paragraph's base level.
+/// Characters are split into runs by bidi level (Unicode Bidirectional Algorithm),
+/// script and font: each character comes from the first font of the chain that has a
+/// glyph for it (spaces and characters no font has stay in the current run). In
+/// vertical text, upright runs are shaped top to bottom with the font's vertical
+/// metrics and sideways runs horizontally, centered on the column
+fn shape_with_fallback(fonts: &[&[u8]], paragraph_text: &str, text: &TextObject) -> Option<(Vec<RunGlyph>, Level)> {
+    let vertical = text.paragraph.writing_mode == WritingMode::Vertical;
+    let faces: Vec<TtfFace> = fonts.iter().map_while(|data| TtfFace::parse(data, 0).ok()).collect();
+    if faces.is_empty() {
+        return None;
+    }
+    let bidi = BidiInfo::new(paragraph_text, None);
+    let base_level = bidi.paragraphs.first().map_or(Level::ltr(), |paragraph| paragraph.level);
+    let mut runs: Vec<Run> = Vec::new();
+    for (char_index, (byte, c)) in paragraph_text.char_indices().enumerate() {
+        let current = runs.last().map_or(0, |run| run.font);
+        let font = if c.is_whitespace() || faces[current].glyph_index(c).is_some() {
+            current
+        } else {
+            faces.iter().position(|face| face.glyph_index(c).is_some()).unwrap_or(current)
+        };
+        let level = bidi.levels.get(byte).copied().unwrap_or(base_level);
+        let script = c.script();
+        let sideways = match runs.last() {
+            Some(run) if c.is_whitespace() => run.sideways,
+            _ => vertical && is_sideways(c),
+        };
+        match runs.last_mut() {
+            Some(run)
+                if run.font == font
+                    && run.level == level
+                    && run.sideways == sideways
+                    && (run.script == script || is_shared_script(run.script) || is_shared_script(script)) =>
+            {
+                if is_shared_script(run.script) {
+                    run.script = script;
+                }
+                run.text.push(c);
+            }
+            _ => runs.push(Run { font, level, script, sideways, first_char: char_index, text: c.to_string() }),
+        }
+    }
+
+    // Settings that do not parse (from a hand-edited document) are skipped
+    let features: Vec<Feature> = text.typography.features.iter().filter_map(|feature| feature.trim().parse().ok()).collect();
+    let mut glyphs = Vec::new();
+    for run in runs {
+        let face = &faces[run.font];
+        let scale = text.size / f64::from(face.units_per_em());
+        let rtl = run.level.is_rtl();
+        let upright = vertical && !run.sideways;
+        let direction = match (upright, rtl) {
+            (true, _) => Direction::TopToBottom,
+            (false, true) => Direction::RightToLeft,
+            (false, false) => Direction::LeftToRight,
+        };
+        // A sideways baseline sits so that the em box is centered on the column
+        let center = f64::from(face.ascender() + face.descender()) / 2.0 * scale;
+        // Manual kerning is added between clusters by the layout, like tracking, so pairs
+        // across runs are kept
+        let shaped = shape_text_with(fonts[run.font], &run.text, &KerningOverrides::new(), &features, 0.0, direction)?;
+        // Offsets are relative to the pen, which advances in visual order
+        let mut pen = 0.0;
+        let mut run_glyphs: Vec<RunGlyph> = shaped
+            .into_iter()
+            .map(|glyph| {
+                let (x, y) = (f64::from(glyph.x) * scale, f64::from(glyph.y) * scale);
+                // Turned clockwise, the font's up points right
+                let (along, across) = if upright {
+                    (-y - pen, x)
+                } else if run.sideways {
+                    (x - pen, y - center)
+                } else {
+                    (x - pen, -y)
+                };
+                pen += f64::from(glyph.advance) * scale;
+                RunGlyph {
+                    font: run.font,
+                    glyph_id: glyph.glyph_id,
+                    char_index: run.first_char + glyph.char_index,
+                    level: run.level.number(),
+                    along,
+                    across,
+                    advance: f64::from(glyph.advance) * scale,
+                    sideways: run.sideways,
+                }
+            })
+            .collect();
+        if rtl {
+            run_glyphs.reverse();
+        }
+        glyphs.extend(run_glyphs);
+    }
+    Some((glyphs, base_level))
+}
+
+/// Manual kerning of the paragraph of a text object starting at character `start`,
+/// indexed from the paragraph's first character. Its last pair (into the next
+/// paragraph) is left out
+pub fn paragraph_kerning(text: &TextObject, start: usize, paragraph_text: &str) -> KerningOverrides {
+    let end = start + paragraph_text.chars().count().saturating_sub(1);
+    text.typography.kerning.range(start..end).map(|(pair, delta)| (pair - start, *delta)).collect()
+}
+
+/// Lay out one paragraph (no '\n') in the format of a text object; see [`layout_text`]
+/// Lines are broken in logical order, then each line's glyphs are placed in visual
+/// order, right-to-left runs reversed. Trailing spaces hang past the end of the line
+/// (on the left in a right-to-left paragraph), outside its width and alignment.
+/// Vertical lines are the same measured down a column. `kerning` is the manual kerning
+/// of the paragraph's own character pairs (see [`paragraph_kerning`])
+pub fn layout_paragraph(
+    fonts: &[&[u8]],
+    paragraph_text: &str,
+    kerning: &KerningOverrides,
+    text: &TextObject,
+) -> Option<Vec<TextLine>> {
+    let (glyphs, base_level) = shape_with_fallback(fonts, paragraph_text, text)?;
+    let vertical = text.paragraph.writing_mode == WritingMode::Vertical;
+    let paragraph = &text.paragraph;
+    let chars: Vec<char> = paragraph_text.chars().collect();
+    let is_space = |glyph: &RunGlyph| chars.get(glyph.char_index).is_some_and(|c| c.is_whitespace());
+    let indent = if paragraph.indent.is_finite() { paragraph.indent } else { 0.0 };
+    let width = paragraph.width.filter(|width| width.is_finite() && *width > 0.0);
+    let tracking = tracking_of(text);
+    // Manual kerning between two clusters (the pairs from one's first character to the other's)
+    let kerning_scale = fonts
+        .first()
+        .and_then(|data| TtfFace::parse(data, 0).ok())
+        .map_or(0.0, |face| text.size / f64::from(face.units_per_em()));
+    let kern = |a: usize, b: usize| -> f64 {
+        kerning.range(a.min(b)..a.max(b)).map(|(_, delta)| f64::from(*delta)).sum::<f64>() * kerning_scale
+    };
+
+    // Logical pen positions; tracking and manual kerning separate clusters
+    let mut pens = Vec::with_capacity(glyphs.len());
+    let mut pen = 0.0;
+    for (index, glyph) in glyphs.iter().enumerate() {
+        if index > 0 && glyphs[index - 1].char_index != glyph.char_index {
+            pen += tracking + kern(glyphs[index - 1].char_index, glyph.char_index);
+        }
+        pens.push(pen);
+        pen += glyph.advance;
+    }
+    let left = |index: usize| pens[index];
+    let right = |index: usize| pens[index] + glyphs[index].advance;
+
+    // Greedy wrapping: break after the last space that fits, or inside a word
+    // that is wider than the line on its own
+    let mut ranges = Vec::new();
+    let mut line_start = 0;
+    let mut last_break = None;
+    let mut index = 0;
+    while index < glyphs.len() {
+        if is_space(&glyphs[index]) {
+            last_break = Some(index + 1);
+        } else if let Some(width) = width {
+            let available = if ranges.is_empty() { width - indent } else { width };
+            if index > line_start && right(index) - left(line_start) > available {
+                let end = last_break.filter(|&end| end > line_start).unwrap_or(index);
+                ranges.push((line_start, end));
+                line_start = end;
+                last_break = None;
+                continue;
+            }
+        }
+        index += 1;
+    }
+    ranges.push((line_start, glyphs.len()));
+
+    let char_at = |glyph_index: usize| glyphs.get(glyph_index).map_or(chars.len(), |glyph| glyph.char_index);
+    let line_count = ranges.len();
+    let lines = ranges
+        .into_iter()
+        .enumerate()
+        .map(|(line_index, (start, end))| {
+            let visible = glyphs[start..end].iter().rposition(|glyph| !is_space(glyph)).map_or(start, |last| start + last + 1);
+            let line_width = if visible > start { right(visible - 1) - left(start) } else { 0.0 };
+            let offset = if line_index == 0 { indent } else { 0.0 };
+            let slack = width.map_or(0.0, |width| width - offset - line_width);
+            let x = match (paragraph.align, width) {
+                (TextAlign::Center, Some(_)) => offset + slack / 2.0,
+                (TextAlign::Right, Some(_)) => offset + slack,
+                (TextAlign::Center, None) => offset - line_width / 2.0,
+                (TextAlign::Right, None) => offset - line_width,
+                _ => offset,
+            };
+            // Justified lines share the slack between their inner spaces
+            let spaces = glyphs[start..visible].iter().filter(|glyph| is_space(glyph)).count();
+            let stretch = if paragraph.align == TextAlign::Justify && line_index + 1 < line_count && spaces > 0 {
+                slack.max(0.0) / spaces as f64
+            } else {
+                0.0
+            };
+
+            // Visual order of the visible glyphs, then the trailing spaces at the line's end
+            let levels: Vec<Level> =
+                glyphs[start..visible].iter().map(|glyph| Level::new(glyph.level).unwrap_or(base_level)).collect();
+            let mut order: Vec<usize> = BidiInfo::reorder_visual(&levels).into_iter().map(|index| start + index).collect();
+            let trailing_width = match (end > visible, visible > start) {
+                (false, _) => 0.0,
+                (true, true) => right(end - 1) - right(visible - 1),
+                (true, false) => right(end - 1) - left(start),
+            };
+            let mut pen = x;
+            if base_level.is_rtl() {
+                order.splice(0..0, (visible..end).rev());
+                pen -= trailing_width;
+            } else {
+                order.extend(visible..end);
+            }
+
+            // Columns stack leftwards
+            let baseline = if vertical { -1.0 } else { 1.0 } * line_index as f64 * line_advance(text);
+            let mut previous: Option<usize> = None;
+            let mut positioned = Vec::with_capacity(order.len());
+            for index in order {
+                let glyph = &glyphs[index];
+                if let Some(previous) = previous.filter(|previous| *previous != glyph.char_index) {
+                    pen += tracking + kern(previous, glyph.char_index);
+                }
+                previous = Some(glyph.char_index);
+                let (x, y) = if vertical {
+                    (baseline + glyph.across, pen + glyph.along)
+                } else {
+                    (pen + glyph.along, baseline + glyph.across)
+                };
+                positioned.push(PositionedGlyph {
+                    font: glyph.font,
+                    glyph_id: glyph.glyph_id,
+                    char_index: glyph.char_index,
+                    x,
+                    y,
+                    inline: pen,
+                    advance: glyph.advance,
+                    rtl: glyph.level % 2 == 1,
+                    sideways: glyph.sideways,
+                });
+                pen += glyph.advance;
+                if index < visible && is_space(glyph) {
+                    pen += stretch;
+                }
+            }
+            TextLine {
+                start: char_at(start),
+                end: char_at(end),
+                x,
+                baseline,
+                width: line_width,
+                glyphs: positioned,
+                vertical,
+            }
+        })
+        .collect();
+    Some(lines)
+}
+
+/// Outlines of laid out text: plain glyphs in one path, color glyphs as colored layers
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TextOutline {
+    pub commands: Vec<PathCommand>,
+    pub color_layers: Vec<ColorLayer>,
+}
+
+impl TextOutline {
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty() && self.color_layers.is_empty()
+    }
+
+    /// Copy moved by (dx, dy)
+    pub fn translated(&self, dx: f64, dy: f64) -> TextOutline {
+        let moved = |commands: &[PathCommand]| commands.iter().map(|command| translated(command, dx, dy)).collect();
+        TextOutline {
+            commands: moved(&self.commands),
+            color_layers: self
+                .color_layers
+                .iter()
+                .map(|layer| ColorLayer { color: layer.color.clone(), commands: moved(&layer.commands) })
+                .collect(),
+        }
+    }
+}
+
+fn translated(command: &PathCommand, dx: f64, dy: f64) -> PathCommand {
+    match *command {
+        PathCommand::MoveTo { x, y } => PathCommand::MoveTo { x: x + dx, y: y + dy },
+        PathCommand::LineTo { x, y } => PathCommand::LineTo { x: x + dx, y: y + dy },
+        PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+            PathCommand::CurveTo { x1: x1 + dx, y1: y1 + dy, x2: x2 + dx, y2: y2 + dy, x: x + dx, y: y + dy }
+        }
+        PathCommand::QuadTo { x1, y1, x, y } => PathCommand::QuadTo { x1: x1 + dx, y1: y1 + dy, x: x + dx, y: y + dy },
+        PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x, y } => {
+            PathCommand::ArcTo { rx, ry, rotation, large_arc, sweep, x: x + dx, y: y + dy }
+        }
+        PathCommand::ClosePath => PathCommand::ClosePath,
+    }
+}
+
+/// Glyph outlines of laid out lines as path commands, with the text origin at (x, y)
+/// and y growing downwards as in the scene. `fonts` is the chain the lines were laid
+/// out with. Returns None if a glyph's font cannot be read
+pub fn lines_outline(fonts: &[&[u8]], lines: &[TextLine], size: f64, x: f64, y: f64) -> Option<TextOutline> {
+    let faces: Vec<TtfFace> = fonts.iter().map_while(|data| TtfFace::parse(data, 0).ok()).collect();
+    let mut outline = TextOutline::default();
+    for glyph in lines.iter().flat_map(|line| &line.glyphs) {
+        let glyph_outline = outline_glyph(&faces, glyph, size, (x, y))?;
+        outline.commands.extend(glyph_outline.commands);
+        outline.color_layers.extend(glyph_outline.color_layers);
+    }
+    Some(outline)
+}
+
+/// Outline of each glyph of a text object at its position that draws something,
+/// with the index of its first character. Returns None if the fonts cannot be read
+pub fn glyph_outlines(fonts: &[&[u8]], text: &TextObject) -> Option<Vec<(usize, TextOutline)>> {
+    let faces: Vec<TtfFace> = fonts.iter().map_while(|data| TtfFace::parse(data, 0).ok()).collect();
+    let mut outlines = Vec::new();
+    for glyph in layout_text(fonts, text)?.iter().flat_map(|line| &line.glyphs) {
+        let outline = outline_glyph(&faces, glyph, text.size, (text.x, text.y))?;
+        if !outline.is_empty() {
+            outlines.push((glyph.char_index, outline));
+        }
+    }
+    Some(outlines)
+}
+
+/// Outline of a glyph at an em size with its pen position at the origin, turned 90°
+/// clockwise if `sideways`. Returns None if the font cannot be read
+pub fn glyph_outline(font_data: &[u8], glyph_id: u16, size: f64, sideways: bool) -> Option<TextOutline> {
+    let face = TtfFace::parse(font_data, 0).ok()?;
+    Some(face_glyph_outline(&face, glyph_id, size, (0.0, 0.0), sideways))
+}
+
+/// Outline of a placed glyph
+fn outline_glyph(faces: &[TtfFace], glyph: &PositionedGlyph, size: f64, origin: (f64, f64)) -> Option<TextOutline> {
+    let face = faces.get(glyph.font)?;
+    Some(face_glyph_outline(face, glyph.glyph_id, size, (origin.0 + glyph.x, origin.1 + glyph.y), glyph.sideways))
+}
+
+/// Outline of a glyph with its pen position at `pen`; a color glyph (COLR) gives its
+/// layers instead
+fn face_glyph_outline(face: &TtfFace, glyph_id: u16, size: f64, pen: (f64, f64), sideways: bool) -> TextOutline {
+    let builder = PathCommandBuilder {
+        commands: Vec::new(),
+        scale: size / f64::from(face.units_per_em()),
+        offset_x: pen.0,
+        offset_y: pen.1,
+        sideways,
+    };
+    let glyph_id = GlyphId(glyph_id);
+    if face.is_color_glyph(glyph_id) {
+        let mut painter = ColorGlyphPainter {
+            face,
+            builder,
+            transforms: Vec::new(),
+            outline: Vec::new(),
+            clips: Vec::new(),
+            layers: Vec::new(),
+        };
+        // The foreground color stands for the text's fill (see `layer_color`)
+        if face.paint_color_glyph(glyph_id, 0, FOREGROUND, &mut painter).is_some() {
+            return TextOutline { commands: Vec::new(), color_layers: painter.layers };
+        }
+        return TextOutline::default();
+    }
+    let mut builder = builder;
+    face.outline_glyph(glyph_id, &mut builder);
+    TextOutline { commands: builder.commands, color_layers: Vec::new() }
+}
+
+/// Glyph outlines of a text object laid out with [`layout_text`] at its position
+/// Returns None if the fonts cannot be read
+pub fn text_outline(fonts: &[&[u8]], text: &TextObject) -> Option<TextOutline> {
+    lines_outline(fonts, &layout_text(fonts, text)?, text.size, text.x, text.y)
+}
+
+/// Stand-in for the text's fill color passed to COLR painting; fully transparent,
+/// so no color a font paints with can be mistaken for it
+const FOREGROUND: RgbaColor = RgbaColor { red: 1, green: 2, blue: 3, alpha: 0 };
+
+/// CSS color of a COLR paint, None for the text's fill color
+fn layer_color(color: RgbaColor) -> Option<String> {
+    if color == FOREGROUND {
+        return None;
+    }
+    let channel = |value: u8| f64::from(value) / 255.0;
+    Some(Color::rgba(channel(color.red), channel(color.green), channel(color.blue), channel(color.alpha)).to_css())
+}
+
+/// Collects the layers of a color glyph as outlines filled with one color each
+/// Gradients are filled with the average of their stops, and blending modes are
+/// ignored; a paint covers the innermost clip (the glyph it is drawn with).
+struct ColorGlyphPainter<'f, 'a> {
+    face: &'f TtfFace<'a>,
+    /// Maps font units of the glyph to output units
+    builder: PathCommandBuilder,
+    /// Combined transforms of the current paint
+    transforms: Vec<Transform>,
+    outline: Vec<PathCommand>,
+    clips: Vec<Vec<PathCommand>>,
+    layers: Vec<ColorLayer>,
+}
+
+impl ColorGlyphPainter<'_, '_> {
+    fn transform(&self) -> Transform {
+        self.transforms.last().copied().unwrap_or_default()
+    }
+}
+
+impl<'a> Painter<'a> for ColorGlyphPainter<'_, 'a> {
+    fn outline_glyph(&mut self, glyph_id: GlyphId) {
+        self.builder.commands.clear();
+        let mut transformed = TransformedBuilder { transform: self.transform(), builder: &mut self.builder };
+        self.face.outline_glyph(glyph_id, &mut transformed);
+        self.outline = std::mem::take(&mut self.builder.commands);
+    }
+
+    fn paint(&mut self, paint: Paint<'a>) {
+        let coords = self.face.variation_coordinates();
+        let stops: Vec<RgbaColor> = match &paint {
+            Paint::Solid(color) => vec![*color],
+            Paint::LinearGradient(gradient) => gradient.stops(0, coords).map(|stop| stop.color).collect(),
+            Paint::RadialGradient(gradient) => gradient.stops(0, coords).map(|stop| stop.color).collect(),
+            Paint::SweepGradient(gradient) => gradient.stops(0, coords).map(|stop| stop.color).collect(),
+        };
+        let color = match stops.as_slice() {
+            [] => return,
+            [color] => *color,
+            stops => {
+                let average = |channel: fn(&RgbaColor) -> u8| {
+                    (stops.iter().map(|color| u32::from(channel(color))).sum::<u32>() / stops.len() as u32) as u8
+                };
+                RgbaColor::new(average(|c| c.red), average(|c| c.green), average(|c| c.blue), average(|c| c.alpha))
+            }
+        };
+        let commands = self.clips.last().unwrap_or(&self.outline);
+        if !commands.is_empty() {
+            self.layers.push(ColorLayer { color: layer_color(color), commands: commands.clone() });
+        }
+    }
+
+    fn push_clip(&mut self) {
+        self.clips.push(self.outline.clone());
+    }
+
+    fn push_clip_box(&mut self, clipbox: ClipBox) {
+        self.builder.commands.clear();
+        let mut transformed = TransformedBuilder { transform: self.transform(), builder: &mut self.builder };
+        transformed.move_to(clipbox.x_min, clipbox.y_min);
+        transformed.line_to(clipbox.x_max, clipbox.y_min);
+        transformed.line_to(clipbox.x_max, clipbox.y_max);
+        transformed.line_to(clipbox.x_min, clipbox.y_max);
+        transformed.close();
+        self.clips.push(std::mem::take(&mut self.builder.commands));
+    }
+
+    fn pop_clip(&mut self) {
+        self.clips.pop();
+    }
+
+    fn push_layer(&mut self, _mode: CompositeMode) {}
+
+    fn pop_layer(&mut self) {}
+
+    fn push_transform(&mut self, transform: Transform) {
+        self.transforms.push(Transform::combine(self.transform(), transform));
+    }
+
+    fn pop_transform(&mut self) {
+        self.transforms.pop();
+    }
+}
+
+/// Applies a COLR transform (font units) before another outline builder
+struct TransformedBuilder<'b> {
+    transform: Transform,
+    builder: &'b mut PathCommandBuilder,
+}
+
+impl TransformedBuilder<'_> {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        let t = self.transform;
+        (t.a * x + t.c * y + t.e, t.b * x + t.d * y + t.f)
+    }
+}
+
+impl OutlineBuilder for TransformedBuilder<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.builder.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.builder.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((x1, y1), (x, y)) = (self.point(x1, y1), self.point(x, y));
+        self.builder.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((x1, y1), (x2, y2), (x, y)) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.builder.curve_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.builder.close();
+    }
+}
+
+struct PathCommandBuilder {
+    commands: Vec<PathCommand>,
+    scale: f64,
+    offset_x: f64,
+    offset_y: f64,
+    /// Turn the glyph 90° clockwise (its baseline running down)
+    sideways: bool,
+}
+
+impl PathCommandBuilder {
+    fn point(&self, x: f32, y: f32) -> (f64, f64) {
+        let (x, y) = (f64::from(x) * self.scale, f64::from(y) * self.scale);
+        if self.sideways {
+            (self.offset_x + y, self.offset_y + x)
+        } else {
+            (self.offset_x + x, self.offset_y - y)
+        }
+    }
+}
+
+impl OutlineBuilder for PathCommandBuilder {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.commands.push(PathCommand::MoveTo { x, y });
+    }
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.commands.push(PathCommand::LineTo { x, y });
+    }
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((x1, y1), (x, y)) = (self.point(x1, y1), self.point(x, y));
+        self.commands.push(PathCommand::QuadTo { x1, y1, x, y });
+    }
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((x1, y1), (x2, y2), (x, y)) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.commands.push(PathCommand::CurveTo { x1, y1, x2, y2, x, y });
+    }
+    fn close(&mut self) {
+        self.commands.push(PathCommand::ClosePath);
+    }
 }
 
 // --- OutlineBuilder の実装 (変更なし) ---
@@ -68,4 +884,317 @@ impl OutlineBuilder for SvgPathBuilder {
     fn close(&mut self) {
         self.path_data.push_str("Z ");
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::{ParagraphStyle, TextFont, Typography};
+
+    const ROBOTO: &[u8] = include_bytes!("../../public/Roboto-VariableFont_wdth,wght.ttf");
+
+    fn text(content: &str, size: f64, x: f64, y: f64, paragraph: ParagraphStyle) -> TextObject {
+        TextObject {
+            content: content.to_string(),
+            font: TextFont { family: "Roboto".to_string(), weight: 400, italic: false },
+            size,
+            x,
+            y,
+            paragraph,
+            typography: Typography::default(),
+            outline: Vec::new(),
+            color_layers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_manual_kerning_shifts_following_glyphs() {
+        let plain = shape_text(ROBOTO, "ABC", &KerningOverrides::new()).unwrap();
+        let kerning = KerningOverrides::from([(0, 50.0)]);
+        let kerned = shape_text(ROBOTO, "ABC", &kerning).unwrap();
+
+        assert_eq!(kerned[0].x, plain[0].x);
+        assert_eq!(kerned[0].manual_kerning, 50.0);
+        assert_eq!(kerned[1].x, plain[1].x + 50.0);
+        assert_eq!(kerned[2].x, plain[2].x + 50.0);
+    }
+
+    #[test]
+    fn test_reports_font_kerning() {
+        // Roboto tightens the "AV" pair
+        let glyphs = shape_text(ROBOTO, "AV", &KerningOverrides::new()).unwrap();
+        assert!(glyphs[0].auto_kerning < 0.0);
+    }
+
+    #[test]
+    fn test_text_outline_sits_on_the_baseline() {
+        let outline = text_outline(&[ROBOTO], &text("H", 100.0, 10.0, 200.0, ParagraphStyle::default())).unwrap().commands;
+        let points: Vec<(f64, f64)> = outline.iter().filter_map(|command| command.end_point()).collect();
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        // Roboto's cap height is about 0.71 em; the glyph stands on the baseline
+        assert!((max_y - 200.0).abs() < 1e-6);
+        assert!((200.0 - min_y - 71.1).abs() < 1.0, "{}", min_y);
+        assert!(min_x > 10.0 && min_x < 25.0);
+        assert!(outline.contains(&PathCommand::ClosePath));
+        assert!(text_outline(&[], &text("H", 100.0, 0.0, 0.0, ParagraphStyle::default())).is_none());
+    }
+
+    #[test]
+    fn test_lines_stack_by_line_height() {
+        let one = text_outline(&[ROBOTO], &text("H", 100.0, 0.0, 0.0, ParagraphStyle::default())).unwrap().commands;
+        let two = text_outline(&[ROBOTO], &text("H\nH", 100.0, 0.0, 0.0, ParagraphStyle::default())).unwrap().commands;
+        assert_eq!(two.len(), one.len() * 2);
+        let first = one[0].end_point().unwrap();
+        let second = two[one.len()].end_point().unwrap();
+        assert_eq!(second.0, first.0);
+        assert!((second.1 - first.1 - 100.0 * LINE_HEIGHT).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_wrapping_breaks_after_spaces() {
+        let paragraph = ParagraphStyle { width: Some(300.0), ..ParagraphStyle::default() };
+        let lines = layout_text(&[ROBOTO], &text("the quick brown fox jumps\nover", 100.0, 0.0, 0.0, paragraph.clone())).unwrap();
+        let texts: Vec<(usize, usize)> = lines.iter().map(|line| (line.start, line.end)).collect();
+        // "the " "quick " "brown " "fox " "jumps" then the second paragraph
+        assert_eq!(texts, vec![(0, 4), (4, 10), (10, 16), (16, 20), (20, 25), (26, 30)]);
+        assert!(lines.iter().all(|line| line.width <= 300.0));
+        assert_eq!(lines[5].glyphs[0].char_index, 26);
+        assert!((lines[5].baseline - 5.0 * 100.0 * LINE_HEIGHT).abs() < 1e-9);
+
+        // A word wider than the line is broken inside
+        let narrow = ParagraphStyle { width: Some(60.0), ..ParagraphStyle::default() };
+        let lines = layout_text(&[ROBOTO], &text("WWWW", 100.0, 0.0, 0.0, narrow.clone())).unwrap();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(layout_text(&[ROBOTO], &text("", 100.0, 0.0, 0.0, narrow.clone())).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_alignment_and_indent() {
+        let layout = |align: TextAlign, width: Option<f64>| {
+            let paragraph = ParagraphStyle { width, align, indent: 20.0, ..ParagraphStyle::default() };
+            layout_text(&[ROBOTO], &text("aaa bbb ccc ddd", 50.0, 0.0, 0.0, paragraph.clone())).unwrap()
+        };
+        let left = layout(TextAlign::Left, Some(200.0));
+        assert!(left.len() > 1);
+        assert_eq!((left[0].x, left[1].x), (20.0, 0.0));
+        let right = layout(TextAlign::Right, Some(200.0));
+        assert!((right[1].x + right[1].width - 200.0).abs() < 1e-9);
+        let center = layout(TextAlign::Center, Some(200.0));
+        assert!((center[1].x * 2.0 + center[1].width - 200.0).abs() < 1e-9);
+
+        // Justified lines end at the width except the last one
+        let justify = layout(TextAlign::Justify, Some(200.0));
+        let last_letter = left[0].glyphs.iter().rposition(|glyph| "aaa bbb ccc ddd".as_bytes()[glyph.char_index] != b' ').unwrap();
+        let shift = justify[0].glyphs[last_letter].x - left[0].glyphs[last_letter].x;
+        assert!((shift - (200.0 - 20.0 - left[0].width)).abs() < 1e-9);
+        assert_eq!(justify.last().unwrap().glyphs, left.last().unwrap().glyphs);
+
+        // Point text aligns around its origin
+        let point = layout(TextAlign::Right, None);
+        assert_eq!(point.len(), 1);
+        assert!((point[0].x + point[0].width - 20.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_tracking_leading_and_features() {
+        let plain = text("fi 10", 100.0, 0.0, 0.0, ParagraphStyle::default());
+        let glyph_xs = |text: &TextObject| -> Vec<f64> {
+            layout_text(&[ROBOTO], text).unwrap()[0].glyphs.iter().map(|glyph| glyph.x).collect()
+        };
+
+        // 100/1000 em of tracking after each of the first four characters
+        let tracked = TextObject { typography: Typography { tracking: 100.0, ..Typography::default() }, ..plain.clone() };
+        let (before, after) = (glyph_xs(&plain), glyph_xs(&tracked));
+        assert_eq!(before.len(), after.len());
+        let last = before.len() - 1;
+        assert!((after[last] - before[last] - 10.0 * last as f64).abs() < 1e-3, "{:?} {:?}", before, after);
+
+        // Roboto has an "fi" ligature, which "-liga" turns off
+        let no_ligatures = TextObject { typography: Typography { features: vec!["-liga".to_string()], ..Typography::default() }, ..plain.clone() };
+        assert_eq!(glyph_xs(&no_ligatures).len(), glyph_xs(&plain).len() + 1);
+
+        let leading = ParagraphStyle { leading: Some(150.0), ..ParagraphStyle::default() };
+        let lines = layout_text(&[ROBOTO], &text("a\nb", 100.0, 0.0, 0.0, leading)).unwrap();
+        assert_eq!(lines[1].baseline, 150.0);
+
+        assert!(parse_features(&["smcp".to_string(), "kern=0".to_string(), " -liga".to_string()]).is_some());
+        assert!(parse_features(&["not a feature".to_string()]).is_none());
+    }
+
+    #[test]
+    fn test_text_kerning_is_indexed_through_the_content() {
+        let plain = text("AV\nAV", 100.0, 0.0, 0.0, ParagraphStyle::default());
+        // Roboto has 2048 units per em: +1 em in the first paragraph, -0.5 em in the second;
+        // the pair across the line break does nothing
+        let kerning = KerningOverrides::from([(0, 2048.0), (1, 500.0), (3, -1024.0)]);
+        let kerned = TextObject { typography: Typography { kerning, ..Typography::default() }, ..plain.clone() };
+        let (before, after) = (layout_text(&[ROBOTO], &plain).unwrap(), layout_text(&[ROBOTO], &kerned).unwrap());
+        let shift = |line: usize, glyph: usize| after[line].glyphs[glyph].x - before[line].glyphs[glyph].x;
+        assert_eq!((shift(0, 0), shift(1, 0)), (0.0, 0.0));
+        assert!((shift(0, 1) - 100.0).abs() < 1e-9);
+        assert!((shift(1, 1) + 50.0).abs() < 1e-9);
+        assert!((after[0].width - before[0].width - 100.0).abs() < 1e-9);
+        assert_eq!(paragraph_kerning(&kerned, 3, "AV"), KerningOverrides::from([(0, -1024.0)]));
+    }
+
+    #[test]
+    fn test_missing_characters_fall_back_to_other_fonts() {
+        const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+        let mixed = text("Hi 日本", 100.0, 0.0, 0.0, ParagraphStyle::default());
+        let fonts_of = |fonts: &[&[u8]]| -> Vec<usize> {
+            layout_text(fonts, &mixed).unwrap()[0].glyphs.iter().map(|glyph| glyph.font).collect()
+        };
+        assert_eq!(fonts_of(&[ROBOTO, NOTO_JP]), vec![0, 0, 0, 1, 1]);
+        // Without a fallback the Japanese characters are missing glyphs in the first font
+        assert_eq!(fonts_of(&[ROBOTO]), vec![0; 5]);
+
+        // The fallback run continues after the first one
+        let lines = layout_text(&[ROBOTO, NOTO_JP], &mixed).unwrap();
+        let glyphs = &lines[0].glyphs;
+        assert!(glyphs[3].x > glyphs[2].x && glyphs[4].x - glyphs[3].x > 90.0);
+        // The kanji are drawn from the fallback font instead of missing-glyph boxes
+        let kanji = text("日本", 100.0, 0.0, 0.0, ParagraphStyle::default());
+        assert_eq!(text_outline(&[ROBOTO, NOTO_JP], &kanji), text_outline(&[NOTO_JP], &kanji));
+        assert_ne!(text_outline(&[ROBOTO], &kanji), text_outline(&[NOTO_JP], &kanji));
+    }
+
+    #[test]
+    fn test_vertical_text_runs_down_columns() {
+        const NOTO_JP: &[u8] = include_bytes!("../../public/NotoSansJP-VariableFont_wght.ttf");
+        let vertical = ParagraphStyle { writing_mode: WritingMode::Vertical, ..ParagraphStyle::default() };
+        let lines = layout_text(&[NOTO_JP], &text("日本ab\n語", 100.0, 0.0, 0.0, vertical.clone())).unwrap();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.vertical));
+        // Columns stack to the left
+        assert_eq!((lines[0].baseline, lines[1].baseline), (0.0, -120.0));
+
+        let glyphs = &lines[0].glyphs;
+        assert_eq!(glyphs.iter().map(|glyph| glyph.sideways).collect::<Vec<_>>(), vec![false, false, true, true]);
+        // Kanji advance one em down the column, centered on it
+        assert_eq!((glyphs[0].inline, glyphs[1].inline), (0.0, 100.0));
+        assert!((glyphs[0].x + 50.0).abs() < 1.0 && glyphs[1].y > glyphs[0].y + 90.0);
+        // Latin letters follow on their side, advancing by their horizontal widths
+        assert!(glyphs[2].inline >= 200.0 && glyphs[3].inline > glyphs[2].inline);
+        assert!((lines[0].width - (glyphs[3].inline + glyphs[3].advance)).abs() < 1e-9);
+
+        // A sideways glyph is the upright one turned clockwise: its width runs down
+        let outline = text_outline(&[NOTO_JP], &text("日本ab", 100.0, 0.0, 0.0, vertical)).unwrap();
+        let upright = text_outline(&[NOTO_JP], &text("ab", 100.0, 0.0, 0.0, ParagraphStyle::default())).unwrap();
+        let extent = |commands: &[PathCommand], axis: fn((f64, f64)) -> f64| {
+            let values: Vec<f64> = commands.iter().filter_map(PathCommand::end_point).map(axis).collect();
+            values.iter().copied().fold(f64::MIN, f64::max) - values.iter().copied().fold(f64::MAX, f64::min)
+        };
+        let latin = &outline.commands[outline.commands.len() - upright.commands.len()..];
+        assert!((extent(latin, |point| point.1) - extent(&upright.commands, |point| point.0)).abs() < 1e-6);
+        assert!((extent(latin, |point| point.0) - extent(&upright.commands, |point| point.1)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_right_to_left_runs_are_reordered() {
+        let char_order = |content: &str| -> Vec<usize> {
+            let lines = layout_text(&[ROBOTO], &text(content, 100.0, 0.0, 0.0, ParagraphStyle::default())).unwrap();
+            let glyphs = &lines[0].glyphs;
+            // Glyphs are placed left to right in the order returned
+            assert!(glyphs.windows(2).all(|pair| pair[0].x + pair[0].advance <= pair[1].x + 1e-9));
+            glyphs.iter().map(|glyph| glyph.char_index).collect()
+        };
+        // A Hebrew word inside English text reads right to left
+        assert_eq!(char_order("abc שלום def"), vec![0, 1, 2, 3, 7, 6, 5, 4, 8, 9, 10, 11]);
+        // A Hebrew paragraph puts the English word after it on its left
+        assert_eq!(char_order("שלום abc"), vec![5, 6, 7, 4, 3, 2, 1, 0]);
+        // Numbers inside right-to-left text keep their order
+        assert_eq!(char_order("שם 42"), vec![3, 4, 2, 1, 0]);
+
+        let lines = layout_text(&[ROBOTO], &text("שלום abc ", 100.0, 0.0, 0.0, ParagraphStyle::default())).unwrap();
+        let glyphs = &lines[0].glyphs;
+        assert!(glyphs.iter().filter(|glyph| glyph.char_index < 5).all(|glyph| glyph.rtl));
+        assert!(glyphs.iter().filter(|glyph| (5..8).contains(&glyph.char_index)).all(|glyph| !glyph.rtl));
+        // The trailing space ends the right-to-left line on its left, outside its width
+        assert_eq!(glyphs[0].char_index, 8);
+        assert!(glyphs[0].x < 0.0 && glyphs[1].x >= 0.0);
+        let last = &glyphs[glyphs.len() - 1];
+        assert!((last.x + last.advance - lines[0].width).abs() < 1e-9);
+    }
+
+    /// Roboto with COLR/CPAL tables making "o" a color glyph: its outline in red under
+    /// a "." in the text color
+    fn color_roboto() -> Vec<u8> {
+        let face = TtfFace::parse(ROBOTO, 0).unwrap();
+        let (o, period) = (face.glyph_index('o').unwrap().0, face.glyph_index('.').unwrap().0);
+        let mut colr = Vec::new();
+        for value in [0u16, 1, 0, 14, 0, 20, 2, o, 0, 2, o, 0, period, 0xFFFF] {
+            colr.extend(value.to_be_bytes());
+        }
+        let mut cpal = Vec::new();
+        for value in [0u16, 1, 1, 1, 0, 14, 0] {
+            cpal.extend(value.to_be_bytes());
+        }
+        cpal.extend([0, 0, 255, 255]);
+
+        // Shift the table directory by two records and append the new tables
+        let count = usize::from(u16::from_be_bytes([ROBOTO[4], ROBOTO[5]]));
+        let directory_end = 12 + 16 * count;
+        let shift = 32;
+        let mut records: Vec<([u8; 4], u32, u32)> = (0..count)
+            .map(|i| {
+                let record = &ROBOTO[12 + 16 * i..28 + 16 * i];
+                let field = |at: usize| u32::from_be_bytes([record[at], record[at + 1], record[at + 2], record[at + 3]]);
+                ([record[0], record[1], record[2], record[3]], field(8) + shift, field(12))
+            })
+            .collect();
+        let mut appended = ROBOTO[directory_end..].to_vec();
+        for (tag, table) in [(*b"COLR", &colr), (*b"CPAL", &cpal)] {
+            while !appended.len().is_multiple_of(4) {
+                appended.push(0);
+            }
+            records.push((tag, (directory_end as u32 + shift) + appended.len() as u32, table.len() as u32));
+            appended.extend(table);
+        }
+        records.sort_by_key(|record| record.0);
+        let mut font = ROBOTO[..12].to_vec();
+        font[4..6].copy_from_slice(&(count as u16 + 2).to_be_bytes());
+        for (tag, offset, length) in records {
+            font.extend(tag);
+            font.extend([0; 4]);
+            font.extend(offset.to_be_bytes());
+            font.extend(length.to_be_bytes());
+        }
+        font.extend(appended);
+        font
+    }
+
+    #[test]
+    fn test_color_glyphs_are_painted_in_layers() {
+        let color_font = color_roboto();
+        let word = text("Hi o", 100.0, 0.0, 0.0, ParagraphStyle::default());
+        let color = text_outline(&[&color_font], &word).unwrap();
+        let plain = text_outline(&[ROBOTO], &word).unwrap();
+        assert!(plain.color_layers.is_empty());
+
+        // The color glyph leaves the plain outline for its layers
+        let without_o = text_outline(&[ROBOTO], &text("Hi ", 100.0, 0.0, 0.0, ParagraphStyle::default())).unwrap();
+        assert_eq!(color.commands, without_o.commands);
+        let colors: Vec<Option<&str>> = color.color_layers.iter().map(|layer| layer.color.as_deref()).collect();
+        assert_eq!(colors, vec![Some("#ff0000"), None]);
+        // Layers are placed like the glyph they replace
+        assert_eq!(color.color_layers[0].commands, plain.commands[without_o.commands.len()..]);
+        assert!(!color.color_layers[1].commands.is_empty());
+    }
+
+    #[test]
+    fn test_glyph_outlines_skip_blank_glyphs() {
+        let hello = text("Hi yo", 100.0, 10.0, 20.0, ParagraphStyle::default());
+        let glyphs = glyph_outlines(&[ROBOTO], &hello).unwrap();
+        assert_eq!(glyphs.iter().map(|(index, _)| *index).collect::<Vec<_>>(), vec![0, 1, 3, 4]);
+        let merged: Vec<PathCommand> = glyphs.into_iter().flat_map(|(_, outline)| outline.commands).collect();
+        assert_eq!(Some(merged), text_outline(&[ROBOTO], &hello).map(|outline| outline.commands));
+    }
+
+    #[test]
+    fn test_invalid_font_is_not_a_panic() {
+        assert_eq!(convert_text_to_svg(&[0, 1, 2], "A"), "");
+        assert_eq!(get_shaped_glyphs(&[], "A", "{}"), "[]");
+    }
+}
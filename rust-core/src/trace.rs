@@ -0,0 +1,371 @@
+//! Trace Module - Vectorizing bitmaps into filled paths
+//!
+//! The image is composited over white and reduced to a few gray levels (one
+//! level is plain black-and-white thresholding). Each level's mask is traced
+//! along pixel edges into closed outlines, speckles below a minimum area are
+//! dropped, and the staircase outlines are simplified to polygons. Like
+//! potrace, every polygon vertex then becomes either a corner or a smooth
+//! cubic Bézier running between the midpoints of its two edges, depending on
+//! how sharply the outline turns there.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tiny_skia::Pixmap;
+
+use crate::core::scene::PathCommand;
+use crate::path_ops::Polygon;
+
+/// Largest bitmap we agree to trace, in pixels
+const MAX_PIXELS: usize = 4096 * 4096;
+
+/// Most gray levels a trace can produce
+pub const MAX_LEVELS: u32 = 8;
+
+/// Largest distance (in pixels) between a staircase outline and its simplified polygon
+const SIMPLIFY_TOLERANCE: f64 = 0.75;
+
+/// How to trace a bitmap
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TraceOptions {
+    /// Luminance (0..255) below which a pixel is ink, when tracing a single level
+    pub threshold: f64,
+    /// Number of gray levels to trace (1 = black and white, at most MAX_LEVELS)
+    pub levels: u32,
+    /// Outlines enclosing fewer pixels than this are dropped (speckles and pinholes)
+    pub min_area: f64,
+    /// Corner threshold: 0 keeps straight polygon edges, larger values round more
+    /// vertices (potrace's alphamax; 1.0 is a good default, 4/3 rounds everything)
+    pub smoothing: f64,
+    /// World units per image pixel
+    pub scale: f64,
+    /// World position of the image's top-left corner
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Default for TraceOptions {
+    fn default() -> Self {
+        TraceOptions { threshold: 128.0, levels: 1, min_area: 4.0, smoothing: 1.0, scale: 1.0, x: 0.0, y: 0.0 }
+    }
+}
+
+/// One traced gray level: a compound path filled with the level's color
+#[derive(Debug, Clone, PartialEq)]
+pub struct TracedLayer {
+    /// Fill color ("#rrggbb")
+    pub color: String,
+    /// Closed subpaths of the outlines and holes (fill with the even-odd rule)
+    pub commands: Vec<PathCommand>,
+}
+
+/// Decode a PNG and trace it
+/// Returns None if the image cannot be decoded, is too large, or the options are invalid.
+pub fn trace_png(png: &[u8], options: &TraceOptions) -> Option<Vec<TracedLayer>> {
+    let pixmap = Pixmap::decode_png(png).ok()?;
+    let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+    if width * height > MAX_PIXELS {
+        return None;
+    }
+    // Pixels are premultiplied, so compositing over white adds the missing coverage
+    let luminance = pixmap
+        .pixels()
+        .iter()
+        .map(|pixel| {
+            let gray = 0.2126 * pixel.red() as f64 + 0.7152 * pixel.green() as f64 + 0.0722 * pixel.blue() as f64;
+            gray + (255 - pixel.alpha()) as f64
+        })
+        .collect::<Vec<_>>();
+    trace_luminance(&luminance, width, height, options)
+}
+
+/// Trace a grayscale image given as row-major luminance values in 0..255
+/// Layers come out bottom to top: lighter levels cover more area and are painted first.
+pub fn trace_luminance(luminance: &[f64], width: usize, height: usize, options: &TraceOptions) -> Option<Vec<TracedLayer>> {
+    let finite = [options.threshold, options.min_area, options.smoothing, options.scale, options.x, options.y];
+    if luminance.len() != width * height
+        || !finite.iter().all(|value| value.is_finite())
+        || options.scale <= 0.0
+        || !(1..=MAX_LEVELS).contains(&options.levels)
+    {
+        return None;
+    }
+
+    let levels = options.levels;
+    let mut layers = Vec::new();
+    for level in (1..=levels).rev() {
+        let threshold = if levels == 1 { options.threshold } else { 255.0 * level as f64 / (levels + 1) as f64 };
+        let mask: Vec<bool> = luminance.iter().map(|&value| value < threshold).collect();
+        let outlines: Vec<Polygon> = trace_outlines(&mask, width, height)
+            .into_iter()
+            .filter(|outline| signed_area(outline).abs() >= options.min_area)
+            .map(|outline| simplify_closed(&outline, SIMPLIFY_TOLERANCE))
+            .filter(|polygon| polygon.len() >= 3)
+            .collect();
+        if outlines.is_empty() {
+            continue;
+        }
+
+        let place = |(px, py): (f64, f64)| (options.x + px * options.scale, options.y + py * options.scale);
+        let mut commands = Vec::new();
+        for polygon in &outlines {
+            let placed: Polygon = polygon.iter().map(|&point| place(point)).collect();
+            commands.extend(fit_curves(&placed, options.smoothing));
+        }
+        let gray = (255.0 * (level - 1) as f64 / levels as f64).round() as u8;
+        layers.push(TracedLayer { color: format!("#{:02x}{:02x}{:02x}", gray, gray, gray), commands });
+    }
+    Some(layers)
+}
+
+/// Closed outlines along the pixel edges between ink and background
+/// Outer boundaries run clockwise on screen and holes counterclockwise.
+fn trace_outlines(mask: &[bool], width: usize, height: usize) -> Vec<Polygon> {
+    let ink = |x: i64, y: i64| {
+        x >= 0 && y >= 0 && (x as usize) < width && (y as usize) < height && mask[y as usize * width + x as usize]
+    };
+
+    // Directed boundary edges with ink on their right (clockwise around ink)
+    let mut edges: Vec<((i64, i64), (i64, i64))> = Vec::new();
+    for y in 0..height as i64 {
+        for x in 0..width as i64 {
+            if !ink(x, y) {
+                continue;
+            }
+            if !ink(x, y - 1) {
+                edges.push(((x, y), (x + 1, y)));
+            }
+            if !ink(x + 1, y) {
+                edges.push(((x + 1, y), (x + 1, y + 1)));
+            }
+            if !ink(x, y + 1) {
+                edges.push(((x + 1, y + 1), (x, y + 1)));
+            }
+            if !ink(x - 1, y) {
+                edges.push(((x, y + 1), (x, y)));
+            }
+        }
+    }
+    let mut outgoing: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    for (index, &(from, _)) in edges.iter().enumerate() {
+        outgoing.entry(from).or_default().push(index);
+    }
+
+    let mut used = vec![false; edges.len()];
+    let mut outlines = Vec::new();
+    for start in 0..edges.len() {
+        if used[start] {
+            continue;
+        }
+        let mut outline = Vec::new();
+        let mut current = start;
+        loop {
+            used[current] = true;
+            let (from, to) = edges[current];
+            outline.push((from.0 as f64, from.1 as f64));
+            let direction = (to.0 - from.0, to.1 - from.1);
+            // Where two ink pixels touch only at a corner, turn right so they stay apart
+            let next = outgoing.get(&to).and_then(|candidates| {
+                candidates.iter().copied().filter(|&edge| !used[edge]).max_by_key(|&edge| {
+                    let (next_from, next_to) = edges[edge];
+                    let turn = (next_to.0 - next_from.0, next_to.1 - next_from.1);
+                    direction.0 * turn.1 - direction.1 * turn.0
+                })
+            });
+            match next {
+                Some(edge) => current = edge,
+                None => break,
+            }
+        }
+        outlines.push(remove_collinear(outline));
+    }
+    outlines
+}
+
+/// Drop staircase vertices in the middle of straight runs
+fn remove_collinear(outline: Polygon) -> Polygon {
+    let count = outline.len();
+    (0..count)
+        .filter(|&i| {
+            let (prev, here, next) = (outline[(i + count - 1) % count], outline[i], outline[(i + 1) % count]);
+            (here.0 - prev.0) * (next.1 - here.1) - (here.1 - prev.1) * (next.0 - here.0) != 0.0
+        })
+        .map(|i| outline[i])
+        .collect()
+}
+
+fn signed_area(polygon: &Polygon) -> f64 {
+    let count = polygon.len();
+    (0..count)
+        .map(|i| {
+            let (a, b) = (polygon[i], polygon[(i + 1) % count]);
+            a.0 * b.1 - b.0 * a.1
+        })
+        .sum::<f64>()
+        / 2.0
+}
+
+/// Ramer-Douglas-Peucker simplification of a closed polygon
+/// The first vertex and the vertex farthest from it are always kept.
+fn simplify_closed(polygon: &Polygon, tolerance: f64) -> Polygon {
+    if polygon.len() < 4 {
+        return polygon.clone();
+    }
+    let first = polygon[0];
+    let far = (1..polygon.len())
+        .max_by(|&a, &b| distance(first, polygon[a]).total_cmp(&distance(first, polygon[b])))
+        .unwrap_or(1);
+
+    let mut result = Vec::new();
+    simplify_run(&polygon[..=far], tolerance, &mut result);
+    let mut closing: Polygon = polygon[far..].to_vec();
+    closing.push(first);
+    simplify_run(&closing, tolerance, &mut result);
+    result
+}
+
+/// Append the simplified run to `out`, without its last point
+fn simplify_run(points: &[(f64, f64)], tolerance: f64, out: &mut Polygon) {
+    let (Some(&start), Some(&end)) = (points.first(), points.last()) else {
+        return;
+    };
+    let farthest = (1..points.len().saturating_sub(1))
+        .map(|i| (i, distance_to_line(points[i], start, end)))
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    match farthest {
+        Some((i, offset)) if offset > tolerance => {
+            simplify_run(&points[..=i], tolerance, out);
+            simplify_run(&points[i..], tolerance, out);
+        }
+        _ => out.push(start),
+    }
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    (b.0 - a.0).hypot(b.1 - a.1)
+}
+
+fn distance_to_line(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let length = distance(a, b);
+    if length == 0.0 {
+        return distance(point, a);
+    }
+    ((b.0 - a.0) * (point.1 - a.1) - (b.1 - a.1) * (point.0 - a.0)).abs() / length
+}
+
+/// Closed path through the midpoints of the polygon's edges
+/// Each vertex becomes a corner (two lines meeting at the vertex) when it sticks out far
+/// from the chord between its neighbours, otherwise a cubic pulled towards the vertex.
+fn fit_curves(polygon: &Polygon, smoothing: f64) -> Vec<PathCommand> {
+    let count = polygon.len();
+    let midpoint = |i: usize| {
+        let (a, b) = (polygon[i % count], polygon[(i + 1) % count]);
+        ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0)
+    };
+    let toward = |from: (f64, f64), to: (f64, f64), t: f64| (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+
+    let start = midpoint(count - 1);
+    let mut commands = vec![PathCommand::MoveTo { x: start.0, y: start.1 }];
+    for j in 0..count {
+        let (prev, vertex, next) = (polygon[(j + count - 1) % count], polygon[j], polygon[(j + 1) % count]);
+        let end = midpoint(j);
+        // Height of the vertex over the chord, in pixels of the traced outline
+        let height = distance_to_line(vertex, prev, next);
+        let alpha = if height > 1.0 { (1.0 - 1.0 / height) / 0.75 } else { 0.0 };
+        if alpha >= smoothing {
+            commands.push(PathCommand::LineTo { x: vertex.0, y: vertex.1 });
+            commands.push(PathCommand::LineTo { x: end.0, y: end.1 });
+        } else {
+            let t = 0.5 + 0.5 * alpha.clamp(0.55, 1.0);
+            let (c1, c2) = (toward(prev, vertex, t), toward(next, vertex, t));
+            commands.push(PathCommand::CurveTo { x1: c1.0, y1: c1.1, x2: c2.0, y2: c2.1, x: end.0, y: end.1 });
+        }
+    }
+    commands.push(PathCommand::ClosePath);
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// White image with a black rectangle
+    fn image_with_square(width: usize, height: usize, (x0, y0, x1, y1): (usize, usize, usize, usize)) -> Vec<f64> {
+        (0..width * height)
+            .map(|i| if (x0..x1).contains(&(i % width)) && (y0..y1).contains(&(i / width)) { 0.0 } else { 255.0 })
+            .collect()
+    }
+
+    fn anchors(commands: &[PathCommand]) -> Vec<(f64, f64)> {
+        commands.iter().filter_map(|command| command.end_point()).collect()
+    }
+
+    #[test]
+    fn test_square_traces_to_corners() {
+        let image = image_with_square(30, 30, (5, 6, 25, 26));
+        let layers = trace_luminance(&image, 30, 30, &TraceOptions::default()).expect("traced");
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[0].color, "#000000");
+        // All four vertices are corners, so the outline passes through them exactly
+        let points = anchors(&layers[0].commands);
+        for corner in [(5.0, 6.0), (25.0, 6.0), (25.0, 26.0), (5.0, 26.0)] {
+            assert!(points.contains(&corner), "{:?} missing from {:?}", corner, points);
+        }
+        assert!(layers[0].commands.iter().all(|command| !matches!(command, PathCommand::CurveTo { .. })));
+    }
+
+    #[test]
+    fn test_disc_traces_to_curves() {
+        let (size, radius) = (40usize, 15.0);
+        let image: Vec<f64> = (0..size * size)
+            .map(|i| {
+                let (x, y) = ((i % size) as f64 + 0.5 - 20.0, (i / size) as f64 + 0.5 - 20.0);
+                if x.hypot(y) < radius { 0.0 } else { 255.0 }
+            })
+            .collect();
+        let layers = trace_luminance(&image, size, size, &TraceOptions::default()).expect("traced");
+        let commands = &layers[0].commands;
+        assert!(commands.iter().all(|command| !matches!(command, PathCommand::LineTo { .. })));
+        for (x, y) in anchors(commands) {
+            let offset = (x - 20.0).hypot(y - 20.0) - radius;
+            assert!(offset.abs() < 1.0, "({}, {}) is {} off the circle", x, y, offset);
+        }
+    }
+
+    #[test]
+    fn test_holes_speckles_and_placement() {
+        let mut image = image_with_square(12, 12, (1, 1, 11, 11));
+        for (x, y) in [(4, 4), (5, 4), (4, 5), (5, 5), (6, 4), (6, 5), (4, 6), (5, 6), (6, 6)] {
+            image[y * 12 + x] = 255.0;
+        }
+        image[9 * 12 + 9] = 255.0;
+        let options = TraceOptions { smoothing: 0.0, scale: 2.0, x: 100.0, ..TraceOptions::default() };
+        let layers = trace_luminance(&image, 12, 12, &options).expect("traced");
+        // Outer boundary plus the 3x3 hole; the single-pixel pinhole is dropped
+        let subpaths = layers[0].commands.iter().filter(|c| matches!(c, PathCommand::MoveTo { .. })).count();
+        assert_eq!(subpaths, 2);
+        let points = anchors(&layers[0].commands);
+        assert!(points.contains(&(102.0, 2.0)) && points.contains(&(108.0, 8.0)), "{:?}", points);
+    }
+
+    #[test]
+    fn test_levels_stack_light_to_dark() {
+        let mut image = vec![255.0; 100];
+        image[..50].fill(100.0);
+        image[..20].fill(10.0);
+        let options = TraceOptions { levels: 2, min_area: 0.0, ..TraceOptions::default() };
+        let layers = trace_luminance(&image, 10, 10, &options).expect("traced");
+        let colors: Vec<&str> = layers.iter().map(|layer| layer.color.as_str()).collect();
+        assert_eq!(colors, ["#808080", "#000000"]);
+    }
+
+    #[test]
+    fn test_rejects_invalid_input() {
+        let image = vec![0.0; 4];
+        assert!(trace_luminance(&image, 3, 1, &TraceOptions::default()).is_none());
+        assert!(trace_luminance(&image, 2, 2, &TraceOptions { levels: 0, ..TraceOptions::default() }).is_none());
+        assert!(trace_luminance(&image, 2, 2, &TraceOptions { scale: 0.0, ..TraceOptions::default() }).is_none());
+        assert!(trace_png(b"not a png", &TraceOptions::default()).is_none());
+        assert_eq!(trace_luminance(&[255.0; 4], 2, 2, &TraceOptions::default()), Some(vec![]));
+    }
+}
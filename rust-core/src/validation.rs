@@ -0,0 +1,605 @@
+//! Validation Module - Limits and sanity checks for untrusted documents
+//!
+//! Imported files may be corrupted or hostile. Before a parsed scene replaces
+//! the current one, it is checked against size limits (so it cannot hang or
+//! exhaust the memory of the Wasm instance) and for non-finite numbers.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tsify::Tsify;
+
+use crate::animation::KeyValue;
+use crate::core::math::TransformMatrix;
+use crate::core::scene::{PathCommand, SceneGraph, SceneNode, VectorObject};
+use crate::pattern::{PatternFill, PatternSource};
+
+/// Resource limits applied when importing documents
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ImportLimits {
+    /// Maximum size of the raw input in bytes
+    pub max_input_bytes: usize,
+    /// Maximum number of scene nodes (groups, leaves and instances), both as stored and
+    /// as drawn with every instance expanded
+    pub max_nodes: usize,
+    /// Maximum number of commands in a single path
+    pub max_path_commands: usize,
+    /// Maximum group nesting depth
+    pub max_depth: usize,
+}
+
+impl Default for ImportLimits {
+    fn default() -> Self {
+        ImportLimits {
+            max_input_bytes: 64 * 1024 * 1024,
+            max_nodes: 100_000,
+            max_path_commands: 100_000,
+            max_depth: 32,
+        }
+    }
+}
+
+/// Machine-readable import error category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Tsify)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportErrorCode {
+    InputTooLarge,
+    TooDeep,
+    ParseError,
+    TooManyNodes,
+    TooManyPathCommands,
+    NonFiniteNumber,
+    InvalidSymbol,
+    UnsupportedVersion,
+}
+
+/// Where in the input an import error was found
+/// Only the fields that apply are set: line and column in JSON text, the byte offset
+/// in SVG path data, or the object whose values were rejected.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct ErrorLocation {
+    /// 1-based line in JSON text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// 1-based column in JSON text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// Byte offset in path data
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub object_id: Option<String>,
+}
+
+/// Structured import error
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Tsify)]
+pub struct ImportError {
+    pub code: ImportErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<ErrorLocation>,
+}
+
+impl ImportError {
+    pub fn new(code: ImportErrorCode, message: impl Into<String>) -> Self {
+        ImportError { code, message: message.into(), location: None }
+    }
+
+    /// A JSON syntax or shape error, located at its line and column when known
+    pub fn json(error: &serde_json::Error) -> Self {
+        let parse_error = ImportError::new(ImportErrorCode::ParseError, error.to_string());
+        if error.line() == 0 {
+            return parse_error;
+        }
+        parse_error.at(ErrorLocation { line: Some(error.line()), column: Some(error.column()), ..Default::default() })
+    }
+
+    pub fn at(self, location: ErrorLocation) -> Self {
+        ImportError { location: Some(location), ..self }
+    }
+
+    /// Located at an offset in path data
+    pub fn at_offset(self, offset: usize) -> Self {
+        self.at(ErrorLocation { offset: Some(offset), ..Default::default() })
+    }
+
+    /// Located at an object of the document
+    pub fn at_object(self, id: &str) -> Self {
+        self.at(ErrorLocation { object_id: Some(id.to_string()), ..Default::default() })
+    }
+}
+
+/// Cheap pre-parse checks on raw JSON text: input size and bracket nesting depth.
+/// Runs before deserialization so deeply nested input is rejected without recursion.
+pub fn check_json_input(json: &str, limits: &ImportLimits) -> Result<(), ImportError> {
+    if json.len() > limits.max_input_bytes {
+        return Err(ImportError::new(
+            ImportErrorCode::InputTooLarge,
+            format!("input is {} bytes, limit is {}", json.len(), limits.max_input_bytes),
+        ));
+    }
+
+    JsonScanner::new(limits).feed(json.as_bytes())
+}
+
+/// Bracket nesting scanner for JSON text that may arrive in pieces
+/// State carries over between calls to `feed`, so chunk boundaries can fall anywhere.
+#[derive(Debug, Clone)]
+pub struct JsonScanner {
+    max_json_depth: usize,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+}
+
+impl JsonScanner {
+    pub fn new(limits: &ImportLimits) -> Self {
+        JsonScanner {
+            // Each group level costs a few JSON levels (node, variant, children array)
+            max_json_depth: limits.max_depth * 4 + 8,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+        }
+    }
+
+    /// Scan the next piece of input
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), ImportError> {
+        for &byte in bytes {
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => {
+                    self.depth += 1;
+                    if self.depth > self.max_json_depth {
+                        return Err(ImportError::new(
+                            ImportErrorCode::TooDeep,
+                            format!("JSON nesting exceeds {} levels", self.max_json_depth),
+                        ));
+                    }
+                }
+                b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Validate a parsed scene against the limits
+pub fn validate_scene(scene: &SceneGraph, limits: &ImportLimits) -> Result<(), ImportError> {
+    let mut node_count = 0usize;
+    check_nodes(&scene.roots, limits, &mut node_count)?;
+    validate_checked_scene(scene, limits, node_count)
+}
+
+/// Check top-level nodes on their own, before the rest of their scene is known:
+/// nesting, node count (added to `node_count`), transforms and geometry
+/// References to symbols are checked by validate_checked_scene.
+pub fn check_nodes(nodes: &[SceneNode], limits: &ImportLimits, node_count: &mut usize) -> Result<(), ImportError> {
+    validate_nodes(nodes, 1, limits, node_count)
+}
+
+/// Validate the rest of a scene whose roots passed check_nodes, counting `node_count` nodes
+pub fn validate_checked_scene(scene: &SceneGraph, limits: &ImportLimits, mut node_count: usize) -> Result<(), ImportError> {
+    for symbol in &scene.symbols {
+        validate_nodes(&symbol.children, 1, limits, &mut node_count)?;
+    }
+    check_references(scene, &scene.roots)?;
+    for symbol in &scene.symbols {
+        check_references(scene, &symbol.children)?;
+    }
+
+    // Instances nested through symbols must terminate (this also rejects cycles), and
+    // the scene as drawn, with every instance expanded, must stay within the node limit
+    let mut expansions = HashMap::new();
+    for symbol in &scene.symbols {
+        symbol_expansion(scene, &symbol.id, 1, limits, &mut expansions)?;
+    }
+    if expand_nodes(scene, &scene.roots, 0, limits, &mut expansions)?.nodes > limits.max_nodes {
+        return Err(ImportError::new(
+            ImportErrorCode::TooManyNodes,
+            format!("scene expands to more than {} nodes through its instances", limits.max_nodes),
+        ));
+    }
+
+    for shared in &scene.shared_styles {
+        check_finite(&shared.id, "stroke_width", &[shared.style.stroke_width])?;
+        check_finite(&shared.id, "opacity", &[shared.style.opacity])?;
+        if let Some(pattern) = &shared.style.fill_pattern {
+            validate_pattern(scene, &shared.id, pattern)?;
+        }
+    }
+
+    for guide in &scene.guides {
+        check_finite(&guide.id, "position", &[guide.position])?;
+    }
+
+    let document = &scene.document;
+    check_finite("document", "page", &[document.width, document.height, document.dpi])?;
+
+    for artboard in &scene.artboards {
+        check_finite(&artboard.id, "frame", &[artboard.x, artboard.y, artboard.width, artboard.height])?;
+    }
+
+    let timeline = &scene.timeline;
+    check_finite("timeline", "playback", &[timeline.duration, timeline.fps, timeline.current_time])?;
+    for track in &timeline.tracks {
+        for keyframe in &track.keyframes {
+            let values = match &keyframe.value {
+                KeyValue::Number(value) => vec![keyframe.time, *value],
+                KeyValue::Pair([x, y]) => vec![keyframe.time, *x, *y],
+                KeyValue::Color(_) => vec![keyframe.time],
+            };
+            check_finite(&track.object_id, "keyframe", &values)?;
+        }
+    }
+    Ok(())
+}
+
+fn validate_nodes(
+    nodes: &[SceneNode],
+    depth: usize,
+    limits: &ImportLimits,
+    node_count: &mut usize,
+) -> Result<(), ImportError> {
+    if depth > limits.max_depth {
+        return Err(ImportError::new(
+            ImportErrorCode::TooDeep,
+            format!("group nesting exceeds {} levels", limits.max_depth),
+        ));
+    }
+
+    for node in nodes {
+        *node_count += 1;
+        if *node_count > limits.max_nodes {
+            return Err(ImportError::new(
+                ImportErrorCode::TooManyNodes,
+                format!("scene has more than {} nodes", limits.max_nodes),
+            ));
+        }
+
+        match node {
+            SceneNode::Group { id, children, transform, .. } => {
+                check_transform(id, transform)?;
+                validate_nodes(children, depth + 1, limits, node_count)?;
+            }
+            SceneNode::Leaf { id, object, transform, style, .. } => {
+                check_transform(id, transform)?;
+                check_finite(id, "stroke_width", &[style.stroke_width])?;
+                check_finite(id, "opacity", &[style.opacity])?;
+                if let Some(pattern) = &style.fill_pattern {
+                    check_pattern_numbers(id, pattern)?;
+                }
+                validate_object(id, object, limits)?;
+            }
+            SceneNode::Instance { id, transform, .. } => check_transform(id, transform)?,
+        }
+    }
+    Ok(())
+}
+
+/// Check that instances and pattern fills refer to symbols of the scene
+fn check_references(scene: &SceneGraph, nodes: &[SceneNode]) -> Result<(), ImportError> {
+    for node in nodes {
+        match node {
+            SceneNode::Group { children, .. } => check_references(scene, children)?,
+            SceneNode::Leaf { id, style, .. } => {
+                if let Some(pattern) = &style.fill_pattern {
+                    check_pattern_symbol(scene, id, pattern)?;
+                }
+            }
+            SceneNode::Instance { id, symbol_id, .. } => {
+                if scene.get_symbol(symbol_id).is_none() {
+                    return Err(ImportError::new(
+                        ImportErrorCode::InvalidSymbol,
+                        format!("instance '{}' references unknown symbol '{}'", id, symbol_id),
+                    )
+                    .at_object(id));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Check a pattern fill: finite numbers and an existing symbol
+fn validate_pattern(scene: &SceneGraph, id: &str, pattern: &PatternFill) -> Result<(), ImportError> {
+    check_pattern_numbers(id, pattern)?;
+    check_pattern_symbol(scene, id, pattern)
+}
+
+fn check_pattern_symbol(scene: &SceneGraph, id: &str, pattern: &PatternFill) -> Result<(), ImportError> {
+    match &pattern.source {
+        PatternSource::Image { .. } => Ok(()),
+        PatternSource::Symbol { symbol_id } if scene.get_symbol(symbol_id).is_none() => Err(ImportError::new(
+            ImportErrorCode::InvalidSymbol,
+            format!("pattern of '{}' references unknown symbol '{}'", id, symbol_id),
+        )
+        .at_object(id)),
+        PatternSource::Symbol { .. } => Ok(()),
+    }
+}
+
+fn check_pattern_numbers(id: &str, pattern: &PatternFill) -> Result<(), ImportError> {
+    let [gap_x, gap_y] = pattern.spacing;
+    check_finite(id, "pattern", &[gap_x, gap_y, pattern.scale, pattern.rotation])?;
+    match &pattern.source {
+        PatternSource::Image { width, height, .. } => check_finite(id, "pattern", &[*width, *height]),
+        PatternSource::Symbol { .. } => Ok(()),
+    }
+}
+
+/// Instance nesting depth of a symbol and its node count with every instance expanded
+#[derive(Debug, Clone, Copy)]
+struct Expansion {
+    depth: usize,
+    nodes: usize,
+}
+
+/// Expansion of a symbol (memoized per symbol)
+fn symbol_expansion<'a>(
+    scene: &'a SceneGraph,
+    symbol_id: &'a str,
+    trail: usize,
+    limits: &ImportLimits,
+    expansions: &mut HashMap<&'a str, Expansion>,
+) -> Result<Expansion, ImportError> {
+    if let Some(expansion) = expansions.get(symbol_id) {
+        return Ok(*expansion);
+    }
+    if trail > limits.max_depth {
+        return Err(ImportError::new(
+            ImportErrorCode::InvalidSymbol,
+            format!("symbol '{}' nests instances more than {} levels deep or references itself", symbol_id, limits.max_depth),
+        )
+        .at_object(symbol_id));
+    }
+
+    let expansion = match scene.get_symbol(symbol_id) {
+        Some(symbol) => expand_nodes(scene, &symbol.children, trail, limits, expansions)?,
+        None => Expansion { depth: 1, nodes: 0 },
+    };
+    expansions.insert(symbol_id, expansion);
+    Ok(expansion)
+}
+
+/// Expansion of a node list, each instance counting as itself plus its symbol's contents
+fn expand_nodes<'a>(
+    scene: &'a SceneGraph,
+    nodes: &'a [SceneNode],
+    trail: usize,
+    limits: &ImportLimits,
+    expansions: &mut HashMap<&'a str, Expansion>,
+) -> Result<Expansion, ImportError> {
+    let mut expansion = Expansion { depth: 1, nodes: 0 };
+    for node in nodes {
+        let inner = match node {
+            SceneNode::Group { children, .. } => expand_nodes(scene, children, trail, limits, expansions)?,
+            SceneNode::Instance { symbol_id, .. } => {
+                let symbol = symbol_expansion(scene, symbol_id, trail + 1, limits, expansions)?;
+                Expansion { depth: symbol.depth + 1, nodes: symbol.nodes }
+            }
+            SceneNode::Leaf { .. } => Expansion { depth: 1, nodes: 0 },
+        };
+        expansion.depth = expansion.depth.max(inner.depth);
+        expansion.nodes = expansion.nodes.saturating_add(1).saturating_add(inner.nodes);
+    }
+    Ok(expansion)
+}
+
+/// Check a single object's geometry: path length limit and finite numbers
+pub fn validate_object(id: &str, object: &VectorObject, limits: &ImportLimits) -> Result<(), ImportError> {
+    match object {
+        VectorObject::Rectangle { x, y, width, height } => check_finite(id, "rectangle", &[*x, *y, *width, *height]),
+        VectorObject::Ellipse { cx, cy, rx, ry } => check_finite(id, "ellipse", &[*cx, *cy, *rx, *ry]),
+        VectorObject::Circle { cx, cy, r } => check_finite(id, "circle", &[*cx, *cy, *r]),
+        VectorObject::Text(text) => {
+            let paragraph = &text.paragraph;
+            let spacing = [paragraph.indent, paragraph.width.unwrap_or_default(), paragraph.leading.unwrap_or_default()];
+            check_finite(id, "text", &[text.size, text.x, text.y, text.typography.tracking])?;
+            check_finite(id, "text spacing", &spacing)?;
+            validate_object(id, &object.drawable(), limits)
+        }
+        VectorObject::Path { commands, .. } => {
+            if commands.len() > limits.max_path_commands {
+                return Err(ImportError::new(
+                    ImportErrorCode::TooManyPathCommands,
+                    format!(
+                        "path '{}' has {} commands, limit is {}",
+                        id,
+                        commands.len(),
+                        limits.max_path_commands
+                    ),
+                )
+                .at_object(id));
+            }
+            for cmd in commands {
+                match cmd {
+                    PathCommand::MoveTo { x, y } | PathCommand::LineTo { x, y } => {
+                        check_finite(id, "path", &[*x, *y])?;
+                    }
+                    PathCommand::CurveTo { x1, y1, x2, y2, x, y } => {
+                        check_finite(id, "path", &[*x1, *y1, *x2, *y2, *x, *y])?;
+                    }
+                    PathCommand::QuadTo { x1, y1, x, y } => {
+                        check_finite(id, "path", &[*x1, *y1, *x, *y])?;
+                    }
+                    PathCommand::ArcTo { rx, ry, rotation, x, y, .. } => {
+                        check_finite(id, "path", &[*rx, *ry, *rotation, *x, *y])?;
+                    }
+                    PathCommand::ClosePath => {}
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+fn check_transform(id: &str, t: &TransformMatrix) -> Result<(), ImportError> {
+    check_finite(id, "transform", &[t.a, t.b, t.c, t.d, t.tx, t.ty])
+}
+
+fn check_finite(id: &str, field: &str, values: &[f64]) -> Result<(), ImportError> {
+    if values.iter().all(|v| v.is_finite()) {
+        Ok(())
+    } else {
+        Err(ImportError::new(
+            ImportErrorCode::NonFiniteNumber,
+            format!("object '{}' has a non-finite {} value", id, field),
+        )
+        .at_object(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::scene::Symbol;
+
+    fn rect_leaf(id: &str) -> SceneNode {
+        SceneNode::Leaf {
+            id: id.to_string(),
+            object: VectorObject::Rectangle { x: 0.0, y: 0.0, width: 1.0, height: 1.0 },
+            transform: TransformMatrix::identity(),
+            style: Default::default(),
+            name: None,
+        }
+    }
+
+    #[test]
+    fn test_rejects_deep_json_before_parsing() {
+        let limits = ImportLimits { max_depth: 2, ..Default::default() };
+        let deep = "[".repeat(100) + &"]".repeat(100);
+        let err = check_json_input(&deep, &limits).unwrap_err();
+        assert_eq!(err.code, ImportErrorCode::TooDeep);
+
+        // Brackets inside strings don't count
+        let quoted = format!("{{\"name\": \"{}\"}}", "[".repeat(100));
+        assert!(check_json_input(&quoted, &limits).is_ok());
+    }
+
+    #[test]
+    fn test_node_and_command_limits() {
+        let limits = ImportLimits { max_nodes: 2, max_path_commands: 1, ..Default::default() };
+
+        let mut scene = SceneGraph::new();
+        scene.roots = vec![rect_leaf("a"), rect_leaf("b"), rect_leaf("c")];
+        assert_eq!(validate_scene(&scene, &limits).unwrap_err().code, ImportErrorCode::TooManyNodes);
+
+        scene.roots = vec![SceneNode::Leaf {
+            id: "p".to_string(),
+            object: VectorObject::Path {
+                commands: vec![PathCommand::MoveTo { x: 0.0, y: 0.0 }, PathCommand::LineTo { x: 1.0, y: 1.0 }],
+                is_closed: false,
+                anchor_types: Vec::new(),
+            },
+            transform: TransformMatrix::identity(),
+            style: Default::default(),
+            name: None,
+        }];
+        assert_eq!(
+            validate_scene(&scene, &limits).unwrap_err().code,
+            ImportErrorCode::TooManyPathCommands
+        );
+    }
+
+    #[test]
+    fn test_rejects_non_finite_numbers() {
+        let mut scene = SceneGraph::new();
+        let mut leaf = rect_leaf("a");
+        if let SceneNode::Leaf { transform, .. } = &mut leaf {
+            transform.tx = f64::NAN;
+        }
+        scene.roots = vec![leaf];
+        let error = validate_scene(&scene, &ImportLimits::default()).unwrap_err();
+        assert_eq!(error.code, ImportErrorCode::NonFiniteNumber);
+        assert_eq!(error.location.and_then(|location| location.object_id).as_deref(), Some("a"));
+    }
+
+    #[test]
+    fn test_json_errors_carry_line_and_column() {
+        let error = serde_json::from_str::<Vec<PathCommand>>("[\n  {\"type\": \"MoveTo\", \"x\": 1,}\n]").unwrap_err();
+        let error = ImportError::json(&error);
+        assert_eq!(error.code, ImportErrorCode::ParseError);
+        let location = error.location.clone().unwrap();
+        assert_eq!((location.line, location.column), (Some(2), Some(29)));
+        // Only the fields that apply are serialized
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["location"], serde_json::json!({ "line": 2, "column": 29 }));
+
+        let error = serde_json::from_value::<PathCommand>(serde_json::json!({ "type": "Spiral" })).unwrap_err();
+        assert_eq!(ImportError::json(&error).location, None);
+    }
+
+    #[test]
+    fn test_rejects_dangling_and_cyclic_symbols() {
+        let instance = |id: &str, symbol_id: &str| SceneNode::Instance {
+            id: id.to_string(),
+            symbol_id: symbol_id.to_string(),
+            transform: TransformMatrix::identity(),
+            name: None,
+        };
+
+        let mut scene = SceneGraph::new();
+        scene.roots = vec![instance("i1", "missing")];
+        assert_eq!(
+            validate_scene(&scene, &ImportLimits::default()).unwrap_err().code,
+            ImportErrorCode::InvalidSymbol
+        );
+
+        // a -> b -> a
+        scene.roots = vec![instance("i1", "a")];
+        scene.symbols = vec![
+            Symbol { id: "a".to_string(), name: "A".to_string(), children: vec![instance("i2", "b")] },
+            Symbol { id: "b".to_string(), name: "B".to_string(), children: vec![instance("i3", "a")] },
+        ];
+        assert_eq!(
+            validate_scene(&scene, &ImportLimits::default()).unwrap_err().code,
+            ImportErrorCode::InvalidSymbol
+        );
+
+        scene.symbols[1].children = vec![rect_leaf("r")];
+        assert!(validate_scene(&scene, &ImportLimits::default()).is_ok());
+    }
+
+    #[test]
+    fn test_counts_nodes_with_instances_expanded() {
+        let instance = |id: String, symbol_id: String| SceneNode::Instance {
+            id,
+            symbol_id,
+            transform: TransformMatrix::identity(),
+            name: None,
+        };
+        // Each symbol draws the previous one twice: a few stored nodes, 2^20 drawn leaves
+        let mut scene = SceneGraph::new();
+        scene.symbols.push(Symbol { id: "s0".to_string(), name: "S0".to_string(), children: vec![rect_leaf("r")] });
+        for level in 1..=20 {
+            let previous = format!("s{}", level - 1);
+            scene.symbols.push(Symbol {
+                id: format!("s{}", level),
+                name: format!("S{}", level),
+                children: vec![
+                    instance(format!("a{}", level), previous.clone()),
+                    instance(format!("b{}", level), previous),
+                ],
+            });
+        }
+        scene.roots = vec![instance("i".to_string(), "s20".to_string())];
+        let error = validate_scene(&scene, &ImportLimits::default()).unwrap_err();
+        assert_eq!(error.code, ImportErrorCode::TooManyNodes);
+
+        // Five levels draw 1 + 2 + 4 + ... + 32 instances and 32 leaves
+        scene.roots = vec![instance("i".to_string(), "s5".to_string())];
+        assert!(validate_scene(&scene, &ImportLimits { max_nodes: 95, ..Default::default() }).is_ok());
+        let limits = ImportLimits { max_nodes: 94, ..Default::default() };
+        assert_eq!(validate_scene(&scene, &limits).unwrap_err().code, ImportErrorCode::TooManyNodes);
+    }
+}
@@ -0,0 +1,104 @@
+//! Viewport Module - Pan and zoom of the canvas view
+//!
+//! The editor works in world coordinates; the frontend draws them scaled by the
+//! zoom and shifted by the pan. The viewport converts between the two so tool
+//! tolerances (handle sizes, snapping and close-path distances) can be given in
+//! screen pixels and stay the same size on screen at any zoom.
+
+use serde::{Deserialize, Serialize};
+
+use crate::core::math::TransformMatrix;
+
+/// Smallest and largest zoom factors accepted
+pub const MIN_ZOOM: f64 = 0.01;
+pub const MAX_ZOOM: f64 = 256.0;
+
+/// View of the world on screen: screen = world × zoom + pan
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Viewport {
+    /// Screen position of the world origin (pixels)
+    pub pan_x: f64,
+    pub pan_y: f64,
+    /// Screen pixels per world unit
+    pub zoom: f64,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Viewport { pan_x: 0.0, pan_y: 0.0, zoom: 1.0 }
+    }
+}
+
+impl Viewport {
+    /// A viewport, or None if a value is not finite or the zoom is outside
+    /// [`MIN_ZOOM`]..=[`MAX_ZOOM`]
+    pub fn new(pan_x: f64, pan_y: f64, zoom: f64) -> Option<Self> {
+        let valid = pan_x.is_finite() && pan_y.is_finite() && (MIN_ZOOM..=MAX_ZOOM).contains(&zoom);
+        valid.then_some(Viewport { pan_x, pan_y, zoom })
+    }
+
+    /// The viewport zoomed by `factor` around a screen point, which stays over the
+    /// same world point (zooming at the cursor). The zoom is clamped to its range
+    pub fn zoomed_at(&self, screen_x: f64, screen_y: f64, factor: f64) -> Option<Self> {
+        if !(factor.is_finite() && factor > 0.0) {
+            return None;
+        }
+        let (world_x, world_y) = self.screen_to_world(screen_x, screen_y);
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        Viewport::new(screen_x - world_x * zoom, screen_y - world_y * zoom, zoom)
+    }
+
+    /// World to screen transform (for drawing the scene)
+    pub fn transform(&self) -> TransformMatrix {
+        TransformMatrix::translate(self.pan_x, self.pan_y).multiply(&TransformMatrix::scale(self.zoom, self.zoom))
+    }
+
+    pub fn screen_to_world(&self, x: f64, y: f64) -> (f64, f64) {
+        ((x - self.pan_x) / self.zoom, (y - self.pan_y) / self.zoom)
+    }
+
+    pub fn world_to_screen(&self, x: f64, y: f64) -> (f64, f64) {
+        (x * self.zoom + self.pan_x, y * self.zoom + self.pan_y)
+    }
+
+    /// World length drawn `pixels` long on screen
+    pub fn world_distance(&self, pixels: f64) -> f64 {
+        pixels / self.zoom
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_screen_and_world_round_trip() {
+        let viewport = Viewport::new(100.0, -50.0, 4.0).unwrap();
+        assert_eq!(viewport.world_to_screen(10.0, 20.0), (140.0, 30.0));
+        assert_eq!(viewport.screen_to_world(140.0, 30.0), (10.0, 20.0));
+        assert_eq!(viewport.transform().transform_point(10.0, 20.0), (140.0, 30.0));
+        // Eight pixels are two world units at 400%
+        assert_eq!(viewport.world_distance(8.0), 2.0);
+    }
+
+    #[test]
+    fn test_invalid_viewports_are_rejected() {
+        assert!(Viewport::new(0.0, 0.0, 0.0).is_none());
+        assert!(Viewport::new(0.0, 0.0, -1.0).is_none());
+        assert!(Viewport::new(f64::NAN, 0.0, 1.0).is_none());
+        assert!(Viewport::new(0.0, 0.0, 1000.0).is_none());
+        assert_eq!(Viewport::default().screen_to_world(3.0, 4.0), (3.0, 4.0));
+    }
+
+    #[test]
+    fn test_zooming_keeps_the_point_under_the_cursor() {
+        let viewport = Viewport::new(20.0, 30.0, 2.0).unwrap();
+        let zoomed = viewport.zoomed_at(200.0, 100.0, 1.5).unwrap();
+        assert_eq!(zoomed.zoom, 3.0);
+        let (before, after) = (viewport.screen_to_world(200.0, 100.0), zoomed.screen_to_world(200.0, 100.0));
+        assert!((before.0 - after.0).abs() < 1e-12 && (before.1 - after.1).abs() < 1e-12);
+        // The zoom stops at its limits
+        assert_eq!(viewport.zoomed_at(0.0, 0.0, 1e6).unwrap().zoom, MAX_ZOOM);
+        assert!(viewport.zoomed_at(0.0, 0.0, 0.0).is_none());
+    }
+}
@@ -8,6 +8,10 @@ import { Toolbar } from './components/Toolbar';
 import { PropertiesPanel } from './components/PropertiesPanel';
 import { initWasm, createEditor, hasDemoShapes, setDemoShapesAdded } from './wasm/wasmLoader';
 import { useEditorStore } from './store/editorStore';
+import type { ImportError } from './wasm/pkg/rust_core.js';
+
+// Bytes of undo history saved with a drawing (objects shared between steps are stored once)
+const SAVED_HISTORY_BYTES = 2 * 1024 * 1024;
 
 export default function App() {
     const { editor, setEditor, setWasmReady, isWasmReady, triggerRender, canvasWidth, canvasHeight } = useEditorStore();
@@ -119,7 +123,8 @@ export default function App() {
         if (!editor || !isWasmReady) return;
 
         try {
-            const json = editor.export_scene_to_json();
+            // Keep recent undo steps so a reloaded drawing can still be undone
+            const json = editor.export_scene_to_json_with_history(SAVED_HISTORY_BYTES);
 
             // Blob作成
             const blob = new Blob([json], { type: 'application/json' });
@@ -157,14 +162,15 @@ export default function App() {
         reader.onload = (event) => {
             const json = event.target?.result as string;
             if (json) {
-                const success = editor.import_scene_from_json(json);
-                if (success) {
+                try {
+                    editor.import_scene_from_json(json);
                     triggerRender();
                     updateHistoryState();
                     console.log('✅ Scene loaded from file');
-                } else {
-                    console.error('Failed to parse scene JSON');
-                    alert('Failed to load scene. The file may be corrupted or invalid.');
+                } catch (err) {
+                    const importError = err as ImportError;
+                    console.error('Failed to parse scene JSON:', importError);
+                    alert(`Failed to load scene: ${importError.message}`);
                 }
             }
         };
@@ -183,7 +189,7 @@ export default function App() {
         if (!editor || !isWasmReady) return;
 
         try {
-            const svgData = editor.export_to_svg(canvasWidth, canvasHeight);
+            const svgData = editor.export_to_svg();
 
             // Blob作成
             const blob = new Blob([svgData], { type: 'image/svg+xml' });
@@ -209,42 +215,71 @@ export default function App() {
 
     // Handle Export PNG
     const handleExportPNG = () => {
-        // Find the canvas element
-        const canvas = document.querySelector('canvas');
-        if (!canvas) {
-            alert('Canvas not found');
-            return;
-        }
+        if (!editor || !isWasmReady) return;
 
         try {
-            canvas.toBlob((blob) => {
-                if (!blob) {
-                    alert('Failed to create PNG image');
-                    return;
-                }
+            const pngData = editor.export_to_png();
+            if (pngData.length === 0) {
+                alert('Failed to create PNG image');
+                return;
+            }
 
-                // Blob URLの作成
-                const url = URL.createObjectURL(blob);
+            // Blob URLの作成
+            const blob = new Blob([pngData], { type: 'image/png' });
+            const url = URL.createObjectURL(blob);
 
-                // ダウンロード発火（window.openは絶対禁止！）
-                const a = document.createElement('a');
-                a.href = url;
-                a.download = 'drawing.png'; // ★ここでファイル名を強制
-                document.body.appendChild(a);
-                a.click();
-                document.body.removeChild(a);
+            // ダウンロード発火（window.openは絶対禁止！）
+            const a = document.createElement('a');
+            a.href = url;
+            a.download = 'drawing.png'; // ★ここでファイル名を強制
+            document.body.appendChild(a);
+            a.click();
+            document.body.removeChild(a);
 
-                // 後始末
-                URL.revokeObjectURL(url);
+            // 後始末
+            URL.revokeObjectURL(url);
 
-                console.log('✅ Exported to drawing.png');
-            }, 'image/png');
+            console.log('✅ Exported to drawing.png');
         } catch (err) {
             console.error('Failed to export PNG:', err);
             alert('Failed to export PNG. See console for details.');
         }
     };
 
+    // Handle Export PDF (page matches the canvas at 96 units per inch)
+    const handleExportPDF = () => {
+        if (!editor || !isWasmReady) return;
+
+        try {
+            const mmPerUnit = 25.4 / 96;
+            const pdfData = editor.export_to_pdf(canvasWidth * mmPerUnit, canvasHeight * mmPerUnit);
+            if (!pdfData) {
+                alert('Failed to create PDF document');
+                return;
+            }
+
+            // Blob URLの作成
+            const blob = new Blob([pdfData], { type: 'application/pdf' });
+            const url = URL.createObjectURL(blob);
+
+            // ダウンロード発火（window.openは絶対禁止！）
+            const a = document.createElement('a');
+            a.href = url;
+            a.download = 'drawing.pdf'; // ★ここでファイル名を強制
+            document.body.appendChild(a);
+            a.click();
+            document.body.removeChild(a);
+
+            // 後始末
+            URL.revokeObjectURL(url);
+
+            console.log('✅ Exported to drawing.pdf');
+        } catch (err) {
+            console.error('Failed to export PDF:', err);
+            alert('Failed to export PDF. See console for details.');
+        }
+    };
+
     if (error) {
         return (
             <div className="app">
@@ -323,6 +358,14 @@ export default function App() {
                     >
                         🖼️ PNG
                     </button>
+                    <button
+                        className="btn btn--primary"
+                        onClick={handleExportPDF}
+                        disabled={!isWasmReady}
+                        title="Export as PDF document"
+                    >
+                        🖨️ PDF
+                    </button>
                 </div>
 
                 <span style={{ color: 'var(--color-text-muted)', fontSize: '12px' }}>
@@ -6,18 +6,7 @@ import { useEffect, useRef, useCallback, useState } from 'react';
 import { useEditorStore } from '../store/editorStore';
 import { DirectSelectOverlay } from './DirectSelectOverlay';
 import init, { convert_text_to_svg } from '../wasm/pkg/rust_core.js';
-
-// Type definitions for render commands from Rust
-interface RenderCommand {
-    type: string;
-    [key: string]: unknown;
-}
-
-// Selection overlay from Rust
-interface SelectionOverlay {
-    id: string;
-    corners: [[number, number], [number, number], [number, number], [number, number]];
-}
+import type { RenderCommand, SelectionOverlay } from '../wasm/pkg/rust_core.js';
 
 export function Canvas() {
     const canvasRef = useRef<HTMLCanvasElement>(null);
@@ -67,14 +56,54 @@ export function Canvas() {
     // Handle hit detection radii
     const HANDLE_HIT_RADIUS = 12; // Inner radius for resize
     const ROTATION_OUTER_RADIUS = 25; // Outer radius for rotation zone
+    const ROTATION_SNAP_DEGREES = 15; // Shift-rotate increment
 
     // Force re-render
     const triggerRender = useCallback(() => {
         setRenderTrigger((n) => n + 1);
     }, []);
 
+    // Pattern tile bitmaps by tile key, and the images they show
+    const patternTilesRef = useRef(new Map<string, OffscreenCanvas>());
+    const patternImagesRef = useRef(new Map<string, HTMLImageElement>());
+
     // Execute render commands on canvas
-    const executeRenderCommands = useCallback((ctx: CanvasRenderingContext2D, commands: RenderCommand[]) => {
+    type DrawingContext = CanvasRenderingContext2D | OffscreenCanvasRenderingContext2D;
+    const executeRenderCommands = useCallback((ctx: DrawingContext, commands: RenderCommand[]) => {
+        // Tile bitmap for a SetFillPattern command (null while its image is loading)
+        const patternTile = (cmd: Extract<RenderCommand, { type: 'SetFillPattern' }>): OffscreenCanvas | null => {
+            const key = cmd.key;
+            const cached = patternTilesRef.current.get(key);
+            if (cached) return cached;
+
+            const image = cmd.image;
+            let element: HTMLImageElement | undefined;
+            if (image) {
+                element = patternImagesRef.current.get(image.href);
+                if (!element) {
+                    element = new Image();
+                    element.onload = triggerRender;
+                    element.src = image.href;
+                    patternImagesRef.current.set(image.href, element);
+                }
+                if (!element.complete) return null;
+            }
+
+            const tile = new OffscreenCanvas(
+                Math.max(1, Math.ceil(cmd.width)),
+                Math.max(1, Math.ceil(cmd.height))
+            );
+            const tileCtx = tile.getContext('2d');
+            if (!tileCtx) return null;
+            if (image && element) {
+                tileCtx.drawImage(element, 0, 0, image.width, image.height);
+            } else {
+                executeRenderCommands(tileCtx, cmd.commands);
+            }
+            patternTilesRef.current.set(key, tile);
+            return tile;
+        };
+
         for (const cmd of commands) {
             switch (cmd.type) {
                 case 'SetTransform':
@@ -128,27 +157,65 @@ export function Canvas() {
                         cmd.y as number
                     );
                     break;
+                case 'QuadraticCurveTo':
+                    ctx.quadraticCurveTo(
+                        cmd.cpx as number,
+                        cmd.cpy as number,
+                        cmd.x as number,
+                        cmd.y as number
+                    );
+                    break;
                 case 'ClosePath':
                     ctx.closePath();
                     break;
                 case 'SetFillStyle':
                     ctx.fillStyle = cmd.color as string;
                     break;
+                case 'SetFillPattern': {
+                    // Until the tile is ready the preceding fill color stays in use
+                    const tile = patternTile(cmd);
+                    const pattern = tile && ctx.createPattern(tile, 'repeat');
+                    if (tile && pattern) {
+                        const matrix = new DOMMatrix([
+                            cmd.a as number,
+                            cmd.b as number,
+                            cmd.c as number,
+                            cmd.d as number,
+                            cmd.e as number,
+                            cmd.f as number,
+                        ]);
+                        // The bitmap is rounded up to whole pixels; stretch it back to the cell size
+                        pattern.setTransform(
+                            matrix.scale((cmd.width as number) / tile.width, (cmd.height as number) / tile.height)
+                        );
+                        ctx.fillStyle = pattern;
+                    }
+                    break;
+                }
                 case 'SetStrokeStyle':
                     ctx.strokeStyle = cmd.color as string;
                     break;
                 case 'SetLineWidth':
                     ctx.lineWidth = cmd.width as number;
                     break;
+                case 'SetLineCap':
+                    ctx.lineCap = cmd.cap as CanvasLineCap;
+                    break;
+                case 'SetLineJoin':
+                    ctx.lineJoin = cmd.join as CanvasLineJoin;
+                    break;
+                case 'SetGlobalAlpha':
+                    ctx.globalAlpha = cmd.alpha as number;
+                    break;
                 case 'Fill':
-                    ctx.fill();
+                    ctx.fill(cmd.rule as CanvasFillRule);
                     break;
                 case 'Stroke':
                     ctx.stroke();
                     break;
             }
         }
-    }, []);
+    }, [triggerRender]);
 
     // Draw selection overlay
     const drawSelectionOverlay = useCallback((ctx: CanvasRenderingContext2D, overlays: SelectionOverlay[]) => {
@@ -170,22 +237,32 @@ export function Canvas() {
             ctx.stroke();
             ctx.setLineDash([]);
 
-            // Draw corner handles
-            const handleSize = 8;
+            // Draw corner and edge-midpoint handles
+            const handleSize = overlay.handle_size;
             ctx.fillStyle = '#ffffff';
             ctx.strokeStyle = '#3b82f6';
             ctx.lineWidth = 2;
 
-            for (const corner of corners) {
+            const midpoint = (p: [number, number], q: [number, number]): [number, number] =>
+                [(p[0] + q[0]) / 2, (p[1] + q[1]) / 2];
+            const handlePoints: [number, number][] = [
+                ...corners,
+                midpoint(corners[0], corners[1]),
+                midpoint(corners[1], corners[2]),
+                midpoint(corners[2], corners[3]),
+                midpoint(corners[3], corners[0]),
+            ];
+
+            for (const point of handlePoints) {
                 ctx.fillRect(
-                    corner[0] - handleSize / 2,
-                    corner[1] - handleSize / 2,
+                    point[0] - handleSize / 2,
+                    point[1] - handleSize / 2,
                     handleSize,
                     handleSize
                 );
                 ctx.strokeRect(
-                    corner[0] - handleSize / 2,
-                    corner[1] - handleSize / 2,
+                    point[0] - handleSize / 2,
+                    point[1] - handleSize / 2,
                     handleSize,
                     handleSize
                 );
@@ -222,129 +299,122 @@ export function Canvas() {
         }
 
         // Get render commands from Rust
-        const commandsJson = editor.get_render_commands();
-        const commands: RenderCommand[] = JSON.parse(commandsJson);
+        const commands: RenderCommand[] = editor.get_render_commands();
 
         // Execute render commands
         executeRenderCommands(ctx, commands);
 
         // Draw selection overlay
-        const overlayJson = editor.get_selection_overlay();
-        const overlays: SelectionOverlay[] = JSON.parse(overlayJson);
+        const overlays: SelectionOverlay[] = editor.get_selection_overlay();
         if (overlays.length > 0) {
             drawSelectionOverlay(ctx, overlays);
         }
 
         // Draw pen tool preview (path being constructed)
         if (editor.is_pen_drawing()) {
-            const previewJson = editor.get_pen_preview();
-            try {
-                const preview = JSON.parse(previewJson);
+            const preview = editor.get_pen_preview();
+
+            if (preview && preview.commands.length > 0) {
+                ctx.save();
+
+                // Draw existing path commands (cyan dashed)
+                ctx.strokeStyle = '#00d4ff';
+                ctx.lineWidth = 2;
+                ctx.setLineDash([5, 5]);
+                ctx.beginPath();
+
+                for (const cmd of preview.commands) {
+                    if (cmd.type === 'MoveTo') {
+                        ctx.moveTo(cmd.x as number, cmd.y as number);
+                    } else if (cmd.type === 'LineTo') {
+                        ctx.lineTo(cmd.x as number, cmd.y as number);
+                    } else if (cmd.type === 'CurveTo') {
+                        ctx.bezierCurveTo(
+                            cmd.x1 as number, cmd.y1 as number,
+                            cmd.x2 as number, cmd.y2 as number,
+                            cmd.x as number, cmd.y as number
+                        );
+                    }
+                }
+                ctx.stroke();
 
-                if (preview.commands && preview.commands.length > 0) {
-                    ctx.save();
+                // Draw preview curve as SEPARATE path (solid cyan)
+                const pc = preview.preview_curve;
+                if (pc?.type === 'CurveTo') {
+                    const [startX, startY] = preview.last_anchor;
 
-                    // Draw existing path commands (cyan dashed)
-                    ctx.strokeStyle = '#00d4ff';
-                    ctx.lineWidth = 2;
-                    ctx.setLineDash([5, 5]);
                     ctx.beginPath();
-
-                    for (const cmd of preview.commands as RenderCommand[]) {
-                        if (cmd.type === 'MoveTo') {
-                            ctx.moveTo(cmd.x as number, cmd.y as number);
-                        } else if (cmd.type === 'LineTo') {
-                            ctx.lineTo(cmd.x as number, cmd.y as number);
-                        } else if (cmd.type === 'CurveTo') {
-                            ctx.bezierCurveTo(
-                                cmd.x1 as number, cmd.y1 as number,
-                                cmd.x2 as number, cmd.y2 as number,
-                                cmd.x as number, cmd.y as number
-                            );
-                        }
-                    }
+                    ctx.strokeStyle = '#00d4ff';
+                    ctx.lineWidth = 3;
+                    ctx.setLineDash([]); // Solid line for preview curve
+                    ctx.moveTo(startX, startY); // Explicit start point!
+                    ctx.bezierCurveTo(
+                        pc.x1, pc.y1,
+                        pc.x2, pc.y2,
+                        pc.x, pc.y
+                    );
                     ctx.stroke();
+                }
 
-                    // Draw preview curve as SEPARATE path (solid cyan)
-                    if (preview.preview_curve && preview.last_anchor) {
-                        const pc = preview.preview_curve;
-                        const [startX, startY] = preview.last_anchor;
-
+                // Draw anchor points
+                ctx.setLineDash([]);
+                ctx.fillStyle = '#00d4ff';
+                for (const cmd of preview.commands) {
+                    if (cmd.type === 'MoveTo' || cmd.type === 'LineTo') {
                         ctx.beginPath();
-                        ctx.strokeStyle = '#00d4ff';
-                        ctx.lineWidth = 3;
-                        ctx.setLineDash([]); // Solid line for preview curve
-                        ctx.moveTo(startX, startY); // Explicit start point!
-                        ctx.bezierCurveTo(
-                            pc.x1 as number, pc.y1 as number,
-                            pc.x2 as number, pc.y2 as number,
-                            pc.x as number, pc.y as number
-                        );
-                        ctx.stroke();
-                    }
-
-                    // Draw anchor points
-                    ctx.setLineDash([]);
-                    ctx.fillStyle = '#00d4ff';
-                    for (const cmd of preview.commands as RenderCommand[]) {
-                        if (cmd.type === 'MoveTo' || cmd.type === 'LineTo') {
-                            ctx.beginPath();
-                            ctx.arc(cmd.x as number, cmd.y as number, 4, 0, Math.PI * 2);
-                            ctx.fill();
-                        } else if (cmd.type === 'CurveTo') {
-                            ctx.beginPath();
-                            ctx.arc(cmd.x as number, cmd.y as number, 4, 0, Math.PI * 2);
-                            ctx.fill();
-                        }
+                        ctx.arc(cmd.x as number, cmd.y as number, 4, 0, Math.PI * 2);
+                        ctx.fill();
+                    } else if (cmd.type === 'CurveTo') {
+                        ctx.beginPath();
+                        ctx.arc(cmd.x as number, cmd.y as number, 4, 0, Math.PI * 2);
+                        ctx.fill();
                     }
+                }
 
-                    // Draw drag handle and curve preview if present
-                    if (preview.handle && preview.is_dragging && preview.last_anchor) {
-                        const [hx, hy] = preview.handle;
-                        const [lax, lay] = preview.last_anchor;
+                // Draw drag handle and curve preview if present
+                if (preview.handle && preview.is_dragging) {
+                    const [hx, hy] = preview.handle;
+                    const [lax, lay] = preview.last_anchor;
 
-                        // Draw preview curve from last anchor to handle position
-                        // Control points: cp1 at last anchor, cp2 symmetric to handle around endpoint
-                        ctx.strokeStyle = '#ff6b6b';
-                        ctx.lineWidth = 2;
-                        ctx.setLineDash([3, 3]);
-                        ctx.beginPath();
-                        ctx.moveTo(lax, lay);
-                        // bezierCurveTo(cp1x, cp1y, cp2x, cp2y, endX, endY)
-                        // cp1 = last anchor (straight start)
-                        // cp2 = symmetric of handle around endpoint (hx, hy)
-                        // endpoint = handle position (where curve goes to)
-                        const cp2x = 2 * hx - lax;
-                        const cp2y = 2 * hy - lay;
-                        ctx.bezierCurveTo(lax, lay, cp2x, cp2y, hx, hy);
-                        ctx.stroke();
-                        ctx.setLineDash([]);
-
-                        // Draw handle whisker line
-                        ctx.strokeStyle = '#ff6b6b';
-                        ctx.lineWidth = 1;
-                        ctx.beginPath();
-                        ctx.moveTo(lax, lay);
-                        ctx.lineTo(hx, hy);
-                        ctx.stroke();
+                    // Draw preview curve from last anchor to handle position
+                    // Control points: cp1 at last anchor, cp2 symmetric to handle around endpoint
+                    ctx.strokeStyle = '#ff6b6b';
+                    ctx.lineWidth = 2;
+                    ctx.setLineDash([3, 3]);
+                    ctx.beginPath();
+                    ctx.moveTo(lax, lay);
+                    // bezierCurveTo(cp1x, cp1y, cp2x, cp2y, endX, endY)
+                    // cp1 = last anchor (straight start)
+                    // cp2 = symmetric of handle around endpoint (hx, hy)
+                    // endpoint = handle position (where curve goes to)
+                    const cp2x = 2 * hx - lax;
+                    const cp2y = 2 * hy - lay;
+                    ctx.bezierCurveTo(lax, lay, cp2x, cp2y, hx, hy);
+                    ctx.stroke();
+                    ctx.setLineDash([]);
 
-                        // Draw handle point
-                        ctx.fillStyle = '#ff6b6b';
-                        ctx.beginPath();
-                        ctx.arc(hx, hy, 5, 0, Math.PI * 2);
-                        ctx.fill();
+                    // Draw handle whisker line
+                    ctx.strokeStyle = '#ff6b6b';
+                    ctx.lineWidth = 1;
+                    ctx.beginPath();
+                    ctx.moveTo(lax, lay);
+                    ctx.lineTo(hx, hy);
+                    ctx.stroke();
 
-                        // Draw mirrored handle point
-                        ctx.beginPath();
-                        ctx.arc(cp2x, cp2y, 4, 0, Math.PI * 2);
-                        ctx.strokeStyle = '#ff6b6b';
-                        ctx.stroke();
-                    }
+                    // Draw handle point
+                    ctx.fillStyle = '#ff6b6b';
+                    ctx.beginPath();
+                    ctx.arc(hx, hy, 5, 0, Math.PI * 2);
+                    ctx.fill();
 
-                    ctx.restore();
+                    // Draw mirrored handle point
+                    ctx.beginPath();
+                    ctx.arc(cp2x, cp2y, 4, 0, Math.PI * 2);
+                    ctx.strokeStyle = '#ff6b6b';
+                    ctx.stroke();
                 }
-            } catch {
-                // Ignore parse errors
+
+                ctx.restore();
             }
         }
     }, [editor, executeRenderCommands, drawSelectionOverlay]);
@@ -393,13 +463,75 @@ export function Canvas() {
 
                 if (currentTool === 'pen' && isDrawing) {
                     console.log('  → Finishing open path...');
-                    editor.save_snapshot(); // Save for undo
+                    editor.save_named_snapshot('Pen path'); // Save for undo
                     const pathId = editor.pen_finish();
                     console.log('  → Open path finished:', pathId);
                     render();
                     storeRender();
                 }
             }
+
+            // Clipboard: Ctrl/Cmd+C / X / V via the system clipboard (undo steps recorded by the editor)
+            if ((e.ctrlKey || e.metaKey) && currentTool === 'select') {
+                if ((e.key === 'c' || e.key === 'x') && editor.has_selection()) {
+                    e.preventDefault();
+                    const fragment = e.key === 'x' ? editor.cut_selection() : editor.copy_selection();
+                    navigator.clipboard.writeText(fragment).catch((err: unknown) => {
+                        console.error('Clipboard write failed:', err);
+                    });
+                    if (e.key === 'x') {
+                        render();
+                        storeRender();
+                    }
+                    return;
+                }
+                if (e.key === 'v') {
+                    e.preventDefault();
+                    navigator.clipboard.readText().then((text) => {
+                        editor.paste(text);
+                        render();
+                        storeRender();
+                    }).catch((err: unknown) => {
+                        console.error('Clipboard read failed:', err);
+                    });
+                    return;
+                }
+            }
+
+            // Delete/Backspace: remove the selection
+            if ((e.key === 'Delete' || e.key === 'Backspace') && currentTool === 'select' && editor.has_selection()) {
+                e.preventDefault();
+                editor.delete_selected();
+                render();
+                storeRender();
+                return;
+            }
+
+            // Ctrl/Cmd+D: duplicate the selection (records its own undo step)
+            if (e.key === 'd' && (e.ctrlKey || e.metaKey) && currentTool === 'select' && editor.has_selection()) {
+                e.preventDefault();
+                editor.duplicate_selected(10, 10);
+                render();
+                storeRender();
+                return;
+            }
+
+            // Arrow keys: nudge the selection (1px, 10px with Shift)
+            const nudgeDirections: Record<string, string> = {
+                ArrowUp: 'up',
+                ArrowDown: 'down',
+                ArrowLeft: 'left',
+                ArrowRight: 'right',
+            };
+            const direction = nudgeDirections[e.key];
+            if (direction && currentTool === 'select' && editor.has_selection()) {
+                e.preventDefault();
+                // Undo snapshots are taken by the editor (consecutive nudges share one entry)
+                if (editor.nudge_selected(direction, e.shiftKey ? 10 : 1)) {
+                    render();
+                    storeRender();
+                }
+            }
         };
 
         window.addEventListener('keydown', handleKeyDown);
@@ -428,25 +560,33 @@ export function Canvas() {
                 const handlesJson = editor.get_handle_positions();
                 try {
                     const handles: [number, number][] = JSON.parse(handlesJson);
-                    if (handles.length === 4) {
-                        // Check each handle for hit (resize or rotate zone)
-                        for (let i = 0; i < 4; i++) {
+                    if (handles.length === 8) {
+                        // Resize handles take priority: 0-3 corners, 4-7 edge midpoints
+                        for (let i = 0; i < handles.length; i++) {
                             const [hx, hy] = handles[i];
                             const distance = Math.sqrt((x - hx) ** 2 + (y - hy) ** 2);
 
                             if (distance <= HANDLE_HIT_RADIUS) {
                                 // Inside handle - start resize drag
                                 console.log('Resize handle:', i);
-                                editor.save_snapshot(); // Save for undo
+                                editor.save_named_snapshot('Resize'); // Save for undo
                                 editor.begin_resize_drag(x, y, i);
                                 setIsDragging(true);
                                 setDragMode('resize');
                                 render();
                                 return;
-                            } else if (distance <= ROTATION_OUTER_RADIUS) {
+                            }
+                        }
+
+                        // Rotation zones surround the corner handles only
+                        for (let i = 0; i < 4; i++) {
+                            const [hx, hy] = handles[i];
+                            const distance = Math.sqrt((x - hx) ** 2 + (y - hy) ** 2);
+
+                            if (distance <= ROTATION_OUTER_RADIUS) {
                                 // Outside handle but in rotation zone
                                 console.log('Rotation zone near handle:', i);
-                                editor.save_snapshot(); // Save for undo
+                                editor.save_named_snapshot('Rotate'); // Save for undo
                                 editor.begin_rotate_drag(x, y);
                                 setIsDragging(true);
                                 setDragMode('rotate');
@@ -465,8 +605,8 @@ export function Canvas() {
             if (selectedId) {
                 console.log('Selected:', selectedId);
                 // Begin move drag operation
-                editor.save_snapshot(); // Save for undo
-                editor.begin_move_drag(x, y);
+                editor.save_named_snapshot(e.altKey ? 'Duplicate' : 'Move'); // Save for undo
+                editor.begin_move_drag(x, y, e.altKey); // Alt-drag moves a copy
                 setIsDragging(true);
                 setDragMode('move');
             } else {
@@ -475,11 +615,11 @@ export function Canvas() {
             }
             render();
         } else if (currentTool === 'rectangle') {
-            editor.save_snapshot(); // Save for undo
+            editor.save_named_snapshot('Add rectangle'); // Save for undo
             editor.add_rectangle(x - 50, y - 30, 100, 60);
             render();
         } else if (currentTool === 'ellipse') {
-            editor.save_snapshot(); // Save for undo
+            editor.save_named_snapshot('Add ellipse'); // Save for undo
             editor.add_ellipse(x, y, 50, 35);
             render();
         } else if (currentTool === 'pen') {
@@ -487,7 +627,7 @@ export function Canvas() {
             const shouldClose = editor.pen_down(x, y);
             if (shouldClose) {
                 // Close the path
-                editor.save_snapshot(); // Save for undo before creating path
+                editor.save_named_snapshot('Pen path'); // Save for undo before creating path
                 const pathId = editor.pen_close();
                 console.log('Path closed:', pathId);
                 setIsDragging(false); // Path is done, no more dragging
@@ -533,11 +673,15 @@ export function Canvas() {
 
         if (editor.has_selection()) {
             if (dragMode === 'resize') {
-                editor.update_resize_drag(x, y);
+                editor.update_resize_drag(x, y, e.altKey, e.shiftKey);
             } else if (dragMode === 'rotate') {
-                editor.update_rotate_drag(x, y);
+                if (e.shiftKey) {
+                    editor.update_rotate_drag_snapped(x, y, ROTATION_SNAP_DEGREES);
+                } else {
+                    editor.update_rotate_drag(x, y);
+                }
             } else {
-                editor.update_move_drag(x, y);
+                editor.update_move_drag(x, y, e.shiftKey);
             }
             render();
         }
@@ -8,12 +8,7 @@
 
 import { useEffect, useState, useRef, useCallback } from 'react';
 import { useEditorStore } from '../store/editorStore';
-
-interface PathPoint {
-    x: number;
-    y: number;
-    type: 'move' | 'line' | 'curve';
-}
+import type { PathPoint } from '../wasm/pkg/rust_core.js';
 
 export function DirectSelectOverlay() {
     const { editor, currentTool, selectedIds, renderVersion, triggerRender } = useEditorStore();
@@ -59,7 +54,7 @@ export function DirectSelectOverlay() {
         if (!editor || !selectedId) return;
 
         // Save snapshot for undo before starting drag
-        editor.save_snapshot();
+        editor.save_named_snapshot('Edit anchor');
 
         setDraggingIndex(index);
 
@@ -2,8 +2,9 @@
  * Properties Panel - Style editing for selected objects
  */
 
-import { useEffect, useState, useCallback } from 'react';
+import { useEffect, useState } from 'react';
 import { useEditorStore } from '../store/editorStore';
+import type { SelectionState } from '../wasm/pkg/rust_core.js';
 
 interface StyleData {
     fill: string | null;
@@ -16,37 +17,22 @@ export function PropertiesPanel() {
     const [style, setStyle] = useState<StyleData | null>(null);
     const [hasSelection, setHasSelection] = useState(false);
 
-    // Poll for selection changes and update style
-    const updateSelection = useCallback(() => {
+    // Render from the selection state the editor pushes whenever it changes
+    useEffect(() => {
         if (!editor || !isWasmReady) return;
 
-        const selected = editor.has_selection();
-        setHasSelection(selected);
-
-        if (selected) {
-            try {
-                const styleJson = editor.get_selected_style();
-                const parsed = JSON.parse(styleJson);
-                setStyle({
-                    fill: parsed.fill || '#3b82f6',
-                    stroke: parsed.stroke || '#1e40af',
-                    strokeWidth: parsed.strokeWidth || 2,
-                });
-            } catch {
-                setStyle(null);
-            }
-        } else {
-            setStyle(null);
-        }
+        editor.set_selection_observer((selection: SelectionState) => {
+            const selected = selection.ids.length > 0;
+            setHasSelection(selected);
+            setStyle(selected ? {
+                fill: selection.style.fill || '#3b82f6',
+                stroke: selection.style.stroke || '#1e40af',
+                strokeWidth: selection.style.stroke_width || 2,
+            } : null);
+        });
+        return () => editor.set_selection_observer(undefined);
     }, [editor, isWasmReady]);
 
-    // Update on mount and periodically
-    useEffect(() => {
-        updateSelection();
-        const interval = setInterval(updateSelection, 100);
-        return () => clearInterval(interval);
-    }, [updateSelection]);
-
     // Handle fill color change (supports both onChange and onInput for real-time updates)
     const handleFillChange = (e: React.ChangeEvent<HTMLInputElement>) => {
         if (!editor || !style) return;
@@ -69,7 +55,7 @@ export function PropertiesPanel() {
     const handleStrokeWidthChange = (e: React.ChangeEvent<HTMLInputElement>) => {
         if (!editor || !style) return;
         const newWidth = parseFloat(e.target.value) || 1;
-        editor.save_snapshot(); // Save for undo
+        editor.save_named_snapshot('Stroke width'); // Save for undo
         editor.update_style(style.fill || 'none', style.stroke || 'none', newWidth);
         setStyle({ ...style, strokeWidth: newWidth });
         triggerRender(); // Force canvas re-render
@@ -78,7 +64,7 @@ export function PropertiesPanel() {
     // Handle bring to front (layer ordering)
     const handleBringToFront = () => {
         if (!editor) return;
-        editor.save_snapshot(); // Save for undo
+        editor.save_named_snapshot('Bring to front'); // Save for undo
         editor.bring_to_front();
         triggerRender(); // Force canvas re-render
     };
@@ -86,7 +72,7 @@ export function PropertiesPanel() {
     // Handle send to back (layer ordering)
     const handleSendToBack = () => {
         if (!editor) return;
-        editor.save_snapshot(); // Save for undo
+        editor.save_named_snapshot('Send to back'); // Save for undo
         editor.send_to_back();
         triggerRender(); // Force canvas re-render
     };